@@ -0,0 +1,70 @@
+//! Measures how long the interpreter takes to run the programs
+//! under `benches/programs`.
+//!
+//! Run with `cargo bench -p rusty_basic`. Any extra argument filters
+//! the programs by name, e.g. `cargo bench -p rusty_basic -- SIEVE`.
+//! Parsing, linting and generating the instructions are not measured.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use rusty_basic::instruction_generator::{generate_instructions, unwrap_linter_context};
+use rusty_basic::interpreter::{InterpreterTrait, new_interpreter_with_io};
+use rusty_linter::core::lint;
+use rusty_parser::{Program, parse_main_str};
+
+/// How many times each program runs.
+const RUNS: usize = 5;
+
+fn main() {
+    let filters: Vec<String> = std::env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .collect();
+    println!("{:<20} {:>10} {:>10}", "program", "min", "median");
+    for path in programs() {
+        let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+        if !filters.is_empty() && !filters.iter().any(|f| name.contains(f.as_str())) {
+            continue;
+        }
+        let program = parse(&path);
+        let mut durations: Vec<Duration> = (0..RUNS).map(|_| run(&program)).collect();
+        durations.sort();
+        println!(
+            "{:<20} {:>8}ms {:>8}ms",
+            name,
+            durations[0].as_millis(),
+            durations[RUNS / 2].as_millis()
+        );
+    }
+}
+
+fn programs() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("benches/programs");
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .expect("Could not read the programs")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "BAS"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn parse(path: &Path) -> Program {
+    let input = std::fs::read_to_string(path).expect("Could not read the program");
+    parse_main_str(input).unwrap_or_else(|e| panic!("{}: {:?}", path.display(), e))
+}
+
+/// Runs the given program, returning how long it took.
+fn run(program: &Program) -> Duration {
+    let (linted_program, linter_context) = lint(program.clone()).expect("Lint error");
+    let (linter_names, user_defined_types) = unwrap_linter_context(linter_context);
+    let instruction_generator_result = generate_instructions(linted_program, linter_names);
+    let mut interpreter =
+        new_interpreter_with_io(user_defined_types, std::io::empty(), std::io::sink());
+    let start = Instant::now();
+    interpreter
+        .interpret(instruction_generator_result)
+        .expect("Runtime error");
+    start.elapsed()
+}
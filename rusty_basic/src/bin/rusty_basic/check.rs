@@ -0,0 +1,18 @@
+use std::process::ExitCode;
+
+use crate::source::lint_file;
+use crate::usage_error;
+
+/// Parses and lints the given files, reporting all errors.
+pub fn check(args: &[String]) -> ExitCode {
+    if args.is_empty() {
+        return usage_error("Please specify at least one program.");
+    }
+    let mut exit_code = ExitCode::SUCCESS;
+    for file_name in args {
+        if let Err(e) = lint_file(file_name) {
+            exit_code = e;
+        }
+    }
+    exit_code
+}
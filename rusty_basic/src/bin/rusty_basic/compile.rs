@@ -0,0 +1,32 @@
+use std::path::Path;
+use std::process::ExitCode;
+
+use crate::source::compile_file;
+use crate::{EXIT_IO_ERROR, usage_error};
+
+/// The extension of compiled programs.
+const COMPILED_EXTENSION: &str = "rbc";
+
+/// Compiles the given program into a file that can be run
+/// without parsing and linting it again.
+pub fn compile(args: &[String]) -> ExitCode {
+    let (file_name, out_file) = match args {
+        [file_name] => (
+            file_name,
+            Path::new(file_name).with_extension(COMPILED_EXTENSION),
+        ),
+        [file_name, out_file] => (file_name, Path::new(out_file).to_path_buf()),
+        _ => return usage_error("Please specify the program and optionally the output file."),
+    };
+    let (compiled_program, _) = match compile_file(file_name) {
+        Ok(x) => x,
+        Err(e) => return e,
+    };
+    match std::fs::write(&out_file, compiled_program.to_bytes()) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Could not write {}. {}", out_file.display(), e);
+            ExitCode::from(EXIT_IO_ERROR)
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::BTreeSet;
+use std::io::{BufRead, Write};
+
+use rusty_basic::diagnostics::Diagnostics;
+use rusty_basic::interpreter::{DebugAction, Debugger, Frame, Pause, PauseReason, format_value};
+
+const HELP: &str = "Commands:
+  c, continue           Run until the next breakpoint or STOP statement.
+  s, step               Run the next statement, entering SUB/FUNCTION calls.
+  n, next               Run the next statement, stepping over SUB/FUNCTION calls.
+  o, out                Run until the current SUB/FUNCTION returns.
+  b, break [line]       Toggle a breakpoint on the given line, or list the breakpoints.
+  bt, where             Show the call stack.
+  v, vars [frame]       Show the variables of the given frame (0 is the innermost).
+  p, print NAME [frame] Show a variable of the given frame.
+  l, list               Show the source around the current line.
+  q, quit               End the program.
+  h, help               Show this message.";
+
+/// The number of lines before and after the current line shown by `list`.
+const LIST_CONTEXT: u32 = 4;
+
+/// A debugger that is controlled with commands from the standard input.
+pub struct TerminalDebugger<'a> {
+    diagnostics: &'a Diagnostics,
+    breakpoints: BTreeSet<u32>,
+}
+
+impl<'a> TerminalDebugger<'a> {
+    pub fn new(diagnostics: &'a Diagnostics) -> Self {
+        eprintln!("Type h for help.");
+        Self {
+            diagnostics,
+            breakpoints: BTreeSet::new(),
+        }
+    }
+
+    /// Handles a command that does not resume the program.
+    fn run_command(&mut self, command: &str, args: &[&str], pause: &Pause) {
+        match command {
+            "b" | "break" => match args.first() {
+                Some(arg) => match arg.parse::<u32>() {
+                    Ok(row) if self.breakpoints.remove(&row) => {
+                        eprintln!("Removed breakpoint on line {}.", row)
+                    }
+                    Ok(row) => {
+                        self.breakpoints.insert(row);
+                        eprintln!("Added breakpoint on line {}.", row);
+                    }
+                    Err(_) => eprintln!("Invalid line number: {}", arg),
+                },
+                None if self.breakpoints.is_empty() => eprintln!("No breakpoints."),
+                None => {
+                    for row in &self.breakpoints {
+                        eprintln!("{}", self.source_line(*row));
+                    }
+                }
+            },
+            "bt" | "where" => {
+                for (index, frame) in pause.frames.iter().enumerate() {
+                    eprintln!("#{} {}", index, self.describe_frame(frame));
+                }
+            }
+            "v" | "vars" => {
+                if let Some(frame) = self.frame(pause, args.first()) {
+                    for (name, value) in frame.variables() {
+                        eprintln!("{} = {}", name, format_value(value));
+                    }
+                }
+            }
+            "p" | "print" => match args.first() {
+                Some(name) => {
+                    if let Some(frame) = self.frame(pause, args.get(1)) {
+                        match frame.find_variable(name) {
+                            Some((name, value)) => eprintln!("{} = {}", name, format_value(value)),
+                            None => eprintln!("Variable {} not found.", name),
+                        }
+                    }
+                }
+                None => eprintln!("Please specify the variable."),
+            },
+            "l" | "list" => {
+                let row = pause.pos().row();
+                for r in row.saturating_sub(LIST_CONTEXT).max(1)..=row + LIST_CONTEXT {
+                    if self.diagnostics.line(r).is_some() {
+                        let marker = if r == row { "->" } else { "  " };
+                        eprintln!("{} {}", marker, self.source_line(r));
+                    }
+                }
+            }
+            "h" | "help" => eprintln!("{}", HELP),
+            _ => eprintln!("Unknown command {}. Type h for help.", command),
+        }
+    }
+
+    fn frame<'p, 'f>(&self, pause: &'p Pause<'f>, arg: Option<&&str>) -> Option<&'p Frame<'f>> {
+        let index = match arg.map(|arg| arg.parse::<usize>()) {
+            Some(Ok(index)) => index,
+            Some(Err(_)) => {
+                eprintln!("Invalid frame number.");
+                return None;
+            }
+            None => 0,
+        };
+        let frame = pause.frames.get(index);
+        if frame.is_none() {
+            eprintln!("There is no frame #{}.", index);
+        }
+        frame
+    }
+
+    fn describe_frame(&self, frame: &Frame) -> String {
+        format!(
+            "{} ({})",
+            self.diagnostics.subprogram_name(frame.pos),
+            self.diagnostics.location(frame.pos)
+        )
+    }
+
+    fn source_line(&self, row: u32) -> String {
+        let breakpoint = if self.breakpoints.contains(&row) {
+            '*'
+        } else {
+            ' '
+        };
+        format!(
+            "{}{:>5} | {}",
+            breakpoint,
+            row,
+            self.diagnostics.line(row).unwrap_or_default()
+        )
+    }
+}
+
+impl Debugger for TerminalDebugger<'_> {
+    fn has_breakpoint(&self, row: u32) -> bool {
+        self.breakpoints.contains(&row)
+    }
+
+    fn on_pause(&mut self, pause: &Pause) -> DebugAction {
+        let reason = match pause.reason {
+            PauseReason::Step => "Paused",
+            PauseReason::Breakpoint => "Breakpoint",
+            PauseReason::Stop => "STOP",
+            PauseReason::Requested => "Paused",
+        };
+        eprintln!("{} in {}", reason, self.describe_frame(&pause.frames[0]));
+        eprintln!("{}", self.source_line(pause.pos().row()));
+        let mut stdin = std::io::stdin().lock();
+        loop {
+            eprint!("(debug) ");
+            std::io::stderr().flush().unwrap_or_default();
+            let mut line = String::new();
+            match stdin.read_line(&mut line) {
+                // end of input, nobody can control the debugger anymore
+                Ok(0) | Err(_) => return DebugAction::Quit,
+                Ok(_) => {}
+            }
+            let mut words = line.split_whitespace();
+            let Some(command) = words.next() else {
+                continue;
+            };
+            let args: Vec<&str> = words.collect();
+            match command {
+                "c" | "continue" => return DebugAction::Continue,
+                "s" | "step" => return DebugAction::StepInto,
+                "n" | "next" => return DebugAction::StepOver,
+                "o" | "out" => return DebugAction::StepOut,
+                "q" | "quit" => return DebugAction::Quit,
+                _ => self.run_command(command, &args, pause),
+            }
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use std::process::ExitCode;
+
+use rusty_basic::instruction_generator::{generate_instructions, unwrap_linter_context};
+
+use crate::single_file_arg;
+use crate::source::{lint_file, parse_file};
+
+/// Prints the parsed program.
+pub fn dump_ast(args: &[String]) -> ExitCode {
+    match single_file_arg(args).and_then(parse_file) {
+        Ok((program, _)) => {
+            println!("{:#?}", program);
+            ExitCode::SUCCESS
+        }
+        Err(e) => e,
+    }
+}
+
+/// Prints the generated instructions, with their address and source position.
+pub fn dump_instructions(args: &[String]) -> ExitCode {
+    match single_file_arg(args).and_then(lint_file) {
+        Ok((program, linter_context, _)) => {
+            let (linter_names, _) = unwrap_linter_context(linter_context);
+            let instruction_generator_result = generate_instructions(program, linter_names);
+            for (address, instruction_pos) in
+                instruction_generator_result.instructions.iter().enumerate()
+            {
+                let pos = instruction_pos.pos;
+                // the implicit halt at the end of the program has no source position
+                let location = if pos.row() == u32::MAX {
+                    "-".to_owned()
+                } else {
+                    format!("{}:{}", pos.row(), pos.col())
+                };
+                println!(
+                    "{:>6} {:>9} {:?}",
+                    address, location, instruction_pos.element
+                );
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => e,
+    }
+}
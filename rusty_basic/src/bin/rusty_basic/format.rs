@@ -0,0 +1,45 @@
+use std::process::ExitCode;
+
+use rusty_parser::format_program;
+
+use crate::source::parse_file;
+use crate::{EXIT_IO_ERROR, usage_error};
+
+/// Formats the given files in place.
+///
+/// With `--check`, the files are not modified. Instead, the files
+/// that are not formatted are listed and the exit code is 1.
+pub fn format(args: &[String]) -> ExitCode {
+    let (check, file_names) = match args.split_first() {
+        Some((first, rest)) if first == "--check" => (true, rest),
+        _ => (false, args),
+    };
+    if file_names.is_empty() {
+        return usage_error("Please specify at least one program.");
+    }
+    let mut exit_code = ExitCode::SUCCESS;
+    for file_name in file_names {
+        if let Err(e) = format_file(file_name, check) {
+            exit_code = e;
+        }
+    }
+    exit_code
+}
+
+fn format_file(file_name: &str, check: bool) -> Result<(), ExitCode> {
+    let (program, _) = parse_file(file_name)?;
+    let formatted = format_program(&program);
+    // parse_file has already read the file successfully
+    let source = std::fs::read_to_string(file_name).unwrap_or_default();
+    if source == formatted {
+        return Ok(());
+    }
+    if check {
+        println!("{}", file_name);
+        return Err(ExitCode::FAILURE);
+    }
+    std::fs::write(file_name, formatted).map_err(|e| {
+        eprintln!("Could not write {}. {}", file_name, e);
+        ExitCode::from(EXIT_IO_ERROR)
+    })
+}
@@ -0,0 +1,146 @@
+//! The command line interface of the interpreter.
+
+mod check;
+mod compile;
+mod debug;
+mod dump;
+mod format;
+mod repl;
+mod run;
+mod source;
+mod test;
+mod transpile;
+
+use std::process::ExitCode;
+
+const USAGE: &str = "Usage: rusty_basic <command> [arguments]
+
+Commands:
+  run <file> [args...]        Runs a program or a compiled program. The arguments are
+                              available with COMMAND$.
+  check <file>...             Parses and lints the given programs.
+  compile <file> [<output>]   Compiles a program into a file that `run` can execute
+                              without parsing it again. The default output is the
+                              program with the .rbc extension.
+  debug <file> [args...]      Runs a program under the terminal debugger.
+  dump-ast <file>             Prints the parsed program.
+  dump-instructions <file>    Prints the generated instructions.
+  profile [--folded <file>] <file> [args...]
+                              Runs a program and prints the time spent per SUB/FUNCTION
+                              and per line to stderr. With --folded, writes the call
+                              stacks to the given file in the folded format of flame
+                              graph tools.
+  format [--check] <file>...  Formats the given programs in place. With --check, lists
+                              the programs that are not formatted instead.
+  test [--coverage <lcov>] <file>...
+                              Runs the tests of the given programs: every SUB without
+                              parameters whose name starts with Test. With --coverage,
+                              writes the line coverage to the given file in lcov format.
+  transpile-rust --runtime-path <runtime-dir> <file> <dir>
+                              Generates a Rust crate from a program in the given directory.
+                              The crate depends on the rusty_runtime crate in the given
+                              runtime directory (of a checkout of this repository).
+  transpile-js <file> <dir>   Generates a JavaScript module and a web page that runs it
+                              in the given directory.
+  repl                        Starts an interactive session (also when no command is given).
+  help                        Prints this message.
+
+`rusty_basic <file> [args...]` is a shortcut for `rusty_basic run <file> [args...]`.
+
+Environment variables (limits for running untrusted programs, e.g. in Apache):
+  RUSTY_BASIC_MAX_INSTRUCTIONS  The maximum number of instructions to run.
+  RUSTY_BASIC_TIMEOUT           The maximum time to run, in seconds.
+  RUSTY_BASIC_MAX_MEMORY        The maximum size of all variables, in bytes.
+  RUSTY_BASIC_MAX_CALL_DEPTH    The maximum number of nested SUB/FUNCTION calls
+                                (default 10000).
+  RUSTY_BASIC_FILES             The access to files and environment variables: allow
+                                (default), deny, read-only, or a directory that files
+                                are confined to.
+
+Other environment variables:
+  LPT1                          Where LPRINT output goes: a file, or a command to pipe
+                                it to when it starts with | (e.g. |lpr). By default,
+                                it goes to stderr.
+
+Exit codes:
+  0      The program ended normally (e.g. with END or SYSTEM).
+  1      The program ended with an unhandled runtime error, which is printed to stderr.
+         For `format --check`, 1 means that some programs are not formatted.
+         For `test`, 1 means that some tests failed.
+  2      The program has parse or lint errors, or it cannot be transpiled.
+  64     Invalid command line arguments.
+  74     The program could not be read, or the compiled program is invalid
+         (e.g. it was compiled by a different version).
+";
+
+/// Exit code for unhandled runtime errors.
+/// It is the same for all errors, so that it does not collide with the other exit codes.
+const EXIT_RUNTIME_ERROR: u8 = 1;
+
+/// Exit code for parse and lint errors.
+const EXIT_COMPILE_ERROR: u8 = 2;
+
+/// Exit code for invalid command line arguments.
+const EXIT_USAGE: u8 = 64;
+
+/// Exit code for programs that cannot be read.
+const EXIT_IO_ERROR: u8 = 74;
+
+fn main() -> ExitCode {
+    if is_running_in_apache() {
+        return run::run_in_apache();
+    }
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.split_first() {
+        Some((command, rest)) => match command.as_str() {
+            "run" => run::run(rest),
+            "check" => check::check(rest),
+            "compile" => compile::compile(rest),
+            "debug" => run::debug(rest),
+            "profile" => run::profile(rest),
+            "dump-ast" => dump::dump_ast(rest),
+            "dump-instructions" => dump::dump_instructions(rest),
+            "format" => format::format(rest),
+            "test" => test::test(rest),
+            "transpile-rust" => transpile::transpile_rust(rest),
+            "transpile-js" => transpile::transpile_js(rest),
+            "repl" => repl::repl(rest),
+            "help" | "--help" | "-h" => {
+                print!("{}", USAGE);
+                ExitCode::SUCCESS
+            }
+            _ if is_program_file(command) => run::run(&args),
+            _ => usage_error(&format!("Unknown command {}.", command)),
+        },
+        None => repl::repl(&[]),
+    }
+}
+
+fn usage_error(msg: &str) -> ExitCode {
+    eprintln!("{}\n\n{}", msg, USAGE);
+    ExitCode::from(EXIT_USAGE)
+}
+
+/// Gets the single file argument of a command.
+fn single_file_arg(args: &[String]) -> Result<&str, ExitCode> {
+    match args {
+        [file_name] => Ok(file_name),
+        [] => Err(usage_error("Please specify the program.")),
+        _ => Err(usage_error("Please specify only one program.")),
+    }
+}
+
+/// Checks if the first argument is a program, for the `rusty_basic <file>` shortcut,
+/// so that a mistyped command is not run as a program.
+fn is_program_file(arg: &str) -> bool {
+    let path = std::path::Path::new(arg);
+    path.extension().is_some() || path.is_file()
+}
+
+/// Checks if we're running inside Apache with mod_cgi.
+fn is_running_in_apache() -> bool {
+    match std::env::var("SERVER_NAME") {
+        Ok(x) => !x.is_empty(),
+        Err(_) => false,
+    }
+}
@@ -0,0 +1,65 @@
+use std::io::{BufRead, Write};
+use std::process::ExitCode;
+
+use rusty_basic::interpreter::new_default_interpreter_with_lpt1;
+use rusty_basic::repl::{Response, Session};
+use rusty_parser::UserDefinedTypes;
+
+use crate::run::open_lpt1;
+use crate::{EXIT_IO_ERROR, usage_error};
+
+const BANNER: &str = "rusty_basic interactive mode.
+Enter statements to run them immediately. LOAD, SAVE, LIST, RUN and NEW
+manage the program buffer, SYSTEM exits. An empty line ends an unfinished block.";
+
+/// Runs an interactive session, reading one line at a time.
+pub fn repl(args: &[String]) -> ExitCode {
+    if !args.is_empty() {
+        return usage_error("The repl command does not take arguments.");
+    }
+    let mut session = Session::new(|| {
+        let lpt1 = open_lpt1().unwrap_or_else(|e| {
+            eprintln!("Could not open printer, output will be discarded. {}", e);
+            Default::default()
+        });
+        new_default_interpreter_with_lpt1(UserDefinedTypes::default(), lpt1)
+    });
+    println!("{}", BANNER);
+    let mut stdin = std::io::stdin().lock();
+    // the lines of an unfinished block
+    let mut pending = String::new();
+    loop {
+        print!("{}", if pending.is_empty() { "> " } else { ". " });
+        std::io::stdout().flush().unwrap_or_default();
+        let mut line = String::new();
+        match stdin.read_line(&mut line) {
+            Ok(0) => return ExitCode::SUCCESS,
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("Could not read input. {}", e);
+                return ExitCode::from(EXIT_IO_ERROR);
+            }
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        let result = if line.trim().is_empty() {
+            if pending.is_empty() {
+                continue;
+            }
+            session.eval_complete(&pending)
+        } else {
+            if !pending.is_empty() {
+                pending.push('\n');
+            }
+            pending.push_str(line);
+            session.eval(&pending)
+        };
+        match result {
+            Ok(Response::Incomplete) => continue,
+            Ok(Response::Done) => {}
+            Ok(Response::Listing(program)) => print!("{}", program),
+            Ok(Response::Exit) => return ExitCode::SUCCESS,
+            Err(e) => eprint!("{}", session.render_error(&e)),
+        }
+        pending.clear();
+    }
+}
@@ -0,0 +1,193 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::str::FromStr;
+use std::time::Duration;
+
+use rusty_basic::bytecode::CompiledProgram;
+use rusty_basic::interpreter::{
+    ExecutionLimits, FilePolicy, InterpreterTrait, Lpt1Write, Profiler,
+    new_default_interpreter_with_lpt1,
+};
+
+use crate::debug::TerminalDebugger;
+use crate::source::load_program;
+use crate::{EXIT_IO_ERROR, EXIT_RUNTIME_ERROR, usage_error};
+
+/// Runs the program, which is the first argument.
+/// It can be a source file or a precompiled program.
+/// The remaining arguments are the command line of the program.
+pub fn run(args: &[String]) -> ExitCode {
+    match args.split_first() {
+        Some((file_name, program_args)) => {
+            run_file(file_name, &program_args.join(" "), false, Mode::Run)
+        }
+        None => usage_error("Please specify the program to run."),
+    }
+}
+
+/// Runs the program, which is the first argument, under the terminal debugger.
+/// The remaining arguments are the command line of the program.
+pub fn debug(args: &[String]) -> ExitCode {
+    match args.split_first() {
+        Some((file_name, program_args)) => {
+            run_file(file_name, &program_args.join(" "), false, Mode::Debug)
+        }
+        None => usage_error("Please specify the program to debug."),
+    }
+}
+
+/// Runs the program, which is the first argument, under the profiler.
+/// The remaining arguments are the command line of the program.
+///
+/// The report is printed to stderr, so that it does not mix with the output
+/// of the program. With `--folded <file>`, the folded call stacks are written
+/// to the given file, for flame graph tools.
+pub fn profile(args: &[String]) -> ExitCode {
+    let (folded_file, args) = match args {
+        [flag, folded_file, rest @ ..] if flag == "--folded" => (Some(folded_file.as_str()), rest),
+        [flag] if flag == "--folded" => {
+            return usage_error("Please specify the folded stacks file.");
+        }
+        _ => (None, args),
+    };
+    match args.split_first() {
+        Some((file_name, program_args)) => run_file(
+            file_name,
+            &program_args.join(" "),
+            false,
+            Mode::Profile(folded_file),
+        ),
+        None => usage_error("Please specify the program to profile."),
+    }
+}
+
+/// Runs the program specified by the PATH_TRANSLATED env variable,
+/// which is set by Apache with mod_cgi.
+pub fn run_in_apache() -> ExitCode {
+    let file_name = std::env::var("PATH_TRANSLATED")
+        .expect("The PATH_TRANSLATED env variable should be the program to run");
+    run_file(&file_name, "", true, Mode::Run)
+}
+
+/// How to run a program.
+enum Mode<'a> {
+    Run,
+    Debug,
+    /// Profiles the program, optionally writing the folded stacks to the given file.
+    Profile(Option<&'a str>),
+}
+
+fn run_file(file_name: &str, command_line: &str, set_current_dir: bool, mode: Mode) -> ExitCode {
+    let (
+        CompiledProgram {
+            instruction_generator_result,
+            user_defined_types,
+            ..
+        },
+        diagnostics,
+    ) = match load_program(file_name) {
+        Ok(x) => x,
+        Err(e) => return e,
+    };
+    let limits = match limits_from_env() {
+        Ok(limits) => limits,
+        Err(e) => return usage_error(&e),
+    };
+    let lpt1 = match open_lpt1() {
+        Ok(lpt1) => lpt1,
+        Err(e) => {
+            eprintln!("Could not open printer. {}", e);
+            return ExitCode::from(EXIT_IO_ERROR);
+        }
+    };
+    let mut interpreter = new_default_interpreter_with_lpt1(user_defined_types, lpt1);
+    interpreter.set_limits(limits);
+    interpreter
+        .stdlib_mut()
+        .set_command_line(command_line.to_owned());
+    if set_current_dir {
+        set_current_dir_to_parent(file_name);
+    }
+    let result = match mode {
+        Mode::Run => interpreter.interpret(instruction_generator_result),
+        Mode::Debug => {
+            let mut debugger = TerminalDebugger::new(&diagnostics);
+            interpreter.debug(instruction_generator_result, &mut debugger)
+        }
+        Mode::Profile(folded_file) => {
+            let mut profiler = Profiler::new();
+            let result = interpreter.profile(instruction_generator_result, &mut profiler);
+            eprint!("{}", profiler.report(&diagnostics));
+            if let Some(folded_file) = folded_file
+                && let Err(e) = std::fs::write(folded_file, profiler.folded_stacks())
+            {
+                eprintln!("Could not write {}. {}", folded_file, e);
+                return ExitCode::from(EXIT_IO_ERROR);
+            }
+            result
+        }
+    };
+    match result {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprint!("{}", diagnostics.render_runtime_error(&e));
+            ExitCode::from(EXIT_RUNTIME_ERROR)
+        }
+    }
+}
+
+/// Reads the execution limits from the environment variables,
+/// e.g. set with `SetEnv` in the Apache configuration.
+fn limits_from_env() -> Result<ExecutionLimits, String> {
+    Ok(ExecutionLimits {
+        max_instructions: env_number("RUSTY_BASIC_MAX_INSTRUCTIONS")?,
+        timeout: env_number("RUSTY_BASIC_TIMEOUT")?
+            .map(|seconds| {
+                Duration::try_from_secs_f64(seconds)
+                    .map_err(|_| format!("Invalid value for RUSTY_BASIC_TIMEOUT: {}", seconds))
+            })
+            .transpose()?,
+        max_memory: env_number("RUSTY_BASIC_MAX_MEMORY")?,
+        max_call_depth: env_number("RUSTY_BASIC_MAX_CALL_DEPTH")?,
+        file_policy: match std::env::var("RUSTY_BASIC_FILES") {
+            Ok(value) => match value.as_str() {
+                "" | "allow" => FilePolicy::Allow,
+                "deny" => FilePolicy::Deny,
+                "read-only" => FilePolicy::ReadOnly,
+                dir => FilePolicy::Confined(PathBuf::from(dir)),
+            },
+            Err(_) => FilePolicy::Allow,
+        },
+    })
+}
+
+/// Reads a non-negative number from the given environment variable, if it is set.
+fn env_number<T: FromStr>(name: &str) -> Result<Option<T>, String> {
+    match std::env::var(name) {
+        Ok(value) if !value.is_empty() => value
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("Invalid value for {}: {}", name, value)),
+        _ => Ok(None),
+    }
+}
+
+/// Opens the sink for `LPRINT` output, based on the LPT1 env variable.
+/// A value starting with `|` pipes the output to a command (e.g. `|lpr`),
+/// any other value is a file. If not set, the output goes to stderr,
+/// so that it does not mix with the output of the program.
+pub fn open_lpt1() -> std::io::Result<Lpt1Write> {
+    match std::env::var("LPT1") {
+        Ok(value) if !value.is_empty() => match value.strip_prefix('|') {
+            Some(command) => Lpt1Write::pipe(command),
+            None => Lpt1Write::file(value),
+        },
+        _ => Ok(Lpt1Write::stderr()),
+    }
+}
+
+fn set_current_dir_to_parent(file_name: &str) {
+    let canonical = std::fs::canonicalize(file_name).unwrap();
+    let parent = canonical.parent().unwrap();
+    std::env::set_current_dir(parent).expect("Could not set current directory");
+}
@@ -0,0 +1,79 @@
+use std::process::ExitCode;
+
+use rusty_basic::bytecode::CompiledProgram;
+use rusty_basic::diagnostics::Diagnostics;
+use rusty_basic::instruction_generator::{generate_instructions, unwrap_linter_context};
+use rusty_linter::core::{LinterContext, lint};
+use rusty_parser::{Program, parse_main_str};
+
+use crate::{EXIT_COMPILE_ERROR, EXIT_IO_ERROR};
+
+/// Reads and parses the given file.
+/// Errors are printed to stderr.
+pub fn parse_file(file_name: &str) -> Result<(Program, Diagnostics), ExitCode> {
+    let source = std::fs::read_to_string(file_name).map_err(|e| {
+        eprintln!("Could not read {}. {}", file_name, e);
+        ExitCode::from(EXIT_IO_ERROR)
+    })?;
+    let mut diagnostics = Diagnostics::new(file_name, &source);
+    match parse_main_str(source) {
+        Ok(program) => {
+            diagnostics.register_subprograms(&program);
+            Ok((program, diagnostics))
+        }
+        Err(e) => {
+            eprint!("{}", diagnostics.render_parse_error(&e));
+            Err(ExitCode::from(EXIT_COMPILE_ERROR))
+        }
+    }
+}
+
+/// Reads, parses and lints the given file.
+/// Errors are printed to stderr.
+pub fn lint_file(file_name: &str) -> Result<(Program, LinterContext, Diagnostics), ExitCode> {
+    let (program, diagnostics) = parse_file(file_name)?;
+    match lint(program) {
+        Ok((linted_program, linter_context)) => Ok((linted_program, linter_context, diagnostics)),
+        Err(e) => {
+            eprint!("{}", diagnostics.render_lint_error(&e));
+            Err(ExitCode::from(EXIT_COMPILE_ERROR))
+        }
+    }
+}
+
+/// Reads, parses, lints and compiles the given file.
+/// Errors are printed to stderr.
+pub fn compile_file(file_name: &str) -> Result<(CompiledProgram, Diagnostics), ExitCode> {
+    let (program, linter_context, diagnostics) = lint_file(file_name)?;
+    let (linter_names, user_defined_types) = unwrap_linter_context(linter_context);
+    let compiled_program = CompiledProgram {
+        file_name: file_name.to_owned(),
+        instruction_generator_result: generate_instructions(program, linter_names),
+        user_defined_types,
+    };
+    Ok((compiled_program, diagnostics))
+}
+
+/// Loads the program to run from the given file, which is either a source file
+/// or a precompiled program (see the `compile` command).
+/// Errors are printed to stderr.
+pub fn load_program(file_name: &str) -> Result<(CompiledProgram, Diagnostics), ExitCode> {
+    let bytes = std::fs::read(file_name).map_err(|e| {
+        eprintln!("Could not read {}. {}", file_name, e);
+        ExitCode::from(EXIT_IO_ERROR)
+    })?;
+    if !CompiledProgram::is_compiled(&bytes) {
+        return compile_file(file_name);
+    }
+    match CompiledProgram::from_bytes(&bytes) {
+        Ok(compiled_program) => {
+            // the source is not available, errors show only the location
+            let diagnostics = Diagnostics::new(&compiled_program.file_name, "");
+            Ok((compiled_program, diagnostics))
+        }
+        Err(e) => {
+            eprintln!("Could not read {}. {}", file_name, e);
+            Err(ExitCode::from(EXIT_IO_ERROR))
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::process::ExitCode;
+
+use rusty_basic::test_runner::{Coverage, run_tests};
+
+use crate::source::parse_file;
+use crate::{EXIT_COMPILE_ERROR, EXIT_IO_ERROR, usage_error};
+
+/// Runs the tests of the given files.
+///
+/// With `--coverage <lcov-file>`, the line coverage of the files
+/// is written to the given file in lcov format.
+pub fn test(args: &[String]) -> ExitCode {
+    let (coverage_file, file_names) = match args {
+        [flag, coverage_file, rest @ ..] if flag == "--coverage" => (Some(coverage_file), rest),
+        [flag] if flag == "--coverage" => {
+            return usage_error("Please specify the coverage file.");
+        }
+        _ => (None, args),
+    };
+    if file_names.is_empty() {
+        return usage_error("Please specify at least one program.");
+    }
+    let mut exit_code = ExitCode::SUCCESS;
+    let mut passed = 0;
+    let mut failed = 0;
+    let mut lcov = String::new();
+    for file_name in file_names {
+        let (program, diagnostics) = match parse_file(file_name) {
+            Ok(x) => x,
+            Err(e) => {
+                exit_code = e;
+                continue;
+            }
+        };
+        let mut coverage = Coverage::default();
+        let results = match run_tests(&program, &mut coverage) {
+            Ok(results) => results,
+            Err(e) => {
+                eprint!("{}", diagnostics.render_lint_error(&e));
+                exit_code = ExitCode::from(EXIT_COMPILE_ERROR);
+                continue;
+            }
+        };
+        for result in results {
+            let location = format!("{}:{}", file_name, result.pos.row());
+            match &result.error {
+                Some(e) => {
+                    println!("FAIL {} ({})", result.name, location);
+                    print!("{}", result.output);
+                    print!("{}", diagnostics.render_runtime_error(e));
+                    failed += 1;
+                }
+                None if !result.ran => {
+                    println!("FAIL {} ({})", result.name, location);
+                    print!("{}", result.output);
+                    println!("The program ended before the test was called.");
+                    failed += 1;
+                }
+                None => {
+                    println!("PASS {} ({})", result.name, location);
+                    passed += 1;
+                }
+            }
+        }
+        lcov.push_str(&coverage.to_lcov(file_name));
+    }
+    println!("{} passed, {} failed", passed, failed);
+    if let Some(coverage_file) = coverage_file
+        && let Err(e) = std::fs::write(coverage_file, lcov)
+    {
+        eprintln!("Could not write {}. {}", coverage_file, e);
+        return ExitCode::from(EXIT_IO_ERROR);
+    }
+    if failed > 0 {
+        ExitCode::FAILURE
+    } else {
+        exit_code
+    }
+}
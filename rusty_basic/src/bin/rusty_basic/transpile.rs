@@ -0,0 +1,89 @@
+use std::path::Path;
+use std::process::ExitCode;
+
+use rusty_basic::transpiler::{transpile_to_js, transpile_to_rust};
+
+use crate::source::lint_file;
+use crate::{EXIT_COMPILE_ERROR, EXIT_IO_ERROR, usage_error};
+
+/// Transpiles the given program into a Rust crate in the given directory.
+///
+/// The crate depends on the runtime crate in the directory given with
+/// `--runtime-path <dir>`.
+pub fn transpile_rust(args: &[String]) -> ExitCode {
+    let [flag, runtime_path, file_name, out_dir] = args else {
+        return usage_error(
+            "Please specify the path of the runtime crate, the program and the output directory.",
+        );
+    };
+    if flag != "--runtime-path" {
+        return usage_error("Please specify the path of the runtime crate with --runtime-path.");
+    }
+    let (program, _, diagnostics) = match lint_file(file_name) {
+        Ok(x) => x,
+        Err(exit_code) => return exit_code,
+    };
+    let rust_crate = match transpile_to_rust(program, &crate_name(file_name), runtime_path) {
+        Ok(rust_crate) => rust_crate,
+        Err(e) => {
+            eprint!("{}", diagnostics.render_transpile_error(&e));
+            return ExitCode::from(EXIT_COMPILE_ERROR);
+        }
+    };
+    match rust_crate.write_to(Path::new(out_dir)) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Could not write {}. {}", out_dir, e);
+            ExitCode::from(EXIT_IO_ERROR)
+        }
+    }
+}
+
+/// Derives the name of the crate from the name of the program,
+/// e.g. `hello_world` for `Hello World.bas`.
+fn crate_name(file_name: &str) -> String {
+    let stem = Path::new(file_name)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let name: String = stem
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() {
+                ch.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.starts_with(|ch: char| ch.is_ascii_alphabetic()) {
+        name
+    } else {
+        format!("program_{}", name)
+    }
+}
+
+/// Transpiles the given program into a JavaScript module in the given directory.
+pub fn transpile_js(args: &[String]) -> ExitCode {
+    let [file_name, out_dir] = args else {
+        return usage_error("Please specify the program and the output directory.");
+    };
+    let (program, _, diagnostics) = match lint_file(file_name) {
+        Ok(x) => x,
+        Err(exit_code) => return exit_code,
+    };
+    let js_module = match transpile_to_js(program) {
+        Ok(js_module) => js_module,
+        Err(e) => {
+            eprint!("{}", diagnostics.render_transpile_error(&e));
+            return ExitCode::from(EXIT_COMPILE_ERROR);
+        }
+    };
+    match js_module.write_to(Path::new(out_dir)) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Could not write {}. {}", out_dir, e);
+            ExitCode::from(EXIT_IO_ERROR)
+        }
+    }
+}
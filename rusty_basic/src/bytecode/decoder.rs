@@ -0,0 +1,538 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use rusty_common::{AtPos, CaseInsensitiveString, Position, Positioned};
+use rusty_linter::core::ScopeName;
+use rusty_parser::{
+    BareNamePos, BuiltInFunction, BuiltInSub, Element, ElementType, EventAction, EventKind,
+    Expression, ExpressionType, FileHandle, Name, TypeQualifier, UserDefinedType, UserDefinedTypes,
+};
+use rusty_variant::{UserDefinedTypeValue, VArray, Variant};
+
+use crate::RuntimeError;
+use crate::bytecode::encoder::BUILT_IN_SUBS;
+use crate::bytecode::{BytecodeError, CompiledProgram};
+use crate::instruction_generator::{
+    AddressOrLabel, Instruction, InstructionGeneratorResult, InstructionPos, Path, PrinterType,
+    RootPath,
+};
+
+/// Reads values in the binary format.
+/// Reading past the end or finding an unknown tag is an error.
+pub struct Decoder<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    /// Gets the bytes that have not been read yet.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Checks that all bytes have been read.
+    pub fn finish(&self) -> Result<(), BytecodeError> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err(corrupted("unexpected data after the end of the program"))
+        }
+    }
+
+    fn raw(&mut self, len: usize) -> Result<&'a [u8], BytecodeError> {
+        if len > self.bytes.len() {
+            return Err(corrupted("unexpected end of file"));
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], BytecodeError> {
+        Ok(self.raw(N)?.try_into().unwrap())
+    }
+
+    pub fn u8(&mut self) -> Result<u8, BytecodeError> {
+        Ok(self.raw(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, BytecodeError> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    pub fn u32(&mut self) -> Result<u32, BytecodeError> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    pub fn u64(&mut self) -> Result<u64, BytecodeError> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    pub fn bool(&mut self) -> Result<bool, BytecodeError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            tag => Err(invalid_tag("boolean", tag)),
+        }
+    }
+
+    pub fn option<T>(
+        &mut self,
+        decode: impl FnOnce(&mut Self) -> Result<T, BytecodeError>,
+    ) -> Result<Option<T>, BytecodeError> {
+        if self.bool()? {
+            decode(self).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn usize(&mut self) -> Result<usize, BytecodeError> {
+        self.u32().map(|value| value as usize)
+    }
+
+    /// Reads the length of a list, making sure that it is not bigger than
+    /// the remaining bytes (every item takes at least one byte),
+    /// so that a corrupted length can't allocate too much memory.
+    pub fn len(&mut self) -> Result<usize, BytecodeError> {
+        let len = self.usize()?;
+        if len > self.bytes.len() {
+            Err(corrupted("unexpected end of file"))
+        } else {
+            Ok(len)
+        }
+    }
+
+    fn string(&mut self, len: usize) -> Result<String, BytecodeError> {
+        let bytes = self.raw(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| corrupted("invalid string"))
+    }
+
+    pub fn short_str(&mut self) -> Result<String, BytecodeError> {
+        let len = self.u8()? as usize;
+        self.string(len)
+    }
+
+    pub fn str(&mut self) -> Result<String, BytecodeError> {
+        let len = self.usize()?;
+        self.string(len)
+    }
+
+    pub fn case_insensitive_string(&mut self) -> Result<CaseInsensitiveString, BytecodeError> {
+        self.str().map(CaseInsensitiveString::new)
+    }
+
+    pub fn pos(&mut self) -> Result<Position, BytecodeError> {
+        let row = self.u32()?;
+        let col = self.u32()?;
+        if row == 0 || col == 0 {
+            Err(corrupted("invalid position"))
+        } else {
+            Ok(Position::new(row, col))
+        }
+    }
+
+    fn bare_name_pos(&mut self) -> Result<BareNamePos, BytecodeError> {
+        let bare_name = self.case_insensitive_string()?;
+        let pos = self.pos()?;
+        Ok(bare_name.at_pos(pos))
+    }
+
+    fn type_qualifier(&mut self) -> Result<TypeQualifier, BytecodeError> {
+        match self.u8()? {
+            0 => Ok(TypeQualifier::BangSingle),
+            1 => Ok(TypeQualifier::HashDouble),
+            2 => Ok(TypeQualifier::DollarString),
+            3 => Ok(TypeQualifier::PercentInteger),
+            4 => Ok(TypeQualifier::AmpersandLong),
+            tag => Err(invalid_tag("type qualifier", tag)),
+        }
+    }
+
+    pub fn name(&mut self) -> Result<Name, BytecodeError> {
+        let bare_name = self.case_insensitive_string()?;
+        let opt_q = if self.bool()? {
+            Some(self.type_qualifier()?)
+        } else {
+            None
+        };
+        Ok(Name::new(bare_name, opt_q))
+    }
+
+    pub fn variant(&mut self) -> Result<Variant, BytecodeError> {
+        match self.u8()? {
+            0 => self
+                .u32()
+                .map(|bits| Variant::VSingle(f32::from_bits(bits))),
+            1 => self
+                .u64()
+                .map(|bits| Variant::VDouble(f64::from_bits(bits))),
+            2 => self.str().map(Variant::VString),
+            3 => self.u32().map(|i| Variant::VInteger(i as i32)),
+            4 => self.u64().map(|l| Variant::VLong(l as i64)),
+            5 => self
+                .array_value()
+                .map(|v_arr| Variant::VArray(Box::new(v_arr))),
+            6 => {
+                let len = self.len()?;
+                let properties = (0..len)
+                    .map(|_| Ok((self.case_insensitive_string()?, self.variant()?)))
+                    .collect::<Result<_, BytecodeError>>()?;
+                Ok(Variant::VUserDefined(Box::new(UserDefinedTypeValue::new(
+                    properties,
+                ))))
+            }
+            tag => Err(invalid_tag("value", tag)),
+        }
+    }
+
+    fn array_value(&mut self) -> Result<VArray, BytecodeError> {
+        let len = self.len()?;
+        let mut dimensions: Vec<(i32, i32)> = Vec::with_capacity(len);
+        // every element takes at least one byte,
+        // so a corrupted file can't allocate too much memory
+        let mut elements: usize = 1;
+        for _ in 0..len {
+            let lbound = self.u32()? as i32;
+            let ubound = self.u32()? as i32;
+            elements = (ubound as i64 - lbound as i64 + 1)
+                .try_into()
+                .ok()
+                .and_then(|count: usize| elements.checked_mul(count))
+                .filter(|elements| *elements > 0 && *elements <= self.bytes.len())
+                .ok_or_else(|| corrupted("invalid array dimensions"))?;
+            dimensions.push((lbound, ubound));
+        }
+        if dimensions.is_empty() {
+            return Err(corrupted("invalid array dimensions"));
+        }
+        let first = self.variant()?;
+        let mut v_arr = VArray::new(dimensions, first);
+        for i in 1..elements {
+            *v_arr.get_mut(i).unwrap() = self.variant()?;
+        }
+        Ok(v_arr)
+    }
+
+    pub fn path(&mut self) -> Result<Path, BytecodeError> {
+        match self.u8()? {
+            0 => self.root_path().map(Path::Root),
+            1 => {
+                let parent = self.path()?;
+                let len = self.len()?;
+                let indices = (0..len).map(|_| self.variant()).collect::<Result<_, _>>()?;
+                Ok(Path::ArrayElement(Box::new(parent), indices))
+            }
+            2 => {
+                let parent = self.path()?;
+                let property_name = self.case_insensitive_string()?;
+                Ok(Path::Property(Box::new(parent), property_name))
+            }
+            tag => Err(invalid_tag("path", tag)),
+        }
+    }
+
+    fn root_path(&mut self) -> Result<RootPath, BytecodeError> {
+        let slot = self.usize()?;
+        let shared = self.bool()?;
+        Ok(RootPath { slot, shared })
+    }
+
+    fn address(&mut self) -> Result<AddressOrLabel, BytecodeError> {
+        self.usize().map(AddressOrLabel::Resolved)
+    }
+
+    fn expression_type(&mut self) -> Result<ExpressionType, BytecodeError> {
+        match self.u8()? {
+            0 => Ok(ExpressionType::Unresolved),
+            1 => self.type_qualifier().map(ExpressionType::BuiltIn),
+            2 => self.u16().map(ExpressionType::FixedLengthString),
+            3 => self
+                .case_insensitive_string()
+                .map(ExpressionType::UserDefined),
+            4 => self
+                .expression_type()
+                .map(|element_type| ExpressionType::Array(Box::new(element_type))),
+            tag => Err(invalid_tag("expression type", tag)),
+        }
+    }
+
+    pub fn scope_name(&mut self) -> Result<ScopeName, BytecodeError> {
+        match self.u8()? {
+            0 => Ok(ScopeName::Global),
+            1 => self.name().map(ScopeName::Function),
+            2 => self.case_insensitive_string().map(ScopeName::Sub),
+            tag => Err(invalid_tag("scope", tag)),
+        }
+    }
+
+    fn runtime_error(&mut self) -> Result<RuntimeError, BytecodeError> {
+        match self.u8()? {
+            0 => self.str().map(RuntimeError::AssertionFailed),
+            1 => Ok(RuntimeError::BadFileMode),
+            2 => Ok(RuntimeError::BadFileNameOrNumber),
+            3 => Ok(RuntimeError::BadRecordLength),
+            4 => Ok(RuntimeError::BadRecordNumber),
+            5 => Ok(RuntimeError::DivisionByZero),
+            6 => Ok(RuntimeError::ElementNotDefined),
+            7 => Ok(RuntimeError::FieldOverflow),
+            8 => Ok(RuntimeError::FileAlreadyOpen),
+            9 => Ok(RuntimeError::FileNotFound),
+            10 => Ok(RuntimeError::ForLoopZeroStep),
+            11 => self.str().map(RuntimeError::DeviceIOError),
+            12 => Ok(RuntimeError::IllegalFunctionCall),
+            13 => Ok(RuntimeError::InputPastEndOfFile),
+            14 => Ok(RuntimeError::OutOfData),
+            15 => Ok(RuntimeError::Overflow),
+            16 => Ok(RuntimeError::ReturnWithoutGoSub),
+            17 => Ok(RuntimeError::SubscriptOutOfRange),
+            18 => Ok(RuntimeError::TypeMismatch),
+            19 => Ok(RuntimeError::VariableRequired),
+            20 => self.str().map(RuntimeError::Other),
+            21 => Ok(RuntimeError::ResumeWithoutError),
+            22 => self.u32().map(|code| RuntimeError::ErrorCode(code as i32)),
+            23 => Ok(RuntimeError::InstructionLimitExceeded),
+            24 => Ok(RuntimeError::OutOfMemory),
+            25 => Ok(RuntimeError::OutOfStackSpace),
+            26 => Ok(RuntimeError::PermissionDenied),
+            27 => Ok(RuntimeError::TimeLimitExceeded),
+            tag => Err(invalid_tag("error", tag)),
+        }
+    }
+
+    fn event_kind(&mut self) -> Result<EventKind, BytecodeError> {
+        match self.u8()? {
+            0 => Ok(EventKind::Key),
+            1 => Ok(EventKind::Play),
+            2 => Ok(EventKind::Timer),
+            tag => Err(invalid_tag("event", tag)),
+        }
+    }
+
+    fn event_action(&mut self) -> Result<EventAction, BytecodeError> {
+        match self.u8()? {
+            0 => Ok(EventAction::On),
+            1 => Ok(EventAction::Off),
+            2 => Ok(EventAction::Stop),
+            tag => Err(invalid_tag("event action", tag)),
+        }
+    }
+
+    pub fn printer_type(&mut self) -> Result<PrinterType, BytecodeError> {
+        match self.u8()? {
+            0 => Ok(PrinterType::Print),
+            1 => Ok(PrinterType::LPrint),
+            2 => Ok(PrinterType::File),
+            tag => Err(invalid_tag("printer type", tag)),
+        }
+    }
+
+    fn built_in_sub(&mut self) -> Result<BuiltInSub, BytecodeError> {
+        let tag = self.u8()?;
+        BUILT_IN_SUBS
+            .get(tag as usize)
+            .copied()
+            .ok_or_else(|| invalid_tag("built-in sub", tag))
+    }
+
+    fn built_in_function(&mut self) -> Result<BuiltInFunction, BytecodeError> {
+        let name = self.short_str()?;
+        BuiltInFunction::try_from(name.as_str())
+            .map_err(|_| corrupted(&format!("unknown built-in function {}", name)))
+    }
+
+    fn instruction(&mut self) -> Result<Instruction, BytecodeError> {
+        let instruction = match self.u8()? {
+            0 => {
+                let root_path = self.root_path()?;
+                Instruction::VarPathName(root_path, self.name()?)
+            }
+            1 => Instruction::VarPathIndex,
+            2 => Instruction::VarPathProperty(self.case_insensitive_string()?),
+            3 => Instruction::CopyAToVarPath,
+            4 => Instruction::CopyVarPathToA,
+            5 => Instruction::PopVarPath,
+            6 => {
+                let root_path = self.root_path()?;
+                Instruction::CopyVariableToA(root_path, self.name()?)
+            }
+            7 => {
+                let root_path = self.root_path()?;
+                Instruction::CopyVariableToB(root_path, self.name()?)
+            }
+            8 => {
+                let root_path = self.root_path()?;
+                Instruction::CopyAToVariable(root_path, self.name()?)
+            }
+            9 => Instruction::LoadIntoA(self.variant()?),
+            10 => Instruction::LoadIntoB(self.variant()?),
+            11 => Instruction::CopyAToB,
+            12 => Instruction::CopyAToC,
+            13 => Instruction::CopyAToD,
+            14 => Instruction::CopyCToB,
+            15 => Instruction::CopyDToA,
+            16 => Instruction::CopyDToB,
+            17 => Instruction::Plus,
+            18 => Instruction::Minus,
+            19 => Instruction::Multiply,
+            20 => Instruction::Divide,
+            21 => Instruction::Modulo,
+            22 => Instruction::Less,
+            23 => Instruction::LessOrEqual,
+            24 => Instruction::Equal,
+            25 => Instruction::GreaterOrEqual,
+            26 => Instruction::Greater,
+            27 => Instruction::NotEqual,
+            28 => Instruction::NegateA,
+            29 => Instruction::NotA,
+            30 => Instruction::And,
+            31 => Instruction::Or,
+            32 => Instruction::PlusTyped(self.type_qualifier()?),
+            33 => Instruction::MinusTyped(self.type_qualifier()?),
+            34 => Instruction::MultiplyTyped(self.type_qualifier()?),
+            35 => Instruction::LessTyped(self.type_qualifier()?),
+            36 => Instruction::LessOrEqualTyped(self.type_qualifier()?),
+            37 => Instruction::EqualTyped(self.type_qualifier()?),
+            38 => Instruction::GreaterOrEqualTyped(self.type_qualifier()?),
+            39 => Instruction::GreaterTyped(self.type_qualifier()?),
+            40 => Instruction::NotEqualTyped(self.type_qualifier()?),
+            41 => Instruction::Label(self.case_insensitive_string()?),
+            42 => Instruction::Jump(self.address()?),
+            43 => Instruction::JumpIfFalse(self.address()?),
+            44 => Instruction::GoSub(self.address()?),
+            45 => Instruction::Return(None),
+            46 => Instruction::Return(Some(self.address()?)),
+            47 => Instruction::Resume,
+            48 => Instruction::ResumeNext,
+            49 => Instruction::ResumeLabel(self.address()?),
+            50 => Instruction::BuiltInSub(self.built_in_sub()?),
+            51 => Instruction::BuiltInFunction(self.built_in_function()?),
+            52 => Instruction::Halt,
+            53 => Instruction::Stop,
+            54 => Instruction::PushRegisters,
+            55 => Instruction::PopRegisters,
+            56 => Instruction::PushAToValueStack,
+            57 => Instruction::PopValueStackIntoA,
+            58 => Instruction::PushRet(self.usize()?),
+            59 => Instruction::PopRet,
+            60 => Instruction::BeginCollectArguments,
+            61 => Instruction::PushNamed(Rc::new(self.name()?)),
+            62 => Instruction::PushUnnamedByVal,
+            63 => Instruction::PushUnnamedByRef,
+            64 => Instruction::PushStack,
+            65 => Instruction::PushStaticStack(self.scope_name()?),
+            66 => Instruction::PopStack,
+            67 => Instruction::EnqueueToReturnStack(self.usize()?),
+            68 => Instruction::DequeueFromReturnStack,
+            69 => Instruction::StashFunctionReturnValue(self.usize()?),
+            70 => Instruction::UnStashFunctionReturnValue,
+            71 => Instruction::Throw(self.runtime_error()?),
+            72 => Instruction::OnErrorGoTo(self.address()?),
+            73 => Instruction::OnErrorResumeNext,
+            74 => Instruction::OnErrorGoToZero,
+            75 => {
+                let event_kind = self.event_kind()?;
+                Instruction::OnEventGoSub(event_kind, self.address()?)
+            }
+            76 => {
+                let event_kind = self.event_kind()?;
+                Instruction::EventControl(event_kind, self.event_action()?)
+            }
+            77 => Instruction::Cast(self.type_qualifier()?),
+            78 => Instruction::FixLength(self.u16()?),
+            79 => Instruction::AllocateBuiltIn(self.type_qualifier()?),
+            80 => Instruction::AllocateFixedLengthString(self.u16()?),
+            81 => Instruction::AllocateArrayIntoA(self.expression_type()?),
+            82 => Instruction::AllocateUserDefined(self.case_insensitive_string()?),
+            83 => Instruction::PrintSetPrinterType(self.printer_type()?),
+            84 => Instruction::PrintSetFileHandle(FileHandle::from(self.u8()?)),
+            85 => Instruction::PrintSetFormatStringFromA,
+            86 => Instruction::PrintComma,
+            87 => Instruction::PrintSemicolon,
+            88 => Instruction::PrintValueFromA,
+            89 => Instruction::PrintEnd,
+            90 => Instruction::IsVariableDefined(self.usize()?),
+            91 => Instruction::CallNative(self.case_insensitive_string()?),
+            tag => return Err(invalid_tag("instruction", tag)),
+        };
+        Ok(instruction)
+    }
+
+    fn user_defined_type(&mut self) -> Result<UserDefinedType, BytecodeError> {
+        let name = self.case_insensitive_string()?;
+        let len = self.len()?;
+        let mut elements = Vec::with_capacity(len);
+        for _ in 0..len {
+            let element_name = self.case_insensitive_string()?;
+            let pos = self.pos()?;
+            let element_type = match self.u8()? {
+                0 => ElementType::Integer,
+                1 => ElementType::Long,
+                2 => ElementType::Single,
+                3 => ElementType::Double,
+                4 => {
+                    // the length is all the interpreter needs,
+                    // the expression that defined it is not kept
+                    let len = self.u16()?;
+                    ElementType::FixedLengthString(
+                        Expression::IntegerLiteral(len as i32).at_pos(pos),
+                        len,
+                    )
+                }
+                5 => ElementType::UserDefined(self.bare_name_pos()?),
+                tag => return Err(invalid_tag("element type", tag)),
+            };
+            elements.push(Element::new(element_name, element_type, vec![]).at_pos(pos));
+        }
+        Ok(UserDefinedType::new(name, vec![], elements))
+    }
+
+    pub fn program(&mut self) -> Result<CompiledProgram, BytecodeError> {
+        let file_name = self.str()?;
+        let instruction_generator_result = self.instruction_generator_result()?;
+        let len = self.len()?;
+        let mut user_defined_types: UserDefinedTypes = HashMap::new();
+        for _ in 0..len {
+            let user_defined_type = self.user_defined_type()?;
+            user_defined_types.insert(user_defined_type.bare_name().clone(), user_defined_type);
+        }
+        Ok(CompiledProgram {
+            file_name,
+            instruction_generator_result,
+            user_defined_types,
+        })
+    }
+
+    fn instruction_generator_result(
+        &mut self,
+    ) -> Result<InstructionGeneratorResult, BytecodeError> {
+        let len = self.len()?;
+        let mut instructions: Vec<InstructionPos> = Vec::with_capacity(len);
+        for _ in 0..len {
+            let pos = self.pos()?;
+            instructions.push(Positioned::new(self.instruction()?, pos));
+        }
+        let len = self.len()?;
+        let statement_addresses = (0..len).map(|_| self.usize()).collect::<Result<_, _>>()?;
+        let len = self.len()?;
+        let global_names = (0..len).map(|_| self.name()).collect::<Result<_, _>>()?;
+        let max_local_slots = self.usize()?;
+        Ok(InstructionGeneratorResult {
+            instructions,
+            statement_addresses,
+            global_names,
+            max_local_slots,
+        })
+    }
+}
+
+pub fn corrupted(msg: &str) -> BytecodeError {
+    BytecodeError::Corrupted(msg.to_owned())
+}
+
+fn invalid_tag(what: &str, tag: u8) -> BytecodeError {
+    corrupted(&format!("invalid {} {}", what, tag))
+}
@@ -0,0 +1,603 @@
+use rusty_common::{CaseInsensitiveString, Position, Positioned};
+use rusty_linter::core::ScopeName;
+use rusty_parser::{
+    AsBareName, BareNamePos, BuiltInSub, ElementType, EventAction, EventKind, ExpressionType, Name,
+    TypeQualifier, UserDefinedType,
+};
+use rusty_variant::Variant;
+
+use crate::RuntimeError;
+use crate::bytecode::CompiledProgram;
+use crate::instruction_generator::{
+    AddressOrLabel, Instruction, InstructionGeneratorResult, Path, PrinterType, RootPath,
+};
+
+/// The built-in subs, by their encoded index.
+/// New subs need to be added at the end.
+pub const BUILT_IN_SUBS: &[BuiltInSub] = &[
+    BuiltInSub::Assert,
+    BuiltInSub::AssertEqual,
+    BuiltInSub::Beep,
+    BuiltInSub::CallAbsolute,
+    BuiltInSub::Close,
+    BuiltInSub::Cls,
+    BuiltInSub::Color,
+    BuiltInSub::Data,
+    BuiltInSub::DefSeg,
+    BuiltInSub::Environ,
+    BuiltInSub::Error,
+    BuiltInSub::Field,
+    BuiltInSub::Get,
+    BuiltInSub::Input,
+    BuiltInSub::Key,
+    BuiltInSub::Kill,
+    BuiltInSub::LineInput,
+    BuiltInSub::Locate,
+    BuiltInSub::LSet,
+    BuiltInSub::Name,
+    BuiltInSub::Open,
+    BuiltInSub::Poke,
+    BuiltInSub::Put,
+    BuiltInSub::Read,
+    BuiltInSub::Screen,
+    BuiltInSub::ViewPrint,
+    BuiltInSub::Width,
+];
+
+/// Writes values in the binary format.
+pub struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Self { buf: vec![] }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+
+    pub fn raw(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.raw(&value.to_le_bytes());
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.raw(&value.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.raw(&value.to_le_bytes());
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    pub fn option<T: ?Sized>(&mut self, value: Option<&T>, encode: impl FnOnce(&mut Self, &T)) {
+        match value {
+            Some(value) => {
+                self.bool(true);
+                encode(self, value);
+            }
+            None => self.bool(false),
+        }
+    }
+
+    /// Writes a length or an address.
+    pub fn usize(&mut self, value: usize) {
+        self.u32(u32::try_from(value).expect("Program is too big"));
+    }
+
+    /// Writes a string of up to 255 bytes.
+    pub fn short_str(&mut self, value: &str) {
+        self.u8(u8::try_from(value.len()).expect("String is too long"));
+        self.raw(value.as_bytes());
+    }
+
+    pub fn str(&mut self, value: &str) {
+        self.usize(value.len());
+        self.raw(value.as_bytes());
+    }
+
+    pub fn case_insensitive_string(&mut self, value: &CaseInsensitiveString) {
+        self.str(value);
+    }
+
+    pub fn pos(&mut self, pos: Position) {
+        self.u32(pos.row());
+        self.u32(pos.col());
+    }
+
+    fn bare_name_pos(&mut self, bare_name_pos: &BareNamePos) {
+        self.case_insensitive_string(&bare_name_pos.element);
+        self.pos(bare_name_pos.pos);
+    }
+
+    fn type_qualifier(&mut self, q: TypeQualifier) {
+        self.u8(match q {
+            TypeQualifier::BangSingle => 0,
+            TypeQualifier::HashDouble => 1,
+            TypeQualifier::DollarString => 2,
+            TypeQualifier::PercentInteger => 3,
+            TypeQualifier::AmpersandLong => 4,
+        });
+    }
+
+    pub fn name(&mut self, name: &Name) {
+        self.case_insensitive_string(name.as_bare_name());
+        match name.qualifier() {
+            Some(q) => {
+                self.bool(true);
+                self.type_qualifier(q);
+            }
+            None => self.bool(false),
+        }
+    }
+
+    pub fn variant(&mut self, v: &Variant) {
+        match v {
+            Variant::VSingle(f) => {
+                self.u8(0);
+                self.u32(f.to_bits());
+            }
+            Variant::VDouble(d) => {
+                self.u8(1);
+                self.u64(d.to_bits());
+            }
+            Variant::VString(s) => {
+                self.u8(2);
+                self.str(s);
+            }
+            Variant::VInteger(i) => {
+                self.u8(3);
+                self.u32(*i as u32);
+            }
+            Variant::VLong(l) => {
+                self.u8(4);
+                self.u64(*l as u64);
+            }
+            // constants are never arrays or user defined types,
+            // but the variables of a snapshot can be
+            Variant::VArray(v_arr) => {
+                self.u8(5);
+                let dimensions: Vec<&(i32, i32)> =
+                    (0..).map_while(|i| v_arr.get_dimension_bounds(i)).collect();
+                self.usize(dimensions.len());
+                for (lbound, ubound) in dimensions {
+                    self.u32(*lbound as u32);
+                    self.u32(*ubound as u32);
+                }
+                for i in 0..v_arr.len() {
+                    self.variant(v_arr.get(i).unwrap());
+                }
+            }
+            Variant::VUserDefined(v_u) => {
+                self.u8(6);
+                self.usize(v_u.names().count());
+                for (name, value) in v_u.names().zip(v_u.values()) {
+                    self.case_insensitive_string(name);
+                    self.variant(value);
+                }
+            }
+        }
+    }
+
+    fn root_path(&mut self, root_path: RootPath) {
+        self.usize(root_path.slot);
+        self.bool(root_path.shared);
+    }
+
+    pub fn path(&mut self, path: &Path) {
+        match path {
+            Path::Root(root_path) => {
+                self.u8(0);
+                self.root_path(*root_path);
+            }
+            Path::ArrayElement(parent, indices) => {
+                self.u8(1);
+                self.path(parent);
+                self.usize(indices.len());
+                for index in indices {
+                    self.variant(index);
+                }
+            }
+            Path::Property(parent, property_name) => {
+                self.u8(2);
+                self.path(parent);
+                self.case_insensitive_string(property_name);
+            }
+        }
+    }
+
+    fn address(&mut self, address_or_label: &AddressOrLabel) {
+        self.usize(address_or_label.address());
+    }
+
+    fn expression_type(&mut self, expression_type: &ExpressionType) {
+        match expression_type {
+            ExpressionType::Unresolved => self.u8(0),
+            ExpressionType::BuiltIn(q) => {
+                self.u8(1);
+                self.type_qualifier(*q);
+            }
+            ExpressionType::FixedLengthString(len) => {
+                self.u8(2);
+                self.u16(*len);
+            }
+            ExpressionType::UserDefined(name) => {
+                self.u8(3);
+                self.case_insensitive_string(name);
+            }
+            ExpressionType::Array(element_type) => {
+                self.u8(4);
+                self.expression_type(element_type);
+            }
+        }
+    }
+
+    pub fn scope_name(&mut self, scope_name: &ScopeName) {
+        match scope_name {
+            ScopeName::Global => self.u8(0),
+            ScopeName::Function(name) => {
+                self.u8(1);
+                self.name(name);
+            }
+            ScopeName::Sub(bare_name) => {
+                self.u8(2);
+                self.case_insensitive_string(bare_name);
+            }
+        }
+    }
+
+    fn runtime_error(&mut self, err: &RuntimeError) {
+        match err {
+            RuntimeError::AssertionFailed(msg) => {
+                self.u8(0);
+                self.str(msg);
+            }
+            RuntimeError::BadFileMode => self.u8(1),
+            RuntimeError::BadFileNameOrNumber => self.u8(2),
+            RuntimeError::BadRecordLength => self.u8(3),
+            RuntimeError::BadRecordNumber => self.u8(4),
+            RuntimeError::DivisionByZero => self.u8(5),
+            RuntimeError::ElementNotDefined => self.u8(6),
+            RuntimeError::FieldOverflow => self.u8(7),
+            RuntimeError::FileAlreadyOpen => self.u8(8),
+            RuntimeError::FileNotFound => self.u8(9),
+            RuntimeError::ForLoopZeroStep => self.u8(10),
+            RuntimeError::DeviceIOError(msg) => {
+                self.u8(11);
+                self.str(msg);
+            }
+            RuntimeError::IllegalFunctionCall => self.u8(12),
+            RuntimeError::InputPastEndOfFile => self.u8(13),
+            RuntimeError::OutOfData => self.u8(14),
+            RuntimeError::Overflow => self.u8(15),
+            RuntimeError::ReturnWithoutGoSub => self.u8(16),
+            RuntimeError::SubscriptOutOfRange => self.u8(17),
+            RuntimeError::TypeMismatch => self.u8(18),
+            RuntimeError::VariableRequired => self.u8(19),
+            RuntimeError::Other(msg) => {
+                self.u8(20);
+                self.str(msg);
+            }
+            RuntimeError::ResumeWithoutError => self.u8(21),
+            RuntimeError::ErrorCode(code) => {
+                self.u8(22);
+                self.u32(*code as u32);
+            }
+            RuntimeError::InstructionLimitExceeded => self.u8(23),
+            RuntimeError::OutOfMemory => self.u8(24),
+            RuntimeError::OutOfStackSpace => self.u8(25),
+            RuntimeError::PermissionDenied => self.u8(26),
+            RuntimeError::TimeLimitExceeded => self.u8(27),
+        }
+    }
+
+    fn event_kind(&mut self, event_kind: EventKind) {
+        self.u8(match event_kind {
+            EventKind::Key => 0,
+            EventKind::Play => 1,
+            EventKind::Timer => 2,
+        });
+    }
+
+    fn event_action(&mut self, event_action: EventAction) {
+        self.u8(match event_action {
+            EventAction::On => 0,
+            EventAction::Off => 1,
+            EventAction::Stop => 2,
+        });
+    }
+
+    pub fn printer_type(&mut self, printer_type: PrinterType) {
+        self.u8(match printer_type {
+            PrinterType::Print => 0,
+            PrinterType::LPrint => 1,
+            PrinterType::File => 2,
+        });
+    }
+
+    fn built_in_sub(&mut self, built_in_sub: BuiltInSub) {
+        let index = BUILT_IN_SUBS
+            .iter()
+            .position(|s| *s == built_in_sub)
+            .expect("Built-in sub missing from BUILT_IN_SUBS");
+        self.u8(index as u8);
+    }
+
+    fn instruction(&mut self, instruction: &Instruction) {
+        match instruction {
+            Instruction::VarPathName(root_path, name) => {
+                self.u8(0);
+                self.root_path(*root_path);
+                self.name(name);
+            }
+            Instruction::VarPathIndex => self.u8(1),
+            Instruction::VarPathProperty(name) => {
+                self.u8(2);
+                self.case_insensitive_string(name);
+            }
+            Instruction::CopyAToVarPath => self.u8(3),
+            Instruction::CopyVarPathToA => self.u8(4),
+            Instruction::PopVarPath => self.u8(5),
+            Instruction::CopyVariableToA(root_path, name) => {
+                self.u8(6);
+                self.root_path(*root_path);
+                self.name(name);
+            }
+            Instruction::CopyVariableToB(root_path, name) => {
+                self.u8(7);
+                self.root_path(*root_path);
+                self.name(name);
+            }
+            Instruction::CopyAToVariable(root_path, name) => {
+                self.u8(8);
+                self.root_path(*root_path);
+                self.name(name);
+            }
+            Instruction::LoadIntoA(v) => {
+                self.u8(9);
+                self.variant(v);
+            }
+            Instruction::LoadIntoB(v) => {
+                self.u8(10);
+                self.variant(v);
+            }
+            Instruction::CopyAToB => self.u8(11),
+            Instruction::CopyAToC => self.u8(12),
+            Instruction::CopyAToD => self.u8(13),
+            Instruction::CopyCToB => self.u8(14),
+            Instruction::CopyDToA => self.u8(15),
+            Instruction::CopyDToB => self.u8(16),
+            Instruction::Plus => self.u8(17),
+            Instruction::Minus => self.u8(18),
+            Instruction::Multiply => self.u8(19),
+            Instruction::Divide => self.u8(20),
+            Instruction::Modulo => self.u8(21),
+            Instruction::Less => self.u8(22),
+            Instruction::LessOrEqual => self.u8(23),
+            Instruction::Equal => self.u8(24),
+            Instruction::GreaterOrEqual => self.u8(25),
+            Instruction::Greater => self.u8(26),
+            Instruction::NotEqual => self.u8(27),
+            Instruction::NegateA => self.u8(28),
+            Instruction::NotA => self.u8(29),
+            Instruction::And => self.u8(30),
+            Instruction::Or => self.u8(31),
+            Instruction::PlusTyped(q) => self.typed(32, *q),
+            Instruction::MinusTyped(q) => self.typed(33, *q),
+            Instruction::MultiplyTyped(q) => self.typed(34, *q),
+            Instruction::LessTyped(q) => self.typed(35, *q),
+            Instruction::LessOrEqualTyped(q) => self.typed(36, *q),
+            Instruction::EqualTyped(q) => self.typed(37, *q),
+            Instruction::GreaterOrEqualTyped(q) => self.typed(38, *q),
+            Instruction::GreaterTyped(q) => self.typed(39, *q),
+            Instruction::NotEqualTyped(q) => self.typed(40, *q),
+            Instruction::Label(label) => {
+                self.u8(41);
+                self.case_insensitive_string(label);
+            }
+            Instruction::Jump(address) => {
+                self.u8(42);
+                self.address(address);
+            }
+            Instruction::JumpIfFalse(address) => {
+                self.u8(43);
+                self.address(address);
+            }
+            Instruction::GoSub(address) => {
+                self.u8(44);
+                self.address(address);
+            }
+            Instruction::Return(None) => self.u8(45),
+            Instruction::Return(Some(address)) => {
+                self.u8(46);
+                self.address(address);
+            }
+            Instruction::Resume => self.u8(47),
+            Instruction::ResumeNext => self.u8(48),
+            Instruction::ResumeLabel(address) => {
+                self.u8(49);
+                self.address(address);
+            }
+            Instruction::BuiltInSub(built_in_sub) => {
+                self.u8(50);
+                self.built_in_sub(*built_in_sub);
+            }
+            Instruction::BuiltInFunction(built_in_function) => {
+                self.u8(51);
+                self.short_str(built_in_function.as_str());
+            }
+            Instruction::CallNative(name) => {
+                self.u8(91);
+                self.case_insensitive_string(name);
+            }
+            Instruction::Halt => self.u8(52),
+            Instruction::Stop => self.u8(53),
+            Instruction::PushRegisters => self.u8(54),
+            Instruction::PopRegisters => self.u8(55),
+            Instruction::PushAToValueStack => self.u8(56),
+            Instruction::PopValueStackIntoA => self.u8(57),
+            Instruction::PushRet(address) => {
+                self.u8(58);
+                self.usize(*address);
+            }
+            Instruction::PopRet => self.u8(59),
+            Instruction::BeginCollectArguments => self.u8(60),
+            Instruction::PushNamed(param_name) => {
+                self.u8(61);
+                self.name(param_name);
+            }
+            Instruction::PushUnnamedByVal => self.u8(62),
+            Instruction::PushUnnamedByRef => self.u8(63),
+            Instruction::PushStack => self.u8(64),
+            Instruction::PushStaticStack(scope_name) => {
+                self.u8(65);
+                self.scope_name(scope_name);
+            }
+            Instruction::PopStack => self.u8(66),
+            Instruction::EnqueueToReturnStack(index) => {
+                self.u8(67);
+                self.usize(*index);
+            }
+            Instruction::DequeueFromReturnStack => self.u8(68),
+            Instruction::StashFunctionReturnValue(slot) => {
+                self.u8(69);
+                self.usize(*slot);
+            }
+            Instruction::UnStashFunctionReturnValue => self.u8(70),
+            Instruction::Throw(err) => {
+                self.u8(71);
+                self.runtime_error(err);
+            }
+            Instruction::OnErrorGoTo(address) => {
+                self.u8(72);
+                self.address(address);
+            }
+            Instruction::OnErrorResumeNext => self.u8(73),
+            Instruction::OnErrorGoToZero => self.u8(74),
+            Instruction::OnEventGoSub(event_kind, address) => {
+                self.u8(75);
+                self.event_kind(*event_kind);
+                self.address(address);
+            }
+            Instruction::EventControl(event_kind, event_action) => {
+                self.u8(76);
+                self.event_kind(*event_kind);
+                self.event_action(*event_action);
+            }
+            Instruction::Cast(q) => self.typed(77, *q),
+            Instruction::FixLength(len) => {
+                self.u8(78);
+                self.u16(*len);
+            }
+            Instruction::AllocateBuiltIn(q) => self.typed(79, *q),
+            Instruction::AllocateFixedLengthString(len) => {
+                self.u8(80);
+                self.u16(*len);
+            }
+            Instruction::AllocateArrayIntoA(expression_type) => {
+                self.u8(81);
+                self.expression_type(expression_type);
+            }
+            Instruction::AllocateUserDefined(name) => {
+                self.u8(82);
+                self.case_insensitive_string(name);
+            }
+            Instruction::PrintSetPrinterType(printer_type) => {
+                self.u8(83);
+                self.printer_type(*printer_type);
+            }
+            Instruction::PrintSetFileHandle(file_handle) => {
+                self.u8(84);
+                self.u8(i32::from(*file_handle) as u8);
+            }
+            Instruction::PrintSetFormatStringFromA => self.u8(85),
+            Instruction::PrintComma => self.u8(86),
+            Instruction::PrintSemicolon => self.u8(87),
+            Instruction::PrintValueFromA => self.u8(88),
+            Instruction::PrintEnd => self.u8(89),
+            Instruction::IsVariableDefined(slot) => {
+                self.u8(90);
+                self.usize(*slot);
+            }
+        }
+    }
+
+    fn typed(&mut self, tag: u8, q: TypeQualifier) {
+        self.u8(tag);
+        self.type_qualifier(q);
+    }
+
+    fn user_defined_type(&mut self, user_defined_type: &UserDefinedType) {
+        self.case_insensitive_string(user_defined_type.bare_name());
+        self.usize(user_defined_type.elements().len());
+        for Positioned { element, pos } in user_defined_type.elements() {
+            self.case_insensitive_string(&element.name);
+            self.pos(*pos);
+            match &element.element_type {
+                ElementType::Integer => self.u8(0),
+                ElementType::Long => self.u8(1),
+                ElementType::Single => self.u8(2),
+                ElementType::Double => self.u8(3),
+                ElementType::FixedLengthString(_, len) => {
+                    self.u8(4);
+                    self.u16(*len);
+                }
+                ElementType::UserDefined(bare_name_pos) => {
+                    self.u8(5);
+                    self.bare_name_pos(bare_name_pos);
+                }
+            }
+        }
+    }
+
+    pub fn program(&mut self, program: &CompiledProgram) {
+        self.str(&program.file_name);
+        self.instruction_generator_result(&program.instruction_generator_result);
+        // sorted, so that compiling the same program gives the same bytes
+        let mut user_defined_types: Vec<&UserDefinedType> =
+            program.user_defined_types.values().collect();
+        user_defined_types.sort_by_key(|u| u.bare_name().to_ascii_uppercase());
+        self.usize(user_defined_types.len());
+        for user_defined_type in user_defined_types {
+            self.user_defined_type(user_defined_type);
+        }
+    }
+
+    pub fn instruction_generator_result(
+        &mut self,
+        instruction_generator_result: &InstructionGeneratorResult,
+    ) {
+        let InstructionGeneratorResult {
+            instructions,
+            statement_addresses,
+            global_names,
+            max_local_slots,
+        } = instruction_generator_result;
+        self.usize(instructions.len());
+        for instruction_pos in instructions {
+            self.pos(instruction_pos.pos);
+            self.instruction(&instruction_pos.element);
+        }
+        self.usize(statement_addresses.len());
+        for address in statement_addresses {
+            self.usize(*address);
+        }
+        self.usize(global_names.len());
+        for name in global_names {
+            self.name(name);
+        }
+        self.usize(*max_local_slots);
+    }
+}
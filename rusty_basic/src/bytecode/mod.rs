@@ -0,0 +1,579 @@
+//! A compact binary format for precompiled programs.
+//!
+//! A precompiled program holds everything the interpreter needs in order to
+//! run a program without parsing and linting it again: the instructions with
+//! their source positions, the statement addresses, the user defined types
+//! and the name of the source file (to report runtime errors).
+//!
+//! The file starts with a header:
+//!
+//! | bytes | content                                                      |
+//! |-------|--------------------------------------------------------------|
+//! | 4     | the magic bytes `RBBC`                                       |
+//! | 2     | the version of the format                                    |
+//! | 1 + n | the version of the interpreter that compiled it              |
+//! | 8     | a checksum of the payload                                    |
+//!
+//! The payload follows. All numbers are little endian. The interpreter only
+//! runs files that were compiled by the same version, because the
+//! instructions are not part of a stable public interface.
+
+pub(crate) mod decoder;
+pub(crate) mod encoder;
+
+use std::fmt::Display;
+
+use rusty_parser::UserDefinedTypes;
+
+use crate::bytecode::decoder::Decoder;
+use crate::bytecode::encoder::Encoder;
+use crate::instruction_generator::{Instruction, InstructionGeneratorResult, RootPath};
+
+/// The first bytes of a precompiled program.
+pub const MAGIC: &[u8; 4] = b"RBBC";
+
+/// The version of the binary format.
+/// It needs to be increased whenever the encoding changes.
+const FORMAT_VERSION: u16 = 4;
+
+/// The version of the interpreter.
+pub(crate) const INTERPRETER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// A program that has been parsed, linted and compiled into instructions.
+pub struct CompiledProgram {
+    /// The name of the source file, used to report runtime errors.
+    pub file_name: String,
+    pub instruction_generator_result: InstructionGeneratorResult,
+    pub user_defined_types: UserDefinedTypes,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum BytecodeError {
+    /// The file does not start with the magic bytes.
+    NotCompiled,
+
+    /// The file was written with a different version of the format.
+    FormatVersionMismatch(u16),
+
+    /// The file was compiled by a different version of the interpreter.
+    InterpreterVersionMismatch(String),
+
+    /// The file is truncated or its contents are invalid.
+    Corrupted(String),
+}
+
+impl Display for BytecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotCompiled => f.write_str("Not a precompiled program"),
+            Self::FormatVersionMismatch(version) => write!(
+                f,
+                "Precompiled program has format version {}, expected {}. Please compile it again",
+                version, FORMAT_VERSION
+            ),
+            Self::InterpreterVersionMismatch(version) => write!(
+                f,
+                "Precompiled program was compiled by version {}, this is version {}. Please compile it again",
+                version, INTERPRETER_VERSION
+            ),
+            Self::Corrupted(msg) => write!(f, "Precompiled program is corrupted: {}", msg),
+        }
+    }
+}
+
+impl CompiledProgram {
+    /// Checks if the given bytes look like a precompiled program.
+    pub fn is_compiled(bytes: &[u8]) -> bool {
+        bytes.starts_with(MAGIC)
+    }
+
+    /// Encodes this program into the binary format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut payload = Encoder::new();
+        payload.program(self);
+        let payload = payload.into_bytes();
+        let mut header = Encoder::new();
+        header.raw(MAGIC);
+        header.u16(FORMAT_VERSION);
+        header.short_str(INTERPRETER_VERSION);
+        header.u64(checksum(&payload));
+        let mut bytes = header.into_bytes();
+        bytes.extend(payload);
+        bytes
+    }
+
+    /// Decodes a program from the binary format,
+    /// validating its header and its contents.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BytecodeError> {
+        if !Self::is_compiled(bytes) {
+            return Err(BytecodeError::NotCompiled);
+        }
+        let mut header = Decoder::new(&bytes[MAGIC.len()..]);
+        let format_version = header.u16()?;
+        if format_version != FORMAT_VERSION {
+            return Err(BytecodeError::FormatVersionMismatch(format_version));
+        }
+        let interpreter_version = header.short_str()?;
+        if interpreter_version != INTERPRETER_VERSION {
+            return Err(BytecodeError::InterpreterVersionMismatch(
+                interpreter_version,
+            ));
+        }
+        let expected_checksum = header.u64()?;
+        let payload = header.remaining();
+        if checksum(payload) != expected_checksum {
+            return Err(BytecodeError::Corrupted("checksum mismatch".to_owned()));
+        }
+        let mut decoder = Decoder::new(payload);
+        let program = decoder.program()?;
+        decoder.finish()?;
+        program.validate()?;
+        Ok(program)
+    }
+
+    /// Checks that the addresses point inside the instructions and that the
+    /// slots of the variables fit in their frames,
+    /// so that a file that was tampered with can't crash the interpreter.
+    fn validate(&self) -> Result<(), BytecodeError> {
+        let InstructionGeneratorResult {
+            instructions,
+            statement_addresses,
+            global_names,
+            max_local_slots,
+        } = &self.instruction_generator_result;
+        let len = instructions.len();
+        if len == 0 {
+            return Err(BytecodeError::Corrupted("no instructions".to_owned()));
+        }
+        for (index, instruction_pos) in instructions.iter().enumerate() {
+            match address_of(&instruction_pos.element) {
+                Some(address) if address >= len => {
+                    return Err(BytecodeError::Corrupted(format!(
+                        "instruction {} refers to address {}",
+                        index, address
+                    )));
+                }
+                _ => {}
+            }
+            if let Some(RootPath { slot, shared }) = root_path_of(&instruction_pos.element) {
+                // a non-shared variable belongs either to the module-level
+                // code or to a SUB/FUNCTION, the instruction doesn't say which
+                let frame_len = if shared {
+                    global_names.len()
+                } else {
+                    global_names.len().max(*max_local_slots)
+                };
+                if slot >= frame_len {
+                    return Err(BytecodeError::Corrupted(format!(
+                        "instruction {} refers to slot {}",
+                        index, slot
+                    )));
+                }
+            }
+        }
+        if statement_addresses.iter().any(|address| *address > len) {
+            return Err(BytecodeError::Corrupted(
+                "invalid statement address".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Gets the address that the given instruction jumps to, if any.
+fn address_of(instruction: &Instruction) -> Option<usize> {
+    match instruction {
+        Instruction::Jump(address)
+        | Instruction::JumpIfFalse(address)
+        | Instruction::GoSub(address)
+        | Instruction::Return(Some(address))
+        | Instruction::ResumeLabel(address)
+        | Instruction::OnErrorGoTo(address)
+        | Instruction::OnEventGoSub(_, address) => Some(address.address()),
+        Instruction::PushRet(address) => Some(*address),
+        _ => None,
+    }
+}
+
+/// Gets the variable that the given instruction refers to, if any.
+fn root_path_of(instruction: &Instruction) -> Option<RootPath> {
+    match instruction {
+        Instruction::VarPathName(root_path, _)
+        | Instruction::CopyVariableToA(root_path, _)
+        | Instruction::CopyVariableToB(root_path, _)
+        | Instruction::CopyAToVariable(root_path, _) => Some(*root_path),
+        Instruction::IsVariableDefined(slot) => Some(RootPath {
+            slot: *slot,
+            shared: false,
+        }),
+        _ => None,
+    }
+}
+
+/// Identifies the instructions of a program, e.g. in order to check that a
+/// snapshot of the interpreter belongs to the program that is restoring it.
+pub(crate) fn fingerprint(instruction_generator_result: &InstructionGeneratorResult) -> u64 {
+    let mut encoder = Encoder::new();
+    encoder.instruction_generator_result(instruction_generator_result);
+    checksum(&encoder.into_bytes())
+}
+
+/// The 64-bit FNV-1a hash of the given bytes.
+pub(crate) fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use rusty_common::AtPos;
+    use rusty_linter::core::ScopeName;
+    use rusty_parser::{
+        BuiltInFunction, BuiltInSub, EventAction, EventKind, ExpressionType, FileHandle,
+        HasExpressionType, Name, TypeQualifier, UserDefinedType,
+    };
+    use rusty_variant::Variant;
+
+    use super::*;
+    use crate::RuntimeError;
+    use crate::instruction_generator::test_utils::generate_instructions_str_with_types;
+    use crate::instruction_generator::{AddressOrLabel, PrinterType, RootPath};
+    use crate::interpreter::InterpreterTrait;
+    use crate::interpreter::test_utils::mock_interpreter_for_user_defined_types;
+
+    const PROGRAM: &str = r#"
+    TYPE Card
+        Suit AS STRING * 9
+        Value AS INTEGER
+    END TYPE
+    DIM c AS Card
+    c.Suit = "Hearts"
+    c.Value = 7
+    ON ERROR GOTO ErrTrap
+    PRINT c.Suit; c.Value
+    PRINT Twice(21)
+    GOSUB Greet
+    PRINT 1 / 0
+    END
+
+    Greet:
+        PRINT "hello"
+        RETURN
+
+    ErrTrap:
+        PRINT "oops"
+        RESUME NEXT
+
+    FUNCTION Twice(X%)
+        Twice = X% * 2
+    END FUNCTION
+    "#;
+
+    fn compile(input: &str) -> CompiledProgram {
+        let (instruction_generator_result, user_defined_types) =
+            generate_instructions_str_with_types(input);
+        CompiledProgram {
+            file_name: "PROG.BAS".to_owned(),
+            instruction_generator_result,
+            user_defined_types,
+        }
+    }
+
+    fn run(program: CompiledProgram) -> Vec<String> {
+        let mut interpreter = mock_interpreter_for_user_defined_types(program.user_defined_types);
+        interpreter
+            .interpret(program.instruction_generator_result)
+            .unwrap();
+        interpreter.stdout().output_lines()
+    }
+
+    #[test]
+    fn test_round_trip_runs_the_same() {
+        let bytes = compile(PROGRAM).to_bytes();
+        let program = CompiledProgram::from_bytes(&bytes).unwrap();
+        assert_eq!(program.file_name, "PROG.BAS");
+        assert_eq!(run(program), vec!["Hearts    7", "42", "hello", "oops"]);
+    }
+
+    #[test]
+    fn test_compiling_is_deterministic() {
+        assert_eq!(compile(PROGRAM).to_bytes(), compile(PROGRAM).to_bytes());
+    }
+
+    #[test]
+    fn test_round_trip_every_instruction() {
+        let q = TypeQualifier::PercentInteger;
+        let root_path = RootPath {
+            slot: 3,
+            shared: true,
+        };
+        let address = || AddressOrLabel::Resolved(0);
+        let instructions = vec![
+            Instruction::VarPathName(root_path, "c".into()),
+            Instruction::VarPathIndex,
+            Instruction::VarPathProperty("Suit".into()),
+            Instruction::CopyAToVarPath,
+            Instruction::CopyVarPathToA,
+            Instruction::PopVarPath,
+            Instruction::CopyVariableToA(root_path, "A$".into()),
+            Instruction::CopyVariableToB(root_path, "A$".into()),
+            Instruction::CopyAToVariable(root_path, "A$".into()),
+            Instruction::LoadIntoA(Variant::VSingle(1.5)),
+            Instruction::LoadIntoA(Variant::VDouble(-2.25)),
+            Instruction::LoadIntoA(Variant::VString("hi".to_owned())),
+            Instruction::LoadIntoA(Variant::VInteger(-32768)),
+            Instruction::LoadIntoB(Variant::VLong(-2147483648)),
+            Instruction::CopyAToB,
+            Instruction::CopyAToC,
+            Instruction::CopyAToD,
+            Instruction::CopyCToB,
+            Instruction::CopyDToA,
+            Instruction::CopyDToB,
+            Instruction::Plus,
+            Instruction::Minus,
+            Instruction::Multiply,
+            Instruction::Divide,
+            Instruction::Modulo,
+            Instruction::Less,
+            Instruction::LessOrEqual,
+            Instruction::Equal,
+            Instruction::GreaterOrEqual,
+            Instruction::Greater,
+            Instruction::NotEqual,
+            Instruction::NegateA,
+            Instruction::NotA,
+            Instruction::And,
+            Instruction::Or,
+            Instruction::PlusTyped(q),
+            Instruction::MinusTyped(q),
+            Instruction::MultiplyTyped(q),
+            Instruction::LessTyped(q),
+            Instruction::LessOrEqualTyped(q),
+            Instruction::EqualTyped(q),
+            Instruction::GreaterOrEqualTyped(q),
+            Instruction::GreaterTyped(q),
+            Instruction::NotEqualTyped(q),
+            Instruction::Label("Start".into()),
+            Instruction::Jump(address()),
+            Instruction::JumpIfFalse(address()),
+            Instruction::GoSub(address()),
+            Instruction::Return(None),
+            Instruction::Return(Some(address())),
+            Instruction::Resume,
+            Instruction::ResumeNext,
+            Instruction::ResumeLabel(address()),
+            Instruction::BuiltInSub(BuiltInSub::Width),
+            Instruction::BuiltInFunction(BuiltInFunction::InStr),
+            Instruction::CallNative("Log".into()),
+            Instruction::Halt,
+            Instruction::Stop,
+            Instruction::PushRegisters,
+            Instruction::PopRegisters,
+            Instruction::PushAToValueStack,
+            Instruction::PopValueStackIntoA,
+            Instruction::PushRet(0),
+            Instruction::PopRet,
+            Instruction::BeginCollectArguments,
+            Instruction::PushNamed(Rc::new(Name::qualified("A".into(), q))),
+            Instruction::PushNamed(Rc::new("C".into())),
+            Instruction::PushUnnamedByVal,
+            Instruction::PushUnnamedByRef,
+            Instruction::PushStack,
+            Instruction::PushStaticStack(ScopeName::Function("Twice%".into())),
+            Instruction::PushStaticStack(ScopeName::Sub("Greet".into())),
+            Instruction::PopStack,
+            Instruction::EnqueueToReturnStack(2),
+            Instruction::DequeueFromReturnStack,
+            Instruction::StashFunctionReturnValue(1),
+            Instruction::UnStashFunctionReturnValue,
+            Instruction::Throw(RuntimeError::ForLoopZeroStep),
+            Instruction::Throw(RuntimeError::ErrorCode(-5)),
+            Instruction::OnErrorGoTo(address()),
+            Instruction::OnErrorResumeNext,
+            Instruction::OnErrorGoToZero,
+            Instruction::OnEventGoSub(EventKind::Timer, address()),
+            Instruction::EventControl(EventKind::Key, EventAction::Stop),
+            Instruction::Cast(TypeQualifier::HashDouble),
+            Instruction::FixLength(10),
+            Instruction::AllocateBuiltIn(TypeQualifier::DollarString),
+            Instruction::AllocateFixedLengthString(5),
+            Instruction::AllocateArrayIntoA(ExpressionType::Array(Box::new(
+                ExpressionType::UserDefined("Card".into()),
+            ))),
+            Instruction::AllocateUserDefined("Card".into()),
+            Instruction::PrintSetPrinterType(PrinterType::LPrint),
+            Instruction::PrintSetFileHandle(FileHandle::from(7)),
+            Instruction::PrintSetFormatStringFromA,
+            Instruction::PrintComma,
+            Instruction::PrintSemicolon,
+            Instruction::PrintValueFromA,
+            Instruction::PrintEnd,
+            Instruction::IsVariableDefined(4),
+        ];
+        let len = instructions.len();
+        let program = CompiledProgram {
+            file_name: "ALL.BAS".to_owned(),
+            instruction_generator_result: InstructionGeneratorResult {
+                instructions: instructions
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, instruction)| instruction.at_rc(index as u32 + 1, 1))
+                    .collect(),
+                statement_addresses: vec![0, len],
+                global_names: vec!["A$".into(), "c".into(), "D".into(), "E%".into()],
+                max_local_slots: 5,
+            },
+            user_defined_types: UserDefinedTypes::default(),
+        };
+        let decoded = CompiledProgram::from_bytes(&program.to_bytes()).unwrap();
+        assert_eq!(
+            decoded.instruction_generator_result.instructions,
+            program.instruction_generator_result.instructions
+        );
+        assert_eq!(
+            decoded.instruction_generator_result.statement_addresses,
+            vec![0, len]
+        );
+        assert_eq!(
+            decoded.instruction_generator_result.global_names,
+            program.instruction_generator_result.global_names
+        );
+        assert_eq!(decoded.instruction_generator_result.max_local_slots, 5);
+    }
+
+    #[test]
+    fn test_round_trip_user_defined_types() {
+        let program = compile(PROGRAM);
+        let decoded = CompiledProgram::from_bytes(&program.to_bytes()).unwrap();
+        let card = &decoded.user_defined_types[&"Card".into()];
+        let expected = &program.user_defined_types[&"Card".into()];
+        let element_types = |u: &UserDefinedType| -> Vec<(String, ExpressionType)> {
+            u.elements()
+                .map(|e| {
+                    (
+                        e.element.name.to_string(),
+                        e.element.element_type.expression_type(),
+                    )
+                })
+                .collect()
+        };
+        assert_eq!(element_types(card), element_types(expected));
+    }
+
+    #[test]
+    fn test_not_compiled() {
+        assert_eq!(
+            CompiledProgram::from_bytes(b"PRINT 1").err(),
+            Some(BytecodeError::NotCompiled)
+        );
+    }
+
+    #[test]
+    fn test_format_version_mismatch() {
+        let mut bytes = compile(PROGRAM).to_bytes();
+        bytes[MAGIC.len()] = 99;
+        assert_eq!(
+            CompiledProgram::from_bytes(&bytes).err(),
+            Some(BytecodeError::FormatVersionMismatch(99))
+        );
+    }
+
+    #[test]
+    fn test_interpreter_version_mismatch() {
+        let mut bytes = compile(PROGRAM).to_bytes();
+        // the first digit of the version
+        bytes[MAGIC.len() + 3] = b'9';
+        assert!(matches!(
+            CompiledProgram::from_bytes(&bytes),
+            Err(BytecodeError::InterpreterVersionMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_corrupted_payload() {
+        let mut bytes = compile(PROGRAM).to_bytes();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        assert_eq!(
+            CompiledProgram::from_bytes(&bytes).err(),
+            Some(BytecodeError::Corrupted("checksum mismatch".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_truncated() {
+        let bytes = compile(PROGRAM).to_bytes();
+        for len in [MAGIC.len() + 1, MAGIC.len() + 4, bytes.len() / 2] {
+            assert!(matches!(
+                CompiledProgram::from_bytes(&bytes[..len]),
+                Err(BytecodeError::Corrupted(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_address_out_of_range() {
+        let program = CompiledProgram {
+            file_name: "PROG.BAS".to_owned(),
+            instruction_generator_result: InstructionGeneratorResult {
+                instructions: vec![
+                    Instruction::Jump(AddressOrLabel::Resolved(2)).at_rc(1, 1),
+                    Instruction::Halt.at_rc(1, 1),
+                ],
+                statement_addresses: vec![0],
+                global_names: vec![],
+                max_local_slots: 0,
+            },
+            user_defined_types: UserDefinedTypes::default(),
+        };
+        assert_eq!(
+            CompiledProgram::from_bytes(&program.to_bytes()).err(),
+            Some(BytecodeError::Corrupted(
+                "instruction 0 refers to address 2".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_slot_out_of_range() {
+        let program = |root_path| CompiledProgram {
+            file_name: "PROG.BAS".to_owned(),
+            instruction_generator_result: InstructionGeneratorResult {
+                instructions: vec![
+                    Instruction::CopyVariableToA(root_path, "A".into()).at_rc(1, 1),
+                    Instruction::Halt.at_rc(1, 1),
+                ],
+                statement_addresses: vec![0],
+                global_names: vec!["A".into(), "B".into()],
+                max_local_slots: 3,
+            },
+            user_defined_types: UserDefinedTypes::default(),
+        };
+        let error = Some(BytecodeError::Corrupted(
+            "instruction 0 refers to slot 2".to_owned(),
+        ));
+        let shared = program(RootPath {
+            slot: 2,
+            shared: true,
+        });
+        assert_eq!(CompiledProgram::from_bytes(&shared.to_bytes()).err(), error);
+        let local = program(RootPath {
+            slot: 2,
+            shared: false,
+        });
+        assert!(CompiledProgram::from_bytes(&local.to_bytes()).is_ok());
+        let local = program(RootPath {
+            slot: 3,
+            shared: false,
+        });
+        assert_eq!(
+            CompiledProgram::from_bytes(&local.to_bytes()).err(),
+            Some(BytecodeError::Corrupted(
+                "instruction 0 refers to slot 3".to_owned()
+            ))
+        );
+    }
+}
@@ -0,0 +1,224 @@
+//! Renders parse, lint and runtime errors in a human readable format,
+//! showing the offending source line and the call stack.
+
+use std::fmt::{Display, Write};
+
+use rusty_common::Position;
+use rusty_linter::core::LintErrorPos;
+use rusty_parser::{GlobalStatement, ParseErrorPos, Program};
+
+use crate::RuntimeErrorPos;
+use crate::transpiler::TranspileErrorPos;
+
+/// The name of the call stack frame that is outside any SUB or FUNCTION.
+const MODULE_LEVEL: &str = "module-level code";
+
+/// Renders errors of a program.
+pub struct Diagnostics {
+    file_name: String,
+    lines: Vec<String>,
+    /// The SUBs and FUNCTIONs of the program, sorted by their starting row.
+    subprograms: Vec<SubprogramRange>,
+}
+
+/// The rows occupied by a SUB or a FUNCTION.
+struct SubprogramRange {
+    name: String,
+    start_row: u32,
+    /// The row where the next global statement starts (exclusive).
+    end_row: Option<u32>,
+}
+
+impl Diagnostics {
+    pub fn new(file_name: &str, source: &str) -> Self {
+        Self {
+            file_name: file_name.to_owned(),
+            lines: source.lines().map(str::to_owned).collect(),
+            subprograms: vec![],
+        }
+    }
+
+    /// Collects the SUBs and FUNCTIONs of the parsed program,
+    /// in order to show their names in the call stack of runtime errors.
+    pub fn register_subprograms(&mut self, program: &Program) {
+        self.subprograms.clear();
+        for (index, global_statement_pos) in program.iter().enumerate() {
+            let name = match &global_statement_pos.element {
+                GlobalStatement::FunctionImplementation(f) => f.name.element.to_string(),
+                GlobalStatement::SubImplementation(s) => s.name.element.to_string(),
+                _ => continue,
+            };
+            self.subprograms.push(SubprogramRange {
+                name,
+                start_row: global_statement_pos.pos.row(),
+                end_row: program.get(index + 1).map(|next| next.pos.row()),
+            });
+        }
+    }
+
+    pub fn render_parse_error(&self, e: &ParseErrorPos) -> String {
+        self.render(&e.element, &[e.pos])
+    }
+
+    pub fn render_lint_error(&self, e: &LintErrorPos) -> String {
+        self.render(&e.element, &[e.pos])
+    }
+
+    pub fn render_runtime_error(&self, e: &RuntimeErrorPos) -> String {
+        self.render(e.err(), e.stacktrace())
+    }
+
+    pub fn render_transpile_error(&self, e: &TranspileErrorPos) -> String {
+        self.render(&e.element, &[e.pos])
+    }
+
+    fn render(&self, message: &impl Display, stacktrace: &[Position]) -> String {
+        let mut buf = String::new();
+        writeln!(buf, "error: {}", message).unwrap();
+        if let Some(pos) = stacktrace.first() {
+            self.render_snippet(&mut buf, *pos);
+        }
+        if stacktrace.len() > 1 {
+            writeln!(buf, "call stack:").unwrap();
+            for pos in stacktrace {
+                writeln!(
+                    buf,
+                    "    at {} ({})",
+                    self.subprogram_name(*pos),
+                    self.location(*pos)
+                )
+                .unwrap();
+            }
+        }
+        buf
+    }
+
+    /// Gets the source line of the given row (1 based).
+    pub fn line(&self, row: u32) -> Option<&str> {
+        row.checked_sub(1)
+            .and_then(|index| self.lines.get(index as usize))
+            .map(String::as_str)
+    }
+
+    fn render_snippet(&self, buf: &mut String, pos: Position) {
+        let row_label = pos.row().to_string();
+        let padding = " ".repeat(row_label.len());
+        writeln!(buf, "{}--> {}", padding, self.location(pos)).unwrap();
+        let Some(line) = self.line(pos.row()) else {
+            // e.g. unexpected end of file
+            return;
+        };
+        writeln!(buf, "{} |", padding).unwrap();
+        writeln!(buf, "{} | {}", row_label, line).unwrap();
+        // keep tabs, so that the caret lines up with the source line
+        let indentation: String = line
+            .chars()
+            .chain(std::iter::repeat(' '))
+            .take(pos.col().saturating_sub(1) as usize)
+            .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+            .collect();
+        writeln!(buf, "{} | {}^", padding, indentation).unwrap();
+    }
+
+    /// Formats the given position as `file:row:col`.
+    pub fn location(&self, pos: Position) -> String {
+        format!("{}:{}:{}", self.file_name, pos.row(), pos.col())
+    }
+
+    /// Gets the name of the SUB or FUNCTION that contains the given position.
+    /// Requires [Self::register_subprograms].
+    pub fn subprogram_name(&self, pos: Position) -> &str {
+        let row = pos.row();
+        self.subprograms
+            .iter()
+            .find(|s| s.start_row <= row && s.end_row.is_none_or(|end_row| row < end_row))
+            .map_or(MODULE_LEVEL, |s| s.name.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusty_linter::core::lint;
+    use rusty_parser::{parse, parse_main_str};
+
+    use super::*;
+    use crate::interpreter::test_utils::interpret_err;
+
+    #[test]
+    fn test_render_parse_error() {
+        let input = "PRINT 1\nIF X THEN\n";
+        let err = parse_main_str(input.to_owned()).unwrap_err();
+        let diagnostics = Diagnostics::new("PROG.BAS", input);
+        assert_eq!(
+            diagnostics.render_parse_error(&err),
+            "error: Syntax error. Expected: END or ELSE or ELSEIF
+ --> PROG.BAS:2:11
+  |
+2 | IF X THEN
+  |           ^
+"
+        );
+    }
+
+    #[test]
+    fn test_render_lint_error() {
+        let input = "PRINT 1\nA = \"hello\" + 1\n";
+        let err = lint(parse(input)).err().expect("Should have lint error");
+        let diagnostics = Diagnostics::new("PROG.BAS", input);
+        assert_eq!(
+            diagnostics.render_lint_error(&err),
+            "error: Type mismatch
+ --> PROG.BAS:2:15
+  |
+2 | A = \"hello\" + 1
+  |               ^
+"
+        );
+    }
+
+    #[test]
+    fn test_render_runtime_error_with_call_stack() {
+        let input = "DIM SHARED A(1 TO 2)
+Hello 1
+Hello 3
+
+SUB Hello(N)
+\tA(N) = 1
+END SUB
+
+FUNCTION Other
+END FUNCTION
+";
+        let err = interpret_err(input);
+        let mut diagnostics = Diagnostics::new("PROG.BAS", input);
+        diagnostics.register_subprograms(&parse(input));
+        assert_eq!(
+            diagnostics.render_runtime_error(&err),
+            "error: Subscript out of range
+ --> PROG.BAS:6:2
+  |
+6 | \tA(N) = 1
+  | \t^
+call stack:
+    at Hello (PROG.BAS:6:2)
+    at module-level code (PROG.BAS:3:1)
+"
+        );
+    }
+
+    #[test]
+    fn test_render_runtime_error_at_module_level() {
+        let input = "X = 1 / 0";
+        let err = interpret_err(input);
+        let diagnostics = Diagnostics::new("PROG.BAS", input);
+        assert_eq!(
+            diagnostics.render_runtime_error(&err),
+            "error: Division by zero
+ --> PROG.BAS:1:7
+  |
+1 | X = 1 / 0
+  |       ^
+"
+        );
+    }
+}
@@ -0,0 +1,506 @@
+//! An API to embed the interpreter in an application, e.g. as a scripting engine.
+//!
+//! The application builds an [Engine] with the SUBs and FUNCTIONs that it
+//! implements natively and with its own standard input and output.
+//! Programs call the native subprograms like their own (the linter validates
+//! the calls against the given signatures) and the application can read and
+//! write the global variables of the programs before and after running them.
+//!
+//! ```
+//! use rusty_basic::engine::Engine;
+//! use rusty_parser::TypeQualifier;
+//! use rusty_runtime::WritePrinter;
+//! use rusty_variant::Variant;
+//!
+//! let mut engine = Engine::builder()
+//!     .stdout(WritePrinter::new(Vec::<u8>::new()))
+//!     .function(
+//!         "Twice",
+//!         &[TypeQualifier::PercentInteger],
+//!         TypeQualifier::PercentInteger,
+//!         // the arguments have already been cast to the types of the parameters
+//!         |args| match args[0] {
+//!             Variant::VInteger(n) => Ok(Variant::VInteger(n * 2)),
+//!             _ => unreachable!(),
+//!         },
+//!     )
+//!     .build();
+//! engine.set_global("N%", 21).unwrap();
+//! engine.eval("Answer% = Twice(N%)").unwrap();
+//! assert_eq!(engine.global("Answer%"), Some(Variant::VInteger(42)));
+//! ```
+
+use std::fmt::Display;
+use std::io::{Stdin, Stdout};
+
+use rusty_common::{AtPos, Position};
+use rusty_linter::core::{LintErrorPos, lint_with_natives};
+use rusty_parser::{
+    DimVar, GlobalStatement, GlobalStatementPos, Name, ParseErrorPos, Program, Statement,
+    ToBareName, TypeQualifier, parse_main_str,
+};
+use rusty_runtime::{Input, Printer, ReadInputSource, WritePrinter, cast};
+use rusty_variant::Variant;
+
+use crate::bytecode::CompiledProgram;
+use crate::instruction_generator::{generate_instructions, unwrap_linter_context};
+use crate::interpreter::{
+    DefaultStdlib, ExecutionLimits, HeadlessScreen, Interpreter, InterpreterTrait, Keyboard,
+    LPT1_DEFAULT_WIDTH, Lpt1Write, NativeSubprograms, NoKeyboard, Screen, Stdlib,
+};
+use crate::{RuntimeError, RuntimeErrorPos};
+
+/// The name under which the programs of [Engine::eval] appear in error messages.
+const EVAL_FILE_NAME: &str = "eval";
+
+/// Builds an [Engine].
+///
+/// By default, the engine uses the standard input and output of the process,
+/// keeps the `LPRINT` output in memory, ignores screen commands (e.g. `CLS`)
+/// and never reports a key press.
+pub struct EngineBuilder<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1: Printer> {
+    stdlib: TStdlib,
+    stdin: TStdIn,
+    stdout: TStdOut,
+    lpt1: TLpt1,
+    screen: Box<dyn Screen>,
+    keyboard: Box<dyn Keyboard>,
+    natives: NativeSubprograms,
+    limits: ExecutionLimits,
+}
+
+pub type DefaultEngineBuilder = EngineBuilder<
+    DefaultStdlib,
+    ReadInputSource<Stdin>,
+    WritePrinter<Stdout>,
+    WritePrinter<Lpt1Write>,
+>;
+
+impl DefaultEngineBuilder {
+    pub fn new() -> Self {
+        Self {
+            stdlib: DefaultStdlib::default(),
+            stdin: ReadInputSource::new(std::io::stdin()),
+            stdout: WritePrinter::new(std::io::stdout()),
+            lpt1: WritePrinter::with_width(Lpt1Write::default(), LPT1_DEFAULT_WIDTH),
+            screen: Box::new(HeadlessScreen {}),
+            keyboard: Box::new(NoKeyboard),
+            natives: NativeSubprograms::default(),
+            limits: ExecutionLimits::default(),
+        }
+    }
+}
+
+impl Default for DefaultEngineBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1: Printer>
+    EngineBuilder<TStdlib, TStdIn, TStdOut, TLpt1>
+{
+    /// Uses the given system calls (e.g. environment variables, the clock).
+    pub fn stdlib<T: Stdlib>(self, stdlib: T) -> EngineBuilder<T, TStdIn, TStdOut, TLpt1> {
+        EngineBuilder {
+            stdlib,
+            stdin: self.stdin,
+            stdout: self.stdout,
+            lpt1: self.lpt1,
+            screen: self.screen,
+            keyboard: self.keyboard,
+            natives: self.natives,
+            limits: self.limits,
+        }
+    }
+
+    /// Uses the given standard input (e.g. for `INPUT`).
+    pub fn stdin<T: Input>(self, stdin: T) -> EngineBuilder<TStdlib, T, TStdOut, TLpt1> {
+        EngineBuilder {
+            stdlib: self.stdlib,
+            stdin,
+            stdout: self.stdout,
+            lpt1: self.lpt1,
+            screen: self.screen,
+            keyboard: self.keyboard,
+            natives: self.natives,
+            limits: self.limits,
+        }
+    }
+
+    /// Uses the given standard output (e.g. for `PRINT`).
+    pub fn stdout<T: Printer>(self, stdout: T) -> EngineBuilder<TStdlib, TStdIn, T, TLpt1> {
+        EngineBuilder {
+            stdlib: self.stdlib,
+            stdin: self.stdin,
+            stdout,
+            lpt1: self.lpt1,
+            screen: self.screen,
+            keyboard: self.keyboard,
+            natives: self.natives,
+            limits: self.limits,
+        }
+    }
+
+    /// Uses the given printer for `LPRINT`.
+    pub fn lpt1<T: Printer>(self, lpt1: T) -> EngineBuilder<TStdlib, TStdIn, TStdOut, T> {
+        EngineBuilder {
+            stdlib: self.stdlib,
+            stdin: self.stdin,
+            stdout: self.stdout,
+            lpt1,
+            screen: self.screen,
+            keyboard: self.keyboard,
+            natives: self.natives,
+            limits: self.limits,
+        }
+    }
+
+    /// Uses the given screen (e.g. for `CLS`, `LOCATE`, `COLOR`).
+    pub fn screen(mut self, screen: impl Screen + 'static) -> Self {
+        self.screen = Box::new(screen);
+        self
+    }
+
+    /// Uses the given keyboard (e.g. for `INKEY$`).
+    pub fn keyboard(mut self, keyboard: impl Keyboard + 'static) -> Self {
+        self.keyboard = Box::new(keyboard);
+        self
+    }
+
+    /// Enforces the given limits while a program runs.
+    pub fn limits(mut self, limits: ExecutionLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Adds a native FUNCTION with the given parameter types and return type.
+    ///
+    /// The arguments are cast to the types of the parameters before
+    /// the function is called and its result is cast to the return type.
+    /// An error of the function is raised as a runtime error of the program,
+    /// at the call site (so it can be handled with `ON ERROR`).
+    pub fn function(
+        mut self,
+        name: &str,
+        param_types: &[TypeQualifier],
+        return_type: TypeQualifier,
+        function: impl FnMut(&[Variant]) -> Result<Variant, RuntimeError> + 'static,
+    ) -> Self {
+        self.natives
+            .add_function(name, param_types, return_type, function);
+        self
+    }
+
+    /// Adds a native SUB with the given parameter types.
+    ///
+    /// The arguments are cast to the types of the parameters before
+    /// the sub is called. An error of the sub is raised as a runtime error
+    /// of the program, at the call site (so it can be handled with `ON ERROR`).
+    pub fn sub(
+        mut self,
+        name: &str,
+        param_types: &[TypeQualifier],
+        sub: impl FnMut(&[Variant]) -> Result<(), RuntimeError> + 'static,
+    ) -> Self {
+        self.natives.add_sub(name, param_types, sub);
+        self
+    }
+
+    pub fn build(self) -> Engine<TStdlib, TStdIn, TStdOut, TLpt1> {
+        let mut interpreter = Interpreter::with_devices(
+            self.stdlib,
+            self.stdin,
+            self.stdout,
+            self.lpt1,
+            self.screen,
+            self.keyboard,
+            Default::default(),
+        );
+        interpreter.set_limits(self.limits);
+        *interpreter.natives_mut() = self.natives;
+        Engine {
+            interpreter,
+            host_globals: vec![],
+        }
+    }
+}
+
+/// Compiles and runs programs that can call native SUBs and FUNCTIONs.
+///
+/// Global variables and open files survive a run,
+/// so the same engine can run more programs afterwards.
+pub struct Engine<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1: Printer> {
+    interpreter: Interpreter<TStdlib, TStdIn, TStdOut, TLpt1>,
+    /// The global variables that were set with [Engine::set_global].
+    /// The programs see them as declared, so that they keep their value.
+    host_globals: Vec<Name>,
+}
+
+impl Engine<DefaultStdlib, ReadInputSource<Stdin>, WritePrinter<Stdout>, WritePrinter<Lpt1Write>> {
+    pub fn builder() -> DefaultEngineBuilder {
+        DefaultEngineBuilder::new()
+    }
+}
+
+impl<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1: Printer>
+    Engine<TStdlib, TStdIn, TStdOut, TLpt1>
+{
+    /// Parses, lints and compiles the given program.
+    /// The file name is used in order to report errors.
+    pub fn compile(&self, file_name: &str, source: &str) -> Result<CompiledProgram, EngineError> {
+        let parsed = parse_main_str(source.to_owned()).map_err(EngineError::Parse)?;
+        // declare the variables of the host, otherwise they would be
+        // implicitly defined by the program, losing their value
+        let declarations: Program = self.host_globals.iter().map(declaration).collect();
+        let mut program = declarations.clone();
+        program.extend(parsed);
+        let natives = self.interpreter.natives().signatures();
+        let (linted_program, linter_context) =
+            lint_with_natives(program, &natives).map_err(EngineError::Lint)?;
+        let linted_program: Program = linted_program
+            .into_iter()
+            .filter(|s| !declarations.contains(s))
+            .collect();
+        let (linter_names, user_defined_types) = unwrap_linter_context(linter_context);
+        Ok(CompiledProgram {
+            file_name: file_name.to_owned(),
+            instruction_generator_result: generate_instructions(linted_program, linter_names),
+            user_defined_types,
+        })
+    }
+
+    /// Runs the given compiled program.
+    pub fn run(&mut self, program: CompiledProgram) -> Result<(), EngineError> {
+        self.interpreter
+            .set_user_defined_types(program.user_defined_types);
+        self.interpreter
+            .interpret(program.instruction_generator_result)
+            .map_err(EngineError::Runtime)
+    }
+
+    /// Compiles and runs the given program.
+    pub fn eval(&mut self, source: &str) -> Result<(), EngineError> {
+        let program = self.compile(EVAL_FILE_NAME, source)?;
+        self.run(program)
+    }
+
+    /// Gets the value of the given global variable, if it exists.
+    ///
+    /// The name should have a type qualifier (e.g. `A$`),
+    /// otherwise it is assumed to be a `SINGLE`, which is the default type.
+    pub fn global(&self, name: &str) -> Option<Variant> {
+        self.interpreter
+            .context()
+            .global_variables()
+            .get_by_name(&qualified_name(name))
+            .cloned()
+    }
+
+    /// Sets the value of the given global variable, so that the next program
+    /// can use it. The value is cast to the type of the variable.
+    ///
+    /// The name should have a type qualifier (e.g. `A$`),
+    /// otherwise it is assumed to be a `SINGLE`, which is the default type.
+    pub fn set_global(
+        &mut self,
+        name: &str,
+        value: impl Into<Variant>,
+    ) -> Result<(), RuntimeError> {
+        let name = qualified_name(name);
+        let q = name.qualifier().expect("Should be qualified");
+        let value = cast(value.into(), q)?;
+        if !self.host_globals.contains(&name) {
+            self.host_globals.push(name.clone());
+        }
+        self.interpreter
+            .context_mut()
+            .global_variables_mut()
+            .set_by_name(name, value);
+        Ok(())
+    }
+
+    /// Gets the interpreter, e.g. in order to access its standard output.
+    pub fn interpreter(
+        &mut self,
+    ) -> &mut impl InterpreterTrait<TStdlib = TStdlib, TStdIn = TStdIn, TStdOut = TStdOut, TLpt1 = TLpt1>
+    {
+        &mut self.interpreter
+    }
+}
+
+fn declaration(name: &Name) -> GlobalStatementPos {
+    GlobalStatement::Statement(Statement::Dim(
+        DimVar::from(name.clone()).into_list(Position::start()),
+    ))
+    .at_pos(Position::start())
+}
+
+fn qualified_name(name: &str) -> Name {
+    let name = Name::from(name);
+    match name.qualifier() {
+        Some(_) => name,
+        None => Name::qualified(name.to_bare_name(), TypeQualifier::BangSingle),
+    }
+}
+
+#[derive(Debug)]
+pub enum EngineError {
+    Parse(ParseErrorPos),
+    Lint(LintErrorPos),
+    Runtime(RuntimeErrorPos),
+}
+
+impl Display for EngineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Parse(e) => write!(f, "{}", e.element),
+            Self::Lint(e) => write!(f, "{}", e.element),
+            Self::Runtime(e) => write!(f, "{}", e.err()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use rusty_linter::core::LintError;
+
+    use super::*;
+
+    fn engine()
+    -> Engine<DefaultStdlib, ReadInputSource<Stdin>, WritePrinter<Vec<u8>>, WritePrinter<Lpt1Write>>
+    {
+        Engine::builder()
+            .stdout(WritePrinter::new(Vec::new()))
+            .function(
+                "Twice",
+                &[TypeQualifier::PercentInteger],
+                TypeQualifier::AmpersandLong,
+                |args| match args[0] {
+                    Variant::VInteger(n) => Ok(Variant::VLong(n as i64 * 2)),
+                    _ => unreachable!(),
+                },
+            )
+            .function("Fail", &[], TypeQualifier::PercentInteger, |_| {
+                Err(RuntimeError::IllegalFunctionCall)
+            })
+            .build()
+    }
+
+    fn output(
+        engine: &mut Engine<impl Stdlib, impl Input, WritePrinter<Vec<u8>>, impl Printer>,
+    ) -> String {
+        engine.interpreter().stdout().output_exact()
+    }
+
+    #[test]
+    fn calls_native_function() {
+        let mut engine = engine();
+        // the argument is cast to INTEGER
+        engine.eval("PRINT Twice(2.6)").unwrap();
+        assert_eq!(output(&mut engine), " 6 \r\n");
+    }
+
+    #[test]
+    fn calls_native_sub() {
+        let log: Rc<RefCell<Vec<String>>> = Rc::default();
+        let sink = log.clone();
+        let mut engine = Engine::builder()
+            .sub(
+                "Log",
+                &[TypeQualifier::DollarString, TypeQualifier::HashDouble],
+                move |args| {
+                    sink.borrow_mut().push(format!("{} {}", args[0], args[1]));
+                    Ok(())
+                },
+            )
+            .build();
+        engine
+            .eval(
+                r#"
+                Log "a", 1
+                Log "b", 2
+                "#,
+            )
+            .unwrap();
+        assert_eq!(*log.borrow(), vec!["a 1", "b 2"]);
+    }
+
+    #[test]
+    fn native_error_can_be_handled() {
+        let mut engine = engine();
+        engine
+            .eval(
+                r#"
+                ON ERROR GOTO ErrTrap
+                X = Fail
+                PRINT "after"
+                END
+                ErrTrap:
+                PRINT "error"; ERR
+                RESUME NEXT
+                "#,
+            )
+            .unwrap();
+        assert_eq!(output(&mut engine), "error 5 \r\nafter\r\n");
+    }
+
+    #[test]
+    fn native_error_is_reported_at_call_site() {
+        let mut engine = engine();
+        match engine.eval("PRINT 1\nPRINT Fail") {
+            Err(EngineError::Runtime(e)) => {
+                assert_eq!(*e.err(), RuntimeError::IllegalFunctionCall);
+                assert_eq!(e.stacktrace()[0], Position::new(2, 7));
+            }
+            _ => panic!("Expected runtime error"),
+        }
+    }
+
+    #[test]
+    fn validates_calls_to_natives() {
+        let engine = engine();
+        match engine.compile("test.bas", "X = Twice(\"a\")") {
+            Err(EngineError::Lint(e)) => assert_eq!(e.element, LintError::ArgumentTypeMismatch),
+            _ => panic!("Expected lint error"),
+        }
+    }
+
+    #[test]
+    fn reads_and_writes_globals() {
+        let mut engine = engine();
+        engine.set_global("N%", 20).unwrap();
+        engine.set_global("Greeting$", "hello").unwrap();
+        engine.set_global("X", 1.5).unwrap();
+        engine
+            .eval(
+                r#"
+                N% = N% + 1
+                Greeting$ = Greeting$ + " world"
+                Y = X * 2
+                "#,
+            )
+            .unwrap();
+        assert_eq!(engine.global("N%"), Some(Variant::VInteger(21)));
+        assert_eq!(
+            engine.global("Greeting$"),
+            Some(Variant::from("hello world"))
+        );
+        assert_eq!(engine.global("Y!"), Some(Variant::VSingle(3.0)));
+        assert_eq!(engine.global("Missing$"), None);
+    }
+
+    #[test]
+    fn set_global_casts_the_value() {
+        let mut engine = engine();
+        engine.set_global("N%", 2.6).unwrap();
+        assert_eq!(engine.global("N%"), Some(Variant::VInteger(3)));
+        assert_eq!(
+            engine.set_global("N%", "a"),
+            Err(RuntimeError::TypeMismatch)
+        );
+    }
+}
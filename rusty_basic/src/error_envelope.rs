@@ -28,6 +28,12 @@ impl<T> ErrorEnvelope<T> {
         &self.0
     }
 
+    /// Returns the positions of the error,
+    /// starting with the innermost one.
+    pub fn stacktrace(&self) -> &[Position] {
+        &self.1
+    }
+
     pub fn appen_draining_stacktrace(self, stacktrace: &mut Vec<Position>) -> Self {
         let Self(err, mut old_stacktrace) = self;
         old_stacktrace.append(stacktrace);
@@ -1,8 +1,12 @@
+use std::rc::Rc;
+
 use rusty_common::{AtPos, Position, Positioned};
 use rusty_linter::core::ScopeName;
 use rusty_parser::*;
 
-use crate::instruction_generator::{AddressOrLabel, Instruction, InstructionGenerator};
+use crate::instruction_generator::{
+    AddressOrLabel, Instruction, InstructionGenerator, parameter_name,
+};
 
 impl InstructionGenerator {
     pub fn generate_built_in_function_call_instructions(
@@ -15,7 +19,8 @@ impl InstructionGenerator {
         self.push(Instruction::PushStack, pos);
         self.push(Instruction::BuiltInFunction(function_name), pos);
         self.generate_stash_by_ref_args(&args);
-        self.generate_stash_function_return_value(function_name.into(), pos);
+        // the result of a built-in function is stored after its arguments
+        self.generate_stash_function_return_value(args.len(), pos);
         self.push(Instruction::PopStack, pos);
         self.generate_un_stash_by_ref_args(&args);
         self.generate_un_stash_function_return_value(pos);
@@ -43,6 +48,14 @@ impl InstructionGenerator {
         let Positioned { element: name, pos } = function_name;
         let qualified_name = name.demand_qualified();
         let scope_name = ScopeName::Function(qualified_name.clone());
+        if self.subprogram_info_repository.find(&scope_name).is_none() {
+            self.generate_native_call_instructions(qualified_name.to_bare_name(), &args, pos);
+            // the result of a native function is stored after its arguments
+            self.generate_stash_function_return_value(args.len(), pos);
+            self.push(Instruction::PopStack, pos);
+            self.generate_un_stash_function_return_value(pos);
+            return;
+        }
         // cloning to fight the borrow checker
         let function_parameters: Vec<Parameter> = self
             .subprogram_info_repository
@@ -57,8 +70,13 @@ impl InstructionGenerator {
         // TODO find different way for by ref args
         // stash by-ref variables
         self.generate_stash_by_ref_args(&args);
-        // stash function name
-        self.generate_stash_function_return_value(qualified_name, pos);
+        // stash function result, which is stored after the parameters
+        // (or in a parameter that has the same name as the function)
+        let result_slot = function_parameters
+            .iter()
+            .position(|p| parameter_name(p) == qualified_name)
+            .unwrap_or(function_parameters.len());
+        self.generate_stash_function_return_value(result_slot, pos);
         // switch to parent context
         self.push(Instruction::PopStack, pos);
         // un-stash by-ref variables
@@ -69,7 +87,12 @@ impl InstructionGenerator {
 
     pub fn generate_sub_call_instructions(&mut self, sub_call: SubCall, pos: Position) {
         let (name, args) = sub_call.into();
-        let scope_name = ScopeName::Sub(name);
+        let scope_name = ScopeName::Sub(name.clone());
+        if self.subprogram_info_repository.find(&scope_name).is_none() {
+            self.generate_native_call_instructions(name, &args, pos);
+            self.push(Instruction::PopStack, pos);
+            return;
+        }
         // cloning to fight the borrow checker
         let sub_impl_parameters: Vec<Parameter> = self
             .subprogram_info_repository
@@ -86,6 +109,21 @@ impl InstructionGenerator {
         self.generate_un_stash_by_ref_args(&args);
     }
 
+    fn generate_native_call_instructions(
+        &mut self,
+        name: BareName,
+        args: &Expressions,
+        pos: Position,
+    ) {
+        self.push(Instruction::BeginCollectArguments, pos);
+        for Positioned { element: arg, pos } in args {
+            self.generate_expression_instructions(arg.clone().at(pos));
+            self.push(Instruction::PushUnnamedByVal, *pos);
+        }
+        self.push(Instruction::PushStack, pos);
+        self.push(Instruction::CallNative(name), pos);
+    }
+
     fn generate_push_named_args_instructions(
         &mut self,
         param_names: &[Parameter],
@@ -98,7 +136,10 @@ impl InstructionGenerator {
                 arg.clone().at(pos),
                 param_name.expression_type(),
             );
-            self.push(Instruction::PushNamed(param_name.clone()), *pos);
+            self.push(
+                Instruction::PushNamed(Rc::new(parameter_name(param_name))),
+                *pos,
+            );
         }
     }
 
@@ -133,9 +174,8 @@ impl InstructionGenerator {
         }
     }
 
-    fn generate_stash_function_return_value(&mut self, qualified_name: Name, pos: Position) {
-        debug_assert!(!qualified_name.is_bare());
-        self.push(Instruction::StashFunctionReturnValue(qualified_name), pos);
+    fn generate_stash_function_return_value(&mut self, slot: usize, pos: Position) {
+        self.push(Instruction::StashFunctionReturnValue(slot), pos);
     }
 
     fn generate_un_stash_function_return_value(&mut self, pos: Position) {
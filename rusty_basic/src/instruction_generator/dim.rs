@@ -3,7 +3,7 @@ use rusty_linter::core::ScopeName;
 use rusty_parser::*;
 use rusty_variant::Variant;
 
-use super::{Instruction, InstructionGenerator, RootPath};
+use super::{Instruction, InstructionGenerator};
 
 impl InstructionGenerator {
     pub fn visit_dim_list(&mut self, item: DimList) {
@@ -32,7 +32,8 @@ impl InstructionGenerator {
                 !shared,
                 "Should not be possible to have a SHARED variable inside a function/sub"
             );
-            self.push(Instruction::IsVariableDefined(dim_name.clone()), pos);
+            let root_path = self.root_path(&dim_var_name(&dim_name), shared);
+            self.push(Instruction::IsVariableDefined(root_path.slot), pos);
             self.jump_if_false("begin-dim", pos);
             self.jump("end-dim", pos);
             self.label("begin-dim", pos);
@@ -56,7 +57,8 @@ impl InstructionGenerator {
     }
 
     fn generate_dim_name(&mut self, dim_name: DimVar, shared: bool, pos: Position) {
-        let (bare_name, dim_type) = dim_name.into();
+        let name = dim_var_name(&dim_name);
+        let (_, dim_type) = dim_name.into();
         match dim_type {
             DimType::Array(array_dimensions, box_element_type) => {
                 self.push(Instruction::BeginCollectArguments, pos);
@@ -76,45 +78,13 @@ impl InstructionGenerator {
                 }
 
                 let element_type = box_element_type.expression_type();
-
-                let opt_q = match &element_type {
-                    ExpressionType::BuiltIn(q) => Some(*q),
-                    ExpressionType::FixedLengthString(_) => Some(TypeQualifier::DollarString),
-                    _ => None,
-                };
-
                 self.push(Instruction::AllocateArrayIntoA(element_type), pos);
-
-                self.push(
-                    Instruction::VarPathName(RootPath {
-                        name: Name::new(bare_name, opt_q),
-                        shared,
-                    }),
-                    pos,
-                );
-                self.push(Instruction::CopyAToVarPath, pos);
             }
             DimType::BuiltIn(q, _) => {
                 self.push(Instruction::AllocateBuiltIn(q), pos);
-                self.push(
-                    Instruction::VarPathName(RootPath {
-                        name: Name::qualified(bare_name, q),
-                        shared,
-                    }),
-                    pos,
-                );
-                self.push(Instruction::CopyAToVarPath, pos);
             }
             DimType::FixedLengthString(_, len) => {
                 self.push(Instruction::AllocateFixedLengthString(len), pos);
-                self.push(
-                    Instruction::VarPathName(RootPath {
-                        name: Name::qualified(bare_name, TypeQualifier::DollarString),
-                        shared,
-                    }),
-                    pos,
-                );
-                self.push(Instruction::CopyAToVarPath, pos);
             }
             DimType::UserDefined(Positioned {
                 element: user_defined_type_name,
@@ -124,16 +94,27 @@ impl InstructionGenerator {
                     Instruction::AllocateUserDefined(user_defined_type_name),
                     pos,
                 );
-                self.push(
-                    Instruction::VarPathName(RootPath {
-                        name: Name::bare(bare_name),
-                        shared,
-                    }),
-                    pos,
-                );
-                self.push(Instruction::CopyAToVarPath, pos);
             }
             DimType::Bare => panic!("Unresolved type"),
         }
+        let root_path = self.root_path(&name, shared);
+        self.push(Instruction::CopyAToVariable(root_path, name), pos);
+    }
+}
+
+/// Gets the name of the variable that is declared by a DIM statement,
+/// e.g. `A$` for `DIM A AS STRING * 5`, `P` for `DIM P AS Card`.
+fn dim_var_name(dim_var: &DimVar) -> Name {
+    fn name_of_type(bare_name: &BareName, dim_type: &DimType) -> Name {
+        match dim_type {
+            DimType::BuiltIn(q, _) => Name::qualified(bare_name.clone(), *q),
+            DimType::FixedLengthString(_, _) => {
+                Name::qualified(bare_name.clone(), TypeQualifier::DollarString)
+            }
+            DimType::UserDefined(_) => Name::bare(bare_name.clone()),
+            DimType::Array(_, element_type) => name_of_type(bare_name, element_type),
+            DimType::Bare => panic!("Unresolved type"),
+        }
     }
+    name_of_type(dim_var.as_bare_name(), dim_var.var_type())
 }
@@ -53,6 +53,10 @@ impl InstructionGenerator {
             Expression::LongLiteral(s) => {
                 self.push_load(Variant::VLong(s), pos);
             }
+            Expression::Variable(name, _) if consume_var_path => {
+                let root_path = self.variable_root_path(&name);
+                self.push(Instruction::CopyVariableToA(root_path, name), pos);
+            }
             Expression::Variable(_, _)
             | Expression::ArrayElement(_, _, _)
             | Expression::Property(_, _, _) => {
@@ -70,26 +74,13 @@ impl InstructionGenerator {
                 self.generate_built_in_function_call_instructions(n, args, pos);
             }
             Expression::BinaryExpression(op, left, right, _) => {
+                let operand_type = same_operand_type(&left, &right);
                 self.generate_expression_instructions(*left);
                 self.push(Instruction::PushAToValueStack, pos);
                 self.generate_expression_instructions(*right);
                 self.push(Instruction::CopyAToB, pos);
                 self.push(Instruction::PopValueStackIntoA, pos);
-                match op {
-                    Operator::Plus => self.push(Instruction::Plus, pos),
-                    Operator::Minus => self.push(Instruction::Minus, pos),
-                    Operator::Multiply => self.push(Instruction::Multiply, pos),
-                    Operator::Divide => self.push(Instruction::Divide, pos),
-                    Operator::Modulo => self.push(Instruction::Modulo, pos),
-                    Operator::Less => self.push(Instruction::Less, pos),
-                    Operator::LessOrEqual => self.push(Instruction::LessOrEqual, pos),
-                    Operator::Equal => self.push(Instruction::Equal, pos),
-                    Operator::GreaterOrEqual => self.push(Instruction::GreaterOrEqual, pos),
-                    Operator::Greater => self.push(Instruction::Greater, pos),
-                    Operator::NotEqual => self.push(Instruction::NotEqual, pos),
-                    Operator::And => self.push(Instruction::And, pos),
-                    Operator::Or => self.push(Instruction::Or, pos),
-                }
+                self.push(binary_operator_instruction(op, operand_type), pos);
             }
             Expression::UnaryExpression(op, child) => match op {
                 UnaryOperator::Not => {
@@ -112,30 +103,12 @@ impl InstructionGenerator {
 
         match expr {
             Expression::Variable(var_name, ..) => {
-                let linter_var_info: &VariableInfo = self
-                    .linter_names
-                    .get_resolved_variable_info(&self.current_subprogram, &var_name);
-
-                self.push(
-                    Instruction::VarPathName(RootPath {
-                        name: var_name,
-                        shared: linter_var_info.shared,
-                    }),
-                    pos,
-                );
+                let root_path = self.variable_root_path(&var_name);
+                self.push(Instruction::VarPathName(root_path, var_name), pos);
             }
             Expression::ArrayElement(array_name, indices, ..) => {
-                let linter_var_info: &VariableInfo = self
-                    .linter_names
-                    .get_resolved_variable_info(&self.current_subprogram, &array_name);
-
-                self.push(
-                    Instruction::VarPathName(RootPath {
-                        name: array_name,
-                        shared: linter_var_info.shared,
-                    }),
-                    pos,
-                );
+                let root_path = self.variable_root_path(&array_name);
+                self.push(Instruction::VarPathName(root_path, array_name), pos);
                 for arg in indices {
                     let arg_pos = arg.pos();
                     self.push(Instruction::PushAToValueStack, arg_pos);
@@ -158,4 +131,56 @@ impl InstructionGenerator {
             _ => panic!("Not a name expression {:?}", expr),
         }
     }
+
+    /// Gets the slot of a variable that is referenced in the current SUB/FUNCTION
+    /// (or the module-level code), which might be a shared variable.
+    pub fn variable_root_path(&mut self, name: &Name) -> RootPath {
+        let linter_var_info: &VariableInfo = self
+            .linter_names
+            .get_resolved_variable_info(&self.current_subprogram, name);
+        let shared = linter_var_info.shared;
+        self.root_path(name, shared)
+    }
+}
+
+/// Gets the type of the operands of a binary expression,
+/// if both of them are of the same built-in type.
+fn same_operand_type(left: &ExpressionPos, right: &ExpressionPos) -> Option<TypeQualifier> {
+    match (left.expression_type(), right.expression_type()) {
+        (ExpressionType::BuiltIn(l), ExpressionType::BuiltIn(r)) if l == r => Some(l),
+        _ => None,
+    }
+}
+
+/// Gets the instruction of the given operator. If the type of the operands
+/// is known, it picks the typed instruction of the operator, if one exists.
+pub fn binary_operator_instruction(
+    op: Operator,
+    operand_type: Option<TypeQualifier>,
+) -> Instruction {
+    let is_numeric = |q: &TypeQualifier| *q != TypeQualifier::DollarString;
+    match (op, operand_type) {
+        (Operator::Plus, Some(q)) => Instruction::PlusTyped(q),
+        (Operator::Minus, Some(q)) if is_numeric(&q) => Instruction::MinusTyped(q),
+        (Operator::Multiply, Some(q)) if is_numeric(&q) => Instruction::MultiplyTyped(q),
+        (Operator::Less, Some(q)) => Instruction::LessTyped(q),
+        (Operator::LessOrEqual, Some(q)) => Instruction::LessOrEqualTyped(q),
+        (Operator::Equal, Some(q)) => Instruction::EqualTyped(q),
+        (Operator::GreaterOrEqual, Some(q)) => Instruction::GreaterOrEqualTyped(q),
+        (Operator::Greater, Some(q)) => Instruction::GreaterTyped(q),
+        (Operator::NotEqual, Some(q)) => Instruction::NotEqualTyped(q),
+        (Operator::Plus, _) => Instruction::Plus,
+        (Operator::Minus, _) => Instruction::Minus,
+        (Operator::Multiply, _) => Instruction::Multiply,
+        (Operator::Divide, _) => Instruction::Divide,
+        (Operator::Modulo, _) => Instruction::Modulo,
+        (Operator::Less, _) => Instruction::Less,
+        (Operator::LessOrEqual, _) => Instruction::LessOrEqual,
+        (Operator::Equal, _) => Instruction::Equal,
+        (Operator::GreaterOrEqual, _) => Instruction::GreaterOrEqual,
+        (Operator::Greater, _) => Instruction::Greater,
+        (Operator::NotEqual, _) => Instruction::NotEqual,
+        (Operator::And, _) => Instruction::And,
+        (Operator::Or, _) => Instruction::Or,
+    }
 }
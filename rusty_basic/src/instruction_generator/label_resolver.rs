@@ -45,6 +45,12 @@ impl LabelResolver {
                     *label_to_address.get(x).unwrap(),
                 ));
             }
+            Instruction::OnEventGoSub(kind, AddressOrLabel::Unresolved(x)) => {
+                *instruction = Instruction::OnEventGoSub(
+                    *kind,
+                    AddressOrLabel::Resolved(*label_to_address.get(x).unwrap()),
+                );
+            }
             Instruction::GoSub(AddressOrLabel::Unresolved(x)) => {
                 *instruction =
                     Instruction::GoSub(AddressOrLabel::Resolved(*label_to_address.get(x).unwrap()));
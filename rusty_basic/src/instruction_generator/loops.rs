@@ -1,10 +1,11 @@
 use rusty_common::*;
 use rusty_parser::{
     ConditionalBlock, DoLoop, DoLoopConditionKind, DoLoopConditionPosition, Expression,
-    ExpressionPos, ForLoop, HasExpressionType, Statements,
+    ExpressionPos, ExpressionType, ForLoop, HasExpressionType, Operator, Statements, TypeQualifier,
 };
 use rusty_variant::Variant;
 
+use super::expression::binary_operator_instruction;
 use super::{Instruction, InstructionGenerator, Visitor};
 use crate::RuntimeError;
 
@@ -58,6 +59,8 @@ impl InstructionGenerator {
         match step {
             Some(s) => {
                 let step_pos = s.pos();
+                let is_step_typed = counter_type(&counter_var_name).is_some()
+                    && s.expression_type() == counter_var_name.expression_type();
                 // load 0 to B
                 self.push_load(Variant::VInteger(0), pos);
                 self.push(Instruction::CopyAToB, pos);
@@ -73,6 +76,7 @@ impl InstructionGenerator {
                     &counter_var_name,
                     statements.clone(),
                     false,
+                    is_step_typed,
                     pos,
                 );
                 // jump out
@@ -89,6 +93,7 @@ impl InstructionGenerator {
                     &counter_var_name,
                     statements,
                     true,
+                    is_step_typed,
                     pos,
                 );
                 // jump out
@@ -99,13 +104,22 @@ impl InstructionGenerator {
                 self.label("out-of-for", pos);
             }
             None => {
-                self.push_load(Variant::VInteger(1), pos);
+                // the default step has the type of the counter,
+                // so that the increment can use a typed instruction
+                let step = match counter_type(&counter_var_name) {
+                    Some(TypeQualifier::AmpersandLong) => Variant::VLong(1),
+                    Some(TypeQualifier::BangSingle) => Variant::VSingle(1.0),
+                    Some(TypeQualifier::HashDouble) => Variant::VDouble(1.0),
+                    _ => Variant::VInteger(1),
+                };
+                self.push_load(step, pos);
                 // A to D (step is in D)
                 self.push(Instruction::CopyAToD, pos);
                 self.generate_for_loop_instructions_positive_or_negative_step(
                     &counter_var_name,
                     statements,
                     true,
+                    true,
                     pos,
                 );
                 self.label("out-of-for", pos);
@@ -118,8 +132,10 @@ impl InstructionGenerator {
         counter_var_name: &Expression,
         statements: Statements,
         is_positive: bool,
+        is_step_typed: bool,
         pos: Position,
     ) {
+        let counter_type = counter_type(counter_var_name);
         let loop_label = if is_positive {
             "positive-loop"
         } else {
@@ -131,11 +147,13 @@ impl InstructionGenerator {
         self.push(Instruction::CopyCToB, pos);
         // counter to A
         self.load_counter(counter_var_name, pos);
-        if is_positive {
-            self.push(Instruction::LessOrEqual, pos);
+        // the upper bound has been cast to the type of the counter
+        let op = if is_positive {
+            Operator::LessOrEqual
         } else {
-            self.push(Instruction::GreaterOrEqual, pos);
-        }
+            Operator::GreaterOrEqual
+        };
+        self.push(binary_operator_instruction(op, counter_type), pos);
         self.jump_if_false("out-of-for", pos);
 
         // push registers
@@ -152,7 +170,8 @@ impl InstructionGenerator {
         self.load_counter(counter_var_name, pos);
         // copy step from D to B
         self.push(Instruction::CopyDToB, pos);
-        self.push(Instruction::Plus, pos);
+        let step_type = if is_step_typed { counter_type } else { None };
+        self.push(binary_operator_instruction(Operator::Plus, step_type), pos);
         self.store_counter(counter_var_name, pos);
 
         // back to loop
@@ -214,3 +233,11 @@ impl InstructionGenerator {
         self.label("loop", pos);
     }
 }
+
+/// Gets the type of the counter of a FOR loop.
+fn counter_type(counter_var_name: &Expression) -> Option<TypeQualifier> {
+    match counter_var_name.expression_type() {
+        ExpressionType::BuiltIn(q) => Some(q),
+        _ => None,
+    }
+}
@@ -1,15 +1,19 @@
+use std::rc::Rc;
+
 use rusty_common::{AtPos, CaseInsensitiveString, Position, Positioned};
 use rusty_linter::core::{LinterContext, ScopeName};
 use rusty_linter::names::Names;
 use rusty_parser::{
-    Assignment, BareName, BuiltInFunction, BuiltInSub, DimVar, Expression, ExpressionType,
-    FileHandle, FunctionImplementation, GlobalStatement, HasExpressionType, Name, Parameter,
+    Assignment, BareName, BuiltInFunction, BuiltInSub, EventAction, EventKind, Expression,
+    ExpressionType, FileHandle, FunctionImplementation, GlobalStatement, HasExpressionType, Name,
     Program, Statement, Statements, SubImplementation, TypeQualifier, UserDefinedTypes,
 };
 use rusty_variant::Variant;
 
 use crate::RuntimeError;
 use crate::instruction_generator::label_resolver::LabelResolver;
+use crate::instruction_generator::optimizer::optimize;
+use crate::instruction_generator::slots::{Slots, parameter_name};
 use crate::instruction_generator::subprogram_info::{
     SubprogramInfoCollector, SubprogramInfoRepository,
 };
@@ -31,21 +35,31 @@ pub fn generate_instructions(program: Program, linter_names: Names) -> Instructi
     let InstructionGenerator {
         instructions,
         statement_addresses,
+        global_slots,
+        max_local_slots,
         ..
     } = generator;
-    // pass 3 resolve labels to addresses
+    // pass 3 optimize, while the labels are still unresolved
+    let (instructions, statement_addresses) = optimize(instructions, statement_addresses);
+    // pass 4 resolve labels to addresses
     let mut label_resolver = LabelResolver::new(instructions);
     label_resolver.resolve_labels();
     let LabelResolver { instructions } = label_resolver;
     InstructionGeneratorResult {
         instructions,
         statement_addresses,
+        global_names: global_slots.into_names(),
+        max_local_slots,
     }
 }
 
 pub struct InstructionGeneratorResult {
     pub instructions: Vec<InstructionPos>,
     pub statement_addresses: Vec<usize>,
+    /// The names of the module-level variables, by slot.
+    pub global_names: Vec<Name>,
+    /// The number of slots of the largest SUB/FUNCTION frame.
+    pub max_local_slots: usize,
 }
 
 #[derive(Clone, Debug)]
@@ -55,10 +69,10 @@ pub enum Path {
     Property(Box<Self>, BareName),
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct RootPath {
-    /// The name of the root variable
-    pub name: Name,
+    /// The slot of the root variable in its frame
+    pub slot: usize,
 
     /// If true, the variable belongs to the global shared context,
     /// i.e. it was declared with DIM SHARED
@@ -89,7 +103,8 @@ pub enum Instruction {
     // qualified for built-in types.
     // The second step is to write the register A into the variant that the
     // variable path references.
-    VarPathName(RootPath),
+    // The name is used to define the variable, if it is not already defined.
+    VarPathName(RootPath, Name),
 
     VarPathIndex,
 
@@ -110,9 +125,27 @@ pub enum Instruction {
     /// Pops a value from the var path deque.
     PopVarPath,
 
+    /// Copies the value of a variable into register A,
+    /// without going through the var path deque.
+    /// The name is used to define the variable, if it is not already defined.
+    CopyVariableToA(RootPath, Name),
+
+    /// Copies the value of a variable into register B.
+    /// Only generated by the optimizer.
+    CopyVariableToB(RootPath, Name),
+
+    /// Copies the value of register A into a variable,
+    /// without going through the var path deque.
+    /// The name is used to define the variable, if it is not already defined.
+    CopyAToVariable(RootPath, Name),
+
     /// Loads a value into register A
     LoadIntoA(Variant),
 
+    /// Loads a value into register B.
+    /// Only generated by the optimizer.
+    LoadIntoB(Variant),
+
     CopyAToB,
     CopyAToC,
     CopyAToD,
@@ -136,6 +169,19 @@ pub enum Instruction {
     And,
     Or,
 
+    /// Like [Instruction::Plus], but both registers A and B hold values of the
+    /// given type, so it skips the type conversions. Integer and long
+    /// arithmetic is checked for overflow.
+    PlusTyped(TypeQualifier),
+    MinusTyped(TypeQualifier),
+    MultiplyTyped(TypeQualifier),
+    LessTyped(TypeQualifier),
+    LessOrEqualTyped(TypeQualifier),
+    EqualTyped(TypeQualifier),
+    GreaterOrEqualTyped(TypeQualifier),
+    GreaterTyped(TypeQualifier),
+    NotEqualTyped(TypeQualifier),
+
     Label(CaseInsensitiveString),
 
     Jump(AddressOrLabel),
@@ -152,8 +198,16 @@ pub enum Instruction {
 
     BuiltInSub(BuiltInSub),
     BuiltInFunction(BuiltInFunction),
+
+    /// Calls a native SUB or FUNCTION, which is implemented by the application
+    /// that embeds the interpreter. It is called like a built-in,
+    /// with unnamed arguments that are passed by value.
+    CallNative(BareName),
     Halt,
 
+    /// Pauses the program when a debugger is attached, otherwise acts like [Instruction::Halt].
+    Stop,
+
     PushRegisters,
     PopRegisters,
 
@@ -170,7 +224,9 @@ pub enum Instruction {
     BeginCollectArguments,
 
     /// Pushes the value of register A as a named parameter to a child context.
-    PushNamed(Parameter),
+    /// Holds the name of the parameter, as given by [parameter_name],
+    /// which is shared with the variable of the child context.
+    PushNamed(Rc<Name>),
 
     /// Pushes the value of register A as an unnamed parameter to a child context.
     /// Unnamed parameters are used by built-in functions/subs.
@@ -188,8 +244,9 @@ pub enum Instruction {
     EnqueueToReturnStack(usize),
     DequeueFromReturnStack,
 
-    // The name of the function should be qualified.
-    StashFunctionReturnValue(Name),
+    /// Stashes the result of a function, which is held in the given slot
+    /// of its frame (right after the parameters).
+    StashFunctionReturnValue(usize),
 
     UnStashFunctionReturnValue,
 
@@ -199,6 +256,14 @@ pub enum Instruction {
     OnErrorResumeNext,
     OnErrorGoToZero,
 
+    /// Sets the handler of an event (`ON KEY(n)`, `ON PLAY(n)`, `ON TIMER(n)`).
+    /// The argument of the event is in register A.
+    OnEventGoSub(EventKind, AddressOrLabel),
+
+    /// Enables, disables or suspends trapping of an event.
+    /// For `KEY(n)`, the key number is in register A.
+    EventControl(EventKind, EventAction),
+
     /// Cast the contents of A into the given type
     Cast(TypeQualifier),
 
@@ -230,9 +295,9 @@ pub enum Instruction {
     // TODO #[deprecated]
     PrintEnd,
 
-    /// Checks if a variable is defined (used to prevent re-allocation of variables in STATIC functions/subs).
+    /// Checks if the variable in the given slot is defined (used to prevent re-allocation of variables in STATIC functions/subs).
     /// If the variable is already present, it will set the A register to true, otherwise to false.
-    IsVariableDefined(DimVar),
+    IsVariableDefined(usize),
 }
 
 pub type InstructionPos = Positioned<Instruction>;
@@ -267,6 +332,12 @@ pub struct InstructionGenerator {
     pub subprogram_info_repository: SubprogramInfoRepository,
     pub current_subprogram: ScopeName,
     pub linter_names: Names,
+    /// The slots of the module-level variables.
+    pub global_slots: Slots,
+    /// The slots of the variables of the current SUB/FUNCTION.
+    pub local_slots: Slots,
+    /// The number of slots of the largest SUB/FUNCTION frame so far.
+    pub max_local_slots: usize,
 }
 
 impl InstructionGenerator {
@@ -277,6 +348,9 @@ impl InstructionGenerator {
             subprogram_info_repository,
             current_subprogram: ScopeName::Global,
             linter_names,
+            global_slots: Slots::default(),
+            local_slots: Slots::default(),
+            max_local_slots: 0,
         }
     }
 
@@ -336,7 +410,7 @@ impl InstructionGenerator {
         data_statements
     }
 
-    fn is_data_statement(statement: &Statement) -> bool {
+    pub(super) fn is_data_statement(statement: &Statement) -> bool {
         if let Statement::BuiltInSubCall(b) = statement {
             *b.built_in_sub() == BuiltInSub::Data
         } else {
@@ -345,7 +419,7 @@ impl InstructionGenerator {
     }
 
     fn visit_global_statements(&mut self, statements: Statements) {
-        self.visit(statements);
+        self.visit_hoisting_block(statements);
 
         // add HALT instruction at end of program to separate from the functions and subs
         self.mark_statement_address();
@@ -369,6 +443,7 @@ impl InstructionGenerator {
                     element: function_name,
                     ..
                 },
+            params,
             body,
             ..
         } = function_implementation;
@@ -376,9 +451,13 @@ impl InstructionGenerator {
         let qualifier = function_name
             .qualifier()
             .expect("Expected qualified function name");
-        self.mark_current_subprogram(ScopeName::Function(function_name), pos);
-        // set default value
-        self.push(Instruction::AllocateBuiltIn(qualifier), pos);
+        self.mark_current_subprogram(ScopeName::Function(function_name.clone()), pos);
+        let root_path = self.root_path(&function_name, false);
+        // set default value, unless a parameter has the same name as the function
+        if root_path.slot == params.len() {
+            self.push(Instruction::AllocateBuiltIn(qualifier), pos);
+            self.push(Instruction::CopyAToVariable(root_path, function_name), pos);
+        }
         self.subprogram_body(body, pos);
     }
 
@@ -412,11 +491,35 @@ impl InstructionGenerator {
             Instruction::Label(Self::format_subprogram_label(&scope_name)),
             pos,
         );
+        // the arguments are collected in the first slots of the frame,
+        // followed by the result of a FUNCTION
+        self.local_slots = Slots::default();
+        for parameter in &self
+            .subprogram_info_repository
+            .get_subprogram_info(&scope_name)
+            .params
+        {
+            let slot = self.local_slots.get_or_insert(&parameter_name(parameter));
+            self.max_local_slots = self.max_local_slots.max(slot + 1);
+        }
         self.current_subprogram = scope_name;
     }
 
+    /// Gets the slot of the given variable. Shared variables and
+    /// the variables of the module-level code live in the global frame.
+    pub fn root_path(&mut self, name: &Name, shared: bool) -> RootPath {
+        let slot = if shared || self.current_subprogram == ScopeName::Global {
+            self.global_slots.get_or_insert(name)
+        } else {
+            let slot = self.local_slots.get_or_insert(name);
+            self.max_local_slots = self.max_local_slots.max(slot + 1);
+            slot
+        };
+        RootPath { slot, shared }
+    }
+
     fn subprogram_body(&mut self, block: Statements, pos: Position) {
-        self.visit(block);
+        self.visit_hoisting_block(block);
         // to be able to RESUME NEXT if an error occurs on the last statement
         self.mark_statement_address();
         self.push(Instruction::PopRet, pos);
@@ -467,8 +570,13 @@ impl InstructionGenerator {
     }
 
     pub fn generate_store_instructions(&mut self, l: Expression, pos: Position) {
-        self.generate_path_instructions(l.at_pos(pos));
-        self.push(Instruction::CopyAToVarPath, pos);
+        if let Expression::Variable(name, _) = l {
+            let root_path = self.variable_root_path(&name);
+            self.push(Instruction::CopyAToVariable(root_path, name), pos);
+        } else {
+            self.generate_path_instructions(l.at_pos(pos));
+            self.push(Instruction::CopyAToVarPath, pos);
+        }
     }
 
     pub fn mark_statement_address(&mut self) {
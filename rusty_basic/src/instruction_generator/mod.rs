@@ -5,8 +5,10 @@ mod if_block;
 mod label_resolver;
 mod loops;
 mod main;
+mod optimizer;
 pub mod print;
 mod select_case;
+mod slots;
 mod statement;
 mod subprogram_info;
 
@@ -16,3 +18,4 @@ pub mod test_utils;
 mod tests;
 
 pub use self::main::*;
+pub use self::slots::parameter_name;
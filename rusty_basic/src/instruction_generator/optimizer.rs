@@ -0,0 +1,537 @@
+//! A peephole optimiser over the generated instructions.
+//!
+//! It runs while the labels are still unresolved, so removing instructions
+//! only requires remapping the absolute addresses that the generator
+//! already knows about: the statement addresses and the return addresses
+//! of `PushRet`.
+//!
+//! Execution can arrive at an instruction other than by falling through to it
+//! when it is a label, a statement address (`RESUME`, `RESUME NEXT`, the
+//! debugger) or a return address. These instructions are barriers: they are
+//! never removed and a pattern never spans across them. The position of every
+//! instruction that is kept stays the same, so error positions don't change.
+
+use std::collections::{HashMap, HashSet};
+
+use rusty_common::CaseInsensitiveString;
+use rusty_linter::core::CastVariant;
+
+use crate::instruction_generator::{AddressOrLabel, Instruction, InstructionPos};
+
+/// Optimises the given instructions, returning them together with
+/// the remapped statement addresses.
+pub fn optimize(
+    instructions: Vec<InstructionPos>,
+    statement_addresses: Vec<usize>,
+) -> (Vec<InstructionPos>, Vec<usize>) {
+    let mut optimizer = Optimizer::new(instructions, statement_addresses);
+    // every pass can reveal new opportunities to the others
+    loop {
+        let mut changed = optimizer.thread_jumps();
+        changed |= optimizer.remove_dead_code();
+        changed |= optimizer.remove_jumps_to_next();
+        changed |= optimizer.combine_instructions();
+        changed |= optimizer.remove_unused_labels();
+        if !changed {
+            break;
+        }
+        optimizer.compact();
+    }
+    (optimizer.instructions, optimizer.statement_addresses)
+}
+
+struct Optimizer {
+    instructions: Vec<InstructionPos>,
+    statement_addresses: Vec<usize>,
+    /// Instructions that execution can arrive at other than by falling through,
+    /// except for labels (statement addresses and return addresses).
+    pinned: Vec<bool>,
+    /// Instructions that will be removed by the next compaction.
+    removed: Vec<bool>,
+}
+
+impl Optimizer {
+    fn new(instructions: Vec<InstructionPos>, statement_addresses: Vec<usize>) -> Self {
+        let len = instructions.len();
+        let mut pinned = vec![false; len];
+        for address in statement_addresses.iter().filter(|a| **a < len) {
+            pinned[*address] = true;
+        }
+        for instruction_pos in &instructions {
+            if let Instruction::PushRet(address) = instruction_pos.element
+                && address < len
+            {
+                pinned[address] = true;
+            }
+        }
+        Self {
+            instructions,
+            statement_addresses,
+            pinned,
+            removed: vec![false; len],
+        }
+    }
+
+    fn instruction(&self, index: usize) -> &Instruction {
+        &self.instructions[index].element
+    }
+
+    fn is_label(&self, index: usize) -> bool {
+        matches!(self.instruction(index), Instruction::Label(_))
+    }
+
+    fn is_barrier(&self, index: usize) -> bool {
+        self.pinned[index] || self.is_label(index)
+    }
+
+    fn remove(&mut self, index: usize) {
+        debug_assert!(!self.pinned[index]);
+        self.removed[index] = true;
+    }
+
+    /// Gets the indices of the instructions that are not removed,
+    /// starting at the given index.
+    fn live_from(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        (index..self.instructions.len()).filter(|i| !self.removed[*i])
+    }
+
+    fn label_indices(&self) -> HashMap<CaseInsensitiveString, usize> {
+        self.live_from(0)
+            .filter_map(|i| match self.instruction(i) {
+                Instruction::Label(label) => Some((label.clone(), i)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Makes jumps that land on an unconditional jump go directly
+    /// to the final destination.
+    fn thread_jumps(&mut self) -> bool {
+        let label_indices = self.label_indices();
+        let mut changed = false;
+        for i in self.live_from(0).collect::<Vec<_>>() {
+            let label = match self.instruction(i) {
+                Instruction::Jump(AddressOrLabel::Unresolved(label))
+                | Instruction::JumpIfFalse(AddressOrLabel::Unresolved(label)) => label,
+                _ => continue,
+            };
+            let mut target = label.clone();
+            let mut visited: HashSet<CaseInsensitiveString> = HashSet::new();
+            while visited.insert(target.clone()) {
+                // the first instruction after the label (and any labels following it)
+                let landing = self
+                    .live_from(label_indices[&target])
+                    .find(|j| !self.is_label(*j));
+                match landing.map(|j| (j, self.instruction(j))) {
+                    Some((j, Instruction::Jump(AddressOrLabel::Unresolved(next))))
+                        if !self.pinned[j] =>
+                    {
+                        target = next.clone();
+                    }
+                    _ => break,
+                }
+            }
+            if target != *label {
+                match &mut self.instructions[i].element {
+                    Instruction::Jump(address_or_label)
+                    | Instruction::JumpIfFalse(address_or_label) => {
+                        *address_or_label = AddressOrLabel::Unresolved(target);
+                    }
+                    _ => unreachable!(),
+                }
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Removes the instructions that follow an unconditional jump,
+    /// up to the next barrier.
+    fn remove_dead_code(&mut self) -> bool {
+        let mut changed = false;
+        let mut is_dead = false;
+        for i in 0..self.instructions.len() {
+            if self.removed[i] {
+                continue;
+            }
+            if is_dead && !self.is_barrier(i) {
+                self.remove(i);
+                changed = true;
+                continue;
+            }
+            is_dead = matches!(self.instruction(i), Instruction::Jump(_));
+        }
+        changed
+    }
+
+    /// Removes unconditional jumps to a label that follows them directly.
+    fn remove_jumps_to_next(&mut self) -> bool {
+        let mut changed = false;
+        for i in self.live_from(0).collect::<Vec<_>>() {
+            let Instruction::Jump(AddressOrLabel::Unresolved(label)) = self.instruction(i) else {
+                continue;
+            };
+            if self.pinned[i] {
+                continue;
+            }
+            let is_next = self
+                .live_from(i + 1)
+                .take_while(|j| self.is_label(*j))
+                .any(|j| matches!(self.instruction(j), Instruction::Label(l) if l == label));
+            if is_next {
+                self.remove(i);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Replaces sequences of instructions with equivalent shorter ones.
+    fn combine_instructions(&mut self) -> bool {
+        let mut changed = false;
+        let mut i = 0;
+        while i < self.instructions.len() {
+            if self.removed[i] {
+                i += 1;
+                continue;
+            }
+            // the instructions that follow, up to the next barrier
+            let window: Vec<usize> = self
+                .live_from(i + 1)
+                .take_while(|j| !self.is_barrier(*j))
+                .take(3)
+                .collect();
+            if self.combine_at(i, &window) {
+                changed = true;
+            } else {
+                i += 1;
+            }
+        }
+        changed
+    }
+
+    /// Tries to combine the instruction at the given index with the
+    /// instructions of the window that follow it.
+    fn combine_at(&mut self, i: usize, window: &[usize]) -> bool {
+        let next = |n: usize| window.get(n).map(|j| self.instruction(*j));
+        match (self.instruction(i), next(0), next(1), next(2)) {
+            // fold the cast of a constant
+            (Instruction::LoadIntoA(v), Some(Instruction::Cast(q)), _, _) => {
+                match v.clone().cast(*q) {
+                    Ok(casted) => {
+                        self.instructions[i].element = Instruction::LoadIntoA(casted);
+                        self.remove(window[0]);
+                        true
+                    }
+                    // keep it, so that the error happens at runtime as before
+                    Err(_) => false,
+                }
+            }
+            // evaluating the right side of a binary expression
+            (
+                Instruction::PushAToValueStack,
+                Some(Instruction::LoadIntoA(_) | Instruction::CopyVariableToA(..)),
+                Some(Instruction::CopyAToB),
+                Some(Instruction::PopValueStackIntoA),
+            ) if !self.pinned[i] => {
+                let load_into_b = into_load_into_b(self.instruction(window[0]));
+                self.instructions[window[0]].element = load_into_b;
+                self.remove(i);
+                self.remove(window[1]);
+                self.remove(window[2]);
+                true
+            }
+            // A is overwritten right after it is copied into B
+            (
+                Instruction::LoadIntoA(_) | Instruction::CopyVariableToA(..),
+                Some(Instruction::CopyAToB),
+                Some(Instruction::LoadIntoA(_) | Instruction::CopyVariableToA(..)),
+                _,
+            ) => {
+                let load_into_b = into_load_into_b(self.instruction(i));
+                self.instructions[i].element = load_into_b;
+                self.remove(window[0]);
+                true
+            }
+            // the same copy twice
+            (
+                a @ (Instruction::CopyAToB
+                | Instruction::CopyAToC
+                | Instruction::CopyAToD
+                | Instruction::CopyCToB
+                | Instruction::CopyDToB),
+                Some(b),
+                _,
+                _,
+            ) if a == b => {
+                self.remove(window[0]);
+                true
+            }
+            // copying back what was just copied
+            (Instruction::CopyAToD, Some(Instruction::CopyDToA), _, _) => {
+                self.remove(window[0]);
+                true
+            }
+            // pushing and immediately popping
+            (Instruction::PushRegisters, Some(Instruction::PopRegisters), _, _)
+            | (Instruction::PushAToValueStack, Some(Instruction::PopValueStackIntoA), _, _)
+                if !self.pinned[i] =>
+            {
+                self.remove(i);
+                self.remove(window[0]);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Removes the labels that nothing refers to.
+    fn remove_unused_labels(&mut self) -> bool {
+        let used: HashSet<CaseInsensitiveString> = self
+            .live_from(0)
+            .filter_map(|i| referenced_label(self.instruction(i)))
+            .cloned()
+            .collect();
+        let mut changed = false;
+        for i in self.live_from(0).collect::<Vec<_>>() {
+            if let Instruction::Label(label) = self.instruction(i)
+                && !self.pinned[i]
+                && !used.contains(label)
+            {
+                self.remove(i);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Drops the removed instructions and remaps the addresses.
+    fn compact(&mut self) {
+        // maps an old address to a new one (the end of the list maps to the new end)
+        let mut new_addresses: Vec<usize> = Vec::with_capacity(self.instructions.len() + 1);
+        let mut next = 0;
+        for removed in &self.removed {
+            new_addresses.push(next);
+            if !removed {
+                next += 1;
+            }
+        }
+        new_addresses.push(next);
+        let old_instructions = std::mem::take(&mut self.instructions);
+        let old_pinned = std::mem::take(&mut self.pinned);
+        for ((mut instruction_pos, removed), pinned) in old_instructions
+            .into_iter()
+            .zip(self.removed.iter())
+            .zip(old_pinned)
+        {
+            if *removed {
+                continue;
+            }
+            if let Instruction::PushRet(address) = &mut instruction_pos.element {
+                *address = new_addresses[*address];
+            }
+            self.instructions.push(instruction_pos);
+            self.pinned.push(pinned);
+        }
+        for address in self.statement_addresses.iter_mut() {
+            *address = new_addresses[*address];
+        }
+        self.removed = vec![false; self.instructions.len()];
+    }
+}
+
+/// Converts an instruction that loads a value into A
+/// into one that loads the same value into B.
+fn into_load_into_b(instruction: &Instruction) -> Instruction {
+    match instruction {
+        Instruction::LoadIntoA(v) => Instruction::LoadIntoB(v.clone()),
+        Instruction::CopyVariableToA(root_path, name) => {
+            Instruction::CopyVariableToB(*root_path, name.clone())
+        }
+        _ => panic!(
+            "Expected an instruction that loads into A, found {:?}",
+            instruction
+        ),
+    }
+}
+
+/// Gets the label that the given instruction refers to, if any.
+fn referenced_label(instruction: &Instruction) -> Option<&CaseInsensitiveString> {
+    match instruction {
+        Instruction::Jump(AddressOrLabel::Unresolved(label))
+        | Instruction::JumpIfFalse(AddressOrLabel::Unresolved(label))
+        | Instruction::GoSub(AddressOrLabel::Unresolved(label))
+        | Instruction::Return(Some(AddressOrLabel::Unresolved(label)))
+        | Instruction::ResumeLabel(AddressOrLabel::Unresolved(label))
+        | Instruction::OnErrorGoTo(AddressOrLabel::Unresolved(label))
+        | Instruction::OnEventGoSub(_, AddressOrLabel::Unresolved(label)) => Some(label),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusty_common::AtPos;
+    use rusty_parser::TypeQualifier;
+    use rusty_variant::Variant;
+
+    use super::*;
+
+    fn label(name: &str) -> Instruction {
+        Instruction::Label(name.into())
+    }
+
+    fn jump(name: &str) -> Instruction {
+        Instruction::Jump(AddressOrLabel::Unresolved(name.into()))
+    }
+
+    fn at_rows(instructions: Vec<Instruction>) -> Vec<InstructionPos> {
+        instructions
+            .into_iter()
+            .enumerate()
+            .map(|(row, instruction)| instruction.at_rc(row as u32 + 1, 1))
+            .collect()
+    }
+
+    #[test]
+    fn test_threads_jump_chains() {
+        let (instructions, _) = optimize(
+            at_rows(vec![
+                Instruction::JumpIfFalse(AddressOrLabel::Unresolved("a".into())),
+                Instruction::Stop,
+                label("a"),
+                jump("b"),
+                label("b"),
+                jump("c"),
+                label("c"),
+                Instruction::Halt,
+            ]),
+            vec![],
+        );
+        assert_eq!(
+            instructions,
+            vec![
+                Instruction::JumpIfFalse(AddressOrLabel::Unresolved("c".into())).at_rc(1, 1),
+                Instruction::Stop.at_rc(2, 1),
+                label("c").at_rc(7, 1),
+                Instruction::Halt.at_rc(8, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_removes_dead_code_up_to_statement_address() {
+        let (instructions, statement_addresses) = optimize(
+            at_rows(vec![
+                jump("end"),
+                Instruction::Stop,
+                Instruction::Stop,
+                Instruction::Plus,
+                label("end"),
+                Instruction::Halt,
+            ]),
+            vec![0, 3, 6],
+        );
+        assert_eq!(
+            instructions
+                .into_iter()
+                .map(|i| i.element)
+                .collect::<Vec<_>>(),
+            vec![
+                jump("end"),
+                Instruction::Plus,
+                label("end"),
+                Instruction::Halt
+            ]
+        );
+        assert_eq!(statement_addresses, vec![0, 1, 4]);
+    }
+
+    #[test]
+    fn test_folds_cast_of_constant() {
+        let (instructions, _) = optimize(
+            vec![
+                Instruction::LoadIntoA(Variant::VInteger(1)).at_rc(1, 5),
+                Instruction::Cast(TypeQualifier::BangSingle).at_rc(1, 5),
+            ],
+            vec![0],
+        );
+        assert_eq!(
+            instructions,
+            vec![Instruction::LoadIntoA(Variant::VSingle(1.0)).at_rc(1, 5)]
+        );
+    }
+
+    #[test]
+    fn test_keeps_cast_that_overflows() {
+        let input = vec![
+            Instruction::LoadIntoA(Variant::VLong(100_000)).at_rc(1, 5),
+            Instruction::Cast(TypeQualifier::PercentInteger).at_rc(1, 5),
+        ];
+        let (instructions, _) = optimize(input, vec![0]);
+        assert_eq!(instructions.len(), 2);
+    }
+
+    #[test]
+    fn test_loads_right_operand_into_b() {
+        let (instructions, _) = optimize(
+            at_rows(vec![
+                Instruction::LoadIntoA(Variant::VInteger(1)),
+                Instruction::PushAToValueStack,
+                Instruction::LoadIntoA(Variant::VInteger(2)),
+                Instruction::CopyAToB,
+                Instruction::PopValueStackIntoA,
+                Instruction::Plus,
+            ]),
+            vec![0],
+        );
+        assert_eq!(
+            instructions,
+            vec![
+                Instruction::LoadIntoA(Variant::VInteger(1)).at_rc(1, 1),
+                Instruction::LoadIntoB(Variant::VInteger(2)).at_rc(3, 1),
+                Instruction::Plus.at_rc(6, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_does_not_combine_across_statement_address() {
+        let input = vec![
+            Instruction::PushRegisters.at_rc(1, 1),
+            Instruction::PopRegisters.at_rc(2, 1),
+        ];
+        let (instructions, statement_addresses) = optimize(input, vec![0, 1]);
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(statement_addresses, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_remaps_return_address() {
+        let (instructions, _) = optimize(
+            at_rows(vec![
+                Instruction::PushRegisters,
+                Instruction::PopRegisters,
+                Instruction::PushRet(4),
+                jump("sub"),
+                Instruction::Halt,
+                label("sub"),
+                Instruction::PopRet,
+            ]),
+            vec![2],
+        );
+        assert_eq!(
+            instructions
+                .into_iter()
+                .map(|i| i.element)
+                .collect::<Vec<_>>(),
+            vec![
+                Instruction::PushRet(2),
+                jump("sub"),
+                Instruction::Halt,
+                label("sub"),
+                Instruction::PopRet,
+            ]
+        );
+    }
+}
@@ -0,0 +1,49 @@
+use std::collections::HashMap;
+
+use rusty_parser::{AsBareName, BareName, Name, ParamType, Parameter};
+
+/// Assigns a slot to every variable of a frame (the module-level code or
+/// a SUB/FUNCTION call), in order of appearance.
+///
+/// The parameters of a SUB/FUNCTION are registered first, so that they occupy
+/// the slots in which the arguments of the call are collected.
+#[derive(Default)]
+pub struct Slots {
+    names: Vec<Name>,
+    indices: HashMap<Name, usize>,
+}
+
+impl Slots {
+    /// Gets the slot of the given variable, assigning the next free slot
+    /// if the variable is seen for the first time.
+    pub fn get_or_insert(&mut self, name: &Name) -> usize {
+        match self.indices.get(name) {
+            Some(slot) => *slot,
+            None => {
+                let slot = self.names.len();
+                self.names.push(name.clone());
+                self.indices.insert(name.clone(), slot);
+                slot
+            }
+        }
+    }
+
+    /// Gets the names of the variables, by slot.
+    pub fn into_names(self) -> Vec<Name> {
+        self.names
+    }
+}
+
+/// Gets the name of the variable that holds the given parameter,
+/// e.g. `A$` for `A AS STRING`, `P` for `P AS Card`.
+pub fn parameter_name(parameter: &Parameter) -> Name {
+    fn name_of_type(bare_name: &BareName, param_type: &ParamType) -> Name {
+        match param_type {
+            ParamType::Bare => panic!("Unresolved param {:?}", bare_name),
+            ParamType::BuiltIn(q, _) => Name::qualified(bare_name.clone(), *q),
+            ParamType::UserDefined(_) => Name::bare(bare_name.clone()),
+            ParamType::Array(element_type) => name_of_type(bare_name, element_type),
+        }
+    }
+    name_of_type(parameter.as_bare_name(), parameter.var_type())
+}
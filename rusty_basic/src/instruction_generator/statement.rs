@@ -13,16 +13,40 @@ impl Visitor<Statements> for InstructionGenerator {
 
 impl Visitor<StatementPos> for InstructionGenerator {
     fn visit(&mut self, statement_pos: StatementPos) {
+        if let Statement::Comment(_) = &statement_pos.element {
+        } else {
+            self.mark_statement_address();
+        }
+        self.generate_statement_instructions(statement_pos);
+    }
+}
+
+impl InstructionGenerator {
+    /// Visits the statements of the program or of a SUB/FUNCTION body.
+    ///
+    /// The linter hoists the declarations of implicit variables to the top
+    /// of these blocks, positioned where each variable is first used.
+    /// The hoisted declarations are not marked as statements, because they
+    /// are out of source order (e.g. a debugger should not pause on them).
+    /// They can only fail by exceeding the execution limits, which an error
+    /// handler cannot handle, so they are not needed for `RESUME NEXT` either.
+    pub fn visit_hoisting_block(&mut self, block: Statements) {
+        let hoisted = hoisted_declarations(&block);
+        for (statement_pos, is_hoisted) in block.into_iter().zip(hoisted) {
+            if is_hoisted {
+                self.generate_statement_instructions(statement_pos);
+            } else {
+                self.visit(statement_pos);
+            }
+        }
+    }
+
+    fn generate_statement_instructions(&mut self, statement_pos: StatementPos) {
         let Positioned {
             element: statement,
             pos,
         } = statement_pos;
 
-        if let Statement::Comment(_) = &statement {
-        } else {
-            self.mark_statement_address();
-        }
-
         match statement {
             Statement::Assignment(a) => self.generate_assignment_instructions(a, pos),
             Statement::Const(_) => {
@@ -56,6 +80,25 @@ impl Visitor<StatementPos> for InstructionGenerator {
                     self.push(Instruction::OnErrorGoToZero, pos);
                 }
             },
+            Statement::OnEvent(on_event) => {
+                self.generate_expression_instructions(on_event.arg);
+                self.push(
+                    Instruction::OnEventGoSub(
+                        on_event.kind,
+                        AddressOrLabel::Unresolved(on_event.label),
+                    ),
+                    pos,
+                );
+            }
+            Statement::EventControl(event_control) => {
+                if let Some(arg) = event_control.arg {
+                    self.generate_expression_instructions(arg);
+                }
+                self.push(
+                    Instruction::EventControl(event_control.kind, event_control.action),
+                    pos,
+                );
+            }
             Statement::Label(name) => {
                 self.push(Instruction::Label(name), pos);
             }
@@ -98,6 +141,35 @@ impl Visitor<StatementPos> for InstructionGenerator {
             Statement::End | Statement::System => {
                 self.push(Instruction::Halt, pos);
             }
+            Statement::Stop => {
+                self.push(Instruction::Stop, pos);
+            }
+        }
+    }
+}
+
+/// Finds the hoisted declarations of implicit variables, among the `DIM` statements
+/// at the top of the block (the `DATA` statements of the program are moved before them).
+/// An explicit `DIM` statement is positioned before all the statements that follow it,
+/// while a hoisted declaration is positioned at or after the statement that uses the variable.
+fn hoisted_declarations(block: &Statements) -> Vec<bool> {
+    let key = |statement_pos: &StatementPos| (statement_pos.pos.row(), statement_pos.pos.col());
+    let top = block
+        .iter()
+        .take_while(|statement_pos| {
+            matches!(statement_pos.element, Statement::Dim(_))
+                || InstructionGenerator::is_data_statement(&statement_pos.element)
+        })
+        .count();
+    let mut min_following = block[top..].iter().map(key).min();
+    let mut result = vec![false; block.len()];
+    for index in (0..top).rev() {
+        let statement_pos = &block[index];
+        if let Statement::Dim(_) = &statement_pos.element {
+            result[index] = min_following.is_some_and(|min| min <= key(statement_pos));
+            min_following =
+                Some(min_following.map_or(key(statement_pos), |min| min.min(key(statement_pos))));
         }
     }
+    result
 }
@@ -72,7 +72,13 @@ impl SubprogramInfoRepository {
     }
 
     pub fn get_subprogram_info(&self, scope_name: &ScopeName) -> &SubprogramInfo {
-        self.map.get(scope_name).expect("Function/Sub not found")
+        self.find(scope_name).expect("Function/Sub not found")
+    }
+
+    /// Finds the info of the given subprogram. Native subprograms
+    /// are not implemented by the program, so they have no info.
+    pub fn find(&self, scope_name: &ScopeName) -> Option<&SubprogramInfo> {
+        self.map.get(scope_name)
     }
 }
 
@@ -1,6 +1,6 @@
 use rusty_common::NoPosContainer;
-use rusty_linter::core::lint;
-use rusty_parser::{UserDefinedTypes, parse};
+use rusty_linter::core::{LintErrorPos, LinterContext, lint, lint_tests};
+use rusty_parser::{Program, UserDefinedTypes, parse};
 
 use crate::instruction_generator::{
     Instruction, InstructionGeneratorResult, InstructionPos, generate_instructions,
@@ -9,9 +9,24 @@ use crate::instruction_generator::{
 
 pub fn generate_instructions_str_with_types(
     input: &str,
+) -> (InstructionGeneratorResult, UserDefinedTypes) {
+    generate_instructions_linted_with(input, lint)
+}
+
+/// Like [generate_instructions_str_with_types], but lints the program
+/// in order to run its unit tests, so that it can call the assertions.
+pub fn generate_instructions_str_in_tests(
+    input: &str,
+) -> (InstructionGeneratorResult, UserDefinedTypes) {
+    generate_instructions_linted_with(input, lint_tests)
+}
+
+fn generate_instructions_linted_with(
+    input: &str,
+    linter: impl FnOnce(Program) -> Result<(Program, LinterContext), LintErrorPos>,
 ) -> (InstructionGeneratorResult, UserDefinedTypes) {
     let program = parse(input);
-    let (linted_program, linter_context) = lint(program).expect("Linter should succeed");
+    let (linted_program, linter_context) = linter(program).expect("Linter should succeed");
     let (linter_names, user_defined_types) = unwrap_linter_context(linter_context);
     (
         generate_instructions(linted_program, linter_names),
@@ -1,5 +1,7 @@
+use std::rc::Rc;
+
 use rusty_common::AtPos;
-use rusty_parser::{BuiltInStyle, ExpressionType, ParamType, Parameter, TypeQualifier};
+use rusty_parser::{ExpressionType, TypeQualifier};
 use rusty_variant::Variant;
 
 use crate::instruction_generator::test_utils::*;
@@ -31,12 +33,14 @@ fn test_declaration() {
             Instruction::AllocateArrayIntoA(ExpressionType::BuiltIn(TypeQualifier::BangSingle))
                 .at_rc(2, 9),
             // store allocated array value into variable
-            Instruction::VarPathName(RootPath {
-                name: "X!".into(),
-                shared: false
-            })
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "X!".into()
+            )
             .at_rc(2, 9),
-            Instruction::CopyAToVarPath.at_rc(2, 9),
             Instruction::Halt.at_rc(u32::MAX, u32::MAX)
         ]
     );
@@ -63,20 +67,25 @@ fn test_assignment() {
             Instruction::AllocateArrayIntoA(ExpressionType::BuiltIn(TypeQualifier::PercentInteger))
                 .at_rc(2, 9),
             // store allocated array value into variable
-            Instruction::VarPathName(RootPath {
-                name: "X%".into(),
-                shared: false
-            })
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "X%".into()
+            )
             .at_rc(2, 9),
-            Instruction::CopyAToVarPath.at_rc(2, 9),
             // assignment to array
             // evaluate right side into A
             Instruction::LoadIntoA(Variant::VInteger(4)).at_rc(3, 13),
             // build name path
-            Instruction::VarPathName(RootPath {
-                name: "X%".into(),
-                shared: false
-            })
+            Instruction::VarPathName(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "X%".into()
+            )
             .at_rc(3, 5),
             Instruction::PushAToValueStack.at_rc(3, 8),
             Instruction::LoadIntoA(Variant::VInteger(2)).at_rc(3, 8), // loads into A, therefore needs PushRegisters before
@@ -108,19 +117,23 @@ fn test_assign_and_print_one_element() {
             Instruction::PushUnnamedByVal,
             // allocate array into A
             Instruction::AllocateArrayIntoA(ExpressionType::BuiltIn(TypeQualifier::BangSingle)),
-            Instruction::VarPathName(RootPath {
-                name: "A!".into(),
-                shared: false
-            }),
-            Instruction::CopyAToVarPath,
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "A!".into()
+            ),
             // assign to array element
             // evaluate right side into A
-            Instruction::LoadIntoA(Variant::VInteger(42)),
-            Instruction::Cast(TypeQualifier::BangSingle),
-            Instruction::VarPathName(RootPath {
-                name: "A!".into(),
-                shared: false
-            }),
+            Instruction::LoadIntoA(Variant::VSingle(42.0)),
+            Instruction::VarPathName(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "A!".into()
+            ),
             Instruction::PushAToValueStack,
             Instruction::LoadIntoA(Variant::VInteger(1)),
             Instruction::VarPathIndex,
@@ -130,10 +143,13 @@ fn test_assign_and_print_one_element() {
             Instruction::PrintSetPrinterType(PrinterType::Print),
             Instruction::LoadIntoA(Variant::VInteger(0)),
             Instruction::PrintSetFormatStringFromA,
-            Instruction::VarPathName(RootPath {
-                name: "A!".into(),
-                shared: false
-            }),
+            Instruction::VarPathName(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "A!".into()
+            ),
             Instruction::PushAToValueStack,
             Instruction::LoadIntoA(Variant::VInteger(1)),
             Instruction::VarPathIndex,
@@ -169,36 +185,38 @@ fn test_pass_param_to_sub() {
             Instruction::PushUnnamedByVal,
             // allocate array into A
             Instruction::AllocateArrayIntoA(ExpressionType::BuiltIn(TypeQualifier::BangSingle)),
-            Instruction::VarPathName(RootPath {
-                name: "A!".into(),
-                shared: false
-            }),
-            Instruction::CopyAToVarPath,
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "A!".into()
+            ),
             // call sub
             Instruction::BeginCollectArguments,
-            Instruction::VarPathName(RootPath {
-                name: "A!".into(),
-                shared: false
-            }),
+            Instruction::VarPathName(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "A!".into()
+            ),
             Instruction::CopyVarPathToA,
             Instruction::PopVarPath,
-            Instruction::PushNamed(Parameter::new(
-                "values".into(),
-                ParamType::Array(Box::new(ParamType::BuiltIn(
-                    TypeQualifier::BangSingle,
-                    BuiltInStyle::Compact
-                )))
-            )),
+            Instruction::PushNamed(Rc::new("values!".into())),
             Instruction::PushStack,
-            Instruction::PushRet(16),
-            Instruction::Jump(AddressOrLabel::Resolved(22)),
+            Instruction::PushRet(15),
+            Instruction::Jump(AddressOrLabel::Resolved(21)),
             Instruction::EnqueueToReturnStack(0),
             Instruction::PopStack,
             Instruction::DequeueFromReturnStack,
-            Instruction::VarPathName(RootPath {
-                name: "A!".into(),
-                shared: false
-            }),
+            Instruction::VarPathName(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "A!".into()
+            ),
             Instruction::CopyAToVarPath,
             Instruction::Halt,
             // sub implementation
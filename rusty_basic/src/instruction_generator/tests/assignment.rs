@@ -12,21 +12,24 @@ fn test_assignment() {
         [
             // implicit dim
             Instruction::AllocateBuiltIn(TypeQualifier::BangSingle).at_rc(1, 1),
-            Instruction::VarPathName(RootPath {
-                name: "X!".into(),
-                shared: false
-            })
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "X!".into()
+            )
             .at_rc(1, 1),
-            Instruction::CopyAToVarPath.at_rc(1, 1),
-            // assignment with casting
-            Instruction::LoadIntoA(Variant::VInteger(1)).at_rc(1, 5),
-            Instruction::Cast(TypeQualifier::BangSingle).at_rc(1, 5),
-            Instruction::VarPathName(RootPath {
-                name: "X!".into(),
-                shared: false
-            })
+            // assignment with the casting folded into the constant
+            Instruction::LoadIntoA(Variant::VSingle(1.0)).at_rc(1, 5),
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "X!".into()
+            )
             .at_rc(1, 1),
-            Instruction::CopyAToVarPath.at_rc(1, 1),
             Instruction::Halt.at_rc(u32::MAX, u32::MAX)
         ]
     );
@@ -39,18 +42,22 @@ fn test_assignment_no_cast_implicit_variable() {
         [
             // implicit dim
             Instruction::AllocateBuiltIn(TypeQualifier::PercentInteger),
-            Instruction::VarPathName(RootPath {
-                name: "X%".into(),
-                shared: false
-            }),
-            Instruction::CopyAToVarPath,
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "X%".into()
+            ),
             // assign
             Instruction::LoadIntoA(Variant::VInteger(1)),
-            Instruction::VarPathName(RootPath {
-                name: "X%".into(),
-                shared: false
-            }),
-            Instruction::CopyAToVarPath,
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "X%".into()
+            ),
             Instruction::Halt
         ]
     );
@@ -67,18 +74,22 @@ fn test_assignment_no_cast_explicit_variable() {
         [
             // dim
             Instruction::AllocateBuiltIn(TypeQualifier::PercentInteger),
-            Instruction::VarPathName(RootPath {
-                name: "X%".into(),
-                shared: false
-            }),
-            Instruction::CopyAToVarPath,
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "X%".into()
+            ),
             // assign
             Instruction::LoadIntoA(Variant::VInteger(1)),
-            Instruction::VarPathName(RootPath {
-                name: "X%".into(),
-                shared: false
-            }),
-            Instruction::CopyAToVarPath,
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "X%".into()
+            ),
             Instruction::Halt
         ]
     );
@@ -95,25 +106,31 @@ fn test_assignment_no_cast_implicit_variable_implicit_dim_is_only_once() {
         [
             // implicit dim
             Instruction::AllocateBuiltIn(TypeQualifier::PercentInteger),
-            Instruction::VarPathName(RootPath {
-                name: "X%".into(),
-                shared: false
-            }),
-            Instruction::CopyAToVarPath,
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "X%".into()
+            ),
             // assign
             Instruction::LoadIntoA(Variant::VInteger(1)),
-            Instruction::VarPathName(RootPath {
-                name: "X%".into(),
-                shared: false
-            }),
-            Instruction::CopyAToVarPath,
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "X%".into()
+            ),
             // assign
             Instruction::LoadIntoA(Variant::VInteger(2)),
-            Instruction::VarPathName(RootPath {
-                name: "X%".into(),
-                shared: false
-            }),
-            Instruction::CopyAToVarPath,
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "X%".into()
+            ),
             Instruction::Halt
         ]
     );
@@ -126,25 +143,57 @@ fn test_assignment_binary_plus() {
         [
             // implicit dim
             Instruction::AllocateBuiltIn(TypeQualifier::PercentInteger),
-            Instruction::VarPathName(RootPath {
-                name: "X%".into(),
-                shared: false
-            }),
-            Instruction::CopyAToVarPath,
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "X%".into()
+            ),
             // evaluation of binary expression
             Instruction::LoadIntoA(Variant::VInteger(1)),
-            Instruction::PushAToValueStack,
-            Instruction::LoadIntoA(Variant::VSingle(2.1)),
-            Instruction::CopyAToB,
-            Instruction::PopValueStackIntoA,
+            Instruction::LoadIntoB(Variant::VSingle(2.1)),
             Instruction::Plus,
             // assignment with casting
             Instruction::Cast(TypeQualifier::PercentInteger),
-            Instruction::VarPathName(RootPath {
-                name: "X%".into(),
-                shared: false
-            }),
-            Instruction::CopyAToVarPath,
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "X%".into()
+            ),
+            Instruction::Halt
+        ]
+    );
+}
+
+#[test]
+fn test_assignment_binary_plus_same_type_is_typed() {
+    assert_eq!(
+        generate_instructions_str_no_pos("X% = 1 + 2"),
+        [
+            // implicit dim
+            Instruction::AllocateBuiltIn(TypeQualifier::PercentInteger),
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "X%".into()
+            ),
+            // evaluation of binary expression
+            Instruction::LoadIntoA(Variant::VInteger(1)),
+            Instruction::LoadIntoB(Variant::VInteger(2)),
+            Instruction::PlusTyped(TypeQualifier::PercentInteger),
+            // assignment without casting
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "X%".into()
+            ),
             Instruction::Halt
         ]
     );
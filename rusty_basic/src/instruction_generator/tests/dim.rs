@@ -13,12 +13,14 @@ fn test_declaration_built_in_bare() {
         generate_instructions_str(input),
         [
             Instruction::AllocateBuiltIn(TypeQualifier::BangSingle).at_rc(2, 9),
-            Instruction::VarPathName(RootPath {
-                name: "X!".into(),
-                shared: false
-            })
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "X!".into()
+            )
             .at_rc(2, 9),
-            Instruction::CopyAToVarPath.at_rc(2, 9),
             Instruction::Halt.at_rc(u32::MAX, u32::MAX)
         ]
     );
@@ -33,12 +35,14 @@ fn test_declaration_built_in_qualified() {
         generate_instructions_str(input),
         [
             Instruction::AllocateBuiltIn(TypeQualifier::PercentInteger).at_rc(2, 9),
-            Instruction::VarPathName(RootPath {
-                name: "X%".into(),
-                shared: false
-            })
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "X%".into()
+            )
             .at_rc(2, 9),
-            Instruction::CopyAToVarPath.at_rc(2, 9),
             Instruction::Halt.at_rc(u32::MAX, u32::MAX)
         ]
     );
@@ -53,12 +57,14 @@ fn test_declaration_built_in_extended() {
         generate_instructions_str(input),
         [
             Instruction::AllocateBuiltIn(TypeQualifier::HashDouble).at_rc(2, 9),
-            Instruction::VarPathName(RootPath {
-                name: "X#".into(),
-                shared: false
-            })
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "X#".into()
+            )
             .at_rc(2, 9),
-            Instruction::CopyAToVarPath.at_rc(2, 9),
             Instruction::Halt.at_rc(u32::MAX, u32::MAX)
         ]
     );
@@ -73,12 +79,14 @@ fn test_declaration_built_in_fixed_length_string() {
         generate_instructions_str(input),
         [
             Instruction::AllocateFixedLengthString(5).at_rc(2, 9),
-            Instruction::VarPathName(RootPath {
-                name: "X$".into(),
-                shared: false
-            })
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "X$".into()
+            )
             .at_rc(2, 9),
-            Instruction::CopyAToVarPath.at_rc(2, 9),
             Instruction::Halt.at_rc(u32::MAX, u32::MAX)
         ]
     );
@@ -97,12 +105,14 @@ fn test_declaration_user_defined() {
         generate_instructions_str(input),
         [
             Instruction::AllocateUserDefined("Card".into()).at_rc(6, 9),
-            Instruction::VarPathName(RootPath {
-                name: "X".into(),
-                shared: false
-            })
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "X".into()
+            )
             .at_rc(6, 9),
-            Instruction::CopyAToVarPath.at_rc(6, 9),
             Instruction::Halt.at_rc(u32::MAX, u32::MAX)
         ]
     );
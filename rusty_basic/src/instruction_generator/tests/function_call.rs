@@ -1,5 +1,5 @@
 use rusty_common::AtPos;
-use rusty_parser::{BuiltInFunction, Name, TypeQualifier};
+use rusty_parser::{BuiltInFunction, TypeQualifier};
 
 use crate::instruction_generator::test_utils::*;
 use crate::instruction_generator::{Instruction, RootPath};
@@ -14,26 +14,33 @@ fn test_built_in_len_with_string_variable_parameter() {
         [
             // implicit dim A$
             Instruction::AllocateBuiltIn(TypeQualifier::DollarString).at_rc(2, 13),
-            Instruction::VarPathName(RootPath {
-                name: "A$".into(),
-                shared: false
-            })
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "A$".into()
+            )
             .at_rc(2, 13),
-            Instruction::CopyAToVarPath.at_rc(2, 13),
             // implicit dim X
             Instruction::AllocateBuiltIn(TypeQualifier::BangSingle).at_rc(2, 5),
-            Instruction::VarPathName(RootPath {
-                name: "X!".into(),
-                shared: false
-            })
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 1,
+                    shared: false
+                },
+                "X!".into()
+            )
             .at_rc(2, 5),
-            Instruction::CopyAToVarPath.at_rc(2, 5),
             // function call
             Instruction::BeginCollectArguments.at_rc(2, 9),
-            Instruction::VarPathName(RootPath {
-                name: "A$".into(),
-                shared: false
-            })
+            Instruction::VarPathName(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "A$".into()
+            )
             .at_rc(2, 13),
             Instruction::CopyVarPathToA.at_rc(2, 13),
             Instruction::PushUnnamedByRef.at_rc(2, 13),
@@ -41,27 +48,30 @@ fn test_built_in_len_with_string_variable_parameter() {
             Instruction::BuiltInFunction(BuiltInFunction::Len).at_rc(2, 9),
             // after function call
             Instruction::EnqueueToReturnStack(0).at_rc(2, 13),
-            Instruction::StashFunctionReturnValue(Name::from("LEN%").demand_qualified())
-                .at_rc(2, 9),
+            Instruction::StashFunctionReturnValue(1).at_rc(2, 9),
             Instruction::PopStack.at_rc(2, 9),
             // assign to by-ref variables
             Instruction::DequeueFromReturnStack.at_rc(2, 13),
-            Instruction::VarPathName(RootPath {
-                name: "A$".into(),
-                shared: false
-            })
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 0,
+                    shared: false
+                },
+                "A$".into()
+            )
             .at_rc(2, 13),
-            Instruction::CopyAToVarPath.at_rc(2, 13),
             // cast result
             Instruction::UnStashFunctionReturnValue.at_rc(2, 9),
             Instruction::Cast(TypeQualifier::BangSingle).at_rc(2, 9),
             // assignment
-            Instruction::VarPathName(RootPath {
-                name: "X!".into(),
-                shared: false
-            })
+            Instruction::CopyAToVariable(
+                RootPath {
+                    slot: 1,
+                    shared: false
+                },
+                "X!".into()
+            )
             .at_rc(2, 5),
-            Instruction::CopyAToVarPath.at_rc(2, 5),
             Instruction::Halt.at_rc(u32::MAX, u32::MAX)
         ]
     );
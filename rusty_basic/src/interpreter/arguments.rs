@@ -1,9 +1,13 @@
+use std::rc::Rc;
 use std::slice::Iter;
 use std::vec::IntoIter;
 
-use rusty_parser::Parameter;
+use rusty_parser::Name;
 use rusty_variant::Variant;
 
+use crate::bytecode::BytecodeError;
+use crate::bytecode::decoder::Decoder;
+use crate::bytecode::encoder::Encoder;
 use crate::instruction_generator::Path;
 
 #[derive(Debug, Default)]
@@ -14,7 +18,10 @@ pub struct Arguments {
 #[derive(Debug)]
 pub struct ArgumentInfo {
     pub value: Variant,
-    pub param_name: Option<Parameter>,
+    /// The name of the parameter, as given by [crate::instruction_generator::parameter_name].
+    /// It is shared with the [crate::instruction_generator::Instruction::PushNamed]
+    /// instruction, so that pushing an argument does not allocate a new name.
+    pub param_name: Option<Rc<Name>>,
     pub arg_path: Option<Path>,
 }
 
@@ -35,7 +42,7 @@ impl Arguments {
         });
     }
 
-    pub fn push_named(&mut self, parameter_name: Parameter, arg: Variant) {
+    pub fn push_named(&mut self, parameter_name: Rc<Name>, arg: Variant) {
         self.v.push(ArgumentInfo {
             value: arg,
             param_name: Some(parameter_name),
@@ -50,4 +57,29 @@ impl Arguments {
     pub fn into_iter(self) -> IntoIter<ArgumentInfo> {
         self.v.into_iter()
     }
+
+    /// Writes the arguments into a snapshot.
+    pub fn encode(&self, encoder: &mut Encoder) {
+        encoder.usize(self.v.len());
+        for arg in &self.v {
+            encoder.variant(&arg.value);
+            encoder.option(arg.param_name.as_deref(), Encoder::name);
+            encoder.option(arg.arg_path.as_ref(), Encoder::path);
+        }
+    }
+
+    /// Reads the arguments from a snapshot.
+    pub fn decode(decoder: &mut Decoder) -> Result<Self, BytecodeError> {
+        let len = decoder.len()?;
+        let v = (0..len)
+            .map(|_| {
+                Ok(ArgumentInfo {
+                    value: decoder.variant()?,
+                    param_name: decoder.option(Decoder::name)?.map(Rc::new),
+                    arg_path: decoder.option(Decoder::path)?,
+                })
+            })
+            .collect::<Result<_, BytecodeError>>()?;
+        Ok(Self { v })
+    }
 }
@@ -0,0 +1,49 @@
+use rusty_linter::core::QBNumberCast;
+use rusty_runtime::VariantCasts;
+
+use crate::RuntimeError;
+use crate::interpreter::interpreter_trait::InterpreterTrait;
+
+pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
+    let condition: bool = interpreter.context()[0].try_cast()?;
+    if condition {
+        Ok(())
+    } else {
+        let message = match interpreter.context().variables().get(1) {
+            Some(v) => v.to_str_unchecked().to_owned(),
+            None => "Condition is false.".to_owned(),
+        };
+        Err(RuntimeError::AssertionFailed(message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::test_utils::*;
+    use crate::{RuntimeError, assert_interpreter_err_in_tests};
+
+    #[test]
+    fn test_assert_true() {
+        interpret_in_tests("ASSERT 1 < 2");
+    }
+
+    #[test]
+    fn test_assert_false() {
+        assert_interpreter_err_in_tests!(
+            "ASSERT 2 < 1",
+            RuntimeError::AssertionFailed("Condition is false.".to_owned()),
+            1,
+            1
+        );
+    }
+
+    #[test]
+    fn test_assert_false_with_message() {
+        assert_interpreter_err_in_tests!(
+            r#"ASSERT 0, "not " + "ok""#,
+            RuntimeError::AssertionFailed("not ok".to_owned()),
+            1,
+            1
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use std::cmp::Ordering;
+
+use crate::RuntimeError;
+use crate::interpreter::format_value;
+use crate::interpreter::interpreter_trait::InterpreterTrait;
+
+pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
+    let expected = &interpreter.context()[0];
+    let actual = &interpreter.context()[1];
+    if expected.try_cmp(actual)? == Ordering::Equal {
+        Ok(())
+    } else {
+        Err(RuntimeError::AssertionFailed(format!(
+            "Expected {}, but was {}",
+            format_value(expected),
+            format_value(actual)
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::test_utils::*;
+    use crate::{RuntimeError, assert_interpreter_err_in_tests};
+
+    #[test]
+    fn test_assert_equal_numbers() {
+        interpret_in_tests("ASSERTEQUAL 2, 1 + 1");
+        interpret_in_tests("ASSERTEQUAL 2, 2.0");
+    }
+
+    #[test]
+    fn test_assert_equal_strings() {
+        interpret_in_tests(r#"ASSERTEQUAL "ab", "a" + "b""#);
+    }
+
+    #[test]
+    fn test_assert_equal_numbers_differ() {
+        assert_interpreter_err_in_tests!(
+            "X = 3\r\nASSERTEQUAL 2, X",
+            RuntimeError::AssertionFailed("Expected 2, but was 3".to_owned()),
+            2,
+            1
+        );
+    }
+
+    #[test]
+    fn test_assert_equal_strings_differ() {
+        assert_interpreter_err_in_tests!(
+            r#"ASSERTEQUAL "a", "b""#,
+            RuntimeError::AssertionFailed(r#"Expected "a", but was "b""#.to_owned()),
+            1,
+            1
+        );
+    }
+}
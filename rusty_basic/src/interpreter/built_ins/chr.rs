@@ -1,13 +1,13 @@
 use rusty_linter::core::QBNumberCast;
 use rusty_parser::BuiltInFunction;
+use rusty_runtime::functions;
 
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
 
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
     let i: i32 = interpreter.context()[0].try_cast()?;
-    let mut s: String = String::new();
-    s.push((i as u8) as char);
+    let s: String = functions::chr(i);
     interpreter
         .context_mut()
         .set_built_in_function_result(BuiltInFunction::Chr, s);
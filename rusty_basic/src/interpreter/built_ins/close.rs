@@ -1,8 +1,8 @@
 use rusty_parser::FileHandle;
+use rusty_runtime::VariantCasts;
 
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
-use crate::interpreter::variant_casts::VariantCasts;
 
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
     let file_handles: Vec<FileHandle> = interpreter
@@ -1,6 +1,7 @@
+use rusty_runtime::Printer;
+
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
-use crate::interpreter::io::Printer;
 
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
     match interpreter.screen().get_view_print() {
@@ -0,0 +1,39 @@
+use rusty_parser::BuiltInFunction;
+
+use crate::RuntimeError;
+use crate::interpreter::Stdlib;
+use crate::interpreter::interpreter_trait::InterpreterTrait;
+
+pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
+    // QBasic converts the command line to uppercase
+    let result = interpreter.stdlib().command_line().to_uppercase();
+    interpreter
+        .context_mut()
+        .set_built_in_function_result(BuiltInFunction::Command, result);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_prints;
+    use crate::interpreter::interpreter_trait::InterpreterTrait;
+    use crate::interpreter::test_utils::*;
+
+    #[test]
+    fn test_command_without_arguments() {
+        assert_prints!("PRINT \"[\"; COMMAND$; \"]\"", "[]");
+    }
+
+    #[test]
+    fn test_command_is_uppercase() {
+        let stdlib = MockStdlib {
+            command_line: "hello /w World.txt".to_owned(),
+            ..Default::default()
+        };
+        let mut interpreter = interpret_with_env("PRINT COMMAND$", stdlib);
+        assert_eq!(
+            interpreter.stdout().output_lines(),
+            vec!["HELLO /W WORLD.TXT"]
+        );
+    }
+}
@@ -1,10 +1,9 @@
 use rusty_parser::BuiltInFunction;
+use rusty_runtime::{VariantCasts, to_ascii_bytes};
 use rusty_variant::bytes_to_f64;
 
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
-use crate::interpreter::string_utils::to_ascii_bytes;
-use crate::interpreter::variant_casts::VariantCasts;
 
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
     let s = interpreter.context()[0].to_str_unchecked();
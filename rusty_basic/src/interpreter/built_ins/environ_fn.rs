@@ -1,11 +1,12 @@
 use rusty_parser::BuiltInFunction;
+use rusty_runtime::VariantCasts;
 
 use crate::RuntimeError;
 use crate::interpreter::Stdlib;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
-use crate::interpreter::variant_casts::VariantCasts;
 
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
+    interpreter.limits().file_policy.check_environ(false)?;
     let env_var_name: &str = interpreter.context()[0].to_str_unchecked();
     let result = interpreter.stdlib().get_env_var(env_var_name);
     interpreter
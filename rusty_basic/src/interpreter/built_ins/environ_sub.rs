@@ -1,9 +1,11 @@
+use rusty_runtime::VariantCasts;
+
 use crate::RuntimeError;
 use crate::interpreter::Stdlib;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
-use crate::interpreter::variant_casts::VariantCasts;
 
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
+    interpreter.limits().file_policy.check_environ(true)?;
     let s: &str = interpreter.context()[0].to_str_unchecked();
     let parts: Vec<&str> = s.split('=').collect();
     if parts.len() != 2 {
@@ -1,9 +1,8 @@
 use rusty_parser::{BuiltInFunction, FileHandle};
+use rusty_runtime::{Input, VariantCasts};
 
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
-use crate::interpreter::io::Input;
-use crate::interpreter::variant_casts::VariantCasts;
 
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
     let file_handle: FileHandle = interpreter.context()[0].to_file_handle()?;
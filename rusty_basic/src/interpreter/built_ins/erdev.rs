@@ -0,0 +1,52 @@
+use rusty_parser::BuiltInFunction;
+
+use crate::RuntimeError;
+use crate::interpreter::interpreter_trait::InterpreterTrait;
+
+pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
+    let error_code: i32 = interpreter.get_last_device_error_code().unwrap_or_default();
+    interpreter
+        .context_mut()
+        .set_built_in_function_result(BuiltInFunction::ErDev, error_code);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_prints;
+    use crate::interpreter::interpreter_trait::InterpreterTrait;
+
+    #[test]
+    fn test_erdev_without_error() {
+        assert_prints!("PRINT ERDEV", "0");
+    }
+
+    #[test]
+    fn test_erdev_is_not_set_by_other_errors() {
+        let input = r#"
+        ON ERROR GOTO ErrTrap
+        ERROR 5
+        END
+
+        ErrTrap:
+            PRINT ERDEV
+            RESUME NEXT
+        "#;
+        assert_prints!(input, "0");
+    }
+
+    #[test]
+    fn test_erdev_survives_resume() {
+        let input = r#"
+        ON ERROR GOTO ErrTrap
+        ERROR 57
+        PRINT ERR; ERDEV
+        END
+
+        ErrTrap:
+            PRINT ERR; ERDEV
+            RESUME NEXT
+        "#;
+        assert_prints!(input, "57  57", "0  57");
+    }
+}
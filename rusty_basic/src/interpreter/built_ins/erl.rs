@@ -0,0 +1,58 @@
+use rusty_parser::BuiltInFunction;
+
+use crate::RuntimeError;
+use crate::interpreter::interpreter_trait::InterpreterTrait;
+
+// Line numbers are not supported, so ERL returns the source line instead.
+pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
+    let error_line: i32 = interpreter.get_last_error_line().unwrap_or_default() as i32;
+    interpreter
+        .context_mut()
+        .set_built_in_function_result(BuiltInFunction::Erl, error_line);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_prints;
+    use crate::interpreter::interpreter_trait::InterpreterTrait;
+
+    #[test]
+    fn test_erl_without_error() {
+        assert_prints!("PRINT ERL", "0");
+    }
+
+    #[test]
+    fn test_erl() {
+        let input = r#"
+        ON ERROR GOTO ErrTrap
+        PRINT "before"
+        X = 1 / 0
+        PRINT ERL
+        END
+
+        ErrTrap:
+            PRINT ERL
+            RESUME NEXT
+        "#;
+        assert_prints!(input, "before", "4", "0");
+    }
+
+    #[test]
+    fn test_erl_inside_sub() {
+        let input = r#"
+        ON ERROR GOTO ErrTrap
+        Oops
+        END
+
+        SUB Oops
+            ERROR 42
+        END SUB
+
+        ErrTrap:
+            PRINT ERR; ERL
+            RESUME NEXT
+        "#;
+        assert_prints!(input, "42  7");
+    }
+}
@@ -0,0 +1,87 @@
+use rusty_linter::core::QBNumberCast;
+
+use crate::RuntimeError;
+use crate::interpreter::interpreter_trait::InterpreterTrait;
+
+pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
+    let error_code: i32 = interpreter.context()[0].try_cast()?;
+    if (1..=255).contains(&error_code) {
+        Err(RuntimeError::from_code(error_code))
+    } else {
+        Err(RuntimeError::IllegalFunctionCall)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::interpreter_trait::InterpreterTrait;
+    use crate::{RuntimeError, assert_interpreter_err, assert_prints};
+
+    #[test]
+    fn test_unhandled_error_with_variant() {
+        assert_interpreter_err!("ERROR 53", RuntimeError::FileNotFound, 1, 1);
+    }
+
+    #[test]
+    fn test_unhandled_error_without_variant() {
+        assert_interpreter_err!("ERROR 200", RuntimeError::ErrorCode(200), 1, 1);
+    }
+
+    #[test]
+    fn test_error_code_out_of_range() {
+        assert_interpreter_err!("ERROR 0", RuntimeError::IllegalFunctionCall, 1, 1);
+        assert_interpreter_err!("ERROR 256", RuntimeError::IllegalFunctionCall, 1, 1);
+    }
+
+    #[test]
+    fn test_handled_user_defined_error() {
+        let input = r#"
+        ON ERROR GOTO ErrTrap
+        ERROR 200
+        PRINT "after"
+        END
+
+        ErrTrap:
+            PRINT ERR
+            RESUME NEXT
+        "#;
+        assert_prints!(input, "200", "after");
+    }
+
+    #[test]
+    fn test_handled_built_in_error() {
+        let input = r#"
+        ON ERROR GOTO ErrTrap
+        X = 5
+        ERROR X + 1
+        END
+
+        ErrTrap:
+            PRINT ERR
+            RESUME NEXT
+        "#;
+        assert_prints!(input, "6");
+    }
+
+    #[test]
+    fn test_resume_next_inside_sub() {
+        let input = r#"
+        X = 1
+        ON ERROR GOTO ErrTrap
+        Oops
+        PRINT X
+        END
+
+        SUB Oops
+            X = 2
+            ERROR 42
+            PRINT "in sub"; X
+        END SUB
+
+        ErrTrap:
+            PRINT "trap"
+            RESUME NEXT
+        "#;
+        assert_prints!(input, "trap", "in sub 2", "1");
+    }
+}
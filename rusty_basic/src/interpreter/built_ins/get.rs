@@ -1,11 +1,9 @@
-use rusty_parser::{BareName, FileHandle, TypeQualifier};
+use rusty_parser::{BareName, FileHandle, Name, TypeQualifier};
+use rusty_runtime::{Field, VariantCasts, to_ascii_string};
 use rusty_variant::Variant;
 
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
-use crate::interpreter::io::Field;
-use crate::interpreter::string_utils::to_ascii_string;
-use crate::interpreter::variant_casts::VariantCasts;
 
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
     let handle: FileHandle = interpreter.context()[0].to_file_handle()?;
@@ -23,7 +21,7 @@ pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError>
             interpreter
                 .context_mut()
                 .caller_variables_mut()
-                .insert_built_in(bare_name, TypeQualifier::DollarString, v);
+                .set_by_name(Name::qualified(bare_name, TypeQualifier::DollarString), v);
             // shift to next offset
             start += width;
         }
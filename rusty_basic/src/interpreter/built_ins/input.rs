@@ -1,12 +1,11 @@
 use std::convert::TryFrom;
 
-use rusty_linter::core::qualifier_of_variant;
 use rusty_parser::{FileHandle, TypeQualifier};
+use rusty_runtime::{Input, parse_input};
 use rusty_variant::Variant;
 
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
-use crate::interpreter::io::Input;
 
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
     let mut file_handle: FileHandle = FileHandle::default();
@@ -45,12 +44,7 @@ fn do_input_one_var<S: InterpreterTrait>(
 ) -> Result<(), RuntimeError> {
     let raw_input: String = raw_input(interpreter, file_handle)?;
     let q: TypeQualifier = qualifier(interpreter, index)?;
-    let new_value: Variant = match q {
-        TypeQualifier::BangSingle => Variant::from(parse_single_input(raw_input)?),
-        TypeQualifier::DollarString => Variant::from(raw_input),
-        TypeQualifier::PercentInteger => Variant::from(parse_int_input(raw_input)?),
-        _ => todo!("INPUT type {} not supported yet", q),
-    };
+    let new_value: Variant = parse_input(raw_input, q)?;
     interpreter.context_mut()[index] = new_value;
     Ok(())
 }
@@ -73,25 +67,7 @@ fn qualifier<S: InterpreterTrait>(
     interpreter: &S,
     index: usize,
 ) -> Result<TypeQualifier, RuntimeError> {
-    qualifier_of_variant(&interpreter.context()[index]).map_err(RuntimeError::from)
-}
-
-fn parse_single_input(s: String) -> Result<f32, RuntimeError> {
-    if s.is_empty() {
-        Ok(0.0)
-    } else {
-        s.parse::<f32>()
-            .map_err(|e| RuntimeError::Other(format!("Could not parse {} as float: {}", s, e)))
-    }
-}
-
-fn parse_int_input(s: String) -> Result<i32, RuntimeError> {
-    if s.is_empty() {
-        Ok(0)
-    } else {
-        s.parse::<i32>()
-            .map_err(|e| RuntimeError::Other(format!("Could not parse {} as int: {}", s, e)))
-    }
+    rusty_runtime::qualifier(&interpreter.context()[index])
 }
 
 #[cfg(test)]
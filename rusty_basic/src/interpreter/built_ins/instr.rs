@@ -1,20 +1,20 @@
 use rusty_parser::BuiltInFunction;
+use rusty_runtime::{VariantCasts, functions};
 use rusty_variant::Variant;
 
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
-use crate::interpreter::variant_casts::VariantCasts;
 
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
     let a: &Variant = &interpreter.context()[0];
     let b: &Variant = &interpreter.context()[1];
     let result: i32 = match interpreter.context().variables().get(2) {
-        Some(c) => do_instr(
+        Some(c) => functions::instr(
             a.to_positive_int()?,
             b.to_str_unchecked(),
             c.to_str_unchecked(),
-        )?,
-        None => do_instr(1, a.to_str_unchecked(), b.to_str_unchecked())?,
+        ),
+        None => functions::instr(1, a.to_str_unchecked(), b.to_str_unchecked()),
     };
     interpreter
         .context_mut()
@@ -22,25 +22,6 @@ pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError>
     Ok(())
 }
 
-fn do_instr(start: usize, hay: &str, needle: &str) -> Result<i32, RuntimeError> {
-    debug_assert!(start >= 1);
-    if hay.is_empty() {
-        Ok(0)
-    } else if needle.is_empty() {
-        Ok(1)
-    } else {
-        let mut i: usize = start - 1;
-        while i + needle.len() <= hay.len() {
-            let sub = hay.get(i..(i + needle.len())).unwrap();
-            if sub == needle {
-                return Ok((i as i32) + 1);
-            }
-            i += 1;
-        }
-        Ok(0)
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use rusty_common::Position;
@@ -0,0 +1,93 @@
+use rusty_linter::core::QBNumberCast;
+use rusty_runtime::{Printer, VariantCasts};
+
+use crate::RuntimeError;
+use crate::interpreter::interpreter_trait::InterpreterTrait;
+
+pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
+    if interpreter.context().variables().len() > 0 {
+        let key_number: i32 = interpreter.context()[0].try_cast()?;
+        let text: String = interpreter.context()[1].to_str_unchecked().to_owned();
+        interpreter.keyboard_mut().define_key(key_number, &text)
+    } else {
+        list(interpreter)
+    }
+}
+
+fn list<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
+    for (name, text) in interpreter.keyboard_mut().soft_keys() {
+        interpreter.stdout().print(&format!("{} {}", name, text))?;
+        interpreter.stdout().println()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent};
+
+    use crate::interpreter::interpreter_trait::InterpreterTrait;
+    use crate::interpreter::test_utils::*;
+    use crate::{RuntimeError, assert_interpreter_err, assert_prints};
+
+    #[test]
+    fn soft_key_is_returned_by_inkey() {
+        let input = r#"
+        KEY 2, "RUN"
+        DO
+            K$ = INKEY$
+            IF K$ <> "" THEN PRINT K$
+        LOOP UNTIL K$ = "N"
+        "#;
+        let mut interpreter = interpret_with_env_and_keys(
+            input,
+            MockStdlib::default(),
+            &[KeyEvent::from(KeyCode::F(2))],
+        );
+        assert_eq!(interpreter.stdout().output_lines(), vec!["R", "U", "N"]);
+    }
+
+    #[test]
+    fn key_list() {
+        let input = r#"
+        KEY 1, "LIST"
+        KEY 31, "F12 text"
+        KEY LIST
+        "#;
+        assert_prints!(
+            input,
+            "F1 LIST",
+            "F2",
+            "F3",
+            "F4",
+            "F5",
+            "F6",
+            "F7",
+            "F8",
+            "F9",
+            "F10",
+            "F11",
+            "F12 F12 text"
+        );
+    }
+
+    #[test]
+    fn soft_key_is_truncated() {
+        let input = r#"
+        KEY 1, "12345678901234567890"
+        KEY LIST
+        "#;
+        let mut interpreter = interpret(input);
+        assert_eq!(interpreter.stdout().output_lines()[0], "F1 123456789012345");
+    }
+
+    #[test]
+    fn user_defined_key_must_have_two_characters() {
+        assert_interpreter_err!(r#"KEY 15, "A""#, RuntimeError::IllegalFunctionCall, 1, 1);
+    }
+
+    #[test]
+    fn key_number_out_of_range() {
+        assert_interpreter_err!(r#"KEY 11, "A""#, RuntimeError::IllegalFunctionCall, 1, 1);
+    }
+}
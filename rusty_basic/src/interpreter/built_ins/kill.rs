@@ -1,10 +1,12 @@
+use rusty_runtime::VariantCasts;
+
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
-use crate::interpreter::variant_casts::VariantCasts;
 
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
     let file_name: &str = interpreter.context()[0].to_str_unchecked();
-    std::fs::remove_file(file_name).map_err(RuntimeError::from)
+    let path = interpreter.limits().file_policy.resolve(file_name, true)?;
+    std::fs::remove_file(path).map_err(RuntimeError::from)
 }
 
 #[cfg(test)]
@@ -1,8 +1,8 @@
 use rusty_parser::BuiltInFunction;
+use rusty_runtime::QByteSize;
 use rusty_variant::Variant;
 
 use crate::RuntimeError;
-use crate::interpreter::byte_size::QByteSize;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
 
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
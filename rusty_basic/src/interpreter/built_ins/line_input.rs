@@ -1,11 +1,11 @@
 use std::convert::TryFrom;
 
 use rusty_parser::FileHandle;
+use rusty_runtime::Input;
 use rusty_variant::Variant;
 
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
-use crate::interpreter::io::Input;
 
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
     let mut file_handle: FileHandle = FileHandle::default();
@@ -1,6 +1,7 @@
+use rusty_runtime::VariantCasts;
+
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
-use crate::interpreter::variant_casts::VariantCasts;
 
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
     let mut iterator = interpreter.context().variables().iter();
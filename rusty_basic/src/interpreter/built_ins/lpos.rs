@@ -0,0 +1,55 @@
+use rusty_linter::core::QBNumberCast;
+use rusty_parser::BuiltInFunction;
+use rusty_runtime::Printer;
+
+use crate::RuntimeError;
+use crate::interpreter::interpreter_trait::InterpreterTrait;
+
+pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
+    let printer_number: i32 = interpreter.context()[0].try_cast()?;
+    // only LPT1 is supported, which is 0 or 1
+    if printer_number != 0 && printer_number != 1 {
+        return Err(RuntimeError::IllegalFunctionCall);
+    }
+    let position: i32 = interpreter.lpt1().column() as i32 + 1;
+    interpreter
+        .context_mut()
+        .set_built_in_function_result(BuiltInFunction::LPos, position);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::interpreter_trait::InterpreterTrait;
+    use crate::{RuntimeError, assert_interpreter_err, assert_prints};
+
+    #[test]
+    fn test_lpos_at_start() {
+        assert_prints!("PRINT LPOS(1)", "1");
+    }
+
+    #[test]
+    fn test_lpos_after_lprint() {
+        let input = r#"
+        LPRINT "hello";
+        PRINT LPOS(0)
+        LPRINT
+        PRINT LPOS(1)
+        "#;
+        assert_prints!(input, "6", "1");
+    }
+
+    #[test]
+    fn test_lpos_after_form_feed() {
+        let input = r#"
+        LPRINT "hello"; CHR$(12); "hi";
+        PRINT LPOS(1)
+        "#;
+        assert_prints!(input, "3");
+    }
+
+    #[test]
+    fn test_lpos_illegal_printer() {
+        assert_interpreter_err!("PRINT LPOS(4)", RuntimeError::IllegalFunctionCall, 1, 7);
+    }
+}
@@ -1,8 +1,8 @@
+use rusty_runtime::VariantCasts;
 use rusty_variant::Variant;
 
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
-use crate::interpreter::variant_casts::VariantCasts;
 
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
     let name: String = interpreter.context()[0].to_str_unchecked().to_owned(); // TODO fighting borrow checker
@@ -1,12 +1,12 @@
 use rusty_parser::BuiltInFunction;
+use rusty_runtime::{VariantCasts, functions};
 
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
-use crate::interpreter::variant_casts::VariantCasts;
 
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
     let s: &str = interpreter.context()[0].to_str_unchecked();
-    let result = s.trim_start().to_owned();
+    let result = functions::ltrim(s);
     interpreter
         .context_mut()
         .set_built_in_function_result(BuiltInFunction::LTrim, result);
@@ -1,8 +1,8 @@
 use rusty_parser::BuiltInFunction;
+use rusty_runtime::{VariantCasts, functions};
 
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
-use crate::interpreter::variant_casts::VariantCasts;
 
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
     let s: &str = interpreter.context()[0].to_str_unchecked();
@@ -11,28 +11,13 @@ pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError>
         Some(v) => Some(v.to_non_negative_int()?),
         None => None,
     };
-    let result: String = do_mid(s, start, length)?;
+    let result: String = functions::mid(s, start, length);
     interpreter
         .context_mut()
         .set_built_in_function_result(BuiltInFunction::Mid, result);
     Ok(())
 }
 
-fn do_mid(s: &str, start: usize, opt_length: Option<usize>) -> Result<String, RuntimeError> {
-    let start_index: usize = start - 1;
-    match opt_length {
-        Some(length) => {
-            let end: usize = if start_index + length > s.len() {
-                s.len()
-            } else {
-                start_index + length
-            };
-            Ok(s.get(start_index..end).unwrap_or_default().to_string())
-        }
-        None => Ok(s.get(start_index..).unwrap_or_default().to_string()),
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,10 +1,10 @@
 use rusty_linter::core::QBNumberCast;
 use rusty_parser::BuiltInFunction;
+use rusty_runtime::to_ascii_string;
 use rusty_variant::f64_to_bytes;
 
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
-use crate::interpreter::string_utils::to_ascii_string;
 
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
     let f: f64 = interpreter.context()[0].try_cast()?;
@@ -1,20 +1,27 @@
+mod assert;
+mod assert_equal;
 mod beep;
 mod chr;
 mod close;
 mod cls;
 mod color;
+mod command;
 mod cvd;
 mod data;
 mod def_seg;
 mod environ_fn;
 mod environ_sub;
 mod eof;
+mod erdev;
+mod erl;
 mod err;
+mod error;
 mod field;
 mod get;
 mod inkey;
 mod input;
 mod instr;
+mod key;
 mod kill;
 mod lbound;
 mod lcase;
@@ -22,6 +29,7 @@ mod left;
 mod len;
 mod line_input;
 mod locate;
+mod lpos;
 mod lset;
 mod ltrim;
 mod mid_fn;
@@ -55,6 +63,8 @@ pub fn run_sub<S: InterpreterTrait>(
     interpreter: &mut S,
 ) -> Result<(), RuntimeError> {
     match s {
+        BuiltInSub::Assert => assert::run(interpreter),
+        BuiltInSub::AssertEqual => assert_equal::run(interpreter),
         BuiltInSub::Beep => beep::run(interpreter),
         BuiltInSub::CallAbsolute => Ok(()),
         BuiltInSub::Close => close::run(interpreter),
@@ -63,9 +73,11 @@ pub fn run_sub<S: InterpreterTrait>(
         BuiltInSub::Data => data::run(interpreter),
         BuiltInSub::DefSeg => def_seg::run(interpreter),
         BuiltInSub::Environ => environ_sub::run(interpreter),
+        BuiltInSub::Error => error::run(interpreter),
         BuiltInSub::Field => field::run(interpreter),
         BuiltInSub::Get => get::run(interpreter),
         BuiltInSub::Input => input::run(interpreter),
+        BuiltInSub::Key => key::run(interpreter),
         BuiltInSub::Kill => kill::run(interpreter),
         BuiltInSub::LineInput => line_input::run(interpreter),
         BuiltInSub::Locate => locate::run(interpreter),
@@ -87,9 +99,12 @@ pub fn run_function<S: InterpreterTrait>(
 ) -> Result<(), RuntimeError> {
     match f {
         BuiltInFunction::Chr => chr::run(interpreter),
+        BuiltInFunction::Command => command::run(interpreter),
         BuiltInFunction::Cvd => cvd::run(interpreter),
         BuiltInFunction::Environ => environ_fn::run(interpreter),
         BuiltInFunction::Eof => eof::run(interpreter),
+        BuiltInFunction::ErDev => erdev::run(interpreter),
+        BuiltInFunction::Erl => erl::run(interpreter),
         BuiltInFunction::Err => err::run(interpreter),
         BuiltInFunction::InKey => inkey::run(interpreter),
         BuiltInFunction::InStr => instr::run(interpreter),
@@ -97,6 +112,7 @@ pub fn run_function<S: InterpreterTrait>(
         BuiltInFunction::LCase => lcase::run(interpreter),
         BuiltInFunction::Left => left::run(interpreter),
         BuiltInFunction::Len => len::run(interpreter),
+        BuiltInFunction::LPos => lpos::run(interpreter),
         BuiltInFunction::LTrim => ltrim::run(interpreter),
         BuiltInFunction::Mid => mid_fn::run(interpreter),
         BuiltInFunction::Mkd => mkd::run(interpreter),
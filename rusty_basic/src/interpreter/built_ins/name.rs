@@ -1,11 +1,15 @@
+use rusty_runtime::VariantCasts;
+
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
-use crate::interpreter::variant_casts::VariantCasts;
 
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
     let old_file_name: &str = interpreter.context()[0].to_str_unchecked();
     let new_file_name: &str = interpreter.context()[1].to_str_unchecked();
-    std::fs::rename(old_file_name, new_file_name).map_err(RuntimeError::from)
+    let file_policy = &interpreter.limits().file_policy;
+    let old_path = file_policy.resolve(old_file_name, true)?;
+    let new_path = file_policy.resolve(new_file_name, true)?;
+    std::fs::rename(old_path, new_path).map_err(RuntimeError::from)
 }
 
 #[cfg(test)]
@@ -1,10 +1,10 @@
 use rusty_linter::core::QBNumberCast;
 use rusty_parser::{FileAccess, FileHandle, FileMode};
+use rusty_runtime::VariantCasts;
 use rusty_variant::Variant;
 
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
-use crate::interpreter::variant_casts::VariantCasts;
 
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
     let file_name: String = interpreter.context()[0].to_str_unchecked().to_owned(); // TODO fighting borrow checker
@@ -12,9 +12,17 @@ pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError>
     let file_access: FileAccess = to_file_access(&interpreter.context()[2]);
     let file_handle: FileHandle = interpreter.context()[3].to_file_handle()?;
     let rec_len: usize = to_record_length(&interpreter.context()[4])?;
-    interpreter
-        .file_manager()
-        .open(file_handle, &file_name, file_mode, file_access, rec_len)
+    let path = interpreter
+        .limits()
+        .file_policy
+        .resolve(&file_name, file_mode != FileMode::Input)?;
+    interpreter.file_manager().open(
+        file_handle,
+        &path.to_string_lossy(),
+        file_mode,
+        file_access,
+        rec_len,
+    )
 }
 
 fn to_file_mode(v: &Variant) -> FileMode {
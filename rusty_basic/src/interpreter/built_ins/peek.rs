@@ -1,9 +1,9 @@
 use rusty_parser::BuiltInFunction;
+use rusty_runtime::VariantCasts;
 
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
 use crate::interpreter::keyboard::get_indicator_keys;
-use crate::interpreter::variant_casts::VariantCasts;
 
 pub const INDICATOR_KEYS_ADDRESS: usize = 1047;
 
@@ -1,10 +1,8 @@
-use rusty_parser::{BareName, FileHandle, TypeQualifier};
+use rusty_parser::{BareName, FileHandle, Name, TypeQualifier};
+use rusty_runtime::{Field, VariantCasts, to_ascii_bytes};
 
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
-use crate::interpreter::io::Field;
-use crate::interpreter::string_utils::to_ascii_bytes;
-use crate::interpreter::variant_casts::VariantCasts;
 
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
     let handle: FileHandle = interpreter.context()[0].to_file_handle()?;
@@ -18,11 +16,11 @@ pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError>
         .clone(); // TODO fighting the borrow checker
     // convert the variables into a string
     for Field { width, name } in field_list {
-        let bare_name: BareName = BareName::from(name.as_str());
+        let name = Name::qualified(BareName::from(name.as_str()), TypeQualifier::DollarString);
         let v = interpreter
             .context()
             .caller_variables()
-            .get_built_in(&bare_name, TypeQualifier::DollarString)
+            .get_by_name(&name)
             .ok_or(RuntimeError::VariableRequired)?;
         let mut bytes: Vec<u8> = to_ascii_bytes(v.to_str_unchecked());
         fix_length(&mut bytes, width);
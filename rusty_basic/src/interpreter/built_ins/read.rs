@@ -1,4 +1,5 @@
-use rusty_linter::core::{CastVariant, qualifier_of_variant};
+use rusty_linter::core::CastVariant;
+use rusty_runtime::qualifier;
 
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
@@ -7,7 +8,7 @@ pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError>
     // variables are passed by ref, so we can assign to them
     let len = interpreter.context().variables().len();
     for i in 0..len {
-        let target_type = qualifier_of_variant(interpreter.context().variables().get(i).unwrap())?;
+        let target_type = qualifier(interpreter.context().variables().get(i).unwrap())?;
         let data_value = interpreter.data_segment().pop()?;
         let casted_value = data_value.cast(target_type)?;
         interpreter.context_mut()[i] = casted_value;
@@ -1,17 +1,13 @@
 use rusty_parser::BuiltInFunction;
+use rusty_runtime::{VariantCasts, functions};
 
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
-use crate::interpreter::variant_casts::VariantCasts;
 
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
     let s: &str = interpreter.context()[0].to_str_unchecked();
     let count: usize = interpreter.context()[1].to_non_negative_int()?;
-    let right_part: String = if s.len() > count {
-        s.chars().skip(s.len() - count).collect()
-    } else {
-        s.to_owned()
-    };
+    let right_part: String = functions::right(s, count);
     interpreter
         .context_mut()
         .set_built_in_function_result(BuiltInFunction::Right, right_part);
@@ -1,15 +1,15 @@
 use rusty_linter::core::QBNumberCast;
 use rusty_parser::BuiltInFunction;
+use rusty_runtime::functions;
 
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
+use crate::interpreter::limits::check_allocation;
 
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
     let len: i32 = interpreter.context()[0].try_cast()?;
-    let mut s: String = String::new();
-    for _ in 0..len {
-        s.push(' ');
-    }
+    check_allocation(interpreter, len.max(0) as usize)?;
+    let s: String = functions::space(len);
     interpreter
         .context_mut()
         .set_built_in_function_result(BuiltInFunction::Space, s);
@@ -1,28 +1,13 @@
 use rusty_parser::BuiltInFunction;
+use rusty_runtime::functions;
 use rusty_variant::Variant;
 
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
 
-macro_rules! str_fmt {
-        ($e: expr, $zero: expr) => {
-            if $e >= $zero {
-                format!(" {}", $e)
-            } else {
-                format!("{}", $e)
-            }
-        };
-    }
-
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
     let v: &Variant = &interpreter.context()[0];
-    let result = match v {
-        Variant::VSingle(f) => str_fmt!(*f, 0.0),
-        Variant::VDouble(f) => str_fmt!(*f, 0.0),
-        Variant::VInteger(f) => str_fmt!(*f, 0),
-        Variant::VLong(f) => str_fmt!(*f, 0),
-        _ => panic!("unexpected arg to STR$"),
-    };
+    let result = functions::str(v);
     interpreter
         .context_mut()
         .set_built_in_function_result(BuiltInFunction::Str, result);
@@ -1,48 +1,21 @@
-use rusty_linter::core::QBNumberCast;
 use rusty_parser::BuiltInFunction;
-use rusty_variant::Variant;
+use rusty_runtime::{VariantCasts, functions};
 
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
-use crate::interpreter::variant_casts::VariantCasts;
+use crate::interpreter::limits::check_allocation;
 
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
     let count: usize = interpreter.context()[0].to_non_negative_int()?;
+    check_allocation(interpreter, count)?;
     let v = &interpreter.context()[1];
-    let s = run_with_variant(count, v)?;
+    let s = functions::string(count, v)?;
     interpreter
         .context_mut()
         .set_built_in_function_result(BuiltInFunction::String, s);
     Ok(())
 }
 
-fn run_with_variant(count: usize, v: &Variant) -> Result<String, RuntimeError> {
-    if let Variant::VString(s) = v {
-        run_with_string_argument(count, s)
-    } else {
-        let ascii: i32 = v.try_cast()?;
-        run_with_ascii_code_argument(count, ascii)
-    }
-}
-
-fn run_with_string_argument(count: usize, s: &str) -> Result<String, RuntimeError> {
-    let first_char = s.chars().next().ok_or(RuntimeError::IllegalFunctionCall)?;
-    run_with_char(count, first_char)
-}
-
-fn run_with_ascii_code_argument(count: usize, ascii: i32) -> Result<String, RuntimeError> {
-    if (0..=255).contains(&ascii) {
-        let u: u8 = ascii as u8;
-        run_with_char(count, u as char)
-    } else {
-        Err(RuntimeError::IllegalFunctionCall)
-    }
-}
-
-fn run_with_char(count: usize, ch: char) -> Result<String, RuntimeError> {
-    Ok(std::iter::repeat_n(ch, count).collect())
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
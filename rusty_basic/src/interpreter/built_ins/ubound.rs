@@ -1,29 +1,19 @@
 use rusty_parser::BuiltInFunction;
-use rusty_variant::Variant;
+use rusty_runtime::{VariantCasts, functions};
 
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
-use crate::interpreter::variant_casts::VariantCasts;
 
 pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
-    let v: Variant = interpreter.context()[0].clone();
     let dimension: usize = match interpreter.context().variables().get(1) {
         Some(v) => v.to_positive_int_or(RuntimeError::SubscriptOutOfRange)?,
         _ => 1,
     };
-    match v {
-        Variant::VArray(a) => match a.get_dimension_bounds(dimension - 1) {
-            Some((_, upper)) => {
-                interpreter.context_mut().set_built_in_function_result(
-                    BuiltInFunction::UBound,
-                    Variant::VInteger(*upper),
-                );
-                Ok(())
-            }
-            _ => Err(RuntimeError::SubscriptOutOfRange),
-        },
-        _ => Err(RuntimeError::TypeMismatch),
-    }
+    let result: i32 = functions::ubound(&interpreter.context()[0], dimension)?;
+    interpreter
+        .context_mut()
+        .set_built_in_function_result(BuiltInFunction::UBound, result);
+    Ok(())
 }
 
 #[cfg(test)]
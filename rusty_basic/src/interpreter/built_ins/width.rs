@@ -1,6 +1,72 @@
+use rusty_linter::core::QBNumberCast;
+use rusty_runtime::Printer;
+
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
 
-pub fn run<S: InterpreterTrait>(_interpreter: &mut S) -> Result<(), RuntimeError> {
+/// The printer width that disables wrapping.
+const UNLIMITED_WIDTH: i32 = 255;
+
+pub fn run<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
+    // the screen form (WIDTH columns%, rows%) is ignored
+    if interpreter.context().variables().len() == 2 {
+        let flag: i32 = interpreter.context()[0].try_cast()?;
+        if flag == 2 {
+            return set_lpt1_width(interpreter);
+        }
+    }
+    Ok(())
+}
+
+fn set_lpt1_width<S: InterpreterTrait>(interpreter: &mut S) -> Result<(), RuntimeError> {
+    let columns: i32 = interpreter.context()[1].try_cast()?;
+    match columns {
+        UNLIMITED_WIDTH => interpreter.lpt1().set_width(None),
+        1..UNLIMITED_WIDTH => interpreter.lpt1().set_width(Some(columns as usize)),
+        _ => return Err(RuntimeError::IllegalFunctionCall),
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::interpreter_trait::InterpreterTrait;
+    use crate::{RuntimeError, assert_interpreter_err, assert_lprints_exact};
+
+    #[test]
+    fn test_lprint_wraps_at_default_width() {
+        let input = "LPRINT STRING$(85, \"a\")";
+        assert_lprints_exact!(input, &"a".repeat(80), "aaaaa", "");
+    }
+
+    #[test]
+    fn test_width_lprint() {
+        let input = r#"
+        WIDTH LPRINT 5
+        LPRINT "hello, world"
+        LPRINT "12345"
+        "#;
+        assert_lprints_exact!(input, "hello", ", wor", "ld", "12345", "");
+    }
+
+    #[test]
+    fn test_width_lprint_unlimited() {
+        let input = "WIDTH LPRINT 255\nLPRINT STRING$(300, \"a\")";
+        assert_lprints_exact!(input, &"a".repeat(300), "");
+    }
+
+    #[test]
+    fn test_width_lprint_out_of_range() {
+        assert_interpreter_err!("WIDTH LPRINT 0", RuntimeError::IllegalFunctionCall, 1, 1);
+        assert_interpreter_err!("WIDTH LPRINT 256", RuntimeError::IllegalFunctionCall, 1, 1);
+    }
+
+    #[test]
+    fn test_width_screen_is_ignored() {
+        let input = r#"
+        WIDTH 40, 25
+        LPRINT "hi"
+        "#;
+        assert_lprints_exact!(input, "hi", "");
+    }
+}
@@ -2,13 +2,16 @@ use std::collections::HashMap;
 
 use rusty_common::CaseInsensitiveString;
 use rusty_linter::core::{QBNumberCast, ScopeName};
-use rusty_parser::{BareName, BuiltInFunction, TypeQualifier};
+use rusty_parser::{BareName, BuiltInFunction, Name, TypeQualifier};
+use rusty_runtime::QByteSize;
 use rusty_variant::{UserDefinedTypeValue, VArray, Variant, bytes_to_i32, i32_to_bytes};
 
 use crate::RuntimeError;
+use crate::bytecode::BytecodeError;
+use crate::bytecode::decoder::{Decoder, corrupted};
+use crate::bytecode::encoder::Encoder;
 use crate::instruction_generator::{Path, RootPath};
 use crate::interpreter::arguments::Arguments;
-use crate::interpreter::byte_size::QByteSize;
 use crate::interpreter::variables::Variables;
 
 // This is an arbitrary value, not what QBasic is doing
@@ -86,6 +89,26 @@ impl Context {
         self.do_push_existing(0, false);
     }
 
+    /// Drops the states of all SUB/FUNCTION calls in progress,
+    /// e.g. after the program stopped inside a SUB.
+    pub fn unwind_to_module_level(&mut self) {
+        while self.states.len() > 1 {
+            self.do_pop();
+        }
+    }
+
+    /// Gets the variables of every SUB/FUNCTION call in progress,
+    /// starting from the innermost one and ending with the global variables,
+    /// together with a flag indicating if the call belongs to a STATIC SUB/FUNCTION.
+    pub fn frames(&self) -> impl Iterator<Item = (&Variables, bool)> {
+        self.states
+            .iter()
+            .rev()
+            .filter(|state| state.arguments.is_none())
+            .map(|state| &self.memory_blocks[state.memory_block_index])
+            .map(|memory_block| (&memory_block.variables, memory_block.is_static))
+    }
+
     pub fn global_variables(&self) -> &Variables {
         &self.memory_blocks.first().unwrap().variables
     }
@@ -158,18 +181,83 @@ impl Context {
     {
         let q: TypeQualifier = TypeQualifier::from(&built_in_function);
         let bare_name: BareName = BareName::from(built_in_function);
+        // the result goes in the slot after the arguments
         self.variables_mut()
-            .insert_built_in(bare_name, q, Variant::from(value));
+            .push(Name::qualified(bare_name, q), Variant::from(value));
     }
 
     #[cfg(test)]
     pub fn get_by_name(&self, name: &rusty_parser::Name) -> Variant {
         self.variables()
             .get_by_name(name)
-            .map(Clone::clone)
+            .cloned()
             .expect("Variable not found")
     }
 
+    /// Writes the states and the memory blocks into a snapshot.
+    pub fn encode(&self, encoder: &mut Encoder) {
+        encoder.usize(self.memory_blocks.len());
+        for memory_block in &self.memory_blocks {
+            memory_block.variables.encode(encoder);
+            encoder.usize(memory_block.ref_count);
+            encoder.bool(memory_block.is_static);
+        }
+        encoder.usize(self.states.len());
+        for state in &self.states {
+            encoder.usize(state.memory_block_index);
+            encoder.option(state.arguments.as_ref(), |encoder, arguments| {
+                arguments.encode(encoder)
+            });
+        }
+        encoder.usize(self.static_memory_blocks.len());
+        for (scope_name, memory_block_index) in &self.static_memory_blocks {
+            encoder.scope_name(scope_name);
+            encoder.usize(*memory_block_index);
+        }
+    }
+
+    /// Reads the states and the memory blocks from a snapshot.
+    pub fn decode(decoder: &mut Decoder) -> Result<Self, BytecodeError> {
+        let len = decoder.len()?;
+        let memory_blocks: Vec<MemoryBlock> = (0..len)
+            .map(|_| {
+                Ok(MemoryBlock {
+                    variables: Variables::decode(decoder)?,
+                    ref_count: decoder.usize()?,
+                    is_static: decoder.bool()?,
+                })
+            })
+            .collect::<Result<_, BytecodeError>>()?;
+        let check_index = |memory_block_index: usize| {
+            if memory_block_index < memory_blocks.len() {
+                Ok(memory_block_index)
+            } else {
+                Err(corrupted("invalid memory block"))
+            }
+        };
+        let len = decoder.len()?;
+        let states: Vec<State> = (0..len)
+            .map(|_| {
+                Ok(State {
+                    memory_block_index: check_index(decoder.usize()?)?,
+                    arguments: decoder.option(Arguments::decode)?,
+                })
+            })
+            .collect::<Result<_, BytecodeError>>()?;
+        let len = decoder.len()?;
+        let static_memory_blocks = (0..len)
+            .map(|_| Ok((decoder.scope_name()?, check_index(decoder.usize()?)?)))
+            .collect::<Result<_, BytecodeError>>()?;
+        if states.is_empty() || memory_blocks.iter().any(|m| m.ref_count == 0) {
+            return Err(corrupted("invalid context"));
+        }
+        Ok(Self {
+            states,
+            memory_blocks,
+            static_memory_blocks,
+        })
+    }
+
     fn state(&self) -> &State {
         self.states.last().expect("Empty states!")
     }
@@ -204,11 +292,31 @@ impl Context {
         let state = self.states.pop().expect("States underflow");
         let removed_from_rc = self.decrease_ref_count(state.memory_block_index);
         if removed_from_rc {
-            self.memory_blocks.remove(state.memory_block_index);
+            self.remove_memory_block(state.memory_block_index);
         }
         state
     }
 
+    fn remove_memory_block(&mut self, memory_block_index: usize) {
+        self.memory_blocks.remove(memory_block_index);
+        if memory_block_index == self.memory_blocks.len() {
+            // the usual case, the last memory block was removed
+            return;
+        }
+        // a STATIC subprogram was called for the first time after this memory block
+        // was created, so the memory blocks that follow need to be re-indexed
+        for index in self
+            .states
+            .iter_mut()
+            .map(|state| &mut state.memory_block_index)
+            .chain(self.static_memory_blocks.values_mut())
+        {
+            if *index > memory_block_index {
+                *index -= 1;
+            }
+        }
+    }
+
     fn increase_ref_count(&mut self, memory_block_index: usize) {
         self.memory_blocks[memory_block_index].increase_ref_count();
     }
@@ -219,7 +327,7 @@ impl Context {
 
     pub fn calculate_varptr(&self, path: &Path) -> Result<usize, RuntimeError> {
         match path {
-            Path::Root(RootPath { name, shared }) => {
+            Path::Root(RootPath { slot, shared }) => {
                 // figure out the memory block where the variable lives
                 let memory_block_index = if *shared {
                     0
@@ -234,7 +342,7 @@ impl Context {
                 // add the varptr of this variable
                 result += self.memory_blocks[memory_block_index]
                     .variables
-                    .calculate_var_ptr(name);
+                    .calculate_var_ptr(*slot);
                 Ok(result)
             }
             Path::ArrayElement(parent_path, indices) => {
@@ -262,7 +370,7 @@ impl Context {
 
     fn find_value_in_caller_context(&self, path: &Path) -> Result<&Variant, RuntimeError> {
         match path {
-            Path::Root(RootPath { name, shared }) => {
+            Path::Root(RootPath { slot, shared }) => {
                 let memory_block_index = if *shared {
                     0
                 } else {
@@ -270,7 +378,7 @@ impl Context {
                 };
                 self.memory_blocks[memory_block_index]
                     .variables
-                    .get_by_name(name)
+                    .get(*slot)
                     .ok_or(RuntimeError::VariableRequired)
             }
             Path::ArrayElement(parent_path, indices) => {
@@ -301,7 +409,7 @@ impl Context {
                 VAR_SEG_BASE
             }
             Path::ArrayElement(parent_path, ..) => {
-                if let Path::Root(RootPath { name, shared }) = parent_path.as_ref() {
+                if let Path::Root(RootPath { slot, shared }) = parent_path.as_ref() {
                     // figure out the memory block index where the Array is defined
                     let memory_block_index = if *shared {
                         0
@@ -311,13 +419,13 @@ impl Context {
                     let mut result: usize = 0;
                     // add one segment for every array defined in parent memory blocks
                     for i in 0..memory_block_index {
-                        result += self.memory_blocks[i].variables.array_names().count();
+                        result += self.memory_blocks[i].variables.array_slots().count();
                     }
                     // add one segment for every array defined in the memory block of the array, until we find the array name
                     result += self.memory_blocks[memory_block_index]
                         .variables
-                        .array_names()
-                        .take_while(|p| *p != name)
+                        .array_slots()
+                        .take_while(|s| s != slot)
                         .count();
                     // add the array itself
                     result += 1;
@@ -422,6 +530,17 @@ impl std::ops::IndexMut<usize> for Context {
     }
 }
 
+/// The size of all variables of all memory blocks
+/// (global, static and of the subprograms that are running).
+impl QByteSize for Context {
+    fn byte_size(&self) -> usize {
+        self.memory_blocks
+            .iter()
+            .map(|memory_block| memory_block.variables.byte_size())
+            .sum()
+    }
+}
+
 impl Default for Context {
     fn default() -> Self {
         Self::new()
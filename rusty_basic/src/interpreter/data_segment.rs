@@ -1,6 +1,9 @@
 use rusty_variant::Variant;
 
 use crate::RuntimeError;
+use crate::bytecode::BytecodeError;
+use crate::bytecode::decoder::Decoder;
+use crate::bytecode::encoder::Encoder;
 
 #[derive(Default)]
 pub struct DataSegment {
@@ -22,4 +25,23 @@ impl DataSegment {
             _ => Err(RuntimeError::OutOfData),
         }
     }
+
+    /// Writes the values and the position of the next value to `READ` into a snapshot.
+    pub fn encode(&self, encoder: &mut Encoder) {
+        encoder.usize(self.values.len());
+        for value in &self.values {
+            encoder.variant(value);
+        }
+        encoder.usize(self.index);
+    }
+
+    /// Reads the values and the position of the next value to `READ` from a snapshot.
+    pub fn decode(decoder: &mut Decoder) -> Result<Self, BytecodeError> {
+        let len = decoder.len()?;
+        let values = (0..len)
+            .map(|_| decoder.variant())
+            .collect::<Result<_, _>>()?;
+        let index = decoder.usize()?;
+        Ok(Self { values, index })
+    }
 }
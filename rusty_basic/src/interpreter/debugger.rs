@@ -0,0 +1,475 @@
+use rusty_common::Position;
+use rusty_parser::{AsBareName, Name};
+use rusty_variant::Variant;
+
+use crate::instruction_generator::Instruction;
+use crate::interpreter::variables::Variables;
+
+/// The maximum number of array elements that [format_value] shows.
+const MAX_FORMATTED_ARRAY_ELEMENTS: usize = 20;
+
+/// Takes control whenever a program that runs under a debugger pauses.
+pub trait Debugger {
+    /// Decides how the program starts.
+    /// By default, the program pauses at its first statement.
+    fn on_start(&mut self) -> DebugAction {
+        DebugAction::StepInto
+    }
+
+    /// Checks if there is a breakpoint on the given source line.
+    fn has_breakpoint(&self, row: u32) -> bool;
+
+    /// Called when the program pauses, before running the statement
+    /// at the position of the innermost frame. Returns how to continue.
+    fn on_pause(&mut self, pause: &Pause) -> DebugAction;
+
+    /// Called before running every statement, even if the program does not pause
+    /// (e.g. to record which statements ran).
+    fn on_statement(&mut self, _pos: Position) {}
+
+    /// Called before running every statement, in order to pause the running
+    /// program when the user asks for it (e.g. with the pause button of a client).
+    fn is_pause_requested(&mut self) -> bool {
+        false
+    }
+}
+
+/// Decides how a paused program continues.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DebugAction {
+    /// Runs until the next breakpoint or `STOP` statement.
+    Continue,
+    /// Pauses at the next statement, entering SUB/FUNCTION calls.
+    StepInto,
+    /// Pauses at the next statement of the current SUB/FUNCTION (or module),
+    /// running any calls without pausing.
+    StepOver,
+    /// Pauses after returning from the current SUB/FUNCTION.
+    StepOut,
+    /// Ends the program.
+    Quit,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PauseReason {
+    /// A step requested by [DebugAction::StepInto], [DebugAction::StepOver] or [DebugAction::StepOut].
+    Step,
+    Breakpoint,
+    /// A `STOP` statement.
+    Stop,
+    /// The user asked to pause the running program
+    /// (see [Debugger::is_pause_requested]).
+    Requested,
+}
+
+/// The state of a paused program.
+pub struct Pause<'a> {
+    pub reason: PauseReason,
+    /// The call stack, starting from the innermost frame.
+    pub frames: Vec<Frame<'a>>,
+}
+
+impl Pause<'_> {
+    /// Gets the position of the statement that is about to run.
+    pub fn pos(&self) -> Position {
+        self.frames[0].pos
+    }
+}
+
+/// A frame of the call stack.
+pub struct Frame<'a> {
+    /// The statement that is about to run (innermost frame)
+    /// or the call that is in progress (outer frames).
+    pub pos: Position,
+    variables: &'a Variables,
+    is_static: bool,
+}
+
+impl<'a> Frame<'a> {
+    pub(crate) fn new(pos: Position, variables: &'a Variables, is_static: bool) -> Self {
+        Self {
+            pos,
+            variables,
+            is_static,
+        }
+    }
+
+    /// Checks if the frame belongs to a STATIC SUB/FUNCTION,
+    /// whose variables are retained between calls.
+    pub fn is_static(&self) -> bool {
+        self.is_static
+    }
+
+    /// Gets the variables of the frame, in order of allocation.
+    pub fn variables(&self) -> impl Iterator<Item = (&'a Name, &'a Variant)> {
+        self.variables.iter_named()
+    }
+
+    /// Finds a variable of the frame, by its name with or without the type qualifier
+    /// (e.g. `A` or `A$`), ignoring case.
+    pub fn find_variable(&self, name: &str) -> Option<(&'a Name, &'a Variant)> {
+        self.variables().find(|(n, _)| {
+            n.as_bare_name().eq_ignore_ascii_case(name) || n.to_string().eq_ignore_ascii_case(name)
+        })
+    }
+}
+
+/// Formats the value of a variable for display,
+/// e.g. `"hello"`, `42`, `(1 TO 3) [1, 2, 3]`, `{ Suit: "H", Value: 10 }`.
+pub fn format_value(value: &Variant) -> String {
+    match value {
+        Variant::VString(s) => format!("\"{}\"", s),
+        Variant::VArray(array) => {
+            let dimensions: Vec<String> = (0..)
+                .map_while(|index| array.get_dimension_bounds(index))
+                .map(|(lbound, ubound)| format!("{} TO {}", lbound, ubound))
+                .collect();
+            let mut elements: Vec<String> = (0..array.len().min(MAX_FORMATTED_ARRAY_ELEMENTS))
+                .filter_map(|index| array.get(index))
+                .map(format_value)
+                .collect();
+            if array.len() > MAX_FORMATTED_ARRAY_ELEMENTS {
+                elements.push("...".to_owned());
+            }
+            format!("({}) [{}]", dimensions.join(", "), elements.join(", "))
+        }
+        Variant::VUserDefined(user_defined_value) => {
+            let properties: Vec<String> = user_defined_value
+                .names()
+                .zip(user_defined_value.values())
+                .map(|(name, value)| format!("{}: {}", name, format_value(value)))
+                .collect();
+            format!("{{ {} }}", properties.join(", "))
+        }
+        _ => value.to_string(),
+    }
+}
+
+/// Decides when a program that runs under a debugger pauses.
+pub(crate) struct Stepper {
+    mode: StepMode,
+    /// The position of the last statement, in order to pause only once
+    /// on a breakpoint line with many statements, and to step over the
+    /// implicit declarations that share the position of the statement.
+    last_pos: Option<Position>,
+}
+
+#[derive(Clone, Copy)]
+enum StepMode {
+    Run,
+    Into,
+    /// Pause when the call depth is at most the given one.
+    Over(usize),
+    /// Pause when the call depth is less than the given one.
+    Out(usize),
+}
+
+impl Stepper {
+    pub fn new(action: DebugAction, depth: usize) -> Self {
+        let mut result = Self {
+            mode: StepMode::Run,
+            last_pos: None,
+        };
+        result.resume(action, depth);
+        result
+    }
+
+    /// Checks if the program should pause before running the statement
+    /// that starts with the given instruction.
+    pub fn check(
+        &mut self,
+        instruction: &Instruction,
+        pos: Position,
+        depth: usize,
+        debugger: &mut dyn Debugger,
+    ) -> Option<PauseReason> {
+        match instruction {
+            Instruction::Stop => {
+                self.last_pos = Some(pos);
+                return Some(PauseReason::Stop);
+            }
+            // the end of a SUB/FUNCTION and the end of the program are statement addresses
+            // only in order to be able to RESUME NEXT, they are not in the source
+            Instruction::PopRet | Instruction::Halt => return None,
+            _ => {}
+        }
+        let is_new_pos = self.last_pos != Some(pos);
+        let is_new_line = self
+            .last_pos
+            .is_none_or(|last_pos| last_pos.row() != pos.row());
+        self.last_pos = Some(pos);
+        // asked every time, so that the request is consumed even if
+        // the program pauses for another reason
+        let is_requested = debugger.is_pause_requested();
+        let is_step = is_new_pos
+            && match self.mode {
+                StepMode::Run => false,
+                StepMode::Into => true,
+                StepMode::Over(d) => depth <= d,
+                StepMode::Out(d) => depth < d,
+            };
+        if is_step {
+            Some(PauseReason::Step)
+        } else if is_new_line && debugger.has_breakpoint(pos.row()) {
+            Some(PauseReason::Breakpoint)
+        } else if is_requested {
+            Some(PauseReason::Requested)
+        } else {
+            None
+        }
+    }
+
+    /// Continues after a pause. The quit action is handled by the caller.
+    pub fn resume(&mut self, action: DebugAction, depth: usize) {
+        self.mode = match action {
+            DebugAction::Continue | DebugAction::Quit => StepMode::Run,
+            DebugAction::StepInto => StepMode::Into,
+            DebugAction::StepOver => StepMode::Over(depth),
+            DebugAction::StepOut => StepMode::Out(depth),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashSet, VecDeque};
+
+    use super::*;
+    use crate::assert_prints;
+    use crate::interpreter::interpreter_trait::InterpreterTrait;
+    use crate::interpreter::test_utils::mock_interpreter_for_input;
+
+    /// A debugger that records the pauses and replies with the given actions.
+    struct ScriptedDebugger {
+        breakpoints: HashSet<u32>,
+        on_start: DebugAction,
+        actions: VecDeque<DebugAction>,
+        /// For every pause: the reason, the row of every frame
+        /// and the formatted variables of the innermost frame.
+        pauses: Vec<(PauseReason, Vec<u32>, Vec<String>)>,
+        /// The number of statements to run before requesting a pause.
+        pause_request_countdown: Option<usize>,
+    }
+
+    impl ScriptedDebugger {
+        fn new(on_start: DebugAction, breakpoints: &[u32], actions: &[DebugAction]) -> Self {
+            Self {
+                breakpoints: breakpoints.iter().copied().collect(),
+                on_start,
+                actions: actions.iter().copied().collect(),
+                pauses: vec![],
+                pause_request_countdown: None,
+            }
+        }
+
+        fn rows(&self) -> Vec<Vec<u32>> {
+            self.pauses
+                .iter()
+                .map(|(_, rows, _)| rows.clone())
+                .collect()
+        }
+    }
+
+    impl Debugger for ScriptedDebugger {
+        fn on_start(&mut self) -> DebugAction {
+            self.on_start
+        }
+
+        fn has_breakpoint(&self, row: u32) -> bool {
+            self.breakpoints.contains(&row)
+        }
+
+        fn on_pause(&mut self, pause: &Pause) -> DebugAction {
+            self.pauses.push((
+                pause.reason,
+                pause.frames.iter().map(|frame| frame.pos.row()).collect(),
+                pause.frames[0]
+                    .variables()
+                    .map(|(name, value)| format!("{} = {}", name, format_value(value)))
+                    .collect(),
+            ));
+            self.actions.pop_front().unwrap_or(DebugAction::Continue)
+        }
+
+        fn is_pause_requested(&mut self) -> bool {
+            match self.pause_request_countdown {
+                Some(0) => {
+                    self.pause_request_countdown = None;
+                    true
+                }
+                Some(n) => {
+                    self.pause_request_countdown = Some(n - 1);
+                    false
+                }
+                None => false,
+            }
+        }
+    }
+
+    fn debug(input: &str, debugger: &mut ScriptedDebugger) -> String {
+        let (instruction_generator_result, mut interpreter) = mock_interpreter_for_input(input);
+        interpreter
+            .debug(instruction_generator_result, debugger)
+            .unwrap();
+        interpreter.stdout().output_lines().join("\n")
+    }
+
+    const PROGRAM: &str = r#"
+A = 1
+Hello A
+PRINT "done"
+
+SUB Hello(X)
+    PRINT "hello"; X
+    Bye
+END SUB
+
+SUB Bye
+    PRINT "bye"
+END SUB
+"#;
+
+    #[test]
+    fn test_breakpoint() {
+        let mut debugger = ScriptedDebugger::new(DebugAction::Continue, &[7, 12], &[]);
+        let output = debug(PROGRAM, &mut debugger);
+        assert_eq!(output, "hello 1\nbye\ndone");
+        assert_eq!(debugger.rows(), vec![vec![7, 3], vec![12, 8, 3]]);
+        assert!(
+            debugger
+                .pauses
+                .iter()
+                .all(|(reason, _, _)| *reason == PauseReason::Breakpoint)
+        );
+    }
+
+    #[test]
+    fn test_pause_requested_while_running() {
+        let input = r#"
+WHILE 1
+    X = X + 1
+WEND
+"#;
+        let mut debugger = ScriptedDebugger::new(DebugAction::Continue, &[], &[DebugAction::Quit]);
+        debugger.pause_request_countdown = Some(10);
+        debug(input, &mut debugger);
+        assert_eq!(debugger.pauses.len(), 1);
+        let (reason, rows, variables) = &debugger.pauses[0];
+        assert_eq!(*reason, PauseReason::Requested);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(variables, &vec!["X! = 3".to_owned()]);
+    }
+
+    #[test]
+    fn test_step_into() {
+        let mut debugger = ScriptedDebugger::new(
+            DebugAction::Continue,
+            &[3],
+            &[DebugAction::StepInto, DebugAction::StepInto],
+        );
+        debug(PROGRAM, &mut debugger);
+        assert_eq!(debugger.rows(), vec![vec![3], vec![7, 3], vec![8, 3]]);
+    }
+
+    #[test]
+    fn test_step_over() {
+        let mut debugger = ScriptedDebugger::new(
+            DebugAction::Continue,
+            &[7],
+            &[DebugAction::StepOver, DebugAction::StepOver],
+        );
+        let output = debug(PROGRAM, &mut debugger);
+        assert_eq!(output, "hello 1\nbye\ndone");
+        assert_eq!(debugger.rows(), vec![vec![7, 3], vec![8, 3], vec![4]]);
+    }
+
+    #[test]
+    fn test_step_out() {
+        let mut debugger =
+            ScriptedDebugger::new(DebugAction::Continue, &[12], &[DebugAction::StepOut]);
+        debug(PROGRAM, &mut debugger);
+        assert_eq!(debugger.rows(), vec![vec![12, 8, 3], vec![4]]);
+    }
+
+    #[test]
+    fn test_step_over_implicit_variable() {
+        let mut debugger = ScriptedDebugger::new(
+            DebugAction::StepInto,
+            &[],
+            &[DebugAction::StepOver, DebugAction::StepOver],
+        );
+        debug("A = 1\nB = 2\nPRINT A + B", &mut debugger);
+        assert_eq!(debugger.rows(), vec![vec![1], vec![2], vec![3]]);
+    }
+
+    #[test]
+    fn test_stop_on_entry() {
+        let mut debugger = ScriptedDebugger::new(DebugAction::StepInto, &[], &[]);
+        debug("PRINT 1\nPRINT 2", &mut debugger);
+        assert_eq!(debugger.rows(), vec![vec![1]]);
+        assert_eq!(debugger.pauses[0].0, PauseReason::Step);
+    }
+
+    #[test]
+    fn test_quit() {
+        let mut debugger = ScriptedDebugger::new(DebugAction::Continue, &[2], &[DebugAction::Quit]);
+        let output = debug("PRINT 1\nPRINT 2\nPRINT 3", &mut debugger);
+        assert_eq!(output, "1");
+    }
+
+    #[test]
+    fn test_stop_statement_pauses_and_continues() {
+        let mut debugger = ScriptedDebugger::new(DebugAction::Continue, &[], &[]);
+        let output = debug("PRINT 1\nSTOP\nPRINT 2", &mut debugger);
+        assert_eq!(output, "1\n2");
+        assert_eq!(debugger.rows(), vec![vec![2]]);
+        assert_eq!(debugger.pauses[0].0, PauseReason::Stop);
+    }
+
+    #[test]
+    fn test_stop_statement_ends_program_without_debugger() {
+        assert_prints!("PRINT 1\nSTOP\nPRINT 2", "1");
+    }
+
+    #[test]
+    fn test_variables_of_frames() {
+        let input = r#"
+TYPE Card
+    Suit AS STRING * 1
+    Value AS INTEGER
+END TYPE
+DIM C AS Card
+DIM A(1 TO 3) AS INTEGER
+C.Suit = "H"
+C.Value = 10
+A(2) = 5
+N$ = "hi"
+Show 42
+STOP
+
+SUB Show(X%)
+    Y = X% * 2
+    STOP
+END SUB
+"#;
+        let mut debugger = ScriptedDebugger::new(DebugAction::Continue, &[], &[]);
+        debug(input, &mut debugger);
+        assert_eq!(debugger.pauses[0].2, vec!["X% = 42", "Y! = 84"]);
+        assert_eq!(
+            debugger.pauses[1].2,
+            vec![
+                "N$ = \"hi\"",
+                "C = { Suit: \"H\", Value: 10 }",
+                "A% = (1 TO 3) [0, 5, 0]"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_value_truncates_large_arrays() {
+        let array = rusty_variant::VArray::new(vec![(0, 99)], Variant::VInteger(0));
+        let formatted = format_value(&Variant::VArray(Box::new(array)));
+        assert!(formatted.starts_with("(0 TO 99) [0, 0,"));
+        assert!(formatted.ends_with(", 0, ...]"));
+    }
+}
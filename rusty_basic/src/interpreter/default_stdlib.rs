@@ -1,6 +1,19 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 use crate::interpreter::Stdlib;
 
-pub struct DefaultStdlib;
+#[derive(Default)]
+pub struct DefaultStdlib {
+    command_line: String,
+}
+
+impl DefaultStdlib {
+    /// Sets the command line arguments of the program,
+    /// which are returned by `COMMAND$`.
+    pub fn set_command_line(&mut self, command_line: String) {
+        self.command_line = command_line;
+    }
+}
 
 impl Stdlib for DefaultStdlib {
     fn system(&self) {
@@ -16,4 +29,14 @@ impl Stdlib for DefaultStdlib {
             std::env::set_var(name, value);
         }
     }
+
+    fn command_line(&self) -> String {
+        self.command_line.clone()
+    }
+
+    fn now(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+    }
 }
@@ -0,0 +1,358 @@
+//! Event trapping (`ON KEY`, `ON PLAY`, `ON TIMER`).
+//!
+//! Events are checked between statements. When a trapped event occurs,
+//! its handler is called like a `GOSUB`. While the handler runs, trapping
+//! of the same event is suspended, until the handler returns.
+//!
+//! Background music is not supported, so `ON PLAY` events never occur.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use rusty_parser::{EventAction, EventKind};
+
+use crate::RuntimeError;
+use crate::bytecode::BytecodeError;
+use crate::bytecode::decoder::{Decoder, corrupted};
+use crate::bytecode::encoder::Encoder;
+use crate::interpreter::Stdlib;
+use crate::interpreter::keyboard::KeyboardBuffer;
+
+/// Holds the state of all event traps.
+#[derive(Default)]
+pub struct EventTraps {
+    timer: EventTrap,
+    timer_interval: Duration,
+    timer_last_tick: Duration,
+    play: EventTrap,
+    keys: HashMap<u8, EventTrap>,
+    /// Set when any of the key traps is not off. Kept up to date by [`Self::control`],
+    /// so that the keys don't need to be scanned before every statement.
+    any_key_active: bool,
+    /// The events whose handler is currently running,
+    /// along with the depth of the `GOSUB` stack when they were called.
+    running_handlers: Vec<(EventId, usize)>,
+    /// Set when an event handler returns, so that the interrupted statement
+    /// runs before any other event is trapped.
+    resuming: bool,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum EventId {
+    Key(u8),
+    Play,
+    Timer,
+}
+
+#[derive(Default)]
+struct EventTrap {
+    handler_address: Option<usize>,
+    state: TrapState,
+    pending: bool,
+    running: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+enum TrapState {
+    #[default]
+    Off,
+    On,
+    Stopped,
+}
+
+impl EventTrap {
+    fn is_active(&self) -> bool {
+        self.state != TrapState::Off
+    }
+
+    fn set_state(&mut self, action: EventAction) {
+        self.state = match action {
+            EventAction::On => TrapState::On,
+            EventAction::Off => {
+                self.pending = false;
+                TrapState::Off
+            }
+            EventAction::Stop => TrapState::Stopped,
+        };
+    }
+
+    fn can_call_handler(&self) -> bool {
+        self.state == TrapState::On && self.pending && !self.running
+    }
+
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.option(self.handler_address.as_ref(), |encoder, handler_address| {
+            encoder.usize(*handler_address)
+        });
+        encoder.u8(match self.state {
+            TrapState::Off => 0,
+            TrapState::On => 1,
+            TrapState::Stopped => 2,
+        });
+        encoder.bool(self.pending);
+        encoder.bool(self.running);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, BytecodeError> {
+        Ok(Self {
+            handler_address: decoder.option(Decoder::usize)?,
+            state: match decoder.u8()? {
+                0 => TrapState::Off,
+                1 => TrapState::On,
+                2 => TrapState::Stopped,
+                _ => return Err(corrupted("invalid event trap state")),
+            },
+            pending: decoder.bool()?,
+            running: decoder.bool()?,
+        })
+    }
+}
+
+impl EventTraps {
+    /// Sets the handler of an event (`ON event(arg) GOSUB label`).
+    pub fn set_handler(
+        &mut self,
+        kind: EventKind,
+        arg: i32,
+        handler_address: usize,
+    ) -> Result<(), RuntimeError> {
+        match kind {
+            EventKind::Key => {
+                let key_number = validate_key_number(arg)?;
+                self.keys.entry(key_number).or_default().handler_address = Some(handler_address);
+            }
+            EventKind::Play => {
+                if !(1..=32).contains(&arg) {
+                    return Err(RuntimeError::IllegalFunctionCall);
+                }
+                self.play.handler_address = Some(handler_address);
+            }
+            EventKind::Timer => {
+                if !(1..=86_400).contains(&arg) {
+                    return Err(RuntimeError::IllegalFunctionCall);
+                }
+                self.timer_interval = Duration::from_secs(arg as u64);
+                self.timer.handler_address = Some(handler_address);
+            }
+        }
+        Ok(())
+    }
+
+    /// Enables, disables or suspends trapping of an event
+    /// (`TIMER ON`, `KEY(n) OFF`, etc).
+    /// The argument is only used for `KEY`.
+    pub fn control(
+        &mut self,
+        kind: EventKind,
+        arg: i32,
+        action: EventAction,
+        stdlib: &impl Stdlib,
+    ) -> Result<(), RuntimeError> {
+        match kind {
+            EventKind::Key => {
+                let key_number = validate_key_number(arg)?;
+                self.keys.entry(key_number).or_default().set_state(action);
+                self.any_key_active = self.keys.values().any(EventTrap::is_active);
+            }
+            EventKind::Play => self.play.set_state(action),
+            EventKind::Timer => {
+                if action == EventAction::On && !self.timer.is_active() {
+                    // the timer starts counting when it gets enabled
+                    self.timer_last_tick = stdlib.now();
+                }
+                self.timer.set_state(action);
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks if any event is being trapped (or remembered).
+    pub fn is_active(&self) -> bool {
+        self.timer.is_active() || self.play.is_active() || self.any_key_active
+    }
+
+    /// Checks the sources of the events (timer and keyboard) for new events.
+    /// Keys that are not trapped remain available to `INKEY$`.
+    pub fn poll(
+        &mut self,
+        stdlib: &impl Stdlib,
+        keyboard: &mut KeyboardBuffer,
+    ) -> Result<(), RuntimeError> {
+        if self.timer.is_active() {
+            let now = stdlib.now();
+            if now.saturating_sub(self.timer_last_tick) >= self.timer_interval {
+                self.timer_last_tick = now;
+                self.timer.pending = true;
+            }
+        }
+        if self.any_key_active {
+            let mut untrapped_keys: Vec<String> = vec![];
+            while let Some(key) = keyboard.poll_unbuffered()? {
+                match self.find_active_key_trap(&key, keyboard) {
+                    Some(trap) => trap.pending = true,
+                    None => untrapped_keys.push(key),
+                }
+            }
+            for key in untrapped_keys {
+                keyboard.push(key);
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds the next event whose handler needs to be called.
+    /// The handler is marked as running, until [`Self::on_return`] is called
+    /// with the same `GOSUB` stack depth.
+    /// Returns `None` right after a handler has returned,
+    /// so that the interrupted statement gets a chance to run.
+    pub fn take_next_handler(&mut self, go_sub_depth: usize) -> Option<usize> {
+        if std::mem::take(&mut self.resuming) {
+            return None;
+        }
+        let event_id = self.find_next_event()?;
+        let trap = self.trap_mut(event_id);
+        trap.pending = false;
+        trap.running = true;
+        let handler_address = trap.handler_address;
+        self.running_handlers.push((event_id, go_sub_depth));
+        handler_address
+    }
+
+    /// Called when a `RETURN` statement is executed.
+    /// Returns `true` if it was the return of an event handler.
+    pub fn on_return(&mut self, go_sub_depth: usize) -> bool {
+        match self.running_handlers.last() {
+            Some((event_id, depth)) if *depth == go_sub_depth => {
+                let event_id = *event_id;
+                self.running_handlers.pop();
+                self.trap_mut(event_id).running = false;
+                self.resuming = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Writes the event traps into a snapshot.
+    /// The time of the last timer tick is saved relative to the given current time,
+    /// because the clock of the interpreter that restores the snapshot might differ.
+    pub fn encode(&self, encoder: &mut Encoder, now: Duration) {
+        self.timer.encode(encoder);
+        encoder.u64(self.timer_interval.as_millis() as u64);
+        encoder.u64(now.saturating_sub(self.timer_last_tick).as_millis() as u64);
+        self.play.encode(encoder);
+        let mut key_numbers: Vec<&u8> = self.keys.keys().collect();
+        key_numbers.sort();
+        encoder.usize(key_numbers.len());
+        for key_number in key_numbers {
+            encoder.u8(*key_number);
+            self.keys[key_number].encode(encoder);
+        }
+        encoder.usize(self.running_handlers.len());
+        for (event_id, go_sub_depth) in &self.running_handlers {
+            match event_id {
+                EventId::Key(key_number) => {
+                    encoder.u8(0);
+                    encoder.u8(*key_number);
+                }
+                EventId::Play => encoder.u8(1),
+                EventId::Timer => encoder.u8(2),
+            }
+            encoder.usize(*go_sub_depth);
+        }
+        encoder.bool(self.resuming);
+    }
+
+    /// Reads the event traps from a snapshot.
+    pub fn decode(decoder: &mut Decoder, now: Duration) -> Result<Self, BytecodeError> {
+        let timer = EventTrap::decode(decoder)?;
+        let timer_interval = Duration::from_millis(decoder.u64()?);
+        let timer_last_tick = now.saturating_sub(Duration::from_millis(decoder.u64()?));
+        let play = EventTrap::decode(decoder)?;
+        let len = decoder.len()?;
+        let keys: HashMap<u8, EventTrap> = (0..len)
+            .map(|_| Ok((decoder.u8()?, EventTrap::decode(decoder)?)))
+            .collect::<Result<_, BytecodeError>>()?;
+        let len = decoder.len()?;
+        let running_handlers = (0..len)
+            .map(|_| {
+                let event_id = match decoder.u8()? {
+                    0 => {
+                        let key_number = decoder.u8()?;
+                        if !keys.contains_key(&key_number) {
+                            return Err(corrupted("invalid key event"));
+                        }
+                        EventId::Key(key_number)
+                    }
+                    1 => EventId::Play,
+                    2 => EventId::Timer,
+                    _ => return Err(corrupted("invalid event")),
+                };
+                Ok((event_id, decoder.usize()?))
+            })
+            .collect::<Result<_, _>>()?;
+        let resuming = decoder.bool()?;
+        let any_key_active = keys.values().any(EventTrap::is_active);
+        Ok(Self {
+            timer,
+            timer_interval,
+            timer_last_tick,
+            play,
+            keys,
+            any_key_active,
+            running_handlers,
+            resuming,
+        })
+    }
+
+    fn find_next_event(&self) -> Option<EventId> {
+        // QBasic gives priority to keys, then timer, then music
+        let mut key_numbers: Vec<&u8> = self.keys.keys().collect();
+        key_numbers.sort();
+        key_numbers
+            .into_iter()
+            .find(|key_number| self.keys[key_number].can_call_handler())
+            .map(|key_number| EventId::Key(*key_number))
+            .or_else(|| {
+                if self.timer.can_call_handler() {
+                    Some(EventId::Timer)
+                } else if self.play.can_call_handler() {
+                    Some(EventId::Play)
+                } else {
+                    None
+                }
+            })
+    }
+
+    fn find_active_key_trap(
+        &mut self,
+        key: &str,
+        keyboard: &KeyboardBuffer,
+    ) -> Option<&mut EventTrap> {
+        self.keys
+            .iter_mut()
+            .find(|(key_number, trap)| {
+                trap.is_active() && keyboard.is_key_number(**key_number, key)
+            })
+            .map(|(_, trap)| trap)
+    }
+
+    fn trap_mut(&mut self, event_id: EventId) -> &mut EventTrap {
+        match event_id {
+            EventId::Key(key_number) => self.keys.get_mut(&key_number).unwrap(),
+            EventId::Play => &mut self.play,
+            EventId::Timer => &mut self.timer,
+        }
+    }
+}
+
+/// Key numbers 1-10 are the function keys F1-F10,
+/// 11-14 are the cursor keys (up, left, right, down),
+/// 15-25 are user defined keys,
+/// 30 and 31 are the function keys F11 and F12.
+fn validate_key_number(arg: i32) -> Result<u8, RuntimeError> {
+    match arg {
+        1..=25 | 30 | 31 => Ok(arg as u8),
+        _ => Err(RuntimeError::IllegalFunctionCall),
+    }
+}
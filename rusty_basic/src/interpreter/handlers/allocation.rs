@@ -1,54 +1,35 @@
 use rusty_common::{CaseInsensitiveString, NoPosIterTrait, Positioned};
 use rusty_parser::{BareName, ElementType, ExpressionType, TypeQualifier, UserDefinedTypes};
-use rusty_variant::{UserDefinedTypeValue, VArray, Variant};
+use rusty_runtime::{QByteSize, allocate_built_in, allocate_fixed_length_string};
+use rusty_variant::{UserDefinedTypeValue, Variant};
 
 use crate::RuntimeError;
 
 // TODO add unit tests
 
-/// Creates the default variant for the given type.
-pub fn allocate_built_in(type_qualifier: TypeQualifier) -> Variant {
-    match type_qualifier {
-        TypeQualifier::BangSingle => Variant::VSingle(0.0),
-        TypeQualifier::HashDouble => Variant::VDouble(0.0),
-        TypeQualifier::DollarString => Variant::VString(String::new()),
-        TypeQualifier::PercentInteger => Variant::VInteger(0),
-        TypeQualifier::AmpersandLong => Variant::VLong(0),
-    }
-}
-
-/// Allocates a new Variant that holds a string.
-/// The string is padded with whitespace of the given length.
-pub fn allocate_fixed_length_string(len: usize) -> Variant {
-    Variant::VString(" ".repeat(len))
-}
-
 pub fn allocate_array(
     dimension_args: Vec<i32>,
     element_type: &ExpressionType,
     types: &UserDefinedTypes,
 ) -> Result<Variant, RuntimeError> {
-    let dimensions = to_dimensions(dimension_args)?;
-    Ok(Variant::VArray(Box::new(VArray::new(
-        dimensions,
-        allocate_array_element(element_type, types),
-    ))))
+    rusty_runtime::allocate_array(dimension_args, allocate_array_element(element_type, types))
 }
 
-fn to_dimensions(dimension_args: Vec<i32>) -> Result<Vec<(i32, i32)>, RuntimeError> {
-    let mut dimensions: Vec<(i32, i32)> = vec![];
-    let mut i: usize = 0;
-    while i < dimension_args.len() {
-        let lbound = dimension_args[i];
-        i += 1;
-        let ubound = dimension_args[i];
-        if ubound < lbound {
-            return Err(RuntimeError::SubscriptOutOfRange);
-        }
-        i += 1;
-        dimensions.push((lbound, ubound));
-    }
-    Ok(dimensions)
+/// Calculates the size in bytes of an array before allocating it,
+/// so that the memory limit can be checked.
+/// Every element takes at least one byte, even if it is an empty string.
+pub fn array_byte_size(
+    dimension_args: &[i32],
+    element_type: &ExpressionType,
+    types: &UserDefinedTypes,
+) -> usize {
+    let element_size = allocate_array_element(element_type, types)
+        .byte_size()
+        .max(1);
+    dimension_args
+        .chunks(2)
+        .map(|bounds| (bounds[1] as i64 - bounds[0] as i64 + 1).max(0) as usize)
+        .fold(element_size, usize::saturating_mul)
 }
 
 pub fn allocate_user_defined_type(
@@ -103,20 +84,3 @@ fn allocate_element_type(element_type: &ElementType, types: &UserDefinedTypes) -
         }
     }
 }
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_allocate_string() {
-        assert_eq!(
-            allocate_fixed_length_string(1),
-            Variant::VString(" ".to_owned())
-        );
-        assert_eq!(
-            allocate_fixed_length_string(2),
-            Variant::VString("  ".to_owned())
-        );
-    }
-}
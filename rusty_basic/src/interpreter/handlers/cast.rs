@@ -1,10 +1,10 @@
 use rusty_linter::core::CastVariant;
 use rusty_parser::TypeQualifier;
+use rusty_runtime::fix_length;
 use rusty_variant::Variant;
 
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
-use crate::interpreter::string_utils::fix_length;
 
 pub fn cast<T: InterpreterTrait>(
     interpreter: &mut T,
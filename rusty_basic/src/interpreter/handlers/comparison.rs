@@ -1,5 +1,8 @@
 use std::cmp::Ordering;
 
+use rusty_parser::TypeQualifier;
+use rusty_variant::Variant;
+
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
 
@@ -31,6 +34,74 @@ pub fn greater_or_equal<T: InterpreterTrait>(interpreter: &mut T) -> Result<(),
     })
 }
 
+pub fn less_typed<T: InterpreterTrait>(
+    interpreter: &mut T,
+    q: TypeQualifier,
+) -> Result<(), RuntimeError> {
+    cmp_typed(interpreter, q, |order| order == Ordering::Less)
+}
+
+pub fn less_or_equal_typed<T: InterpreterTrait>(
+    interpreter: &mut T,
+    q: TypeQualifier,
+) -> Result<(), RuntimeError> {
+    cmp_typed(interpreter, q, |order| order != Ordering::Greater)
+}
+
+pub fn equal_typed<T: InterpreterTrait>(
+    interpreter: &mut T,
+    q: TypeQualifier,
+) -> Result<(), RuntimeError> {
+    cmp_typed(interpreter, q, |order| order == Ordering::Equal)
+}
+
+pub fn greater_or_equal_typed<T: InterpreterTrait>(
+    interpreter: &mut T,
+    q: TypeQualifier,
+) -> Result<(), RuntimeError> {
+    cmp_typed(interpreter, q, |order| order != Ordering::Less)
+}
+
+pub fn greater_typed<T: InterpreterTrait>(
+    interpreter: &mut T,
+    q: TypeQualifier,
+) -> Result<(), RuntimeError> {
+    cmp_typed(interpreter, q, |order| order == Ordering::Greater)
+}
+
+pub fn not_equal_typed<T: InterpreterTrait>(
+    interpreter: &mut T,
+    q: TypeQualifier,
+) -> Result<(), RuntimeError> {
+    cmp_typed(interpreter, q, |order| order != Ordering::Equal)
+}
+
+/// Compares registers A and B, which hold values of the given type,
+/// without copying them.
+fn cmp_typed<T: InterpreterTrait, F: FnOnce(Ordering) -> bool>(
+    interpreter: &mut T,
+    q: TypeQualifier,
+    predicate: F,
+) -> Result<(), RuntimeError> {
+    let registers = interpreter.registers();
+    debug_assert_eq!(
+        (registers.a().qualifier(), registers.b().qualifier()),
+        (Some(q), Some(q)),
+        "the registers should hold {:?} values",
+        q
+    );
+    let order = match (q, registers.a(), registers.b()) {
+        (TypeQualifier::PercentInteger, Variant::VInteger(l), Variant::VInteger(r)) => l.cmp(r),
+        (TypeQualifier::AmpersandLong, Variant::VLong(l), Variant::VLong(r)) => l.cmp(r),
+        (TypeQualifier::DollarString, Variant::VString(l), Variant::VString(r)) => l.cmp(r),
+        // floating point numbers are compared approximately
+        (_, a, b) => a.try_cmp(b)?,
+    };
+    let is_true = predicate(order);
+    interpreter.registers_mut().set_a(is_true.into());
+    Ok(())
+}
+
 fn cmp<T: InterpreterTrait, F: FnOnce(Ordering) -> bool>(
     interpreter: &mut T,
     predicate: F,
@@ -1,10 +1,19 @@
+use rusty_parser::TypeQualifier;
 use rusty_variant::{Variant, VariantError};
 
 use crate::RuntimeError;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
+use crate::interpreter::limits::check_allocation;
 
 pub fn plus<T: InterpreterTrait>(interpreter: &mut T) -> Result<(), RuntimeError> {
-    reduce_a_b_into_a(interpreter, |a, b| a.plus(b))
+    reduce_a_b_into_a(interpreter, |a, b| a.plus(b))?;
+    // concatenating strings is the quickest way to use a lot of memory
+    if interpreter.limits().max_memory.is_some()
+        && let Variant::VString(s) = interpreter.registers().a()
+    {
+        check_allocation(interpreter, s.chars().count())?;
+    }
+    Ok(())
 }
 
 pub fn minus<T: InterpreterTrait>(interpreter: &mut T) -> Result<(), RuntimeError> {
@@ -23,9 +32,115 @@ pub fn modulo<T: InterpreterTrait>(interpreter: &mut T) -> Result<(), RuntimeErr
     reduce_a_b_into_a(interpreter, |a, b| a.modulo(b))
 }
 
-fn reduce_a_b_into_a<T: InterpreterTrait, F>(interpreter: &mut T, f: F) -> Result<(), RuntimeError>
+pub fn plus_typed<T: InterpreterTrait>(
+    interpreter: &mut T,
+    q: TypeQualifier,
+) -> Result<(), RuntimeError> {
+    if q == TypeQualifier::DollarString {
+        return plus(interpreter);
+    }
+    reduce_typed(
+        interpreter,
+        q,
+        TypedOps {
+            integer: i32::checked_add,
+            long: i64::checked_add,
+            single: |l, r| l + r,
+            double: |l, r| l + r,
+            fallback: Variant::plus,
+        },
+    )
+}
+
+pub fn minus_typed<T: InterpreterTrait>(
+    interpreter: &mut T,
+    q: TypeQualifier,
+) -> Result<(), RuntimeError> {
+    reduce_typed(
+        interpreter,
+        q,
+        TypedOps {
+            integer: i32::checked_sub,
+            long: i64::checked_sub,
+            single: |l, r| l - r,
+            double: |l, r| l - r,
+            fallback: Variant::minus,
+        },
+    )
+}
+
+pub fn multiply_typed<T: InterpreterTrait>(
+    interpreter: &mut T,
+    q: TypeQualifier,
+) -> Result<(), RuntimeError> {
+    reduce_typed(
+        interpreter,
+        q,
+        TypedOps {
+            integer: i32::checked_mul,
+            long: i64::checked_mul,
+            single: |l, r| l * r,
+            double: |l, r| l * r,
+            fallback: Variant::multiply,
+        },
+    )
+}
+
+/// The implementations of an arithmetic operator, one per numeric type.
+struct TypedOps {
+    integer: fn(i32, i32) -> Option<i32>,
+    long: fn(i64, i64) -> Option<i64>,
+    single: fn(f32, f32) -> f32,
+    double: fn(f64, f64) -> f64,
+    /// Used if the registers do not hold values of the given type,
+    /// which the instruction generator should have prevented.
+    fallback: fn(Variant, Variant) -> Result<Variant, VariantError>,
+}
+
+/// Applies the operator of the given numeric type to registers A and B,
+/// storing the result in register A.
+fn reduce_typed<T: InterpreterTrait>(
+    interpreter: &mut T,
+    q: TypeQualifier,
+    ops: TypedOps,
+) -> Result<(), RuntimeError> {
+    let registers = interpreter.registers();
+    debug_assert_eq!(
+        (registers.a().qualifier(), registers.b().qualifier()),
+        (Some(q), Some(q)),
+        "the registers should hold {:?} values",
+        q
+    );
+    let c = match (q, registers.a(), registers.b()) {
+        (TypeQualifier::PercentInteger, Variant::VInteger(l), Variant::VInteger(r)) => {
+            (ops.integer)(*l, *r)
+                .map(Variant::VInteger)
+                .ok_or(VariantError::Overflow)
+                .and_then(Variant::check_range)?
+        }
+        (TypeQualifier::AmpersandLong, Variant::VLong(l), Variant::VLong(r)) => (ops.long)(*l, *r)
+            .map(Variant::VLong)
+            .ok_or(VariantError::Overflow)
+            .and_then(Variant::check_range)?,
+        (TypeQualifier::BangSingle, Variant::VSingle(l), Variant::VSingle(r)) => {
+            Variant::VSingle((ops.single)(*l, *r))
+        }
+        (TypeQualifier::HashDouble, Variant::VDouble(l), Variant::VDouble(r)) => {
+            Variant::VDouble((ops.double)(*l, *r))
+        }
+        (_, a, b) => (ops.fallback)(a.clone(), b.clone())?,
+    };
+    interpreter.registers_mut().set_a(c);
+    Ok(())
+}
+
+fn reduce_a_b_into_a<T: InterpreterTrait, F, E>(
+    interpreter: &mut T,
+    f: F,
+) -> Result<(), RuntimeError>
 where
-    F: FnOnce(Variant, Variant) -> Result<Variant, VariantError>,
+    F: FnOnce(Variant, Variant) -> Result<Variant, E>,
+    RuntimeError: From<E>,
 {
     let a = interpreter.registers().get_a();
     let b = interpreter.registers().get_b();
@@ -15,6 +15,10 @@ pub fn load_into_a<T: InterpreterTrait>(interpreter: &mut T, v: &Variant) {
     interpreter.registers_mut().set_a(v.clone());
 }
 
+pub fn load_into_b<T: InterpreterTrait>(interpreter: &mut T, v: &Variant) {
+    interpreter.registers_mut().set_b(v.clone());
+}
+
 pub fn copy_a_to_b<T: InterpreterTrait>(interpreter: &mut T) {
     interpreter.registers_mut().copy_a_to_b();
 }
@@ -1,4 +1,6 @@
-use rusty_parser::{Name, Parameter};
+use std::rc::Rc;
+
+use rusty_parser::Name;
 
 use crate::interpreter::interpreter_trait::InterpreterTrait;
 
@@ -19,14 +21,13 @@ pub fn dequeue_from_return_stack<T: InterpreterTrait>(interpreter: &mut T) {
     interpreter.registers_mut().set_a(v);
 }
 
-pub fn stash_function_return_value<T: InterpreterTrait>(interpreter: &mut T, function_name: &Name) {
-    debug_assert!(!function_name.is_bare());
-    let name = function_name.clone();
+pub fn stash_function_return_value<T: InterpreterTrait>(interpreter: &mut T, slot: usize) {
     let v = interpreter
-        .context_mut()
-        .variables_mut()
-        .get_or_create(name)
-        .clone();
+        .context()
+        .variables()
+        .get(slot)
+        .cloned()
+        .expect("Function result not defined");
     interpreter.set_function_result(v);
 }
 
@@ -57,10 +58,10 @@ pub fn push_unnamed_arg_by_ref<T: InterpreterTrait>(interpreter: &mut T) {
         .push_unnamed_by_ref(v, path);
 }
 
-pub fn push_a_to_named_arg<T: InterpreterTrait>(interpreter: &mut T, param_name: &Parameter) {
+pub fn push_a_to_named_arg<T: InterpreterTrait>(interpreter: &mut T, param_name: &Rc<Name>) {
     let v = interpreter.registers().get_a();
     interpreter
         .context_mut()
         .arguments_mut()
-        .push_named(param_name.clone(), v);
+        .push_named(Rc::clone(param_name), v);
 }
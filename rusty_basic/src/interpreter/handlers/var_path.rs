@@ -1,12 +1,15 @@
 use rusty_linter::core::QBNumberCast;
-use rusty_parser::BareName;
+use rusty_parser::{BareName, Name};
 use rusty_variant::Variant;
 
 use crate::RuntimeError;
 use crate::instruction_generator::{Path, RootPath};
 use crate::interpreter::interpreter_trait::InterpreterTrait;
+use crate::interpreter::variables::Variables;
 
-pub fn var_path_name<T: InterpreterTrait>(interpreter: &mut T, root_path: RootPath) {
+pub fn var_path_name<T: InterpreterTrait>(interpreter: &mut T, root_path: RootPath, name: &Name) {
+    // the path is resolved later, after the variable is defined
+    variables_of(interpreter, root_path.shared).get_or_insert_default(root_path.slot, name);
     interpreter
         .var_path_stack()
         .push_back(Path::Root(root_path));
@@ -48,6 +51,45 @@ pub fn copy_var_path_to_a<T: InterpreterTrait>(interpreter: &mut T) -> Result<()
     Ok(())
 }
 
+pub fn copy_variable_to_a<T: InterpreterTrait>(
+    interpreter: &mut T,
+    root_path: RootPath,
+    name: &Name,
+) {
+    let v = variables_of(interpreter, root_path.shared)
+        .get_or_insert_default(root_path.slot, name)
+        .clone();
+    interpreter.registers_mut().set_a(v);
+}
+
+pub fn copy_variable_to_b<T: InterpreterTrait>(
+    interpreter: &mut T,
+    root_path: RootPath,
+    name: &Name,
+) {
+    let v = variables_of(interpreter, root_path.shared)
+        .get_or_insert_default(root_path.slot, name)
+        .clone();
+    interpreter.registers_mut().set_b(v);
+}
+
+pub fn copy_a_to_variable<T: InterpreterTrait>(
+    interpreter: &mut T,
+    root_path: RootPath,
+    name: &Name,
+) {
+    let a = interpreter.registers().get_a();
+    variables_of(interpreter, root_path.shared).set(root_path.slot, name, a);
+}
+
+fn variables_of<T: InterpreterTrait>(interpreter: &mut T, shared: bool) -> &mut Variables {
+    if shared {
+        interpreter.context_mut().global_variables_mut()
+    } else {
+        interpreter.context_mut().variables_mut()
+    }
+}
+
 pub fn pop_var_path<T: InterpreterTrait>(interpreter: &mut T) -> Result<(), RuntimeError> {
     interpreter
         .var_path_stack()
@@ -73,14 +115,9 @@ fn resolve_some_name_ptr_mut<T: InterpreterTrait>(
     name_ptr: Path,
 ) -> Result<&mut Variant, RuntimeError> {
     match name_ptr {
-        Path::Root(RootPath { name, shared }) => {
-            let variables = if shared {
-                interpreter.context_mut().global_variables_mut()
-            } else {
-                interpreter.context_mut().variables_mut()
-            };
-            Ok(variables.get_or_create(name))
-        }
+        Path::Root(RootPath { slot, shared }) => Ok(variables_of(interpreter, shared)
+            .get_mut(slot)
+            .expect("Variable should have been defined by VarPathName")),
         Path::ArrayElement(parent_name_ptr, indices) => {
             let parent_variant = resolve_some_name_ptr_mut(interpreter, *parent_name_ptr)?;
             resolve_array_mut(parent_variant, indices)
@@ -1,5 +1,7 @@
 use std::collections::VecDeque;
 
+use rusty_parser::UserDefinedTypes;
+use rusty_runtime::{FileManager, Input, Printer};
 use rusty_variant::Variant;
 
 use crate::RuntimeErrorPos;
@@ -7,9 +9,15 @@ use crate::instruction_generator::{InstructionGeneratorResult, Path};
 use crate::interpreter::Stdlib;
 use crate::interpreter::context::{Context, VAR_SEG_BASE};
 use crate::interpreter::data_segment::DataSegment;
-use crate::interpreter::io::{FileManager, Input, Printer};
+use crate::interpreter::debugger::Debugger;
+use crate::interpreter::keyboard::KeyboardBuffer;
+use crate::interpreter::limits::ExecutionLimits;
+use crate::interpreter::native::NativeSubprograms;
+use crate::interpreter::profiler::Profiler;
 use crate::interpreter::registers::{RegisterStack, Registers};
+use crate::interpreter::run_state::RunState;
 use crate::interpreter::screen::Screen;
+use crate::interpreter::snapshot::SnapshotError;
 
 pub trait InterpreterTrait {
     type TStdlib: Stdlib;
@@ -39,6 +47,9 @@ pub trait InterpreterTrait {
 
     fn screen_mut(&mut self) -> &mut dyn Screen;
 
+    /// Abstracts the keyboard (used by `INKEY$` and event trapping)
+    fn keyboard_mut(&mut self) -> &mut KeyboardBuffer;
+
     /// Contains variables and constants, collects function/sub arguments.
     fn context(&self) -> &Context;
 
@@ -77,8 +88,84 @@ pub trait InterpreterTrait {
 
     fn get_last_error_code(&self) -> Option<i32>;
 
+    /// Gets the source line of the statement that caused the most recent error.
+    fn get_last_error_line(&self) -> Option<u32>;
+
+    /// Gets the code of the most recent device error.
+    fn get_last_device_error_code(&self) -> Option<i32>;
+
+    /// Runs the given program.
+    ///
+    /// Global variables and open files survive the run,
+    /// so the same interpreter can run more programs afterwards.
     fn interpret(
         &mut self,
         instruction_generator_result: InstructionGeneratorResult,
     ) -> Result<(), RuntimeErrorPos>;
+
+    /// Runs the given program under the given debugger.
+    fn debug(
+        &mut self,
+        instruction_generator_result: InstructionGeneratorResult,
+        debugger: &mut dyn Debugger,
+    ) -> Result<(), RuntimeErrorPos>;
+
+    /// Runs the given program, counting the executed instructions
+    /// and measuring the time spent per line and per subprogram.
+    fn profile(
+        &mut self,
+        instruction_generator_result: InstructionGeneratorResult,
+        profiler: &mut Profiler,
+    ) -> Result<(), RuntimeErrorPos>;
+
+    /// Loads the given program, without running it.
+    /// The program runs with calls to [Self::resume].
+    ///
+    /// A program that was started earlier but has not finished is discarded.
+    fn start(&mut self, instruction_generator_result: InstructionGeneratorResult);
+
+    /// Continues the program that was loaded with [Self::start],
+    /// running at most the given number of instructions.
+    ///
+    /// The program also pauses before it would block waiting for input
+    /// (`INPUT`, `LINE INPUT` and `INKEY$`). The input is supplied through
+    /// [Self::stdin] and [Self::keyboard_mut] before resuming.
+    /// Only a standard input that implements [Input::is_line_ready]
+    /// (e.g. `rusty_runtime::InputQueue`) can make `INPUT` wait this way.
+    fn resume(&mut self, max_instructions: u64) -> Result<RunState, RuntimeErrorPos>;
+
+    /// Saves the state of the program that was started with [Self::start],
+    /// including the open files (their names and positions, not their contents).
+    fn snapshot(&mut self) -> Result<Vec<u8>, SnapshotError>;
+
+    /// Continues the given program from a snapshot that was saved with
+    /// [Self::snapshot] while running the same program, even by another interpreter.
+    /// The program runs with calls to [Self::resume].
+    ///
+    /// Files that were open are opened again (subject to the file policy)
+    /// and files that are open in this interpreter are closed.
+    /// The user defined types of the program need to be set separately.
+    fn restore(
+        &mut self,
+        instruction_generator_result: InstructionGeneratorResult,
+        snapshot: &[u8],
+    ) -> Result<(), SnapshotError>;
+
+    /// Replaces the user defined types, e.g. when the next program to run
+    /// defines new types.
+    fn set_user_defined_types(&mut self, user_defined_types: UserDefinedTypes);
+
+    /// Gets the limits that are enforced while a program runs.
+    fn limits(&self) -> &ExecutionLimits;
+
+    /// Sets the limits that are enforced while a program runs,
+    /// e.g. in order to run an untrusted program.
+    fn set_limits(&mut self, limits: ExecutionLimits);
+
+    /// Gets the SUBs and FUNCTIONs that are implemented by the embedding application.
+    fn natives(&self) -> &NativeSubprograms;
+
+    /// Gets the SUBs and FUNCTIONs that are implemented by the embedding application,
+    /// e.g. in order to add more of them.
+    fn natives_mut(&mut self) -> &mut NativeSubprograms;
 }
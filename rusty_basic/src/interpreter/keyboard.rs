@@ -1,5 +1,174 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::Duration;
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, poll, read};
+
 use crate::RuntimeError;
 
+/// Abstracts the source of keyboard events.
+pub trait Keyboard {
+    /// Waits up to the given timeout for an event.
+    fn poll_event(&mut self, timeout: Duration) -> Result<Option<Event>, RuntimeError>;
+}
+
+/// Reads the keyboard of the terminal.
+#[derive(Default)]
+pub struct CrossTermKeyboard;
+
+impl Keyboard for CrossTermKeyboard {
+    fn poll_event(&mut self, timeout: Duration) -> Result<Option<Event>, RuntimeError> {
+        if poll(timeout)? {
+            Ok(Some(read()?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// A keyboard without any key presses, for when the standard input is not a terminal.
+#[derive(Default)]
+pub struct NoKeyboard;
+
+impl Keyboard for NoKeyboard {
+    fn poll_event(&mut self, timeout: Duration) -> Result<Option<Event>, RuntimeError> {
+        std::thread::sleep(timeout);
+        Ok(None)
+    }
+}
+
+/// Holds keys that have been read from the keyboard but not consumed yet
+/// (e.g. while checking for trapped keys), as well as the key definitions
+/// of the `KEY` statement.
+///
+/// Keys are represented as strings, in the same way that `INKEY$` returns them.
+pub struct KeyboardBuffer {
+    keyboard: Box<dyn Keyboard>,
+    buffer: VecDeque<String>,
+    /// Soft key assignments of the function keys (`KEY n, text`), by key number.
+    soft_keys: BTreeMap<u8, String>,
+    /// Scan codes of the user defined trappable keys (`KEY 15..25, text`), by key number.
+    user_keys: HashMap<u8, u8>,
+}
+
+/// The maximum length of a soft key assignment.
+const SOFT_KEY_MAX_LEN: usize = 15;
+
+impl KeyboardBuffer {
+    pub fn new(keyboard: Box<dyn Keyboard>) -> Self {
+        Self {
+            keyboard,
+            buffer: VecDeque::new(),
+            soft_keys: BTreeMap::new(),
+            user_keys: HashMap::new(),
+        }
+    }
+
+    /// Reads the next key, waiting up to the given timeout if the buffer is empty.
+    /// Returns an empty string if no key was pressed.
+    pub fn read_key(&mut self, timeout: Duration) -> Result<String, RuntimeError> {
+        if self.buffer.is_empty()
+            && let Some(event) = self.keyboard.poll_event(timeout)?
+        {
+            self.push(handle_event(event));
+        }
+        Ok(self.buffer.pop_front().unwrap_or_default())
+    }
+
+    /// Checks if a key can be read without waiting for it.
+    pub fn has_key(&mut self) -> Result<bool, RuntimeError> {
+        if self.buffer.is_empty()
+            && let Some(event) = self.keyboard.poll_event(Duration::ZERO)?
+        {
+            self.push(handle_event(event));
+        }
+        Ok(!self.buffer.is_empty())
+    }
+
+    /// Reads a key directly from the keyboard, bypassing the buffer, without waiting.
+    /// Events that do not represent a key press are skipped.
+    pub fn poll_unbuffered(&mut self) -> Result<Option<String>, RuntimeError> {
+        while let Some(event) = self.keyboard.poll_event(Duration::ZERO)? {
+            let key = handle_event(event);
+            if !key.is_empty() {
+                return Ok(Some(key));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Adds a key at the end of the buffer.
+    /// A function key with a soft key assignment is replaced by the assigned text.
+    pub fn push(&mut self, key: String) {
+        match function_key_number(&key).and_then(|n| self.soft_keys.get(&n)) {
+            Some(text) => self.buffer.extend(text.chars().map(String::from)),
+            None if !key.is_empty() => self.buffer.push_back(key),
+            None => {}
+        }
+    }
+
+    /// Implements `KEY n, text`.
+    ///
+    /// For the function keys (1-10, 30, 31), it assigns a soft key string.
+    /// An empty string clears the assignment.
+    ///
+    /// For the user defined keys (15-25), the text must be `CHR$(flags) + CHR$(scan-code)`.
+    /// The keyboard flags (shift state) are ignored, only the scan code is matched.
+    pub fn define_key(&mut self, key_number: i32, text: &str) -> Result<(), RuntimeError> {
+        match key_number {
+            1..=10 | 30 | 31 => {
+                let text: String = text.chars().take(SOFT_KEY_MAX_LEN).collect();
+                if text.is_empty() {
+                    self.soft_keys.remove(&(key_number as u8));
+                } else {
+                    self.soft_keys.insert(key_number as u8, text);
+                }
+                Ok(())
+            }
+            15..=25 => {
+                let chars: Vec<char> = text.chars().collect();
+                if chars.len() == 2 {
+                    self.user_keys.insert(key_number as u8, chars[1] as u8);
+                    Ok(())
+                } else {
+                    Err(RuntimeError::IllegalFunctionCall)
+                }
+            }
+            _ => Err(RuntimeError::IllegalFunctionCall),
+        }
+    }
+
+    /// Gets the soft key assignments of all function keys (for `KEY LIST`),
+    /// as pairs of key name (e.g. `F1`) and assigned text.
+    pub fn soft_keys(&self) -> Vec<(String, String)> {
+        (1..=10)
+            .chain(30..=31)
+            .map(|key_number| {
+                let f = if key_number <= 10 {
+                    key_number
+                } else {
+                    key_number - 19
+                };
+                (
+                    format!("F{}", f),
+                    self.soft_keys.get(&key_number).cloned().unwrap_or_default(),
+                )
+            })
+            .collect()
+    }
+
+    /// Checks if the given key is the one identified by the given key number,
+    /// as used in `ON KEY(n)`.
+    pub fn is_key_number(&self, key_number: u8, key: &str) -> bool {
+        match key_number {
+            15..=25 => self
+                .user_keys
+                .get(&key_number)
+                .is_some_and(|scan| scan_code(key) == Some(*scan)),
+            _ => trappable_key(key_number).is_some_and(|k| k == key),
+        }
+    }
+}
+
 #[cfg(windows)]
 pub unsafe fn get_indicator_keys() -> Result<u8, RuntimeError> {
     windows_impl::get_indicator_keys()
@@ -10,18 +179,164 @@ pub unsafe fn set_indicator_keys(flags: u8) -> Result<(), RuntimeError> {
     windows_impl::set_indicator_keys(flags)
 }
 
-#[cfg(not(windows))]
+#[cfg(target_os = "linux")]
+pub unsafe fn get_indicator_keys() -> Result<u8, RuntimeError> {
+    linux_impl::get_indicator_keys()
+}
+
+#[cfg(target_os = "linux")]
+pub unsafe fn set_indicator_keys(flags: u8) -> Result<(), RuntimeError> {
+    linux_impl::set_indicator_keys(flags)
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
 pub unsafe fn get_indicator_keys() -> Result<u8, RuntimeError> {
     // TODO implement get_indicator_keys for other platforms
     Ok(0)
 }
 
-#[cfg(not(windows))]
+#[cfg(not(any(windows, target_os = "linux")))]
 pub unsafe fn set_indicator_keys(_flags: u8) -> Result<(), RuntimeError> {
     // TODO implement set_indicator_keys for other platforms
     Ok(())
 }
 
+fn handle_event(event: Event) -> String {
+    match event {
+        Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind,
+            ..
+        }) if kind != KeyEventKind::Release => handle_key(code, modifiers),
+        _ => String::new(),
+    }
+}
+
+/// Maps a key to the string that `INKEY$` returns.
+/// Special keys return two characters, `CHR$(0)` followed by the scan code.
+fn handle_key(code: KeyCode, modifiers: KeyModifiers) -> String {
+    let shift = modifiers.contains(KeyModifiers::SHIFT);
+    let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+    let alt = modifiers.contains(KeyModifiers::ALT);
+    match code {
+        KeyCode::Char(ch) if alt => alt_char_scan_code(ch).map(extended).unwrap_or_default(),
+        KeyCode::Char(ch) if ctrl => {
+            if ch.is_ascii_alphabetic() {
+                // CTRL+A is CHR$(1) etc
+                String::from((ch.to_ascii_uppercase() as u8 - b'A' + 1) as char)
+            } else {
+                String::new()
+            }
+        }
+        KeyCode::Char(ch) => String::from(ch),
+        KeyCode::Enter => String::from(13_u8 as char),
+        KeyCode::Tab if shift => extended(15),
+        KeyCode::Tab => String::from(9 as char),
+        KeyCode::BackTab => extended(15),
+        KeyCode::Esc => String::from(27 as char),
+        KeyCode::Backspace => String::from(8 as char),
+        KeyCode::F(f @ 1..=10) => extended(f + modified(modifiers, 58, 83, 93, 103)),
+        KeyCode::F(f @ 11..=12) => extended(f - 11 + modified(modifiers, 133, 135, 137, 139)),
+        KeyCode::Home => extended(if ctrl { 119 } else { 71 }),
+        KeyCode::Up => extended(if ctrl { 141 } else { 72 }),
+        KeyCode::PageUp => extended(if ctrl { 132 } else { 73 }),
+        KeyCode::Left => extended(if ctrl { 115 } else { 75 }),
+        KeyCode::Right => extended(if ctrl { 116 } else { 77 }),
+        KeyCode::End => extended(if ctrl { 117 } else { 79 }),
+        KeyCode::Down => extended(if ctrl { 145 } else { 80 }),
+        KeyCode::PageDown => extended(if ctrl { 118 } else { 81 }),
+        KeyCode::Insert => extended(if ctrl { 146 } else { 82 }),
+        KeyCode::Delete => extended(if ctrl { 147 } else { 83 }),
+        _ => String::new(),
+    }
+}
+
+/// Picks the scan code base of a function key, depending on the modifier keys.
+fn modified(modifiers: KeyModifiers, normal: u8, shift: u8, ctrl: u8, alt: u8) -> u8 {
+    if modifiers.contains(KeyModifiers::ALT) {
+        alt
+    } else if modifiers.contains(KeyModifiers::CONTROL) {
+        ctrl
+    } else if modifiers.contains(KeyModifiers::SHIFT) {
+        shift
+    } else {
+        normal
+    }
+}
+
+/// Creates the two character string of an extended key.
+fn extended(scan_code: u8) -> String {
+    let mut s = String::new();
+    s.push(0 as char);
+    s.push(scan_code as char);
+    s
+}
+
+/// The scan codes that ALT combined with a letter or digit returns.
+fn alt_char_scan_code(ch: char) -> Option<u8> {
+    match ch {
+        '1'..='9' => Some(ch as u8 - b'1' + 120),
+        '0' => Some(129),
+        '-' => Some(130),
+        '=' => Some(131),
+        _ => letter_scan_code(ch),
+    }
+}
+
+/// The scan codes of the letter keys.
+fn letter_scan_code(ch: char) -> Option<u8> {
+    const ROWS: [(&str, u8); 3] = [("QWERTYUIOP", 16), ("ASDFGHJKL", 30), ("ZXCVBNM", 44)];
+    let ch = ch.to_ascii_uppercase();
+    ROWS.iter()
+        .find_map(|(row, first_scan_code)| row.find(ch).map(|index| first_scan_code + index as u8))
+}
+
+/// Gets the scan code of the key represented by the given `INKEY$` string.
+fn scan_code(key: &str) -> Option<u8> {
+    let mut chars = key.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some('\0'), Some(scan_code), None) => Some(scan_code as u8),
+        (Some(ch), None, None) => match ch {
+            '\u{1b}' => Some(1),
+            '1'..='9' => Some(ch as u8 - b'1' + 2),
+            '0' => Some(11),
+            '\u{8}' => Some(14),
+            '\t' => Some(15),
+            '\r' => Some(28),
+            ' ' => Some(57),
+            _ => letter_scan_code(ch),
+        },
+        _ => None,
+    }
+}
+
+/// Gets the `INKEY$` representation of the predefined trappable keys.
+///
+/// Key numbers 1-10 are the function keys F1-F10,
+/// 11-14 are the cursor keys (up, left, right, down),
+/// 30 and 31 are the function keys F11 and F12.
+fn trappable_key(key_number: u8) -> Option<String> {
+    let scan_code: u8 = match key_number {
+        1..=10 => 58 + key_number,
+        11 => 72,
+        12 => 75,
+        13 => 77,
+        14 => 80,
+        30 => 133,
+        31 => 134,
+        _ => return None,
+    };
+    Some(extended(scan_code))
+}
+
+/// Gets the key number of the function key represented by the given `INKEY$` string.
+fn function_key_number(key: &str) -> Option<u8> {
+    (1..=10)
+        .chain(30..=31)
+        .find(|key_number| trappable_key(*key_number).is_some_and(|k| k == key))
+}
+
 #[cfg(windows)]
 mod windows_impl {
     extern crate winapi;
@@ -93,3 +408,284 @@ mod windows_impl {
         result
     }
 }
+
+#[cfg(target_os = "linux")]
+mod linux_impl {
+    //! Uses the keyboard LEDs exposed by the kernel under `/sys/class/leds`
+    //! (e.g. `input3::numlock/brightness`).
+
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::RuntimeError;
+
+    const LEDS_DIR: &str = "/sys/class/leds";
+
+    const KEYS_FLAGS: [(&str, u8); 3] = [("numlock", 1), ("capslock", 2), ("scrolllock", 4)];
+
+    pub fn get_indicator_keys() -> Result<u8, RuntimeError> {
+        let mut result: u8 = 0;
+        for (led, flag) in &KEYS_FLAGS {
+            if is_led_on(led)? {
+                result |= *flag;
+            }
+        }
+        Ok(result)
+    }
+
+    /// Writing to the LEDs requires elevated permissions,
+    /// so this is best effort and failures are ignored.
+    pub fn set_indicator_keys(flags: u8) -> Result<(), RuntimeError> {
+        for (led, flag) in &KEYS_FLAGS {
+            let value = if flags & flag != 0 { "1" } else { "0" };
+            for path in brightness_paths(led)? {
+                let _ = fs::write(path, value);
+            }
+        }
+        Ok(())
+    }
+
+    fn is_led_on(led: &str) -> Result<bool, RuntimeError> {
+        for path in brightness_paths(led)? {
+            let brightness = fs::read_to_string(path)?;
+            if brightness.trim() != "0" {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Finds the brightness files of the given LED, for all keyboards.
+    fn brightness_paths(led: &str) -> Result<Vec<PathBuf>, RuntimeError> {
+        let suffix = format!("::{}", led);
+        match fs::read_dir(LEDS_DIR) {
+            Ok(entries) => {
+                let mut result: Vec<PathBuf> = vec![];
+                for entry in entries {
+                    let entry = entry?;
+                    if entry.file_name().to_string_lossy().ends_with(&suffix) {
+                        result.push(entry.path().join("brightness"));
+                    }
+                }
+                Ok(result)
+            }
+            // no LEDs (e.g. running in a container)
+            Err(_) => Ok(vec![]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+
+    use super::{handle_event, handle_key, scan_code};
+
+    #[test]
+    fn test_mapping_lowercase_letters() {
+        for ch in 'a'..='z' {
+            assert_eq!(
+                handle_key(KeyCode::Char(ch), KeyModifiers::NONE),
+                String::from(ch)
+            );
+        }
+    }
+
+    #[test]
+    fn test_mapping_uppercase_letters() {
+        for ch in 'A'..='Z' {
+            assert_eq!(
+                handle_key(KeyCode::Char(ch), KeyModifiers::SHIFT),
+                String::from(ch)
+            );
+        }
+    }
+
+    #[test]
+    fn test_mapping_function_keys() {
+        assert_eq!(
+            handle_key(KeyCode::F(2), KeyModifiers::NONE),
+            String::from("\0<")
+        );
+        assert_eq!(
+            handle_key(KeyCode::F(3), KeyModifiers::NONE),
+            String::from("\0=")
+        );
+    }
+
+    #[test]
+    fn test_backspace() {
+        assert_eq!(
+            handle_key(KeyCode::Backspace, KeyModifiers::NONE),
+            String::from(8 as char)
+        );
+    }
+
+    #[test]
+    fn test_enter() {
+        assert_eq!(
+            handle_key(KeyCode::Enter, KeyModifiers::NONE),
+            String::from(13 as char)
+        );
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            handle_key(KeyCode::Esc, KeyModifiers::NONE),
+            String::from(27 as char)
+        );
+    }
+
+    #[test]
+    fn test_up_arrow() {
+        assert_eq!(
+            handle_key(KeyCode::Up, KeyModifiers::NONE),
+            String::from("\0H")
+        );
+    }
+
+    #[test]
+    fn test_down_arrow() {
+        assert_eq!(
+            handle_key(KeyCode::Down, KeyModifiers::NONE),
+            String::from("\0P")
+        );
+    }
+
+    #[test]
+    fn test_left_arrow() {
+        assert_eq!(
+            handle_key(KeyCode::Left, KeyModifiers::NONE),
+            String::from("\0K")
+        );
+    }
+
+    #[test]
+    fn test_right_arrow() {
+        assert_eq!(
+            handle_key(KeyCode::Right, KeyModifiers::NONE),
+            String::from("\0M")
+        );
+    }
+
+    #[test]
+    fn test_tab() {
+        assert_eq!(
+            handle_key(KeyCode::Tab, KeyModifiers::NONE),
+            String::from(9 as char)
+        );
+    }
+
+    #[test]
+    fn test_shift_tab() {
+        assert_eq!(
+            handle_key(KeyCode::Tab, KeyModifiers::SHIFT),
+            String::from("\0\u{f}")
+        );
+    }
+
+    #[test]
+    fn test_function_keys_with_modifiers() {
+        assert_eq!(
+            handle_key(KeyCode::F(1), KeyModifiers::SHIFT),
+            String::from("\0T")
+        );
+        assert_eq!(
+            handle_key(KeyCode::F(1), KeyModifiers::CONTROL),
+            String::from("\0^")
+        );
+        assert_eq!(
+            handle_key(KeyCode::F(1), KeyModifiers::ALT),
+            String::from("\0h")
+        );
+    }
+
+    #[test]
+    fn test_f11_f12() {
+        assert_eq!(
+            handle_key(KeyCode::F(11), KeyModifiers::NONE),
+            String::from("\0\u{85}")
+        );
+        assert_eq!(
+            handle_key(KeyCode::F(12), KeyModifiers::NONE),
+            String::from("\0\u{86}")
+        );
+    }
+
+    #[test]
+    fn test_navigation_keys() {
+        assert_eq!(
+            handle_key(KeyCode::Home, KeyModifiers::NONE),
+            String::from("\0G")
+        );
+        assert_eq!(
+            handle_key(KeyCode::End, KeyModifiers::NONE),
+            String::from("\0O")
+        );
+        assert_eq!(
+            handle_key(KeyCode::PageUp, KeyModifiers::NONE),
+            String::from("\0I")
+        );
+        assert_eq!(
+            handle_key(KeyCode::PageDown, KeyModifiers::NONE),
+            String::from("\0Q")
+        );
+        assert_eq!(
+            handle_key(KeyCode::Insert, KeyModifiers::NONE),
+            String::from("\0R")
+        );
+        assert_eq!(
+            handle_key(KeyCode::Delete, KeyModifiers::NONE),
+            String::from("\0S")
+        );
+        assert_eq!(
+            handle_key(KeyCode::Left, KeyModifiers::CONTROL),
+            String::from("\0s")
+        );
+    }
+
+    #[test]
+    fn test_back_tab() {
+        assert_eq!(
+            handle_key(KeyCode::BackTab, KeyModifiers::SHIFT),
+            String::from("\0\u{f}")
+        );
+    }
+
+    #[test]
+    fn test_ctrl_letter() {
+        assert_eq!(
+            handle_key(KeyCode::Char('c'), KeyModifiers::CONTROL),
+            String::from(3 as char)
+        );
+    }
+
+    #[test]
+    fn test_alt_letter_and_digit() {
+        assert_eq!(
+            handle_key(KeyCode::Char('x'), KeyModifiers::ALT),
+            String::from("\0-")
+        );
+        assert_eq!(
+            handle_key(KeyCode::Char('1'), KeyModifiers::ALT),
+            String::from("\0x")
+        );
+    }
+
+    #[test]
+    fn test_key_release_is_ignored() {
+        let mut key_event = KeyEvent::from(KeyCode::Char('a'));
+        key_event.kind = KeyEventKind::Release;
+        assert_eq!(handle_event(Event::Key(key_event)), "");
+    }
+
+    #[test]
+    fn test_scan_code() {
+        assert_eq!(scan_code("\0H"), Some(72));
+        assert_eq!(scan_code("a"), Some(30));
+        assert_eq!(scan_code("1"), Some(2));
+        assert_eq!(scan_code("\r"), Some(28));
+        assert_eq!(scan_code("ab"), None);
+    }
+}
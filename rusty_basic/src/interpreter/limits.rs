@@ -0,0 +1,294 @@
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use rusty_runtime::QByteSize;
+
+use crate::RuntimeError;
+use crate::interpreter::interpreter_trait::InterpreterTrait;
+
+/// How often the limits that are expensive to check (time and memory)
+/// are checked, in executed instructions.
+const CHECK_INTERVAL: u64 = 256;
+
+/// The maximum number of nested calls to subprograms,
+/// when [ExecutionLimits::max_call_depth] is not set.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 10_000;
+
+/// Limits that the interpreter enforces while running a program,
+/// in order to run untrusted programs safely.
+///
+/// By default, there are no limits, apart from [DEFAULT_MAX_CALL_DEPTH].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExecutionLimits {
+    /// The maximum number of instructions to run.
+    pub max_instructions: Option<u64>,
+
+    /// The maximum time to run, measured with [crate::interpreter::Stdlib::now].
+    pub timeout: Option<Duration>,
+
+    /// The maximum size of all variables, as calculated by `QByteSize`
+    /// (e.g. an `INTEGER` takes 2 bytes, a string takes a byte per character).
+    pub max_memory: Option<usize>,
+
+    /// The maximum number of nested calls to subprograms.
+    /// Exceeding it raises `Out of stack space`, which the program
+    /// can handle with `ON ERROR`. When not set, [DEFAULT_MAX_CALL_DEPTH]
+    /// applies, so that a runaway recursion does not use up the memory.
+    pub max_call_depth: Option<usize>,
+
+    /// The access to files and environment variables.
+    pub file_policy: FilePolicy,
+}
+
+/// Controls what the program can do with files (`OPEN`, `KILL`, `NAME`)
+/// and with environment variables (`ENVIRON`, `ENVIRON$`).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum FilePolicy {
+    /// Files and environment variables can be used without restrictions.
+    #[default]
+    Allow,
+
+    /// Files and environment variables cannot be used at all.
+    Deny,
+
+    /// Files can only be opened `FOR INPUT` and environment variables
+    /// can only be read.
+    ReadOnly,
+
+    /// Files can only be used inside the given directory. File names are
+    /// relative to that directory and cannot refer to a parent directory.
+    Confined(PathBuf),
+}
+
+impl FilePolicy {
+    /// Gets the path of the given file, if the policy allows to access it.
+    pub fn resolve(&self, file_name: &str, write: bool) -> Result<PathBuf, RuntimeError> {
+        match self {
+            Self::Allow => Ok(PathBuf::from(file_name)),
+            Self::Deny => Err(RuntimeError::PermissionDenied),
+            Self::ReadOnly if write => Err(RuntimeError::PermissionDenied),
+            Self::ReadOnly => Ok(PathBuf::from(file_name)),
+            Self::Confined(root) => {
+                let path = Path::new(file_name);
+                if path
+                    .components()
+                    .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+                {
+                    Ok(root.join(path))
+                } else {
+                    Err(RuntimeError::PermissionDenied)
+                }
+            }
+        }
+    }
+
+    /// Checks if the policy allows to access a path that was resolved earlier
+    /// by [Self::resolve], e.g. when a snapshot opens its files again.
+    pub fn check_resolved(&self, path: &Path, write: bool) -> Result<(), RuntimeError> {
+        match self {
+            Self::Confined(root) => match path.strip_prefix(root) {
+                Ok(relative) => self.resolve(&relative.to_string_lossy(), write).map(|_| ()),
+                Err(_) => Err(RuntimeError::PermissionDenied),
+            },
+            _ => self.resolve(&path.to_string_lossy(), write).map(|_| ()),
+        }
+    }
+
+    /// Checks if the policy allows to read (`ENVIRON$`) or to
+    /// set (`ENVIRON`) environment variables.
+    pub fn check_environ(&self, write: bool) -> Result<(), RuntimeError> {
+        match self {
+            Self::Allow | Self::Confined(_) => Ok(()),
+            Self::ReadOnly if !write => Ok(()),
+            _ => Err(RuntimeError::PermissionDenied),
+        }
+    }
+}
+
+/// Enforces the instruction count, the time and the memory limits while a program runs.
+#[derive(Debug)]
+pub(crate) struct LimitsChecker {
+    max_instructions: Option<u64>,
+    deadline: Option<Duration>,
+    max_memory: Option<usize>,
+    instructions: u64,
+}
+
+impl LimitsChecker {
+    /// Creates a checker for a program that starts now,
+    /// unless there are no limits to check while the program runs.
+    pub fn new(limits: &ExecutionLimits, now: impl FnOnce() -> Duration) -> Option<Self> {
+        if limits.max_instructions.is_none()
+            && limits.timeout.is_none()
+            && limits.max_memory.is_none()
+        {
+            return None;
+        }
+        Some(Self {
+            max_instructions: limits.max_instructions,
+            deadline: limits.timeout.map(|timeout| now() + timeout),
+            max_memory: limits.max_memory,
+            instructions: 0,
+        })
+    }
+
+    /// Called before an instruction runs.
+    ///
+    /// The current time and the size of all variables are only
+    /// calculated every few instructions, because it is expensive.
+    pub fn check(
+        &mut self,
+        now: impl FnOnce() -> Duration,
+        memory: impl FnOnce() -> usize,
+    ) -> Result<(), RuntimeError> {
+        self.instructions += 1;
+        if self
+            .max_instructions
+            .is_some_and(|max_instructions| self.instructions > max_instructions)
+        {
+            return Err(RuntimeError::InstructionLimitExceeded);
+        }
+        if !self.instructions.is_multiple_of(CHECK_INTERVAL) {
+            return Ok(());
+        }
+        if self.deadline.is_some_and(|deadline| now() > deadline) {
+            return Err(RuntimeError::TimeLimitExceeded);
+        }
+        check_memory(self.max_memory, memory())
+    }
+}
+
+/// Checks that the variables, together with a new value of the given size
+/// in bytes (e.g. an array of `DIM` or the result of a concatenation),
+/// do not exceed the memory limit.
+///
+/// The periodic check of [LimitsChecker] could miss a big allocation
+/// of a short program, which ends before the next check.
+pub(crate) fn check_allocation<T: InterpreterTrait>(
+    interpreter: &T,
+    size: usize,
+) -> Result<(), RuntimeError> {
+    let max_memory = interpreter.limits().max_memory;
+    if max_memory.is_none() {
+        // the size of the variables is expensive to calculate
+        return Ok(());
+    }
+    check_memory(max_memory, interpreter.context().byte_size() + size)
+}
+
+/// Checks that the given size in bytes does not exceed the memory limit.
+fn check_memory(max_memory: Option<usize>, size: usize) -> Result<(), RuntimeError> {
+    match max_memory {
+        Some(max_memory) if size > max_memory => Err(RuntimeError::OutOfMemory),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allow_keeps_the_file_name() {
+        assert_eq!(
+            FilePolicy::Allow.resolve("../A.TXT", true),
+            Ok(PathBuf::from("../A.TXT"))
+        );
+        assert_eq!(FilePolicy::Allow.check_environ(true), Ok(()));
+    }
+
+    #[test]
+    fn deny_denies_everything() {
+        assert_eq!(
+            FilePolicy::Deny.resolve("A.TXT", false),
+            Err(RuntimeError::PermissionDenied)
+        );
+        assert_eq!(
+            FilePolicy::Deny.check_environ(false),
+            Err(RuntimeError::PermissionDenied)
+        );
+    }
+
+    #[test]
+    fn read_only_denies_writing() {
+        assert_eq!(
+            FilePolicy::ReadOnly.resolve("A.TXT", false),
+            Ok(PathBuf::from("A.TXT"))
+        );
+        assert_eq!(
+            FilePolicy::ReadOnly.resolve("A.TXT", true),
+            Err(RuntimeError::PermissionDenied)
+        );
+        assert_eq!(FilePolicy::ReadOnly.check_environ(false), Ok(()));
+        assert_eq!(
+            FilePolicy::ReadOnly.check_environ(true),
+            Err(RuntimeError::PermissionDenied)
+        );
+    }
+
+    #[test]
+    fn confined_resolves_inside_the_root() {
+        let policy = FilePolicy::Confined(PathBuf::from("/srv/sandbox"));
+        assert_eq!(
+            policy.resolve("DATA/A.TXT", true),
+            Ok(PathBuf::from("/srv/sandbox/DATA/A.TXT"))
+        );
+        for file_name in ["../A.TXT", "DATA/../A.TXT", "/etc/passwd"] {
+            assert_eq!(
+                policy.resolve(file_name, false),
+                Err(RuntimeError::PermissionDenied),
+                "{}",
+                file_name
+            );
+        }
+    }
+
+    #[test]
+    fn no_checker_without_limits() {
+        let limits = ExecutionLimits {
+            max_call_depth: Some(10),
+            file_policy: FilePolicy::Deny,
+            ..Default::default()
+        };
+        assert!(LimitsChecker::new(&limits, || unreachable!()).is_none());
+    }
+
+    #[test]
+    fn checker_counts_instructions() {
+        let limits = ExecutionLimits {
+            max_instructions: Some(2),
+            ..Default::default()
+        };
+        let mut checker = LimitsChecker::new(&limits, || Duration::ZERO).unwrap();
+        assert_eq!(checker.check(|| Duration::ZERO, || 0), Ok(()));
+        assert_eq!(checker.check(|| Duration::ZERO, || 0), Ok(()));
+        assert_eq!(
+            checker.check(|| Duration::ZERO, || 0),
+            Err(RuntimeError::InstructionLimitExceeded)
+        );
+    }
+
+    #[test]
+    fn checker_checks_time_and_memory_periodically() {
+        let limits = ExecutionLimits {
+            timeout: Some(Duration::from_secs(1)),
+            max_memory: Some(100),
+            ..Default::default()
+        };
+        let mut checker = LimitsChecker::new(&limits, || Duration::from_secs(10)).unwrap();
+        for _ in 1..CHECK_INTERVAL {
+            assert_eq!(checker.check(|| unreachable!(), || unreachable!()), Ok(()));
+        }
+        assert_eq!(
+            checker.check(|| Duration::from_secs(12), || 0),
+            Err(RuntimeError::TimeLimitExceeded)
+        );
+        for _ in 1..CHECK_INTERVAL {
+            checker.check(|| unreachable!(), || unreachable!()).unwrap();
+        }
+        assert_eq!(
+            checker.check(|| Duration::from_secs(10), || 101),
+            Err(RuntimeError::OutOfMemory)
+        );
+    }
+}
@@ -1,13 +1,143 @@
-use std::io::Write;
+use std::fs::File;
+use std::io::{Stderr, Write};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
 
-pub struct Lpt1Write {}
+/// The default width of the printer, in columns.
+pub const LPT1_DEFAULT_WIDTH: usize = 80;
+
+/// The sink where `LPRINT` output goes to.
+pub enum Lpt1Write {
+    /// Writes to a file.
+    File(File),
+    /// Pipes the output to the standard input of a command (e.g. `lpr`).
+    Pipe(Child),
+    /// Writes to the standard error.
+    Stderr(Stderr),
+    /// Keeps the output in memory.
+    Memory(Vec<u8>),
+}
+
+impl Lpt1Write {
+    /// Creates a sink that writes to the given file, truncating it.
+    pub fn file(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        File::create(path).map(Self::File)
+    }
+
+    /// Creates a sink that pipes the output to the given command.
+    /// The command is run by the shell.
+    pub fn pipe(command: &str) -> std::io::Result<Self> {
+        let mut shell = if cfg!(windows) {
+            let mut c = Command::new("cmd");
+            c.arg("/C");
+            c
+        } else {
+            let mut c = Command::new("sh");
+            c.arg("-c");
+            c
+        };
+        shell
+            .arg(command)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map(Self::Pipe)
+    }
+
+    /// Creates a sink that writes to the standard error.
+    pub fn stderr() -> Self {
+        Self::Stderr(std::io::stderr())
+    }
+
+    /// Creates a sink that keeps the output in memory.
+    pub fn memory() -> Self {
+        Self::Memory(vec![])
+    }
+
+    /// Returns the output collected so far, if this is an in-memory sink.
+    pub fn buffer(&self) -> Option<&[u8]> {
+        match self {
+            Self::Memory(buffer) => Some(buffer),
+            _ => None,
+        }
+    }
+
+    fn writer(&mut self) -> std::io::Result<&mut dyn Write> {
+        match self {
+            Self::File(file) => Ok(file),
+            Self::Pipe(child) => child
+                .stdin
+                .as_mut()
+                .map(|stdin| stdin as &mut dyn Write)
+                .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::BrokenPipe)),
+            Self::Stderr(stderr) => Ok(stderr),
+            Self::Memory(buffer) => Ok(buffer),
+        }
+    }
+}
+
+impl Default for Lpt1Write {
+    fn default() -> Self {
+        Self::memory()
+    }
+}
 
 impl Write for Lpt1Write {
-    fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
-        unimplemented!()
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.writer()?.write(buf)
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
-        unimplemented!()
+        self.writer()?.flush()
+    }
+}
+
+impl Drop for Lpt1Write {
+    fn drop(&mut self) {
+        if let Self::Pipe(child) = self {
+            // close stdin so that the command sees the end of its input
+            drop(child.stdin.take());
+            child.wait().unwrap_or_default();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn test_memory() {
+        let mut lpt1 = Lpt1Write::memory();
+        lpt1.write_all(b"hello").unwrap();
+        lpt1.flush().unwrap();
+        assert_eq!(lpt1.buffer(), Some("hello".as_bytes()));
+    }
+
+    #[test]
+    fn test_file() {
+        let filename = "test_lpt1_write_file.txt";
+        {
+            let mut lpt1 = Lpt1Write::file(filename).unwrap();
+            lpt1.write_all(b"hello").unwrap();
+            assert_eq!(lpt1.buffer(), None);
+        }
+        let contents = std::fs::read_to_string(filename).unwrap_or_default();
+        std::fs::remove_file(filename).unwrap_or(());
+        assert_eq!(contents, "hello");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_pipe() {
+        let filename = "test_lpt1_write_pipe.txt";
+        {
+            let mut lpt1 = Lpt1Write::pipe(&format!("cat > {}", filename)).unwrap();
+            lpt1.write_all(b"hello").unwrap();
+        }
+        let contents = std::fs::read_to_string(filename).unwrap_or_default();
+        std::fs::remove_file(filename).unwrap_or(());
+        assert_eq!(contents, "hello");
     }
 }
@@ -2,28 +2,47 @@ use std::collections::VecDeque;
 
 use rusty_common::*;
 use rusty_linter::core::QBNumberCast;
-use rusty_parser::UserDefinedTypes;
+use rusty_parser::{
+    BuiltInFunction, BuiltInSub, EventKind, FILE_MODE_APPEND, FILE_MODE_RANDOM, FileHandle,
+    FileMode, UserDefinedTypes,
+};
+use rusty_runtime::{
+    Field, FileManager, Input, OpenFile, PrintHelper, Printer, QByteSize, ReadInputSource,
+    WritePrinter, allocate_built_in, allocate_fixed_length_string,
+};
 use rusty_variant::Variant;
 
 use super::handlers::{cast, comparison, logical, math, registers, subprogram, var_path};
+use crate::bytecode::decoder::{Decoder, corrupted};
+use crate::bytecode::encoder::Encoder;
+use crate::bytecode::{BytecodeError, fingerprint};
 use crate::error_envelope::WithErrAt;
-use crate::instruction_generator::{Instruction, InstructionGeneratorResult, Path, PrinterType};
+use crate::instruction_generator::{
+    Instruction, InstructionGeneratorResult, InstructionPos, Path, PrinterType,
+};
 use crate::interpreter::Stdlib;
 use crate::interpreter::arguments::ArgumentInfo;
 use crate::interpreter::context::*;
 use crate::interpreter::data_segment::DataSegment;
+use crate::interpreter::debugger::{DebugAction, Debugger, Frame, Pause, PauseReason, Stepper};
 use crate::interpreter::default_stdlib::DefaultStdlib;
+use crate::interpreter::events::EventTraps;
 use crate::interpreter::handlers::allocation::{
-    allocate_array, allocate_built_in, allocate_fixed_length_string, allocate_user_defined_type,
+    allocate_array, allocate_user_defined_type, array_byte_size,
 };
 use crate::interpreter::interpreter_trait::InterpreterTrait;
-use crate::interpreter::io::{FileManager, Input, Printer};
-use crate::interpreter::lpt1_write::Lpt1Write;
-use crate::interpreter::print::{PrintHelper, PrintState};
-use crate::interpreter::read_input::ReadInputSource;
+use crate::interpreter::keyboard::{CrossTermKeyboard, Keyboard, KeyboardBuffer, NoKeyboard};
+use crate::interpreter::limits::{
+    DEFAULT_MAX_CALL_DEPTH, ExecutionLimits, LimitsChecker, check_allocation,
+};
+use crate::interpreter::lpt1_write::{LPT1_DEFAULT_WIDTH, Lpt1Write};
+use crate::interpreter::native::NativeSubprograms;
+use crate::interpreter::print::PrintState;
+use crate::interpreter::profiler::Profiler;
 use crate::interpreter::registers::{RegisterStack, Registers};
-use crate::interpreter::screen::{CrossTermScreen, Screen};
-use crate::interpreter::write_printer::WritePrinter;
+use crate::interpreter::run_state::{InputRequest, RunState};
+use crate::interpreter::screen::{CrossTermScreen, HeadlessScreen, Screen};
+use crate::interpreter::snapshot::{self, SnapshotError};
 use crate::{RuntimeError, RuntimeErrorPos, WithStacktrace};
 
 pub struct Interpreter<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1: Printer> {
@@ -44,6 +63,9 @@ pub struct Interpreter<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1:
 
     screen: Box<dyn Screen>,
 
+    /// Abstracts the keyboard
+    keyboard: KeyboardBuffer,
+
     /// Holds the definition of user defined types
     user_defined_types: UserDefinedTypes,
 
@@ -59,7 +81,7 @@ pub struct Interpreter<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1:
     /// Holds addresses to RETURN to after a GOSUB
     go_sub_address_stack: Vec<usize>,
 
-    /// Holds the current call stack
+    /// Holds the current call stack, ending with the innermost call
     stacktrace: Vec<Position>,
 
     /// Holds a path to a variable
@@ -76,11 +98,29 @@ pub struct Interpreter<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1:
 
     last_error_code: Option<i32>,
 
+    last_error_line: Option<u32>,
+
+    /// Holds the code of the most recent device error (used by `ERDEV`).
+    /// Unlike `ERR`, it is not cleared by `RESUME`.
+    last_device_error_code: Option<i32>,
+
     print_state: PrintState,
 
     data_segment: DataSegment,
 
     def_seg: Option<usize>,
+
+    /// Holds the state of event trapping (`ON TIMER`, `ON KEY`, etc)
+    event_traps: EventTraps,
+
+    /// The limits that are enforced while a program runs
+    limits: ExecutionLimits,
+
+    /// The SUBs and FUNCTIONs that are implemented by the embedding application
+    natives: NativeSubprograms,
+
+    /// The program that was started with `start`, until it finishes
+    execution: Option<Execution>,
 }
 
 impl<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1: Printer> InterpreterTrait
@@ -123,6 +163,10 @@ impl<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1: Printer> Interpret
         self.screen.as_mut()
     }
 
+    fn keyboard_mut(&mut self) -> &mut KeyboardBuffer {
+        &mut self.keyboard
+    }
+
     fn context(&self) -> &Context {
         &self.context
     }
@@ -175,54 +219,129 @@ impl<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1: Printer> Interpret
         self.last_error_code
     }
 
+    fn get_last_error_line(&self) -> Option<u32> {
+        self.last_error_line
+    }
+
+    fn get_last_device_error_code(&self) -> Option<i32> {
+        self.last_device_error_code
+    }
+
     fn interpret(
         &mut self,
         instruction_generator_result: InstructionGeneratorResult,
     ) -> Result<(), RuntimeErrorPos> {
-        let InstructionGeneratorResult {
-            instructions,
-            statement_addresses,
-        } = instruction_generator_result;
-        let mut i: usize = 0;
-        let mut ctx: InterpretOneContext = InterpretOneContext {
-            halt: false,
-            error_handler: ErrorHandler::None,
-            opt_next_index: None,
-            nearest_statement_finder: NearestStatementFinder::new(statement_addresses),
+        self.run(instruction_generator_result, None, None)
+    }
+
+    fn debug(
+        &mut self,
+        instruction_generator_result: InstructionGeneratorResult,
+        debugger: &mut dyn Debugger,
+    ) -> Result<(), RuntimeErrorPos> {
+        self.run(instruction_generator_result, Some(debugger), None)
+    }
+
+    fn profile(
+        &mut self,
+        instruction_generator_result: InstructionGeneratorResult,
+        profiler: &mut Profiler,
+    ) -> Result<(), RuntimeErrorPos> {
+        self.run(instruction_generator_result, None, Some(profiler))
+    }
+
+    fn start(&mut self, instruction_generator_result: InstructionGeneratorResult) {
+        let fingerprint = fingerprint(&instruction_generator_result);
+        let mut execution = self.load(instruction_generator_result, false);
+        execution.fingerprint = fingerprint;
+        self.execution = Some(execution);
+    }
+
+    fn resume(&mut self, max_instructions: u64) -> Result<RunState, RuntimeErrorPos> {
+        let Some(mut execution) = self.execution.take() else {
+            return Ok(RunState::Finished);
         };
-        while i < instructions.len() && !ctx.halt {
-            let instruction = &instructions[i].element;
-            let pos = instructions[i].pos();
-            match self.interpret_one(i, instruction, pos, &mut ctx) {
-                Ok(_) => match ctx.opt_next_index.take() {
-                    Some(next_index) => {
-                        i = next_index;
-                    }
-                    _ => {
-                        i += 1;
-                    }
-                },
-                Err(e) => {
-                    self.last_error_code = Some(e.err().get_code());
-                    match ctx.error_handler {
-                        ErrorHandler::Address(handler_address) => {
-                            // store error address, so we can call RESUME and RESUME NEXT from within the error handler
-                            self.context.push_error_handler_context();
-                            self.last_error_address = Some(i);
-                            i = handler_address;
-                        }
-                        ErrorHandler::Next => {
-                            i = ctx.nearest_statement_finder.find_next(i);
-                        }
-                        ErrorHandler::None => {
-                            return Err(e.with_stacktrace(&mut self.stacktrace));
-                        }
-                    }
+        let mut budget = max_instructions;
+        while !execution.is_finished() {
+            if budget == 0 {
+                self.execution = Some(execution);
+                return Ok(RunState::Yielded);
+            }
+            let pos = execution.instructions[execution.i].pos();
+            match self.input_request(&mut execution).with_err_at(&pos) {
+                Ok(Some(input_request)) => {
+                    self.execution = Some(execution);
+                    return Ok(RunState::WaitingForInput(input_request));
                 }
+                Ok(None) => {}
+                Err(e) => return Err(self.abort(e)),
             }
+            execution.key_wait = None;
+            budget -= 1;
+            self.step(&mut execution).map_err(|e| self.abort(e))?;
+        }
+        self.reset_program_state();
+        Ok(RunState::Finished)
+    }
+
+    fn snapshot(&mut self) -> Result<Vec<u8>, SnapshotError> {
+        let Some(execution) = self.execution.take() else {
+            return Err(SnapshotError::NotRunning);
+        };
+        let mut encoder = Encoder::new();
+        let result = self.encode_state(&execution, &mut encoder);
+        let fingerprint = execution.fingerprint;
+        self.execution = Some(execution);
+        result.map_err(SnapshotError::File)?;
+        Ok(snapshot::to_bytes(fingerprint, encoder.into_bytes()))
+    }
+
+    fn restore(
+        &mut self,
+        instruction_generator_result: InstructionGeneratorResult,
+        snapshot: &[u8],
+    ) -> Result<(), SnapshotError> {
+        let fingerprint = fingerprint(&instruction_generator_result);
+        let payload = snapshot::payload(snapshot, fingerprint)?;
+        let mut execution = self.load(instruction_generator_result, false);
+        execution.fingerprint = fingerprint;
+        let mut decoder = Decoder::new(payload);
+        let open_files = self.decode_state(&mut execution, &mut decoder)?;
+        decoder.finish()?;
+        self.file_manager.close_all();
+        for open_file in open_files {
+            self.limits
+                .file_policy
+                .check_resolved(
+                    std::path::Path::new(&open_file.file_name),
+                    open_file.file_mode != FileMode::Input,
+                )
+                .and_then(|_| self.file_manager.reopen(open_file))
+                .map_err(SnapshotError::File)?;
         }
+        self.execution = Some(execution);
         Ok(())
     }
+
+    fn set_user_defined_types(&mut self, user_defined_types: UserDefinedTypes) {
+        self.user_defined_types = user_defined_types;
+    }
+
+    fn limits(&self) -> &ExecutionLimits {
+        &self.limits
+    }
+
+    fn set_limits(&mut self, limits: ExecutionLimits) {
+        self.limits = limits;
+    }
+
+    fn natives(&self) -> &NativeSubprograms {
+        &self.natives
+    }
+
+    fn natives_mut(&mut self) -> &mut NativeSubprograms {
+        &mut self.natives
+    }
 }
 
 pub type DefaultInterpreter = Interpreter<
@@ -232,24 +351,89 @@ pub type DefaultInterpreter = Interpreter<
     WritePrinter<Lpt1Write>,
 >;
 
+/// Creates an interpreter that keeps the `LPRINT` output in memory.
 pub fn new_default_interpreter(user_defined_types: UserDefinedTypes) -> DefaultInterpreter {
-    let stdlib = DefaultStdlib;
+    new_default_interpreter_with_lpt1(user_defined_types, Lpt1Write::default())
+}
+
+/// Creates an interpreter that sends the `LPRINT` output to the given sink.
+pub fn new_default_interpreter_with_lpt1(
+    user_defined_types: UserDefinedTypes,
+    lpt1: Lpt1Write,
+) -> DefaultInterpreter {
+    let stdlib = DefaultStdlib::default();
     let stdin = ReadInputSource::new(std::io::stdin());
     let stdout = WritePrinter::new(std::io::stdout());
-    let lpt1 = WritePrinter::new(Lpt1Write {});
+    let lpt1 = WritePrinter::with_width(lpt1, LPT1_DEFAULT_WIDTH);
     let screen = CrossTermScreen::default();
-    Interpreter::new(stdlib, stdin, stdout, lpt1, screen, user_defined_types)
+    let keyboard = CrossTermKeyboard;
+    Interpreter::new(
+        stdlib,
+        stdin,
+        stdout,
+        lpt1,
+        screen,
+        keyboard,
+        user_defined_types,
+    )
+}
+
+pub type IoInterpreter<R, W> =
+    Interpreter<DefaultStdlib, ReadInputSource<R>, WritePrinter<W>, WritePrinter<Lpt1Write>>;
+
+/// Creates an interpreter that reads `INPUT` from the given reader and
+/// writes `PRINT` output to the given writer, instead of using the terminal
+/// (e.g. when the standard input and output are used by a debug adapter).
+/// Screen commands (e.g. `CLS`, `LOCATE`) are ignored and `INKEY$` never returns a key.
+pub fn new_interpreter_with_io<R: std::io::Read, W: std::io::Write>(
+    user_defined_types: UserDefinedTypes,
+    stdin: R,
+    stdout: W,
+) -> IoInterpreter<R, W> {
+    let lpt1 = WritePrinter::with_width(Lpt1Write::default(), LPT1_DEFAULT_WIDTH);
+    Interpreter::new(
+        DefaultStdlib::default(),
+        ReadInputSource::new(stdin),
+        WritePrinter::new(stdout),
+        lpt1,
+        HeadlessScreen {},
+        NoKeyboard,
+        user_defined_types,
+    )
 }
 
 impl<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1: Printer>
     Interpreter<TStdlib, TStdIn, TStdOut, TLpt1>
 {
-    pub fn new<TScreen: Screen + 'static>(
+    pub fn new<TScreen: Screen + 'static, TKeyboard: Keyboard + 'static>(
         stdlib: TStdlib,
         stdin: TStdIn,
         stdout: TStdOut,
         lpt1: TLpt1,
         screen: TScreen,
+        keyboard: TKeyboard,
+        user_defined_types: UserDefinedTypes,
+    ) -> Self {
+        Self::with_devices(
+            stdlib,
+            stdin,
+            stdout,
+            lpt1,
+            Box::new(screen),
+            Box::new(keyboard),
+            user_defined_types,
+        )
+    }
+
+    /// Creates an interpreter with the given screen and keyboard,
+    /// whose types are not known at compile time.
+    pub(crate) fn with_devices(
+        stdlib: TStdlib,
+        stdin: TStdIn,
+        stdout: TStdOut,
+        lpt1: TLpt1,
+        screen: Box<dyn Screen>,
+        keyboard: Box<dyn Keyboard>,
         user_defined_types: UserDefinedTypes,
     ) -> Self {
         Self {
@@ -257,7 +441,8 @@ impl<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1: Printer>
             stdin,
             stdout,
             lpt1,
-            screen: Box::new(screen),
+            screen,
+            keyboard: KeyboardBuffer::new(keyboard),
             context: Context::new(),
             return_address_stack: vec![],
             go_sub_address_stack: vec![],
@@ -271,9 +456,15 @@ impl<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1: Printer>
             value_stack: vec![],
             last_error_address: None,
             last_error_code: None,
+            last_error_line: None,
+            last_device_error_code: None,
             print_state: PrintState::new(),
             data_segment: DataSegment::default(),
             def_seg: None,
+            event_traps: EventTraps::default(),
+            limits: ExecutionLimits::default(),
+            natives: NativeSubprograms::default(),
+            execution: None,
         }
     }
 
@@ -303,6 +494,9 @@ impl<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1: Printer>
             Instruction::LoadIntoA(v) => {
                 registers::load_into_a(self, v);
             }
+            Instruction::LoadIntoB(v) => {
+                registers::load_into_b(self, v);
+            }
             Instruction::Cast(q) => {
                 cast::cast(self, q).with_err_at(&pos)?;
             }
@@ -372,6 +566,33 @@ impl<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1: Printer>
             Instruction::Or => {
                 logical::or(self).with_err_at(&pos)?;
             }
+            Instruction::PlusTyped(q) => {
+                math::plus_typed(self, *q).with_err_at(&pos)?;
+            }
+            Instruction::MinusTyped(q) => {
+                math::minus_typed(self, *q).with_err_at(&pos)?;
+            }
+            Instruction::MultiplyTyped(q) => {
+                math::multiply_typed(self, *q).with_err_at(&pos)?;
+            }
+            Instruction::LessTyped(q) => {
+                comparison::less_typed(self, *q).with_err_at(&pos)?;
+            }
+            Instruction::LessOrEqualTyped(q) => {
+                comparison::less_or_equal_typed(self, *q).with_err_at(&pos)?;
+            }
+            Instruction::EqualTyped(q) => {
+                comparison::equal_typed(self, *q).with_err_at(&pos)?;
+            }
+            Instruction::GreaterOrEqualTyped(q) => {
+                comparison::greater_or_equal_typed(self, *q).with_err_at(&pos)?;
+            }
+            Instruction::GreaterTyped(q) => {
+                comparison::greater_typed(self, *q).with_err_at(&pos)?;
+            }
+            Instruction::NotEqualTyped(q) => {
+                comparison::not_equal_typed(self, *q).with_err_at(&pos)?;
+            }
             Instruction::JumpIfFalse(address_or_label) => {
                 let a = self.registers().get_a();
                 let is_true: bool = a.try_cast().map_err(RuntimeError::from).with_err_at(&pos)?;
@@ -387,16 +608,16 @@ impl<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1: Printer>
             }
             Instruction::PushStack => {
                 self.context.stop_collecting_arguments();
-                self.stacktrace.insert(0, pos);
+                self.stacktrace.push(pos);
             }
             Instruction::PushStaticStack(scope_name) => {
                 self.context
                     .stop_collecting_arguments_static(scope_name.clone());
-                self.stacktrace.insert(0, pos);
+                self.stacktrace.push(pos);
             }
             Instruction::PopStack => {
                 self.context.pop();
-                self.stacktrace.remove(0);
+                self.stacktrace.pop();
             }
             Instruction::EnqueueToReturnStack(index) => {
                 subprogram::enqueue_to_return_stack(self, *index);
@@ -404,8 +625,8 @@ impl<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1: Printer>
             Instruction::DequeueFromReturnStack => {
                 subprogram::dequeue_from_return_stack(self);
             }
-            Instruction::StashFunctionReturnValue(function_name) => {
-                subprogram::stash_function_return_value(self, function_name);
+            Instruction::StashFunctionReturnValue(slot) => {
+                subprogram::stash_function_return_value(self, *slot);
             }
             Instruction::UnStashFunctionReturnValue => {
                 subprogram::un_stash_function_return_value(self);
@@ -422,18 +643,31 @@ impl<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1: Printer>
             Instruction::BuiltInSub(s) => {
                 // the stacktrace should be already populated by Instruction::PushStack
                 debug_assert!(!self.stacktrace.is_empty());
-                super::built_ins::run_sub(s, self).with_stacktrace(&mut self.stacktrace)?;
+                super::built_ins::run_sub(s, self).map_err(|e| self.unwind_built_in_call(e))?;
             }
             Instruction::BuiltInFunction(f) => {
                 // the stacktrace should be already populated by Instruction::PushStack
                 debug_assert!(!self.stacktrace.is_empty());
-                super::built_ins::run_function(f, self).with_stacktrace(&mut self.stacktrace)?;
+                super::built_ins::run_function(f, self)
+                    .map_err(|e| self.unwind_built_in_call(e))?;
+            }
+            Instruction::CallNative(name) => {
+                // the stacktrace should be already populated by Instruction::PushStack
+                debug_assert!(!self.stacktrace.is_empty());
+                self.natives
+                    .call(name, self.context.variables_mut())
+                    .map_err(|e| self.unwind_built_in_call(e))?;
             }
             Instruction::Label(_) => (), // no-op
             Instruction::Halt => {
                 ctx.halt = true;
             }
+            Instruction::Stop => {
+                // the debugger has already paused on this statement
+                ctx.halt = !ctx.is_debugging;
+            }
             Instruction::PushRet(address) => {
+                self.check_call_depth().with_err_at(&pos)?;
                 self.return_address_stack.push(*address);
             }
             Instruction::PopRet => {
@@ -441,15 +675,19 @@ impl<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1: Printer>
                 ctx.opt_next_index = Some(address);
             }
             Instruction::GoSub(address_or_label) => {
-                self.go_sub_address_stack.push(i);
+                self.go_sub_address_stack.push(i + 1);
                 ctx.opt_next_index = Some(address_or_label.address());
             }
             Instruction::Return(opt_address) => match self.go_sub_address_stack.pop() {
                 Some(address) => {
                     ctx.opt_next_index = Some(match opt_address {
                         Some(address_or_label) => address_or_label.address(),
-                        _ => address + 1,
+                        _ => address,
                     });
+                    if self.event_traps.on_return(self.go_sub_address_stack.len()) {
+                        // restore the context that was active when the event occurred
+                        self.context.pop();
+                    }
                 }
                 _ => {
                     return Err(RuntimeError::ReturnWithoutGoSub).with_err_at(&pos);
@@ -475,6 +713,31 @@ impl<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1: Printer>
                 ctx.opt_next_index = Some(resume_label.address());
                 self.context.pop();
             }
+            Instruction::OnEventGoSub(kind, address_or_label) => {
+                let arg: i32 = self
+                    .registers()
+                    .get_a()
+                    .try_cast()
+                    .map_err(RuntimeError::from)
+                    .with_err_at(&pos)?;
+                self.event_traps
+                    .set_handler(*kind, arg, address_or_label.address())
+                    .with_err_at(&pos)?;
+            }
+            Instruction::EventControl(kind, action) => {
+                let arg: i32 = match kind {
+                    EventKind::Key => self
+                        .registers()
+                        .get_a()
+                        .try_cast()
+                        .map_err(RuntimeError::from)
+                        .with_err_at(&pos)?,
+                    _ => 0,
+                };
+                self.event_traps
+                    .control(*kind, arg, *action, &self.stdlib)
+                    .with_err_at(&pos)?;
+            }
             Instruction::Throw(interpreter_error) => {
                 return Err(interpreter_error.clone()).with_err_at(&pos);
             }
@@ -496,6 +759,11 @@ impl<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1: Printer>
                     .map(|res| res.map_err(RuntimeError::from))
                     .collect();
                 let args = r_args.with_err_at(&pos)?;
+                check_allocation(
+                    self,
+                    array_byte_size(&args, element_type, &self.user_defined_types),
+                )
+                .with_err_at(&pos)?;
                 let v = allocate_array(args, element_type, &self.user_defined_types)
                     .with_err_at(&pos)?;
                 self.registers_mut().set_a(v);
@@ -505,8 +773,8 @@ impl<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1: Printer>
                     allocate_user_defined_type(user_defined_type_name, &self.user_defined_types);
                 self.registers_mut().set_a(v);
             }
-            Instruction::VarPathName(root_path) => {
-                var_path::var_path_name(self, root_path.clone());
+            Instruction::VarPathName(root_path, name) => {
+                var_path::var_path_name(self, *root_path, name);
             }
             Instruction::VarPathIndex => {
                 var_path::var_path_index(self);
@@ -521,6 +789,15 @@ impl<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1: Printer>
             Instruction::CopyVarPathToA => {
                 var_path::copy_var_path_to_a(self).with_err_at(&pos)?;
             }
+            Instruction::CopyVariableToA(root_path, name) => {
+                var_path::copy_variable_to_a(self, *root_path, name);
+            }
+            Instruction::CopyVariableToB(root_path, name) => {
+                var_path::copy_variable_to_b(self, *root_path, name);
+            }
+            Instruction::CopyAToVariable(root_path, name) => {
+                var_path::copy_a_to_variable(self, *root_path, name);
+            }
             Instruction::PopVarPath => {
                 var_path::pop_var_path(self).with_err_at(&pos)?;
             }
@@ -560,14 +837,14 @@ impl<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1: Printer>
             Instruction::PrintEnd => {
                 self.print_end().with_err_at(&pos)?;
             }
-            Instruction::IsVariableDefined(dim_name) => {
+            Instruction::IsVariableDefined(slot) => {
                 debug_assert_ne!(
                     0,
                     self.context.current_memory_block_index(),
                     "Should not be in global scope"
                 );
                 let variables = self.context.variables();
-                let is_variable_defined = variables.get_by_dim_name(dim_name).is_some();
+                let is_variable_defined = variables.is_defined(*slot);
                 self.registers_mut().set_a(is_variable_defined.into());
             }
         }
@@ -621,10 +898,487 @@ impl<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1: Printer>
         Ok(())
     }
 
+    /// Checks for trapped events, before executing the statement at the given address.
+    /// If an event handler needs to be called, it calls it like a `GOSUB`
+    /// and returns `true`. The statement will be executed when the handler returns.
+    fn trap_event(
+        &mut self,
+        i: usize,
+        ctx: &mut InterpretOneContext,
+    ) -> Result<bool, RuntimeError> {
+        if !self.event_traps.is_active() || !ctx.nearest_statement_finder.is_statement_address(i) {
+            return Ok(false);
+        }
+        self.event_traps.poll(&self.stdlib, &mut self.keyboard)?;
+        match self
+            .event_traps
+            .take_next_handler(self.go_sub_address_stack.len())
+        {
+            Some(handler_address) => {
+                // event handlers run in the global scope, like error handlers
+                self.context.push_error_handler_context();
+                self.go_sub_address_stack.push(i);
+                ctx.opt_next_index = Some(handler_address);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Removes the call frame of a built-in sub or function that failed,
+    /// because its `PopStack` instruction will not run
+    /// (e.g. `RESUME NEXT` continues after it).
+    /// The error is reported at the position of the built-in call.
+    fn unwind_built_in_call(&mut self, err: RuntimeError) -> RuntimeErrorPos {
+        self.context.pop();
+        RuntimeErrorPos::new(
+            err,
+            self.stacktrace.pop().expect("Should have a call frame"),
+        )
+    }
+
+    /// Runs the given program, optionally under a debugger or a profiler.
+    fn run(
+        &mut self,
+        instruction_generator_result: InstructionGeneratorResult,
+        mut debugger: Option<&mut dyn Debugger>,
+        mut profiler: Option<&mut Profiler>,
+    ) -> Result<(), RuntimeErrorPos> {
+        let mut execution = self.load(instruction_generator_result, debugger.is_some());
+        let mut stepper = debugger
+            .as_mut()
+            .map(|debugger| Stepper::new(debugger.on_start(), self.stacktrace.len()));
+        if let Some(profiler) = profiler.as_mut() {
+            profiler.on_start();
+        }
+        while !execution.is_finished() {
+            let i = execution.i;
+            let instruction = &execution.instructions[i].element;
+            let pos = execution.instructions[i].pos();
+            if let Some(profiler) = profiler.as_mut() {
+                profiler.on_instruction(pos);
+            }
+            if let (Some(debugger), Some(stepper)) = (debugger.as_mut(), stepper.as_mut())
+                && execution
+                    .ctx
+                    .nearest_statement_finder
+                    .is_statement_address(i)
+            {
+                if !matches!(instruction, Instruction::PopRet | Instruction::Halt) {
+                    debugger.on_statement(pos);
+                }
+                let depth = self.stacktrace.len();
+                if let Some(reason) = stepper.check(instruction, pos, depth, *debugger) {
+                    let action = debugger.on_pause(&self.pause(reason, pos));
+                    if action == DebugAction::Quit {
+                        break;
+                    }
+                    stepper.resume(action, depth);
+                }
+            }
+            let result = self.step(&mut execution);
+            if let Some(profiler) = profiler.as_mut() {
+                profiler.on_call_depth(self.stacktrace.len(), &execution.instructions, i);
+            }
+            if let Err(e) = result {
+                if let Some(profiler) = profiler.as_mut() {
+                    profiler.on_stop();
+                }
+                return Err(self.abort(e));
+            }
+        }
+        if let Some(profiler) = profiler.as_mut() {
+            profiler.on_stop();
+        }
+        self.reset_program_state();
+        Ok(())
+    }
+
+    /// Prepares the given program to run from its first instruction.
+    fn load(
+        &mut self,
+        instruction_generator_result: InstructionGeneratorResult,
+        is_debugging: bool,
+    ) -> Execution {
+        let InstructionGeneratorResult {
+            instructions,
+            statement_addresses,
+            global_names,
+            ..
+        } = instruction_generator_result;
+        // the global variables might have been created by a previous program
+        self.context.global_variables_mut().arrange(&global_names);
+        Execution {
+            instructions,
+            i: 0,
+            ctx: InterpretOneContext {
+                halt: false,
+                error_handler: ErrorHandler::None,
+                opt_next_index: None,
+                nearest_statement_finder: NearestStatementFinder::new(statement_addresses),
+                is_debugging,
+            },
+            limits_checker: LimitsChecker::new(&self.limits, || self.stdlib.now()),
+            fingerprint: 0,
+            key_wait: None,
+        }
+    }
+
+    /// Runs the next instruction of the given program.
+    /// An error is handled by the error handler of the program, if any,
+    /// otherwise it is returned.
+    fn step(&mut self, execution: &mut Execution) -> Result<(), RuntimeErrorPos> {
+        let i = execution.i;
+        let instruction = &execution.instructions[i].element;
+        let pos = execution.instructions[i].pos();
+        let ctx = &mut execution.ctx;
+        let checked = match execution.limits_checker.as_mut() {
+            Some(limits_checker) => {
+                limits_checker.check(|| self.stdlib.now(), || self.context.byte_size())
+            }
+            None => Ok(()),
+        };
+        let result = match checked
+            .and_then(|_| self.trap_event(i, ctx))
+            .with_err_at(&pos)
+        {
+            // an event handler was called, its address is in opt_next_index
+            Ok(true) => Ok(()),
+            Ok(false) => self.interpret_one(i, instruction, pos, ctx),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(_) => match ctx.opt_next_index.take() {
+                Some(next_index) => {
+                    execution.i = next_index;
+                }
+                _ => {
+                    execution.i += 1;
+                }
+            },
+            Err(e) => {
+                self.last_error_code = Some(e.err().get_code());
+                self.last_error_line = Some(pos.row());
+                if e.err().is_device_error() {
+                    self.last_device_error_code = self.last_error_code;
+                }
+                // the execution limits cannot be bypassed with an error handler
+                let error_handler = if e.err().is_fatal() {
+                    ErrorHandler::None
+                } else {
+                    ctx.error_handler
+                };
+                match error_handler {
+                    ErrorHandler::Address(handler_address) => {
+                        // store error address, so we can call RESUME and RESUME NEXT from within the error handler
+                        self.context.push_error_handler_context();
+                        self.last_error_address = Some(i);
+                        execution.i = handler_address;
+                    }
+                    ErrorHandler::Next => {
+                        execution.i = ctx.nearest_statement_finder.find_next(i);
+                    }
+                    ErrorHandler::None => {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks if the next instruction needs input that is not available yet.
+    fn input_request(
+        &mut self,
+        execution: &mut Execution,
+    ) -> Result<Option<InputRequest>, RuntimeError> {
+        let i = execution.i;
+        match &execution.instructions[i].element {
+            // the first argument indicates if INPUT reads from a file
+            Instruction::BuiltInSub(BuiltInSub::Input | BuiltInSub::LineInput)
+                if self.context.variables().get(0) != Some(&Variant::VInteger(1))
+                    && !self.stdin.is_line_ready() =>
+            {
+                Ok(Some(InputRequest::Line))
+            }
+            Instruction::BuiltInFunction(BuiltInFunction::InKey)
+                if execution.key_wait != Some(i) && !self.keyboard.has_key()? =>
+            {
+                execution.key_wait = Some(i);
+                Ok(Some(InputRequest::Key))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Writes the state of the given program into a snapshot.
+    fn encode_state(
+        &mut self,
+        execution: &Execution,
+        encoder: &mut Encoder,
+    ) -> Result<(), RuntimeError> {
+        encoder.usize(execution.i);
+        match execution.ctx.error_handler {
+            ErrorHandler::None => encoder.u8(0),
+            ErrorHandler::Next => encoder.u8(1),
+            ErrorHandler::Address(address) => {
+                encoder.u8(2);
+                encoder.usize(address);
+            }
+        }
+        self.context.encode(encoder);
+        encoder.usize(self.register_stack.len());
+        for registers in &self.register_stack {
+            registers.encode(encoder);
+        }
+        for addresses in [&self.return_address_stack, &self.go_sub_address_stack] {
+            encoder.usize(addresses.len());
+            for address in addresses {
+                encoder.usize(*address);
+            }
+        }
+        encoder.usize(self.stacktrace.len());
+        for pos in self.stacktrace.iter().rev() {
+            encoder.pos(*pos);
+        }
+        encoder.usize(self.var_path_stack.len());
+        for path in &self.var_path_stack {
+            encoder.path(path);
+        }
+        encoder.usize(self.by_ref_stack.len());
+        for value in &self.by_ref_stack {
+            encoder.variant(value);
+        }
+        encoder.usize(self.value_stack.len());
+        for value in &self.value_stack {
+            encoder.variant(value);
+        }
+        encoder.option(self.function_result.as_ref(), Encoder::variant);
+        encoder.option(self.last_error_address.as_ref(), |encoder, address| {
+            encoder.usize(*address)
+        });
+        encoder.option(self.last_error_code.as_ref(), |encoder, code| {
+            encoder.u32(*code as u32)
+        });
+        encoder.option(self.last_error_line.as_ref(), |encoder, line| {
+            encoder.u32(*line)
+        });
+        encoder.option(self.last_device_error_code.as_ref(), |encoder, code| {
+            encoder.u32(*code as u32)
+        });
+        self.print_state.encode(encoder);
+        self.data_segment.encode(encoder);
+        encoder.option(self.def_seg.as_ref(), |encoder, def_seg| {
+            encoder.usize(*def_seg)
+        });
+        self.event_traps.encode(encoder, self.stdlib.now());
+        let open_files = self.file_manager.open_files()?;
+        encoder.usize(open_files.len());
+        for open_file in open_files {
+            encoder.u8(i32::from(open_file.handle) as u8);
+            encoder.str(&open_file.file_name);
+            encoder.u8(open_file.file_mode.into());
+            encoder.usize(open_file.rec_len);
+            encoder.u64(open_file.position);
+            encoder.usize(open_file.column);
+            encoder.usize(open_file.field_lists.len());
+            for field_list in &open_file.field_lists {
+                encoder.usize(field_list.len());
+                for field in field_list {
+                    encoder.usize(field.width);
+                    encoder.str(&field.name);
+                }
+            }
+            encoder.option(
+                open_file.current_field_list_index.as_ref(),
+                |encoder, index| encoder.usize(*index),
+            );
+        }
+        Ok(())
+    }
+
+    /// Reads the state of the given program from a snapshot.
+    /// The interpreter is only modified if the whole snapshot is valid.
+    /// The open files are returned, so that the caller can open them again.
+    fn decode_state(
+        &mut self,
+        execution: &mut Execution,
+        decoder: &mut Decoder,
+    ) -> Result<Vec<OpenFile>, BytecodeError> {
+        let len = execution.instructions.len();
+        let address = |decoder: &mut Decoder| match decoder.usize()? {
+            address if address < len => Ok(address),
+            _ => Err(corrupted("invalid address")),
+        };
+        let addresses = |decoder: &mut Decoder| {
+            let count = decoder.len()?;
+            (0..count)
+                .map(|_| address(decoder))
+                .collect::<Result<Vec<usize>, _>>()
+        };
+        let i = address(decoder)?;
+        let error_handler = match decoder.u8()? {
+            0 => ErrorHandler::None,
+            1 => ErrorHandler::Next,
+            2 => ErrorHandler::Address(address(decoder)?),
+            tag => return Err(corrupted(&format!("invalid error handler {}", tag))),
+        };
+        let context = Context::decode(decoder)?;
+        let count = decoder.len()?;
+        let register_stack: RegisterStack = (0..count)
+            .map(|_| Registers::decode(decoder))
+            .collect::<Result<_, _>>()?;
+        if register_stack.is_empty() {
+            return Err(corrupted("invalid registers"));
+        }
+        let return_address_stack = addresses(decoder)?;
+        let go_sub_address_stack = addresses(decoder)?;
+        let count = decoder.len()?;
+        let mut stacktrace: Vec<Position> = (0..count)
+            .map(|_| decoder.pos())
+            .collect::<Result<_, _>>()?;
+        // the snapshot has the innermost call first
+        stacktrace.reverse();
+        let count = decoder.len()?;
+        let var_path_stack = (0..count)
+            .map(|_| decoder.path())
+            .collect::<Result<_, _>>()?;
+        let count = decoder.len()?;
+        let by_ref_stack = (0..count)
+            .map(|_| decoder.variant())
+            .collect::<Result<_, _>>()?;
+        let count = decoder.len()?;
+        let value_stack = (0..count)
+            .map(|_| decoder.variant())
+            .collect::<Result<_, _>>()?;
+        let function_result = decoder.option(Decoder::variant)?;
+        let last_error_address = decoder.option(address)?;
+        let last_error_code = decoder.option(|decoder| decoder.u32().map(|code| code as i32))?;
+        let last_error_line = decoder.option(Decoder::u32)?;
+        let last_device_error_code =
+            decoder.option(|decoder| decoder.u32().map(|code| code as i32))?;
+        let print_state = PrintState::decode(decoder)?;
+        let data_segment = DataSegment::decode(decoder)?;
+        let def_seg = decoder.option(Decoder::usize)?;
+        let event_traps = EventTraps::decode(decoder, self.stdlib.now())?;
+        let count = decoder.len()?;
+        let open_files = (0..count)
+            .map(|_| {
+                let handle = FileHandle::from(decoder.u8()?);
+                let file_name = decoder.str()?;
+                let file_mode = match decoder.u8()? {
+                    mode @ FILE_MODE_APPEND..=FILE_MODE_RANDOM => FileMode::from(mode),
+                    tag => return Err(corrupted(&format!("invalid file mode {}", tag))),
+                };
+                let rec_len = decoder.usize()?;
+                let position = decoder.u64()?;
+                let column = decoder.usize()?;
+                let count = decoder.len()?;
+                let field_lists = (0..count)
+                    .map(|_| {
+                        let count = decoder.len()?;
+                        (0..count)
+                            .map(|_| {
+                                Ok(Field {
+                                    width: decoder.usize()?,
+                                    name: decoder.str()?,
+                                })
+                            })
+                            .collect::<Result<Vec<Field>, BytecodeError>>()
+                    })
+                    .collect::<Result<_, _>>()?;
+                let current_field_list_index = decoder.option(Decoder::usize)?;
+                Ok(OpenFile {
+                    handle,
+                    file_name,
+                    file_mode,
+                    rec_len,
+                    position,
+                    column,
+                    field_lists,
+                    current_field_list_index,
+                })
+            })
+            .collect::<Result<_, BytecodeError>>()?;
+        execution.i = i;
+        execution.ctx.error_handler = error_handler;
+        self.context = context;
+        self.register_stack = register_stack;
+        self.return_address_stack = return_address_stack;
+        self.go_sub_address_stack = go_sub_address_stack;
+        self.stacktrace = stacktrace;
+        self.var_path_stack = var_path_stack;
+        self.by_ref_stack = by_ref_stack;
+        self.value_stack = value_stack;
+        self.function_result = function_result;
+        self.last_error_address = last_error_address;
+        self.last_error_code = last_error_code;
+        self.last_error_line = last_error_line;
+        self.last_device_error_code = last_device_error_code;
+        self.print_state = print_state;
+        self.data_segment = data_segment;
+        self.def_seg = def_seg;
+        self.event_traps = event_traps;
+        Ok(open_files)
+    }
+
+    /// Stops the program because of an error that was not handled.
+    fn abort(&mut self, e: RuntimeErrorPos) -> RuntimeErrorPos {
+        // the error has the innermost position first
+        self.stacktrace.reverse();
+        let e = e.with_stacktrace(&mut self.stacktrace);
+        self.reset_program_state();
+        e
+    }
+
+    /// Checks that the SUB/FUNCTION call that is starting does not exceed
+    /// the call depth limit. Built-in calls are not counted, so that an error handler
+    /// can still use them. If the limit is exceeded, the call frame is dropped,
+    /// so that an error handler continues in the context of the caller.
+    fn check_call_depth(&mut self) -> Result<(), RuntimeError> {
+        let max_call_depth = self.limits.max_call_depth.unwrap_or(DEFAULT_MAX_CALL_DEPTH);
+        if self.return_address_stack.len() >= max_call_depth {
+            self.context.pop();
+            self.stacktrace.pop();
+            Err(RuntimeError::OutOfStackSpace)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Collects the call stack and the variables of a paused program.
+    fn pause(&self, reason: PauseReason, pos: Position) -> Pause<'_> {
+        let frames = std::iter::once(pos)
+            .chain(self.stacktrace.iter().rev().copied())
+            .zip(self.context.frames())
+            .map(|(pos, (variables, is_static))| Frame::new(pos, variables, is_static))
+            .collect();
+        Pause { reason, frames }
+    }
+
+    /// Discards the state that belongs to the program that just finished
+    /// (e.g. call stacks, event handler addresses), so that the interpreter
+    /// can run another program keeping the global variables and open files.
+    fn reset_program_state(&mut self) {
+        self.context.unwind_to_module_level();
+        self.register_stack = vec![Registers::new()];
+        self.return_address_stack.clear();
+        self.go_sub_address_stack.clear();
+        self.stacktrace.clear();
+        self.var_path_stack.clear();
+        self.by_ref_stack.clear();
+        self.function_result = None;
+        self.value_stack.clear();
+        self.last_error_address = None;
+        self.print_state = PrintState::new();
+        self.event_traps = EventTraps::default();
+    }
+
     /// Gets the instruction address where the most recent error occurred.
-    /// Clears that address and also clears the most recent error code.
+    /// Clears that address and also clears the most recent error code and line.
     fn take_last_error_address(&mut self) -> Result<usize, RuntimeError> {
         self.last_error_code = None;
+        self.last_error_line = None;
         match self.last_error_address.take() {
             Some(a) => Ok(a),
             None => Err(RuntimeError::ResumeWithoutError),
@@ -632,6 +1386,33 @@ impl<TStdlib: Stdlib, TStdIn: Input, TStdOut: Printer, TLpt1: Printer>
     }
 }
 
+/// A program that is being run, together with the position of the next
+/// instruction. It is kept in the interpreter between calls to `resume`.
+struct Execution {
+    instructions: Vec<InstructionPos>,
+
+    /// The index of the next instruction
+    i: usize,
+
+    ctx: InterpretOneContext,
+
+    limits_checker: Option<LimitsChecker>,
+
+    /// Identifies the program in snapshots
+    /// (only calculated for programs that run with `resume`).
+    fingerprint: u64,
+
+    /// The index of an `INKEY$` instruction that already reported
+    /// that it waits for a key, so that it does not wait again.
+    key_wait: Option<usize>,
+}
+
+impl Execution {
+    fn is_finished(&self) -> bool {
+        self.i >= self.instructions.len() || self.ctx.halt
+    }
+}
+
 /// Context available to the execution of a single instruction.
 struct InterpretOneContext {
     /// The instruction handler can set this to `true` in order to terminate
@@ -647,6 +1428,9 @@ struct InterpretOneContext {
     opt_next_index: Option<usize>,
 
     nearest_statement_finder: NearestStatementFinder,
+
+    /// Indicates that a debugger is attached, so `STOP` does not end the program.
+    is_debugging: bool,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -680,6 +1464,10 @@ impl NearestStatementFinder {
         }
     }
 
+    pub fn is_statement_address(&self, address: usize) -> bool {
+        self.statement_addresses.binary_search(&address).is_ok()
+    }
+
     pub fn find_next(&self, address: usize) -> usize {
         match self.statement_addresses.binary_search(&address) {
             Ok(existing_index) => {
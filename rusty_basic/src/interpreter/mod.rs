@@ -1,36 +1,45 @@
 mod arguments;
 mod built_ins;
-mod byte_size;
 mod context;
 mod data_segment;
+mod debugger;
 mod default_stdlib;
 pub mod error;
+mod events;
 mod handlers;
-mod indexed_map;
 mod interpreter_trait;
-mod io;
 mod keyboard;
+mod limits;
 mod lpt1_write;
 mod main;
+mod native;
 mod print;
-mod read_input;
+mod profiler;
 mod registers;
+mod run_state;
 mod screen;
+mod snapshot;
 mod stdlib;
-mod string_utils;
 mod variables;
-mod variant_casts;
-mod write_printer;
 
 #[cfg(test)]
-mod test_utils;
+pub(crate) mod test_utils;
 #[cfg(test)]
 mod tests;
 
+pub use self::debugger::{DebugAction, Debugger, Frame, Pause, PauseReason, format_value};
+pub use self::default_stdlib::DefaultStdlib;
 pub use self::interpreter_trait::InterpreterTrait;
-pub use self::main::new_default_interpreter;
+pub use self::keyboard::{CrossTermKeyboard, Keyboard, NoKeyboard};
+pub use self::limits::{DEFAULT_MAX_CALL_DEPTH, ExecutionLimits, FilePolicy};
+pub use self::lpt1_write::{LPT1_DEFAULT_WIDTH, Lpt1Write};
+pub use self::main::{
+    Interpreter, new_default_interpreter, new_default_interpreter_with_lpt1,
+    new_interpreter_with_io,
+};
+pub use self::native::{NativeFunction, NativeSub, NativeSubprograms};
+pub use self::profiler::{LineProfile, Profiler, SubprogramProfile};
+pub use self::run_state::{InputRequest, RunState};
+pub use self::screen::{CrossTermScreen, HeadlessScreen, Screen};
+pub use self::snapshot::SnapshotError;
 pub use self::stdlib::*;
-
-fn is_cr_lf(ch: char) -> bool {
-    ch == '\r' || ch == '\n'
-}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use rusty_linter::core::NativeSignature;
+use rusty_parser::{BareName, Name, TypeQualifier};
+use rusty_runtime::cast;
+use rusty_variant::Variant;
+
+use crate::RuntimeError;
+use crate::interpreter::variables::Variables;
+
+/// The implementation of a native FUNCTION. It gets the arguments,
+/// already cast to the types of the parameters, and returns the result.
+pub type NativeFunction = Box<dyn FnMut(&[Variant]) -> Result<Variant, RuntimeError>>;
+
+/// The implementation of a native SUB. It gets the arguments,
+/// already cast to the types of the parameters.
+pub type NativeSub = Box<dyn FnMut(&[Variant]) -> Result<(), RuntimeError>>;
+
+/// The SUBs and FUNCTIONs that are implemented by the application
+/// that embeds the interpreter.
+///
+/// The program calls them like its own subprograms, but the arguments
+/// are always passed by value.
+#[derive(Default)]
+pub struct NativeSubprograms {
+    map: HashMap<BareName, (NativeSignature, NativeImplementation)>,
+}
+
+enum NativeImplementation {
+    Function(NativeFunction),
+    Sub(NativeSub),
+}
+
+impl NativeSubprograms {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a native FUNCTION, replacing any native subprogram of the same name.
+    pub fn add_function(
+        &mut self,
+        name: &str,
+        param_types: &[TypeQualifier],
+        return_type: TypeQualifier,
+        function: impl FnMut(&[Variant]) -> Result<Variant, RuntimeError> + 'static,
+    ) {
+        self.add(
+            name,
+            param_types,
+            Some(return_type),
+            NativeImplementation::Function(Box::new(function)),
+        );
+    }
+
+    /// Adds a native SUB, replacing any native subprogram of the same name.
+    pub fn add_sub(
+        &mut self,
+        name: &str,
+        param_types: &[TypeQualifier],
+        sub: impl FnMut(&[Variant]) -> Result<(), RuntimeError> + 'static,
+    ) {
+        self.add(
+            name,
+            param_types,
+            None,
+            NativeImplementation::Sub(Box::new(sub)),
+        );
+    }
+
+    fn add(
+        &mut self,
+        name: &str,
+        param_types: &[TypeQualifier],
+        return_type: Option<TypeQualifier>,
+        implementation: NativeImplementation,
+    ) {
+        let signature = NativeSignature {
+            name: BareName::from(name),
+            param_types: param_types.to_vec(),
+            return_type,
+        };
+        self.map
+            .insert(signature.name.clone(), (signature, implementation));
+    }
+
+    /// Gets the signatures of the native subprograms, so that the linter
+    /// can validate the calls to them.
+    pub fn signatures(&self) -> Vec<NativeSignature> {
+        self.map
+            .values()
+            .map(|(signature, _)| signature.clone())
+            .collect()
+    }
+
+    /// Calls the given native subprogram with the arguments of the given frame.
+    /// The result of a FUNCTION is stored after its arguments.
+    pub(crate) fn call(
+        &mut self,
+        name: &BareName,
+        variables: &mut Variables,
+    ) -> Result<(), RuntimeError> {
+        let (signature, implementation) = self
+            .map
+            .get_mut(name)
+            // Subprogram not defined
+            .ok_or(RuntimeError::ErrorCode(35))?;
+        let args: Vec<Variant> = variables
+            .iter()
+            .zip(&signature.param_types)
+            .map(|(arg, q)| cast(arg.clone(), *q))
+            .collect::<Result<_, _>>()?;
+        match implementation {
+            NativeImplementation::Function(function) => {
+                let q = signature.return_type.expect("Function without return type");
+                let result = cast(function(&args)?, q)?;
+                variables.push(Name::qualified(name.clone(), q), result);
+                Ok(())
+            }
+            NativeImplementation::Sub(sub) => sub(&args),
+        }
+    }
+}
@@ -1,21 +1,20 @@
-use std::fmt::Display;
-
 use rusty_parser::FileHandle;
+use rusty_runtime::FormatString;
 use rusty_variant::Variant;
 
 use crate::RuntimeError;
+use crate::bytecode::BytecodeError;
+use crate::bytecode::decoder::Decoder;
+use crate::bytecode::encoder::Encoder;
 use crate::instruction_generator::PrinterType;
-use crate::interpreter::io::Printer;
-use crate::interpreter::string_utils::fix_length;
 
 /// Handles the PRINT and LPRINT statements.
 #[derive(Debug)]
 pub struct PrintState {
     printer_type: PrinterType,
     file_handle: FileHandle,
-    format_string: Option<String>,
+    format_string: Option<FormatString>,
     should_skip_new_line: bool,
-    format_string_index: usize,
 }
 
 impl PrintState {
@@ -25,7 +24,6 @@ impl PrintState {
             file_handle: 0.into(),
             format_string: None,
             should_skip_new_line: false,
-            format_string_index: 0,
         }
     }
 
@@ -33,7 +31,6 @@ impl PrintState {
         self.printer_type = PrinterType::Print;
         self.file_handle = 0.into();
         self.format_string = None;
-        self.format_string_index = 0;
     }
 
     pub fn get_printer_type(&self) -> PrinterType {
@@ -54,7 +51,7 @@ impl PrintState {
     }
 
     pub fn set_format_string(&mut self, format_string: Option<String>) {
-        self.format_string = format_string;
+        self.format_string = format_string.as_deref().map(FormatString::new);
     }
 
     pub fn on_print_comma(&mut self) {
@@ -70,19 +67,19 @@ impl PrintState {
         v: Variant,
     ) -> Result<(Option<String>, Option<Variant>), RuntimeError> {
         self.should_skip_new_line = false;
-        if self.format_string.is_some() {
-            let s = self.print_value_with_format_string(v)?;
-            Ok((Some(s), None))
-        } else {
-            Ok((None, Some(v)))
+        match self.format_string.as_mut() {
+            Some(format_string) => {
+                let s = format_string.format_value(v)?;
+                Ok((Some(s), None))
+            }
+            None => Ok((None, Some(v))),
         }
     }
 
     pub fn print_end(&mut self) -> Result<(Option<String>, bool), RuntimeError> {
-        let opt_remaining = if self.format_string.is_some() {
-            Some(self.print_remaining_chars()?)
-        } else {
-            None
+        let opt_remaining = match self.format_string.as_mut() {
+            Some(format_string) => Some(format_string.remaining()?),
+            None => None,
         };
         let should_print_new_line = if self.should_skip_new_line {
             self.should_skip_new_line = false;
@@ -93,309 +90,29 @@ impl PrintState {
         Ok((opt_remaining, should_print_new_line))
     }
 
-    fn print_remaining_chars(&mut self) -> Result<String, RuntimeError> {
-        let format_string_chars: Vec<char> = self.format_string.as_ref().unwrap().chars().collect();
-        print_remaining_non_formatting_chars(
-            format_string_chars.as_slice(),
-            &mut self.format_string_index,
-        )
-    }
-
-    fn print_value_with_format_string(&mut self, v: Variant) -> Result<String, RuntimeError> {
-        let format_string_chars: Vec<char> = self.format_string.as_ref().unwrap().chars().collect();
-        if format_string_chars.is_empty() {
-            return Err(RuntimeError::IllegalFunctionCall);
-        }
-
-        // ensure we are in the range of chars
-        self.format_string_index %= format_string_chars.len();
-
-        // copy from format_string until we hit a formatting character
-        let mut result = print_non_formatting_chars(
-            format_string_chars.as_slice(),
-            &mut self.format_string_index,
-        )?;
-
-        // format the argument using the formatting character
-        let second_part = print_formatting_chars(
-            format_string_chars.as_slice(),
-            &mut self.format_string_index,
-            v,
-        )?;
-        result.push_str(&second_part);
-
-        Ok(result)
-    }
-}
-
-fn print_non_formatting_chars(
-    format_string_chars: &[char],
-    index: &mut usize,
-) -> Result<String, RuntimeError> {
-    // copy from format_string until we hit a formatting character
-    let mut buf: String = String::new();
-    let starting_index = *index;
-    let mut i = starting_index;
-    while !is_formatting_char(format_string_chars[i]) {
-        buf.push(format_string_chars[i]);
-        i = (i + 1) % format_string_chars.len();
-        if i == starting_index {
-            // looped over to the starting point without encountering a formatting character
-            return Err(RuntimeError::IllegalFunctionCall);
-        }
-    }
-    *index = i;
-    Ok(buf)
-}
-
-fn is_formatting_char(ch: char) -> bool {
-    ch == '#' || ch == '\\' || ch == '!'
-}
-
-fn print_remaining_non_formatting_chars(
-    format_string_chars: &[char],
-    index: &mut usize,
-) -> Result<String, RuntimeError> {
-    // copy from format_string until we hit a formatting character
-    let mut buf: String = String::new();
-    let starting_index = *index;
-    let mut i = starting_index;
-    while i < format_string_chars.len() && !is_formatting_char(format_string_chars[i]) {
-        buf.push(format_string_chars[i]);
-        i += 1;
-    }
-    *index = i;
-    Ok(buf)
-}
-
-fn print_formatting_chars(
-    format_string_chars: &[char],
-    index: &mut usize,
-    v: Variant,
-) -> Result<String, RuntimeError> {
-    match format_string_chars[*index] {
-        '#' => numeric_formatting::print_digit_formatting_chars(format_string_chars, index, v),
-        '\\' => print_string_formatting_chars(format_string_chars, index, v),
-        '!' => print_first_char_formatting_chars(format_string_chars, index, v),
-        _ => Err(RuntimeError::Other(format!(
-            "Not a formatting character: {}",
-            format_string_chars[*index]
-        ))),
-    }
-}
-
-mod numeric_formatting {
-    //! Handles formatting of numbers.
-
-    use rusty_variant::Variant;
-
-    use crate::RuntimeError;
-
-    pub fn print_digit_formatting_chars(
-        format_string_chars: &[char],
-        index: &mut usize,
-        v: Variant,
-    ) -> Result<String, RuntimeError> {
-        debug_assert_eq!(format_string_chars[*index], '#');
-        // collect just the formatting chars e.g. ###,###.##
-        let number_format_chars: &[char] = format_string_chars[*index..]
-            .split(|ch| *ch != '#' && *ch != ',' && *ch != '.')
-            .next()
-            .expect("Should find at least one formatting character");
-        // increment the index
-        *index += number_format_chars.len();
-        // split at decimal point
-        let mut decimal_split = number_format_chars.split(|ch| *ch == '.');
-        let integer_part = decimal_split.next().unwrap();
-        if integer_part.is_empty() {
-            // leading dot
-            return Err(RuntimeError::IllegalFunctionCall);
-        }
-        match decimal_split.next() {
-            Some(fractional_part) => {
-                if fractional_part.is_empty() {
-                    // trailing dot
-                    Err(RuntimeError::IllegalFunctionCall)
-                } else {
-                    fmt_with_fractional_part(integer_part, fractional_part, v)
-                }
-            }
-            _ => fmt_without_fractional_part(integer_part, v),
-        }
-    }
-
-    fn fmt_with_fractional_part(
-        integer_fmt: &[char],
-        fractional_fmt: &[char],
-        v: Variant,
-    ) -> Result<String, RuntimeError> {
-        let unformatted = format_variant(v, fractional_fmt.len())?;
-        let mut unformatted_decimal_split = unformatted.split('.');
-        let unformatted_integer = unformatted_decimal_split.next().unwrap();
-        let unformatted_fractional = unformatted_decimal_split.next().unwrap_or("");
-        let mut result = fmt_integer_part(integer_fmt, unformatted_integer)?;
-
-        // append the fractional parts
-        result.push('.');
-        for ch in unformatted_fractional
-            .chars()
-            .chain(std::iter::repeat('0'))
-            .take(fractional_fmt.len())
-        {
-            result.push(ch);
-        }
-        Ok(result)
-    }
-
-    fn fmt_without_fractional_part(
-        integer_fmt: &[char],
-        v: Variant,
-    ) -> Result<String, RuntimeError> {
-        let unformatted: String = format_variant(v, 0)?;
-        fmt_integer_part(integer_fmt, &unformatted)
-    }
-
-    fn fmt_integer_part(
-        integer_fmt: &[char],
-        unformatted_str: &str,
-    ) -> Result<String, RuntimeError> {
-        let mut result: String = String::new();
-        let unformatted: Vec<char> = unformatted_str.chars().collect();
-        // start with the rightmost digit
-        let mut i: usize = integer_fmt.len();
-        let mut j: usize = unformatted.len();
-        while i > 0 || j > 0 {
-            if i > 0 {
-                match integer_fmt[i - 1] {
-                    ',' => {
-                        result.insert(0, if j > 0 { ',' } else { ' ' });
-                    }
-                    '#' => {
-                        if j > 0 {
-                            result.insert(0, unformatted[j - 1]);
-                            j -= 1;
-                        } else {
-                            result.insert(0, ' ');
-                        }
-                    }
-                    _ => {
-                        // unsupported formatting character
-                        return Err(RuntimeError::IllegalFunctionCall);
-                    }
-                }
-                i -= 1;
-            } else {
-                // we run out formatting characters but we still have digits to print
-                result.insert(0, unformatted[j - 1]);
-                j -= 1;
-            }
-        }
-        Ok(result)
-    }
-
-    fn format_variant(v: Variant, fractional_digits: usize) -> Result<String, RuntimeError> {
-        match v {
-            Variant::VSingle(f) => Ok(if fractional_digits > 0 {
-                format!("{:.1$}", f, fractional_digits)
-            } else {
-                let l = f.round() as i64;
-                l.to_string()
-            }),
-            Variant::VDouble(d) => Ok(if fractional_digits > 0 {
-                format!("{:.1$}", d, fractional_digits)
-            } else {
-                let l = d.round() as i64;
-                l.to_string()
-            }),
-            Variant::VInteger(i) => Ok(i.to_string()),
-            Variant::VLong(l) => Ok(l.to_string()),
-            _ => Err(RuntimeError::TypeMismatch),
-        }
-    }
-}
-
-fn print_string_formatting_chars(
-    format_string_chars: &[char],
-    index: &mut usize,
-    v: Variant,
-) -> Result<String, RuntimeError> {
-    debug_assert_eq!(format_string_chars[*index], '\\');
-    *index += 1;
-    let mut counter: usize = 2;
-    while *index < format_string_chars.len() && format_string_chars[*index] != '\\' {
-        if format_string_chars[*index] != ' ' {
-            // only spaces should be allowed within backslashes
-            return Err(RuntimeError::IllegalFunctionCall);
-        }
-        *index += 1;
-        counter += 1;
-    }
-    if *index < format_string_chars.len() {
-        *index += 1;
-        if let Variant::VString(mut s) = v {
-            fix_length(&mut s, counter);
-            Ok(s)
-        } else {
-            Err(RuntimeError::TypeMismatch)
-        }
-    } else {
-        // did not find closing backslash
-        Err(RuntimeError::IllegalFunctionCall)
-    }
-}
-
-fn print_first_char_formatting_chars(
-    format_string_chars: &[char],
-    index: &mut usize,
-    v: Variant,
-) -> Result<String, RuntimeError> {
-    debug_assert_eq!(format_string_chars[*index], '!');
-    if let Variant::VString(s) = v {
-        let ch = s.chars().next().ok_or(RuntimeError::IllegalFunctionCall)?;
-        let result = String::from(ch);
-        *index += 1;
-        Ok(result)
-    } else {
-        Err(RuntimeError::TypeMismatch)
-    }
-}
-
-pub trait PrintHelper {
-    fn print_number<V: Display>(
-        &mut self,
-        number: V,
-        leading_space: bool,
-    ) -> std::io::Result<usize>;
-
-    fn print_variant(&mut self, v: &Variant) -> std::io::Result<usize>;
-}
-
-impl<T: Printer + ?Sized> PrintHelper for T {
-    fn print_number<V: Display>(
-        &mut self,
-        number: V,
-        leading_space: bool,
-    ) -> std::io::Result<usize> {
-        let s: String = if leading_space {
-            format!(" {} ", number)
-        } else {
-            format!("{} ", number)
-        };
-        self.print(s.as_str())
-    }
-
-    fn print_variant(&mut self, v: &Variant) -> std::io::Result<usize> {
-        match v {
-            Variant::VSingle(f) => self.print_number(f, *f >= 0.0),
-            Variant::VDouble(d) => self.print_number(d, *d >= 0.0),
-            Variant::VString(s) => self.print(s),
-            Variant::VInteger(i) => self.print_number(i, *i >= 0),
-            Variant::VLong(l) => self.print_number(l, *l >= 0),
-            Variant::VArray(_) | Variant::VUserDefined(_) => panic!(
-                "Cannot print user defined type {:?}, linter should have caught this",
-                v
-            ),
-        }
+    /// Writes the state of the `PRINT` statement in progress into a snapshot.
+    pub fn encode(&self, encoder: &mut Encoder) {
+        encoder.printer_type(self.printer_type);
+        encoder.u8(i32::from(self.file_handle) as u8);
+        encoder.option(self.format_string.as_ref(), |encoder, format_string| {
+            encoder.str(&format_string.to_string());
+            encoder.usize(format_string.index());
+        });
+        encoder.bool(self.should_skip_new_line);
+    }
+
+    /// Reads the state of the `PRINT` statement in progress from a snapshot.
+    pub fn decode(decoder: &mut Decoder) -> Result<Self, BytecodeError> {
+        Ok(Self {
+            printer_type: decoder.printer_type()?,
+            file_handle: decoder.u8()?.into(),
+            format_string: decoder.option(|decoder| {
+                let format_string = decoder.str()?;
+                let index = decoder.usize()?;
+                Ok(FormatString::with_index(&format_string, index))
+            })?,
+            should_skip_new_line: decoder.bool()?,
+        })
     }
 }
 
@@ -539,7 +256,7 @@ mod tests {
     #[test]
     fn test_print_using_numeric_types_with_two_integer_digits_format_string() {
         let input = ["42", "2", "-1", "3.0", "3.14#", "3.9", "61.9", "&HFFFFFF"];
-        let output = ["42", " 2", "-1", " 3", " 3", " 4", "62", "16777215"];
+        let output = ["42", " 2", "-1", " 3", " 3", " 4", "62", "%16777215"];
         for i in 0..input.len() {
             let program = format!("PRINT USING \"##\"; {};", input[i]);
             assert_prints_exact!(&program, output[i]);
@@ -561,7 +278,7 @@ mod tests {
             " 3.14",
             " 3.90",
             "61.90",
-            "16777215.00",
+            "%16777215.00",
             " 1.23",
             " 1.99",
             " 2.13",
@@ -649,4 +366,177 @@ mod tests {
         "#;
         assert_prints!(input, "h");
     }
+
+    fn assert_formats(format_string: &str, cases: &[(&str, &str)]) {
+        for (input, expected) in cases {
+            let program = format!("PRINT USING \"{}\"; {};", format_string, input);
+            assert_prints_exact!(&program, *expected);
+        }
+    }
+
+    #[test]
+    fn test_print_using_ampersand() {
+        assert_prints_exact!(
+            "PRINT USING \"[&]\"; \"hello, world\"",
+            "[hello, world]",
+            ""
+        );
+        assert_prints_exact!("PRINT USING \"&-&\"; \"a\"; \"bc\"", "a-bc", "");
+    }
+
+    #[test]
+    fn test_print_using_ampersand_type_mismatch() {
+        assert_interpreter_err!("PRINT USING \"&\"; 42", RuntimeError::TypeMismatch, 1, 18);
+    }
+
+    #[test]
+    fn test_print_using_numeric_with_string_is_type_mismatch() {
+        assert_interpreter_err!(
+            "PRINT USING \"##\"; \"hi\"",
+            RuntimeError::TypeMismatch,
+            1,
+            19
+        );
+    }
+
+    #[test]
+    fn test_print_using_leading_plus() {
+        assert_formats(
+            "+##.#",
+            &[("1.5", " +1.5"), ("-1.5", " -1.5"), ("12", "+12.0")],
+        );
+    }
+
+    #[test]
+    fn test_print_using_trailing_plus() {
+        assert_formats("##.#+", &[("1.5", " 1.5+"), ("-1.5", " 1.5-")]);
+    }
+
+    #[test]
+    fn test_print_using_trailing_minus() {
+        assert_formats(
+            "##.#-",
+            &[("1.5", " 1.5 "), ("-1.5", " 1.5-"), ("-12", "12.0-")],
+        );
+    }
+
+    #[test]
+    fn test_print_using_asterisk_fill() {
+        assert_formats(
+            "**#.#",
+            &[("1.5", "**1.5"), ("-1.5", "*-1.5"), ("123.4", "123.4")],
+        );
+    }
+
+    #[test]
+    fn test_print_using_floating_dollar() {
+        assert_formats("$$##.##", &[("4.5", "  $4.50"), ("123.456", "$123.46")]);
+        assert_formats("$$#", &[("-4", "-$4"), ("4", " $4")]);
+    }
+
+    #[test]
+    fn test_print_using_asterisk_dollar() {
+        assert_formats("**$##.##", &[("4.5", "***$4.50"), ("1234", "$1234.00")]);
+    }
+
+    #[test]
+    fn test_print_using_comma() {
+        assert_formats(
+            "#,######.##",
+            &[("1234567.891#", "%1,234,567.89"), ("1234.5", "   1,234.50")],
+        );
+        assert_formats("##,###", &[("12345", "12,345"), ("123", "   123")]);
+    }
+
+    #[test]
+    fn test_print_using_comma_as_literal_after_field() {
+        assert_prints_exact!("PRINT USING \"##, ##\"; 1; 2", " 1,  2", "");
+    }
+
+    #[test]
+    fn test_print_using_fraction_only() {
+        assert_formats(".##", &[("0.5", ".50"), ("0.126", ".13")]);
+    }
+
+    #[test]
+    fn test_print_using_trailing_decimal_point() {
+        assert_formats("##.", &[("3.7", " 4.")]);
+    }
+
+    #[test]
+    fn test_print_using_exponential() {
+        assert_formats(
+            "##.##^^^^",
+            &[
+                ("234.56", " 2.35E+02"),
+                ("-234.56", "-2.35E+02"),
+                ("0.0012", " 1.20E-03"),
+                ("0", " 0.00E+00"),
+            ],
+        );
+        assert_formats("+.##^^^^", &[("234.56", "+.23E+03")]);
+        assert_formats("###^^^^^", &[("12345", " 12E+003")]);
+        assert_formats("#.##^^^^-", &[("-9.999", "1.00E+01-")]);
+    }
+
+    #[test]
+    fn test_print_using_overflow() {
+        assert_formats(
+            "##.##",
+            &[
+                ("123.456", "%123.46"),
+                ("-12.5", "%-12.50"),
+                ("99.999", "%100.00"),
+            ],
+        );
+        assert_formats("+#", &[("12", "%+12")]);
+    }
+
+    #[test]
+    fn test_print_using_underscore_escapes_formatting_char() {
+        assert_prints_exact!("PRINT USING \"_###_#\"; 12", "#12#", "");
+        assert_prints_exact!("PRINT USING \"__&\"; \"x\"", "_x", "");
+    }
+
+    #[test]
+    fn test_print_using_plus_and_minus_as_literals() {
+        assert_prints_exact!("PRINT USING \"a+b -##-\"; 5", "a+b - 5 ", "");
+        assert_prints_exact!("PRINT USING \"-##\"; 5", "- 5", "");
+    }
+
+    #[test]
+    fn test_print_using_lone_dollar_and_asterisk_are_literals() {
+        assert_formats("$##.##*", &[("4.5", "$ 4.50*")]);
+    }
+
+    #[test]
+    fn test_lprint_using_full_format() {
+        assert_lprints_exact!("LPRINT USING \"**$#,###.##\"; 1234.5", "**$1,234.50", "");
+    }
+
+    #[test]
+    fn test_lprint_form_feed() {
+        let input = r#"
+        LPRINT "page 1"; CHR$(12); "page 2"
+        "#;
+        assert_lprints_exact!(input, "page 1\u{c}page 2", "");
+    }
+
+    #[test]
+    fn test_print_file_using() {
+        let filename = "test_print_file_using.TXT";
+        std::fs::remove_file(filename).unwrap_or(());
+        let input = format!(
+            r#"
+        OPEN "{}" FOR OUTPUT AS #1
+        PRINT #1, USING "$$##.##+ & _!"; 3.5; "each"
+        CLOSE #1
+        "#,
+            filename
+        );
+        crate::interpreter::test_utils::interpret(&input);
+        let contents = std::fs::read_to_string(filename).unwrap_or_default();
+        std::fs::remove_file(filename).unwrap_or(());
+        assert_eq!("  $3.50+ each !\r\n", contents);
+    }
 }
@@ -0,0 +1,405 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use rusty_common::Position;
+
+use crate::diagnostics::Diagnostics;
+use crate::instruction_generator::{Instruction, InstructionPos};
+
+/// The name of the frame of the module-level code.
+const MODULE_LEVEL: &str = "module-level code";
+
+/// Counts the executed instructions and measures the time spent
+/// per source line and per SUB/FUNCTION.
+///
+/// The time of an instruction is the time between its start and the start
+/// of the next one (or the change of the call stack, if it calls or returns),
+/// so it includes any time spent in I/O.
+/// Built-in subs and functions (e.g. `LEN`) are profiled as
+/// separate frames, so that their time can be told apart from the
+/// time of the code that calls them.
+#[derive(Debug, Default)]
+pub struct Profiler {
+    lines: HashMap<u32, LineProfile>,
+    subprograms: HashMap<String, SubprogramProfile>,
+    /// The time spent in every call stack, by the names of its frames
+    /// (outermost first) separated by `;`.
+    stacks: HashMap<String, Duration>,
+    /// The frames of the call stack, outermost first.
+    frames: Vec<ProfiledFrame>,
+    /// The instruction that is currently running, with its start time and row.
+    current: Option<(Instant, u32)>,
+    total_instructions: u64,
+    total_time: Duration,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LineProfile {
+    /// How many instructions of the line ran.
+    pub instructions: u64,
+    pub time: Duration,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SubprogramProfile {
+    pub calls: u64,
+    /// How many instructions ran in the subprogram itself, excluding its calls.
+    pub instructions: u64,
+    /// The time spent in the subprogram itself, excluding its calls.
+    pub self_time: Duration,
+    /// The time spent in the subprogram, including its calls.
+    /// For recursive calls, only the outermost call is counted.
+    pub total_time: Duration,
+}
+
+#[derive(Debug)]
+struct ProfiledFrame {
+    name: String,
+    start: Instant,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Called when the program starts.
+    pub(crate) fn on_start(&mut self) {
+        self.enter(MODULE_LEVEL.to_owned(), Instant::now());
+    }
+
+    /// Called before an instruction runs.
+    pub(crate) fn on_instruction(&mut self, pos: Position) {
+        let now = Instant::now();
+        self.finish_current(now);
+        self.current = Some((now, pos.row()));
+    }
+
+    /// Called after an instruction ran, with the depth of the call stack.
+    /// A call adds a frame, which is named after the subprogram that it calls.
+    pub(crate) fn on_call_depth(
+        &mut self,
+        depth: usize,
+        instructions: &[InstructionPos],
+        index: usize,
+    ) {
+        // the module-level frame is not part of the depth
+        if self.frames.len() == depth + 1 {
+            return;
+        }
+        // the instruction that changed the call stack ran in the previous frame,
+        // and the frames are entered and exited at the same time,
+        // so that the self time of a frame cannot exceed its total time
+        let now = Instant::now();
+        self.finish_current(now);
+        self.current = None;
+        while self.frames.len() > depth + 1 {
+            self.exit(now);
+        }
+        if self.frames.len() < depth + 1 {
+            self.enter(callee_name(instructions, index), now);
+        }
+    }
+
+    /// Called when the program stops.
+    pub(crate) fn on_stop(&mut self) {
+        let now = Instant::now();
+        self.finish_current(now);
+        self.current = None;
+        while !self.frames.is_empty() {
+            self.exit(now);
+        }
+    }
+
+    fn finish_current(&mut self, now: Instant) {
+        let Some((start, row)) = self.current else {
+            return;
+        };
+        let elapsed = now - start;
+        self.total_instructions += 1;
+        self.total_time += elapsed;
+        // the halt instruction at the end of the program has no source line
+        if row != u32::MAX {
+            let line = self.lines.entry(row).or_default();
+            line.instructions += 1;
+            line.time += elapsed;
+        }
+        if let Some(frame) = self.frames.last() {
+            let subprogram = self.subprograms.entry(frame.name.clone()).or_default();
+            subprogram.instructions += 1;
+            subprogram.self_time += elapsed;
+            let stack: Vec<&str> = self.frames.iter().map(|f| f.name.as_str()).collect();
+            *self.stacks.entry(stack.join(";")).or_default() += elapsed;
+        }
+    }
+
+    fn enter(&mut self, name: String, now: Instant) {
+        self.subprograms.entry(name.clone()).or_default().calls += 1;
+        self.frames.push(ProfiledFrame { name, start: now });
+    }
+
+    fn exit(&mut self, now: Instant) {
+        let frame = self.frames.pop().expect("Profiler frames underflow");
+        let is_recursive = self.frames.iter().any(|f| f.name == frame.name);
+        if !is_recursive {
+            self.subprograms.entry(frame.name).or_default().total_time += now - frame.start;
+        }
+    }
+
+    pub fn total_instructions(&self) -> u64 {
+        self.total_instructions
+    }
+
+    pub fn total_time(&self) -> Duration {
+        self.total_time
+    }
+
+    /// Gets the profile of every source line that ran, by row.
+    pub fn lines(&self) -> &HashMap<u32, LineProfile> {
+        &self.lines
+    }
+
+    /// Gets the profile of every SUB, FUNCTION and built-in that ran, by name.
+    pub fn subprograms(&self) -> &HashMap<String, SubprogramProfile> {
+        &self.subprograms
+    }
+
+    /// Renders a report of the subprograms and of the lines,
+    /// the most expensive first.
+    pub fn report(&self, diagnostics: &Diagnostics) -> String {
+        let mut buf = String::new();
+        writeln!(
+            buf,
+            "{} instructions in {}",
+            self.total_instructions,
+            format_millis(self.total_time)
+        )
+        .unwrap();
+        writeln!(buf).unwrap();
+        writeln!(
+            buf,
+            "{:>12} {:>12} {:>10} {:>14}  subprogram",
+            "total", "self", "calls", "instructions"
+        )
+        .unwrap();
+        let mut subprograms: Vec<(&String, &SubprogramProfile)> = self.subprograms.iter().collect();
+        subprograms.sort_by(|(left_name, left), (right_name, right)| {
+            right
+                .total_time
+                .cmp(&left.total_time)
+                .then_with(|| left_name.cmp(right_name))
+        });
+        for (name, subprogram) in subprograms {
+            writeln!(
+                buf,
+                "{:>12} {:>12} {:>10} {:>14}  {}",
+                format_millis(subprogram.total_time),
+                format_millis(subprogram.self_time),
+                subprogram.calls,
+                subprogram.instructions,
+                name
+            )
+            .unwrap();
+        }
+        writeln!(buf).unwrap();
+        writeln!(buf, "{:>12} {:>14}  line", "time", "instructions").unwrap();
+        let mut lines: Vec<(&u32, &LineProfile)> = self.lines.iter().collect();
+        lines.sort_by(|(left_row, left), (right_row, right)| {
+            right
+                .time
+                .cmp(&left.time)
+                .then_with(|| left_row.cmp(right_row))
+        });
+        for (row, line) in lines {
+            let location = diagnostics.location(Position::new(*row, 1));
+            // the column is always 1, keep only the file and the row
+            let location = location.strip_suffix(":1").unwrap_or(&location);
+            write!(
+                buf,
+                "{:>12} {:>14}  {}",
+                format_millis(line.time),
+                line.instructions,
+                location
+            )
+            .unwrap();
+            match diagnostics.line(*row) {
+                Some(source) => writeln!(buf, "  {}", source.trim()).unwrap(),
+                None => writeln!(buf).unwrap(),
+            }
+        }
+        buf
+    }
+
+    /// Renders the time spent in every call stack in the "folded stacks" format
+    /// of flame graph tools, e.g. `module-level code;Fib;Fib 1500`.
+    /// The time is in microseconds.
+    pub fn folded_stacks(&self) -> String {
+        let mut stacks: Vec<(&String, &Duration)> = self.stacks.iter().collect();
+        stacks.sort();
+        let mut buf = String::new();
+        for (stack, time) in stacks {
+            writeln!(buf, "{} {}", stack, time.as_micros()).unwrap();
+        }
+        buf
+    }
+}
+
+fn format_millis(duration: Duration) -> String {
+    format!("{:.3}ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// Gets the name of the subprogram that is called by the given instruction,
+/// which adds a frame to the call stack.
+///
+/// Built-ins run right after the instruction that adds their frame.
+/// Subs and functions jump to their label right after pushing the return address.
+fn callee_name(instructions: &[InstructionPos], index: usize) -> String {
+    let next = |index: usize| instructions.get(index).map(|i| &i.element);
+    match next(index + 1) {
+        Some(Instruction::BuiltInSub(built_in_sub)) => {
+            format!("{:?}", built_in_sub).to_ascii_uppercase()
+        }
+        Some(Instruction::BuiltInFunction(built_in_function)) => {
+            built_in_function.as_str().to_ascii_uppercase()
+        }
+        Some(Instruction::CallNative(name)) => name.to_string(),
+        Some(Instruction::PushRet(_)) => match next(index + 2) {
+            Some(Instruction::Jump(address)) => match next(address.address()) {
+                Some(Instruction::Label(label)) => label
+                    .strip_prefix(":fun:")
+                    .or_else(|| label.strip_prefix(":sub:"))
+                    .unwrap_or(label)
+                    .to_owned(),
+                _ => "?".to_owned(),
+            },
+            _ => "?".to_owned(),
+        },
+        _ => "?".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::interpreter_trait::InterpreterTrait;
+    use crate::interpreter::test_utils::mock_interpreter_for_input;
+
+    const PROGRAM: &str = r#"
+FOR I = 1 TO 3
+    Hello
+NEXT
+PRINT Twice(LEN("abc"))
+
+SUB Hello
+    PRINT "hello"
+END SUB
+
+FUNCTION Twice(X)
+    Twice = X * 2
+END FUNCTION
+"#;
+
+    const RECURSIVE_PROGRAM: &str = r#"
+PRINT Fact(5)
+
+FUNCTION Fact(N)
+    IF N <= 1 THEN Fact = 1 ELSE Fact = N * Fact(N - 1)
+END FUNCTION
+"#;
+
+    fn profile(input: &str) -> Profiler {
+        let (instruction_generator_result, mut interpreter) = mock_interpreter_for_input(input);
+        let mut profiler = Profiler::new();
+        interpreter
+            .profile(instruction_generator_result, &mut profiler)
+            .unwrap();
+        profiler
+    }
+
+    #[test]
+    fn counts_calls_per_subprogram() {
+        let profiler = profile(PROGRAM);
+        let calls = |name: &str| profiler.subprograms()[name].calls;
+        assert_eq!(calls(MODULE_LEVEL), 1);
+        assert_eq!(calls("Hello"), 3);
+        assert_eq!(calls("Twice!"), 1);
+        assert_eq!(calls("LEN"), 1);
+    }
+
+    #[test]
+    fn counts_instructions_per_line() {
+        let profiler = profile(PROGRAM);
+        let lines = profiler.lines();
+        // the body of Hello runs once per call
+        assert_eq!(lines[&8].instructions % 3, 0);
+        assert!(lines[&12].instructions > 0);
+        // lines that never run are not reported
+        assert!(!lines.contains_key(&9));
+        let instructions: u64 = profiler
+            .subprograms()
+            .values()
+            .map(|s| s.instructions)
+            .sum();
+        assert_eq!(instructions, profiler.total_instructions());
+    }
+
+    #[test]
+    fn total_time_includes_callees() {
+        let profiler = profile(PROGRAM);
+        let module_level = profiler.subprograms()[MODULE_LEVEL];
+        let hello = profiler.subprograms()["Hello"];
+        assert!(module_level.total_time >= hello.total_time);
+        assert!(module_level.total_time >= module_level.self_time);
+    }
+
+    #[test]
+    fn self_time_does_not_exceed_total_time() {
+        for input in [PROGRAM, RECURSIVE_PROGRAM] {
+            let profiler = profile(input);
+            for (name, subprogram) in profiler.subprograms() {
+                assert!(
+                    subprogram.self_time <= subprogram.total_time,
+                    "{}: {:?}",
+                    name,
+                    subprogram
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn recursive_calls_are_counted_once_in_total_time() {
+        let profiler = profile(RECURSIVE_PROGRAM);
+        let fact = profiler.subprograms()["Fact!"];
+        assert_eq!(fact.calls, 5);
+        assert!(fact.total_time <= profiler.total_time());
+    }
+
+    #[test]
+    fn folded_stacks() {
+        let profiler = profile(PROGRAM);
+        let folded_stacks = profiler.folded_stacks();
+        let stacks: Vec<&str> = folded_stacks
+            .lines()
+            .map(|line| line.rsplit_once(' ').unwrap().0)
+            .collect();
+        assert_eq!(
+            stacks,
+            vec![
+                "module-level code",
+                "module-level code;Hello",
+                "module-level code;LEN",
+                "module-level code;Twice!",
+            ]
+        );
+    }
+
+    #[test]
+    fn report_lists_subprograms_and_lines() {
+        let profiler = profile(PROGRAM);
+        let report = profiler.report(&Diagnostics::new("PROGRAM.BAS", PROGRAM));
+        assert!(report.contains("  Hello\n"));
+        assert!(report.contains("  PROGRAM.BAS:12  Twice = X * 2\n"));
+    }
+}
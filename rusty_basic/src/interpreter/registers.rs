@@ -1,5 +1,9 @@
 use rusty_variant::Variant;
 
+use crate::bytecode::BytecodeError;
+use crate::bytecode::decoder::Decoder;
+use crate::bytecode::encoder::Encoder;
+
 #[derive(Debug)]
 pub struct Registers {
     a: Variant,
@@ -26,10 +30,22 @@ impl Registers {
         self.b.clone()
     }
 
+    pub fn a(&self) -> &Variant {
+        &self.a
+    }
+
+    pub fn b(&self) -> &Variant {
+        &self.b
+    }
+
     pub fn set_a(&mut self, v: Variant) {
         self.a = v;
     }
 
+    pub fn set_b(&mut self, v: Variant) {
+        self.b = v;
+    }
+
     pub fn copy_a_to_b(&mut self) {
         self.b = self.a.clone();
     }
@@ -53,6 +69,23 @@ impl Registers {
     pub fn copy_d_to_b(&mut self) {
         self.b = self.d.clone();
     }
+
+    /// Writes the registers into a snapshot.
+    pub fn encode(&self, encoder: &mut Encoder) {
+        for v in [&self.a, &self.b, &self.c, &self.d] {
+            encoder.variant(v);
+        }
+    }
+
+    /// Reads the registers from a snapshot.
+    pub fn decode(decoder: &mut Decoder) -> Result<Self, BytecodeError> {
+        Ok(Self {
+            a: decoder.variant()?,
+            b: decoder.variant()?,
+            c: decoder.variant()?,
+            d: decoder.variant()?,
+        })
+    }
 }
 
 pub type RegisterStack = Vec<Registers>;
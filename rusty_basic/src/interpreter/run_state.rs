@@ -0,0 +1,32 @@
+/// The state of a program that runs with a budget of instructions
+/// (see [crate::interpreter::InterpreterTrait::resume]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunState {
+    /// The budget of instructions was used up. The program continues
+    /// with the next call to `resume`.
+    Yielded,
+
+    /// The program needs input that is not available yet.
+    /// The program continues with the next call to `resume`,
+    /// after the input has been supplied.
+    WaitingForInput(InputRequest),
+
+    /// The program has ended (or no program has been started).
+    Finished,
+}
+
+/// The kind of input that a paused program waits for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputRequest {
+    /// `INPUT` or `LINE INPUT` needs a whole line from the standard input.
+    ///
+    /// An `INPUT` statement with several variables expects all of their
+    /// values on the same line, separated by commas.
+    Line,
+
+    /// `INKEY$` found no key in the keyboard buffer.
+    ///
+    /// If the program is resumed without supplying a key,
+    /// `INKEY$` returns an empty string, as it does when no key was pressed.
+    Key,
+}
@@ -29,10 +29,9 @@ pub trait Screen {
     fn reset_view_print(&mut self);
 }
 
-#[cfg(test)]
+/// Ignores all screen commands, for when the standard output is not a terminal.
 pub struct HeadlessScreen {}
 
-#[cfg(test)]
 impl Screen for HeadlessScreen {
     fn cls(&self) -> Result<(), RuntimeError> {
         Ok(())
@@ -0,0 +1,118 @@
+//! The binary format of a snapshot of the interpreter.
+//!
+//! A snapshot holds the state of a program that was started with
+//! [crate::interpreter::InterpreterTrait::start]: the index of the next
+//! instruction, the variables of every memory block, the registers,
+//! the various stacks, the `DATA` cursor, the error handling state and
+//! the open files.
+//!
+//! The file starts with a header:
+//!
+//! | bytes | content                                                      |
+//! |-------|--------------------------------------------------------------|
+//! | 4     | the magic bytes `RBSS`                                       |
+//! | 2     | the version of the format                                    |
+//! | 1 + n | the version of the interpreter that saved it                 |
+//! | 8     | the fingerprint of the instructions of the program           |
+//! | 8     | a checksum of the payload                                    |
+//!
+//! The payload follows, in the encoding of [crate::bytecode].
+
+use std::fmt::Display;
+
+use crate::RuntimeError;
+use crate::bytecode::decoder::Decoder;
+use crate::bytecode::encoder::Encoder;
+use crate::bytecode::{BytecodeError, INTERPRETER_VERSION, checksum};
+
+/// The first bytes of a snapshot.
+const MAGIC: &[u8; 4] = b"RBSS";
+
+/// The version of the snapshot format.
+/// It needs to be increased whenever the encoding changes.
+const FORMAT_VERSION: u16 = 2;
+
+#[derive(Debug, PartialEq)]
+pub enum SnapshotError {
+    /// There is no program running (it was not started or it has finished).
+    NotRunning,
+
+    /// The bytes do not start with the magic bytes of a snapshot.
+    NotSnapshot,
+
+    /// The snapshot was saved by a different version of the interpreter
+    /// or of the format.
+    VersionMismatch(String),
+
+    /// The snapshot was saved while running a different program.
+    ProgramMismatch,
+
+    /// The snapshot is truncated or its contents are invalid.
+    Corrupted(String),
+
+    /// An open file could not be saved or opened again.
+    File(RuntimeError),
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotRunning => f.write_str("No program is running"),
+            Self::NotSnapshot => f.write_str("Not a snapshot"),
+            Self::VersionMismatch(version) => write!(
+                f,
+                "Snapshot was saved by version {}, this is version {}",
+                version, INTERPRETER_VERSION
+            ),
+            Self::ProgramMismatch => f.write_str("Snapshot was saved by a different program"),
+            Self::Corrupted(msg) => write!(f, "Snapshot is corrupted: {}", msg),
+            Self::File(err) => write!(f, "Could not restore open file: {}", err),
+        }
+    }
+}
+
+impl From<BytecodeError> for SnapshotError {
+    fn from(err: BytecodeError) -> Self {
+        match err {
+            BytecodeError::Corrupted(msg) => Self::Corrupted(msg),
+            _ => Self::Corrupted(err.to_string()),
+        }
+    }
+}
+
+/// Adds the header to the given payload.
+pub fn to_bytes(fingerprint: u64, payload: Vec<u8>) -> Vec<u8> {
+    let mut header = Encoder::new();
+    header.raw(MAGIC);
+    header.u16(FORMAT_VERSION);
+    header.short_str(INTERPRETER_VERSION);
+    header.u64(fingerprint);
+    header.u64(checksum(&payload));
+    let mut bytes = header.into_bytes();
+    bytes.extend(payload);
+    bytes
+}
+
+/// Validates the header of the given snapshot
+/// against the fingerprint of the program that restores it,
+/// returning the payload.
+pub fn payload(bytes: &[u8], fingerprint: u64) -> Result<&[u8], SnapshotError> {
+    if !bytes.starts_with(MAGIC) {
+        return Err(SnapshotError::NotSnapshot);
+    }
+    let mut header = Decoder::new(&bytes[MAGIC.len()..]);
+    let format_version = header.u16()?;
+    let interpreter_version = header.short_str()?;
+    if format_version != FORMAT_VERSION || interpreter_version != INTERPRETER_VERSION {
+        return Err(SnapshotError::VersionMismatch(interpreter_version));
+    }
+    if header.u64()? != fingerprint {
+        return Err(SnapshotError::ProgramMismatch);
+    }
+    let expected_checksum = header.u64()?;
+    let payload = header.remaining();
+    if checksum(payload) != expected_checksum {
+        return Err(SnapshotError::Corrupted("checksum mismatch".to_owned()));
+    }
+    Ok(payload)
+}
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 /// The standard functions that QBasic offers
 pub trait Stdlib {
     /// Implementation of SYSTEM
@@ -8,4 +10,11 @@ pub trait Stdlib {
 
     /// Sets an environment variable (used by built-in sub ENVIRON)
     fn set_env_var(&mut self, name: String, value: String);
+
+    /// Gets the command line arguments of the program (used by built-in function COMMAND$)
+    fn command_line(&self) -> String;
+
+    /// Gets the current time, measured from an arbitrary fixed point
+    /// (used by event trapping with `ON TIMER`)
+    fn now(&self) -> Duration;
 }
@@ -1,31 +1,43 @@
-use std::collections::HashMap;
+use std::cell::Cell;
+use std::collections::{HashMap, VecDeque};
 use std::fs::File;
 use std::io::Read;
+use std::time::Duration;
 
+use crossterm::event::{Event, KeyEvent};
 use rusty_linter::core::lint;
 use rusty_parser::{UserDefinedTypes, parse_main_file};
+use rusty_runtime::{ReadInputSource, WritePrinter};
 
-use crate::RuntimeErrorPos;
-use crate::instruction_generator::test_utils::generate_instructions_str_with_types;
+use crate::instruction_generator::test_utils::{
+    generate_instructions_str_in_tests, generate_instructions_str_with_types,
+};
 use crate::instruction_generator::{
     InstructionGeneratorResult, generate_instructions, unwrap_linter_context,
 };
 use crate::interpreter::Stdlib;
 use crate::interpreter::interpreter_trait::InterpreterTrait;
+use crate::interpreter::keyboard::Keyboard;
+use crate::interpreter::lpt1_write::LPT1_DEFAULT_WIDTH;
 use crate::interpreter::main::Interpreter;
-use crate::interpreter::read_input::ReadInputSource;
 use crate::interpreter::screen::{CrossTermScreen, HeadlessScreen};
-use crate::interpreter::write_printer::WritePrinter;
+use crate::{RuntimeError, RuntimeErrorPos};
 
 type MockStdout = WritePrinter<Vec<u8>>;
 
 pub trait MockInterpreterTrait:
-    InterpreterTrait<TStdOut = MockStdout, TStdIn = ReadInputSource<MockStdin>, TLpt1 = MockStdout>
+    InterpreterTrait<
+        TStdlib = MockStdlib,
+        TStdOut = MockStdout,
+        TStdIn = ReadInputSource<MockStdin>,
+        TLpt1 = MockStdout,
+    >
 {
 }
 
 impl<S> MockInterpreterTrait for S where
     S: InterpreterTrait<
+            TStdlib = MockStdlib,
             TStdOut = MockStdout,
             TStdIn = ReadInputSource<MockStdin>,
             TLpt1 = MockStdout,
@@ -33,7 +45,7 @@ impl<S> MockInterpreterTrait for S where
 {
 }
 
-fn mock_interpreter_for_user_defined_types(
+pub fn mock_interpreter_for_user_defined_types(
     user_defined_types: UserDefinedTypes,
 ) -> impl MockInterpreterTrait {
     let stdlib = MockStdlib::default();
@@ -43,10 +55,22 @@ fn mock_interpreter_for_user_defined_types(
 fn mock_interpreter_for_user_defined_types_stdlib(
     user_defined_types: UserDefinedTypes,
     stdlib: MockStdlib,
+) -> impl MockInterpreterTrait {
+    mock_interpreter_for_user_defined_types_stdlib_keyboard(
+        user_defined_types,
+        stdlib,
+        MockKeyboard::default(),
+    )
+}
+
+fn mock_interpreter_for_user_defined_types_stdlib_keyboard(
+    user_defined_types: UserDefinedTypes,
+    stdlib: MockStdlib,
+    keyboard: MockKeyboard,
 ) -> impl MockInterpreterTrait {
     let stdin = ReadInputSource::new(MockStdin { stdin: vec![] });
     let stdout = WritePrinter::new(vec![]);
-    let lpt1 = WritePrinter::new(vec![]);
+    let lpt1 = WritePrinter::with_width(vec![], LPT1_DEFAULT_WIDTH);
     if std::env::var("USE_REAL_SCREEN")
         .unwrap_or_default()
         .is_empty()
@@ -57,6 +81,7 @@ fn mock_interpreter_for_user_defined_types_stdlib(
             stdout,
             lpt1,
             HeadlessScreen {},
+            keyboard,
             user_defined_types,
         )
     } else {
@@ -66,11 +91,16 @@ fn mock_interpreter_for_user_defined_types_stdlib(
             stdout,
             lpt1,
             CrossTermScreen::default(),
+            keyboard,
             user_defined_types,
         )
     }
 }
 
+pub fn mock_interpreter() -> impl MockInterpreterTrait {
+    mock_interpreter_for_user_defined_types(UserDefinedTypes::default())
+}
+
 pub fn mock_interpreter_for_input(
     input: &str,
 ) -> (InstructionGeneratorResult, impl MockInterpreterTrait) {
@@ -98,6 +128,29 @@ pub fn interpret_err(input: &str) -> RuntimeErrorPos {
         .unwrap_err()
 }
 
+/// Interprets the given program, which can call the assertions of the unit
+/// tests (e.g. `ASSERT`), returning its error.
+pub fn interpret_err_in_tests(input: &str) -> RuntimeErrorPos {
+    let (instruction_generator_result, user_defined_types) =
+        generate_instructions_str_in_tests(input);
+    let mut interpreter = mock_interpreter_for_user_defined_types(user_defined_types);
+    interpreter
+        .interpret(instruction_generator_result)
+        .unwrap_err()
+}
+
+/// Interprets the given program, which can call the assertions of the unit
+/// tests (e.g. `ASSERT`).
+pub fn interpret_in_tests(input: &str) -> impl MockInterpreterTrait {
+    let (instruction_generator_result, user_defined_types) =
+        generate_instructions_str_in_tests(input);
+    let mut interpreter = mock_interpreter_for_user_defined_types(user_defined_types);
+    interpreter
+        .interpret(instruction_generator_result)
+        .map(|_| interpreter)
+        .unwrap()
+}
+
 pub fn interpret_with_raw_input(input: &str, raw_input: &str) -> impl MockInterpreterTrait {
     let (instruction_generator_result, user_defined_types) =
         generate_instructions_str_with_types(input);
@@ -106,7 +159,7 @@ pub fn interpret_with_raw_input(input: &str, raw_input: &str) -> impl MockInterp
     // }
     let mut interpreter = mock_interpreter_for_user_defined_types(user_defined_types);
     if !raw_input.is_empty() {
-        interpreter.stdin().add_next_input(raw_input);
+        add_next_input(interpreter.stdin(), raw_input);
     }
     interpreter
         .interpret(instruction_generator_result)
@@ -126,6 +179,27 @@ pub fn interpret_with_env(input: &str, stdlib: MockStdlib) -> impl MockInterpret
         .unwrap()
 }
 
+pub fn interpret_with_env_and_keys(
+    input: &str,
+    stdlib: MockStdlib,
+    keys: &[KeyEvent],
+) -> impl MockInterpreterTrait + use<> {
+    let (instruction_generator_result, user_defined_types) =
+        generate_instructions_str_with_types(input);
+    let keyboard = MockKeyboard {
+        events: keys.iter().map(|key| Event::Key(*key)).collect(),
+    };
+    let mut interpreter = mock_interpreter_for_user_defined_types_stdlib_keyboard(
+        user_defined_types,
+        stdlib,
+        keyboard,
+    );
+    interpreter
+        .interpret(instruction_generator_result)
+        .map(|_| interpreter)
+        .unwrap()
+}
+
 pub fn interpret_file(filename: &str) -> Result<impl MockInterpreterTrait, RuntimeErrorPos> {
     let file_path = format!("../fixtures/{}", filename);
     let f = File::open(file_path).expect("Could not read bas file");
@@ -151,7 +225,7 @@ pub fn interpret_file_with_raw_input(
     let instruction_generator_result = generate_instructions(linted_program, linter_names);
     let mut interpreter = mock_interpreter_for_user_defined_types(user_defined_types);
     if !raw_input.is_empty() {
-        interpreter.stdin().add_next_input(raw_input);
+        add_next_input(interpreter.stdin(), raw_input);
     }
     interpreter
         .interpret(instruction_generator_result)
@@ -161,6 +235,27 @@ pub fn interpret_file_with_raw_input(
 #[derive(Default)]
 pub struct MockStdlib {
     pub env: HashMap<String, String>,
+
+    /// The current time, as returned by `now()`.
+    pub clock: Cell<Duration>,
+
+    /// Every call to `now()` advances the clock by this amount.
+    pub clock_step: Duration,
+
+    /// The command line arguments, as returned by `command_line()`.
+    pub command_line: String,
+}
+
+/// A scripted keyboard that returns the given events, one per poll.
+#[derive(Default)]
+pub struct MockKeyboard {
+    pub events: VecDeque<Event>,
+}
+
+impl Keyboard for MockKeyboard {
+    fn poll_event(&mut self, _timeout: Duration) -> Result<Option<Event>, RuntimeError> {
+        Ok(self.events.pop_front())
+    }
 }
 
 #[derive(Default)]
@@ -168,10 +263,8 @@ pub struct MockStdin {
     pub stdin: Vec<u8>,
 }
 
-impl ReadInputSource<MockStdin> {
-    pub fn add_next_input(&mut self, value: &str) {
-        self.inner().stdin.extend_from_slice(value.as_bytes());
-    }
+fn add_next_input(stdin: &mut ReadInputSource<MockStdin>, value: &str) {
+    stdin.inner().stdin.extend_from_slice(value.as_bytes());
 }
 
 impl Read for MockStdin {
@@ -185,41 +278,6 @@ impl Read for MockStdin {
     }
 }
 
-impl MockStdout {
-    /// Gets the captured output of stdout as-is, without trimming or removing CRLF
-    pub fn output_exact(&self) -> String {
-        let bytes: Vec<u8> = self.inner().clone();
-        String::from_utf8(bytes).unwrap()
-    }
-
-    /// Gets the captured output of stdout, trimmed and without CRLF
-    pub fn output(&self) -> String {
-        self.output_exact()
-            .trim()
-            .trim_matches(|ch| ch == '\r' || ch == '\n')
-            .to_string()
-    }
-
-    /// Gets the captured output of stdout as a collection of lines.
-    /// Each line is trimmed of whitespace and CRLF.
-    pub fn output_lines(&self) -> Vec<String> {
-        self.output_exact()
-            .trim_matches(|ch| ch == '\r' || ch == '\n')
-            .split("\r\n")
-            .map(|x| x.trim().to_string())
-            .collect()
-    }
-
-    /// Gets the captures output of stdout as a collection of lines.
-    /// Lines are not trimmed.
-    pub fn output_lines_exact(&self) -> Vec<String> {
-        self.output_exact()
-            .split("\r\n")
-            .map(|x| x.to_string())
-            .collect()
-    }
-}
-
 impl Stdlib for MockStdlib {
     fn system(&self) {
         println!("would have exited")
@@ -235,6 +293,16 @@ impl Stdlib for MockStdlib {
     fn set_env_var(&mut self, name: String, value: String) {
         self.env.insert(name, value);
     }
+
+    fn command_line(&self) -> String {
+        self.command_line.clone()
+    }
+
+    fn now(&self) -> Duration {
+        let now = self.clock.get();
+        self.clock.set(now + self.clock_step);
+        now
+    }
 }
 
 #[macro_export]
@@ -259,6 +327,19 @@ macro_rules! assert_interpreter_err {
     };
 }
 
+#[macro_export]
+macro_rules! assert_interpreter_err_in_tests {
+    ($program:expr, $expected_err:expr, $expected_row:expr, $expected_col:expr) => {
+        assert_eq!(
+            $crate::interpreter::test_utils::interpret_err_in_tests($program),
+            $crate::ErrorEnvelope::new(
+                $expected_err,
+                rusty_common::Position::new($expected_row, $expected_col)
+            )
+        );
+    };
+}
+
 #[macro_export]
 macro_rules! assert_prints_nothing {
     ($program:expr) => {
@@ -411,3 +411,36 @@ mod redim {
         assert_prints!(input, "42");
     }
 }
+
+#[test]
+fn test_dim_skipped_with_go_to() {
+    let program = r#"
+    GOTO Skip
+    DIM A AS INTEGER
+    Skip:
+    PRINT A
+    "#;
+    assert_prints!(program, "0");
+}
+
+#[test]
+fn test_dim_skipped_in_if_block() {
+    let program = r#"
+    IF 0 THEN
+        DIM B AS STRING
+    END IF
+    PRINT B; "x"
+    "#;
+    assert_prints!(program, "x");
+}
+
+#[test]
+fn test_dim_skipped_passed_by_reference() {
+    let program = r#"
+    IF 0 THEN
+        DIM B AS STRING
+    END IF
+    PRINT LEN(B)
+    "#;
+    assert_prints!(program, "0");
+}
@@ -1,6 +1,6 @@
 use crate::interpreter::interpreter_trait::InterpreterTrait;
 use crate::interpreter::test_utils::*;
-use crate::{assert_has_variable, assert_prints};
+use crate::{RuntimeError, assert_has_variable, assert_interpreter_err, assert_prints};
 
 mod binary_plus {
     use super::*;
@@ -67,6 +67,49 @@ mod binary_plus {
         "#;
         assert_prints!(program, "4");
     }
+
+    #[test]
+    fn test_integer_overflow() {
+        assert_interpreter_err!("X% = 32767 + 1", RuntimeError::Overflow, 1, 12);
+        assert_interpreter_err!(
+            "X& = 2147483647 + 2147483647",
+            RuntimeError::Overflow,
+            1,
+            17
+        );
+    }
+
+    #[test]
+    fn test_long_overflow_after_type_promotion() {
+        assert_interpreter_err!(
+            "C& = 2147483647: C& = C& + 1",
+            RuntimeError::Overflow,
+            1,
+            26
+        );
+        assert_interpreter_err!(
+            "C& = -2147483648: C& = C& - 1",
+            RuntimeError::Overflow,
+            1,
+            27
+        );
+        assert_interpreter_err!(
+            "C& = 65536: PRINT C& * 32767 * 2",
+            RuntimeError::Overflow,
+            1,
+            30
+        );
+    }
+
+    #[test]
+    fn test_integer_variables_in_range() {
+        let program = r#"
+        A% = 32766
+        B% = 1
+        PRINT A% + B%
+        "#;
+        assert_prints!(program, "32767");
+    }
 }
 
 mod binary_minus {
@@ -140,6 +183,24 @@ mod multiply {
         "#;
         assert_prints!(input, "15");
     }
+
+    #[test]
+    fn test_multiply_integer_overflow() {
+        let input = r#"
+        A% = 200
+        PRINT A% * A%
+        "#;
+        assert_interpreter_err!(input, RuntimeError::Overflow, 3, 18);
+    }
+
+    #[test]
+    fn test_multiply_long() {
+        let input = r#"
+        A& = 200
+        PRINT A& * A&
+        "#;
+        assert_prints!(input, "40000");
+    }
 }
 
 mod divide {
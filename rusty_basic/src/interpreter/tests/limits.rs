@@ -0,0 +1,316 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use rusty_common::Position;
+
+use crate::interpreter::interpreter_trait::InterpreterTrait;
+use crate::interpreter::test_utils::mock_interpreter_for_input;
+use crate::interpreter::{DEFAULT_MAX_CALL_DEPTH, ExecutionLimits, FilePolicy};
+use crate::{RuntimeError, RuntimeErrorPos};
+
+fn interpret_with_limits(input: &str, limits: ExecutionLimits) -> Result<String, RuntimeErrorPos> {
+    let (instruction_generator_result, mut interpreter) = mock_interpreter_for_input(input);
+    interpreter.set_limits(limits);
+    interpreter.stdlib_mut().clock_step = Duration::from_millis(1);
+    interpreter.interpret(instruction_generator_result)?;
+    Ok(interpreter.stdout().output_lines().join("\n"))
+}
+
+fn interpret_err_with_limits(input: &str, limits: ExecutionLimits) -> (RuntimeError, u32) {
+    let e = interpret_with_limits(input, limits).unwrap_err();
+    (e.err().clone(), e.stacktrace()[0].row())
+}
+
+const INFINITE_LOOP: &str = r#"
+ON ERROR RESUME NEXT
+WHILE -1
+    I = I + 1
+WEND
+"#;
+
+#[test]
+fn max_instructions() {
+    let limits = ExecutionLimits {
+        max_instructions: Some(1_000),
+        ..Default::default()
+    };
+    let (err, _) = interpret_err_with_limits(INFINITE_LOOP, limits);
+    assert_eq!(err, RuntimeError::InstructionLimitExceeded);
+}
+
+#[test]
+fn max_instructions_not_exceeded() {
+    let limits = ExecutionLimits {
+        max_instructions: Some(1_000),
+        ..Default::default()
+    };
+    assert_eq!(
+        interpret_with_limits("PRINT \"hello\"", limits).unwrap(),
+        "hello"
+    );
+}
+
+#[test]
+fn timeout() {
+    let limits = ExecutionLimits {
+        timeout: Some(Duration::from_millis(10)),
+        ..Default::default()
+    };
+    let (err, _) = interpret_err_with_limits(INFINITE_LOOP, limits);
+    assert_eq!(err, RuntimeError::TimeLimitExceeded);
+}
+
+#[test]
+fn max_memory_dim() {
+    let input = r#"
+    DIM A(100) AS INTEGER
+    DIM B(100) AS INTEGER
+    "#;
+    let limits = ExecutionLimits {
+        max_memory: Some(300),
+        ..Default::default()
+    };
+    assert_eq!(
+        interpret_err_with_limits(input, limits),
+        (RuntimeError::OutOfMemory, 3)
+    );
+}
+
+#[test]
+fn max_memory_dim_of_strings() {
+    let limits = ExecutionLimits {
+        max_memory: Some(1_000),
+        ..Default::default()
+    };
+    assert_eq!(
+        interpret_err_with_limits("DIM A$(1 TO 1000, 1 TO 1000)", limits),
+        (RuntimeError::OutOfMemory, 1)
+    );
+}
+
+#[test]
+fn max_memory_concatenation() {
+    let input = r#"
+    ON ERROR RESUME NEXT
+    A$ = "x"
+    WHILE -1
+        A$ = A$ + A$
+    WEND
+    "#;
+    let limits = ExecutionLimits {
+        max_memory: Some(1_000),
+        ..Default::default()
+    };
+    assert_eq!(
+        interpret_err_with_limits(input, limits),
+        (RuntimeError::OutOfMemory, 5)
+    );
+}
+
+#[test]
+fn max_memory_space() {
+    let limits = ExecutionLimits {
+        max_memory: Some(1_000),
+        ..Default::default()
+    };
+    assert_eq!(
+        interpret_err_with_limits("A$ = SPACE$(30000)", limits),
+        (RuntimeError::OutOfMemory, 1)
+    );
+}
+
+#[test]
+fn max_memory_short_program() {
+    // ends before the periodic check of the memory
+    let input = r#"
+    DIM A(30000) AS DOUBLE
+    B$ = SPACE$(30000)
+    C$ = B$ + B$ + B$ + B$
+    "#;
+    let limits = ExecutionLimits {
+        max_memory: Some(300_000),
+        ..Default::default()
+    };
+    assert_eq!(
+        interpret_err_with_limits(input, limits),
+        (RuntimeError::OutOfMemory, 4)
+    );
+}
+
+#[test]
+fn max_memory_many_variables() {
+    let input = r#"
+    DIM A(1 TO 10) AS STRING * 100
+    FOR I = 1 TO 100
+        A(1 + I MOD 10) = STRING$(100, "x")
+    NEXT
+    DIM B(1 TO 10) AS STRING * 100
+    "#;
+    let limits = ExecutionLimits {
+        max_memory: Some(1_500),
+        ..Default::default()
+    };
+    assert_eq!(
+        interpret_err_with_limits(input, limits),
+        (RuntimeError::OutOfMemory, 6)
+    );
+}
+
+#[test]
+fn max_call_depth() {
+    let input = r#"
+    DECLARE SUB Recurse(N)
+    Recurse 1
+
+    SUB Recurse(N)
+        PRINT N
+        Recurse N + 1
+    END SUB
+    "#;
+    let limits = ExecutionLimits {
+        max_call_depth: Some(10),
+        ..Default::default()
+    };
+    let e = interpret_with_limits(input, limits).unwrap_err();
+    assert_eq!(e.err(), &RuntimeError::OutOfStackSpace);
+    // the stacktrace goes all the way down
+    assert_eq!(e.stacktrace().len(), 11);
+    assert_eq!(e.stacktrace()[10], Position::new(3, 5));
+}
+
+#[test]
+fn max_call_depth_with_error_handler() {
+    let input = r#"
+    DECLARE FUNCTION Recurse(N)
+    ON ERROR GOTO ErrTrap
+    PRINT Recurse(1)
+    PRINT "done"
+    END
+
+    ErrTrap:
+        PRINT "error"; ERR
+        RESUME NEXT
+
+    FUNCTION Recurse(N)
+        Recurse = N
+        Recurse = Recurse(N + 1)
+    END FUNCTION
+    "#;
+    let limits = ExecutionLimits {
+        max_call_depth: Some(10),
+        ..Default::default()
+    };
+    // the innermost call keeps its result, the error handler runs once
+    assert_eq!(
+        interpret_with_limits(input, limits).unwrap(),
+        "error 28\n10\ndone"
+    );
+}
+
+#[test]
+fn default_max_call_depth() {
+    let input = r#"
+    DECLARE SUB Recurse(N)
+    Recurse 1
+
+    SUB Recurse(N)
+        Recurse N + 1
+    END SUB
+    "#;
+    let e = interpret_with_limits(input, ExecutionLimits::default()).unwrap_err();
+    assert_eq!(e.err(), &RuntimeError::OutOfStackSpace);
+    assert_eq!(e.stacktrace().len(), DEFAULT_MAX_CALL_DEPTH + 1);
+}
+
+#[test]
+fn file_policy_deny() {
+    let limits = ExecutionLimits {
+        file_policy: FilePolicy::Deny,
+        ..Default::default()
+    };
+    assert_eq!(
+        interpret_err_with_limits(r#"OPEN "LIMITS1.TXT" FOR INPUT AS #1"#, limits.clone()),
+        (RuntimeError::PermissionDenied, 1)
+    );
+    assert_eq!(
+        interpret_err_with_limits(r#"KILL "*.*""#, limits.clone()),
+        (RuntimeError::PermissionDenied, 1)
+    );
+    assert_eq!(
+        interpret_err_with_limits(r#"NAME "A.TXT" AS "B.TXT""#, limits.clone()),
+        (RuntimeError::PermissionDenied, 1)
+    );
+    assert_eq!(
+        interpret_err_with_limits(r#"ENVIRON "A=B""#, limits.clone()),
+        (RuntimeError::PermissionDenied, 1)
+    );
+    assert_eq!(
+        interpret_err_with_limits(r#"PRINT ENVIRON$("PATH")"#, limits),
+        (RuntimeError::PermissionDenied, 1)
+    );
+}
+
+#[test]
+fn file_policy_read_only() {
+    std::fs::write("LIMITS2.TXT", "hello\r\n").unwrap();
+    let limits = ExecutionLimits {
+        file_policy: FilePolicy::ReadOnly,
+        ..Default::default()
+    };
+    let read = r#"
+    OPEN "LIMITS2.TXT" FOR INPUT AS #1
+    LINE INPUT #1, A$
+    CLOSE
+    PRINT A$
+    "#;
+    assert_eq!(
+        interpret_with_limits(read, limits.clone()).unwrap(),
+        "hello"
+    );
+    let write = r#"OPEN "LIMITS2.TXT" FOR APPEND AS #1"#;
+    assert_eq!(
+        interpret_err_with_limits(write, limits),
+        (RuntimeError::PermissionDenied, 1)
+    );
+    std::fs::remove_file("LIMITS2.TXT").unwrap_or(());
+}
+
+#[test]
+fn file_policy_confined() {
+    let root = PathBuf::from("LIMITS3.DIR");
+    std::fs::create_dir_all(&root).unwrap();
+    let limits = ExecutionLimits {
+        file_policy: FilePolicy::Confined(root.clone()),
+        ..Default::default()
+    };
+    let input = r#"
+    OPEN "A.TXT" FOR OUTPUT AS #1
+    PRINT #1, "hello"
+    CLOSE
+    NAME "A.TXT" AS "B.TXT"
+    "#;
+    interpret_with_limits(input, limits.clone()).unwrap();
+    let contents = std::fs::read_to_string(root.join("B.TXT")).unwrap_or_default();
+    std::fs::remove_dir_all(&root).unwrap_or(());
+    assert_eq!(contents, "hello\r\n");
+    assert_eq!(
+        interpret_err_with_limits(r#"KILL "../Cargo.toml""#, limits),
+        (RuntimeError::PermissionDenied, 1)
+    );
+}
+
+#[test]
+fn file_policy_can_be_handled_with_on_error() {
+    let input = r#"
+    ON ERROR GOTO ErrTrap
+    KILL "A.TXT"
+    END
+    ErrTrap:
+        PRINT "Error"; ERR
+    "#;
+    let limits = ExecutionLimits {
+        file_policy: FilePolicy::Deny,
+        ..Default::default()
+    };
+    assert_eq!(interpret_with_limits(input, limits).unwrap(), "Error 70");
+}
@@ -9,10 +9,14 @@ mod function_implementation;
 mod go_sub;
 mod go_to;
 mod if_block;
+mod limits;
 mod loops;
 mod name_resolution;
 mod on_error;
+mod on_event;
+mod resume;
 mod select_case;
+mod snapshot;
 mod sub_call;
 mod sub_implementation;
 mod user_defined_type;
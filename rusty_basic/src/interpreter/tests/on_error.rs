@@ -285,3 +285,23 @@ fn resume_at_end_of_do_loop_while() {
     "#;
     assert_prints!(input, "2", "1.5");
 }
+
+#[test]
+fn resume_next_after_overflow_in_optimized_if_block() {
+    let input = r#"
+    ON ERROR GOTO ErrTrap
+    IF 1 THEN
+        X% = 32767 + 1
+        PRINT "then"
+    ELSE
+        PRINT "else"
+    END IF
+    PRINT "after"
+    END
+
+    ErrTrap:
+        PRINT "oops"
+        RESUME NEXT
+    "#;
+    assert_prints!(input, "oops", "then", "after");
+}
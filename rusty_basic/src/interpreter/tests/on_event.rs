@@ -0,0 +1,221 @@
+use std::time::Duration;
+
+use crossterm::event::{KeyCode, KeyEvent};
+
+use crate::interpreter::interpreter_trait::InterpreterTrait;
+use crate::interpreter::test_utils::*;
+use crate::{RuntimeError, assert_interpreter_err, assert_prints};
+
+fn ticking_stdlib() -> MockStdlib {
+    MockStdlib {
+        clock_step: Duration::from_secs(1),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn on_timer_calls_handler() {
+    let input = r#"
+    ON TIMER(10) GOSUB Tick
+    TIMER ON
+    DO WHILE Ticks < 3
+    LOOP
+    TIMER OFF
+    PRINT Ticks
+    END
+
+    Tick:
+    Ticks = Ticks + 1
+    PRINT "tick"
+    RETURN
+    "#;
+    let mut interpreter = interpret_with_env(input, ticking_stdlib());
+    assert_eq!(
+        interpreter.stdout().output_lines(),
+        vec!["tick", "tick", "tick", "3"]
+    );
+}
+
+#[test]
+fn on_timer_handler_runs_in_global_scope() {
+    let input = r#"
+    DECLARE SUB Wait ()
+    ON TIMER(1) GOSUB Tick
+    TIMER ON
+    Wait
+    TIMER OFF
+    PRINT Ticks > 0
+    END
+
+    Tick:
+    Ticks = Ticks + 1
+    RETURN
+
+    SUB Wait
+        FOR I = 1 TO 3
+        NEXT
+        PRINT Ticks
+    END SUB
+    "#;
+    // the handler increments the global Ticks, not the local one of the SUB
+    let mut interpreter = interpret_with_env(input, ticking_stdlib());
+    assert_eq!(interpreter.stdout().output_lines(), vec!["0", "-1"]);
+}
+
+#[test]
+fn timer_stop_remembers_event() {
+    let input = r#"
+    ON TIMER(2) GOSUB Tick
+    TIMER ON
+    TIMER STOP
+    FOR I = 1 TO 5
+    NEXT
+    PRINT "stopped"; Ticks
+    TIMER ON
+    TIMER OFF
+    PRINT "resumed"; Ticks
+    END
+
+    Tick:
+    Ticks = Ticks + 1
+    RETURN
+    "#;
+    let mut interpreter = interpret_with_env(input, ticking_stdlib());
+    assert_eq!(
+        interpreter.stdout().output_lines(),
+        vec!["stopped 0", "resumed 1"]
+    );
+}
+
+#[test]
+fn timer_without_clock_never_fires() {
+    let input = r#"
+    ON TIMER(1) GOSUB Tick
+    TIMER ON
+    FOR I = 1 TO 5
+    NEXT
+    PRINT "done"
+    END
+
+    Tick:
+    PRINT "tick"
+    RETURN
+    "#;
+    assert_prints!(input, "done");
+}
+
+#[test]
+fn on_timer_interval_out_of_range() {
+    let input = r#"
+    ON TIMER(0) GOSUB Tick
+    END
+    Tick:
+    RETURN
+    "#;
+    assert_interpreter_err!(input, RuntimeError::IllegalFunctionCall, 2, 5);
+}
+
+#[test]
+fn key_number_out_of_range() {
+    assert_interpreter_err!("KEY(26) ON", RuntimeError::IllegalFunctionCall, 1, 1);
+}
+
+#[test]
+fn on_key_calls_handler() {
+    let input = r#"
+    ON KEY(1) GOSUB F1
+    KEY(1) ON
+    DO WHILE Pressed = 0
+    LOOP
+    PRINT "done"
+    END
+
+    F1:
+    Pressed = 1
+    PRINT "F1"
+    RETURN
+    "#;
+    let mut interpreter = interpret_with_env_and_keys(
+        input,
+        MockStdlib::default(),
+        &[KeyEvent::from(KeyCode::F(1))],
+    );
+    assert_eq!(interpreter.stdout().output_lines(), vec!["F1", "done"]);
+}
+
+#[test]
+fn untrapped_key_is_available_to_inkey() {
+    let input = r#"
+    ON KEY(1) GOSUB F1
+    KEY(1) ON
+    DO
+        K$ = INKEY$
+    LOOP UNTIL K$ <> ""
+    PRINT K$
+    END
+
+    F1:
+    PRINT "F1"
+    RETURN
+    "#;
+    let mut interpreter = interpret_with_env_and_keys(
+        input,
+        MockStdlib::default(),
+        &[
+            KeyEvent::from(KeyCode::Char('a')),
+            KeyEvent::from(KeyCode::F(1)),
+        ],
+    );
+    assert_eq!(interpreter.stdout().output_lines(), vec!["F1", "a"]);
+}
+
+#[test]
+fn on_user_defined_key_calls_handler() {
+    let input = r#"
+    KEY 15, CHR$(0) + CHR$(30)
+    ON KEY(15) GOSUB KeyA
+    KEY(15) ON
+    DO WHILE Pressed = 0
+    LOOP
+    PRINT "done"
+    END
+
+    KeyA:
+    Pressed = 1
+    PRINT "A"
+    RETURN
+    "#;
+    let mut interpreter = interpret_with_env_and_keys(
+        input,
+        MockStdlib::default(),
+        &[KeyEvent::from(KeyCode::Char('a'))],
+    );
+    assert_eq!(interpreter.stdout().output_lines(), vec!["A", "done"]);
+}
+
+#[test]
+fn key_trap_turned_off_leaves_key_to_inkey() {
+    let input = r#"
+    KEY 15, CHR$(0) + CHR$(30)
+    ON KEY(1) GOSUB Trapped
+    ON KEY(15) GOSUB Trapped
+    KEY(1) ON
+    KEY(15) ON
+    KEY(15) OFF
+    DO
+        K$ = INKEY$
+    LOOP UNTIL K$ <> ""
+    PRINT K$
+    END
+
+    Trapped:
+    PRINT "trapped"
+    RETURN
+    "#;
+    let mut interpreter = interpret_with_env_and_keys(
+        input,
+        MockStdlib::default(),
+        &[KeyEvent::from(KeyCode::Char('a'))],
+    );
+    assert_eq!(interpreter.stdout().output_lines(), vec!["a"]);
+}
@@ -0,0 +1,198 @@
+use rusty_common::Position;
+use rusty_runtime::{InputQueue, WritePrinter};
+
+use crate::RuntimeError;
+use crate::instruction_generator::test_utils::generate_instructions_str_with_types;
+use crate::interpreter::interpreter_trait::InterpreterTrait;
+use crate::interpreter::lpt1_write::LPT1_DEFAULT_WIDTH;
+use crate::interpreter::main::Interpreter;
+use crate::interpreter::screen::HeadlessScreen;
+use crate::interpreter::test_utils::{MockKeyboard, MockStdlib};
+use crate::interpreter::{InputRequest, RunState};
+
+fn start(
+    input: &str,
+) -> impl InterpreterTrait<TStdIn = InputQueue, TStdOut = WritePrinter<Vec<u8>>> {
+    let (instruction_generator_result, user_defined_types) =
+        generate_instructions_str_with_types(input);
+    let mut interpreter = Interpreter::new(
+        MockStdlib::default(),
+        InputQueue::new(),
+        WritePrinter::new(vec![]),
+        WritePrinter::with_width(vec![], LPT1_DEFAULT_WIDTH),
+        HeadlessScreen {},
+        MockKeyboard::default(),
+        user_defined_types,
+    );
+    interpreter.start(instruction_generator_result);
+    interpreter
+}
+
+#[test]
+fn resume_without_program_is_finished() {
+    let mut interpreter = start("");
+    assert_eq!(interpreter.resume(100).unwrap(), RunState::Finished);
+    assert_eq!(interpreter.resume(100).unwrap(), RunState::Finished);
+}
+
+#[test]
+fn yields_when_budget_is_used_up() {
+    let input = r#"
+    FOR I = 1 TO 3
+        PRINT I
+    NEXT
+    "#;
+    let mut interpreter = start(input);
+    let mut yields = 0;
+    while interpreter.resume(5).unwrap() == RunState::Yielded {
+        yields += 1;
+    }
+    assert!(yields > 3);
+    assert_eq!(interpreter.stdout().output_lines(), ["1", "2", "3"]);
+}
+
+#[test]
+fn zero_budget_does_not_run_anything() {
+    let mut interpreter = start("PRINT \"hello\"");
+    assert_eq!(interpreter.resume(0).unwrap(), RunState::Yielded);
+    assert!(interpreter.stdout().output().is_empty());
+    assert_eq!(interpreter.resume(1_000).unwrap(), RunState::Finished);
+    assert_eq!(interpreter.stdout().output_lines(), ["hello"]);
+}
+
+#[test]
+fn waits_for_input_line() {
+    let input = r#"
+    PRINT "start"
+    INPUT N
+    PRINT N * 2
+    "#;
+    let mut interpreter = start(input);
+    assert_eq!(
+        interpreter.resume(1_000).unwrap(),
+        RunState::WaitingForInput(InputRequest::Line)
+    );
+    assert_eq!(interpreter.stdout().output_lines(), ["start"]);
+    // still waiting, as the line is not complete
+    interpreter.stdin().push_str("2");
+    assert_eq!(
+        interpreter.resume(1_000).unwrap(),
+        RunState::WaitingForInput(InputRequest::Line)
+    );
+    interpreter.stdin().push_line("1");
+    assert_eq!(interpreter.resume(1_000).unwrap(), RunState::Finished);
+    assert_eq!(interpreter.stdout().output_lines(), ["start", "42"]);
+}
+
+#[test]
+fn input_reads_several_variables_from_one_line() {
+    let input = r#"
+    INPUT A, B
+    PRINT A + B
+    "#;
+    let mut interpreter = start(input);
+    assert_eq!(
+        interpreter.resume(1_000).unwrap(),
+        RunState::WaitingForInput(InputRequest::Line)
+    );
+    interpreter.stdin().push_line("1, 2");
+    assert_eq!(interpreter.resume(1_000).unwrap(), RunState::Finished);
+    assert_eq!(interpreter.stdout().output_lines(), ["3"]);
+}
+
+#[test]
+fn waits_for_line_input() {
+    let input = r#"
+    LINE INPUT A$
+    PRINT "Hello, " + A$
+    "#;
+    let mut interpreter = start(input);
+    assert_eq!(
+        interpreter.resume(1_000).unwrap(),
+        RunState::WaitingForInput(InputRequest::Line)
+    );
+    interpreter.stdin().push_line("world");
+    assert_eq!(interpreter.resume(1_000).unwrap(), RunState::Finished);
+    assert_eq!(interpreter.stdout().output_lines(), ["Hello, world"]);
+}
+
+#[test]
+fn waits_for_key() {
+    let input = r#"
+    K$ = INKEY$
+    PRINT "Key: " + K$
+    "#;
+    let mut interpreter = start(input);
+    assert_eq!(
+        interpreter.resume(1_000).unwrap(),
+        RunState::WaitingForInput(InputRequest::Key)
+    );
+    interpreter.keyboard_mut().push("a".to_owned());
+    assert_eq!(interpreter.resume(1_000).unwrap(), RunState::Finished);
+    assert_eq!(interpreter.stdout().output_lines(), ["Key: a"]);
+}
+
+#[test]
+fn inkey_returns_empty_string_when_resumed_without_key() {
+    let input = r#"
+    DO
+        K$ = INKEY$
+        I = I + 1
+    LOOP UNTIL K$ <> ""
+    PRINT I
+    "#;
+    let mut interpreter = start(input);
+    for _ in 0..3 {
+        assert_eq!(
+            interpreter.resume(1_000).unwrap(),
+            RunState::WaitingForInput(InputRequest::Key)
+        );
+    }
+    interpreter.keyboard_mut().push("x".to_owned());
+    assert_eq!(interpreter.resume(1_000).unwrap(), RunState::Finished);
+    assert_eq!(interpreter.stdout().output_lines(), ["3"]);
+}
+
+#[test]
+fn state_survives_between_resumes() {
+    let input = r#"
+    SUB Count(N)
+        FOR I = 1 TO N
+            Total = Total + I
+        NEXT
+        PRINT Total
+    END SUB
+
+    Count 10
+    "#;
+    let mut interpreter = start(input);
+    while interpreter.resume(1).unwrap() == RunState::Yielded {}
+    assert_eq!(interpreter.stdout().output_lines(), ["55"]);
+}
+
+#[test]
+fn error_handler_works_across_resumes() {
+    let input = r#"
+    ON ERROR GOTO ErrTrap
+    X = 1 / 0
+    PRINT "resumed"
+    END
+
+    ErrTrap:
+        PRINT "error"; ERR
+        RESUME NEXT
+    "#;
+    let mut interpreter = start(input);
+    while interpreter.resume(2).unwrap() == RunState::Yielded {}
+    assert_eq!(interpreter.stdout().output_lines(), ["error 11", "resumed"]);
+}
+
+#[test]
+fn unhandled_error_ends_the_program() {
+    let mut interpreter = start("PRINT \"before\"\nX = 1 / 0\nPRINT \"after\"");
+    let e = interpreter.resume(1_000).unwrap_err();
+    assert_eq!(*e.err(), RuntimeError::DivisionByZero);
+    assert_eq!(e.stacktrace(), &[Position::new(2, 7)]);
+    assert_eq!(interpreter.resume(1_000).unwrap(), RunState::Finished);
+    assert_eq!(interpreter.stdout().output_lines(), ["before"]);
+}
@@ -0,0 +1,264 @@
+use rusty_runtime::{InputQueue, WritePrinter};
+
+use crate::RuntimeError;
+use crate::bytecode::CompiledProgram;
+use crate::instruction_generator::InstructionGeneratorResult;
+use crate::instruction_generator::test_utils::generate_instructions_str_with_types;
+use crate::interpreter::interpreter_trait::InterpreterTrait;
+use crate::interpreter::lpt1_write::LPT1_DEFAULT_WIDTH;
+use crate::interpreter::main::Interpreter;
+use crate::interpreter::screen::HeadlessScreen;
+use crate::interpreter::test_utils::{MockKeyboard, MockStdlib};
+use crate::interpreter::{ExecutionLimits, FilePolicy, RunState, SnapshotError};
+
+type TestInterpreter =
+    Interpreter<MockStdlib, InputQueue, WritePrinter<Vec<u8>>, WritePrinter<Vec<u8>>>;
+
+/// Compiles the given program once, so that every interpreter
+/// of a test can get its own copy of the instructions quickly.
+fn compile(input: &str) -> Vec<u8> {
+    let (instruction_generator_result, user_defined_types) =
+        generate_instructions_str_with_types(input);
+    CompiledProgram {
+        file_name: "TEST.BAS".to_owned(),
+        instruction_generator_result,
+        user_defined_types,
+    }
+    .to_bytes()
+}
+
+fn new_interpreter(program: &[u8]) -> (TestInterpreter, InstructionGeneratorResult) {
+    let CompiledProgram {
+        instruction_generator_result,
+        user_defined_types,
+        ..
+    } = CompiledProgram::from_bytes(program).unwrap();
+    let interpreter = Interpreter::new(
+        MockStdlib::default(),
+        InputQueue::new(),
+        WritePrinter::new(vec![]),
+        WritePrinter::with_width(vec![], LPT1_DEFAULT_WIDTH),
+        HeadlessScreen {},
+        MockKeyboard::default(),
+        user_defined_types,
+    );
+    (interpreter, instruction_generator_result)
+}
+
+fn start(program: &[u8]) -> TestInterpreter {
+    let (mut interpreter, instruction_generator_result) = new_interpreter(program);
+    interpreter.start(instruction_generator_result);
+    interpreter
+}
+
+fn restore(program: &[u8], snapshot: &[u8]) -> Result<TestInterpreter, SnapshotError> {
+    let (mut interpreter, instruction_generator_result) = new_interpreter(program);
+    interpreter
+        .restore(instruction_generator_result, snapshot)
+        .map(|_| interpreter)
+}
+
+fn finish(interpreter: &mut TestInterpreter) -> String {
+    assert_eq!(interpreter.resume(100_000).unwrap(), RunState::Finished);
+    interpreter.stdout().output_exact()
+}
+
+/// Takes a snapshot before every instruction of the given program
+/// and checks that a new interpreter continues from it
+/// as if the program had not been interrupted.
+fn assert_restores_everywhere(input: &str, expected_output: &str) {
+    let expected_output = expected_output.replace('\n', "\r\n");
+    let program = compile(input);
+    let mut interpreter = start(&program);
+    let mut steps = 0;
+    loop {
+        let snapshot = interpreter.snapshot().unwrap();
+        let before = interpreter.stdout().output_exact();
+        let after = finish(&mut restore(&program, &snapshot).unwrap());
+        assert_eq!(
+            before + &after,
+            expected_output,
+            "restoring after {} instructions",
+            steps
+        );
+        steps += 1;
+        if interpreter.resume(1).unwrap() == RunState::Finished {
+            break;
+        }
+    }
+    assert_eq!(interpreter.stdout().output_exact(), expected_output);
+    assert!(steps > 10);
+}
+
+#[test]
+fn snapshot_without_program() {
+    let mut interpreter = start(&compile(""));
+    assert_eq!(interpreter.resume(10).unwrap(), RunState::Finished);
+    assert_eq!(interpreter.snapshot(), Err(SnapshotError::NotRunning));
+}
+
+#[test]
+fn restore_subprograms() {
+    let input = r#"
+    DECLARE FUNCTION Fib(N)
+    DECLARE SUB Count()
+
+    PRINT Fib(6) + 1
+    Count
+    Count
+    Greet "world", X$
+    PRINT X$
+
+    FUNCTION Fib(N)
+        IF N < 2 THEN
+            Fib = N
+        ELSE
+            Fib = Fib(N - 1) + Fib(N - 2)
+        END IF
+    END FUNCTION
+
+    SUB Count STATIC
+        C = C + 1
+        PRINT "count"; C
+    END SUB
+
+    SUB Greet(Name$, Result$)
+        Result$ = "Hello, " + Name$
+    END SUB
+    "#;
+    assert_restores_everywhere(input, " 9 \ncount 1 \ncount 2 \nHello, world\n");
+}
+
+#[test]
+fn restore_arrays_and_user_defined_types() {
+    let input = r#"
+    TYPE Card
+        Suit AS STRING * 5
+        Value AS INTEGER
+    END TYPE
+
+    DIM A(1 TO 3, 2) AS INTEGER
+    DIM Cards(2) AS Card
+    FOR I = 1 TO 3
+        A(I, 1) = I * 10
+    NEXT
+    Cards(1).Suit = "Heart"
+    Cards(1).Value = A(2, 1)
+    PRINT Cards(1).Suit; Cards(1).Value; A(3, 1)
+    "#;
+    assert_restores_everywhere(input, "Heart 20  30 \n");
+}
+
+#[test]
+fn restore_go_sub_data_and_error_handler() {
+    let input = r#"
+    ON ERROR GOTO ErrTrap
+    FOR I = 1 TO 2
+        READ N$
+        GOSUB Show
+    NEXT
+    X = 1 / 0
+    PRINT "done"
+    END
+
+    Show:
+        PRINT "hi "; N$
+        RETURN
+
+    ErrTrap:
+        PRINT "error"; ERR
+        RESUME NEXT
+
+    DATA "a", "b"
+    "#;
+    assert_restores_everywhere(input, "hi a\nhi b\nerror 11 \ndone\n");
+}
+
+#[test]
+fn restore_print_using() {
+    let input = r###"
+    PRINT USING "##.# and ##.#"; 1.5; 2.5
+    PRINT USING "##.# and ##.#"; 3; 4
+    "###;
+    assert_restores_everywhere(input, " 1.5 and  2.5\n 3.0 and  4.0\n");
+}
+
+#[test]
+fn restore_open_files() {
+    let input = r#"
+    OPEN "SNAPSHOT1.TXT" FOR OUTPUT AS #1
+    PRINT #1, "one"
+    PRINT #1, "two";
+    PRINT #1, "three"
+    CLOSE #1
+    OPEN "SNAPSHOT1.TXT" FOR INPUT AS #1
+    LINE INPUT #1, A$
+    LINE INPUT #1, B$
+    CLOSE
+    PRINT A$; "/"; B$
+    "#;
+    // every restored run writes the file again, starting at the position of the snapshot
+    assert_restores_everywhere(input, "one/twothree\n");
+    assert_eq!(
+        std::fs::read_to_string("SNAPSHOT1.TXT").unwrap(),
+        "one\r\ntwothree\r\n"
+    );
+    std::fs::remove_file("SNAPSHOT1.TXT").unwrap();
+}
+
+#[test]
+fn restore_with_different_program() {
+    let mut interpreter = start(&compile("PRINT 1\nPRINT 2"));
+    interpreter.resume(1).unwrap();
+    let snapshot = interpreter.snapshot().unwrap();
+    assert_eq!(
+        restore(&compile("PRINT 1\nPRINT 3"), &snapshot).err(),
+        Some(SnapshotError::ProgramMismatch)
+    );
+}
+
+#[test]
+fn restore_invalid_snapshot() {
+    let program = compile("PRINT 1\nPRINT 2");
+    let mut interpreter = start(&program);
+    interpreter.resume(1).unwrap();
+    let snapshot = interpreter.snapshot().unwrap();
+    assert_eq!(
+        restore(&program, b"hello").err(),
+        Some(SnapshotError::NotSnapshot)
+    );
+    let mut corrupted = snapshot.clone();
+    *corrupted.last_mut().unwrap() ^= 1;
+    assert_eq!(
+        restore(&program, &corrupted).err(),
+        Some(SnapshotError::Corrupted("checksum mismatch".to_owned()))
+    );
+    let truncated = &snapshot[..snapshot.len() - 1];
+    assert!(matches!(
+        restore(&program, truncated).err(),
+        Some(SnapshotError::Corrupted(_))
+    ));
+}
+
+#[test]
+fn restore_with_file_policy() {
+    let input = r#"
+    OPEN "SNAPSHOT2.TXT" FOR OUTPUT AS #1
+    PRINT #1, "hello"
+    "#;
+    let program = compile(input);
+    let mut interpreter = start(&program);
+    interpreter.resume(20).unwrap();
+    let snapshot = interpreter.snapshot().unwrap();
+    let (mut other, instruction_generator_result) = new_interpreter(&program);
+    other.set_limits(ExecutionLimits {
+        file_policy: FilePolicy::ReadOnly,
+        ..Default::default()
+    });
+    assert_eq!(
+        other.restore(instruction_generator_result, &snapshot),
+        Err(SnapshotError::File(RuntimeError::PermissionDenied))
+    );
+    finish(&mut interpreter);
+    std::fs::remove_file("SNAPSHOT2.TXT").unwrap();
+}
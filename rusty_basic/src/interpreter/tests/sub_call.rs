@@ -308,6 +308,26 @@ mod static_sub {
         assert_prints!(program, "1", "3");
     }
 
+    #[test]
+    fn static_sub_first_called_from_regular_sub() {
+        let program = r#"
+        Outer
+        Outer
+        Hello
+
+        SUB Outer
+            A = 42
+            Hello
+        END SUB
+
+        SUB Hello STATIC
+            A = A + 1
+            PRINT A
+        END SUB
+        "#;
+        assert_prints!(program, "1", "2", "3");
+    }
+
     #[test]
     fn fixed_length_string_append() {
         let program = r#"
@@ -1,21 +1,32 @@
-use rusty_parser::{
-    AsBareName, BareName, DimType, DimVar, Name, ParamType, Parameter, TypeQualifier,
-};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use rusty_parser::{AsBareName, BareName, Name};
+use rusty_runtime::{QByteSize, allocate_built_in};
 use rusty_variant::{V_FALSE, Variant};
 
+use crate::bytecode::BytecodeError;
+use crate::bytecode::decoder::Decoder;
+use crate::bytecode::encoder::Encoder;
 use crate::instruction_generator::Path;
 use crate::interpreter::arguments::{ArgumentInfo, Arguments};
-use crate::interpreter::byte_size::QByteSize;
-use crate::interpreter::handlers::allocation::allocate_built_in;
-use crate::interpreter::indexed_map::IndexedMap;
 
-#[derive(Debug)]
+/// The variables of a memory block, indexed by the slot that the instruction
+/// generator assigned to them.
+///
+/// A slot is empty until its variable is defined.
+#[derive(Debug, Default)]
 pub struct Variables {
-    map: IndexedMap<Name, RuntimeVariableInfo>,
+    slots: Vec<Option<RuntimeVariableInfo>>,
 }
 
 #[derive(Debug)]
 struct RuntimeVariableInfo {
+    /// The name of the variable. Anonymous arguments have a dummy name
+    /// that starts with a digit. Parameters share their name with the
+    /// instruction that pushed their argument.
+    name: Rc<Name>,
+
     /// Holds the value of the variable.
     value: Variant,
 
@@ -25,205 +36,247 @@ struct RuntimeVariableInfo {
 }
 
 impl RuntimeVariableInfo {
-    pub fn new(value: Variant, arg_path: Option<Path>) -> Self {
-        Self { value, arg_path }
+    pub fn new(name: Rc<Name>, value: Variant, arg_path: Option<Path>) -> Self {
+        Self {
+            name,
+            value,
+            arg_path,
+        }
     }
 }
 
 impl Variables {
     pub fn new() -> Self {
-        Self {
-            map: IndexedMap::new(),
-        }
-    }
-
-    pub fn insert_built_in(
-        &mut self,
-        bare_name: BareName,
-        qualifier: TypeQualifier,
-        value: Variant,
-    ) {
-        self.insert(Name::qualified(bare_name, qualifier), value);
-    }
-
-    pub fn insert_user_defined(&mut self, bare_name: BareName, value: Variant) {
-        self.insert(Name::bare(bare_name), value);
-    }
-
-    fn insert_unnamed(&mut self, value: Variant, arg_path: Option<Path>) {
-        let dummy_name = format!("{}", self.map.len());
-        let name = Name::bare(BareName::new(dummy_name));
-        self.map
-            .insert(name, RuntimeVariableInfo::new(value, arg_path));
+        Self::default()
     }
 
-    pub fn insert_param(&mut self, param_name: Parameter, value: Variant) {
-        self.insert(Self::param_to_name(param_name), value);
+    /// Defines (or re-defines) the variable of the given slot.
+    pub fn insert(&mut self, slot: usize, name: Name, value: Variant) {
+        self.insert_info(slot, RuntimeVariableInfo::new(Rc::new(name), value, None));
     }
 
-    fn param_to_name(param_name: Parameter) -> Name {
-        let (bare_name, param_type) = param_name.into();
-        match param_type {
-            ParamType::Bare => panic!("Unresolved param {:?}", bare_name),
-            ParamType::BuiltIn(q, _) => Name::qualified(bare_name, q),
-            ParamType::UserDefined(_) => Name::bare(bare_name),
-            ParamType::Array(boxed_param_type) => {
-                let dummy_param = Parameter::new(bare_name, *boxed_param_type);
-                Self::param_to_name(dummy_param)
-            }
+    fn insert_info(&mut self, slot: usize, info: RuntimeVariableInfo) {
+        if slot >= self.slots.len() {
+            self.slots.resize_with(slot + 1, || None);
         }
+        self.slots[slot] = Some(info);
     }
 
-    pub fn insert(&mut self, name: Name, value: Variant) {
-        self.map.insert(name, RuntimeVariableInfo::new(value, None));
+    /// Defines the variable in the slot after the last one.
+    pub fn push(&mut self, name: Name, value: Variant) {
+        self.insert(self.slots.len(), name, value);
     }
 
-    pub fn insert_dim(&mut self, dim_name: DimVar, value: Variant) {
-        let (bare_name, dim_type) = dim_name.into();
-        self.insert_dim_internal(bare_name, dim_type, value);
+    /// Assigns the given value to the variable of the given slot,
+    /// defining it with the given name if it is not defined.
+    pub fn set(&mut self, slot: usize, name: &Name, value: Variant) {
+        match self.get_mut(slot) {
+            Some(existing) => *existing = value,
+            None => self.insert(slot, name.clone(), value),
+        }
     }
 
-    fn insert_dim_internal(&mut self, bare_name: BareName, dim_type: DimType, value: Variant) {
-        match dim_type {
-            DimType::BuiltIn(qualifier, _) => {
-                self.insert_built_in(bare_name, qualifier, value);
-            }
-            DimType::FixedLengthString(_, _) => {
-                self.insert_built_in(bare_name, TypeQualifier::DollarString, value);
-            }
-            DimType::UserDefined(_) => {
-                self.insert_user_defined(bare_name, value);
-            }
-            DimType::Array(_, box_element_type) => {
-                self.insert_dim_internal(bare_name, *box_element_type, value);
-            }
-            DimType::Bare => panic!("Unresolved type"),
+    /// Assigns the given value to the variable of the given name,
+    /// defining it in the slot after the last one if it is not defined.
+    pub fn set_by_name(&mut self, name: Name, value: Variant) {
+        match self.find(&name) {
+            Some(slot) => self.set(slot, &name, value),
+            None => self.push(name, value),
         }
     }
 
-    pub fn get_or_create(&mut self, name: Name) -> &mut Variant {
-        &mut self
-            .map
-            .get_or_create(name, |n| {
-                RuntimeVariableInfo::new(Self::default_value_for_name(n), None)
+    /// Gets the variable of the given slot, defining it with the default
+    /// value of its type if it is not defined (e.g. because its DIM statement
+    /// was skipped with GOTO).
+    pub fn get_or_insert_default(&mut self, slot: usize, name: &Name) -> &mut Variant {
+        if slot >= self.slots.len() {
+            self.slots.resize_with(slot + 1, || None);
+        }
+        &mut self.slots[slot]
+            .get_or_insert_with(|| {
+                RuntimeVariableInfo::new(Rc::new(name.clone()), default_value_for_name(name), None)
             })
             .value
     }
 
-    // This is needed only when we're setting the default value for a function
-    // that hasn't set a return value. As functions can only return built-in types,
-    // the value for unqualified names is not important.
-    fn default_value_for_name(name: &Name) -> Variant {
-        if let Some(q) = name.qualifier() {
-            allocate_built_in(q)
-        } else {
-            V_FALSE
-        }
+    pub fn is_defined(&self, slot: usize) -> bool {
+        self.get(slot).is_some()
     }
 
-    /// Gets the number of variables in this object.
+    /// Gets the number of slots in this object.
     pub fn len(&self) -> usize {
-        self.map.len()
+        self.slots.len()
+    }
+
+    fn infos(&self) -> impl Iterator<Item = &RuntimeVariableInfo> {
+        self.slots.iter().flatten()
     }
 
     /// Gets an iterator that returns the variables in this object.
     pub fn iter(&self) -> impl Iterator<Item = &Variant> {
-        self.map.values().map(|r| &r.value)
+        self.infos().map(|r| &r.value)
     }
 
-    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Variant> {
-        self.map.values_mut().map(|r| &mut r.value)
+    /// Gets an iterator that returns the named variables in this object,
+    /// skipping the anonymous arguments.
+    pub fn iter_named(&self) -> impl Iterator<Item = (&Name, &Variant)> {
+        self.infos()
+            .filter(|r| {
+                !r.name
+                    .as_bare_name()
+                    .starts_with(|ch: char| ch.is_ascii_digit())
+            })
+            .map(|r| (r.name.as_ref(), &r.value))
     }
 
-    pub fn get_built_in(&self, bare_name: &BareName, qualifier: TypeQualifier) -> Option<&Variant> {
-        // TODO make a structure that allows to lookup by BareName and QualifiedName without the need to clone
-        let temp = Name::qualified(bare_name.clone(), qualifier);
-        self.get_by_name(&temp)
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Variant> {
+        self.slots.iter_mut().flatten().map(|r| &mut r.value)
     }
 
-    pub fn get_user_defined(&self, bare_name: &BareName) -> Option<&Variant> {
-        // TODO make a structure that allows to lookup by BareName and QualifiedName without the need to clone
-        let temp = Name::bare(bare_name.clone());
-        self.get_by_name(&temp)
+    fn find(&self, name: &Name) -> Option<usize> {
+        self.slots
+            .iter()
+            .position(|r| r.as_ref().is_some_and(|r| *r.name == *name))
     }
 
     pub fn get_by_name(&self, name: &Name) -> Option<&Variant> {
-        self.map.get(name).map(|r| &r.value)
+        self.find(name).and_then(|slot| self.get(slot))
     }
 
-    pub fn get(&self, index: usize) -> Option<&Variant> {
-        self.map.get_by_index(index).map(|r| &r.value)
+    pub fn get(&self, slot: usize) -> Option<&Variant> {
+        match self.slots.get(slot) {
+            Some(Some(r)) => Some(&r.value),
+            _ => None,
+        }
     }
 
-    pub fn get_mut(&mut self, index: usize) -> Option<&mut Variant> {
-        self.map.get_by_index_mut(index).map(|r| &mut r.value)
+    pub fn get_mut(&mut self, slot: usize) -> Option<&mut Variant> {
+        match self.slots.get_mut(slot) {
+            Some(Some(r)) => Some(&mut r.value),
+            _ => None,
+        }
     }
 
+    /// Defines the arguments of a call in the first slots,
+    /// which are the slots of the parameters.
     pub fn apply_arguments(&mut self, arguments: Arguments) {
-        for ArgumentInfo {
-            value,
-            param_name,
-            arg_path,
-        } in arguments.into_iter()
+        for (
+            slot,
+            ArgumentInfo {
+                value,
+                param_name,
+                arg_path,
+            },
+        ) in arguments.into_iter().enumerate()
         {
-            match param_name {
-                Some(param_name) => self.insert_param(param_name, value),
-                None => self.insert_unnamed(value, arg_path),
-            }
+            let name = match param_name {
+                Some(param_name) => param_name,
+                None => Rc::new(Name::bare(BareName::new(format!("{}", slot)))),
+            };
+            self.insert_info(slot, RuntimeVariableInfo::new(name, value, arg_path));
         }
     }
 
-    pub fn get_by_dim_name(&self, dim_name: &DimVar) -> Option<&Variant> {
-        self.get_by_dim_name_internal(dim_name.as_bare_name(), dim_name.var_type())
-    }
-
-    fn get_by_dim_name_internal(
-        &self,
-        bare_name: &BareName,
-        dim_type: &DimType,
-    ) -> Option<&Variant> {
-        match dim_type {
-            DimType::BuiltIn(q, _) => self.get_built_in(bare_name, *q),
-            DimType::FixedLengthString(_, _) => {
-                self.get_built_in(bare_name, TypeQualifier::DollarString)
-            }
-            DimType::UserDefined(_) => self.get_user_defined(bare_name),
-            DimType::Array(_, box_dim_type) => {
-                self.get_by_dim_name_internal(bare_name, box_dim_type.as_ref())
-            }
-            DimType::Bare => panic!("Unresolved dim"),
+    pub fn get_arg_path(&self, slot: usize) -> Option<&Path> {
+        match self.slots.get(slot) {
+            Some(Some(r)) => r.arg_path.as_ref(),
+            _ => None,
         }
     }
 
-    pub fn get_arg_path(&self, index: usize) -> Option<&Path> {
-        self.map
-            .get_by_index(index)
-            .and_then(|r| r.arg_path.as_ref())
-    }
-
-    pub fn calculate_var_ptr(&self, name: &Name) -> usize {
-        debug_assert!(self.map.get(name).is_some());
-        self.map
-            .entries()
-            .take_while(|(k, _)| k != name)
-            .map(|(_, v)| &v.value)
-            .map(Variant::byte_size)
+    pub fn calculate_var_ptr(&self, slot: usize) -> usize {
+        debug_assert!(self.is_defined(slot));
+        self.slots[..slot]
+            .iter()
+            .flatten()
+            .map(|r| r.value.byte_size())
             .sum()
     }
 
-    pub fn array_names(&self) -> impl Iterator<Item = &Name> {
-        self.map
-            .entries()
-            .filter(move |(_, value)| {
+    /// Gets the slots of the variables that are arrays.
+    pub fn array_slots(&self) -> impl Iterator<Item = usize> + '_ {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| {
                 matches!(
-                    value,
-                    RuntimeVariableInfo {
+                    r,
+                    Some(RuntimeVariableInfo {
                         value: Variant::VArray(_),
                         ..
-                    }
+                    })
                 )
             })
-            .map(|(key, _)| key)
+            .map(|(slot, _)| slot)
+    }
+
+    /// Moves the variables into the slots of the given layout, by name.
+    ///
+    /// This is needed when a new program runs on existing variables
+    /// (e.g. in the REPL), as its slots might be assigned differently.
+    /// Variables that are not part of the layout are kept after it.
+    pub fn arrange(&mut self, names: &[Name]) {
+        let is_arranged = self
+            .slots
+            .iter()
+            .zip(names)
+            .all(|(r, name)| r.as_ref().is_none_or(|r| *r.name == *name));
+        if is_arranged && self.slots.len() <= names.len() {
+            return;
+        }
+        let indices: HashMap<&Name, usize> = names
+            .iter()
+            .enumerate()
+            .map(|(slot, name)| (name, slot))
+            .collect();
+        let old_slots = std::mem::take(&mut self.slots);
+        let mut unknown = vec![];
+        for r in old_slots.into_iter().flatten() {
+            match indices.get(r.name.as_ref()) {
+                Some(slot) => self.insert_info(*slot, r),
+                None => unknown.push(r),
+            }
+        }
+        self.slots.resize_with(names.len(), || None);
+        self.slots.extend(unknown.into_iter().map(Some));
+    }
+
+    /// Writes the variables into a snapshot.
+    pub fn encode(&self, encoder: &mut Encoder) {
+        encoder.usize(self.slots.len());
+        for slot in &self.slots {
+            encoder.option(slot.as_ref(), |encoder, r| {
+                encoder.name(&r.name);
+                encoder.variant(&r.value);
+                encoder.option(r.arg_path.as_ref(), Encoder::path);
+            });
+        }
+    }
+
+    /// Reads the variables from a snapshot.
+    pub fn decode(decoder: &mut Decoder) -> Result<Self, BytecodeError> {
+        let len = decoder.len()?;
+        let slots = (0..len)
+            .map(|_| {
+                decoder.option(|decoder| {
+                    let name = decoder.name()?;
+                    let value = decoder.variant()?;
+                    let arg_path = decoder.option(Decoder::path)?;
+                    Ok(RuntimeVariableInfo::new(Rc::new(name), value, arg_path))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { slots })
+    }
+}
+
+// Only the names of user defined types are unqualified. Their value can't be
+// allocated without their DIM statement, so they get a placeholder instead.
+fn default_value_for_name(name: &Name) -> Variant {
+    if let Some(q) = name.qualifier() {
+        allocate_built_in(q)
+    } else {
+        V_FALSE
     }
 }
 
@@ -240,3 +293,32 @@ impl QByteSize for Variables {
         self.iter().map(Variant::byte_size).sum()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arrange_moves_variables_by_name() {
+        let mut variables = Variables::new();
+        variables.insert(0, "A!".into(), Variant::VSingle(1.0));
+        variables.insert(1, "B!".into(), Variant::VSingle(2.0));
+        variables.arrange(&["C!".into(), "B!".into(), "A!".into()]);
+        assert!(!variables.is_defined(0));
+        assert_eq!(variables.get(1), Some(&Variant::VSingle(2.0)));
+        assert_eq!(variables.get(2), Some(&Variant::VSingle(1.0)));
+    }
+
+    #[test]
+    fn test_arrange_keeps_unknown_variables_after_the_layout() {
+        let mut variables = Variables::new();
+        variables.insert(0, "A!".into(), Variant::VSingle(1.0));
+        variables.arrange(&["B!".into()]);
+        assert!(!variables.is_defined(0));
+        assert_eq!(variables.get(1), Some(&Variant::VSingle(1.0)));
+        assert_eq!(
+            variables.get_by_name(&"A!".into()),
+            Some(&Variant::VSingle(1.0))
+        );
+    }
+}
@@ -1,5 +1,11 @@
+pub mod bytecode;
+pub mod diagnostics;
+pub mod engine;
 pub mod instruction_generator;
 pub mod interpreter;
+pub mod repl;
+pub mod test_runner;
+pub mod transpiler;
 pub use self::interpreter::error::*;
 pub mod error_envelope;
 pub use self::error_envelope::*;
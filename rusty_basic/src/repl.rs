@@ -0,0 +1,565 @@
+//! An interactive session, similar to the Immediate window of QBasic.
+//!
+//! Every input (a single line or a multi-line block) is parsed, linted and
+//! run on the same interpreter, so variables, SUBs, FUNCTIONs and open files
+//! survive between inputs. The session also keeps a program buffer,
+//! which can be listed, saved, loaded and run.
+
+use std::fmt::Display;
+
+use rusty_common::Position;
+use rusty_linter::core::{LintErrorPos, lint};
+use rusty_parser::{GlobalStatement, ParseErrorPos, Program, Statement, parse_main_str};
+
+use crate::RuntimeErrorPos;
+use crate::diagnostics::Diagnostics;
+use crate::instruction_generator::{generate_instructions, unwrap_linter_context};
+use crate::interpreter::InterpreterTrait;
+
+/// The name under which the inputs of the session appear in error messages.
+const SESSION_NAME: &str = "immediate";
+
+/// An interactive session.
+pub struct Session<I, F>
+where
+    I: InterpreterTrait,
+    F: Fn() -> I,
+{
+    /// Creates a fresh interpreter for `RUN` and `NEW`.
+    new_interpreter: F,
+    interpreter: I,
+    /// The global statements of previous inputs which are needed in order
+    /// to lint the next input: SUB/FUNCTION implementations and declarations,
+    /// user defined types, `DEFINT` etc., constants and variables.
+    declarations: Program,
+    /// The global statements of all inputs, in order to find the SUB or
+    /// FUNCTION of a source position when showing the call stack of an error.
+    history: Program,
+    /// All the lines entered so far. Every input is parsed as if it followed
+    /// the previous ones, so that source positions are unique in the session.
+    transcript: Vec<String>,
+    /// The program buffer, consisting of the inputs that ran successfully
+    /// or of the program that was loaded.
+    program: String,
+    /// The file of the last `LOAD` or `SAVE` command.
+    file_name: Option<String>,
+}
+
+/// The outcome of evaluating an input.
+#[derive(Debug, PartialEq)]
+pub enum Response {
+    /// The input was handled.
+    Done,
+    /// The input is an unfinished block (e.g. `FOR` without `NEXT`).
+    /// The caller should read more lines and evaluate them together.
+    Incomplete,
+    /// The program buffer, as requested by `LIST`.
+    Listing(String),
+    /// The user asked to leave the session with `SYSTEM`.
+    Exit,
+}
+
+#[derive(Debug)]
+pub enum SessionError {
+    Io(std::io::Error),
+    Parse(ParseErrorPos),
+    Lint(LintErrorPos),
+    Runtime(RuntimeErrorPos),
+    /// `SAVE` was used without a file name before any `LOAD` or `SAVE`.
+    FileNameRequired,
+}
+
+impl Display for SessionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Parse(e) => write!(f, "{}", e.element),
+            Self::Lint(e) => write!(f, "{}", e.element),
+            Self::Runtime(e) => write!(f, "{}", e.err()),
+            Self::FileNameRequired => write!(f, "File name required"),
+        }
+    }
+}
+
+/// The commands that manage the program buffer and the session,
+/// which are not statements of the language.
+#[derive(Debug, PartialEq)]
+enum Command {
+    List,
+    Load(String),
+    New,
+    Run,
+    Save(Option<String>),
+    System,
+}
+
+impl Command {
+    fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        let (name, argument) = match input.split_once(char::is_whitespace) {
+            // e.g. `SAVE = 1` is an assignment, not a command
+            Some((name, argument)) => (name, Some(file_name(argument.trim())?)),
+            None => (input, None),
+        };
+        match (name.to_ascii_uppercase().as_str(), argument) {
+            ("LIST", None) => Some(Self::List),
+            ("LOAD", Some(file_name)) => Some(Self::Load(file_name)),
+            ("NEW", None) => Some(Self::New),
+            ("RUN", None) => Some(Self::Run),
+            ("SAVE", file_name) => Some(Self::Save(file_name)),
+            ("SYSTEM", None) => Some(Self::System),
+            _ => None,
+        }
+    }
+}
+
+/// Gets the file name argument of a command, which is either quoted
+/// or a single word that cannot be part of a statement.
+fn file_name(s: &str) -> Option<String> {
+    match s.strip_prefix('"') {
+        Some(s) => Some(s.strip_suffix('"').unwrap_or(s).to_owned()),
+        None if s.contains(|ch: char| ch.is_whitespace() || "=(:'".contains(ch)) => None,
+        None => Some(s.to_owned()),
+    }
+}
+
+impl<I, F> Session<I, F>
+where
+    I: InterpreterTrait,
+    F: Fn() -> I,
+{
+    pub fn new(new_interpreter: F) -> Self {
+        let interpreter = new_interpreter();
+        Self {
+            new_interpreter,
+            interpreter,
+            declarations: vec![],
+            history: vec![],
+            transcript: vec![],
+            program: String::new(),
+            file_name: None,
+        }
+    }
+
+    pub fn interpreter(&mut self) -> &mut I {
+        &mut self.interpreter
+    }
+
+    /// Gets the program buffer.
+    pub fn program(&self) -> &str {
+        &self.program
+    }
+
+    /// Evaluates a command or the statements of the given input.
+    ///
+    /// If the input ends before a block is closed, it is not evaluated
+    /// and [Response::Incomplete] is returned instead.
+    pub fn eval(&mut self, input: &str) -> Result<Response, SessionError> {
+        self.eval_input(input, true)
+    }
+
+    /// Evaluates the given input, reporting unfinished blocks as errors.
+    pub fn eval_complete(&mut self, input: &str) -> Result<Response, SessionError> {
+        self.eval_input(input, false)
+    }
+
+    /// Renders the given error, showing the offending line of the session.
+    pub fn render_error(&self, e: &SessionError) -> String {
+        let mut diagnostics = Diagnostics::new(SESSION_NAME, &self.transcript.join("\n"));
+        diagnostics.register_subprograms(&self.history);
+        match e {
+            SessionError::Parse(e) => diagnostics.render_parse_error(e),
+            SessionError::Lint(e) => diagnostics.render_lint_error(e),
+            SessionError::Runtime(e) => diagnostics.render_runtime_error(e),
+            SessionError::Io(_) | SessionError::FileNameRequired => format!("error: {}\n", e),
+        }
+    }
+
+    fn eval_input(
+        &mut self,
+        input: &str,
+        allow_incomplete: bool,
+    ) -> Result<Response, SessionError> {
+        match Command::parse(input) {
+            Some(command) => self.run_command(command),
+            None => self.execute(input, allow_incomplete).map(|response| {
+                if response == Response::Done {
+                    if !self.program.is_empty() && !self.program.ends_with('\n') {
+                        self.program.push('\n');
+                    }
+                    self.program.push_str(input);
+                    self.program.push('\n');
+                }
+                response
+            }),
+        }
+    }
+
+    fn run_command(&mut self, command: Command) -> Result<Response, SessionError> {
+        match command {
+            Command::List => Ok(Response::Listing(self.program.clone())),
+            Command::Load(file_name) => {
+                self.program = std::fs::read_to_string(&file_name).map_err(SessionError::Io)?;
+                self.file_name = Some(file_name);
+                Ok(Response::Done)
+            }
+            Command::New => {
+                self.reset();
+                self.program.clear();
+                self.file_name = None;
+                Ok(Response::Done)
+            }
+            Command::Run => {
+                self.reset();
+                let program = self.program.clone();
+                self.execute(&program, false)
+            }
+            Command::Save(file_name) => {
+                let file_name = file_name
+                    .or_else(|| self.file_name.clone())
+                    .ok_or(SessionError::FileNameRequired)?;
+                std::fs::write(&file_name, &self.program).map_err(SessionError::Io)?;
+                self.file_name = Some(file_name);
+                Ok(Response::Done)
+            }
+            Command::System => Ok(Response::Exit),
+        }
+    }
+
+    /// Clears all variables, definitions and open files.
+    fn reset(&mut self) {
+        self.interpreter = (self.new_interpreter)();
+        self.declarations.clear();
+        self.history.clear();
+        self.transcript.clear();
+    }
+
+    fn execute(&mut self, input: &str, allow_incomplete: bool) -> Result<Response, SessionError> {
+        let first_row = self.transcript.len() as u32 + 1;
+        // pad with empty lines, so that the positions follow the previous inputs
+        let source = "\n".repeat(self.transcript.len()) + input;
+        let parsed = match parse_main_str(source) {
+            Ok(parsed) => parsed,
+            Err(e) if allow_incomplete && is_after(e.pos, end_of_input(input, first_row)) => {
+                return Ok(Response::Incomplete);
+            }
+            Err(e) => {
+                self.transcript.extend(input.lines().map(str::to_owned));
+                return Err(SessionError::Parse(e));
+            }
+        };
+        self.transcript.extend(input.lines().map(str::to_owned));
+
+        self.history.extend(parsed.iter().cloned());
+
+        // lint the input together with the declarations of the previous inputs
+        let mut program = self.declarations.clone();
+        program.extend(parsed.iter().cloned());
+        let (linted_program, linter_context) = lint(program).map_err(SessionError::Lint)?;
+
+        // the previous inputs already ran, keep only their SUBs and FUNCTIONs
+        let linted_program: Program = linted_program
+            .into_iter()
+            .filter(|s| s.pos.row() >= first_row || is_subprogram_implementation(&s.element))
+            .collect();
+        self.declarations
+            .extend(parsed.into_iter().filter(|s| is_declaration(&s.element)));
+        // variables, including the implicitly defined ones, in their resolved form
+        self.declarations.extend(
+            linted_program
+                .iter()
+                .filter(|s| matches!(s.element, GlobalStatement::Statement(Statement::Dim(_))))
+                .cloned(),
+        );
+
+        let (linter_names, user_defined_types) = unwrap_linter_context(linter_context);
+        self.interpreter.set_user_defined_types(user_defined_types);
+        let instruction_generator_result = generate_instructions(linted_program, linter_names);
+        self.interpreter
+            .interpret(instruction_generator_result)
+            .map(|_| Response::Done)
+            .map_err(SessionError::Runtime)
+    }
+}
+
+/// Gets the position right after the last non-whitespace character of the input.
+fn end_of_input(input: &str, first_row: u32) -> Position {
+    input
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            line.trim_end()
+                .chars()
+                .count()
+                .checked_sub(1)
+                .map(|last_col| Position::new(first_row + index as u32, last_col as u32 + 1))
+        })
+        .last()
+        .unwrap_or_else(|| Position::new(first_row, 1))
+}
+
+fn is_after(pos: Position, other: Position) -> bool {
+    (pos.row(), pos.col()) > (other.row(), other.col())
+}
+
+fn is_subprogram_implementation(global_statement: &GlobalStatement) -> bool {
+    matches!(
+        global_statement,
+        GlobalStatement::FunctionImplementation(_) | GlobalStatement::SubImplementation(_)
+    )
+}
+
+/// Checks if the given (parsed) global statement is needed
+/// in order to lint the next inputs.
+fn is_declaration(global_statement: &GlobalStatement) -> bool {
+    match global_statement {
+        GlobalStatement::DefType(_)
+        | GlobalStatement::FunctionDeclaration(_)
+        | GlobalStatement::SubDeclaration(_)
+        | GlobalStatement::UserDefinedType(_)
+        | GlobalStatement::FunctionImplementation(_)
+        | GlobalStatement::SubImplementation(_) => true,
+        GlobalStatement::Statement(s) => matches!(s, Statement::Const(_)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::test_utils::mock_interpreter;
+
+    fn eval_all<I: InterpreterTrait, F: Fn() -> I>(session: &mut Session<I, F>, inputs: &[&str]) {
+        for input in inputs {
+            assert_eq!(session.eval(input).unwrap(), Response::Done, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_variables_survive_between_inputs() {
+        let mut session = Session::new(mock_interpreter);
+        eval_all(
+            &mut session,
+            &[
+                "X = 42",
+                "A$ = \"hello\"",
+                "PRINT X; A$",
+                "X = X + 1",
+                "PRINT X",
+            ],
+        );
+        assert_eq!(
+            session.interpreter().stdout().output_lines(),
+            vec!["42 hello", "43"]
+        );
+    }
+
+    #[test]
+    fn test_declared_variables_survive_between_inputs() {
+        let mut session = Session::new(mock_interpreter);
+        eval_all(
+            &mut session,
+            &[
+                "TYPE Card\nSuit AS STRING * 1\nValue AS INTEGER\nEND TYPE",
+                "DIM C AS Card",
+                "C.Suit = \"H\": C.Value = 10",
+                "DIM A(1 TO 3) AS INTEGER",
+                "A(2) = 5",
+                "PRINT C.Suit; C.Value; A(2)",
+            ],
+        );
+        assert_eq!(
+            session.interpreter().stdout().output_lines(),
+            vec!["H 10  5"]
+        );
+    }
+
+    #[test]
+    fn test_constants_and_def_type_survive_between_inputs() {
+        let mut session = Session::new(mock_interpreter);
+        eval_all(
+            &mut session,
+            &["CONST Pi = 3.14", "DEFINT A-Z", "A = 2.6", "PRINT Pi; A"],
+        );
+        assert_eq!(
+            session.interpreter().stdout().output_lines(),
+            vec!["3.14  3"]
+        );
+    }
+
+    #[test]
+    fn test_sub_and_function_survive_between_inputs() {
+        let mut session = Session::new(mock_interpreter);
+        eval_all(
+            &mut session,
+            &[
+                "DIM SHARED Total",
+                "SUB Add(N)\nTotal = Total + N\nEND SUB",
+                "FUNCTION Twice(N)\nTwice = N * 2\nEND FUNCTION",
+                "Add 1",
+                "Add Twice(2)",
+                "PRINT Total",
+            ],
+        );
+        assert_eq!(session.interpreter().stdout().output_lines(), vec!["5"]);
+    }
+
+    #[test]
+    fn test_open_files_survive_between_inputs() {
+        let mut session = Session::new(mock_interpreter);
+        eval_all(
+            &mut session,
+            &[
+                "OPEN \"TEST_REPL.TXT\" FOR OUTPUT AS #1",
+                "PRINT #1, \"hello\"",
+                "CLOSE #1",
+                "OPEN \"TEST_REPL.TXT\" FOR INPUT AS #1",
+                "LINE INPUT #1, A$",
+                "CLOSE #1",
+                "PRINT A$",
+            ],
+        );
+        std::fs::remove_file("TEST_REPL.TXT").unwrap_or(());
+        assert_eq!(session.interpreter().stdout().output_lines(), vec!["hello"]);
+    }
+
+    #[test]
+    fn test_incomplete_blocks() {
+        let mut session = Session::new(mock_interpreter);
+        assert_eq!(
+            session.eval("FOR I = 1 TO 3").unwrap(),
+            Response::Incomplete
+        );
+        assert_eq!(
+            session.eval("FOR I = 1 TO 3\nPRINT I").unwrap(),
+            Response::Incomplete
+        );
+        assert_eq!(
+            session.eval("FOR I = 1 TO 3\nPRINT I\nNEXT").unwrap(),
+            Response::Done
+        );
+        assert_eq!(session.eval("SUB Hello").unwrap(), Response::Incomplete);
+        assert_eq!(session.eval("IF X THEN").unwrap(), Response::Incomplete);
+        assert_eq!(session.eval("TYPE Card").unwrap(), Response::Incomplete);
+        assert_eq!(
+            session.interpreter().stdout().output_lines(),
+            vec!["1", "2", "3"]
+        );
+    }
+
+    #[test]
+    fn test_eval_complete_reports_unfinished_block() {
+        let mut session = Session::new(mock_interpreter);
+        let err = session.eval_complete("FOR I = 1 TO 3").unwrap_err();
+        assert!(matches!(err, SessionError::Parse(_)));
+    }
+
+    #[test]
+    fn test_errors_do_not_end_the_session() {
+        let mut session = Session::new(mock_interpreter);
+        eval_all(&mut session, &["X = 1"]);
+        assert!(matches!(
+            session.eval_complete("PRINT 1 +").unwrap_err(),
+            SessionError::Parse(_)
+        ));
+        assert!(matches!(
+            session.eval("Y = \"a\" + 1").unwrap_err(),
+            SessionError::Lint(_)
+        ));
+        eval_all(&mut session, &["SUB Fail\nERROR 5\nEND SUB"]);
+        let err = session.eval("X = 2: Fail").unwrap_err();
+        assert_eq!(
+            session.render_error(&err),
+            "error: Illegal function call
+ --> immediate:5:1
+  |
+5 | ERROR 5
+  | ^
+call stack:
+    at Fail (immediate:5:1)
+    at module-level code (immediate:7:8)
+"
+        );
+        eval_all(&mut session, &["PRINT X"]);
+        assert_eq!(session.interpreter().stdout().output_lines(), vec!["2"]);
+    }
+
+    #[test]
+    fn test_list_shows_successful_inputs() {
+        let mut session = Session::new(mock_interpreter);
+        eval_all(&mut session, &["X = 1", "PRINT X"]);
+        session.eval("PRINT \"a\" + 1").unwrap_err();
+        assert_eq!(
+            session.eval("list").unwrap(),
+            Response::Listing("X = 1\nPRINT X\n".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_run_starts_over_with_the_program_buffer() {
+        let mut session = Session::new(mock_interpreter);
+        eval_all(&mut session, &["X = X + 1", "PRINT X"]);
+        eval_all(&mut session, &["RUN", "PRINT X"]);
+        assert_eq!(
+            session.interpreter().stdout().output_lines(),
+            vec!["1", "1"]
+        );
+    }
+
+    #[test]
+    fn test_new_clears_program_and_variables() {
+        let mut session = Session::new(mock_interpreter);
+        eval_all(&mut session, &["X = 1", "NEW", "PRINT X"]);
+        assert_eq!(session.program(), "PRINT X\n");
+        assert_eq!(session.interpreter().stdout().output_lines(), vec!["0"]);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let mut session = Session::new(mock_interpreter);
+        eval_all(
+            &mut session,
+            &["SUB Hello\nPRINT \"hi\"\nEND SUB", "SAVE \"TEST_REPL.BAS\""],
+        );
+        let mut session = Session::new(mock_interpreter);
+        eval_all(
+            &mut session,
+            &["LOAD \"TEST_REPL.BAS\"", "RUN", "Hello", "SAVE"],
+        );
+        let saved = std::fs::read_to_string("TEST_REPL.BAS").unwrap();
+        std::fs::remove_file("TEST_REPL.BAS").unwrap_or(());
+        assert_eq!(saved, "SUB Hello\nPRINT \"hi\"\nEND SUB\nHello\n");
+        assert_eq!(session.interpreter().stdout().output_lines(), vec!["hi"]);
+    }
+
+    #[test]
+    fn test_assignments_to_variables_named_like_commands() {
+        let mut session = Session::new(mock_interpreter);
+        eval_all(&mut session, &["SAVE = 1", "LOAD = 5", "PRINT SAVE; LOAD"]);
+        assert_eq!(session.interpreter().stdout().output_lines(), vec!["1  5"]);
+        assert_eq!(Command::parse("SAVE =1"), None);
+        assert_eq!(Command::parse("LOAD (1) = 2"), None);
+        assert_eq!(
+            Command::parse("LOAD TEST.BAS"),
+            Some(Command::Load("TEST.BAS".to_owned()))
+        );
+        assert_eq!(
+            Command::parse("SAVE \"MY TEST.BAS\""),
+            Some(Command::Save(Some("MY TEST.BAS".to_owned())))
+        );
+    }
+
+    #[test]
+    fn test_save_without_file_name() {
+        let mut session = Session::new(mock_interpreter);
+        assert!(matches!(
+            session.eval("SAVE").unwrap_err(),
+            SessionError::FileNameRequired
+        ));
+    }
+
+    #[test]
+    fn test_system_exits() {
+        let mut session = Session::new(mock_interpreter);
+        assert_eq!(session.eval("system").unwrap(), Response::Exit);
+    }
+}
@@ -0,0 +1,374 @@
+//! Runs the unit tests of a program and measures its line coverage.
+//!
+//! A test is a `SUB` without parameters whose name starts with `Test`
+//! (e.g. `SUB TestAdd`). Every test runs on a fresh interpreter: the
+//! module-level code runs first (acting as the setup of the test) and then
+//! the test is called. A test fails when it raises an error that is not
+//! handled, typically a failed `ASSERT` or `ASSERTEQUAL`, or when the
+//! module-level code ends the program (e.g. with `END`) before the test is
+//! called.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use rusty_common::{AtPos, Position};
+use rusty_linter::core::{LintErrorPos, lint_tests};
+use rusty_parser::{GlobalStatement, Program, Statement};
+
+use crate::instruction_generator::{
+    Instruction, InstructionGeneratorResult, generate_instructions, unwrap_linter_context,
+};
+use crate::interpreter::{
+    DebugAction, Debugger, InterpreterTrait, Pause, PauseReason, new_interpreter_with_io,
+};
+use crate::{ErrorEnvelope, RuntimeErrorPos};
+
+/// The prefix of the names of the SUBs that are tests (case insensitive).
+const TEST_PREFIX: &str = "Test";
+
+/// The outcome of a single test.
+pub struct TestResult {
+    /// The name of the test SUB.
+    pub name: String,
+    /// The position of the name of the test SUB.
+    pub pos: Position,
+    /// What the test printed.
+    pub output: String,
+    /// The error that failed the test, if any.
+    pub error: Option<RuntimeErrorPos>,
+    /// Whether the test was called. The module-level code might end the
+    /// program before that, e.g. with `END`, `SYSTEM` or `STOP`.
+    pub ran: bool,
+}
+
+impl TestResult {
+    pub fn is_pass(&self) -> bool {
+        self.ran && self.error.is_none()
+    }
+}
+
+/// The number of times each source line ran, aggregated over many tests.
+#[derive(Debug, Default)]
+pub struct Coverage {
+    /// Maps a row to the number of statements that ran on it.
+    /// Rows with statements that never ran are present with zero hits.
+    hits: BTreeMap<u32, u32>,
+}
+
+impl Coverage {
+    /// Gets the number of hits of the given row,
+    /// or `None` if the row has no statements.
+    pub fn hits(&self, row: u32) -> Option<u32> {
+        self.hits.get(&row).copied()
+    }
+
+    /// Gets the number of rows that have statements.
+    pub fn lines_found(&self) -> usize {
+        self.hits.len()
+    }
+
+    /// Gets the number of rows that have statements that ran.
+    pub fn lines_hit(&self) -> usize {
+        self.hits.values().filter(|hits| **hits > 0).count()
+    }
+
+    /// Renders the coverage as a record of an lcov tracefile.
+    pub fn to_lcov(&self, source_file: &str) -> String {
+        let mut buf = String::new();
+        writeln!(buf, "TN:").unwrap();
+        writeln!(buf, "SF:{}", source_file).unwrap();
+        for (row, hits) in &self.hits {
+            writeln!(buf, "DA:{},{}", row, hits).unwrap();
+        }
+        writeln!(buf, "LF:{}", self.lines_found()).unwrap();
+        writeln!(buf, "LH:{}", self.lines_hit()).unwrap();
+        writeln!(buf, "end_of_record").unwrap();
+        buf
+    }
+
+    /// Registers the rows of the statements of the given program,
+    /// skipping the statement at the given position.
+    fn add_statements(&mut self, result: &InstructionGeneratorResult, excluded: Position) {
+        for address in &result.statement_addresses {
+            let instruction_pos = &result.instructions[*address];
+            // the end of a SUB/FUNCTION and the end of the program are not in the source
+            if matches!(
+                instruction_pos.element,
+                Instruction::PopRet | Instruction::Halt
+            ) || instruction_pos.pos == excluded
+            {
+                continue;
+            }
+            self.hits.entry(instruction_pos.pos.row()).or_default();
+        }
+    }
+}
+
+/// Runs every test of the given (parsed) program, recording the coverage.
+/// Returns an error if the program does not lint.
+pub fn run_tests(
+    program: &Program,
+    coverage: &mut Coverage,
+) -> Result<Vec<TestResult>, LintErrorPos> {
+    lint_tests(program.clone())?;
+    let mut results = vec![];
+    for (name, pos) in find_tests(program) {
+        results.push(run_test(program, name, pos, coverage)?);
+    }
+    Ok(results)
+}
+
+/// Finds the SUBs that are tests, in order of appearance.
+fn find_tests(program: &Program) -> Vec<(String, Position)> {
+    program
+        .iter()
+        .filter_map(|global_statement_pos| match &global_statement_pos.element {
+            GlobalStatement::SubImplementation(s) if s.params.is_empty() => {
+                let name = s.name.element.to_string();
+                let is_test = name
+                    .get(..TEST_PREFIX.len())
+                    .is_some_and(|prefix| prefix.eq_ignore_ascii_case(TEST_PREFIX));
+                is_test.then_some((name, s.name.pos))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn run_test(
+    program: &Program,
+    name: String,
+    pos: Position,
+    coverage: &mut Coverage,
+) -> Result<TestResult, LintErrorPos> {
+    // call the test after the module-level code, at the position of its name
+    let mut test_program = program.clone();
+    test_program.push(
+        GlobalStatement::Statement(Statement::sub_call(name.as_str().into(), vec![])).at_pos(pos),
+    );
+    let (linted_program, linter_context) = lint_tests(test_program)?;
+    let (linter_names, user_defined_types) = unwrap_linter_context(linter_context);
+    let instruction_generator_result = generate_instructions(linted_program, linter_names);
+    coverage.add_statements(&instruction_generator_result, pos);
+    let mut interpreter =
+        new_interpreter_with_io(user_defined_types, std::io::empty(), Vec::<u8>::new());
+    let mut recorder = CoverageRecorder {
+        coverage,
+        last_pos: None,
+        excluded: pos,
+        reached_excluded: false,
+    };
+    let error = interpreter
+        .debug(instruction_generator_result, &mut recorder)
+        .err()
+        .map(|e| without_test_call(e, pos));
+    let ran = recorder.reached_excluded;
+    let output = String::from_utf8_lossy(interpreter.stdout().inner()).into_owned();
+    Ok(TestResult {
+        name,
+        pos,
+        output,
+        error,
+        ran,
+    })
+}
+
+/// Removes the call of the test from the bottom of the call stack of the error,
+/// as it does not exist in the source.
+fn without_test_call(e: RuntimeErrorPos, call_pos: Position) -> RuntimeErrorPos {
+    match e.stacktrace().split_last() {
+        Some((last, rest)) if *last == call_pos && !rest.is_empty() => {
+            ErrorEnvelope::new_with_stacktrace(e.err().clone(), rest.to_vec())
+        }
+        _ => e,
+    }
+}
+
+/// Records the statements that run, without ever pausing the program.
+struct CoverageRecorder<'a> {
+    coverage: &'a mut Coverage,
+    /// The position of the last statement, in order to count the implicit
+    /// declarations that share the position of the statement only once.
+    last_pos: Option<Position>,
+    /// The position of the call of the test, which is not in the source.
+    excluded: Position,
+    /// Whether the call of the test was reached.
+    reached_excluded: bool,
+}
+
+impl Debugger for CoverageRecorder<'_> {
+    fn on_start(&mut self) -> DebugAction {
+        DebugAction::Continue
+    }
+
+    fn has_breakpoint(&self, _row: u32) -> bool {
+        false
+    }
+
+    fn on_pause(&mut self, pause: &Pause) -> DebugAction {
+        // STOP ends the program, as it would without a debugger
+        if pause.reason == PauseReason::Stop {
+            DebugAction::Quit
+        } else {
+            DebugAction::Continue
+        }
+    }
+
+    fn on_statement(&mut self, pos: Position) {
+        if pos == self.excluded {
+            self.reached_excluded = true;
+        } else if self.last_pos != Some(pos) {
+            *self.coverage.hits.entry(pos.row()).or_default() += 1;
+        }
+        self.last_pos = Some(pos);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusty_parser::parse_main_str;
+
+    use super::*;
+    use crate::RuntimeError;
+
+    fn run(input: &str) -> (Vec<TestResult>, Coverage) {
+        let program = parse_main_str(input.to_owned()).unwrap();
+        let mut coverage = Coverage::default();
+        let results = run_tests(&program, &mut coverage).unwrap();
+        (results, coverage)
+    }
+
+    #[test]
+    fn test_finds_subs_starting_with_test() {
+        let input = r#"
+SUB TestOne
+END SUB
+
+SUB Helper
+END SUB
+
+SUB testTwo
+END SUB
+
+SUB TestWithParams(A)
+END SUB
+"#;
+        let (results, _) = run(input);
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["TestOne", "testTwo"]);
+        assert!(results.iter().all(|r| r.ran));
+        assert!(results.iter().all(TestResult::is_pass));
+    }
+
+    #[test]
+    fn test_reports_failed_assertion() {
+        let input = r#"
+SUB TestPass
+    ASSERTEQUAL 2, 1 + 1
+END SUB
+
+SUB TestFail
+    PRINT "checking"
+    ASSERTEQUAL 3, 1 + 1
+END SUB
+"#;
+        let (results, _) = run(input);
+        assert!(results[0].is_pass());
+        assert_eq!(results[1].pos, Position::new(6, 5));
+        assert_eq!(results[1].output, "checking\r\n");
+        let error = results[1].error.as_ref().unwrap();
+        assert_eq!(
+            error.err(),
+            &RuntimeError::AssertionFailed("Expected 3, but was 2".to_owned())
+        );
+        assert_eq!(error.stacktrace(), &[Position::new(8, 5)]);
+    }
+
+    #[test]
+    fn test_fails_when_the_program_ends_before_the_test() {
+        for end in ["END", "SYSTEM", "STOP"] {
+            let input = format!(
+                r#"
+PRINT "main"
+{}
+
+SUB TestFail
+    ASSERT 0, "should fail"
+END SUB
+"#,
+                end
+            );
+            let (results, _) = run(&input);
+            assert!(!results[0].ran, "{}", end);
+            assert!(!results[0].is_pass(), "{}", end);
+            assert!(results[0].error.is_none(), "{}", end);
+            assert_eq!(results[0].output, "main\r\n", "{}", end);
+        }
+    }
+
+    #[test]
+    fn test_isolates_tests() {
+        let input = r#"
+DIM SHARED Count
+Count = Count + 1
+
+SUB TestFirst
+    Count = Count + 1
+    ASSERTEQUAL 2, Count
+END SUB
+
+SUB TestSecond
+    ASSERTEQUAL 1, Count
+END SUB
+"#;
+        let (results, _) = run(input);
+        assert!(results.iter().all(TestResult::is_pass));
+    }
+
+    #[test]
+    fn test_coverage() {
+        let input = r#"
+X = 1
+
+SUB TestOne
+    IF 1 + 1 = 2 THEN
+        PRINT "one"
+    ELSE
+        PRINT "other"
+    END IF
+END SUB
+
+SUB TestTwo
+    PRINT "two"
+END SUB
+
+SUB Unused
+    PRINT "unused"
+END SUB
+"#;
+        let (_, coverage) = run(input);
+        assert_eq!(coverage.hits(2), Some(2));
+        assert_eq!(coverage.hits(4), None);
+        assert_eq!(coverage.hits(5), Some(1));
+        assert_eq!(coverage.hits(6), Some(1));
+        assert_eq!(coverage.hits(8), Some(0));
+        assert_eq!(coverage.hits(13), Some(1));
+        assert_eq!(coverage.hits(17), Some(0));
+        assert_eq!(coverage.lines_found(), 6);
+        assert_eq!(coverage.lines_hit(), 4);
+    }
+
+    #[test]
+    fn test_lcov() {
+        let input = r#"
+SUB TestOne
+    PRINT "one"
+END SUB
+"#;
+        let (_, coverage) = run(input);
+        assert_eq!(
+            coverage.to_lcov("ONE.BAS"),
+            "TN:\nSF:ONE.BAS\nDA:3,1\nLF:1\nLH:1\nend_of_record\n"
+        );
+    }
+}
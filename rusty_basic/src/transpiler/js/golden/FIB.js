@@ -0,0 +1,53 @@
+// Transpiled from QBasic by rusty_basic. Do not edit.
+import { Halt, QArray } from "./runtime.js";
+
+export async function main(rt) {
+    let v_n_sng = 0;
+    let v_i_sng = 0;
+
+    async function fn_fib(v_n_sng) {
+        let result = 0;
+        let pc = 0;
+        for (;;) {
+            switch (pc) {
+                case 0:
+                    if (!(v_n_sng.v <= 1)) { pc = 2; continue; }
+                    result = v_n_sng.v;
+                    pc = 1; continue;
+                case 2:
+                    result = Math.fround((await fn_fib({ v: Math.fround(v_n_sng.v - 1) })) + (await fn_fib({ v: Math.fround(v_n_sng.v - 2) })));
+                case 1:
+                    return result;
+            }
+        }
+    }
+
+    let f0 = 0, a1 = 0, r2 = 0;
+    let pc = 0;
+    for (;;) {
+        switch (pc) {
+            case 0:
+                v_n_sng = 0;
+                v_i_sng = 0;
+                rt.printValue("Enter the number of fibonacci to calculate", "str");
+                rt.printEnd();
+                v_n_sng = await rt.input("sng");
+                v_i_sng = rt.cast(0, "sng");
+                f0 = v_n_sng;
+            case 1:
+                if (v_i_sng > f0) { pc = 2; continue; }
+                rt.printValue("Fibonacci of", "str");
+                rt.printComma();
+                rt.printValue(v_i_sng, "sng");
+                rt.printComma();
+                rt.printValue("is", "str");
+                rt.printComma();
+                rt.printValue((a1 = { v: v_i_sng }, r2 = await fn_fib(a1), v_i_sng = a1.v, r2), "sng");
+                rt.printEnd();
+                v_i_sng = Math.fround(v_i_sng + 1);
+                pc = 1; continue;
+            case 2:
+                return;
+        }
+    }
+}
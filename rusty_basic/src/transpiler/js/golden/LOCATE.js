@@ -0,0 +1,54 @@
+// Transpiled from QBasic by rusty_basic. Do not edit.
+import { Halt, QArray } from "./runtime.js";
+
+export async function main(rt) {
+    let v_n_sng = 0;
+    let v_r_sng = 0;
+    let v_row_sng = 0;
+    let v_col_sng = 0;
+
+    let f0 = 0, f1 = 0;
+    let pc = 0;
+    for (;;) {
+        switch (pc) {
+            case 0:
+                v_n_sng = 0;
+                v_r_sng = 0;
+                v_row_sng = 0;
+                v_col_sng = 0;
+                rt.cls();
+                rt.locate(undefined, undefined, 0);
+                // cursor off
+                v_n_sng = rt.cast(1, "sng");
+                f0 = rt.cast(12, "sng");
+            case 1:
+                if (v_n_sng > f0) { pc = 2; continue; }
+                rt.color(15, rt.cast(v_n_sng, "lng"));
+                v_r_sng = rt.cast(1, "sng");
+                f1 = rt.cast(10, "sng");
+            case 3:
+                if (v_r_sng > f1) { pc = 4; continue; }
+                if (!(rt.modulo(v_n_sng, 2) === 0)) { pc = 6; continue; }
+                v_row_sng = Math.fround(11 - v_r_sng);
+                pc = 5; continue;
+            case 6:
+                v_row_sng = v_r_sng;
+            case 5:
+                v_col_sng = Math.fround(v_r_sng + Math.fround(Math.fround(v_n_sng - 1) * 10));
+                rt.locate(rt.cast(v_row_sng, "lng"), rt.cast(v_col_sng, "lng"));
+                rt.printValue("*", "str");
+                rt.printSemicolon();
+                rt.printEnd();
+                v_r_sng = Math.fround(v_r_sng + 1);
+                pc = 3; continue;
+            case 4:
+                v_n_sng = Math.fround(v_n_sng + 1);
+                pc = 1; continue;
+            case 2:
+                rt.color(7, 0);
+                rt.locate(11, undefined, 1);
+                // cursor on
+                return;
+        }
+    }
+}
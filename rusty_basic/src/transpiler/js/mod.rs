@@ -0,0 +1,1481 @@
+//! Transpiles a linted program into a JavaScript (ES) module.
+//!
+//! The module exports an async `main(rt)` function, where `rt` is the
+//! `Runtime` of `runtime.js`. The runtime implements `PRINT`, `INPUT`,
+//! `LOCATE` and `COLOR` on a text grid, which `index.html` renders
+//! in the browser.
+//!
+//! The module level variables are local variables of `main` and every
+//! SUB and FUNCTION is a nested function, so that `SHARED` variables are
+//! accessible. Parameters are passed by reference as `{ v: value }` cells.
+//!
+//! The statements of a procedure are lowered to a label-dispatch loop
+//! (a `switch` on the program counter inside an endless loop), where every
+//! label, loop and branch is a `case`. This way, `GOTO` and `GOSUB` can jump
+//! anywhere in the procedure, even into a block.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use rusty_common::{AtPos, Position, Positioned};
+use rusty_parser::*;
+
+use super::{CodeWriter, TranspileError, TranspileErrorPos};
+
+/// The runtime that the generated module imports as `./runtime.js`.
+pub const JS_RUNTIME: &str = include_str!("runtime.js");
+
+/// A web page that runs `./program.js` with the runtime.
+pub const JS_INDEX_HTML: &str = include_str!("index.html");
+
+/// A generated JavaScript module.
+pub struct JsModule {
+    pub program_js: String,
+}
+
+impl JsModule {
+    /// Writes `program.js`, `runtime.js` and `index.html` into the given directory.
+    pub fn write_to(&self, dir: &Path) -> std::io::Result<()> {
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(dir.join("program.js"), &self.program_js)?;
+        std::fs::write(dir.join("runtime.js"), JS_RUNTIME)?;
+        std::fs::write(dir.join("index.html"), JS_INDEX_HTML)
+    }
+}
+
+/// Transpiles a linted program into a JavaScript module.
+pub fn transpile_to_js(program: Program) -> Result<JsModule, TranspileErrorPos> {
+    let mut transpiler = JsTranspiler::default();
+    transpiler.transpile(program)?;
+    Ok(JsModule {
+        program_js: transpiler.out.into_string(),
+    })
+}
+
+type TranspileResult<T> = Result<T, TranspileErrorPos>;
+
+fn unsupported<T>(what: impl Into<String>, pos: Position) -> TranspileResult<T> {
+    Err(TranspileError::Unsupported(what.into()).at_pos(pos))
+}
+
+/// The state of the procedure (the module level code, a SUB or a FUNCTION)
+/// whose body is being transpiled.
+#[derive(Default)]
+struct Procedure {
+    body: CodeWriter,
+    /// The bare name of the function, which is used to assign its result.
+    function_name: Option<BareName>,
+    /// The names of the parameters, which are `{ v: value }` cells.
+    params: Vec<String>,
+    /// The case of each label.
+    labels: HashMap<BareName, usize>,
+    /// The next case of the label-dispatch loop, case 0 is the entry point.
+    next_case: usize,
+    /// The temporary variables, e.g. the upper bound of a FOR loop.
+    temps: Vec<String>,
+    uses_gosub: bool,
+}
+
+impl Procedure {
+    fn new(function_name: Option<BareName>, params: Vec<String>) -> Self {
+        Self {
+            // the statements are indented one level deeper than the `case` labels
+            body: CodeWriter::with_indentation(1),
+            function_name,
+            params,
+            next_case: 1,
+            ..Default::default()
+        }
+    }
+
+    fn new_case(&mut self) -> usize {
+        let case = self.next_case;
+        self.next_case += 1;
+        case
+    }
+
+    fn new_temp(&mut self, prefix: &str) -> String {
+        let temp = format!("{}{}", prefix, self.temps.len());
+        self.temps.push(temp.clone());
+        temp
+    }
+
+    fn case(&mut self, case: usize) {
+        self.body.label(format!("case {}:", case));
+    }
+
+    fn jump(&mut self, case: usize) {
+        self.body.line(format!("pc = {}; continue;", case));
+    }
+
+    /// Jumps to the given case if the given JavaScript condition is true.
+    fn jump_if(&mut self, condition: &str, case: usize) {
+        self.body
+            .line(format!("if ({}) {{ pc = {}; continue; }}", condition, case));
+    }
+
+    /// Jumps to the given case if the given JavaScript condition is false.
+    fn jump_unless(&mut self, condition: &str, case: usize) {
+        self.body.line(format!(
+            "if (!({})) {{ pc = {}; continue; }}",
+            condition, case
+        ));
+    }
+
+    fn uses_dispatch_loop(&self) -> bool {
+        self.next_case > 1
+    }
+}
+
+/// The code that passes the arguments of a call. Arguments that are
+/// passed by reference are copied into temporary cells and back.
+struct CallArgs {
+    before: Vec<String>,
+    args: Vec<String>,
+    after: Vec<String>,
+}
+
+#[derive(Default)]
+struct JsTranspiler {
+    out: CodeWriter,
+    functions: HashMap<BareName, Vec<ExpressionType>>,
+    subs: HashMap<BareName, Vec<ExpressionType>>,
+    procedure: Procedure,
+}
+
+impl JsTranspiler {
+    fn transpile(&mut self, program: Program) -> TranspileResult<()> {
+        let mut statements: Statements = vec![];
+        let mut functions: Vec<Positioned<FunctionImplementation>> = vec![];
+        let mut subs: Vec<Positioned<SubImplementation>> = vec![];
+        for Positioned { element, pos } in program {
+            match element {
+                GlobalStatement::Statement(s) => statements.push(s.at_pos(pos)),
+                GlobalStatement::FunctionImplementation(f) => {
+                    self.functions.insert(
+                        f.name.element.as_bare_name().clone(),
+                        param_types(&f.params),
+                    );
+                    functions.push(f.at_pos(pos));
+                }
+                GlobalStatement::SubImplementation(s) => {
+                    self.subs
+                        .insert(s.name.element.clone(), param_types(&s.params));
+                    subs.push(s.at_pos(pos));
+                }
+                GlobalStatement::UserDefinedType(_) => {
+                    return unsupported("TYPE", pos);
+                }
+                GlobalStatement::DefType(_)
+                | GlobalStatement::FunctionDeclaration(_)
+                | GlobalStatement::SubDeclaration(_) => {}
+            }
+        }
+        // like the interpreter, collect the DATA values before the program starts
+        let (data, other): (Statements, Statements) = statements
+            .into_iter()
+            .partition(|s| is_built_in_sub_call(&s.element, BuiltInSub::Data));
+        statements = data;
+        statements.extend(other);
+
+        self.out
+            .line("// Transpiled from QBasic by rusty_basic. Do not edit.");
+        self.out
+            .line("import { Halt, QArray } from \"./runtime.js\";");
+        self.out.line("");
+        self.out.open("export async function main(rt) {");
+        self.declare_variables(&statements)?;
+        for f in functions {
+            self.out.line("");
+            self.function(f)?;
+        }
+        for s in subs {
+            self.out.line("");
+            self.sub(s)?;
+        }
+        self.out.line("");
+        self.procedure = Procedure::new(None, vec![]);
+        self.procedure_body(&statements)?;
+        self.finish_procedure(None);
+        self.out.close("}");
+        Ok(())
+    }
+
+    fn declare_variables(&mut self, statements: &Statements) -> TranspileResult<()> {
+        for (ident, initial_value) in declared_variables(statements)? {
+            self.out.line(format!("let {} = {};", ident, initial_value));
+        }
+        Ok(())
+    }
+
+    fn function(&mut self, f: Positioned<FunctionImplementation>) -> TranspileResult<()> {
+        let Positioned {
+            element:
+                FunctionImplementation {
+                    name,
+                    params,
+                    body,
+                    is_static,
+                },
+            pos,
+        } = f;
+        if is_static {
+            return unsupported("STATIC", pos);
+        }
+        let Some(q) = name.element.qualifier() else {
+            return unsupported("FUNCTION of a user defined type", name.pos);
+        };
+        let bare_name = name.element.as_bare_name().clone();
+        let param_names = param_names(&params)?;
+        self.out.open(format!(
+            "async function {}({}) {{",
+            function_ident(&bare_name),
+            param_names.join(", ")
+        ));
+        self.out.line(format!("let result = {};", initial_value(q)));
+        self.declare_variables(&body)?;
+        self.procedure = Procedure::new(Some(bare_name), param_names);
+        self.procedure_body(&body)?;
+        self.finish_procedure(Some("return result;"));
+        self.out.close("}");
+        Ok(())
+    }
+
+    fn sub(&mut self, s: Positioned<SubImplementation>) -> TranspileResult<()> {
+        let Positioned {
+            element:
+                SubImplementation {
+                    name,
+                    params,
+                    body,
+                    is_static,
+                },
+            pos,
+        } = s;
+        if is_static {
+            return unsupported("STATIC", pos);
+        }
+        let param_names = param_names(&params)?;
+        self.out.open(format!(
+            "async function {}({}) {{",
+            sub_ident(&name.element),
+            param_names.join(", ")
+        ));
+        self.declare_variables(&body)?;
+        self.procedure = Procedure::new(None, param_names);
+        self.procedure_body(&body)?;
+        self.finish_procedure(None);
+        self.out.close("}");
+        Ok(())
+    }
+
+    fn procedure_body(&mut self, statements: &Statements) -> TranspileResult<()> {
+        let mut labels: Vec<BareName> = vec![];
+        collect_labels(statements, &mut labels);
+        for label in labels {
+            let case = self.procedure.new_case();
+            self.procedure.labels.insert(label, case);
+        }
+        self.block(statements)
+    }
+
+    /// Writes the body of the current procedure, wrapping it in the
+    /// label-dispatch loop, if it has any jumps.
+    fn finish_procedure(&mut self, return_statement: Option<&str>) {
+        let mut procedure = std::mem::take(&mut self.procedure);
+        if !procedure.temps.is_empty() {
+            // a GOTO into a FOR loop sees zero bounds, instead of `undefined`
+            self.out
+                .line(format!("let {} = 0;", procedure.temps.join(" = 0, ")));
+        }
+        if procedure.uses_gosub {
+            self.out.line("const returns = [];");
+        }
+        if procedure.uses_dispatch_loop() {
+            // leave the endless loop at the end of the procedure
+            procedure.body.line(return_statement.unwrap_or("return;"));
+            self.out.line("let pc = 0;");
+            self.out.open("for (;;) {");
+            self.out.open("switch (pc) {");
+            self.out.line("case 0:");
+            self.out.append(&procedure.body.into_string());
+            self.out.close("}");
+            self.out.close("}");
+        } else {
+            // without the indentation of the `case` labels
+            let body = procedure.body.into_string();
+            let body: Vec<&str> = body
+                .lines()
+                .map(|line| line.strip_prefix("    ").unwrap_or(line))
+                .collect();
+            self.out.append(&body.join("\n"));
+            if let Some(return_statement) = return_statement {
+                self.out.line(return_statement);
+            }
+        }
+    }
+
+    fn block(&mut self, statements: &Statements) -> TranspileResult<()> {
+        for s in statements {
+            self.statement(s)?;
+        }
+        Ok(())
+    }
+
+    fn line(&mut self, line: impl AsRef<str>) {
+        self.procedure.body.line(line);
+    }
+
+    fn statement(&mut self, statement_pos: &StatementPos) -> TranspileResult<()> {
+        let pos = statement_pos.pos;
+        match &statement_pos.element {
+            Statement::Comment(text) => {
+                self.line(format!("//{}", text));
+            }
+            Statement::Assignment(a) => {
+                let (l, r) = a.into();
+                let value = self.expression_casting(r, &l.expression_type())?;
+                self.store(&l.clone().at_pos(pos), value)?;
+            }
+            Statement::Const(_) => {
+                // the linter has replaced the constants with their values
+            }
+            Statement::Dim(dim_list) | Statement::Redim(dim_list) => {
+                for dim_var in &dim_list.variables {
+                    self.dim(dim_var)?;
+                }
+            }
+            Statement::SubCall(sub_call) => {
+                let (name, args) = sub_call.into();
+                let param_types = self.subs[name].clone();
+                let call_args = self.call_args(&param_types, args)?;
+                for line in call_args.before {
+                    self.line(line);
+                }
+                self.line(format!(
+                    "await {}({});",
+                    sub_ident(name),
+                    call_args.args.join(", ")
+                ));
+                for line in call_args.after {
+                    self.line(line);
+                }
+            }
+            Statement::BuiltInSubCall(sub_call) => {
+                let (sub, args) = sub_call.into();
+                self.built_in_sub_call(*sub, args, pos)?;
+            }
+            Statement::IfBlock(i) => {
+                let end = self.procedure.new_case();
+                let mut branches: Vec<&ConditionalBlock> = vec![&i.if_block];
+                branches.extend(&i.else_if_blocks);
+                let count = branches.len();
+                for (index, branch) in branches.into_iter().enumerate() {
+                    let is_last = index + 1 == count && i.else_block.is_none();
+                    let next = if is_last {
+                        end
+                    } else {
+                        self.procedure.new_case()
+                    };
+                    let condition = self.condition(&branch.condition)?;
+                    self.procedure.jump_unless(&condition, next);
+                    self.block(&branch.statements)?;
+                    if !is_last {
+                        self.procedure.jump(end);
+                        self.procedure.case(next);
+                    }
+                }
+                if let Some(else_block) = &i.else_block {
+                    self.block(else_block)?;
+                }
+                self.procedure.case(end);
+            }
+            Statement::SelectCase(s) => self.select_case(s)?,
+            Statement::ForLoop(f) => self.for_loop(f)?,
+            Statement::While(w) => {
+                let top = self.procedure.new_case();
+                let end = self.procedure.new_case();
+                self.procedure.case(top);
+                let condition = self.condition(&w.condition)?;
+                self.procedure.jump_unless(&condition, end);
+                self.block(&w.statements)?;
+                self.procedure.jump(top);
+                self.procedure.case(end);
+            }
+            Statement::DoLoop(do_loop) => {
+                let top = self.procedure.new_case();
+                let condition = self.condition(&do_loop.condition)?;
+                let is_until = do_loop.kind == DoLoopConditionKind::Until;
+                self.procedure.case(top);
+                match do_loop.position {
+                    DoLoopConditionPosition::Top => {
+                        let end = self.procedure.new_case();
+                        if is_until {
+                            self.procedure.jump_if(&condition, end);
+                        } else {
+                            self.procedure.jump_unless(&condition, end);
+                        }
+                        self.block(&do_loop.statements)?;
+                        self.procedure.jump(top);
+                        self.procedure.case(end);
+                    }
+                    DoLoopConditionPosition::Bottom => {
+                        self.block(&do_loop.statements)?;
+                        if is_until {
+                            self.procedure.jump_unless(&condition, top);
+                        } else {
+                            self.procedure.jump_if(&condition, top);
+                        }
+                    }
+                }
+            }
+            Statement::Label(label) => {
+                let case = self.procedure.labels[label];
+                self.procedure.case(case);
+            }
+            Statement::GoTo(label) => {
+                let case = self.procedure.labels[label];
+                self.procedure.jump(case);
+            }
+            Statement::GoSub(label) => {
+                self.procedure.uses_gosub = true;
+                let case = self.procedure.labels[label];
+                let return_case = self.procedure.new_case();
+                self.line(format!("returns.push({});", return_case));
+                self.procedure.jump(case);
+                self.procedure.case(return_case);
+            }
+            Statement::Return(opt_label) => {
+                self.procedure.uses_gosub = true;
+                self.line("if (returns.length === 0) { throw rt.error(3); }");
+                match opt_label {
+                    Some(label) => {
+                        let case = self.procedure.labels[label];
+                        self.line("returns.pop();");
+                        self.procedure.jump(case);
+                    }
+                    None => self.line("pc = returns.pop(); continue;"),
+                }
+            }
+            Statement::Exit(_) => {
+                if self.procedure.function_name.is_some() {
+                    self.line("return result;");
+                } else {
+                    self.line("return;");
+                }
+            }
+            Statement::End | Statement::System | Statement::Stop => {
+                self.line("throw new Halt();");
+            }
+            Statement::Print(print) => self.print(print, pos)?,
+            Statement::OnError(_) => return unsupported("ON ERROR", pos),
+            Statement::Resume(_) => return unsupported("RESUME", pos),
+            Statement::OnEvent(_) | Statement::EventControl(_) => {
+                return unsupported("Event trapping", pos);
+            }
+        }
+        Ok(())
+    }
+
+    fn dim(&mut self, dim_var_pos: &DimVarPos) -> TranspileResult<()> {
+        let Positioned {
+            element: dim_var,
+            pos,
+        } = dim_var_pos;
+        let bare_name = dim_var.as_bare_name();
+        match dim_var.var_type() {
+            DimType::BuiltIn(q, _) => {
+                let place = self.place(bare_name, *q, false);
+                self.line(format!("{} = {};", place, initial_value(*q)));
+            }
+            DimType::FixedLengthString(_, len) => {
+                let place = self.place(bare_name, TypeQualifier::DollarString, false);
+                self.line(format!("{} = {};", place, fixed_length_string(*len)));
+            }
+            DimType::Array(dimensions, element_type) => {
+                let (q, element) = match element_type.as_ref() {
+                    DimType::BuiltIn(q, _) => (*q, initial_value(*q)),
+                    DimType::FixedLengthString(_, len) => {
+                        (TypeQualifier::DollarString, fixed_length_string(*len))
+                    }
+                    _ => return unsupported("user defined type", *pos),
+                };
+                let mut bounds: Vec<String> = vec![];
+                for ArrayDimension { lbound, ubound } in dimensions {
+                    let lbound = match lbound {
+                        Some(lbound) => self.integer(lbound)?,
+                        None => "0".to_owned(),
+                    };
+                    bounds.push(format!("[{}, {}]", lbound, self.integer(ubound)?));
+                }
+                let place = self.place(bare_name, q, true);
+                self.line(format!(
+                    "{} = new QArray([{}], {});",
+                    place,
+                    bounds.join(", "),
+                    element
+                ));
+            }
+            DimType::UserDefined(_) | DimType::Bare => {
+                return unsupported("user defined type", *pos);
+            }
+        }
+        Ok(())
+    }
+
+    fn built_in_sub_call(
+        &mut self,
+        sub: BuiltInSub,
+        args: &Expressions,
+        pos: Position,
+    ) -> TranspileResult<()> {
+        match sub {
+            BuiltInSub::Beep => self.line("rt.beep();"),
+            BuiltInSub::Cls => self.line("rt.cls();"),
+            BuiltInSub::Color => {
+                // the first argument is a bit flag of the colors that follow
+                let args = self.flagged_args(args, 2, pos)?;
+                self.line(format!("rt.color({});", args.join(", ")));
+            }
+            BuiltInSub::Locate => {
+                let args = self.flagged_args(args, 3, pos)?;
+                self.line(format!("rt.locate({});", args.join(", ")));
+            }
+            BuiltInSub::Data => {
+                for arg in args {
+                    let value = self.expression(arg)?;
+                    self.line(format!("rt.pushData({});", value));
+                }
+            }
+            BuiltInSub::Input | BuiltInSub::LineInput => {
+                // the first argument is 1 if a file number follows
+                if let Some(Expression::IntegerLiteral(1)) = args.first().map(|arg| &arg.element) {
+                    return unsupported("File I/O", pos);
+                }
+                for variable in &args[1..] {
+                    let value = if sub == BuiltInSub::Input {
+                        format!("await rt.input({})", js_type(self.qualifier_of(variable)?))
+                    } else {
+                        "await rt.lineInput()".to_owned()
+                    };
+                    let value = fix_length(value, &variable.expression_type());
+                    self.store(variable, value)?;
+                }
+            }
+            BuiltInSub::Read => {
+                for variable in args {
+                    let value = format!("rt.read({})", js_type(self.qualifier_of(variable)?));
+                    let value = fix_length(value, &variable.expression_type());
+                    self.store(variable, value)?;
+                }
+            }
+            BuiltInSub::Width => {
+                // WIDTH LPRINT is encoded as `2, columns`,
+                // the screen form is ignored like in the interpreter
+                if let [first, _] = args.as_slice()
+                    && first.element == Expression::IntegerLiteral(2)
+                {
+                    return unsupported("WIDTH LPRINT", pos);
+                }
+            }
+            BuiltInSub::Close | BuiltInSub::Open | BuiltInSub::Kill | BuiltInSub::Name => {
+                return unsupported("File I/O", pos);
+            }
+            _ => return unsupported(format!("{:?}", sub).to_uppercase(), pos),
+        }
+        Ok(())
+    }
+
+    /// Decodes the arguments of `COLOR` and `LOCATE`, where the first argument
+    /// is a bit flag of the optional arguments that follow.
+    /// The missing arguments become `undefined`.
+    fn flagged_args(
+        &mut self,
+        args: &Expressions,
+        count: usize,
+        pos: Position,
+    ) -> TranspileResult<Vec<String>> {
+        let Some(Expression::IntegerLiteral(flags)) = args.first().map(|arg| &arg.element) else {
+            return unsupported("Dynamic arguments", pos);
+        };
+        let mut remaining = args[1..].iter();
+        let mut result: Vec<String> = vec![];
+        for bit in 0..count {
+            if flags & (1 << bit) != 0 {
+                let arg = remaining.next().unwrap();
+                result.push(self.integer(arg)?);
+            } else {
+                result.push("undefined".to_owned());
+            }
+        }
+        while result.last().is_some_and(|arg| arg == "undefined") {
+            result.pop();
+        }
+        Ok(result)
+    }
+
+    fn print(&mut self, print: &Print, pos: Position) -> TranspileResult<()> {
+        if print.lpt1 {
+            return unsupported("LPRINT", pos);
+        }
+        if print.file_number.is_some() {
+            return unsupported("File I/O", pos);
+        }
+        if print.format_string.is_some() {
+            return unsupported("PRINT USING", pos);
+        }
+        for arg in &print.args {
+            match arg {
+                PrintArg::Comma => self.line("rt.printComma();"),
+                PrintArg::Semicolon => self.line("rt.printSemicolon();"),
+                PrintArg::Expression(e) => {
+                    let value = self.expression(e)?;
+                    let js_type = match e.expression_type() {
+                        ExpressionType::BuiltIn(q) => js_type(q),
+                        _ => js_type(TypeQualifier::DollarString),
+                    };
+                    self.line(format!("rt.printValue({}, {});", value, js_type));
+                }
+            }
+        }
+        self.line("rt.printEnd();");
+        Ok(())
+    }
+
+    fn select_case(&mut self, s: &SelectCase) -> TranspileResult<()> {
+        let selected = self.procedure.new_temp("s");
+        let value = self.expression(&s.expr)?;
+        self.line(format!("{} = {};", selected, value));
+        let end = self.procedure.new_case();
+        let count = s.case_blocks.len();
+        for (index, case_block) in s.case_blocks.iter().enumerate() {
+            let (case_expressions, statements) = case_block.into();
+            let is_last = index + 1 == count && s.else_block.is_none();
+            let next = if is_last {
+                end
+            } else {
+                self.procedure.new_case()
+            };
+            let mut conditions: Vec<String> = vec![];
+            for case_expression in case_expressions {
+                conditions.push(self.case_condition(&selected, case_expression)?);
+            }
+            self.procedure.jump_unless(&conditions.join(" || "), next);
+            self.block(statements)?;
+            if !is_last {
+                self.procedure.jump(end);
+                self.procedure.case(next);
+            }
+        }
+        if let Some(else_block) = &s.else_block {
+            self.block(else_block)?;
+        }
+        self.procedure.case(end);
+        Ok(())
+    }
+
+    fn case_condition(
+        &mut self,
+        selected: &str,
+        case_expression: &CaseExpression,
+    ) -> TranspileResult<String> {
+        match case_expression {
+            CaseExpression::Simple(e) => Ok(format!("{} === {}", selected, self.expression(e)?)),
+            CaseExpression::Is(op, e) => Ok(format!(
+                "{} {} {}",
+                selected,
+                comparison_operator(*op),
+                self.expression(e)?
+            )),
+            CaseExpression::Range(from, to) => Ok(format!(
+                "({} >= {} && {} <= {})",
+                selected,
+                self.expression(from)?,
+                selected,
+                self.expression(to)?
+            )),
+        }
+    }
+
+    fn for_loop(&mut self, f: &ForLoop) -> TranspileResult<()> {
+        let Expression::Variable(counter_name, counter_type) = &f.variable_name.element else {
+            return unsupported("FOR counter of a user defined type", f.variable_name.pos);
+        };
+        let counter = self.variable_place(counter_name, false, f.variable_name.pos)?;
+        let lower_bound = self.expression_casting(&f.lower_bound, counter_type)?;
+        let upper_bound = self.expression_casting(&f.upper_bound, counter_type)?;
+        let upper = self.procedure.new_temp("f");
+        self.line(format!("{} = {};", counter, lower_bound));
+        self.line(format!("{} = {};", upper, upper_bound));
+        let (is_done, step) = match &f.step {
+            Some(step) => {
+                let step_temp = self.procedure.new_temp("f");
+                let step = self.expression(step)?;
+                self.line(format!("{} = rt.forStep({});", step_temp, step));
+                (
+                    format!(
+                        "{} >= 0 ? {} > {} : {} < {}",
+                        step_temp, counter, upper, counter, upper
+                    ),
+                    step_temp,
+                )
+            }
+            None => (format!("{} > {}", counter, upper), "1".to_owned()),
+        };
+        let top = self.procedure.new_case();
+        let end = self.procedure.new_case();
+        self.procedure.case(top);
+        self.procedure.jump_if(&is_done, end);
+        self.block(&f.statements)?;
+        let next = arithmetic(&counter, "+", &step, counter_type);
+        self.line(format!("{} = {};", counter, next));
+        self.procedure.jump(top);
+        self.procedure.case(end);
+        Ok(())
+    }
+
+    /// Stores the given value into a variable or an array element.
+    fn store(&mut self, target: &ExpressionPos, value: String) -> TranspileResult<()> {
+        match &target.element {
+            Expression::Variable(name, _) => {
+                let place = self.variable_place(name, false, target.pos)?;
+                self.line(format!("{} = {};", place, value));
+            }
+            Expression::ArrayElement(name, indices, _) => {
+                let place = self.variable_place(name, true, target.pos)?;
+                let indices = self.expressions(indices)?;
+                self.line(format!(
+                    "{}.set([{}], {});",
+                    place,
+                    indices.join(", "),
+                    value
+                ));
+            }
+            _ => return unsupported("user defined type", target.pos),
+        }
+        Ok(())
+    }
+
+    /// Evaluates the arguments of a call to a SUB or FUNCTION.
+    ///
+    /// Variables and array elements are passed by reference,
+    /// by copying them into a temporary cell before the call
+    /// and back after the call.
+    fn call_args(
+        &mut self,
+        param_types: &[ExpressionType],
+        args: &Expressions,
+    ) -> TranspileResult<CallArgs> {
+        let mut call_args = CallArgs {
+            before: vec![],
+            args: vec![],
+            after: vec![],
+        };
+        for (param_type, arg) in param_types.iter().zip(args) {
+            let by_ref_place = match &arg.element {
+                Expression::Variable(name, expression_type) => {
+                    let is_array = matches!(expression_type, ExpressionType::Array(_));
+                    Some(self.variable_place(name, is_array, arg.pos)?)
+                }
+                Expression::ArrayElement(name, indices, _) if indices.is_empty() => {
+                    Some(self.variable_place(name, true, arg.pos)?)
+                }
+                Expression::ArrayElement(name, indices, _) => {
+                    let array = self.variable_place(name, true, arg.pos)?;
+                    let indices = self.expressions(indices)?;
+                    let indices_temp = self.procedure.new_temp("i");
+                    let temp = self.procedure.new_temp("a");
+                    call_args
+                        .before
+                        .push(format!("{} = [{}];", indices_temp, indices.join(", ")));
+                    call_args.before.push(format!(
+                        "{} = {{ v: {}.get({}) }};",
+                        temp, array, indices_temp
+                    ));
+                    call_args
+                        .after
+                        .push(format!("{}.set({}, {}.v);", array, indices_temp, temp));
+                    call_args.args.push(temp);
+                    continue;
+                }
+                Expression::Property(_, _, _) => {
+                    return unsupported("user defined type", arg.pos);
+                }
+                _ => None,
+            };
+            match by_ref_place {
+                Some(place) => {
+                    let temp = self.procedure.new_temp("a");
+                    call_args
+                        .before
+                        .push(format!("{} = {{ v: {} }};", temp, place));
+                    call_args.after.push(format!("{} = {}.v;", place, temp));
+                    call_args.args.push(temp);
+                }
+                None => {
+                    let value = self.expression_casting(arg, param_type)?;
+                    call_args.args.push(format!("{{ v: {} }}", value));
+                }
+            }
+        }
+        Ok(call_args)
+    }
+
+    /// Generates a JavaScript condition, i.e. a boolean expression.
+    fn condition(&mut self, e: &ExpressionPos) -> TranspileResult<String> {
+        if let Expression::BinaryExpression(op, left, right, _) = &e.element
+            && op.is_relational()
+        {
+            return Ok(format!(
+                "{} {} {}",
+                self.expression(left)?,
+                comparison_operator(*op),
+                self.expression(right)?
+            ));
+        }
+        Ok(format!("rt.isTrue({})", self.expression(e)?))
+    }
+
+    fn expressions(&mut self, expressions: &Expressions) -> TranspileResult<Vec<String>> {
+        expressions.iter().map(|e| self.expression(e)).collect()
+    }
+
+    /// Generates an expression that is cast to an integer, e.g. an array bound.
+    fn integer(&mut self, e: &ExpressionPos) -> TranspileResult<String> {
+        match e.expression_type() {
+            ExpressionType::BuiltIn(TypeQualifier::PercentInteger)
+            | ExpressionType::BuiltIn(TypeQualifier::AmpersandLong) => self.expression(e),
+            _ => self.expression_casting(e, &ExpressionType::BuiltIn(TypeQualifier::AmpersandLong)),
+        }
+    }
+
+    fn expression_casting(
+        &mut self,
+        e: &ExpressionPos,
+        target_type: &ExpressionType,
+    ) -> TranspileResult<String> {
+        let code = self.expression(e)?;
+        if e.expression_type() == *target_type {
+            return Ok(code);
+        }
+        Ok(match target_type {
+            ExpressionType::BuiltIn(TypeQualifier::DollarString) => code,
+            ExpressionType::BuiltIn(q) => format!("rt.cast({}, {})", code, js_type(*q)),
+            _ => fix_length(code, target_type),
+        })
+    }
+
+    fn expression(&mut self, expr_pos: &ExpressionPos) -> TranspileResult<String> {
+        let pos = expr_pos.pos;
+        let code = match &expr_pos.element {
+            Expression::SingleLiteral(f) => format!("{:?}", f),
+            Expression::DoubleLiteral(f) => format!("{:?}", f),
+            Expression::StringLiteral(s) => js_string(s),
+            Expression::IntegerLiteral(i) => i.to_string(),
+            Expression::LongLiteral(l) => l.to_string(),
+            Expression::Variable(name, expression_type) => {
+                let is_array = matches!(expression_type, ExpressionType::Array(_));
+                self.variable_place(name, is_array, pos)?
+            }
+            Expression::ArrayElement(name, indices, _) => {
+                let place = self.variable_place(name, true, pos)?;
+                if indices.is_empty() {
+                    place
+                } else {
+                    let indices = self.expressions(indices)?;
+                    format!("{}.get([{}])", place, indices.join(", "))
+                }
+            }
+            Expression::FunctionCall(name, args) => {
+                let param_types = self.functions[name.as_bare_name()].clone();
+                let call_args = self.call_args(&param_types, args)?;
+                let call = format!(
+                    "await {}({})",
+                    function_ident(name.as_bare_name()),
+                    call_args.args.join(", ")
+                );
+                if call_args.before.is_empty() {
+                    format!("({})", call)
+                } else {
+                    // a comma expression that copies the arguments back after the call
+                    let result = self.procedure.new_temp("r");
+                    let before: Vec<&str> = call_args
+                        .before
+                        .iter()
+                        .map(|s| s.trim_end_matches(';'))
+                        .collect();
+                    let after: Vec<&str> = call_args
+                        .after
+                        .iter()
+                        .map(|s| s.trim_end_matches(';'))
+                        .collect();
+                    format!(
+                        "({}, {} = {}, {}, {})",
+                        before.join(", "),
+                        result,
+                        call,
+                        after.join(", "),
+                        result
+                    )
+                }
+            }
+            Expression::BuiltInFunctionCall(f, args) => {
+                self.built_in_function_call(*f, args, pos)?
+            }
+            Expression::BinaryExpression(op, left, right, expression_type) => {
+                let left = self.expression(left)?;
+                let right = self.expression(right)?;
+                match op {
+                    Operator::Plus => arithmetic(&left, "+", &right, expression_type),
+                    Operator::Minus => arithmetic(&left, "-", &right, expression_type),
+                    Operator::Multiply => arithmetic(&left, "*", &right, expression_type),
+                    Operator::Divide => {
+                        // like the interpreter, the division of integers is a single
+                        let js_type = match expression_type {
+                            ExpressionType::BuiltIn(TypeQualifier::HashDouble) => {
+                                js_type(TypeQualifier::HashDouble)
+                            }
+                            _ => js_type(TypeQualifier::BangSingle),
+                        };
+                        format!("rt.divide({}, {}, {})", left, right, js_type)
+                    }
+                    Operator::Modulo => format!("rt.modulo({}, {})", left, right),
+                    Operator::And => format!("rt.and({}, {})", left, right),
+                    Operator::Or => format!("rt.or({}, {})", left, right),
+                    _ => format!("rt.bool({} {} {})", left, comparison_operator(*op), right),
+                }
+            }
+            Expression::UnaryExpression(op, child) => {
+                let child = self.expression(child)?;
+                match op {
+                    UnaryOperator::Not => format!("rt.not({})", child),
+                    UnaryOperator::Minus => format!("(-{})", child),
+                }
+            }
+            Expression::Parenthesis(child) => self.expression(child)?,
+            Expression::Property(_, _, _) => return unsupported("user defined type", pos),
+        };
+        Ok(code)
+    }
+
+    fn built_in_function_call(
+        &mut self,
+        f: BuiltInFunction,
+        args: &Expressions,
+        pos: Position,
+    ) -> TranspileResult<String> {
+        let code = match f {
+            BuiltInFunction::LBound | BuiltInFunction::UBound => {
+                let Expression::Variable(name, _) = &args[0].element else {
+                    return unsupported("user defined type", args[0].pos);
+                };
+                let array = self.variable_place(name, true, args[0].pos)?;
+                let dimension = match args.get(1) {
+                    Some(arg) => self.integer(arg)?,
+                    None => "1".to_owned(),
+                };
+                let method = if f == BuiltInFunction::LBound {
+                    "lbound"
+                } else {
+                    "ubound"
+                };
+                format!("{}.{}({})", array, method, dimension)
+            }
+            BuiltInFunction::Len => match args[0].expression_type() {
+                ExpressionType::BuiltIn(TypeQualifier::DollarString)
+                | ExpressionType::FixedLengthString(_) => {
+                    format!("{}.length", self.expression(&args[0])?)
+                }
+                // the size of a number depends only on its type
+                ExpressionType::BuiltIn(TypeQualifier::PercentInteger) => "2".to_owned(),
+                ExpressionType::BuiltIn(TypeQualifier::AmpersandLong)
+                | ExpressionType::BuiltIn(TypeQualifier::BangSingle) => "4".to_owned(),
+                ExpressionType::BuiltIn(TypeQualifier::HashDouble) => "8".to_owned(),
+                _ => return unsupported("user defined type", args[0].pos),
+            },
+            BuiltInFunction::Str => {
+                let js_type = js_type(self.qualifier_of(&args[0])?);
+                format!("rt.str({}, {})", self.expression(&args[0])?, js_type)
+            }
+            BuiltInFunction::InKey => "(await rt.inkey())".to_owned(),
+            BuiltInFunction::Command => "rt.command()".to_owned(),
+            BuiltInFunction::Chr
+            | BuiltInFunction::InStr
+            | BuiltInFunction::LCase
+            | BuiltInFunction::Left
+            | BuiltInFunction::LTrim
+            | BuiltInFunction::Mid
+            | BuiltInFunction::Right
+            | BuiltInFunction::RTrim
+            | BuiltInFunction::Space
+            | BuiltInFunction::String
+            | BuiltInFunction::UCase
+            | BuiltInFunction::Val => {
+                let mut js_args: Vec<String> = vec![];
+                // INSTR starts at the first character by default
+                if f == BuiltInFunction::InStr && args.len() == 2 {
+                    js_args.push("1".to_owned());
+                }
+                for arg in args {
+                    let code = match arg.expression_type() {
+                        ExpressionType::BuiltIn(TypeQualifier::DollarString)
+                        | ExpressionType::FixedLengthString(_) => self.expression(arg)?,
+                        _ => self.integer(arg)?,
+                    };
+                    js_args.push(code);
+                }
+                let method = format!("{:?}", f).to_lowercase();
+                format!("rt.{}({})", method, js_args.join(", "))
+            }
+            _ => return unsupported(format!("{:?}", f).to_uppercase(), pos),
+        };
+        Ok(code)
+    }
+
+    fn qualifier_of(&self, variable: &ExpressionPos) -> TranspileResult<TypeQualifier> {
+        match variable.expression_type() {
+            ExpressionType::BuiltIn(q) => Ok(q),
+            ExpressionType::FixedLengthString(_) => Ok(TypeQualifier::DollarString),
+            _ => unsupported("user defined type", variable.pos),
+        }
+    }
+
+    fn variable_place(
+        &self,
+        name: &Name,
+        is_array: bool,
+        pos: Position,
+    ) -> TranspileResult<String> {
+        if let Some(function_name) = &self.procedure.function_name
+            && !is_array
+            && name.as_bare_name() == function_name
+        {
+            return Ok("result".to_owned());
+        }
+        match name.qualifier() {
+            Some(q) => Ok(self.place(name.as_bare_name(), q, is_array)),
+            None => unsupported("user defined type", pos),
+        }
+    }
+
+    /// Gets the JavaScript place expression of a variable,
+    /// which is the value of the cell for parameters.
+    fn place(&self, bare_name: &BareName, q: TypeQualifier, is_array: bool) -> String {
+        let ident = variable_ident(bare_name, q, is_array);
+        if self.procedure.params.contains(&ident) {
+            format!("{}.v", ident)
+        } else {
+            ident
+        }
+    }
+}
+
+fn param_types(params: &Parameters) -> Vec<ExpressionType> {
+    params
+        .iter()
+        .map(|param| param.element.expression_type())
+        .collect()
+}
+
+fn param_names(params: &Parameters) -> TranspileResult<Vec<String>> {
+    params
+        .iter()
+        .map(
+            |Positioned {
+                 element: param,
+                 pos,
+             }| match param.var_type() {
+                ParamType::BuiltIn(q, _) => Ok(variable_ident(param.as_bare_name(), *q, false)),
+                ParamType::Array(element_type) => match element_type.as_ref() {
+                    ParamType::BuiltIn(q, _) => Ok(variable_ident(param.as_bare_name(), *q, true)),
+                    _ => unsupported("user defined type", *pos),
+                },
+                _ => unsupported("user defined type", *pos),
+            },
+        )
+        .collect()
+}
+
+fn is_built_in_sub_call(statement: &Statement, sub: BuiltInSub) -> bool {
+    matches!(statement, Statement::BuiltInSubCall(b) if *b.built_in_sub() == sub)
+}
+
+/// Collects the labels of the given statements, including nested blocks.
+fn collect_labels(statements: &Statements, labels: &mut Vec<BareName>) {
+    for statement in statements {
+        match &statement.element {
+            Statement::Label(label) => labels.push(label.clone()),
+            Statement::IfBlock(i) => {
+                collect_labels(&i.if_block.statements, labels);
+                for else_if_block in &i.else_if_blocks {
+                    collect_labels(&else_if_block.statements, labels);
+                }
+                if let Some(else_block) = &i.else_block {
+                    collect_labels(else_block, labels);
+                }
+            }
+            Statement::SelectCase(s) => {
+                for case_block in &s.case_blocks {
+                    let (_, statements) = case_block.into();
+                    collect_labels(statements, labels);
+                }
+                if let Some(else_block) = &s.else_block {
+                    collect_labels(else_block, labels);
+                }
+            }
+            Statement::ForLoop(f) => collect_labels(&f.statements, labels),
+            Statement::While(w) => collect_labels(&w.statements, labels),
+            Statement::DoLoop(d) => collect_labels(&d.statements, labels),
+            _ => {}
+        }
+    }
+}
+
+/// Collects the variables that are declared with `DIM` or `REDIM` in the given
+/// statements, including nested blocks, together with their initial value.
+///
+/// The linter has added a `DIM` for every implicitly declared variable.
+fn declared_variables(statements: &Statements) -> TranspileResult<Vec<(String, String)>> {
+    let mut result: Vec<(String, String)> = vec![];
+    collect_declared_variables(statements, &mut result)?;
+    Ok(result)
+}
+
+fn collect_declared_variables(
+    statements: &Statements,
+    result: &mut Vec<(String, String)>,
+) -> TranspileResult<()> {
+    for statement in statements {
+        match &statement.element {
+            Statement::Dim(dim_list) | Statement::Redim(dim_list) => {
+                for Positioned {
+                    element: dim_var,
+                    pos,
+                } in &dim_list.variables
+                {
+                    let (ident, initial_value) = match dim_var.var_type() {
+                        DimType::BuiltIn(q, _) => (
+                            variable_ident(dim_var.as_bare_name(), *q, false),
+                            initial_value(*q),
+                        ),
+                        DimType::FixedLengthString(_, len) => (
+                            variable_ident(
+                                dim_var.as_bare_name(),
+                                TypeQualifier::DollarString,
+                                false,
+                            ),
+                            fixed_length_string(*len),
+                        ),
+                        DimType::Array(_, element_type) => {
+                            let q = match element_type.as_ref() {
+                                DimType::BuiltIn(q, _) => *q,
+                                DimType::FixedLengthString(_, _) => TypeQualifier::DollarString,
+                                _ => return unsupported("user defined type", *pos),
+                            };
+                            (
+                                variable_ident(dim_var.as_bare_name(), q, true),
+                                "null".to_owned(),
+                            )
+                        }
+                        _ => return unsupported("user defined type", *pos),
+                    };
+                    if !result.iter().any(|(existing, _)| *existing == ident) {
+                        result.push((ident, initial_value));
+                    }
+                }
+            }
+            Statement::IfBlock(i) => {
+                collect_declared_variables(&i.if_block.statements, result)?;
+                for else_if_block in &i.else_if_blocks {
+                    collect_declared_variables(&else_if_block.statements, result)?;
+                }
+                if let Some(else_block) = &i.else_block {
+                    collect_declared_variables(else_block, result)?;
+                }
+            }
+            Statement::SelectCase(s) => {
+                for case_block in &s.case_blocks {
+                    let (_, statements) = case_block.into();
+                    collect_declared_variables(statements, result)?;
+                }
+                if let Some(else_block) = &s.else_block {
+                    collect_declared_variables(else_block, result)?;
+                }
+            }
+            Statement::ForLoop(f) => collect_declared_variables(&f.statements, result)?,
+            Statement::While(w) => collect_declared_variables(&w.statements, result)?,
+            Statement::DoLoop(d) => collect_declared_variables(&d.statements, result)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Generates `+`, `-` or `*`, rounding the result to a single if needed.
+fn arithmetic(left: &str, operator: &str, right: &str, result_type: &ExpressionType) -> String {
+    match result_type {
+        ExpressionType::BuiltIn(TypeQualifier::BangSingle) => {
+            format!("Math.fround({} {} {})", left, operator, right)
+        }
+        ExpressionType::BuiltIn(
+            q @ (TypeQualifier::PercentInteger | TypeQualifier::AmpersandLong),
+        ) => {
+            format!(
+                "rt.checkOverflow({} {} {}, {})",
+                left,
+                operator,
+                right,
+                js_type(*q)
+            )
+        }
+        _ => format!("({} {} {})", left, operator, right),
+    }
+}
+
+/// Truncates or pads the given value, if the target is a fixed length string.
+fn fix_length(code: String, target_type: &ExpressionType) -> String {
+    match target_type {
+        ExpressionType::FixedLengthString(len) => format!("rt.fixLength({}, {})", code, len),
+        _ => code,
+    }
+}
+
+fn fixed_length_string(len: u16) -> String {
+    format!("\" \".repeat({})", len)
+}
+
+fn comparison_operator(op: Operator) -> &'static str {
+    match op {
+        Operator::Less => "<",
+        Operator::LessOrEqual => "<=",
+        Operator::Equal => "===",
+        Operator::GreaterOrEqual => ">=",
+        Operator::Greater => ">",
+        Operator::NotEqual => "!==",
+        _ => panic!("Not a comparison operator {:?}", op),
+    }
+}
+
+fn initial_value(q: TypeQualifier) -> String {
+    match q {
+        TypeQualifier::DollarString => "\"\"".to_owned(),
+        _ => "0".to_owned(),
+    }
+}
+
+/// The name of a type in the runtime.
+fn js_type(q: TypeQualifier) -> &'static str {
+    match q {
+        TypeQualifier::BangSingle => "\"sng\"",
+        TypeQualifier::HashDouble => "\"dbl\"",
+        TypeQualifier::DollarString => "\"str\"",
+        TypeQualifier::PercentInteger => "\"int\"",
+        TypeQualifier::AmpersandLong => "\"lng\"",
+    }
+}
+
+fn js_string(s: &str) -> String {
+    let mut result = String::from('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            ' '..='~' => result.push(ch),
+            _ => result.push_str(&format!("\\u{:04x}", ch as u32)),
+        }
+    }
+    result.push('"');
+    result
+}
+
+fn js_name(bare_name: &BareName) -> String {
+    bare_name.to_ascii_lowercase().replace('.', "_")
+}
+
+/// The name of a variable, e.g. `v_count_int` for `Count%`
+/// or `a_names_str` for the array `Names$()`.
+fn variable_ident(bare_name: &BareName, q: TypeQualifier, is_array: bool) -> String {
+    let suffix = match q {
+        TypeQualifier::BangSingle => "sng",
+        TypeQualifier::HashDouble => "dbl",
+        TypeQualifier::DollarString => "str",
+        TypeQualifier::PercentInteger => "int",
+        TypeQualifier::AmpersandLong => "lng",
+    };
+    let prefix = if is_array { "a" } else { "v" };
+    format!("{}_{}_{}", prefix, js_name(bare_name), suffix)
+}
+
+fn function_ident(bare_name: &BareName) -> String {
+    format!("fn_{}", js_name(bare_name))
+}
+
+fn sub_ident(bare_name: &BareName) -> String {
+    format!("sub_{}", js_name(bare_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use rusty_common::Position;
+    use rusty_linter::core::lint;
+    use rusty_parser::parse;
+
+    use super::*;
+
+    fn transpile_str(input: &str) -> Result<String, TranspileErrorPos> {
+        let (program, _) = lint(parse(input)).expect("Linter should succeed");
+        transpile_to_js(program).map(|m| m.program_js)
+    }
+
+    /// Compares the transpiled fixture with its golden file.
+    /// Set `UPDATE_GOLDEN=1` to update the golden file instead.
+    fn assert_golden(fixture: &str) {
+        let input = std::fs::read_to_string(format!("../fixtures/{}.BAS", fixture)).unwrap();
+        let actual = transpile_str(&input).unwrap();
+        let golden_path = format!("src/transpiler/js/golden/{}.js", fixture);
+        if std::env::var("UPDATE_GOLDEN").is_ok() {
+            std::fs::write(&golden_path, &actual).unwrap();
+        }
+        let expected = std::fs::read_to_string(&golden_path).unwrap();
+        assert_eq!(actual, expected, "{}", fixture);
+    }
+
+    fn assert_contains(program_js: &str, lines: &[&str]) {
+        let actual: Vec<&str> = program_js.lines().map(str::trim).collect();
+        for line in lines {
+            assert!(
+                actual.contains(line),
+                "Expected line `{}` in:\n{}",
+                line,
+                program_js
+            );
+        }
+    }
+
+    #[test]
+    fn test_golden_fib() {
+        assert_golden("FIB");
+    }
+
+    #[test]
+    fn test_golden_locate() {
+        assert_golden("LOCATE");
+    }
+
+    #[test]
+    fn test_straight_code_has_no_dispatch_loop() {
+        let program_js = transpile_str("A$ = \"hi\"\nPRINT A$").unwrap();
+        assert_eq!(
+            program_js,
+            "// Transpiled from QBasic by rusty_basic. Do not edit.
+import { Halt, QArray } from \"./runtime.js\";
+
+export async function main(rt) {
+    let v_a_str = \"\";
+
+    v_a_str = \"\";
+    v_a_str = \"hi\";
+    rt.printValue(v_a_str, \"str\");
+    rt.printEnd();
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_gosub_and_return() {
+        let input = "
+        GOSUB Hello
+        END
+        Hello:
+        PRINT \"hello\"
+        RETURN
+        ";
+        let program_js = transpile_str(input).unwrap();
+        assert_contains(
+            &program_js,
+            &[
+                "const returns = [];",
+                "returns.push(2);",
+                "pc = 1; continue;",
+                "case 2:",
+                "throw new Halt();",
+                "case 1:",
+                "if (returns.length === 0) { throw rt.error(3); }",
+                "pc = returns.pop(); continue;",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_goto_into_block() {
+        let input = "
+        GOTO Inside
+        FOR I = 1 TO 2
+        Inside:
+        PRINT I
+        NEXT
+        ";
+        let program_js = transpile_str(input).unwrap();
+        assert_contains(
+            &program_js,
+            &["pc = 1; continue;", "case 1:", "let f0 = 0;"],
+        );
+    }
+
+    #[test]
+    fn test_sub_parameters_are_copied_in_and_out() {
+        let input = "
+        DECLARE SUB Inc(N%)
+        X% = 1
+        Inc X%
+        Inc 2
+        SUB Inc(N%)
+            N% = N% + 1
+        END SUB
+        ";
+        let program_js = transpile_str(input).unwrap();
+        assert_contains(
+            &program_js,
+            &[
+                "async function sub_inc(v_n_int) {",
+                "v_n_int.v = rt.checkOverflow(v_n_int.v + 1, \"int\");",
+                "a0 = { v: v_x_int };",
+                "await sub_inc(a0);",
+                "v_x_int = a0.v;",
+                "await sub_inc({ v: 2 });",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_color_and_locate() {
+        let program_js = transpile_str("COLOR , 1\nLOCATE 2\nLOCATE , , 0").unwrap();
+        assert_contains(
+            &program_js,
+            &[
+                "rt.color(undefined, 1);",
+                "rt.locate(2);",
+                "rt.locate(undefined, undefined, 0);",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_file_io_is_an_error() {
+        let input = "PRINT 1\nOPEN \"A.TXT\" FOR INPUT AS #1";
+        assert_eq!(
+            transpile_str(input),
+            Err(TranspileError::Unsupported("File I/O".to_owned()).at_pos(Position::new(2, 1)))
+        );
+    }
+
+    #[test]
+    fn test_print_using_is_an_error() {
+        let input = "PRINT USING \"##\"; 1";
+        assert_eq!(
+            transpile_str(input),
+            Err(TranspileError::Unsupported("PRINT USING".to_owned()).at_pos(Position::new(1, 1)))
+        );
+    }
+}
@@ -0,0 +1,558 @@
+// The runtime of QBasic programs that have been transpiled to JavaScript.
+//
+// The screen is a text grid of 25 rows and 80 columns, where every cell
+// has a character, a foreground and a background color. The host (e.g. a web
+// page) renders the grid and provides the keyboard input.
+//
+// Numbers are JavaScript numbers and strings are JavaScript strings.
+// The type of a value is given as "int", "lng", "sng", "dbl" or "str".
+
+export const ROWS = 25;
+export const COLUMNS = 80;
+const ZONE_WIDTH = 14;
+
+const MESSAGES = {
+    3: "RETURN without GOSUB",
+    4: "Out of DATA",
+    5: "Illegal function call",
+    6: "Overflow",
+    9: "Subscript out of range",
+    11: "Division by zero",
+    13: "Type mismatch",
+    258: "FOR loop with zero STEP",
+};
+
+// An unhandled runtime error, with its QBasic error code.
+export class QBasicError extends Error {
+    constructor(code, message) {
+        super(message ?? MESSAGES[code] ?? `Error ${code}`);
+        this.code = code;
+    }
+}
+
+// Thrown by END, SYSTEM and STOP.
+export class Halt {}
+
+// The 16 colors of the CGA palette.
+export const PALETTE = [
+    "#000000", "#0000aa", "#00aa00", "#00aaaa", "#aa0000", "#aa00aa", "#aa5500", "#aaaaaa",
+    "#555555", "#5555ff", "#55ff55", "#55ffff", "#ff5555", "#ff55ff", "#ffff55", "#ffffff",
+];
+
+export class TextScreen {
+    constructor(rows = ROWS, columns = COLUMNS) {
+        this.rows = rows;
+        this.columns = columns;
+        this.foreground = 7;
+        this.background = 0;
+        this.cursorVisible = true;
+        this.cls();
+    }
+
+    cls() {
+        this.cells = [];
+        for (let row = 0; row < this.rows; row++) {
+            this.cells.push(this.emptyRow());
+        }
+        this.row = 0;
+        this.col = 0;
+    }
+
+    emptyRow() {
+        const cells = [];
+        for (let col = 0; col < this.columns; col++) {
+            cells.push({ ch: " ", fg: this.foreground, bg: this.background });
+        }
+        return cells;
+    }
+
+    // Moves the cursor to the given 1 based row and column.
+    locate(row, col) {
+        if (row < 1 || row > this.rows || col < 1 || col > this.columns) {
+            throw new QBasicError(5);
+        }
+        this.row = row - 1;
+        this.col = col - 1;
+    }
+
+    color(foreground, background) {
+        if (foreground !== undefined) {
+            this.foreground = foreground;
+        }
+        if (background !== undefined) {
+            this.background = background;
+        }
+    }
+
+    write(s) {
+        for (const ch of s) {
+            if (this.col >= this.columns) {
+                this.newLine();
+            }
+            this.cells[this.row][this.col] = { ch, fg: this.foreground, bg: this.background };
+            this.col++;
+        }
+    }
+
+    newLine() {
+        this.col = 0;
+        this.row++;
+        if (this.row >= this.rows) {
+            this.cells.shift();
+            this.cells.push(this.emptyRow());
+            this.row = this.rows - 1;
+        }
+    }
+
+    moveToNextZone() {
+        const col = (Math.floor(this.col / ZONE_WIDTH) + 1) * ZONE_WIDTH;
+        if (col >= this.columns) {
+            this.newLine();
+        } else {
+            this.col = col;
+        }
+    }
+
+    // The text of the screen, without trailing spaces and empty lines.
+    lines() {
+        const lines = this.cells.map((cells) => cells.map((cell) => cell.ch).join("").trimEnd());
+        while (lines.length > 0 && lines[lines.length - 1] === "") {
+            lines.pop();
+        }
+        return lines;
+    }
+
+    toString() {
+        return this.lines().join("\n");
+    }
+
+    // The screen as HTML, with a span for every run of cells of the same colors.
+    toHtml() {
+        const escape = (ch) => ({ "<": "&lt;", ">": "&gt;", "&": "&amp;" })[ch] ?? ch;
+        let html = "";
+        for (const cells of this.cells) {
+            let run = null;
+            for (const cell of cells) {
+                if (!run || run.fg !== cell.fg || run.bg !== cell.bg) {
+                    if (run) {
+                        html += "</span>";
+                    }
+                    run = cell;
+                    html += `<span style="color:${PALETTE[cell.fg & 15]};background:${PALETTE[cell.bg & 7]}">`;
+                }
+                html += escape(cell.ch);
+            }
+            html += "</span>\n";
+        }
+        return html;
+    }
+}
+
+// A QBasic array, which has a lower and an upper bound for each dimension.
+export class QArray {
+    constructor(bounds, value) {
+        this.bounds = bounds;
+        let size = 1;
+        for (const [lbound, ubound] of bounds) {
+            size *= Math.max(0, ubound - lbound + 1);
+        }
+        this.values = new Array(size).fill(value);
+    }
+
+    index(indices) {
+        if (indices.length !== this.bounds.length) {
+            throw new QBasicError(9);
+        }
+        let index = 0;
+        for (let i = 0; i < indices.length; i++) {
+            const [lbound, ubound] = this.bounds[i];
+            const n = round(indices[i]);
+            if (n < lbound || n > ubound) {
+                throw new QBasicError(9);
+            }
+            index = index * (ubound - lbound + 1) + n - lbound;
+        }
+        return index;
+    }
+
+    get(indices) {
+        return this.values[this.index(indices)];
+    }
+
+    set(indices, value) {
+        this.values[this.index(indices)] = value;
+    }
+
+    // LBOUND, where the dimension is 1 based.
+    lbound(dimension) {
+        return this.dimension(dimension)[0];
+    }
+
+    // UBOUND, where the dimension is 1 based.
+    ubound(dimension) {
+        return this.dimension(dimension)[1];
+    }
+
+    dimension(dimension) {
+        const bounds = this.bounds[round(dimension) - 1];
+        if (!bounds) {
+            throw new QBasicError(9);
+        }
+        return bounds;
+    }
+}
+
+// Rounds half away from zero, like the interpreter.
+function round(n) {
+    return Math.sign(n) * Math.round(Math.abs(n));
+}
+
+// Formats a number like PRINT and STR$ do, without the leading space.
+function formatNumber(n, type) {
+    // the division of integers results in a single
+    if (type !== "dbl" && !Number.isInteger(n)) {
+        // the shortest representation that converts back to the same single
+        for (let precision = 1; precision <= 9; precision++) {
+            const s = Number(n.toPrecision(precision));
+            if (Math.fround(s) === n) {
+                return String(s);
+            }
+        }
+    }
+    return String(n);
+}
+
+export class Runtime {
+    // `readLine` is an async function that returns a line of input,
+    // `onUpdate` is called with the screen whenever it changes.
+    constructor({ screen = new TextScreen(), readLine = null, onUpdate = null, commandLine = "" } = {}) {
+        this.screen = screen;
+        this.readLine = readLine;
+        this.onUpdate = onUpdate;
+        this.commandLine = commandLine;
+        this.data = [];
+        this.keys = [];
+        this.skipNewLine = false;
+    }
+
+    error(code) {
+        return new QBasicError(code);
+    }
+
+    update() {
+        if (this.onUpdate) {
+            this.onUpdate(this.screen);
+        }
+    }
+
+    // values
+
+    cast(v, type) {
+        if (typeof v === "string" || type === "str") {
+            if (typeof v !== "string" || type !== "str") {
+                throw new QBasicError(13);
+            }
+            return v;
+        }
+        switch (type) {
+            case "int":
+                return this.checkRange(round(v), -32768, 32767);
+            case "lng":
+                return this.checkRange(round(v), -2147483648, 2147483647);
+            case "sng":
+                return Math.fround(v);
+            default:
+                return v;
+        }
+    }
+
+    // Checks that the result of an arithmetic operation fits its type,
+    // as the operands are promoted to the type of the result (e.g. `32767 + 1`).
+    checkOverflow(n, type) {
+        switch (type) {
+            case "int":
+                return this.checkRange(n, -32768, 32767);
+            case "lng":
+                return this.checkRange(n, -2147483648, 2147483647);
+            default:
+                return n;
+        }
+    }
+
+    checkRange(n, min, max) {
+        if (n < min || n > max) {
+            throw new QBasicError(6);
+        }
+        return n;
+    }
+
+    // Truncates or pads the string to the length of a fixed length string.
+    fixLength(s, length) {
+        return s.length >= length ? s.substring(0, length) : s.padEnd(length, " ");
+    }
+
+    isTrue(v) {
+        return v !== 0;
+    }
+
+    bool(b) {
+        return b ? -1 : 0;
+    }
+
+    divide(a, b, type) {
+        if (b === 0) {
+            throw new QBasicError(11);
+        }
+        return type === "sng" ? Math.fround(a / b) : a / b;
+    }
+
+    modulo(a, b) {
+        const divisor = round(b);
+        if (divisor === 0) {
+            throw new QBasicError(11);
+        }
+        return round(a) % divisor;
+    }
+
+    and(a, b) {
+        return this.cast(a, "lng") & this.cast(b, "lng");
+    }
+
+    or(a, b) {
+        return this.cast(a, "lng") | this.cast(b, "lng");
+    }
+
+    not(a) {
+        return ~this.cast(a, "lng");
+    }
+
+    forStep(step) {
+        if (step === 0) {
+            throw new QBasicError(258);
+        }
+        return step;
+    }
+
+    // DATA and READ
+
+    pushData(v) {
+        this.data.push(v);
+    }
+
+    read(type) {
+        if (this.data.length === 0) {
+            throw new QBasicError(4);
+        }
+        return this.cast(this.data.shift(), type);
+    }
+
+    // PRINT
+
+    printValue(v, type) {
+        this.skipNewLine = false;
+        if (typeof v === "string") {
+            this.screen.write(v);
+        } else {
+            this.screen.write(`${v >= 0 ? " " : ""}${formatNumber(v, type)} `);
+        }
+    }
+
+    printComma() {
+        this.skipNewLine = true;
+        this.screen.moveToNextZone();
+    }
+
+    printSemicolon() {
+        this.skipNewLine = true;
+    }
+
+    printEnd() {
+        if (!this.skipNewLine) {
+            this.screen.newLine();
+        }
+        this.skipNewLine = false;
+        this.update();
+    }
+
+    // INPUT
+
+    async lineInput() {
+        if (!this.readLine) {
+            throw new QBasicError(257, "Input is not available");
+        }
+        this.screen.write("? ");
+        this.update();
+        const line = await this.readLine();
+        this.screen.write(line);
+        this.screen.newLine();
+        this.update();
+        return line;
+    }
+
+    async input(type) {
+        const line = (await this.lineInput()).trim();
+        if (type === "str") {
+            return line;
+        }
+        if (line === "") {
+            return 0;
+        }
+        const n = Number(line);
+        if (Number.isNaN(n)) {
+            throw new QBasicError(257, `Could not parse ${line} as a number`);
+        }
+        return this.cast(n, type);
+    }
+
+    // Adds a key to the keyboard buffer, which INKEY$ reads.
+    pushKey(key) {
+        this.keys.push(key);
+    }
+
+    // INKEY$ waits for the next turn of the event loop,
+    // so that loops that poll the keyboard do not block the host.
+    async inkey() {
+        this.update();
+        await new Promise((resolve) => setTimeout(resolve, 0));
+        return this.keys.shift() ?? "";
+    }
+
+    // screen
+
+    cls() {
+        this.screen.cls();
+        this.update();
+    }
+
+    color(foreground, background) {
+        this.screen.color(foreground, background);
+    }
+
+    locate(row, col, cursor) {
+        if (row !== undefined) {
+            this.screen.locate(row, col ?? 1);
+        } else if (col !== undefined) {
+            // the current row is unknown to QBasic
+            throw new QBasicError(5);
+        }
+        if (cursor !== undefined) {
+            if (cursor !== 0 && cursor !== 1) {
+                throw new QBasicError(5);
+            }
+            this.screen.cursorVisible = cursor === 1;
+        }
+    }
+
+    beep() {}
+
+    // functions
+
+    chr(code) {
+        if (code < 0 || code > 255) {
+            throw new QBasicError(5);
+        }
+        return String.fromCharCode(code);
+    }
+
+    command() {
+        return this.commandLine.toUpperCase();
+    }
+
+    instr(start, hay, needle) {
+        if (start < 1) {
+            throw new QBasicError(5);
+        }
+        if (hay === "") {
+            return 0;
+        }
+        if (needle === "") {
+            return 1;
+        }
+        return hay.indexOf(needle, start - 1) + 1;
+    }
+
+    lcase(s) {
+        return s.replace(/[A-Z]/g, (ch) => ch.toLowerCase());
+    }
+
+    ucase(s) {
+        return s.replace(/[a-z]/g, (ch) => ch.toUpperCase());
+    }
+
+    left(s, count) {
+        return s.substring(0, this.nonNegative(count));
+    }
+
+    right(s, count) {
+        const n = this.nonNegative(count);
+        return n >= s.length ? s : s.substring(s.length - n);
+    }
+
+    mid(s, start, length) {
+        if (start < 1) {
+            throw new QBasicError(5);
+        }
+        return length === undefined
+            ? s.substring(start - 1)
+            : s.substring(start - 1, start - 1 + this.nonNegative(length));
+    }
+
+    ltrim(s) {
+        return s.replace(/^ +/, "");
+    }
+
+    rtrim(s) {
+        return s.replace(/ +$/, "");
+    }
+
+    space(count) {
+        return " ".repeat(Math.max(0, count));
+    }
+
+    str(n, type) {
+        return `${n >= 0 ? " " : ""}${formatNumber(n, type)}`;
+    }
+
+    string(count, v) {
+        const ch = typeof v === "string" ? v.charAt(0) : this.chr(v);
+        if (ch === "") {
+            throw new QBasicError(5);
+        }
+        return ch.repeat(this.nonNegative(count));
+    }
+
+    val(s) {
+        const match = /^[ \t]*[+-]?[0-9 ]*(\.[0-9 ]*)?/.exec(s);
+        const n = Number(match[0].replace(/[ \t]/g, ""));
+        return Number.isNaN(n) ? 0 : n;
+    }
+
+    nonNegative(n) {
+        if (n < 0) {
+            throw new QBasicError(5);
+        }
+        return n;
+    }
+}
+
+// Runs the given transpiled program, returning the exit code:
+// 0 when the program ends normally, otherwise the code of the error.
+export async function run(main, runtime = new Runtime()) {
+    try {
+        await main(runtime);
+        return 0;
+    } catch (e) {
+        if (e instanceof Halt) {
+            return 0;
+        }
+        if (e instanceof QBasicError) {
+            runtime.screen.write(`error: ${e.message}`);
+            runtime.screen.newLine();
+            runtime.update();
+            return e.code;
+        }
+        throw e;
+    } finally {
+        runtime.update();
+    }
+}
@@ -0,0 +1,191 @@
+//! Transpiles linted programs into the source code of other languages.
+
+mod js;
+mod rust;
+
+use std::fmt::Display;
+
+use rusty_common::Positioned;
+
+pub use self::js::*;
+pub use self::rust::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TranspileError {
+    /// A statement or a function that the transpiler does not support.
+    Unsupported(String),
+
+    /// A `GOTO` to a label inside a block (e.g. a loop)
+    /// that does not also contain the `GOTO` (only in Rust).
+    GoToIntoBlock,
+}
+
+pub type TranspileErrorPos = Positioned<TranspileError>;
+
+impl Display for TranspileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unsupported(what) => write!(f, "{} is not supported", what),
+            Self::GoToIntoBlock => f.write_str("GOTO into a block is not supported"),
+        }
+    }
+}
+
+/// Collects the generated source code, one indented line at a time.
+#[derive(Default)]
+struct CodeWriter {
+    buf: String,
+    indentation: usize,
+}
+
+impl CodeWriter {
+    fn with_indentation(indentation: usize) -> Self {
+        Self {
+            buf: String::new(),
+            indentation,
+        }
+    }
+
+    fn line(&mut self, line: impl AsRef<str>) {
+        let line = line.as_ref();
+        if !line.is_empty() {
+            for _ in 0..self.indentation {
+                self.buf.push_str("    ");
+            }
+        }
+        self.buf.push_str(line);
+        self.buf.push('\n');
+    }
+
+    /// Writes the given line, which opens a block, and indents the following lines.
+    fn open(&mut self, line: impl AsRef<str>) {
+        self.line(line);
+        self.indentation += 1;
+    }
+
+    /// Writes a line one level to the left, e.g. a `case` label.
+    fn label(&mut self, line: impl AsRef<str>) {
+        self.indentation -= 1;
+        self.line(line);
+        self.indentation += 1;
+    }
+
+    /// Appends the code of another writer, indented by the current indentation.
+    fn append(&mut self, code: &str) {
+        for line in code.lines() {
+            self.line(line);
+        }
+    }
+
+    /// Writes a line that closes a block and opens the next one, e.g. `} else {`.
+    fn reopen(&mut self, line: impl AsRef<str>) {
+        self.indentation -= 1;
+        self.open(line);
+    }
+
+    /// Closes a block that was opened with [CodeWriter::open].
+    fn close(&mut self, line: impl AsRef<str>) {
+        self.indentation -= 1;
+        self.line(line);
+    }
+
+    fn into_string(self) -> String {
+        self.buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::process::{Command, Output};
+
+    use rusty_linter::core::lint;
+    use rusty_parser::parse;
+
+    use super::*;
+    use crate::interpreter::InterpreterTrait;
+    use crate::interpreter::test_utils::mock_interpreter_for_input;
+
+    /// The addition overflows, as `A% + 1` is an `INTEGER`.
+    const OVERFLOW_PROGRAM: &str = r#"
+A% = 32767
+PRINT A% - 1
+PRINT A% + 1
+PRINT "unreachable"
+"#;
+
+    /// Interprets the given program, which should fail,
+    /// returning the printed lines and the error message.
+    fn interpret_err(input: &str) -> (Vec<String>, String) {
+        let (instruction_generator_result, mut interpreter) = mock_interpreter_for_input(input);
+        let error = interpreter
+            .interpret(instruction_generator_result)
+            .unwrap_err();
+        (interpreter.stdout().output_lines(), error.err().to_string())
+    }
+
+    /// Returns the printed lines and the error message of a generated program,
+    /// which prints the error to stderr.
+    fn output_lines(output: Output) -> (Vec<String>, String) {
+        let stdout = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| line.trim().to_owned())
+            .collect();
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_owned();
+        (stdout, stderr)
+    }
+
+    fn assert_overflows_like_the_interpreter(output: Output) {
+        let (stdout, message) = interpret_err(OVERFLOW_PROGRAM);
+        assert_eq!(message, "Overflow");
+        assert_eq!(
+            output_lines(output),
+            (stdout, format!("error: {}", message))
+        );
+    }
+
+    #[test]
+    #[ignore = "builds the generated crates with cargo"]
+    fn test_generated_crate_overflows_like_the_interpreter() {
+        let (program, _) = lint(parse(OVERFLOW_PROGRAM)).unwrap();
+        let runtime_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../rusty_runtime");
+        let rust_crate =
+            transpile_to_rust(program, "overflow", runtime_path.to_str().unwrap()).unwrap();
+        let dir = std::env::temp_dir().join("rusty_basic_transpiled_overflow");
+        rust_crate.write_to(&dir).unwrap();
+        let output = Command::new(env!("CARGO"))
+            .args(["run", "--quiet", "--offline", "--manifest-path"])
+            .arg(dir.join("Cargo.toml"))
+            .output()
+            .unwrap();
+        assert_overflows_like_the_interpreter(output);
+    }
+
+    #[test]
+    #[ignore = "runs the generated module with node"]
+    fn test_generated_module_overflows_like_the_interpreter() {
+        let (program, _) = lint(parse(OVERFLOW_PROGRAM)).unwrap();
+        let dir = std::env::temp_dir().join("rusty_basic_transpiled_overflow_js");
+        transpile_to_js(program).unwrap().write_to(&dir).unwrap();
+        std::fs::write(
+            dir.join("main.mjs"),
+            r#"import { Runtime } from "./runtime.js";
+import { main } from "./program.js";
+
+const rt = new Runtime();
+try {
+    await main(rt);
+} catch (e) {
+    console.error(`error: ${e.message}`);
+}
+console.log(rt.screen.toString());
+"#,
+        )
+        .unwrap();
+        let output = Command::new("node")
+            .arg(dir.join("main.mjs"))
+            .output()
+            .unwrap();
+        assert_overflows_like_the_interpreter(output);
+    }
+}
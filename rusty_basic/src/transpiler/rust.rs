@@ -0,0 +1,1313 @@
+//! Transpiles a linted program into a Rust crate.
+//!
+//! The generated crate links against `rusty_runtime`, which provides the
+//! `Variant` arithmetic, the formatting of `PRINT` and the file I/O,
+//! so the compiled program behaves like the interpreted one.
+//!
+//! All values are kept as `Variant`s. The module level variables are fields
+//! of a `Globals` struct, which is passed to every SUB and FUNCTION,
+//! so that `SHARED` variables are accessible. Parameters are passed
+//! by reference, copying the arguments in and out like the interpreter does.
+//!
+//! A list of statements that contains labels becomes a loop, which runs the
+//! statements after the label that a `GOTO` targets. This is why a `GOTO` can
+//! only jump to a label of the same block or of an enclosing block.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use rusty_common::{AtPos, Position, Positioned};
+use rusty_parser::*;
+
+use super::{CodeWriter, TranspileError, TranspileErrorPos};
+
+/// The files of a generated Rust crate.
+pub struct RustCrate {
+    pub cargo_toml: String,
+    pub main_rs: String,
+}
+
+impl RustCrate {
+    /// Writes `Cargo.toml` and `src/main.rs` into the given directory.
+    pub fn write_to(&self, dir: &Path) -> std::io::Result<()> {
+        std::fs::create_dir_all(dir.join("src"))?;
+        std::fs::write(dir.join("Cargo.toml"), &self.cargo_toml)?;
+        std::fs::write(dir.join("src").join("main.rs"), &self.main_rs)
+    }
+}
+
+/// Transpiles a linted program into a Rust crate with the given name,
+/// which depends on the runtime crate at the given path.
+/// The runtime crate is not published, so the path needs to point to
+/// the `rusty_runtime` directory of a checkout of this repository.
+pub fn transpile_to_rust(
+    program: Program,
+    crate_name: &str,
+    runtime_path: &str,
+) -> Result<RustCrate, TranspileErrorPos> {
+    let mut transpiler = RustTranspiler::default();
+    transpiler.transpile(program)?;
+    Ok(RustCrate {
+        cargo_toml: cargo_toml(crate_name, runtime_path),
+        main_rs: transpiler.out.into_string(),
+    })
+}
+
+fn cargo_toml(crate_name: &str, runtime_path: &str) -> String {
+    // the empty workspace prevents the crate from joining an enclosing workspace
+    format!(
+        "[package]
+name = \"{}\"
+version = \"0.1.0\"
+edition = \"2024\"
+
+[dependencies]
+rusty_runtime = {{ path = {:?} }}
+
+[workspace]
+",
+        crate_name, runtime_path
+    )
+}
+
+type TranspileResult<T> = Result<T, TranspileErrorPos>;
+
+fn unsupported<T>(what: impl Into<String>, pos: Position) -> TranspileResult<T> {
+    Err(TranspileError::Unsupported(what.into()).at_pos(pos))
+}
+
+/// The SUB or FUNCTION whose body is being transpiled.
+#[derive(Default)]
+enum Scope {
+    #[default]
+    Global,
+    Sub,
+    /// The bare name of the function, which is used to assign its result.
+    Function(BareName),
+}
+
+/// A list of statements that contains labels.
+struct LabelBlock {
+    id: usize,
+    /// The index of the segment that starts with each label.
+    labels: HashMap<BareName, usize>,
+}
+
+/// The information needed to pass arguments to a SUB or FUNCTION.
+#[derive(Clone)]
+struct ParamInfo {
+    expression_type: ExpressionType,
+}
+
+/// The code that passes the arguments of a call,
+/// copying them into temporary variables and back.
+struct CallArgs {
+    before: Vec<String>,
+    refs: Vec<String>,
+    after: Vec<String>,
+}
+
+#[derive(Default)]
+struct RustTranspiler {
+    out: CodeWriter,
+    functions: HashMap<BareName, Vec<ParamInfo>>,
+    subs: HashMap<BareName, Vec<ParamInfo>>,
+    scope: Scope,
+    params: HashSet<String>,
+    locals: HashSet<String>,
+    label_blocks: Vec<LabelBlock>,
+    next_label_block_id: usize,
+    next_temp_id: usize,
+}
+
+impl RustTranspiler {
+    fn transpile(&mut self, program: Program) -> TranspileResult<()> {
+        let mut statements: Statements = vec![];
+        let mut functions: Vec<Positioned<FunctionImplementation>> = vec![];
+        let mut subs: Vec<Positioned<SubImplementation>> = vec![];
+        for Positioned { element, pos } in program {
+            match element {
+                GlobalStatement::Statement(s) => statements.push(s.at_pos(pos)),
+                GlobalStatement::FunctionImplementation(f) => {
+                    self.functions.insert(
+                        f.name.element.as_bare_name().clone(),
+                        param_infos(&f.params),
+                    );
+                    functions.push(f.at_pos(pos));
+                }
+                GlobalStatement::SubImplementation(s) => {
+                    self.subs
+                        .insert(s.name.element.clone(), param_infos(&s.params));
+                    subs.push(s.at_pos(pos));
+                }
+                GlobalStatement::UserDefinedType(_) => {
+                    return unsupported("TYPE", pos);
+                }
+                GlobalStatement::DefType(_)
+                | GlobalStatement::FunctionDeclaration(_)
+                | GlobalStatement::SubDeclaration(_) => {}
+            }
+        }
+        // like the interpreter, collect the DATA values before the program starts
+        let (data, other): (Statements, Statements) = statements
+            .into_iter()
+            .partition(|s| is_built_in_sub_call(&s.element, BuiltInSub::Data));
+        statements = data;
+        statements.extend(other);
+
+        self.out
+            .line("// Transpiled from QBasic by rusty_basic. Do not edit.");
+        self.out.line(
+            "#![allow(unused_mut, unused_variables, unused_assignments, unused_labels, unreachable_code, unused_parens, non_snake_case, dead_code)]",
+        );
+        self.out.line("");
+        self.out.line("use std::process::ExitCode;");
+        self.out.line("");
+        self.out.line("use rusty_runtime::prelude::*;");
+        self.out.line("");
+        self.out.open("fn main() -> ExitCode {");
+        self.out
+            .line("Runtime::run(|rt| program(rt, &mut Globals::new()))");
+        self.out.close("}");
+        self.out.line("");
+        self.globals(&statements)?;
+        self.out.line("");
+        self.out
+            .open("fn program(rt: &mut Runtime, g: &mut Globals) -> Result<(), Halt> {");
+        self.block(&statements)?;
+        self.out.line("Ok(())");
+        self.out.close("}");
+        for f in functions {
+            self.out.line("");
+            self.function(f)?;
+        }
+        for s in subs {
+            self.out.line("");
+            self.sub(s)?;
+        }
+        Ok(())
+    }
+
+    fn globals(&mut self, statements: &Statements) -> TranspileResult<()> {
+        let variables = declared_variables(statements)?;
+        self.out.open("struct Globals {");
+        for (ident, _) in &variables {
+            self.out.line(format!("{}: Variant,", ident));
+        }
+        self.out.close("}");
+        self.out.line("");
+        self.out.open("impl Globals {");
+        self.out.open("fn new() -> Self {");
+        self.out.open("Self {");
+        for (ident, q) in &variables {
+            self.out
+                .line(format!("{}: allocate_built_in({}),", ident, qualifier(*q)));
+        }
+        self.out.close("}");
+        self.out.close("}");
+        self.out.close("}");
+        Ok(())
+    }
+
+    fn function(&mut self, f: Positioned<FunctionImplementation>) -> TranspileResult<()> {
+        let Positioned {
+            element:
+                FunctionImplementation {
+                    name,
+                    params,
+                    body,
+                    is_static,
+                },
+            pos,
+        } = f;
+        if is_static {
+            return unsupported("STATIC", pos);
+        }
+        let Some(q) = name.element.qualifier() else {
+            return unsupported("FUNCTION of a user defined type", name.pos);
+        };
+        let bare_name = name.element.as_bare_name().clone();
+        let signature =
+            self.enter_subprogram(Scope::Function(bare_name.clone()), &params, &body)?;
+        self.out.open(format!(
+            "fn {}(rt: &mut Runtime, g: &mut Globals{}) -> Result<Variant, Halt> {{",
+            function_ident(&bare_name),
+            signature
+        ));
+        self.out.line(format!(
+            "let mut result = allocate_built_in({});",
+            qualifier(q)
+        ));
+        self.subprogram_body(&body)?;
+        self.out.line("Ok(result)");
+        self.out.close("}");
+        Ok(())
+    }
+
+    fn sub(&mut self, s: Positioned<SubImplementation>) -> TranspileResult<()> {
+        let Positioned {
+            element:
+                SubImplementation {
+                    name,
+                    params,
+                    body,
+                    is_static,
+                },
+            pos,
+        } = s;
+        if is_static {
+            return unsupported("STATIC", pos);
+        }
+        let signature = self.enter_subprogram(Scope::Sub, &params, &body)?;
+        self.out.open(format!(
+            "fn {}(rt: &mut Runtime, g: &mut Globals{}) -> Result<(), Halt> {{",
+            sub_ident(&name.element),
+            signature
+        ));
+        self.subprogram_body(&body)?;
+        self.out.line("Ok(())");
+        self.out.close("}");
+        Ok(())
+    }
+
+    /// Collects the parameters and the local variables of a SUB or FUNCTION,
+    /// returning the parameters of its signature.
+    fn enter_subprogram(
+        &mut self,
+        scope: Scope,
+        params: &Parameters,
+        body: &Statements,
+    ) -> TranspileResult<String> {
+        self.scope = scope;
+        self.params.clear();
+        let mut signature = String::new();
+        for Positioned {
+            element: param,
+            pos,
+        } in params
+        {
+            let ident = match param.var_type() {
+                ParamType::BuiltIn(q, _) => variable_ident(param.as_bare_name(), *q, false),
+                ParamType::Array(element_type) => match element_type.as_ref() {
+                    ParamType::BuiltIn(q, _) => variable_ident(param.as_bare_name(), *q, true),
+                    _ => return unsupported("user defined type", *pos),
+                },
+                _ => return unsupported("user defined type", *pos),
+            };
+            signature.push_str(&format!(", {}: &mut Variant", ident));
+            self.params.insert(ident);
+        }
+        self.locals.clear();
+        for (ident, _) in declared_variables(body)? {
+            self.locals.insert(ident);
+        }
+        Ok(signature)
+    }
+
+    fn subprogram_body(&mut self, body: &Statements) -> TranspileResult<()> {
+        for (ident, q) in declared_variables(body)? {
+            self.out.line(format!(
+                "let mut {} = allocate_built_in({});",
+                ident,
+                qualifier(q)
+            ));
+        }
+        self.block(body)
+    }
+
+    fn block(&mut self, statements: &Statements) -> TranspileResult<()> {
+        if !statements
+            .iter()
+            .any(|s| matches!(s.element, Statement::Label(_)))
+        {
+            for s in statements {
+                self.statement(s)?;
+            }
+            return Ok(());
+        }
+        // split the statements into segments, each one starting with a label
+        let mut segments: Vec<Vec<&StatementPos>> = vec![vec![]];
+        let mut labels: HashMap<BareName, usize> = HashMap::new();
+        for s in statements {
+            if let Statement::Label(label) = &s.element {
+                labels.insert(label.clone(), segments.len());
+                segments.push(vec![]);
+            } else {
+                segments.last_mut().unwrap().push(s);
+            }
+        }
+        let id = self.next_label_block_id;
+        self.next_label_block_id += 1;
+        self.label_blocks.push(LabelBlock { id, labels });
+        self.out.line(format!("let mut target_{}: usize = 0;", id));
+        self.out.open(format!("'block_{}: loop {{", id));
+        for (index, segment) in segments.into_iter().enumerate() {
+            self.out.open(format!("if target_{} <= {} {{", id, index));
+            for s in segment {
+                self.statement(s)?;
+            }
+            self.out.close("}");
+        }
+        self.out.line(format!("break 'block_{};", id));
+        self.out.close("}");
+        self.label_blocks.pop();
+        Ok(())
+    }
+
+    fn statement(&mut self, statement_pos: &StatementPos) -> TranspileResult<()> {
+        let pos = statement_pos.pos;
+        match &statement_pos.element {
+            Statement::Comment(text) => {
+                self.out.line(format!("//{}", text));
+            }
+            Statement::Assignment(a) => {
+                let (l, r) = a.into();
+                let value = self.expression_casting(r, &l.expression_type())?;
+                self.store(&l.clone().at_pos(pos), value)?;
+            }
+            Statement::Const(_) => {
+                // the linter has replaced the constants with their values
+            }
+            Statement::Dim(dim_list) | Statement::Redim(dim_list) => {
+                for dim_var in &dim_list.variables {
+                    self.dim(dim_var)?;
+                }
+            }
+            Statement::SubCall(sub_call) => {
+                let (name, args) = sub_call.into();
+                let params = self.subs[name].clone();
+                let call_args = self.call_args(&params, args)?;
+                self.out.open("{");
+                for line in &call_args.before {
+                    self.out.line(line);
+                }
+                self.out.line(format!(
+                    "{}(rt, g{})?;",
+                    sub_ident(name),
+                    call_args.refs.join("")
+                ));
+                for line in &call_args.after {
+                    self.out.line(line);
+                }
+                self.out.close("}");
+            }
+            Statement::BuiltInSubCall(sub_call) => {
+                let (sub, args) = sub_call.into();
+                self.built_in_sub_call(*sub, args, pos)?;
+            }
+            Statement::IfBlock(i) => {
+                let condition = self.condition(&i.if_block.condition)?;
+                self.out.open(format!("if {} {{", condition));
+                self.block(&i.if_block.statements)?;
+                for else_if_block in &i.else_if_blocks {
+                    let condition = self.condition(&else_if_block.condition)?;
+                    self.out.reopen(format!("}} else if {} {{", condition));
+                    self.block(&else_if_block.statements)?;
+                }
+                if let Some(else_block) = &i.else_block {
+                    self.out.reopen("} else {");
+                    self.block(else_block)?;
+                }
+                self.out.close("}");
+            }
+            Statement::SelectCase(s) => self.select_case(s)?,
+            Statement::ForLoop(f) => self.for_loop(f)?,
+            Statement::While(w) => {
+                let condition = self.condition(&w.condition)?;
+                self.out.open(format!("while {} {{", condition));
+                self.block(&w.statements)?;
+                self.out.close("}");
+            }
+            Statement::DoLoop(do_loop) => {
+                let mut condition = self.expression(&do_loop.condition)?;
+                if do_loop.kind == DoLoopConditionKind::Until {
+                    condition = format!("Variant::unary_not({})?", condition);
+                }
+                match do_loop.position {
+                    DoLoopConditionPosition::Top => {
+                        self.out.open(format!("while is_true({})? {{", condition));
+                        self.block(&do_loop.statements)?;
+                        self.out.close("}");
+                    }
+                    DoLoopConditionPosition::Bottom => {
+                        self.out.open("loop {");
+                        self.block(&do_loop.statements)?;
+                        self.out.open(format!("if !is_true({})? {{", condition));
+                        self.out.line("break;");
+                        self.out.close("}");
+                        self.out.close("}");
+                    }
+                }
+            }
+            Statement::Label(_) => {
+                // handled by the enclosing block
+            }
+            Statement::GoTo(label) => {
+                let Some((id, index)) = self
+                    .label_blocks
+                    .iter()
+                    .rev()
+                    .find_map(|b| b.labels.get(label).map(|index| (b.id, *index)))
+                else {
+                    return Err(TranspileError::GoToIntoBlock.at_pos(pos));
+                };
+                self.out.open("{");
+                self.out.line(format!("target_{} = {};", id, index));
+                self.out.line(format!("continue 'block_{};", id));
+                self.out.close("}");
+            }
+            Statement::Exit(_) => match self.scope {
+                Scope::Function(_) => self.out.line("return Ok(result);"),
+                _ => self.out.line("return Ok(());"),
+            },
+            Statement::End | Statement::System | Statement::Stop => {
+                self.out.line("return Err(Halt::End);");
+            }
+            Statement::Print(print) => self.print(print, pos)?,
+            Statement::OnError(_) => return unsupported("ON ERROR", pos),
+            Statement::Resume(_) => return unsupported("RESUME", pos),
+            Statement::GoSub(_) => return unsupported("GOSUB", pos),
+            Statement::Return(_) => return unsupported("RETURN", pos),
+            Statement::OnEvent(_) | Statement::EventControl(_) => {
+                return unsupported("Event trapping", pos);
+            }
+        }
+        Ok(())
+    }
+
+    fn dim(&mut self, dim_var_pos: &DimVarPos) -> TranspileResult<()> {
+        let Positioned {
+            element: dim_var,
+            pos,
+        } = dim_var_pos;
+        let bare_name = dim_var.as_bare_name();
+        match dim_var.var_type() {
+            DimType::BuiltIn(q, _) => {
+                let place = self.place(bare_name, *q, false);
+                self.out
+                    .line(format!("{} = allocate_built_in({});", place, qualifier(*q)));
+            }
+            DimType::FixedLengthString(_, len) => {
+                let place = self.place(bare_name, TypeQualifier::DollarString, false);
+                self.out.line(format!(
+                    "{} = allocate_fixed_length_string({});",
+                    place, len
+                ));
+            }
+            DimType::Array(dimensions, element_type) => {
+                let (q, element) = match element_type.as_ref() {
+                    DimType::BuiltIn(q, _) => (*q, format!("allocate_built_in({})", qualifier(*q))),
+                    DimType::FixedLengthString(_, len) => (
+                        TypeQualifier::DollarString,
+                        format!("allocate_fixed_length_string({})", len),
+                    ),
+                    _ => return unsupported("user defined type", *pos),
+                };
+                let mut bounds: Vec<String> = vec![];
+                for ArrayDimension { lbound, ubound } in dimensions {
+                    let lbound = match lbound {
+                        Some(lbound) => self.expression(lbound)?,
+                        None => "Variant::VInteger(0)".to_owned(),
+                    };
+                    bounds.push(format!("to_int({})?", lbound));
+                    bounds.push(format!("to_int({})?", self.expression(ubound)?));
+                }
+                let place = self.place(bare_name, q, true);
+                self.out.line(format!(
+                    "{} = allocate_array(vec![{}], {})?;",
+                    place,
+                    bounds.join(", "),
+                    element
+                ));
+            }
+            DimType::UserDefined(_) | DimType::Bare => {
+                return unsupported("user defined type", *pos);
+            }
+        }
+        Ok(())
+    }
+
+    fn built_in_sub_call(
+        &mut self,
+        sub: BuiltInSub,
+        args: &Expressions,
+        pos: Position,
+    ) -> TranspileResult<()> {
+        match sub {
+            // the runtime has a method per statement, e.g. `rt.close(args)`
+            BuiltInSub::Close => self.runtime_sub_call("close", args)?,
+            BuiltInSub::Environ => self.runtime_sub_call("set_environ", args)?,
+            BuiltInSub::Error => self.runtime_sub_call("error", args)?,
+            BuiltInSub::Kill => self.runtime_sub_call("kill", args)?,
+            BuiltInSub::Name => self.runtime_sub_call("name", args)?,
+            BuiltInSub::Open => self.runtime_sub_call("open", args)?,
+            BuiltInSub::Data => {
+                for arg in args {
+                    let value = self.expression(arg)?;
+                    self.out.line(format!("rt.push_data({});", value));
+                }
+            }
+            BuiltInSub::Input | BuiltInSub::LineInput => {
+                // the first argument is 1 if a file number follows
+                let (file_number, variables) = match args.first().map(|arg| &arg.element) {
+                    Some(Expression::IntegerLiteral(1)) => (self.expression(&args[1])?, &args[2..]),
+                    _ => ("Variant::VInteger(0)".to_owned(), &args[1..]),
+                };
+                self.out.open("{");
+                self.out.line(format!("let file_number = {};", file_number));
+                for variable in variables {
+                    let value = if sub == BuiltInSub::Input {
+                        format!(
+                            "rt.input(file_number.clone(), {})?",
+                            qualifier(self.qualifier_of(variable)?)
+                        )
+                    } else {
+                        "rt.line_input(file_number.clone())?".to_owned()
+                    };
+                    let value = fix_length(value, &variable.expression_type());
+                    self.store(variable, value)?;
+                }
+                self.out.close("}");
+            }
+            BuiltInSub::Read => {
+                for variable in args {
+                    let value = format!("rt.read({})?", qualifier(self.qualifier_of(variable)?));
+                    let value = fix_length(value, &variable.expression_type());
+                    self.store(variable, value)?;
+                }
+            }
+            BuiltInSub::Width => {
+                // WIDTH LPRINT is encoded as `2, columns`,
+                // the screen form is ignored like in the interpreter
+                if let [first, _] = args.as_slice()
+                    && first.element == Expression::IntegerLiteral(2)
+                {
+                    return unsupported("WIDTH LPRINT", pos);
+                }
+            }
+            _ => return unsupported(format!("{:?}", sub).to_uppercase(), pos),
+        }
+        Ok(())
+    }
+
+    fn runtime_sub_call(&mut self, method: &str, args: &Expressions) -> TranspileResult<()> {
+        let args = self.expressions(args)?;
+        self.out.open("{");
+        self.out
+            .line(format!("let args = vec![{}];", args.join(", ")));
+        self.out.line(format!("rt.{}(args)?;", method));
+        self.out.close("}");
+        Ok(())
+    }
+
+    fn print(&mut self, print: &Print, pos: Position) -> TranspileResult<()> {
+        if print.lpt1 {
+            return unsupported("LPRINT", pos);
+        }
+        let file_number: i32 = print.file_number.map(i32::from).unwrap_or_default();
+        self.out.open("{");
+        self.out.line(format!("rt.print_begin({});", file_number));
+        if let Some(format_string) = &print.format_string {
+            let format_string = self.expression(format_string)?;
+            self.out
+                .line(format!("let format_string = {};", format_string));
+            self.out.line("rt.print_using(format_string);");
+        }
+        for arg in &print.args {
+            match arg {
+                PrintArg::Comma => self.out.line("rt.print_comma()?;"),
+                PrintArg::Semicolon => self.out.line("rt.print_semicolon();"),
+                PrintArg::Expression(e) => {
+                    let value = self.expression(e)?;
+                    self.out.line(format!("let value = {};", value));
+                    self.out.line("rt.print_value(value)?;");
+                }
+            }
+        }
+        self.out.line("rt.print_end()?;");
+        self.out.close("}");
+        Ok(())
+    }
+
+    fn select_case(&mut self, s: &SelectCase) -> TranspileResult<()> {
+        let selected = self.expression(&s.expr)?;
+        self.out.open("{");
+        self.out.line(format!("let selected = {};", selected));
+        let mut is_first = true;
+        for case_block in &s.case_blocks {
+            let (case_expressions, statements) = case_block.into();
+            let mut conditions: Vec<String> = vec![];
+            for case_expression in case_expressions {
+                conditions.push(self.case_condition(case_expression)?);
+            }
+            let line = format!("if {} {{", conditions.join(" || "));
+            if is_first {
+                self.out.open(line);
+                is_first = false;
+            } else {
+                self.out.reopen(format!("}} else {}", line));
+            }
+            self.block(statements)?;
+        }
+        match &s.else_block {
+            Some(else_block) if is_first => self.block(else_block)?,
+            Some(else_block) => {
+                self.out.reopen("} else {");
+                self.block(else_block)?;
+                self.out.close("}");
+            }
+            None if is_first => {}
+            None => self.out.close("}"),
+        }
+        self.out.close("}");
+        Ok(())
+    }
+
+    fn case_condition(&mut self, case_expression: &CaseExpression) -> TranspileResult<String> {
+        match case_expression {
+            CaseExpression::Simple(e) => Ok(format!(
+                "is_true(equal(selected.clone(), {})?)?",
+                self.expression(e)?
+            )),
+            CaseExpression::Is(op, e) => Ok(format!(
+                "is_true({}(selected.clone(), {})?)?",
+                comparison_function(*op),
+                self.expression(e)?
+            )),
+            CaseExpression::Range(from, to) => Ok(format!(
+                "(is_true(greater_or_equal(selected.clone(), {})?)? && is_true(less_or_equal(selected.clone(), {})?)?)",
+                self.expression(from)?,
+                self.expression(to)?
+            )),
+        }
+    }
+
+    fn for_loop(&mut self, f: &ForLoop) -> TranspileResult<()> {
+        let Expression::Variable(counter_name, counter_type) = &f.variable_name.element else {
+            return unsupported("FOR counter of a user defined type", f.variable_name.pos);
+        };
+        let counter = self.variable_place(counter_name, false, f.variable_name.pos)?;
+        let lower_bound = self.expression_casting(&f.lower_bound, counter_type)?;
+        let upper_bound = self.expression_casting(&f.upper_bound, counter_type)?;
+        self.out.open("{");
+        self.out.line(format!("{} = {};", counter, lower_bound));
+        self.out.line(format!("let upper = {};", upper_bound));
+        match &f.step {
+            Some(step) => {
+                let step = self.expression(step)?;
+                self.out.line(format!("let step = {};", step));
+                self.out
+                    .line("let is_positive = for_step_is_positive(&step)?;");
+            }
+            None => {
+                self.out.line("let step = Variant::VInteger(1);");
+                self.out.line("let is_positive = true;");
+            }
+        }
+        self.out.open(format!(
+            "while for_continues(&{}, &upper, is_positive)? {{",
+            counter
+        ));
+        self.block(&f.statements)?;
+        self.out.line(format!(
+            "{} = Variant::plus({}.clone(), step.clone())?;",
+            counter, counter
+        ));
+        self.out.close("}");
+        self.out.close("}");
+        Ok(())
+    }
+
+    /// Stores the given value into a variable or an array element.
+    fn store(&mut self, target: &ExpressionPos, value: String) -> TranspileResult<()> {
+        match &target.element {
+            Expression::Variable(name, _) => {
+                let place = self.variable_place(name, false, target.pos)?;
+                self.out.line(format!("{} = {};", place, value));
+            }
+            Expression::ArrayElement(name, indices, _) => {
+                let place = self.variable_place(name, true, target.pos)?;
+                let indices = self.expressions(indices)?;
+                self.out.open("{");
+                self.out.line(format!("let value = {};", value));
+                self.out.line(format!(
+                    "array_set(&[{}], &mut {}, value)?;",
+                    indices.join(", "),
+                    place
+                ));
+                self.out.close("}");
+            }
+            _ => return unsupported("user defined type", target.pos),
+        }
+        Ok(())
+    }
+
+    /// Evaluates the arguments of a call to a SUB or FUNCTION.
+    ///
+    /// Variables and array elements are passed by reference,
+    /// by copying them into a temporary variable before the call
+    /// and back after the call.
+    fn call_args(&mut self, params: &[ParamInfo], args: &Expressions) -> TranspileResult<CallArgs> {
+        let mut call_args = CallArgs {
+            before: vec![],
+            refs: vec![],
+            after: vec![],
+        };
+        for (param, arg) in params.iter().zip(args) {
+            let id = self.next_temp_id;
+            self.next_temp_id += 1;
+            let temp = format!("a{}", id);
+            match &arg.element {
+                Expression::Variable(name, expression_type) => {
+                    let is_array = matches!(expression_type, ExpressionType::Array(_));
+                    let place = self.variable_place(name, is_array, arg.pos)?;
+                    call_args.before.push(copy_in(&temp, &place, is_array));
+                    call_args.after.push(format!("{} = {};", place, temp));
+                }
+                Expression::ArrayElement(name, indices, _) if indices.is_empty() => {
+                    let place = self.variable_place(name, true, arg.pos)?;
+                    call_args.before.push(copy_in(&temp, &place, true));
+                    call_args.after.push(format!("{} = {};", place, temp));
+                }
+                Expression::ArrayElement(name, indices, _) => {
+                    let place = self.variable_place(name, true, arg.pos)?;
+                    let indices = self.expressions(indices)?;
+                    call_args
+                        .before
+                        .push(format!("let i{} = [{}];", id, indices.join(", ")));
+                    call_args.before.push(format!(
+                        "let mut {} = array_get(&i{}, &{})?;",
+                        temp, id, place
+                    ));
+                    call_args
+                        .after
+                        .push(format!("array_set(&i{}, &mut {}, {})?;", id, place, temp));
+                }
+                Expression::Property(_, _, _) => {
+                    return unsupported("user defined type", arg.pos);
+                }
+                _ => {
+                    let value = self.expression_casting(arg, &param.expression_type)?;
+                    call_args
+                        .before
+                        .push(format!("let mut {} = {};", temp, value));
+                }
+            }
+            call_args.refs.push(format!(", &mut {}", temp));
+        }
+        Ok(call_args)
+    }
+
+    fn condition(&mut self, e: &ExpressionPos) -> TranspileResult<String> {
+        Ok(format!("is_true({})?", self.expression(e)?))
+    }
+
+    fn expressions(&mut self, expressions: &Expressions) -> TranspileResult<Vec<String>> {
+        expressions.iter().map(|e| self.expression(e)).collect()
+    }
+
+    fn expression_casting(
+        &mut self,
+        e: &ExpressionPos,
+        target_type: &ExpressionType,
+    ) -> TranspileResult<String> {
+        let code = self.expression(e)?;
+        if e.expression_type() == *target_type {
+            return Ok(code);
+        }
+        Ok(match target_type {
+            ExpressionType::BuiltIn(q) => format!("cast({}, {})?", code, qualifier(*q)),
+            _ => fix_length(code, target_type),
+        })
+    }
+
+    /// Generates an expression that evaluates to an owned `Variant`.
+    fn expression(&mut self, expr_pos: &ExpressionPos) -> TranspileResult<String> {
+        let pos = expr_pos.pos;
+        let code = match &expr_pos.element {
+            Expression::SingleLiteral(f) => format!("Variant::VSingle({:?}_f32)", f),
+            Expression::DoubleLiteral(f) => format!("Variant::VDouble({:?}_f64)", f),
+            Expression::StringLiteral(s) => format!("Variant::VString({:?}.to_owned())", s),
+            Expression::IntegerLiteral(i) => format!("Variant::VInteger({})", i),
+            Expression::LongLiteral(l) => format!("Variant::VLong({})", l),
+            Expression::Variable(name, expression_type) => {
+                let is_array = matches!(expression_type, ExpressionType::Array(_));
+                format!("{}.clone()", self.variable_place(name, is_array, pos)?)
+            }
+            Expression::ArrayElement(name, indices, _) => {
+                let place = self.variable_place(name, true, pos)?;
+                if indices.is_empty() {
+                    format!("{}.clone()", place)
+                } else {
+                    let indices = self.expressions(indices)?;
+                    format!("array_get(&[{}], &{})?", indices.join(", "), place)
+                }
+            }
+            Expression::FunctionCall(name, args) => {
+                let params = self.functions[name.as_bare_name()].clone();
+                let call_args = self.call_args(&params, args)?;
+                format!(
+                    "{{ {} let r = {}(rt, g{})?; {} r }}",
+                    call_args.before.join(" "),
+                    function_ident(name.as_bare_name()),
+                    call_args.refs.join(""),
+                    call_args.after.join(" ")
+                )
+            }
+            Expression::BuiltInFunctionCall(f, args) => {
+                self.built_in_function_call(*f, args, pos)?
+            }
+            Expression::BinaryExpression(op, left, right, _) => {
+                let left = self.expression(left)?;
+                let right = self.expression(right)?;
+                let function = match op {
+                    Operator::Plus => "Variant::plus",
+                    Operator::Minus => "Variant::minus",
+                    Operator::Multiply => "Variant::multiply",
+                    Operator::Divide => "Variant::divide",
+                    Operator::Modulo => "Variant::modulo",
+                    Operator::And => "and",
+                    Operator::Or => "or",
+                    _ => comparison_function(*op),
+                };
+                format!("{}({}, {})?", function, left, right)
+            }
+            Expression::UnaryExpression(op, child) => {
+                let child = self.expression(child)?;
+                match op {
+                    UnaryOperator::Not => format!("Variant::unary_not({})?", child),
+                    UnaryOperator::Minus => format!("Variant::negate({})?", child),
+                }
+            }
+            Expression::Parenthesis(child) => self.expression(child)?,
+            Expression::Property(_, _, _) => return unsupported("user defined type", pos),
+        };
+        Ok(code)
+    }
+
+    fn built_in_function_call(
+        &mut self,
+        f: BuiltInFunction,
+        args: &Expressions,
+        pos: Position,
+    ) -> TranspileResult<String> {
+        match f {
+            BuiltInFunction::LBound | BuiltInFunction::UBound => {
+                let Expression::Variable(name, _) = &args[0].element else {
+                    return unsupported("user defined type", args[0].pos);
+                };
+                let array = self.variable_place(name, true, args[0].pos)?;
+                let dimension = match args.get(1) {
+                    Some(arg) => self.expression(arg)?,
+                    None => "Variant::VInteger(1)".to_owned(),
+                };
+                let function = if f == BuiltInFunction::LBound {
+                    "lbound"
+                } else {
+                    "ubound"
+                };
+                Ok(format!(
+                    "{{ let dimension = bound_dimension({})?; Variant::VInteger(functions::{}(&{}, dimension)?) }}",
+                    dimension, function, array
+                ))
+            }
+            BuiltInFunction::Chr
+            | BuiltInFunction::Command
+            | BuiltInFunction::Environ
+            | BuiltInFunction::Eof
+            | BuiltInFunction::InStr
+            | BuiltInFunction::LCase
+            | BuiltInFunction::Left
+            | BuiltInFunction::Len
+            | BuiltInFunction::LTrim
+            | BuiltInFunction::Mid
+            | BuiltInFunction::Right
+            | BuiltInFunction::RTrim
+            | BuiltInFunction::Space
+            | BuiltInFunction::Str
+            | BuiltInFunction::String
+            | BuiltInFunction::UCase
+            | BuiltInFunction::Val => {
+                let args = self.expressions(args)?;
+                // the runtime has a method per function, e.g. `rt.lcase(args)`
+                Ok(format!(
+                    "{{ let args = vec![{}]; rt.{}(args)? }}",
+                    args.join(", "),
+                    format!("{:?}", f).to_lowercase()
+                ))
+            }
+            _ => unsupported(format!("{:?}", f).to_uppercase(), pos),
+        }
+    }
+
+    fn qualifier_of(&self, variable: &ExpressionPos) -> TranspileResult<TypeQualifier> {
+        match variable.expression_type() {
+            ExpressionType::BuiltIn(q) => Ok(q),
+            ExpressionType::FixedLengthString(_) => Ok(TypeQualifier::DollarString),
+            _ => unsupported("user defined type", variable.pos),
+        }
+    }
+
+    fn variable_place(
+        &self,
+        name: &Name,
+        is_array: bool,
+        pos: Position,
+    ) -> TranspileResult<String> {
+        if let Scope::Function(function_name) = &self.scope
+            && !is_array
+            && name.as_bare_name() == function_name
+        {
+            return Ok("result".to_owned());
+        }
+        match name.qualifier() {
+            Some(q) => Ok(self.place(name.as_bare_name(), q, is_array)),
+            None => unsupported("user defined type", pos),
+        }
+    }
+
+    /// Gets the Rust place expression of a variable, depending on
+    /// whether it is a parameter, a local or a global variable.
+    fn place(&self, bare_name: &BareName, q: TypeQualifier, is_array: bool) -> String {
+        let ident = variable_ident(bare_name, q, is_array);
+        if self.params.contains(&ident) {
+            format!("(*{})", ident)
+        } else if self.locals.contains(&ident) {
+            ident
+        } else {
+            format!("g.{}", ident)
+        }
+    }
+}
+
+fn param_infos(params: &Parameters) -> Vec<ParamInfo> {
+    params
+        .iter()
+        .map(|param| ParamInfo {
+            expression_type: param.element.expression_type(),
+        })
+        .collect()
+}
+
+fn is_built_in_sub_call(statement: &Statement, sub: BuiltInSub) -> bool {
+    matches!(statement, Statement::BuiltInSubCall(b) if *b.built_in_sub() == sub)
+}
+
+/// Collects the variables that are declared with `DIM` or `REDIM` in the given
+/// statements, including nested blocks, in the order of their declaration.
+///
+/// The linter has added a `DIM` for every implicitly declared variable.
+fn declared_variables(statements: &Statements) -> TranspileResult<Vec<(String, TypeQualifier)>> {
+    let mut result: Vec<(String, TypeQualifier)> = vec![];
+    let mut seen: HashSet<String> = HashSet::new();
+    collect_declared_variables(statements, &mut result, &mut seen)?;
+    Ok(result)
+}
+
+fn collect_declared_variables(
+    statements: &Statements,
+    result: &mut Vec<(String, TypeQualifier)>,
+    seen: &mut HashSet<String>,
+) -> TranspileResult<()> {
+    for statement in statements {
+        match &statement.element {
+            Statement::Dim(dim_list) | Statement::Redim(dim_list) => {
+                for Positioned {
+                    element: dim_var,
+                    pos,
+                } in &dim_list.variables
+                {
+                    let (q, is_array) = match dim_var.var_type() {
+                        DimType::BuiltIn(q, _) => (*q, false),
+                        DimType::FixedLengthString(_, _) => (TypeQualifier::DollarString, false),
+                        DimType::Array(_, element_type) => match element_type.as_ref() {
+                            DimType::BuiltIn(q, _) => (*q, true),
+                            DimType::FixedLengthString(_, _) => (TypeQualifier::DollarString, true),
+                            _ => return unsupported("user defined type", *pos),
+                        },
+                        _ => return unsupported("user defined type", *pos),
+                    };
+                    let ident = variable_ident(dim_var.as_bare_name(), q, is_array);
+                    if seen.insert(ident.clone()) {
+                        result.push((ident, q));
+                    }
+                }
+            }
+            Statement::IfBlock(i) => {
+                collect_declared_variables(&i.if_block.statements, result, seen)?;
+                for else_if_block in &i.else_if_blocks {
+                    collect_declared_variables(&else_if_block.statements, result, seen)?;
+                }
+                if let Some(else_block) = &i.else_block {
+                    collect_declared_variables(else_block, result, seen)?;
+                }
+            }
+            Statement::SelectCase(s) => {
+                for case_block in &s.case_blocks {
+                    let (_, statements) = case_block.into();
+                    collect_declared_variables(statements, result, seen)?;
+                }
+                if let Some(else_block) = &s.else_block {
+                    collect_declared_variables(else_block, result, seen)?;
+                }
+            }
+            Statement::ForLoop(f) => collect_declared_variables(&f.statements, result, seen)?,
+            Statement::While(w) => collect_declared_variables(&w.statements, result, seen)?,
+            Statement::DoLoop(d) => collect_declared_variables(&d.statements, result, seen)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn copy_in(temp: &str, place: &str, is_array: bool) -> String {
+    if is_array {
+        // move the array instead of copying it, it is moved back after the call
+        format!(
+            "let mut {} = std::mem::replace(&mut {}, Variant::VInteger(0));",
+            temp, place
+        )
+    } else {
+        format!("let mut {} = {}.clone();", temp, place)
+    }
+}
+
+/// Truncates or pads the given value, if the target is a fixed length string.
+fn fix_length(code: String, target_type: &ExpressionType) -> String {
+    match target_type {
+        ExpressionType::FixedLengthString(len) => format!("cast_fixed_length({}, {})?", code, len),
+        _ => code,
+    }
+}
+
+fn comparison_function(op: Operator) -> &'static str {
+    match op {
+        Operator::Less => "less",
+        Operator::LessOrEqual => "less_or_equal",
+        Operator::Equal => "equal",
+        Operator::GreaterOrEqual => "greater_or_equal",
+        Operator::Greater => "greater",
+        Operator::NotEqual => "not_equal",
+        _ => panic!("Not a comparison operator {:?}", op),
+    }
+}
+
+fn qualifier(q: TypeQualifier) -> String {
+    format!("TypeQualifier::{:?}", q)
+}
+
+fn rust_name(bare_name: &BareName) -> String {
+    bare_name.to_ascii_lowercase().replace('.', "_")
+}
+
+/// The name of a variable, e.g. `v_count_int` for `Count%`
+/// or `a_names_str` for the array `Names$()`.
+fn variable_ident(bare_name: &BareName, q: TypeQualifier, is_array: bool) -> String {
+    let suffix = match q {
+        TypeQualifier::BangSingle => "sng",
+        TypeQualifier::HashDouble => "dbl",
+        TypeQualifier::DollarString => "str",
+        TypeQualifier::PercentInteger => "int",
+        TypeQualifier::AmpersandLong => "lng",
+    };
+    let prefix = if is_array { "a" } else { "v" };
+    format!("{}_{}_{}", prefix, rust_name(bare_name), suffix)
+}
+
+fn function_ident(bare_name: &BareName) -> String {
+    format!("fn_{}", rust_name(bare_name))
+}
+
+fn sub_ident(bare_name: &BareName) -> String {
+    format!("sub_{}", rust_name(bare_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use rusty_common::Position;
+    use rusty_linter::core::lint;
+    use rusty_parser::parse;
+
+    use super::*;
+
+    fn transpile_str(input: &str) -> Result<String, TranspileErrorPos> {
+        let (program, _) = lint(parse(input)).expect("Linter should succeed");
+        transpile_to_rust(program, "test", "../rusty_runtime").map(|c| c.main_rs)
+    }
+
+    fn assert_contains(main_rs: &str, lines: &[&str]) {
+        let actual: Vec<&str> = main_rs.lines().map(str::trim).collect();
+        for line in lines {
+            assert!(
+                actual.contains(line),
+                "Expected line `{}` in:\n{}",
+                line,
+                main_rs
+            );
+        }
+    }
+
+    #[test]
+    fn test_cargo_toml() {
+        let (program, _) = lint(parse("PRINT 1")).unwrap();
+        let rust_crate = transpile_to_rust(program, "hello", "../rusty_runtime").unwrap();
+        assert_eq!(
+            rust_crate.cargo_toml,
+            "[package]
+name = \"hello\"
+version = \"0.1.0\"
+edition = \"2024\"
+
+[dependencies]
+rusty_runtime = { path = \"../rusty_runtime\" }
+
+[workspace]
+"
+        );
+    }
+
+    #[test]
+    fn test_global_variables_and_print() {
+        let main_rs = transpile_str("A% = 1 + 2\nPRINT A%; \"x\"").unwrap();
+        assert_contains(
+            &main_rs,
+            &[
+                "v_a_int: Variant,",
+                "v_a_int: allocate_built_in(TypeQualifier::PercentInteger),",
+                "g.v_a_int = Variant::plus(Variant::VInteger(1), Variant::VInteger(2))?;",
+                "rt.print_begin(0);",
+                "let value = g.v_a_int.clone();",
+                "rt.print_semicolon();",
+                "rt.print_end()?;",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_sub_parameters_are_copied_in_and_out() {
+        let input = "
+        DECLARE SUB Inc(N%)
+        X% = 1
+        Inc X%
+        SUB Inc(N%)
+            N% = N% + 1
+        END SUB
+        ";
+        let main_rs = transpile_str(input).unwrap();
+        assert_contains(
+            &main_rs,
+            &[
+                "let mut a0 = g.v_x_int.clone();",
+                "sub_inc(rt, g, &mut a0)?;",
+                "g.v_x_int = a0;",
+                "fn sub_inc(rt: &mut Runtime, g: &mut Globals, v_n_int: &mut Variant) -> Result<(), Halt> {",
+                "(*v_n_int) = Variant::plus((*v_n_int).clone(), Variant::VInteger(1))?;",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_function_result() {
+        let input = "
+        DECLARE FUNCTION Twice%(N%)
+        PRINT Twice%(2)
+        FUNCTION Twice%(N%)
+            Twice% = N% * 2
+        END FUNCTION
+        ";
+        let main_rs = transpile_str(input).unwrap();
+        assert_contains(
+            &main_rs,
+            &[
+                "let mut result = allocate_built_in(TypeQualifier::PercentInteger);",
+                "result = Variant::multiply((*v_n_int).clone(), Variant::VInteger(2))?;",
+                "Ok(result)",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_goto_backwards() {
+        let input = "
+        N% = 0
+        Again:
+        N% = N% + 1
+        IF N% < 3 THEN GOTO Again
+        ";
+        let main_rs = transpile_str(input).unwrap();
+        assert_contains(
+            &main_rs,
+            &[
+                "let mut target_0: usize = 0;",
+                "'block_0: loop {",
+                "if target_0 <= 1 {",
+                "target_0 = 1;",
+                "continue 'block_0;",
+                "break 'block_0;",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_goto_into_block_is_an_error() {
+        let input = "GOTO Inside\nFOR I = 1 TO 2\nInside:\nNEXT";
+        assert_eq!(
+            transpile_str(input),
+            Err(TranspileError::GoToIntoBlock.at_pos(Position::new(1, 1)))
+        );
+    }
+
+    #[test]
+    fn test_unsupported_statement_is_an_error() {
+        let input = "PRINT 1\nGOSUB Here\nHere:\nRETURN";
+        assert_eq!(
+            transpile_str(input),
+            Err(TranspileError::Unsupported("GOSUB".to_owned()).at_pos(Position::new(2, 1)))
+        );
+    }
+
+    #[test]
+    fn test_unsupported_built_in_sub_is_an_error() {
+        let input = "CLS";
+        assert_eq!(
+            transpile_str(input),
+            Err(TranspileError::Unsupported("CLS".to_owned()).at_pos(Position::new(1, 1)))
+        );
+    }
+
+    #[test]
+    #[ignore = "builds the generated crates with cargo"]
+    fn test_generated_crates_of_bench_programs_run() {
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let runtime_path = manifest_dir.join("../rusty_runtime");
+        for (file_name, expected) in [("FIB.BAS", " 17711 "), ("SIEVE.BAS", " 1862 ")] {
+            let input =
+                std::fs::read_to_string(manifest_dir.join("benches/programs").join(file_name))
+                    .unwrap();
+            let (program, _) = lint(parse(&input)).unwrap();
+            let rust_crate =
+                transpile_to_rust(program, "bench", runtime_path.to_str().unwrap()).unwrap();
+            let dir = std::env::temp_dir().join(format!("rusty_basic_transpiled_{}", file_name));
+            rust_crate.write_to(&dir).unwrap();
+            let output = std::process::Command::new(env!("CARGO"))
+                .args(["run", "--quiet", "--offline", "--manifest-path"])
+                .arg(dir.join("Cargo.toml"))
+                .output()
+                .unwrap();
+            assert!(
+                output.status.success(),
+                "{}: {}",
+                file_name,
+                String::from_utf8_lossy(&output.stderr)
+            );
+            assert_eq!(
+                String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .collect::<Vec<_>>(),
+                vec![expected],
+                "{}",
+                file_name
+            );
+        }
+    }
+}
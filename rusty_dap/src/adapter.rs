@@ -0,0 +1,1067 @@
+//! Handles the requests of the client: launching the program,
+//! and inspecting it while it is paused.
+
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+
+use rusty_basic::diagnostics::Diagnostics;
+use rusty_basic::instruction_generator::{
+    InstructionGeneratorResult, generate_instructions, unwrap_linter_context,
+};
+use rusty_basic::interpreter::{
+    DebugAction, Debugger, InterpreterTrait, Pause, PauseReason, format_value,
+    new_interpreter_with_io,
+};
+use rusty_linter::core::lint;
+use rusty_parser::{UserDefinedTypes, parse_main_str};
+use rusty_variant::Variant;
+use serde_json::{Value, json};
+
+use crate::protocol::{Connection, Request, spawn_request_reader};
+use crate::session::{ConsoleInput, ConsoleOutput, Session, SharedSession, THREAD_ID};
+
+/// Serves a client until it disconnects. Returns the writer of the connection.
+pub fn serve<R: BufRead + Send + 'static, W: Write + 'static>(
+    reader: R,
+    writer: W,
+) -> std::io::Result<W> {
+    let session = Rc::new(std::cell::RefCell::new(Session::new(
+        spawn_request_reader(reader),
+        Connection::new(writer),
+    )));
+    let mut adapter = Adapter {
+        session: Rc::clone(&session),
+        program: None,
+    };
+    adapter.run()?;
+    drop(adapter);
+    let session = Rc::try_unwrap(session)
+        .ok()
+        .expect("the session should not be in use")
+        .into_inner();
+    Ok(session.connection.into_writer())
+}
+
+/// A program that has been launched and runs once the client is done
+/// with the configuration (e.g. setting the breakpoints).
+struct LaunchedProgram {
+    file_name: String,
+    diagnostics: Diagnostics,
+    instruction_generator_result: InstructionGeneratorResult,
+    user_defined_types: UserDefinedTypes,
+    command_line: String,
+    stop_on_entry: bool,
+}
+
+struct Adapter<W> {
+    session: SharedSession<W>,
+    program: Option<LaunchedProgram>,
+}
+
+impl<W: Write + 'static> Adapter<W> {
+    fn run(&mut self) -> std::io::Result<()> {
+        loop {
+            let Some(request) = self.session.borrow_mut().read_request()? else {
+                return Ok(());
+            };
+            self.handle_request(&request)?;
+            if self.session.borrow().disconnected {
+                return Ok(());
+            }
+        }
+    }
+
+    fn handle_request(&mut self, request: &Request) -> std::io::Result<()> {
+        match request.command.as_str() {
+            "initialize" => {
+                let mut session = self.session.borrow_mut();
+                session.connection.respond(
+                    request,
+                    json!({
+                        "supportsConfigurationDoneRequest": true,
+                        "supportsEvaluateForHovers": true,
+                    }),
+                )?;
+                session.connection.send_event("initialized", json!({}))
+            }
+            "launch" => match self.launch(request) {
+                Ok(program) => {
+                    self.program = Some(program);
+                    self.session
+                        .borrow_mut()
+                        .connection
+                        .respond(request, json!({}))
+                }
+                Err(message) => self
+                    .session
+                    .borrow_mut()
+                    .connection
+                    .respond_error(request, &message),
+            },
+            "configurationDone" => {
+                self.session
+                    .borrow_mut()
+                    .connection
+                    .respond(request, json!({}))?;
+                match self.program.take() {
+                    Some(program) => self.run_program(program),
+                    None => Ok(()),
+                }
+            }
+            _ => {
+                let mut session = self.session.borrow_mut();
+                if session.handle_common_request(request)? {
+                    Ok(())
+                } else {
+                    session
+                        .connection
+                        .respond_error(request, "The program is not paused.")
+                }
+            }
+        }
+    }
+
+    /// Reads, parses and lints the program of the `launch` request.
+    fn launch(&self, request: &Request) -> Result<LaunchedProgram, String> {
+        let file_name = request
+            .str_argument("program")
+            .ok_or("Please specify the program.")?
+            .to_owned();
+        let source = std::fs::read_to_string(&file_name)
+            .map_err(|e| format!("Could not read {}. {}", file_name, e))?;
+        let mut diagnostics = Diagnostics::new(&file_name, &source);
+        let program = parse_main_str(source).map_err(|e| diagnostics.render_parse_error(&e))?;
+        diagnostics.register_subprograms(&program);
+        let (program, linter_context) =
+            lint(program).map_err(|e| diagnostics.render_lint_error(&e))?;
+        let (linter_names, user_defined_types) = unwrap_linter_context(linter_context);
+        Ok(LaunchedProgram {
+            file_name,
+            diagnostics,
+            instruction_generator_result: generate_instructions(program, linter_names),
+            user_defined_types,
+            command_line: request.str_argument("args").unwrap_or_default().to_owned(),
+            stop_on_entry: request.arguments["stopOnEntry"]
+                .as_bool()
+                .unwrap_or_default(),
+        })
+    }
+
+    fn run_program(&mut self, program: LaunchedProgram) -> std::io::Result<()> {
+        let mut interpreter = new_interpreter_with_io(
+            program.user_defined_types,
+            ConsoleInput(Rc::clone(&self.session)),
+            ConsoleOutput(Rc::clone(&self.session)),
+        );
+        interpreter
+            .stdlib_mut()
+            .set_command_line(program.command_line);
+        let mut debugger = AdapterDebugger {
+            session: Rc::clone(&self.session),
+            file_name: &program.file_name,
+            diagnostics: &program.diagnostics,
+            stop_on_entry: program.stop_on_entry,
+            references: vec![],
+            error: None,
+        };
+        let result = interpreter.debug(program.instruction_generator_result, &mut debugger);
+        drop(interpreter);
+        if let Some(e) = debugger.error {
+            return Err(e);
+        }
+        let mut session = self.session.borrow_mut();
+        if session.disconnected {
+            return Ok(());
+        }
+        session.flush_output()?;
+        let exit_code = match result {
+            Ok(_) => 0,
+            Err(e) => {
+                session.send_output("stderr", &program.diagnostics.render_runtime_error(&e))?;
+                e.err().get_code()
+            }
+        };
+        session
+            .connection
+            .send_event("exited", json!({ "exitCode": exit_code }))?;
+        session.connection.send_event("terminated", json!({}))
+    }
+}
+
+/// Identifies a variable (or a group of variables) that can be expanded
+/// in the variables view, e.g. a scope, an array, or a user defined type.
+/// The references are valid while the program is paused.
+#[derive(Clone)]
+struct Reference {
+    /// The index of the frame whose variables are referenced.
+    frame: usize,
+    /// The path to the expanded variable, empty for all variables of the frame.
+    path: Vec<PathStep>,
+}
+
+#[derive(Clone)]
+enum PathStep {
+    /// A variable of the frame, by its qualified name.
+    Variable(String),
+    /// An array element or a property of a user defined type, by its index.
+    Index(usize),
+}
+
+/// A child of an expanded [Reference].
+struct Child<'a> {
+    name: String,
+    step: PathStep,
+    value: &'a Variant,
+}
+
+struct AdapterDebugger<'a, W> {
+    session: SharedSession<W>,
+    file_name: &'a str,
+    diagnostics: &'a Diagnostics,
+    stop_on_entry: bool,
+    /// The variable references handed out during the current pause.
+    /// The reference id is the index plus one.
+    references: Vec<Reference>,
+    /// An error of the connection, which ends the program.
+    error: Option<std::io::Error>,
+}
+
+impl<W: Write> Debugger for AdapterDebugger<'_, W> {
+    fn on_start(&mut self) -> DebugAction {
+        if self.stop_on_entry {
+            DebugAction::StepInto
+        } else {
+            DebugAction::Continue
+        }
+    }
+
+    fn has_breakpoint(&self, row: u32) -> bool {
+        self.session.borrow().breakpoints.contains(&row)
+    }
+
+    fn on_pause(&mut self, pause: &Pause) -> DebugAction {
+        match self.serve_pause(pause) {
+            Ok(action) => action,
+            Err(e) => {
+                self.error = Some(e);
+                DebugAction::Quit
+            }
+        }
+    }
+
+    fn is_pause_requested(&mut self) -> bool {
+        match self.serve_running() {
+            Ok(is_requested) => is_requested,
+            Err(e) => {
+                self.error = Some(e);
+                true
+            }
+        }
+    }
+}
+
+impl<W: Write> AdapterDebugger<'_, W> {
+    /// Handles the requests that have arrived while the program runs.
+    /// Returns true if the program should pause, i.e. the client asked for it,
+    /// disconnected, or the connection failed.
+    fn serve_running(&mut self) -> std::io::Result<bool> {
+        let mut session = self.session.borrow_mut();
+        while let Some(request) = session.try_read_request()? {
+            match request.command.as_str() {
+                "pause" => {
+                    session.connection.respond(&request, json!({}))?;
+                    return Ok(true);
+                }
+                "evaluate" if request.str_argument("context") == Some("repl") => {
+                    session.add_input(&request)?;
+                }
+                _ => {
+                    if !session.handle_common_request(&request)? {
+                        session
+                            .connection
+                            .respond_error(&request, "The program is running.")?;
+                    }
+                }
+            }
+        }
+        Ok(session.disconnected)
+    }
+
+    /// Handles requests until the client resumes the program.
+    fn serve_pause(&mut self, pause: &Pause) -> std::io::Result<DebugAction> {
+        if self.error.is_some() || self.session.borrow().disconnected {
+            return Ok(DebugAction::Quit);
+        }
+        self.references.clear();
+        let (reason, description) = match pause.reason {
+            PauseReason::Step if self.stop_on_entry => {
+                // only the very first pause is the entry
+                self.stop_on_entry = false;
+                ("entry", "Paused on entry")
+            }
+            PauseReason::Step => ("step", "Paused after step"),
+            PauseReason::Breakpoint => ("breakpoint", "Paused on breakpoint"),
+            PauseReason::Stop => ("pause", "Paused on STOP statement"),
+            PauseReason::Requested => ("pause", "Paused"),
+        };
+        {
+            let mut session = self.session.borrow_mut();
+            session.flush_output()?;
+            session.connection.send_event(
+                "stopped",
+                json!({
+                    "reason": reason,
+                    "description": description,
+                    "threadId": THREAD_ID,
+                    "allThreadsStopped": true,
+                }),
+            )?;
+        }
+        let session = Rc::clone(&self.session);
+        loop {
+            let mut session = session.borrow_mut();
+            let Some(request) = session.read_request()? else {
+                return Ok(DebugAction::Quit);
+            };
+            let action = match request.command.as_str() {
+                "continue" => DebugAction::Continue,
+                "next" => DebugAction::StepOver,
+                "stepIn" => DebugAction::StepInto,
+                "stepOut" => DebugAction::StepOut,
+                "stackTrace" => {
+                    let body = self.stack_trace(pause);
+                    session.connection.respond(&request, body)?;
+                    continue;
+                }
+                "scopes" => {
+                    let frame = request.int_argument("frameId").unwrap_or_default();
+                    match self.scopes(pause, frame) {
+                        Some(body) => session.connection.respond(&request, body)?,
+                        None => session
+                            .connection
+                            .respond_error(&request, "Invalid frame.")?,
+                    }
+                    continue;
+                }
+                "variables" => {
+                    let reference = request
+                        .int_argument("variablesReference")
+                        .unwrap_or_default();
+                    match self.variables(pause, reference) {
+                        Some(body) => session.connection.respond(&request, body)?,
+                        None => session
+                            .connection
+                            .respond_error(&request, "Invalid variable reference.")?,
+                    }
+                    continue;
+                }
+                "evaluate" => {
+                    match self.evaluate(pause, &request) {
+                        Ok(body) => session.connection.respond(&request, body)?,
+                        Err(message) => session.connection.respond_error(&request, &message)?,
+                    }
+                    continue;
+                }
+                "pause" => {
+                    session.connection.respond(&request, json!({}))?;
+                    continue;
+                }
+                _ => {
+                    if !session.handle_common_request(&request)? {
+                        session
+                            .connection
+                            .respond_error(&request, "Unsupported request.")?;
+                    }
+                    if session.disconnected {
+                        return Ok(DebugAction::Quit);
+                    }
+                    continue;
+                }
+            };
+            let body = if action == DebugAction::Continue {
+                json!({ "allThreadsContinued": true })
+            } else {
+                json!({})
+            };
+            session.connection.respond(&request, body)?;
+            return Ok(action);
+        }
+    }
+
+    fn stack_trace(&self, pause: &Pause) -> Value {
+        let frames: Vec<Value> = pause
+            .frames
+            .iter()
+            .enumerate()
+            .map(|(index, frame)| {
+                json!({
+                    "id": index,
+                    "name": self.diagnostics.subprogram_name(frame.pos),
+                    "source": { "path": self.file_name },
+                    "line": frame.pos.row(),
+                    "column": frame.pos.col(),
+                })
+            })
+            .collect();
+        json!({ "stackFrames": frames, "totalFrames": pause.frames.len() })
+    }
+
+    /// Gets the scopes of the given frame: the local variables of a SUB/FUNCTION
+    /// (or its static variables, for a STATIC SUB/FUNCTION) and the module-level
+    /// variables, of which the SUB/FUNCTION can access only the SHARED ones.
+    fn scopes(&mut self, pause: &Pause, frame: i64) -> Option<Value> {
+        let frame = usize::try_from(frame).ok()?;
+        let module_frame = pause.frames.len() - 1;
+        let scopes = if frame == module_frame {
+            vec![("Module", frame)]
+        } else if pause.frames.get(frame)?.is_static() {
+            vec![("Static", frame), ("Module", module_frame)]
+        } else {
+            vec![("Local", frame), ("Module", module_frame)]
+        };
+        let scopes: Vec<Value> = scopes
+            .into_iter()
+            .map(|(name, frame)| {
+                let reference = self.add_reference(Reference {
+                    frame,
+                    path: vec![],
+                });
+                json!({ "name": name, "variablesReference": reference, "expensive": false })
+            })
+            .collect();
+        Some(json!({ "scopes": scopes }))
+    }
+
+    fn variables(&mut self, pause: &Pause, reference: i64) -> Option<Value> {
+        let index = usize::try_from(reference).ok()?.checked_sub(1)?;
+        let reference = self.references.get(index)?.clone();
+        let variables: Vec<Value> = children(pause, &reference)?
+            .into_iter()
+            .map(|child| {
+                let mut path = reference.path.clone();
+                path.push(child.step);
+                let reference = self.reference_of(child.value, reference.frame, path);
+                json!({
+                    "name": child.name,
+                    "value": format_value(child.value),
+                    "variablesReference": reference,
+                })
+            })
+            .collect();
+        Some(json!({ "variables": variables }))
+    }
+
+    /// Shows the value of a variable, e.g. when hovering over it.
+    /// Variables that are not found in the given frame are looked up
+    /// in the module-level variables.
+    fn evaluate(&mut self, pause: &Pause, request: &Request) -> Result<Value, String> {
+        let expression = request
+            .str_argument("expression")
+            .unwrap_or_default()
+            .trim();
+        let frame = request
+            .int_argument("frameId")
+            .and_then(|frame| usize::try_from(frame).ok())
+            .unwrap_or_default();
+        let module_frame = pause.frames.len() - 1;
+        let (frame, (name, value)) = [frame, module_frame]
+            .into_iter()
+            .filter_map(|frame| {
+                pause
+                    .frames
+                    .get(frame)?
+                    .find_variable(expression)
+                    .map(|variable| (frame, variable))
+            })
+            .next()
+            .ok_or_else(|| format!("Variable {} not found.", expression))?;
+        let reference = self.reference_of(value, frame, vec![PathStep::Variable(name.to_string())]);
+        Ok(json!({ "result": format_value(value), "variablesReference": reference }))
+    }
+
+    /// Gets the reference for expanding the given value,
+    /// or zero if the value cannot be expanded.
+    fn reference_of(&mut self, value: &Variant, frame: usize, path: Vec<PathStep>) -> usize {
+        match value {
+            Variant::VArray(_) | Variant::VUserDefined(_) => {
+                self.add_reference(Reference { frame, path })
+            }
+            _ => 0,
+        }
+    }
+
+    fn add_reference(&mut self, reference: Reference) -> usize {
+        self.references.push(reference);
+        self.references.len()
+    }
+}
+
+/// Gets the children of the given reference:
+/// the variables of a frame, the elements of an array, or the properties
+/// of a user defined type.
+fn children<'a>(pause: &'a Pause, reference: &Reference) -> Option<Vec<Child<'a>>> {
+    let frame = pause.frames.get(reference.frame)?;
+    let Some((first, rest)) = reference.path.split_first() else {
+        return Some(
+            frame
+                .variables()
+                .map(|(name, value)| Child {
+                    name: name.to_string(),
+                    step: PathStep::Variable(name.to_string()),
+                    value,
+                })
+                .collect(),
+        );
+    };
+    let PathStep::Variable(variable_name) = first else {
+        return None;
+    };
+    let mut value = frame
+        .variables()
+        .find(|(name, _)| name.to_string() == *variable_name)
+        .map(|(_, value)| value)?;
+    for step in rest {
+        let PathStep::Index(index) = step else {
+            return None;
+        };
+        value = match value {
+            Variant::VArray(array) => array.get(*index)?,
+            Variant::VUserDefined(user_defined_value) => user_defined_value.values().nth(*index)?,
+            _ => return None,
+        };
+    }
+    match value {
+        Variant::VArray(array) => Some(
+            (0..array.len())
+                .filter_map(|index| array.get(index).map(|value| (index, value)))
+                .map(|(index, value)| Child {
+                    name: element_name(array, index),
+                    step: PathStep::Index(index),
+                    value,
+                })
+                .collect(),
+        ),
+        Variant::VUserDefined(user_defined_value) => Some(
+            user_defined_value
+                .names()
+                .zip(user_defined_value.values())
+                .enumerate()
+                .map(|(index, (name, value))| Child {
+                    name: name.to_string(),
+                    step: PathStep::Index(index),
+                    value,
+                })
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Formats the subscripts of an array element, e.g. `(1, 2)`.
+/// The last dimension changes the fastest.
+fn element_name(array: &rusty_variant::VArray, mut index: usize) -> String {
+    let dimensions: Vec<(i32, i32)> = (0..)
+        .map_while(|dimension| array.get_dimension_bounds(dimension))
+        .copied()
+        .collect();
+    let mut subscripts: Vec<String> = dimensions
+        .iter()
+        .rev()
+        .map(|(lbound, ubound)| {
+            let size = (ubound - lbound + 1) as usize;
+            let subscript = lbound + (index % size) as i32;
+            index /= size;
+            subscript.to_string()
+        })
+        .collect();
+    subscripts.reverse();
+    format!("({})", subscripts.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::io::Read;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    /// The output of the adapter, which the [Script] watches for events.
+    #[derive(Clone, Default)]
+    struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// The input of the adapter. Since requests are read while the program
+    /// runs, the script can wait for an event (see [wait_for]) before sending
+    /// the next requests, e.g. the requests that need a paused program.
+    struct Script {
+        steps: VecDeque<Value>,
+        output: SharedOutput,
+        /// The number of events waited for so far, per event.
+        waited: Vec<(String, usize)>,
+        buffer: VecDeque<u8>,
+    }
+
+    impl Script {
+        /// Waits until the adapter has sent the given event once more than
+        /// the last time. Gives up after a while, closing the input.
+        fn wait_for(&mut self, event: &str) -> bool {
+            let count = match self.waited.iter_mut().find(|(name, _)| name == event) {
+                Some((_, count)) => count,
+                None => {
+                    self.waited.push((event.to_owned(), 0));
+                    &mut self.waited.last_mut().unwrap().1
+                }
+            };
+            *count += 1;
+            let expected = *count;
+            let pattern = format!("\"event\":\"{}\"", event);
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while Instant::now() < deadline {
+                let output = String::from_utf8_lossy(&self.output.0.lock().unwrap()).into_owned();
+                if output.matches(&pattern).count() >= expected {
+                    return true;
+                }
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            false
+        }
+    }
+
+    impl Read for Script {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            while self.buffer.is_empty() {
+                let Some(step) = self.steps.pop_front() else {
+                    return Ok(0);
+                };
+                if let Some(event) = step["waitFor"].as_str() {
+                    if !self.wait_for(event) {
+                        return Ok(0);
+                    }
+                } else {
+                    let content = step.to_string();
+                    self.buffer.extend(
+                        format!("Content-Length: {}\r\n\r\n{}", content.len(), content).bytes(),
+                    );
+                }
+            }
+            let n = buf.len().min(self.buffer.len());
+            for (dest, src) in buf.iter_mut().zip(self.buffer.drain(..n)) {
+                *dest = src;
+            }
+            Ok(n)
+        }
+    }
+
+    /// Runs the given program under the adapter, sending the given requests
+    /// after the usual initialization ones. Returns the messages sent by the adapter.
+    fn run_script(
+        test_name: &str,
+        source: &str,
+        stop_on_entry: bool,
+        requests: &[Value],
+    ) -> Vec<Value> {
+        let path = std::env::temp_dir().join(format!("rusty_dap_{}.bas", test_name));
+        std::fs::write(&path, source).unwrap();
+        let program = path.to_str().unwrap();
+        let launch_arguments = json!({ "program": program, "stopOnEntry": stop_on_entry });
+        let mut all_requests = vec![
+            json!({ "command": "initialize", "arguments": { "adapterID": "rusty_basic" } }),
+            json!({ "command": "launch", "arguments": launch_arguments }),
+        ];
+        all_requests.extend_from_slice(requests);
+        for (index, request) in all_requests.iter_mut().enumerate() {
+            if request.get("command").is_some() {
+                request["seq"] = json!(index + 1);
+                request["type"] = json!("request");
+            }
+        }
+        let output = SharedOutput::default();
+        let script = Script {
+            steps: all_requests.into(),
+            output: output.clone(),
+            waited: vec![],
+            buffer: VecDeque::new(),
+        };
+        serve(std::io::BufReader::new(script), output.clone()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut messages = vec![];
+        let mut remaining = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        while let Some((header, rest)) = remaining.split_once("\r\n\r\n") {
+            let length: usize = header
+                .trim_start_matches("Content-Length: ")
+                .parse()
+                .unwrap();
+            messages.push(serde_json::from_str(&rest[..length]).unwrap());
+            remaining = rest[length..].to_owned();
+        }
+        messages
+    }
+
+    /// A step of the script that waits for the given event.
+    fn wait_for(event: &str) -> Value {
+        json!({ "waitFor": event })
+    }
+
+    fn request(command: &str, arguments: Value) -> Value {
+        json!({ "command": command, "arguments": arguments })
+    }
+
+    /// Gets the bodies of the responses to the given command.
+    fn responses<'a>(messages: &'a [Value], command: &str) -> Vec<&'a Value> {
+        messages
+            .iter()
+            .filter(|m| m["type"] == "response" && m["command"] == command)
+            .collect()
+    }
+
+    /// Gets the bodies of the given events.
+    fn events<'a>(messages: &'a [Value], event: &str) -> Vec<&'a Value> {
+        messages
+            .iter()
+            .filter(|m| m["type"] == "event" && m["event"] == event)
+            .map(|m| &m["body"])
+            .collect()
+    }
+
+    /// Gets the output of the program.
+    fn stdout(messages: &[Value]) -> String {
+        events(messages, "output")
+            .into_iter()
+            .filter(|body| body["category"] == "stdout")
+            .map(|body| body["output"].as_str().unwrap())
+            .collect()
+    }
+
+    /// Gets the names and values of the variables of a `variables` response.
+    fn variables(response: &Value) -> Vec<String> {
+        response["body"]["variables"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| {
+                format!(
+                    "{} = {} ({})",
+                    v["name"].as_str().unwrap(),
+                    v["value"].as_str().unwrap(),
+                    v["variablesReference"]
+                )
+            })
+            .collect()
+    }
+
+    const PROGRAM: &str = r#"TYPE Card
+    Suit AS STRING * 1
+    Value AS INTEGER
+END TYPE
+DIM SHARED C AS Card
+DIM A(1 TO 2, 1 TO 2) AS INTEGER
+C.Suit = "H"
+C.Value = 10
+A(2, 1) = 5
+PRINT "start"
+Show 42
+PRINT "end"
+
+SUB Show(X%)
+    Y = X% * 2
+    PRINT Y
+END SUB
+"#;
+
+    #[test]
+    fn test_breakpoint_and_variables() {
+        let messages = run_script(
+            "breakpoint",
+            PROGRAM,
+            false,
+            &[
+                request(
+                    "setBreakpoints",
+                    json!({ "source": {}, "breakpoints": [{ "line": 16 }] }),
+                ),
+                request("configurationDone", json!({})),
+                wait_for("stopped"),
+                request("stackTrace", json!({ "threadId": THREAD_ID })),
+                request("scopes", json!({ "frameId": 0 })),
+                request("variables", json!({ "variablesReference": 1 })),
+                request("variables", json!({ "variablesReference": 2 })),
+                request("variables", json!({ "variablesReference": 3 })),
+                request("variables", json!({ "variablesReference": 4 })),
+                request("evaluate", json!({ "expression": "x", "frameId": 0 })),
+                request("continue", json!({ "threadId": THREAD_ID })),
+                wait_for("terminated"),
+                request("disconnect", json!({})),
+            ],
+        );
+        let stack_trace = &responses(&messages, "stackTrace")[0]["body"]["stackFrames"];
+        assert_eq!(stack_trace[0]["name"], "Show");
+        assert_eq!(stack_trace[0]["line"], 16);
+        assert_eq!(stack_trace[1]["name"], "module-level code");
+        assert_eq!(stack_trace[1]["line"], 11);
+        let scopes = &responses(&messages, "scopes")[0]["body"]["scopes"];
+        assert_eq!(scopes[0]["name"], "Local");
+        assert_eq!(scopes[1]["name"], "Module");
+        let variables: Vec<Vec<String>> = responses(&messages, "variables")
+            .into_iter()
+            .map(variables)
+            .collect();
+        assert_eq!(variables[0], vec!["X% = 42 (0)", "Y! = 84 (0)"]);
+        assert_eq!(
+            variables[1],
+            vec![
+                "C = { Suit: \"H\", Value: 10 } (3)",
+                "A% = (1 TO 2, 1 TO 2) [0, 0, 5, 0] (4)"
+            ]
+        );
+        assert_eq!(variables[2], vec!["Suit = \"H\" (0)", "Value = 10 (0)"]);
+        assert_eq!(
+            variables[3],
+            vec![
+                "(1, 1) = 0 (0)",
+                "(1, 2) = 0 (0)",
+                "(2, 1) = 5 (0)",
+                "(2, 2) = 0 (0)"
+            ]
+        );
+        assert_eq!(responses(&messages, "evaluate")[0]["body"]["result"], "42");
+        assert_eq!(events(&messages, "stopped")[0]["reason"], "breakpoint");
+        assert_eq!(stdout(&messages), "start\n 84 \nend\n");
+        assert_eq!(events(&messages, "exited")[0]["exitCode"], 0);
+        assert_eq!(events(&messages, "terminated").len(), 1);
+    }
+
+    #[test]
+    fn test_stepping() {
+        let stack_trace = request("stackTrace", json!({ "threadId": THREAD_ID }));
+        let messages = run_script(
+            "stepping",
+            PROGRAM,
+            false,
+            &[
+                request(
+                    "setBreakpoints",
+                    json!({ "source": {}, "breakpoints": [{ "line": 11 }] }),
+                ),
+                request("configurationDone", json!({})),
+                wait_for("stopped"),
+                request("stepIn", json!({ "threadId": THREAD_ID })),
+                wait_for("stopped"),
+                stack_trace.clone(),
+                request("next", json!({ "threadId": THREAD_ID })),
+                wait_for("stopped"),
+                stack_trace.clone(),
+                request("stepOut", json!({ "threadId": THREAD_ID })),
+                wait_for("stopped"),
+                stack_trace,
+                request("continue", json!({ "threadId": THREAD_ID })),
+                wait_for("terminated"),
+                request("disconnect", json!({})),
+            ],
+        );
+        let reasons: Vec<&Value> = events(&messages, "stopped")
+            .into_iter()
+            .map(|body| &body["reason"])
+            .collect();
+        assert_eq!(reasons, vec!["breakpoint", "step", "step", "step"]);
+        let lines: Vec<Vec<i64>> = responses(&messages, "stackTrace")
+            .into_iter()
+            .map(|response| {
+                response["body"]["stackFrames"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|frame| frame["line"].as_i64().unwrap())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(lines, vec![vec![15, 11], vec![16, 11], vec![12]]);
+        assert_eq!(stdout(&messages), "start\n 84 \nend\n");
+    }
+
+    #[test]
+    fn test_input_from_debug_console() {
+        let messages = run_script(
+            "input",
+            "PRINT \"Name\";\nINPUT N$\nPRINT \"Hello, \"; N$",
+            false,
+            &[
+                request("configurationDone", json!({})),
+                // the prompt, then the message about waiting for input
+                wait_for("output"),
+                wait_for("output"),
+                request(
+                    "evaluate",
+                    json!({ "expression": "World", "context": "repl" }),
+                ),
+                wait_for("terminated"),
+                request("disconnect", json!({})),
+            ],
+        );
+        assert_eq!(stdout(&messages), "NameHello, World\n");
+        assert!(
+            events(&messages, "output")
+                .iter()
+                .any(|body| body["category"] == "console")
+        );
+        assert_eq!(events(&messages, "exited")[0]["exitCode"], 0);
+    }
+
+    #[test]
+    fn test_stop_on_entry_and_static_scope() {
+        let input = r#"Counter
+Counter
+
+SUB Counter STATIC
+    N = N + 1
+    STOP
+END SUB
+"#;
+        let messages = run_script(
+            "static",
+            input,
+            true,
+            &[
+                request("configurationDone", json!({})),
+                wait_for("stopped"),
+                request("continue", json!({ "threadId": THREAD_ID })),
+                wait_for("stopped"),
+                request("scopes", json!({ "frameId": 0 })),
+                request("continue", json!({ "threadId": THREAD_ID })),
+                wait_for("stopped"),
+                request("scopes", json!({ "frameId": 0 })),
+                request("variables", json!({ "variablesReference": 1 })),
+                request("disconnect", json!({})),
+            ],
+        );
+        let reasons: Vec<&Value> = events(&messages, "stopped")
+            .into_iter()
+            .map(|body| &body["reason"])
+            .collect();
+        assert_eq!(reasons, vec!["entry", "pause", "pause"]);
+        assert_eq!(
+            responses(&messages, "scopes")[1]["body"]["scopes"][0]["name"],
+            "Static"
+        );
+        assert_eq!(
+            variables(responses(&messages, "variables")[0]),
+            vec!["N! = 2 (0)"]
+        );
+        // the program ended because of the disconnect
+        assert!(events(&messages, "exited").is_empty());
+    }
+
+    #[test]
+    fn test_launch_with_syntax_error() {
+        let messages = run_script(
+            "syntax_error",
+            "PRINT 1\nPRINT 2 +",
+            false,
+            &[request("disconnect", json!({}))],
+        );
+        let launch = responses(&messages, "launch")[0];
+        assert_eq!(launch["success"], false);
+        assert!(launch["message"].as_str().unwrap().contains("Expected"));
+    }
+
+    #[test]
+    fn test_runtime_error() {
+        let messages = run_script(
+            "runtime_error",
+            "PRINT 1\nX = 1 / 0",
+            false,
+            &[
+                request("configurationDone", json!({})),
+                wait_for("terminated"),
+            ],
+        );
+        assert_eq!(stdout(&messages), " 1 \n");
+        assert_eq!(events(&messages, "exited")[0]["exitCode"], 11);
+        assert!(
+            events(&messages, "output")
+                .iter()
+                .any(|body| body["category"] == "stderr")
+        );
+    }
+
+    const INFINITE_LOOP: &str = r#"X = 0
+WHILE 1
+    X = X + 1
+    Y = X
+WEND
+"#;
+
+    #[test]
+    fn test_pause_while_running() {
+        let messages = run_script(
+            "pause",
+            INFINITE_LOOP,
+            false,
+            &[
+                request("configurationDone", json!({})),
+                request("pause", json!({ "threadId": THREAD_ID })),
+                wait_for("stopped"),
+                request("stackTrace", json!({ "threadId": THREAD_ID })),
+                request("disconnect", json!({})),
+            ],
+        );
+        assert_eq!(responses(&messages, "pause")[0]["success"], true);
+        let stopped = events(&messages, "stopped");
+        assert_eq!(stopped.len(), 1);
+        assert_eq!(stopped[0]["reason"], "pause");
+        assert_eq!(stopped[0]["description"], "Paused");
+        let stack_trace = &responses(&messages, "stackTrace")[0]["body"]["stackFrames"];
+        assert_eq!(stack_trace[0]["name"], "module-level code");
+        // the program ended because of the disconnect
+        assert!(events(&messages, "exited").is_empty());
+    }
+
+    #[test]
+    fn test_set_breakpoints_while_running() {
+        let messages = run_script(
+            "breakpoint_while_running",
+            INFINITE_LOOP,
+            false,
+            &[
+                request("configurationDone", json!({})),
+                request(
+                    "setBreakpoints",
+                    json!({ "source": {}, "breakpoints": [{ "line": 4 }] }),
+                ),
+                wait_for("stopped"),
+                request("stackTrace", json!({ "threadId": THREAD_ID })),
+                request("disconnect", json!({})),
+            ],
+        );
+        assert_eq!(
+            responses(&messages, "setBreakpoints")[0]["body"]["breakpoints"][0]["verified"],
+            true
+        );
+        assert_eq!(events(&messages, "stopped")[0]["reason"], "breakpoint");
+        let stack_trace = &responses(&messages, "stackTrace")[0]["body"]["stackFrames"];
+        assert_eq!(stack_trace[0]["line"], 4);
+    }
+
+    #[test]
+    fn test_closing_the_connection_ends_a_running_program() {
+        let messages = run_script(
+            "closed_while_running",
+            INFINITE_LOOP,
+            false,
+            &[request("configurationDone", json!({}))],
+        );
+        assert!(events(&messages, "stopped").is_empty());
+        assert!(events(&messages, "exited").is_empty());
+    }
+}
@@ -0,0 +1,21 @@
+//! A Debug Adapter Protocol server (e.g. for VS Code),
+//! which communicates with the client over the standard input and output.
+//!
+//! The output of the program appears in the debug console, where the user
+//! also types the input of the program.
+
+mod adapter;
+mod protocol;
+mod session;
+
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    match adapter::serve(std::io::BufReader::new(std::io::stdin()), std::io::stdout()) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
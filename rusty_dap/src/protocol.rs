@@ -0,0 +1,204 @@
+//! The wire format of the Debug Adapter Protocol:
+//! JSON messages, each one preceded by a `Content-Length` header.
+
+use std::io::{BufRead, Error, ErrorKind, Write};
+use std::sync::mpsc::{Receiver, channel};
+
+use serde_json::{Value, json};
+
+/// A request sent by the client (e.g. VS Code).
+pub struct Request {
+    pub seq: i64,
+    pub command: String,
+    pub arguments: Value,
+}
+
+impl Request {
+    /// Gets an integer argument of the request.
+    pub fn int_argument(&self, name: &str) -> Option<i64> {
+        self.arguments.get(name).and_then(Value::as_i64)
+    }
+
+    /// Gets a string argument of the request.
+    pub fn str_argument(&self, name: &str) -> Option<&str> {
+        self.arguments.get(name).and_then(Value::as_str)
+    }
+}
+
+/// Reads the requests in a background thread, so that they can be received
+/// while the program runs. The channel closes when the client closes the
+/// connection, or after an error.
+pub fn spawn_request_reader<R: BufRead + Send + 'static>(
+    reader: R,
+) -> Receiver<std::io::Result<Request>> {
+    let (sender, receiver) = channel();
+    std::thread::spawn(move || {
+        let mut reader = RequestReader { reader };
+        loop {
+            let result = reader.read_request();
+            let is_last = !matches!(result, Ok(Some(_)));
+            if let Some(result) = result.transpose()
+                && sender.send(result).is_err()
+            {
+                break;
+            }
+            if is_last {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+/// Reads requests.
+struct RequestReader<R> {
+    reader: R,
+}
+
+impl<R: BufRead> RequestReader<R> {
+    /// Reads the next request, skipping any other messages.
+    /// Returns `None` when the client closes the connection.
+    pub fn read_request(&mut self) -> std::io::Result<Option<Request>> {
+        loop {
+            let Some(message) = self.read_message()? else {
+                return Ok(None);
+            };
+            if message["type"] != "request" {
+                continue;
+            }
+            let seq = message["seq"].as_i64().unwrap_or_default();
+            let command = message["command"].as_str().unwrap_or_default().to_owned();
+            let arguments = message.get("arguments").cloned().unwrap_or(Value::Null);
+            return Ok(Some(Request {
+                seq,
+                command,
+                arguments,
+            }));
+        }
+    }
+
+    fn read_message(&mut self) -> std::io::Result<Option<Value>> {
+        let mut content_length: Option<usize> = None;
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                if content_length.is_some() {
+                    break;
+                }
+                // tolerate blank lines between messages
+                continue;
+            }
+            if let Some((name, value)) = line.split_once(':')
+                && name.trim().eq_ignore_ascii_case("Content-Length")
+            {
+                content_length = Some(value.trim().parse().map_err(|_| {
+                    Error::new(ErrorKind::InvalidData, "Invalid Content-Length header")
+                })?);
+            }
+        }
+        let mut buf = vec![0; content_length.unwrap_or_default()];
+        self.reader.read_exact(&mut buf)?;
+        serde_json::from_slice(&buf)
+            .map(Some)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+}
+
+/// Sends responses and events.
+pub struct Connection<W> {
+    writer: W,
+    /// The sequence number of the last message sent.
+    seq: i64,
+}
+
+impl<W: Write> Connection<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer, seq: 0 }
+    }
+
+    pub fn into_writer(self) -> W {
+        self.writer
+    }
+
+    /// Sends a successful response to the given request.
+    pub fn respond(&mut self, request: &Request, body: Value) -> std::io::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request.seq,
+            "command": request.command,
+            "success": true,
+            "body": body,
+        }))
+    }
+
+    /// Sends a failed response to the given request.
+    /// The client shows the message to the user.
+    pub fn respond_error(&mut self, request: &Request, message: &str) -> std::io::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request.seq,
+            "command": request.command,
+            "success": false,
+            "message": message,
+            "body": { "error": { "id": 1, "format": message, "showUser": true } },
+        }))
+    }
+
+    pub fn send_event(&mut self, event: &str, body: Value) -> std::io::Result<()> {
+        self.send(json!({
+            "type": "event",
+            "event": event,
+            "body": body,
+        }))
+    }
+
+    fn send(&mut self, mut message: Value) -> std::io::Result<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        let content = message.to_string();
+        write!(
+            self.writer,
+            "Content-Length: {}\r\n\r\n{}",
+            content.len(),
+            content
+        )?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_read_request() {
+        let content = r#"{"seq":3,"type":"request","command":"next","arguments":{"threadId":1}}"#;
+        let input = format!("Content-Length: {}\r\n\r\n{}", content.len(), content);
+        let mut reader = RequestReader {
+            reader: Cursor::new(input),
+        };
+        let request = reader.read_request().unwrap().unwrap();
+        assert_eq!(request.seq, 3);
+        assert_eq!(request.command, "next");
+        assert_eq!(request.int_argument("threadId"), Some(1));
+        assert!(reader.read_request().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_send_event() {
+        let mut connection = Connection::new(vec![]);
+        connection.send_event("initialized", json!({})).unwrap();
+        let output = String::from_utf8(connection.into_writer()).unwrap();
+        let content = r#"{"body":{},"event":"initialized","seq":1,"type":"event"}"#;
+        assert_eq!(
+            output,
+            format!("Content-Length: {}\r\n\r\n{}", content.len(), content)
+        );
+    }
+}
@@ -0,0 +1,197 @@
+//! The state that the adapter shares with the program it runs.
+//! The `PRINT` output of the program is sent as `output` events,
+//! while `INPUT` is read from the `evaluate` requests of the debug console.
+
+use std::cell::RefCell;
+use std::collections::{BTreeSet, VecDeque};
+use std::io::{Read, Write};
+use std::rc::Rc;
+use std::sync::mpsc::{Receiver, TryRecvError};
+
+use serde_json::json;
+
+use crate::protocol::{Connection, Request};
+
+/// The only thread of the program.
+pub const THREAD_ID: i64 = 1;
+
+pub struct Session<W> {
+    /// The requests of the client, read in a background thread.
+    requests: Receiver<std::io::Result<Request>>,
+    pub connection: Connection<W>,
+    /// The source lines that have a breakpoint.
+    pub breakpoints: BTreeSet<u32>,
+    /// Set when the client disconnects, or closes the connection.
+    pub disconnected: bool,
+    /// Input typed in the debug console, not yet read by the program.
+    input: VecDeque<u8>,
+    /// Output of the program since the last line break.
+    output: String,
+}
+
+pub type SharedSession<W> = Rc<RefCell<Session<W>>>;
+
+impl<W: Write> Session<W> {
+    pub fn new(requests: Receiver<std::io::Result<Request>>, connection: Connection<W>) -> Self {
+        Self {
+            requests,
+            connection,
+            breakpoints: BTreeSet::new(),
+            disconnected: false,
+            input: VecDeque::new(),
+            output: String::new(),
+        }
+    }
+
+    /// Reads the next request. When the client closes the connection,
+    /// the session is marked as disconnected.
+    pub fn read_request(&mut self) -> std::io::Result<Option<Request>> {
+        match self.requests.recv() {
+            Ok(result) => result.map(Some),
+            Err(_) => {
+                self.disconnected = true;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Returns the next request if one has already arrived,
+    /// without waiting for it.
+    pub fn try_read_request(&mut self) -> std::io::Result<Option<Request>> {
+        match self.requests.try_recv() {
+            Ok(result) => result.map(Some),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => {
+                self.disconnected = true;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Handles the requests that are answered the same way
+    /// whether the program is running or not.
+    /// Returns false if the request is not one of them.
+    pub fn handle_common_request(&mut self, request: &Request) -> std::io::Result<bool> {
+        match request.command.as_str() {
+            "setBreakpoints" => {
+                let lines: Vec<u32> = request.arguments["breakpoints"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|breakpoint| breakpoint["line"].as_u64())
+                    .filter_map(|line| u32::try_from(line).ok())
+                    .collect();
+                self.breakpoints = lines.iter().copied().collect();
+                let breakpoints: Vec<_> = lines
+                    .into_iter()
+                    .map(|line| json!({ "verified": true, "line": line }))
+                    .collect();
+                self.connection
+                    .respond(request, json!({ "breakpoints": breakpoints }))?;
+            }
+            "setExceptionBreakpoints" => {
+                self.connection.respond(request, json!({}))?;
+            }
+            "threads" => {
+                self.connection.respond(
+                    request,
+                    json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] }),
+                )?;
+            }
+            "disconnect" | "terminate" => {
+                self.disconnected = true;
+                self.connection.respond(request, json!({}))?;
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Takes an expression typed in the debug console as input for the program.
+    pub fn add_input(&mut self, request: &Request) -> std::io::Result<()> {
+        let expression = request.str_argument("expression").unwrap_or_default();
+        self.input.extend(expression.bytes());
+        self.input.push_back(b'\n');
+        self.connection
+            .respond(request, json!({ "result": "", "variablesReference": 0 }))
+    }
+
+    /// Sends the given text as an `output` event.
+    pub fn send_output(&mut self, category: &str, text: &str) -> std::io::Result<()> {
+        self.connection
+            .send_event("output", json!({ "category": category, "output": text }))
+    }
+
+    /// Buffers the output of the program, sending it one line at a time.
+    fn write_output(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        self.output
+            .push_str(&String::from_utf8_lossy(buf).replace('\r', ""));
+        if let Some(index) = self.output.rfind('\n') {
+            let lines: String = self.output.drain(..=index).collect();
+            self.send_output("stdout", &lines)?;
+        }
+        Ok(())
+    }
+
+    /// Sends the output of the program that does not end with a line break yet
+    /// (e.g. the prompt of `INPUT`).
+    pub fn flush_output(&mut self) -> std::io::Result<()> {
+        if !self.output.is_empty() {
+            let text = std::mem::take(&mut self.output);
+            self.send_output("stdout", &text)?;
+        }
+        Ok(())
+    }
+
+    /// Waits until the user types something in the debug console,
+    /// handling any other requests in the meantime.
+    fn read_input(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.input.is_empty() && !self.disconnected {
+            self.flush_output()?;
+            self.send_output(
+                "console",
+                "Waiting for input, type it in the debug console.\n",
+            )?;
+        }
+        while self.input.is_empty() && !self.disconnected {
+            let Some(request) = self.read_request()? else {
+                break;
+            };
+            if request.command == "evaluate" {
+                self.add_input(&request)?;
+            } else if !self.handle_common_request(&request)? {
+                self.connection
+                    .respond_error(&request, "The program is waiting for input.")?;
+            }
+        }
+        let n = buf.len().min(self.input.len());
+        for (dest, src) in buf.iter_mut().zip(self.input.drain(..n)) {
+            *dest = src;
+        }
+        Ok(n)
+    }
+}
+
+/// The standard output of the program.
+pub struct ConsoleOutput<W>(pub SharedSession<W>);
+
+impl<W: Write> Write for ConsoleOutput<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write_output(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        // the output is sent per line, or before waiting for input
+        Ok(())
+    }
+}
+
+/// The standard input of the program.
+pub struct ConsoleInput<W>(pub SharedSession<W>);
+
+impl<W: Write> Read for ConsoleInput<W> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().read_input(buf)
+    }
+}
@@ -0,0 +1,59 @@
+use rusty_common::{AtPos, Position};
+use rusty_parser::Expressions;
+
+use crate::built_ins::arg_validation::ArgValidation;
+use crate::core::{LintError, LintErrorPos};
+
+pub fn lint(args: &Expressions, pos: Position) -> Result<(), LintErrorPos> {
+    if args.is_empty() || args.len() > 2 {
+        Err(LintError::ArgumentCountMismatch.at_pos(pos))
+    } else {
+        args.require_numeric_argument(0)?;
+        if args.len() == 2 {
+            args.require_string_argument(1)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::LintError;
+    use crate::tests::test_utils::linter_ok;
+    use crate::{assert_linter_err, assert_linter_err_in_tests};
+
+    #[test]
+    fn test_assert_linter_err() {
+        assert_linter_err_in_tests!("ASSERT", LintError::ArgumentCountMismatch, 1, 1);
+        assert_linter_err_in_tests!(r#"ASSERT "yes""#, LintError::ArgumentTypeMismatch, 1, 8);
+        assert_linter_err_in_tests!(r#"ASSERT 1, 2"#, LintError::ArgumentTypeMismatch, 1, 11);
+        assert_linter_err_in_tests!(
+            r#"ASSERT 1, "a", "b""#,
+            LintError::ArgumentCountMismatch,
+            1,
+            1
+        );
+    }
+
+    #[test]
+    fn test_assert_is_only_built_in_for_tests() {
+        assert_linter_err!("ASSERT 1", LintError::SubprogramNotDefined, 1, 1);
+        linter_ok(
+            r#"
+            Assert 1
+            SUB Assert(X)
+            END SUB
+            "#,
+        );
+        assert_linter_err_in_tests!(
+            r#"
+            SUB Assert(X)
+            END SUB
+            "#,
+            LintError::DuplicateDefinition,
+            2,
+            13
+        );
+    }
+}
@@ -0,0 +1,39 @@
+use rusty_common::{AtPos, Position};
+use rusty_parser::{ExpressionType, Expressions, HasExpressionType, TypeQualifier};
+
+use crate::built_ins::arg_validation::ArgValidation;
+use crate::core::{LintError, LintErrorPos};
+
+pub fn lint(args: &Expressions, pos: Position) -> Result<(), LintErrorPos> {
+    if args.len() != 2 {
+        Err(LintError::ArgumentCountMismatch.at_pos(pos))
+    } else if args[0].expression_type() == ExpressionType::BuiltIn(TypeQualifier::DollarString) {
+        args.require_string_argument(1)
+    } else {
+        args.require_numeric_argument(0)?;
+        args.require_numeric_argument(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_linter_err_in_tests;
+    use crate::core::LintError;
+
+    #[test]
+    fn test_assert_equal_linter_err() {
+        assert_linter_err_in_tests!("ASSERTEQUAL 1", LintError::ArgumentCountMismatch, 1, 1);
+        assert_linter_err_in_tests!(
+            r#"ASSERTEQUAL "a", 1"#,
+            LintError::ArgumentTypeMismatch,
+            1,
+            18
+        );
+        assert_linter_err_in_tests!(
+            r#"ASSERTEQUAL 1, "a""#,
+            LintError::ArgumentTypeMismatch,
+            1,
+            16
+        );
+    }
+}
@@ -0,0 +1,25 @@
+use rusty_common::Position;
+use rusty_parser::Expressions;
+
+use crate::built_ins::arg_validation::ArgValidation;
+use crate::core::LintErrorPos;
+
+pub fn lint(args: &Expressions, pos: Position) -> Result<(), LintErrorPos> {
+    args.require_zero_arguments(pos)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_linter_err;
+    use crate::core::LintError;
+
+    #[test]
+    fn test_command_with_args_linter_err() {
+        assert_linter_err!("X$ = COMMAND$(1)", LintError::ArgumentCountMismatch, 1, 6);
+    }
+
+    #[test]
+    fn test_command_unqualified_linter_err() {
+        assert_linter_err!("X$ = COMMAND", LintError::TypeMismatch, 1, 6);
+    }
+}
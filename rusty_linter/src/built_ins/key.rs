@@ -0,0 +1,47 @@
+use rusty_common::{AtPos, Position};
+use rusty_parser::Expressions;
+
+use crate::built_ins::arg_validation::ArgValidation;
+use crate::core::{LintError, LintErrorPos};
+
+pub fn lint(args: &Expressions, pos: Position) -> Result<(), LintErrorPos> {
+    if args.is_empty() {
+        // KEY LIST
+        Ok(())
+    } else if args.len() == 2 {
+        args.require_integer_argument(0)?;
+        args.require_string_argument(1)
+    } else {
+        Err(LintError::ArgumentCountMismatch.at_pos(pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_linter_err;
+
+    #[test]
+    fn one_argument() {
+        let input = "KEY 1";
+        assert_linter_err!(input, LintError::ArgumentCountMismatch);
+    }
+
+    #[test]
+    fn three_arguments() {
+        let input = r#"KEY 1, "a", "b""#;
+        assert_linter_err!(input, LintError::ArgumentCountMismatch);
+    }
+
+    #[test]
+    fn string_first_argument() {
+        let input = r#"KEY "1", "a""#;
+        assert_linter_err!(input, LintError::ArgumentTypeMismatch);
+    }
+
+    #[test]
+    fn numeric_second_argument() {
+        let input = "KEY 1, 2";
+        assert_linter_err!(input, LintError::ArgumentTypeMismatch);
+    }
+}
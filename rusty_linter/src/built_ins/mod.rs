@@ -1,21 +1,28 @@
 mod arg_validation;
+mod assert;
+mod assert_equal;
 mod beep;
 mod chr;
 mod close;
 mod cls;
 mod color;
+mod command;
 mod cvd;
 mod data;
 mod def_seg;
 mod environ_fn;
 mod environ_sub;
 mod eof;
+mod erdev;
+mod erl;
 mod err;
+mod error;
 mod field;
 mod get;
 mod inkey;
 mod input;
 mod instr;
+mod key;
 mod kill;
 mod lbound;
 mod lcase;
@@ -23,6 +30,7 @@ mod left;
 mod len;
 mod line_input;
 mod locate;
+mod lpos;
 mod lset;
 mod ltrim;
 mod mid_fn;
@@ -58,6 +66,8 @@ pub fn lint_sub_call(
     scope_kind: ScopeKind,
 ) -> Result<(), LintErrorPos> {
     match built_in_sub {
+        BuiltInSub::Assert => assert::lint(args, pos),
+        BuiltInSub::AssertEqual => assert_equal::lint(args, pos),
         BuiltInSub::Beep => beep::lint(args, pos),
         BuiltInSub::CallAbsolute => Ok(()),
         BuiltInSub::Close => close::lint(args),
@@ -66,9 +76,11 @@ pub fn lint_sub_call(
         BuiltInSub::Data => data::lint(args, scope_kind, pos),
         BuiltInSub::DefSeg => def_seg::lint(args, pos),
         BuiltInSub::Environ => environ_sub::lint(args, pos),
+        BuiltInSub::Error => error::lint(args, pos),
         BuiltInSub::Field => field::lint(args, pos),
         BuiltInSub::Get => get::lint(args, pos),
         BuiltInSub::Input => input::lint(args, pos),
+        BuiltInSub::Key => key::lint(args, pos),
         BuiltInSub::Kill => kill::lint(args, pos),
         BuiltInSub::LineInput => line_input::lint(args, pos),
         BuiltInSub::Locate => locate::lint(args, pos),
@@ -91,9 +103,12 @@ pub fn lint_function_call(
 ) -> Result<(), LintErrorPos> {
     match built_in {
         BuiltInFunction::Chr => chr::lint(args, pos),
+        BuiltInFunction::Command => command::lint(args, pos),
         BuiltInFunction::Cvd => cvd::lint(args, pos),
         BuiltInFunction::Environ => environ_fn::lint(args, pos),
         BuiltInFunction::Eof => eof::lint(args, pos),
+        BuiltInFunction::ErDev => erdev::lint(args, pos),
+        BuiltInFunction::Erl => erl::lint(args, pos),
         BuiltInFunction::Err => err::lint(args, pos),
         BuiltInFunction::InKey => inkey::lint(args, pos),
         BuiltInFunction::InStr => instr::lint(args, pos),
@@ -101,6 +116,7 @@ pub fn lint_function_call(
         BuiltInFunction::LCase => lcase::lint(args, pos),
         BuiltInFunction::Left => left::lint(args, pos),
         BuiltInFunction::Len => len::lint(args, pos),
+        BuiltInFunction::LPos => lpos::lint(args, pos),
         BuiltInFunction::LTrim => ltrim::lint(args, pos),
         BuiltInFunction::Mid => mid_fn::lint(args, pos),
         BuiltInFunction::Mkd => mkd::lint(args, pos),
@@ -38,16 +38,20 @@ pub fn try_built_in_function(n: &Name) -> Result<Option<BuiltInFunction>, LintEr
         Some(b) => match b {
             BuiltInFunction::Cvd
             | BuiltInFunction::Eof
+            | BuiltInFunction::ErDev
+            | BuiltInFunction::Erl
             | BuiltInFunction::Err
             | BuiltInFunction::InStr
             | BuiltInFunction::Len
+            | BuiltInFunction::LPos
             | BuiltInFunction::Peek
             | BuiltInFunction::LBound
             | BuiltInFunction::UBound
             | BuiltInFunction::Val
             | BuiltInFunction::VarPtr
             | BuiltInFunction::VarSeg => demand_unqualified(b, n),
-            BuiltInFunction::Environ
+            BuiltInFunction::Command
+            | BuiltInFunction::Environ
             | BuiltInFunction::InKey
             | BuiltInFunction::LCase
             | BuiltInFunction::Left
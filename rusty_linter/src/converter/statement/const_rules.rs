@@ -48,6 +48,7 @@ fn new_const(
     } else {
         value_before_casting
             .cast(const_name.qualifier().unwrap())
+            .map_err(LintError::from)
             .with_err_at(&right_side)?
     };
     ctx.names
@@ -0,0 +1,31 @@
+use rusty_common::AtPos;
+use rusty_parser::{EventControl, ExpressionPos, OnEvent, TypeQualifier};
+
+use crate::converter::common::{Convertible, ConvertibleIn};
+use crate::core::{CanCastTo, LintError, LintErrorPos, LinterContext};
+
+impl Convertible for OnEvent {
+    fn convert(self, ctx: &mut LinterContext) -> Result<Self, LintErrorPos> {
+        let arg = self.arg.convert_in_default(ctx)?;
+        ensure_numeric(&arg)?;
+        Ok(Self { arg, ..self })
+    }
+}
+
+impl Convertible for EventControl {
+    fn convert(self, ctx: &mut LinterContext) -> Result<Self, LintErrorPos> {
+        let arg = self.arg.convert_in_default(ctx)?;
+        if let Some(arg) = &arg {
+            ensure_numeric(arg)?;
+        }
+        Ok(Self { arg, ..self })
+    }
+}
+
+fn ensure_numeric(arg: &ExpressionPos) -> Result<(), LintErrorPos> {
+    if arg.can_cast_to(&TypeQualifier::AmpersandLong) {
+        Ok(())
+    } else {
+        Err(LintError::TypeMismatch.at(arg))
+    }
+}
@@ -59,13 +59,18 @@ impl ConvertibleIn<Position> for Statement {
                 .convert_in(ctx, DimContext::Redim)
                 .map(Statement::Redim),
             Self::Print(print) => print.convert(ctx).map(Statement::Print),
+            Self::OnEvent(on_event) => on_event.convert(ctx).map(Statement::OnEvent),
+            Self::EventControl(event_control) => {
+                event_control.convert(ctx).map(Statement::EventControl)
+            }
             Self::OnError(_)
             | Self::Label(_)
             | Self::GoTo(_)
             | Self::GoSub(_)
             | Self::Comment(_)
             | Self::End
-            | Self::System => Ok(self),
+            | Self::System
+            | Self::Stop => Ok(self),
         }
     }
 }
@@ -1,6 +1,7 @@
 mod assignment;
 mod const_rules;
 mod do_loop;
+mod event;
 mod for_loop;
 mod if_blocks;
 mod main;
@@ -34,10 +34,42 @@ pub enum LintError {
 
 pub type LintErrorPos = Positioned<LintError>;
 
+impl std::fmt::Display for LintError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ArgumentCountMismatch => f.write_str("Argument-count mismatch"),
+            Self::ArgumentTypeMismatch => f.write_str("Parameter type mismatch"),
+            Self::ArrayAlreadyDimensioned => f.write_str("Array already dimensioned"),
+            Self::ArrayNotDefined => f.write_str("Array not defined"),
+            Self::DivisionByZero => f.write_str("Division by zero"),
+            Self::DotClash => f.write_str("Identifier cannot include period"),
+            Self::DuplicateDefinition => f.write_str("Duplicate definition"),
+            Self::DuplicateLabel => f.write_str("Duplicate label"),
+            Self::ElementNotDefined => f.write_str("Element not defined"),
+            Self::FunctionNeedsArguments => f.write_str("Function needs arguments"),
+            Self::IllegalInSubFunction => f.write_str("Illegal in SUB/FUNCTION"),
+            Self::IllegalOutsideSubFunction => f.write_str("Illegal outside of SUB/FUNCTION"),
+            Self::InvalidConstant => f.write_str("Invalid constant"),
+            Self::LabelNotDefined => f.write_str("Label not defined"),
+            Self::NextWithoutFor => f.write_str("NEXT without FOR"),
+            Self::OutOfStringSpace => f.write_str("Out of string space"),
+            Self::Overflow => f.write_str("Overflow"),
+            Self::SubprogramNotDefined => f.write_str("Subprogram not defined"),
+            Self::TypeMismatch => f.write_str("Type mismatch"),
+            Self::TypeNotDefined => f.write_str("Type not defined"),
+            Self::VariableRequired => f.write_str("Variable required"),
+            Self::WrongNumberOfDimensions => f.write_str("Wrong number of dimensions"),
+            Self::ParserError(e) => e.fmt(f),
+            Self::NotFiniteNumber => f.write_str("Number is not finite"),
+        }
+    }
+}
+
 impl From<VariantError> for LintError {
     fn from(e: VariantError) -> Self {
         match e {
             VariantError::DivisionByZero => Self::DivisionByZero,
+            VariantError::NotFiniteNumber => Self::NotFiniteNumber,
             VariantError::Overflow => Self::Overflow,
             VariantError::TypeMismatch => Self::TypeMismatch,
         }
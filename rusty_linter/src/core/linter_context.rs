@@ -9,6 +9,9 @@ pub struct LinterContext {
     pub user_defined_types: UserDefinedTypes,
     pub resolver: TypeResolverImpl,
     pub names: Names,
+    /// If true, the program is linted in order to run its unit tests,
+    /// which makes the assertions (e.g. `ASSERT`) available.
+    pub is_test: bool,
 }
 
 impl TypeResolver for LinterContext {
@@ -22,6 +25,7 @@ impl LinterContext {
         functions: SignatureMap,
         subs: SignatureMap,
         user_defined_types: UserDefinedTypes,
+        is_test: bool,
     ) -> Self {
         Self {
             functions,
@@ -29,6 +33,7 @@ impl LinterContext {
             user_defined_types,
             resolver: TypeResolverImpl::new(),
             names: Names::new(),
+            is_test,
         }
     }
 
@@ -1,13 +1,36 @@
 use rusty_parser::Program;
 
 use crate::converter::common::Convertible;
-use crate::core::{LintErrorPos, LinterContext};
+use crate::core::{LintErrorPos, LinterContext, NativeSignature};
 use crate::post_linter::post_linter;
 use crate::pre_linter::pre_lint_program;
 
 pub fn lint(program: Program) -> Result<(Program, LinterContext), LintErrorPos> {
+    lint_with_natives(program, &[])
+}
+
+/// Lints the program in order to run its unit tests,
+/// which can call the assertions (e.g. `ASSERT`).
+pub fn lint_tests(program: Program) -> Result<(Program, LinterContext), LintErrorPos> {
+    lint_program(program, &[], true)
+}
+
+/// Lints the program, which can call the given native subprograms
+/// as if they were implemented by the program.
+pub fn lint_with_natives(
+    program: Program,
+    natives: &[NativeSignature],
+) -> Result<(Program, LinterContext), LintErrorPos> {
+    lint_program(program, natives, false)
+}
+
+fn lint_program(
+    program: Program,
+    natives: &[NativeSignature],
+    is_test: bool,
+) -> Result<(Program, LinterContext), LintErrorPos> {
     // first pass, get user defined types and functions/subs
-    let mut context = pre_lint_program(&program)?;
+    let mut context = pre_lint_program(&program, natives, is_test)?;
     // convert to fully typed
     let program = program.convert(&mut context)?;
     // lint and reduce
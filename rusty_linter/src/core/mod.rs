@@ -5,7 +5,6 @@ mod error;
 mod linter_context;
 mod main;
 mod name_scope;
-mod qb_casting;
 mod qualify_variant;
 mod ref_to_value_visitor;
 mod resolved_param_type;
@@ -23,7 +22,6 @@ pub use self::error::*;
 pub use self::linter_context::*;
 pub use self::main::*;
 pub use self::name_scope::*;
-pub use self::qb_casting::*;
 pub use self::qualify_variant::*;
 pub use self::ref_to_value_visitor::*;
 pub use self::resolved_param_type::*;
@@ -33,3 +31,6 @@ pub use self::type_resolver::*;
 pub use self::type_resolver_impl::*;
 pub use self::variable_info::*;
 pub use self::visitor::*;
+
+// the casting of values is shared with the runtime
+pub use rusty_variant::{CastVariant, QBNumberCast};
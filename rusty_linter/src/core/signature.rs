@@ -1,9 +1,9 @@
 use std::collections::HashMap;
 
 use rusty_common::Positioned;
-use rusty_parser::{BareName, TypeQualifier};
+use rusty_parser::{BareName, BuiltInStyle, TypeQualifier};
 
-use crate::core::ResolvedParamTypes;
+use crate::core::{ResolvedParamType, ResolvedParamTypes};
 
 /// The signature of a FUNCTION or SUB.
 /// Consists of the resolved parameter types and, in case of a FUNCTION, the return type.
@@ -49,5 +49,32 @@ impl PartialEq<TypeQualifier> for Signature {
     }
 }
 
+/// The signature of a native FUNCTION or SUB, i.e. a subprogram that
+/// is implemented by the application that embeds the interpreter,
+/// instead of by the program.
+///
+/// Native subprograms only have parameters of built-in types.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NativeSignature {
+    pub name: BareName,
+    pub param_types: Vec<TypeQualifier>,
+    /// The return type, in case of a FUNCTION.
+    pub return_type: Option<TypeQualifier>,
+}
+
+impl NativeSignature {
+    pub fn signature(&self) -> Signature {
+        let param_types: ResolvedParamTypes = self
+            .param_types
+            .iter()
+            .map(|q| ResolvedParamType::BuiltIn(*q, BuiltInStyle::Compact))
+            .collect();
+        match self.return_type {
+            Some(q) => Signature::Function(param_types, q),
+            None => Signature::Sub(param_types),
+        }
+    }
+}
+
 /// A map of (bare) subprogram names to their respective signatures.
 pub type SignatureMap = HashMap<BareName, Positioned<Signature>>;
@@ -4,7 +4,9 @@ use rusty_common::CaseInsensitiveString;
 use rusty_parser::{AsBareName, BareName, BuiltInStyle, Name, TypeQualifier, VarType};
 use rusty_variant::Variant;
 
-use crate::core::{ConstLookup, RedimInfo, ScopeKind, ScopeName, VariableInfo};
+use crate::core::{
+    ConstLookup, IntoQualified, RedimInfo, ScopeKind, ScopeName, TypeResolver, VariableInfo,
+};
 use crate::names::ImplicitVars;
 use crate::names::names_inner::NamesInner;
 use crate::names::traits::ManyNamesTrait;
@@ -234,6 +236,42 @@ impl Names {
             }
         }
     }
+
+    /// Finds where a name that is used in the given scope is defined:
+    /// in that scope, or in the global scope for constants and SHARED variables
+    /// (e.g. for the references of an editor).
+    ///
+    /// Returns the scope of the definition and, for a compact variable,
+    /// its qualifier. Constants and extended variables are identified
+    /// by their bare name, as they cannot co-exist with other names.
+    pub fn find_definition(
+        &self,
+        scope_name: &ScopeName,
+        name: &Name,
+        resolver: &impl TypeResolver,
+    ) -> Option<(ScopeName, Option<TypeQualifier>)> {
+        let bare_name = name.as_bare_name();
+        let qualifier = name.clone().to_qualified(resolver).qualifier();
+        let mut levels = vec![(scope_name, false)];
+        if scope_name != &ScopeName::Global {
+            levels.push((&ScopeName::Global, true));
+        }
+        levels.into_iter().find_map(|(level, only_shared)| {
+            let names = &self.data.get(level)?.names;
+            let is_visible = |variable_info: &VariableInfo| !only_shared || variable_info.shared;
+            if names.get_const_value(bare_name).is_some()
+                || names.get_extended(bare_name).is_some_and(is_visible)
+            {
+                Some((level.clone(), None))
+            } else {
+                let q = qualifier?;
+                names
+                    .get_compact(bare_name, q)
+                    .filter(|variable_info| is_visible(variable_info))
+                    .map(|_| (level.clone(), Some(q)))
+            }
+        })
+    }
 }
 
 impl ConstLookup for Names {
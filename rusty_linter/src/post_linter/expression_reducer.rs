@@ -120,6 +120,10 @@ pub trait ExpressionReducer {
             Statement::DoLoop(do_loop) => self.visit_do_loop(do_loop).map(Statement::DoLoop),
             Statement::Dim(dim_list) => self.visit_dim_list(dim_list).map(Statement::Dim),
             Statement::Redim(dim_list) => self.visit_dim_list(dim_list).map(Statement::Redim),
+            Statement::OnEvent(on_event) => self.visit_on_event(on_event).map(Statement::OnEvent),
+            Statement::EventControl(event_control) => self
+                .visit_event_control(event_control)
+                .map(Statement::EventControl),
             Statement::OnError(_)
             | Statement::Label(_)
             | Statement::GoTo(_)
@@ -129,7 +133,8 @@ pub trait ExpressionReducer {
             | Statement::Exit(_)
             | Statement::Comment(_)
             | Statement::End
-            | Statement::System => Ok(s),
+            | Statement::System
+            | Statement::Stop => Ok(s),
         }
     }
 
@@ -144,6 +149,26 @@ pub trait ExpressionReducer {
         sub_call.try_map_right(|args| self.visit_expressions(args))
     }
 
+    fn visit_on_event(&mut self, on_event: OnEvent) -> Result<OnEvent, LintErrorPos> {
+        Ok(OnEvent {
+            arg: self.visit_expression_pos(on_event.arg)?,
+            ..on_event
+        })
+    }
+
+    fn visit_event_control(
+        &mut self,
+        event_control: EventControl,
+    ) -> Result<EventControl, LintErrorPos> {
+        Ok(EventControl {
+            arg: match event_control.arg {
+                Some(arg) => Some(self.visit_expression_pos(arg)?),
+                None => None,
+            },
+            ..event_control
+        })
+    }
+
     fn visit_assignment(&mut self, a: Assignment) -> Result<Assignment, LintErrorPos> {
         let (name, v) = a.into();
         Ok(Assignment::new(name, self.visit_expression_pos(v)?))
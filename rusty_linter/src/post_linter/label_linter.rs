@@ -88,6 +88,10 @@ impl PostConversionLinter for LabelLinter {
         }
     }
 
+    fn visit_on_event(&mut self, on_event: &OnEvent, pos: Position) -> Result<(), LintErrorPos> {
+        self.ensure_is_global_label(&on_event.label, pos)
+    }
+
     fn visit_go_to(
         &mut self,
         label: &CaseInsensitiveString,
@@ -74,7 +74,9 @@ pub trait PostConversionLinter {
             Statement::Resume(resume_option) => self.visit_resume(resume_option, pos),
             Statement::Return(opt_label) => self.visit_return(opt_label.as_ref(), pos),
             Statement::Exit(exit_object) => self.visit_exit(*exit_object),
-            Statement::Const(_) | Statement::End | Statement::System => Ok(()),
+            Statement::OnEvent(on_event) => self.visit_on_event(on_event, pos),
+            Statement::EventControl(event_control) => self.visit_event_control(event_control),
+            Statement::Const(_) | Statement::End | Statement::System | Statement::Stop => Ok(()),
         }
     }
 
@@ -94,6 +96,17 @@ pub trait PostConversionLinter {
         Ok(())
     }
 
+    fn visit_on_event(&mut self, on_event: &OnEvent, _pos: Position) -> Result<(), LintErrorPos> {
+        self.visit_expression(&on_event.arg)
+    }
+
+    fn visit_event_control(&mut self, event_control: &EventControl) -> Result<(), LintErrorPos> {
+        match &event_control.arg {
+            Some(arg) => self.visit_expression(arg),
+            None => Ok(()),
+        }
+    }
+
     fn visit_label(
         &mut self,
         _label: &CaseInsensitiveString,
@@ -46,7 +46,7 @@ impl ConstantMap {
         match name.qualifier() {
             Some(qualifier) => resolved_value
                 .cast(qualifier)
-                .map_err(|e| e.at(expression_pos)),
+                .map_err(|e| LintError::from(e).at(expression_pos)),
             _ => Ok(resolved_value),
         }
     }
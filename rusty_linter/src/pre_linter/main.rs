@@ -20,16 +20,34 @@ struct MainContext {
     declaration_pos: Position,
 }
 
-pub fn pre_lint_program(program: &Program) -> Result<LinterContext, LintErrorPos> {
-    let mut visitor = GlobalVisitor::new(MainContext::default());
+pub fn pre_lint_program(
+    program: &Program,
+    natives: &[NativeSignature],
+    is_test: bool,
+) -> Result<LinterContext, LintErrorPos> {
+    let mut main_context = MainContext::default();
+    // the natives act as implementations, so the program cannot implement them again
+    for native in natives {
+        let signature = native.signature().at_pos(Position::start());
+        match native.return_type {
+            Some(_) => main_context
+                .functions
+                .add_implementation(native.name.clone(), signature)?,
+            None => main_context
+                .subs
+                .add_implementation(native.name.clone(), signature)?,
+        }
+    }
+    let mut visitor = GlobalVisitor::new(main_context);
     visitor.visit(program)?;
     let ctx = visitor.delegate();
     ctx.post_visit_functions()?;
-    ctx.post_visit_subs()?;
+    ctx.post_visit_subs(is_test)?;
     Ok(LinterContext::new(
         ctx.functions.implementations(),
         ctx.subs.implementations(),
         ctx.user_defined_types,
+        is_test,
     ))
 }
 
@@ -145,11 +163,12 @@ impl MainContext {
             .ensure_does_not_clash_with_built_in(|name| BuiltInFunction::try_parse(name).is_some())
     }
 
-    fn post_visit_subs(&self) -> Result<(), LintErrorPos> {
+    fn post_visit_subs(&self, is_test: bool) -> Result<(), LintErrorPos> {
         // not checking if declarations are present, because in MONEY.BAS there
         // are two SUBs declared but not implemented (and not called either)
         self.subs.ensure_does_not_clash_with_built_in(|name| {
             BuiltInSub::parse_non_keyword_sub(name.as_ref()).is_some()
+                || (is_test && BuiltInSub::parse_test_sub(name.as_ref()).is_some())
         })
     }
 }
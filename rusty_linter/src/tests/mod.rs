@@ -10,7 +10,9 @@ mod go_sub;
 mod go_to;
 mod labels;
 mod loops;
+mod native;
 mod on_error;
+mod on_event;
 mod resume;
 mod select_case;
 mod sub_implementation;
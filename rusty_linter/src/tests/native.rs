@@ -0,0 +1,80 @@
+use rusty_parser::{TypeQualifier, parse};
+
+use crate::core::{LintError, LintErrorPos, NativeSignature, lint_with_natives};
+
+fn natives() -> Vec<NativeSignature> {
+    vec![
+        NativeSignature {
+            name: "Twice".into(),
+            param_types: vec![TypeQualifier::PercentInteger],
+            return_type: Some(TypeQualifier::AmpersandLong),
+        },
+        NativeSignature {
+            name: "Log".into(),
+            param_types: vec![TypeQualifier::DollarString],
+            return_type: None,
+        },
+    ]
+}
+
+fn lint_err(input: &str) -> LintErrorPos {
+    lint_with_natives(parse(input), &natives())
+        .err()
+        .expect("Linter should fail")
+}
+
+#[test]
+fn calls_to_natives_are_valid() {
+    let program = parse(
+        r#"
+        X& = Twice(21)
+        Y = Twice(2.5)
+        Log "hello"
+        "#,
+    );
+    assert!(lint_with_natives(program, &natives()).is_ok());
+}
+
+#[test]
+fn natives_are_not_defined_without_registering_them() {
+    let program = parse("Log \"hello\"");
+    assert_eq!(
+        lint_with_natives(program, &[])
+            .err()
+            .expect("Linter should fail")
+            .element,
+        LintError::SubprogramNotDefined
+    );
+}
+
+#[test]
+fn wrong_argument_count() {
+    assert_eq!(
+        lint_err("X = Twice(1, 2)").element,
+        LintError::ArgumentCountMismatch
+    );
+    assert_eq!(lint_err("Log").element, LintError::ArgumentCountMismatch);
+}
+
+#[test]
+fn wrong_argument_type() {
+    assert_eq!(
+        lint_err("X = Twice(\"a\")").element,
+        LintError::ArgumentTypeMismatch
+    );
+    assert_eq!(lint_err("Log 42").element, LintError::ArgumentTypeMismatch);
+}
+
+#[test]
+fn wrong_return_type() {
+    assert_eq!(lint_err("X$ = Twice(1)").element, LintError::TypeMismatch);
+}
+
+#[test]
+fn program_cannot_implement_a_native() {
+    let program = r#"
+        SUB Log(Msg$)
+        END SUB
+        "#;
+    assert_eq!(lint_err(program).element, LintError::DuplicateDefinition);
+}
@@ -0,0 +1,41 @@
+use crate::assert_linter_err;
+use crate::core::LintError;
+
+#[test]
+fn on_timer_missing_label() {
+    let input = r#"
+    ON TIMER(1) GOSUB Tick
+    "#;
+    assert_linter_err!(input, LintError::LabelNotDefined, 2, 5);
+}
+
+#[test]
+fn on_key_must_use_global_label() {
+    let input = r#"
+    SUB Test
+        ON KEY(1) GOSUB F1Pressed
+        EXIT SUB
+        F1Pressed:
+            RETURN
+    END SUB
+    "#;
+    assert_linter_err!(input, LintError::LabelNotDefined, 3, 9);
+}
+
+#[test]
+fn on_timer_interval_must_be_numeric() {
+    let input = r#"
+    ON TIMER("1") GOSUB Tick
+    Tick:
+        RETURN
+    "#;
+    assert_linter_err!(input, LintError::TypeMismatch, 2, 14);
+}
+
+#[test]
+fn key_number_must_be_numeric() {
+    let input = r#"
+    KEY(A$) ON
+    "#;
+    assert_linter_err!(input, LintError::TypeMismatch, 2, 9);
+}
@@ -1,6 +1,6 @@
 use rusty_parser::{Program, parse};
 
-use crate::core::{LintErrorPos, LinterContext, lint};
+use crate::core::{LintErrorPos, LinterContext, lint, lint_tests};
 
 /// Lints the given string and returns the results.
 ///
@@ -34,6 +34,20 @@ pub fn linter_err(input: &str, msg: &str) -> LintErrorPos {
     }
 }
 
+/// Lints the given string in order to run its unit tests
+/// and returns the error of the linter.
+///
+/// # Panics
+///
+/// If the parser has an error or if the linter did not have an error.
+pub fn linter_err_in_tests(input: &str) -> LintErrorPos {
+    let program = parse(input);
+    match lint_tests(program) {
+        Ok(_) => panic!("Linter should fail"),
+        Err(e) => e,
+    }
+}
+
 #[macro_export]
 macro_rules! assert_linter_err {
     ($program:expr, $expected_err:expr) => {
@@ -63,6 +77,19 @@ macro_rules! assert_linter_err {
     };
 }
 
+#[macro_export]
+macro_rules! assert_linter_err_in_tests {
+    ($program:expr, $expected_err:expr, $expected_row:expr, $expected_col:expr) => {
+        let rusty_common::Positioned { element, pos } =
+            $crate::tests::test_utils::linter_err_in_tests($program);
+        assert_eq!(element, $expected_err);
+        assert_eq!(
+            pos,
+            rusty_common::Position::new($expected_row, $expected_col)
+        );
+    };
+}
+
 #[macro_export]
 macro_rules! assert_linter_ok_global_statements {
     ($program:expr, $($statement: expr),+) => {
@@ -0,0 +1,460 @@
+//! Analyzes a document: collects its errors and symbols
+//! and resolves the words of the source to the symbols they refer to.
+
+use std::collections::HashMap;
+
+use rusty_common::Position;
+use rusty_linter::core::{LinterContext, ScopeName, lint};
+use rusty_parser::{AsBareName, BareName, Keyword, Name, parse_main_str};
+
+use crate::lexer::{Word, split_qualifier, words};
+use crate::symbols::{Scopes, Symbol, SymbolKind, collect_symbols};
+
+/// A parse or lint error.
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub pos: Position,
+    pub message: String,
+}
+
+/// A reference of a symbol in the source.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Reference {
+    /// The index of the symbol in [Analysis::symbols].
+    pub symbol: usize,
+    pub pos: Position,
+    /// The length of the name, in characters.
+    /// For properties (e.g. `Card.Value`), only the variable is included.
+    pub len: u32,
+}
+
+pub struct Analysis {
+    pub diagnostics: Vec<Diagnostic>,
+    /// The symbols of the program, sorted by their position.
+    pub symbols: Vec<Symbol>,
+    pub scopes: Scopes,
+    /// The number of lines of the source.
+    pub line_count: u32,
+    lines: Vec<String>,
+    words: Vec<Word>,
+    /// The indices of the symbols, by their name.
+    symbols_by_name: HashMap<BareName, Vec<usize>>,
+    /// Resolves the names of variables and constants,
+    /// unless the program could not be linted.
+    linter_context: Option<LinterContext>,
+}
+
+impl Analysis {
+    /// Parses and lints the given source.
+    ///
+    /// The variables and the parameters are taken from the linted program,
+    /// where their types are resolved and the implicitly declared variables
+    /// are present. If linting fails, they are taken from the parsed program.
+    pub fn new(source: &str) -> Self {
+        let mut result = Self {
+            diagnostics: vec![],
+            symbols: vec![],
+            scopes: Scopes::new(&vec![]),
+            line_count: source.lines().count() as u32,
+            lines: source.lines().map(str::to_owned).collect(),
+            words: words(source),
+            symbols_by_name: HashMap::new(),
+            linter_context: None,
+        };
+        let program = match parse_main_str(source.to_owned()) {
+            Ok(program) => program,
+            Err(e) => {
+                result.diagnostics.push(Diagnostic {
+                    pos: e.pos,
+                    message: e.element.to_string(),
+                });
+                return result;
+            }
+        };
+        result.scopes = Scopes::new(&program);
+        let mut symbols = collect_symbols(&program, &result.scopes, source);
+        match lint(program) {
+            Ok((linted_program, linter_context)) => {
+                // CONSTs and TYPEs are removed by the linter
+                symbols.retain(|symbol| is_removed_by_linter(symbol.kind));
+                symbols.extend(
+                    collect_symbols(&linted_program, &result.scopes, source)
+                        .into_iter()
+                        .filter(|symbol| !is_removed_by_linter(symbol.kind)),
+                );
+                result.linter_context = Some(linter_context);
+            }
+            Err(e) => {
+                result.diagnostics.push(Diagnostic {
+                    pos: e.pos,
+                    message: e.element.to_string(),
+                });
+            }
+        }
+        symbols.sort_by_key(|symbol| (symbol.pos.row(), symbol.pos.col()));
+        for (index, symbol) in symbols.iter().enumerate() {
+            result
+                .symbols_by_name
+                .entry(symbol.bare_name.clone())
+                .or_default()
+                .push(index);
+        }
+        result.symbols = symbols;
+        result
+    }
+
+    /// Finds the reference at the given position (1 based).
+    pub fn reference_at(&self, pos: Position) -> Option<Reference> {
+        let index = self
+            .words
+            .iter()
+            .position(|word| word.pos.row() == pos.row() && word.contains_col(pos.col()))?;
+        self.resolve(index)
+    }
+
+    /// Finds all the references of the given symbol, including its definition.
+    pub fn references(&self, symbol: usize) -> Vec<Reference> {
+        let bare_name = &self.symbols[symbol].bare_name;
+        (0..self.words.len())
+            .filter(|index| {
+                // the name itself, or the variable of a property
+                let (bare, _) = split_qualifier(&self.words[*index].text);
+                let variable = bare.split_once('.').map_or(bare, |(prefix, _)| prefix);
+                bare_name == &BareName::from(bare) || bare_name == &BareName::from(variable)
+            })
+            .filter_map(|index| self.resolve(index))
+            .filter(|reference| reference.symbol == symbol)
+            .collect()
+    }
+
+    /// Gets the symbols that can be referenced at the given row.
+    pub fn visible_symbols(&self, row: u32) -> impl Iterator<Item = &Symbol> {
+        let scope = self.scopes.scope_at(row);
+        self.symbols.iter().filter(move |symbol| {
+            symbol.scope == scope
+                || (symbol.scope == ScopeName::Global && (!symbol.is_variable() || symbol.shared))
+        })
+    }
+
+    /// Converts a position of the protocol (0 based, in UTF-16 code units)
+    /// to a [Position] (1 based, in characters).
+    pub fn position_of_utf16(&self, line: u32, character: u32) -> Position {
+        let mut units = 0;
+        let mut col = 1;
+        for ch in self.line(line + 1).chars() {
+            if units >= character {
+                break;
+            }
+            units += ch.len_utf16() as u32;
+            col += 1;
+        }
+        Position::new(line + 1, col + character.saturating_sub(units))
+    }
+
+    /// Converts a column of the given row (1 based, in characters)
+    /// to a character of the protocol (0 based, in UTF-16 code units).
+    pub fn utf16_character(&self, row: u32, col: u32) -> u32 {
+        let line = self.line(row);
+        let offset = col.saturating_sub(1) as usize;
+        let units: usize = line.chars().take(offset).map(char::len_utf16).sum();
+        // a position after the end of the line (e.g. of a diagnostic)
+        (units + offset.saturating_sub(line.chars().count())) as u32
+    }
+
+    fn line(&self, row: u32) -> &str {
+        self.lines
+            .get((row as usize).wrapping_sub(1))
+            .map_or("", String::as_str)
+    }
+
+    /// Resolves the word of the given index to the symbol it refers to.
+    fn resolve(&self, index: usize) -> Option<Reference> {
+        let word = &self.words[index];
+        if word.keyword.is_some() {
+            return None;
+        }
+        let reference = |symbol: usize, len: u32| Reference {
+            symbol,
+            pos: word.pos,
+            len,
+        };
+        let (bare, _) = split_qualifier(&word.text);
+        if let Some(symbol) = self.find(bare, |s| s.pos == word.pos) {
+            return Some(reference(symbol, word.len()));
+        }
+        let scope = self.scopes.scope_at(word.pos.row());
+        let previous_keyword = index
+            .checked_sub(1)
+            .map(|previous| &self.words[previous])
+            .filter(|previous| previous.pos.row() == word.pos.row())
+            .and_then(|previous| previous.keyword);
+        let expected_kind = match previous_keyword {
+            Some(
+                Keyword::GoTo
+                | Keyword::GoSub
+                | Keyword::Resume
+                | Keyword::Return
+                | Keyword::Then
+                | Keyword::Else,
+            ) => Some(SymbolKind::Label),
+            Some(Keyword::As) => Some(SymbolKind::Type),
+            _ => None,
+        };
+        if let Some(kind) = expected_kind
+            && let Some(symbol) = self.find(bare, |s| s.kind == kind)
+        {
+            return Some(reference(symbol, word.len()));
+        }
+        if let Some(symbol) = self.resolve_name(&word.text, &scope) {
+            return Some(reference(symbol, word.len()));
+        }
+        // a property of a user defined type variable (e.g. `Card.Value`)
+        let (prefix, _) = word.text.split_once('.')?;
+        self.resolve_name(prefix, &scope)
+            .map(|symbol| reference(symbol, prefix.chars().count() as u32))
+    }
+
+    /// Resolves a name used in the given scope to a variable, a constant,
+    /// or a SUB/FUNCTION (which includes the return value of a FUNCTION).
+    fn resolve_name(&self, name: &str, scope: &ScopeName) -> Option<usize> {
+        let name = Name::from(name);
+        let qualifier = name.qualifier();
+        let bare_name = name.as_bare_name().as_ref();
+        match &self.linter_context {
+            Some(linter_context) => self.find_linted_definition(&name, scope, linter_context),
+            None => self.find_declaration(&name, scope),
+        }
+        .or_else(|| {
+            self.find(bare_name, |s| {
+                matches!(s.kind, SymbolKind::Sub | SymbolKind::Function)
+                    && (qualifier.is_none() || s.qualifier == qualifier)
+            })
+        })
+    }
+
+    /// Finds the variable or the constant that the given name refers to,
+    /// with the help of the names of the linted program.
+    fn find_linted_definition(
+        &self,
+        name: &Name,
+        scope: &ScopeName,
+        linter_context: &LinterContext,
+    ) -> Option<usize> {
+        let bare_name = name.as_bare_name();
+        // the scopes of the linter have the qualified name of a FUNCTION
+        let linter_scope = match scope {
+            ScopeName::Function(function_name) => ScopeName::Function(Name::new(
+                function_name.as_bare_name().clone(),
+                function_name.qualifier().or_else(|| {
+                    linter_context
+                        .functions
+                        .get(function_name.as_bare_name())
+                        .and_then(|signature| signature.element.qualifier())
+                }),
+            )),
+            _ => scope.clone(),
+        };
+        let (definition_scope, qualifier) =
+            linter_context
+                .names
+                .find_definition(&linter_scope, name, &linter_context.resolver)?;
+        let symbol_scope = match definition_scope {
+            ScopeName::Global => &ScopeName::Global,
+            _ => scope,
+        };
+        self.find(bare_name.as_ref(), |s| {
+            &s.scope == symbol_scope
+                && match qualifier {
+                    Some(q) => s.is_variable() && !s.extended && s.qualifier == Some(q),
+                    None => s.kind == SymbolKind::Constant || s.extended,
+                }
+        })
+    }
+
+    /// Finds the declared variable or constant that the given name refers to,
+    /// when the program could not be linted.
+    fn find_declaration(&self, name: &Name, scope: &ScopeName) -> Option<usize> {
+        let bare_name = name.as_bare_name().as_ref();
+        let is_candidate = |s: &Symbol| {
+            (s.is_variable() || s.kind == SymbolKind::Constant)
+                && (name.qualifier().is_none()
+                    || s.qualifier.is_none()
+                    || s.qualifier == name.qualifier())
+        };
+        self.find(bare_name, |s| is_candidate(s) && &s.scope == scope)
+            .or_else(|| {
+                self.find(bare_name, |s| {
+                    is_candidate(s)
+                        && s.scope == ScopeName::Global
+                        && (scope == &ScopeName::Global
+                            || s.kind == SymbolKind::Constant
+                            || s.shared)
+                })
+            })
+    }
+
+    /// Finds a symbol of the given name.
+    fn find(&self, bare_name: &str, predicate: impl Fn(&Symbol) -> bool) -> Option<usize> {
+        self.symbols_by_name
+            .get(&BareName::from(bare_name))?
+            .iter()
+            .copied()
+            .find(|index| predicate(&self.symbols[*index]))
+    }
+}
+
+fn is_removed_by_linter(kind: SymbolKind) -> bool {
+    matches!(kind, SymbolKind::Constant | SymbolKind::Type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &str = r#"DEFINT A-Z
+CONST Max = 3
+TYPE Card
+    Value AS INTEGER
+END TYPE
+DIM SHARED C AS Card
+C.Value = Max
+Count = 0
+GOSUB Increase
+PRINT Twice(Count)
+END
+Increase:
+Count = Count + 1
+RETURN
+FUNCTION Twice(N)
+    Count$ = "local"
+    Twice = N * C.Value
+END FUNCTION
+"#;
+
+    fn symbol_at(analysis: &Analysis, row: u32, col: u32) -> Option<&Symbol> {
+        analysis
+            .reference_at(Position::new(row, col))
+            .map(|reference| &analysis.symbols[reference.symbol])
+    }
+
+    fn reference_positions(analysis: &Analysis, row: u32, col: u32) -> Vec<(u32, u32, u32)> {
+        let reference = analysis.reference_at(Position::new(row, col)).unwrap();
+        analysis
+            .references(reference.symbol)
+            .into_iter()
+            .map(|r| (r.pos.row(), r.pos.col(), r.len))
+            .collect()
+    }
+
+    #[test]
+    fn test_no_diagnostics() {
+        let analysis = Analysis::new(PROGRAM);
+        assert_eq!(analysis.diagnostics, vec![]);
+    }
+
+    #[test]
+    fn test_resolve_implicit_variable() {
+        let analysis = Analysis::new(PROGRAM);
+        let symbol = symbol_at(&analysis, 13, 10).unwrap();
+        assert_eq!(symbol.kind, SymbolKind::Variable);
+        assert_eq!(symbol.name, "Count%");
+        assert_eq!(symbol.pos, Position::new(8, 1));
+        assert_eq!(
+            reference_positions(&analysis, 8, 1),
+            vec![(8, 1, 5), (10, 13, 5), (13, 1, 5), (13, 9, 5)]
+        );
+    }
+
+    #[test]
+    fn test_resolve_local_variable_with_qualifier() {
+        let analysis = Analysis::new(PROGRAM);
+        let symbol = symbol_at(&analysis, 16, 5).unwrap();
+        assert_eq!(symbol.name, "Count$");
+        assert_eq!(symbol.scope, ScopeName::Function("Twice".into()));
+    }
+
+    #[test]
+    fn test_resolve_shared_variable_property() {
+        let analysis = Analysis::new(PROGRAM);
+        assert_eq!(
+            reference_positions(&analysis, 17, 17),
+            vec![(6, 12, 1), (7, 1, 1), (17, 17, 1)]
+        );
+    }
+
+    #[test]
+    fn test_resolve_label_constant_type_and_function() {
+        let analysis = Analysis::new(PROGRAM);
+        assert_eq!(
+            symbol_at(&analysis, 9, 7).unwrap().pos,
+            Position::new(12, 1)
+        );
+        assert_eq!(
+            symbol_at(&analysis, 7, 11).unwrap().kind,
+            SymbolKind::Constant
+        );
+        assert_eq!(symbol_at(&analysis, 6, 17).unwrap().kind, SymbolKind::Type);
+        assert_eq!(
+            reference_positions(&analysis, 10, 7),
+            vec![(10, 7, 5), (15, 10, 5), (17, 5, 5)]
+        );
+        // parameters are resolved with DEFINT
+        assert_eq!(symbol_at(&analysis, 17, 13).unwrap().name, "N%");
+    }
+
+    #[test]
+    fn test_resolve_compact_variables_and_unshared_variables() {
+        let analysis = Analysis::new(
+            r#"X = 1
+A$ = "a"
+A% = 2
+PRINT A$; A%; X
+SUB Show
+    X = 2
+    PRINT X
+END SUB
+"#,
+        );
+        assert_eq!(symbol_at(&analysis, 4, 7).unwrap().pos, Position::new(2, 1));
+        assert_eq!(
+            symbol_at(&analysis, 4, 11).unwrap().pos,
+            Position::new(3, 1)
+        );
+        // the module-level X is not SHARED, so the SUB has its own X
+        assert_eq!(
+            reference_positions(&analysis, 4, 15),
+            vec![(1, 1, 1), (4, 15, 1)]
+        );
+        assert_eq!(
+            reference_positions(&analysis, 7, 11),
+            vec![(6, 5, 1), (7, 11, 1)]
+        );
+    }
+
+    #[test]
+    fn test_keywords_are_not_resolved() {
+        let analysis = Analysis::new(PROGRAM);
+        assert_eq!(symbol_at(&analysis, 10, 1), None);
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let analysis = Analysis::new("PRINT 1\nDIM A AS\n");
+        assert_eq!(analysis.diagnostics.len(), 1);
+        assert_eq!(analysis.diagnostics[0].pos.row(), 2);
+        assert!(analysis.symbols.is_empty());
+    }
+
+    #[test]
+    fn test_lint_error_keeps_parsed_symbols() {
+        let analysis = Analysis::new("DIM A AS INTEGER\nA = \"hello\"\n");
+        assert_eq!(
+            analysis.diagnostics,
+            vec![Diagnostic {
+                pos: Position::new(2, 5),
+                message: "Type mismatch".to_owned()
+            }]
+        );
+        assert_eq!(symbol_at(&analysis, 2, 1).unwrap().pos, Position::new(1, 5));
+    }
+}
@@ -0,0 +1,159 @@
+//! Splits the source into words, skipping string literals and comments.
+//!
+//! The parser does not keep the position of every name it meets,
+//! so the words are used to find the references of symbols.
+
+use rusty_common::Position;
+use rusty_parser::{Keyword, TypeQualifier};
+
+/// A keyword or a name (including its type qualifier, e.g. `A$`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Word {
+    /// The position of the first character (1 based).
+    pub pos: Position,
+    pub text: String,
+    /// Set if the word is a keyword.
+    pub keyword: Option<Keyword>,
+}
+
+impl Word {
+    /// The length of the word, in characters.
+    pub fn len(&self) -> u32 {
+        self.text.chars().count() as u32
+    }
+
+    /// Checks if the word covers the given column (1 based) of its row.
+    /// The column right after the word also counts,
+    /// as editors place the cursor there after typing it.
+    pub fn contains_col(&self, col: u32) -> bool {
+        self.pos.col() <= col && col <= self.pos.col() + self.len()
+    }
+}
+
+/// Splits the given name into its bare part and its type qualifier.
+pub fn split_qualifier(name: &str) -> (&str, Option<TypeQualifier>) {
+    match name
+        .chars()
+        .last()
+        .and_then(|ch| TypeQualifier::try_from(ch).ok())
+    {
+        Some(q) => (&name[..name.len() - 1], Some(q)),
+        None => (name, None),
+    }
+}
+
+/// Splits the source into words.
+pub fn words(source: &str) -> Vec<Word> {
+    let mut result = vec![];
+    for (index, line) in source.lines().enumerate() {
+        scan_line(line, index as u32 + 1, &mut result);
+    }
+    result
+}
+
+fn scan_line(line: &str, row: u32, result: &mut Vec<Word>) {
+    let chars: Vec<char> = line.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let ch = chars[i];
+        if ch == '"' {
+            // string literal, which might not be closed
+            i += 1;
+            while i < chars.len() && chars[i] != '"' {
+                i += 1;
+            }
+            i += 1;
+        } else if ch == '\'' {
+            return;
+        } else if ch == '&' && i + 1 < chars.len() && "hHoO".contains(chars[i + 1]) {
+            // hexadecimal or octal literal
+            i += 2;
+            while i < chars.len() && chars[i].is_ascii_alphanumeric() {
+                i += 1;
+            }
+        } else if ch.is_ascii_digit() || ch == '.' {
+            // number literal, including its exponent (e.g. 1E+5) and type qualifier
+            i += 1;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.') {
+                i += 1;
+            }
+        } else if ch.is_ascii_alphabetic() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.') {
+                i += 1;
+            }
+            let bare: String = chars[start..i].iter().collect();
+            let qualifier = chars
+                .get(i)
+                .copied()
+                .filter(|ch| TypeQualifier::try_from(*ch).is_ok());
+            if qualifier.is_none() && bare.eq_ignore_ascii_case("REM") {
+                return;
+            }
+            // a keyword followed by a qualifier or containing a dot is a name
+            let keyword = match qualifier {
+                Some(_) => None,
+                None => Keyword::try_from(bare.as_str()).ok(),
+            };
+            let mut text = bare;
+            if let Some(q) = qualifier {
+                text.push(q);
+                i += 1;
+            }
+            result.push(Word {
+                pos: Position::new(row, start as u32 + 1),
+                text,
+                keyword,
+            });
+        } else {
+            i += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(source: &str) -> Vec<String> {
+        words(source).into_iter().map(|word| word.text).collect()
+    }
+
+    #[test]
+    fn test_words() {
+        let words = words("DIM A AS INTEGER\n  PRINT A$; B.C");
+        assert_eq!(words.len(), 7);
+        assert_eq!(words[0].keyword, Some(Keyword::Dim));
+        assert_eq!(words[1].text, "A");
+        assert_eq!(words[1].keyword, None);
+        assert_eq!(words[4].pos, Position::new(2, 3));
+        assert_eq!(words[5].text, "A$");
+        assert_eq!(words[6].text, "B.C");
+    }
+
+    #[test]
+    fn test_skips_strings_comments_and_numbers() {
+        assert_eq!(
+            texts(r#"PRINT "A B", X ' C D"#),
+            vec!["PRINT".to_owned(), "X".to_owned()]
+        );
+        assert_eq!(texts("X = 1 : REM Y"), vec!["X".to_owned()]);
+        assert_eq!(texts("X = &HFF + 1E+5 + 2.5#"), vec!["X".to_owned()]);
+    }
+
+    #[test]
+    fn test_keyword_with_qualifier_is_a_name() {
+        let words = words("PRINT$ = 1");
+        assert_eq!(words[0].text, "PRINT$");
+        assert_eq!(words[0].keyword, None);
+    }
+
+    #[test]
+    fn test_split_qualifier() {
+        assert_eq!(
+            split_qualifier("A%"),
+            ("A", Some(TypeQualifier::PercentInteger))
+        );
+        assert_eq!(split_qualifier("A.B"), ("A.B", None));
+    }
+}
@@ -0,0 +1,24 @@
+//! A Language Server Protocol server for BASIC files,
+//! which communicates with the editor over the standard input and output.
+//!
+//! It publishes the parse and lint errors of open documents
+//! and answers go-to-definition, find-references, hover,
+//! document symbol and completion requests.
+
+mod analysis;
+mod lexer;
+mod protocol;
+mod server;
+mod symbols;
+
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    match server::serve(std::io::stdin().lock(), std::io::stdout()) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! The wire format of the Language Server Protocol:
+//! JSON-RPC messages, each one preceded by a `Content-Length` header.
+
+use std::io::{BufRead, Error, ErrorKind, Write};
+
+use serde_json::{Value, json};
+
+/// The error code of a request whose method is not supported.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+/// A request or a notification sent by the client (e.g. VS Code).
+pub struct Message {
+    /// The id of a request, `None` for notifications.
+    pub id: Option<Value>,
+    pub method: String,
+    pub params: Value,
+}
+
+/// Reads messages and sends responses and notifications.
+pub struct Connection<R, W> {
+    reader: R,
+    writer: W,
+}
+
+impl<R: BufRead, W: Write> Connection<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        Self { reader, writer }
+    }
+
+    pub fn into_writer(self) -> W {
+        self.writer
+    }
+
+    /// Reads the next request or notification, skipping any responses.
+    /// Returns `None` when the client closes the connection.
+    pub fn read_message(&mut self) -> std::io::Result<Option<Message>> {
+        loop {
+            let Some(message) = self.read_json()? else {
+                return Ok(None);
+            };
+            let Some(method) = message["method"].as_str() else {
+                continue;
+            };
+            return Ok(Some(Message {
+                id: message.get("id").cloned(),
+                method: method.to_owned(),
+                params: message.get("params").cloned().unwrap_or(Value::Null),
+            }));
+        }
+    }
+
+    fn read_json(&mut self) -> std::io::Result<Option<Value>> {
+        let mut content_length: Option<usize> = None;
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                if content_length.is_some() {
+                    break;
+                }
+                // tolerate blank lines between messages
+                continue;
+            }
+            if let Some((name, value)) = line.split_once(':')
+                && name.trim().eq_ignore_ascii_case("Content-Length")
+            {
+                content_length = Some(value.trim().parse().map_err(|_| {
+                    Error::new(ErrorKind::InvalidData, "Invalid Content-Length header")
+                })?);
+            }
+        }
+        let mut buf = vec![0; content_length.unwrap_or_default()];
+        self.reader.read_exact(&mut buf)?;
+        serde_json::from_slice(&buf)
+            .map(Some)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+
+    /// Sends the result of the given request.
+    pub fn respond(&mut self, id: &Value, result: Value) -> std::io::Result<()> {
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+    }
+
+    /// Sends an error response to the given request.
+    pub fn respond_error(&mut self, id: &Value, code: i64, message: &str) -> std::io::Result<()> {
+        self.send(json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": code, "message": message },
+        }))
+    }
+
+    pub fn notify(&mut self, method: &str, params: Value) -> std::io::Result<()> {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+
+    fn send(&mut self, message: Value) -> std::io::Result<()> {
+        let content = message.to_string();
+        write!(
+            self.writer,
+            "Content-Length: {}\r\n\r\n{}",
+            content.len(),
+            content
+        )?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_read_message_skips_responses() {
+        let response = r#"{"jsonrpc":"2.0","id":1,"result":null}"#;
+        let request = r#"{"jsonrpc":"2.0","id":2,"method":"shutdown"}"#;
+        let input = format!(
+            "Content-Length: {}\r\n\r\n{}Content-Length: {}\r\n\r\n{}",
+            response.len(),
+            response,
+            request.len(),
+            request
+        );
+        let mut connection = Connection::new(Cursor::new(input), vec![]);
+        let message = connection.read_message().unwrap().unwrap();
+        assert_eq!(message.id, Some(json!(2)));
+        assert_eq!(message.method, "shutdown");
+        assert_eq!(message.params, Value::Null);
+        assert!(connection.read_message().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_respond_error() {
+        let mut connection = Connection::new(Cursor::new(""), vec![]);
+        connection
+            .respond_error(&json!(7), METHOD_NOT_FOUND, "Unknown method")
+            .unwrap();
+        let output = String::from_utf8(connection.into_writer()).unwrap();
+        let content =
+            r#"{"error":{"code":-32601,"message":"Unknown method"},"id":7,"jsonrpc":"2.0"}"#;
+        assert_eq!(
+            output,
+            format!("Content-Length: {}\r\n\r\n{}", content.len(), content)
+        );
+    }
+}
@@ -0,0 +1,554 @@
+//! Handles the messages of the client, keeping the analysis of every open document.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, Write};
+
+use rusty_common::Position;
+use rusty_linter::core::ScopeName;
+use rusty_parser::{
+    BuiltInFunction, BuiltInSub, SORTED_BUILT_IN_FUNCTIONS, SORTED_KEYWORDS, TypeQualifier,
+};
+use serde_json::{Value, json};
+
+use crate::analysis::{Analysis, Reference};
+use crate::protocol::{Connection, METHOD_NOT_FOUND, Message};
+use crate::symbols::{Symbol, SymbolKind};
+
+/// Serves the client until it sends the `exit` notification
+/// or closes the connection. Returns the writer.
+pub fn serve<R: BufRead, W: Write>(reader: R, writer: W) -> std::io::Result<W> {
+    let mut server = Server {
+        connection: Connection::new(reader, writer),
+        documents: HashMap::new(),
+    };
+    while let Some(message) = server.connection.read_message()? {
+        if message.method == "exit" {
+            break;
+        }
+        server.handle_message(message)?;
+    }
+    Ok(server.connection.into_writer())
+}
+
+struct Server<R, W> {
+    connection: Connection<R, W>,
+    /// The open documents, by their uri.
+    documents: HashMap<String, Analysis>,
+}
+
+impl<R: BufRead, W: Write> Server<R, W> {
+    fn handle_message(&mut self, message: Message) -> std::io::Result<()> {
+        let params = &message.params;
+        match message.method.as_str() {
+            "textDocument/didOpen" => {
+                let uri = str_param(&params["textDocument"]["uri"]);
+                let text = str_param(&params["textDocument"]["text"]);
+                return self.update_document(uri, text);
+            }
+            "textDocument/didChange" => {
+                // full synchronization, the last change has the whole text
+                let uri = str_param(&params["textDocument"]["uri"]);
+                if let Some(change) = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                {
+                    return self.update_document(uri, str_param(&change["text"]));
+                }
+                return Ok(());
+            }
+            "textDocument/didClose" => {
+                let uri = str_param(&params["textDocument"]["uri"]);
+                self.documents.remove(uri);
+                return self.connection.notify(
+                    "textDocument/publishDiagnostics",
+                    json!({ "uri": uri, "diagnostics": [] }),
+                );
+            }
+            _ => {}
+        }
+        let Some(id) = &message.id else {
+            // other notifications (e.g. `initialized`) need no action
+            return Ok(());
+        };
+        let result = match message.method.as_str() {
+            "initialize" => json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "definitionProvider": true,
+                    "referencesProvider": true,
+                    "hoverProvider": true,
+                    "documentSymbolProvider": true,
+                    "completionProvider": {},
+                },
+                "serverInfo": { "name": "rusty_lsp", "version": env!("CARGO_PKG_VERSION") },
+            }),
+            "shutdown" => Value::Null,
+            "textDocument/definition" => self.definition(params),
+            "textDocument/references" => self.references(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/documentSymbol" => self.document_symbols(params),
+            "textDocument/completion" => self.completion(params),
+            _ => {
+                return self.connection.respond_error(
+                    id,
+                    METHOD_NOT_FOUND,
+                    &format!("Unsupported method {}", message.method),
+                );
+            }
+        };
+        self.connection.respond(id, result)
+    }
+
+    fn update_document(&mut self, uri: &str, text: &str) -> std::io::Result<()> {
+        let analysis = Analysis::new(text);
+        let diagnostics: Vec<Value> = analysis
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                json!({
+                    "range": range(&analysis, diagnostic.pos, 1),
+                    "severity": 1,
+                    "source": "rusty_basic",
+                    "message": diagnostic.message,
+                })
+            })
+            .collect();
+        self.documents.insert(uri.to_owned(), analysis);
+        self.connection.notify(
+            "textDocument/publishDiagnostics",
+            json!({ "uri": uri, "diagnostics": diagnostics }),
+        )
+    }
+
+    /// Gets the analysis of the document and the reference at the position
+    /// of the given text document position params.
+    fn reference_at(&self, params: &Value) -> Option<(&Analysis, Reference)> {
+        let analysis = self
+            .documents
+            .get(str_param(&params["textDocument"]["uri"]))?;
+        let reference = analysis.reference_at(from_lsp_position(analysis, &params["position"]))?;
+        Some((analysis, reference))
+    }
+
+    fn definition(&self, params: &Value) -> Value {
+        match self.reference_at(params) {
+            Some((analysis, reference)) => {
+                let symbol = &analysis.symbols[reference.symbol];
+                location(params, analysis, symbol.pos, symbol_len(symbol))
+            }
+            None => Value::Null,
+        }
+    }
+
+    fn references(&self, params: &Value) -> Value {
+        let Some((analysis, reference)) = self.reference_at(params) else {
+            return Value::Null;
+        };
+        let include_declaration = params["context"]["includeDeclaration"]
+            .as_bool()
+            .unwrap_or(true);
+        let definition = analysis.symbols[reference.symbol].pos;
+        let locations: Vec<Value> = analysis
+            .references(reference.symbol)
+            .into_iter()
+            .filter(|r| include_declaration || r.pos != definition)
+            .map(|r| location(params, analysis, r.pos, r.len))
+            .collect();
+        json!(locations)
+    }
+
+    fn hover(&self, params: &Value) -> Value {
+        match self.reference_at(params) {
+            Some((analysis, reference)) => json!({
+                "contents": {
+                    "kind": "markdown",
+                    "value": format!("```basic\n{}\n```", analysis.symbols[reference.symbol].detail),
+                },
+                "range": range(analysis, reference.pos, reference.len),
+            }),
+            None => Value::Null,
+        }
+    }
+
+    fn document_symbols(&self, params: &Value) -> Value {
+        let Some(analysis) = self
+            .documents
+            .get(str_param(&params["textDocument"]["uri"]))
+        else {
+            return Value::Null;
+        };
+        let document_symbols: Vec<Value> = analysis
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.scope == ScopeName::Global)
+            .map(|symbol| {
+                let selection_range = range(analysis, symbol.pos, symbol_len(symbol));
+                let subprogram_scope = analysis.scopes.scope_at(symbol.pos.row());
+                let subprogram_rows = match symbol.kind {
+                    SymbolKind::Sub | SymbolKind::Function => analysis
+                        .scopes
+                        .rows_of(&subprogram_scope, analysis.line_count),
+                    _ => None,
+                };
+                match subprogram_rows {
+                    Some((start_row, end_row)) => {
+                        let children: Vec<Value> = analysis
+                            .symbols
+                            .iter()
+                            .filter(|child| child.scope == subprogram_scope)
+                            .map(|child| {
+                                let child_range = range(analysis, child.pos, symbol_len(child));
+                                document_symbol(child, child_range.clone(), child_range, vec![])
+                            })
+                            .collect();
+                        let full_range = json!({
+                            "start": { "line": start_row - 1, "character": 0 },
+                            "end": { "line": end_row, "character": 0 },
+                        });
+                        document_symbol(symbol, full_range, selection_range, children)
+                    }
+                    None => {
+                        document_symbol(symbol, selection_range.clone(), selection_range, vec![])
+                    }
+                }
+            })
+            .collect();
+        json!(document_symbols)
+    }
+
+    fn completion(&self, params: &Value) -> Value {
+        // sorted and without duplicates, as some keywords are also built-in functions
+        let mut items: BTreeMap<String, i64> = BTreeMap::new();
+        for keyword in SORTED_KEYWORDS {
+            items.insert(keyword.as_str().to_uppercase(), COMPLETION_KEYWORD);
+        }
+        for built_in_function in SORTED_BUILT_IN_FUNCTIONS {
+            items.insert(
+                built_in_function_name(built_in_function),
+                COMPLETION_FUNCTION,
+            );
+        }
+        for name in BuiltInSub::non_keyword_sub_names() {
+            items.insert(name.to_uppercase(), COMPLETION_FUNCTION);
+        }
+        if let Some(analysis) = self
+            .documents
+            .get(str_param(&params["textDocument"]["uri"]))
+        {
+            let row = from_lsp_position(analysis, &params["position"]).row();
+            for symbol in analysis.visible_symbols(row) {
+                items.insert(symbol.name.clone(), completion_item_kind(symbol.kind));
+            }
+        }
+        let items: Vec<Value> = items
+            .into_iter()
+            .map(|(label, kind)| json!({ "label": label, "kind": kind }))
+            .collect();
+        json!(items)
+    }
+}
+
+// CompletionItemKind values of the protocol
+const COMPLETION_FUNCTION: i64 = 3;
+const COMPLETION_VARIABLE: i64 = 6;
+const COMPLETION_KEYWORD: i64 = 14;
+const COMPLETION_REFERENCE: i64 = 18;
+const COMPLETION_CONSTANT: i64 = 21;
+const COMPLETION_STRUCT: i64 = 22;
+
+fn completion_item_kind(kind: SymbolKind) -> i64 {
+    match kind {
+        SymbolKind::Sub | SymbolKind::Function => COMPLETION_FUNCTION,
+        SymbolKind::Label => COMPLETION_REFERENCE,
+        SymbolKind::Constant => COMPLETION_CONSTANT,
+        SymbolKind::Type => COMPLETION_STRUCT,
+        SymbolKind::Variable | SymbolKind::Parameter => COMPLETION_VARIABLE,
+    }
+}
+
+/// Gets the name of a built-in function, e.g. `CHR$`.
+fn built_in_function_name(built_in_function: &BuiltInFunction) -> String {
+    let name = built_in_function.as_str().to_uppercase();
+    if TypeQualifier::from(built_in_function) == TypeQualifier::DollarString {
+        format!("{}$", name)
+    } else {
+        name
+    }
+}
+
+fn document_symbol(
+    symbol: &Symbol,
+    range: Value,
+    selection_range: Value,
+    children: Vec<Value>,
+) -> Value {
+    // SymbolKind values of the protocol
+    let kind = match symbol.kind {
+        SymbolKind::Sub => 6,
+        SymbolKind::Function => 12,
+        SymbolKind::Label => 20,
+        SymbolKind::Constant => 14,
+        SymbolKind::Type => 23,
+        SymbolKind::Variable | SymbolKind::Parameter => 13,
+    };
+    json!({
+        "name": symbol.name,
+        "detail": symbol.detail.lines().next().unwrap_or_default(),
+        "kind": kind,
+        "range": range,
+        "selectionRange": selection_range,
+        "children": children,
+    })
+}
+
+fn str_param(value: &Value) -> &str {
+    value.as_str().unwrap_or_default()
+}
+
+/// The length of the name of the symbol, as it appears in its definition.
+fn symbol_len(symbol: &Symbol) -> u32 {
+    symbol.name.chars().count() as u32
+}
+
+/// Converts a position of the protocol to a [Position] of the document.
+fn from_lsp_position(analysis: &Analysis, position: &Value) -> Position {
+    let line = position["line"].as_u64().unwrap_or_default() as u32;
+    let character = position["character"].as_u64().unwrap_or_default() as u32;
+    analysis.position_of_utf16(line, character)
+}
+
+/// Gets the range of the protocol that starts at the given position
+/// and spans the given number of characters.
+fn range(analysis: &Analysis, pos: Position, len: u32) -> Value {
+    let line = pos.row() - 1;
+    json!({
+        "start": { "line": line, "character": analysis.utf16_character(pos.row(), pos.col()) },
+        "end": { "line": line, "character": analysis.utf16_character(pos.row(), pos.col() + len) },
+    })
+}
+
+fn location(params: &Value, analysis: &Analysis, pos: Position, len: u32) -> Value {
+    json!({ "uri": params["textDocument"]["uri"], "range": range(analysis, pos, len) })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    const URI: &str = "file:///test.bas";
+
+    /// Sends the given messages to the server
+    /// and returns the messages it sent back.
+    fn run_session(messages: Vec<Value>) -> Vec<Value> {
+        let input: String = messages
+            .into_iter()
+            .map(|message| {
+                let content = message.to_string();
+                format!("Content-Length: {}\r\n\r\n{}", content.len(), content)
+            })
+            .collect();
+        let output = serve(Cursor::new(input), vec![]).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        output
+            .split("Content-Length: ")
+            .filter(|part| !part.is_empty())
+            .map(|part| {
+                let (_, content) = part.split_once("\r\n\r\n").unwrap();
+                serde_json::from_str(content).unwrap()
+            })
+            .collect()
+    }
+
+    fn open(text: &str) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": URI, "languageId": "basic", "version": 1, "text": text } },
+        })
+    }
+
+    fn request(id: i64, method: &str, line: u32, character: u32) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": {
+                "textDocument": { "uri": URI },
+                "position": { "line": line, "character": character },
+                "context": { "includeDeclaration": false },
+            },
+        })
+    }
+
+    fn result(responses: &[Value], id: i64) -> &Value {
+        &responses
+            .iter()
+            .find(|response| response["id"] == id)
+            .unwrap()["result"]
+    }
+
+    const PROGRAM: &str = "DIM SHARED Total AS LONG\nAdd 2\nPRINT Total\nSUB Add(N)\n    Total = Total + N\nEND SUB\n";
+
+    #[test]
+    fn test_initialize_and_shutdown() {
+        let responses = run_session(vec![
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+            json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "shutdown" }),
+            json!({ "jsonrpc": "2.0", "method": "exit" }),
+            json!({ "jsonrpc": "2.0", "id": 3, "method": "shutdown" }),
+        ]);
+        assert_eq!(responses.len(), 2);
+        assert_eq!(
+            responses[0]["result"]["capabilities"]["textDocumentSync"],
+            1
+        );
+        assert_eq!(
+            responses[1],
+            json!({ "jsonrpc": "2.0", "id": 2, "result": null })
+        );
+    }
+
+    #[test]
+    fn test_publish_diagnostics_on_change() {
+        let responses = run_session(vec![
+            open("PRINT 1\n"),
+            json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/didChange",
+                "params": {
+                    "textDocument": { "uri": URI, "version": 2 },
+                    "contentChanges": [{ "text": "PRINT 1\nX = \"a\" + 1\n" }],
+                },
+            }),
+        ]);
+        assert_eq!(responses[0]["params"]["diagnostics"], json!([]));
+        assert_eq!(
+            responses[1]["params"]["diagnostics"],
+            json!([{
+                "range": {
+                    "start": { "line": 1, "character": 10 },
+                    "end": { "line": 1, "character": 11 },
+                },
+                "severity": 1,
+                "source": "rusty_basic",
+                "message": "Type mismatch",
+            }])
+        );
+    }
+
+    #[test]
+    fn test_definition_references_and_hover() {
+        let responses = run_session(vec![
+            open(PROGRAM),
+            request(1, "textDocument/definition", 4, 13),
+            request(2, "textDocument/references", 0, 12),
+            request(3, "textDocument/hover", 4, 20),
+            request(4, "textDocument/definition", 1, 0),
+        ]);
+        let analysis = Analysis::new(PROGRAM);
+        assert_eq!(
+            result(&responses, 1),
+            &json!({ "uri": URI, "range": range(&analysis, Position::new(1, 12), 5) })
+        );
+        let references: Vec<(u64, u64)> = result(&responses, 2)
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|location| {
+                let start = &location["range"]["start"];
+                (
+                    start["line"].as_u64().unwrap(),
+                    start["character"].as_u64().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(references, vec![(2, 6), (4, 4), (4, 12)]);
+        assert_eq!(
+            result(&responses, 3)["contents"]["value"],
+            "```basic\nN! (parameter)\n```"
+        );
+        assert_eq!(
+            result(&responses, 4),
+            &json!({ "uri": URI, "range": range(&analysis, Position::new(4, 5), 3) })
+        );
+    }
+
+    #[test]
+    fn test_positions_in_utf16_code_units() {
+        // the emoji takes two UTF-16 code units
+        let text = "PRINT \"\u{1F600}\"; Total\nDIM Total AS LONG\n";
+        let responses = run_session(vec![
+            open(text),
+            request(1, "textDocument/definition", 0, 12),
+            request(2, "textDocument/hover", 0, 16),
+        ]);
+        assert_eq!(
+            result(&responses, 1)["range"]["start"],
+            json!({ "line": 1, "character": 4 })
+        );
+        assert_eq!(
+            result(&responses, 2)["range"],
+            json!({
+                "start": { "line": 0, "character": 12 },
+                "end": { "line": 0, "character": 17 },
+            })
+        );
+    }
+
+    #[test]
+    fn test_document_symbols() {
+        let responses = run_session(vec![
+            open(PROGRAM),
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "textDocument/documentSymbol",
+                "params": { "textDocument": { "uri": URI } },
+            }),
+        ]);
+        let symbols = result(&responses, 1).as_array().unwrap();
+        let names: Vec<&str> = symbols
+            .iter()
+            .map(|symbol| symbol["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["Total", "Add"]);
+        assert_eq!(symbols[1]["detail"], "SUB Add (N!)");
+        assert_eq!(symbols[1]["range"]["end"]["line"], 6);
+        assert_eq!(symbols[1]["children"][0]["name"], "N!");
+    }
+
+    #[test]
+    fn test_completion() {
+        let responses = run_session(vec![
+            open(PROGRAM),
+            request(1, "textDocument/completion", 4, 0),
+        ]);
+        let items = result(&responses, 1).as_array().unwrap();
+        let kind_of = |label: &str| {
+            items
+                .iter()
+                .find(|item| item["label"] == label)
+                .map(|item| item["kind"].as_i64().unwrap())
+        };
+        assert_eq!(kind_of("PRINT"), Some(COMPLETION_KEYWORD));
+        assert_eq!(kind_of("CHR$"), Some(COMPLETION_FUNCTION));
+        assert_eq!(kind_of("BEEP"), Some(COMPLETION_FUNCTION));
+        assert_eq!(kind_of("N!"), Some(COMPLETION_VARIABLE));
+        assert_eq!(kind_of("Total"), Some(COMPLETION_VARIABLE));
+        assert_eq!(kind_of("Add"), Some(COMPLETION_FUNCTION));
+    }
+
+    #[test]
+    fn test_unsupported_request() {
+        let responses = run_session(vec![json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "textDocument/formatting",
+            "params": {},
+        })]);
+        assert_eq!(responses[0]["error"]["code"], METHOD_NOT_FOUND);
+    }
+}
@@ -0,0 +1,478 @@
+//! Collects the symbols that a program defines,
+//! i.e. SUBs, FUNCTIONs, labels, CONSTs, TYPEs, variables and parameters.
+
+use rusty_common::Position;
+use rusty_linter::core::{
+    DeepStatementVisitor, DelegateVisitor, ScopeName, SetPosition, VisitResult, Visitor,
+};
+use rusty_parser::*;
+
+use crate::lexer::words;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SymbolKind {
+    Sub,
+    Function,
+    Label,
+    Constant,
+    Type,
+    Variable,
+    Parameter,
+}
+
+/// A symbol defined in the program.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Symbol {
+    pub kind: SymbolKind,
+    /// The name as it is shown to the user, e.g. `A$`.
+    pub name: String,
+    pub bare_name: BareName,
+    /// The type qualifier of a variable, a parameter or a FUNCTION.
+    pub qualifier: Option<TypeQualifier>,
+    /// Set for variables and parameters declared with `AS`,
+    /// which can be referenced without a type qualifier.
+    pub extended: bool,
+    /// Set for variables declared with `DIM SHARED`.
+    pub shared: bool,
+    /// The scope where the symbol is defined.
+    pub scope: ScopeName,
+    /// The position of the name of the symbol in the definition.
+    pub pos: Position,
+    /// A code snippet describing the symbol, shown on hover.
+    pub detail: String,
+}
+
+impl Symbol {
+    pub fn is_variable(&self) -> bool {
+        matches!(self.kind, SymbolKind::Variable | SymbolKind::Parameter)
+    }
+}
+
+/// The rows occupied by a SUB or a FUNCTION.
+struct SubprogramRange {
+    scope: ScopeName,
+    start_row: u32,
+    /// The row where the next global statement starts (exclusive).
+    end_row: Option<u32>,
+}
+
+/// Finds the scope (SUB, FUNCTION or the module) of a source row.
+pub struct Scopes(Vec<SubprogramRange>);
+
+impl Scopes {
+    /// Collects the SUBs and FUNCTIONs of the parsed program.
+    pub fn new(program: &Program) -> Self {
+        let mut ranges = vec![];
+        for (index, global_statement_pos) in program.iter().enumerate() {
+            let scope = match &global_statement_pos.element {
+                GlobalStatement::FunctionImplementation(f) => {
+                    ScopeName::Function(f.name.element.clone())
+                }
+                GlobalStatement::SubImplementation(s) => ScopeName::Sub(s.name.element.clone()),
+                _ => continue,
+            };
+            ranges.push(SubprogramRange {
+                scope,
+                start_row: global_statement_pos.pos.row(),
+                end_row: program.get(index + 1).map(|next| next.pos.row()),
+            });
+        }
+        Self(ranges)
+    }
+
+    pub fn scope_at(&self, row: u32) -> ScopeName {
+        self.0
+            .iter()
+            .find(|r| r.start_row <= row && r.end_row.is_none_or(|end_row| row < end_row))
+            .map_or(ScopeName::Global, |r| r.scope.clone())
+    }
+
+    /// Gets the first and the last row of the given SUB or FUNCTION.
+    pub fn rows_of(&self, scope: &ScopeName, last_row: u32) -> Option<(u32, u32)> {
+        self.0.iter().find(|r| &r.scope == scope).map(|r| {
+            (
+                r.start_row,
+                r.end_row
+                    .map_or(last_row, |end_row| end_row.saturating_sub(1)),
+            )
+        })
+    }
+}
+
+/// Collects the symbols of a program, parsed or linted.
+pub fn collect_symbols(program: &Program, scopes: &Scopes, source: &str) -> Vec<Symbol> {
+    let collector = SymbolCollector {
+        scopes,
+        lines: source.lines().collect(),
+        pos: Position::start(),
+        symbols: vec![],
+    };
+    let mut visitor = DeepStatementVisitor::new(collector);
+    visitor
+        .visit(program)
+        .expect("Collecting symbols should not fail");
+    visitor.delegate().symbols
+}
+
+struct SymbolCollector<'a> {
+    scopes: &'a Scopes,
+    lines: Vec<&'a str>,
+    /// The position of the statement being visited.
+    pos: Position,
+    symbols: Vec<Symbol>,
+}
+
+impl SymbolCollector<'_> {
+    fn add(&mut self, kind: SymbolKind, bare_name: BareName, pos: Position, detail: String) {
+        self.symbols.push(Symbol {
+            kind,
+            name: bare_name.to_string(),
+            bare_name,
+            qualifier: None,
+            extended: false,
+            shared: false,
+            scope: self.scopes.scope_at(pos.row()),
+            pos,
+            detail,
+        });
+    }
+
+    fn add_variable<T: VarType>(
+        &mut self,
+        kind: SymbolKind,
+        var: &TypedName<T>,
+        pos: Position,
+        shared: bool,
+    ) {
+        let var_type = var.var_type();
+        let extended = var_type.is_extended();
+        let qualifier = var_type.to_qualifier_recursively();
+        let bare_name = var.as_bare_name().clone();
+        let name = match qualifier {
+            Some(q) if !extended => format!("{}{}", bare_name, q),
+            _ => bare_name.to_string(),
+        };
+        let detail = match kind {
+            SymbolKind::Parameter => format!("{} (parameter)", format_typed_name(var)),
+            _ if shared => format!("DIM SHARED {}", format_typed_name(var)),
+            _ => format!("DIM {}", format_typed_name(var)),
+        };
+        self.symbols.push(Symbol {
+            kind,
+            name,
+            bare_name,
+            qualifier,
+            extended,
+            shared,
+            scope: self.scopes.scope_at(pos.row()),
+            pos,
+            detail,
+        });
+    }
+
+    fn add_parameters(&mut self, params: &Parameters) {
+        for param in params {
+            self.add_variable(SymbolKind::Parameter, &param.element, param.pos, false);
+        }
+    }
+
+    /// Finds the position of the given name in the source,
+    /// searching from the given position onwards.
+    fn find_name(&self, from: Position, name: &str) -> Position {
+        let words = self
+            .lines
+            .get(from.row() as usize - 1)
+            .map(|line| words(line))
+            .unwrap_or_default();
+        words
+            .into_iter()
+            .find(|word| word.pos.col() >= from.col() && word.text.eq_ignore_ascii_case(name))
+            .map_or(from, |word| Position::new(from.row(), word.pos.col()))
+    }
+
+    /// Gets the source of the statement at the given position,
+    /// until the end of the line or the next statement.
+    fn statement_source(&self, pos: Position) -> String {
+        let line = self.lines.get(pos.row() as usize - 1).unwrap_or(&"");
+        let mut in_string = false;
+        line.chars()
+            .skip(pos.col() as usize - 1)
+            .take_while(|ch| {
+                if *ch == '"' {
+                    in_string = !in_string;
+                }
+                in_string || (*ch != ':' && *ch != '\'')
+            })
+            .collect::<String>()
+            .trim_end()
+            .to_owned()
+    }
+}
+
+impl SetPosition for SymbolCollector<'_> {
+    fn set_position(&mut self, pos: Position) {
+        self.pos = pos;
+    }
+}
+
+impl Visitor<DefType> for SymbolCollector<'_> {
+    fn visit(&mut self, _element: &DefType) -> VisitResult {
+        Ok(())
+    }
+}
+
+impl Visitor<FunctionDeclaration> for SymbolCollector<'_> {
+    fn visit(&mut self, _element: &FunctionDeclaration) -> VisitResult {
+        Ok(())
+    }
+}
+
+impl Visitor<SubDeclaration> for SymbolCollector<'_> {
+    fn visit(&mut self, _element: &SubDeclaration) -> VisitResult {
+        Ok(())
+    }
+}
+
+impl Visitor<FunctionImplementation> for SymbolCollector<'_> {
+    fn visit(&mut self, element: &FunctionImplementation) -> VisitResult {
+        let name = &element.name.element;
+        self.symbols.push(Symbol {
+            kind: SymbolKind::Function,
+            name: name.to_string(),
+            bare_name: name.as_bare_name().clone(),
+            qualifier: name.qualifier(),
+            extended: false,
+            shared: false,
+            scope: ScopeName::Global,
+            pos: element.name.pos,
+            detail: format!("FUNCTION {}{}", name, format_parameters(&element.params)),
+        });
+        self.add_parameters(&element.params);
+        Ok(())
+    }
+}
+
+impl Visitor<SubImplementation> for SymbolCollector<'_> {
+    fn visit(&mut self, element: &SubImplementation) -> VisitResult {
+        let name = &element.name.element;
+        let detail = format!("SUB {}{}", name, format_parameters(&element.params));
+        self.add(SymbolKind::Sub, name.clone(), element.name.pos, detail);
+        // SUBs are global, even though their name is inside their range
+        self.symbols.last_mut().unwrap().scope = ScopeName::Global;
+        self.add_parameters(&element.params);
+        Ok(())
+    }
+}
+
+impl Visitor<UserDefinedType> for SymbolCollector<'_> {
+    fn visit(&mut self, element: &UserDefinedType) -> VisitResult {
+        let bare_name = element.bare_name();
+        let mut detail = format!("TYPE {}\n", bare_name);
+        for element_pos in element.elements() {
+            let Element {
+                name, element_type, ..
+            } = &element_pos.element;
+            detail.push_str(&format!(
+                "    {} AS {}\n",
+                name,
+                type_name(&element_type.expression_type())
+            ));
+        }
+        detail.push_str("END TYPE");
+        let pos = self.find_name(self.pos, bare_name.as_ref());
+        self.add(SymbolKind::Type, bare_name.clone(), pos, detail);
+        Ok(())
+    }
+}
+
+impl Visitor<Statement> for SymbolCollector<'_> {
+    fn visit(&mut self, element: &Statement) -> VisitResult {
+        match element {
+            Statement::Dim(dim_list) => {
+                for var in &dim_list.variables {
+                    self.add_variable(SymbolKind::Variable, &var.element, var.pos, dim_list.shared);
+                }
+            }
+            Statement::Redim(dim_list) => {
+                // REDIM declares the array only if it is not declared yet
+                for var in &dim_list.variables {
+                    let scope = self.scopes.scope_at(var.pos.row());
+                    if !self.symbols.iter().any(|symbol| {
+                        symbol.is_variable()
+                            && symbol.scope == scope
+                            && &symbol.bare_name == var.element.as_bare_name()
+                    }) {
+                        self.add_variable(SymbolKind::Variable, &var.element, var.pos, false);
+                    }
+                }
+            }
+            Statement::Const(constant) => {
+                let name = constant.name();
+                let detail = format!("CONST {}", self.statement_source(name.pos));
+                self.add(
+                    SymbolKind::Constant,
+                    name.element.as_bare_name().clone(),
+                    name.pos,
+                    detail,
+                );
+            }
+            Statement::Label(label) => {
+                let pos = self.find_name(self.pos, label.as_ref());
+                self.add(SymbolKind::Label, label.clone(), pos, format!("{}:", label));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Formats a variable or a parameter, e.g. `A AS INTEGER`, `B$`, `C() AS Card`.
+fn format_typed_name<T: VarType>(var: &TypedName<T>) -> String {
+    let var_type = var.var_type();
+    let resolved = var_type.to_qualifier_recursively().is_some()
+        || var_type.as_user_defined_recursively().is_some();
+    if !resolved {
+        return var.as_bare_name().to_string();
+    }
+    let expression_type = var_type.expression_type();
+    let array = if matches!(expression_type, ExpressionType::Array(_)) {
+        "()"
+    } else {
+        ""
+    };
+    match var_type.to_qualifier_recursively() {
+        Some(q) if !var_type.is_extended() => format!("{}{}{}", var.as_bare_name(), q, array),
+        _ => format!(
+            "{}{} AS {}",
+            var.as_bare_name(),
+            array,
+            type_name(&expression_type)
+        ),
+    }
+}
+
+fn format_parameters(params: &Parameters) -> String {
+    if params.is_empty() {
+        return String::new();
+    }
+    let params: Vec<String> = params
+        .iter()
+        .map(|param| format_typed_name(&param.element))
+        .collect();
+    format!(" ({})", params.join(", "))
+}
+
+/// Gets the name of a type, as it is written after `AS`.
+pub fn type_name(expression_type: &ExpressionType) -> String {
+    match expression_type {
+        ExpressionType::BuiltIn(q) => built_in_type_name(*q).to_owned(),
+        ExpressionType::FixedLengthString(len) => format!("STRING * {}", len),
+        ExpressionType::UserDefined(name) => name.to_string(),
+        ExpressionType::Array(element_type) => type_name(element_type),
+        ExpressionType::Unresolved => String::new(),
+    }
+}
+
+fn built_in_type_name(q: TypeQualifier) -> &'static str {
+    match q {
+        TypeQualifier::BangSingle => "SINGLE",
+        TypeQualifier::HashDouble => "DOUBLE",
+        TypeQualifier::DollarString => "STRING",
+        TypeQualifier::PercentInteger => "INTEGER",
+        TypeQualifier::AmpersandLong => "LONG",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusty_linter::core::lint;
+    use rusty_parser::parse_main_str;
+
+    use super::*;
+
+    fn symbols(source: &str, linted: bool) -> Vec<Symbol> {
+        let program = parse_main_str(source.to_owned()).unwrap();
+        let scopes = Scopes::new(&program);
+        if linted {
+            let (program, _) = lint(program).unwrap();
+            collect_symbols(&program, &scopes, source)
+        } else {
+            collect_symbols(&program, &scopes, source)
+        }
+    }
+
+    #[test]
+    fn test_collect_symbols() {
+        let source = r#"TYPE Card
+    Value AS INTEGER
+END TYPE
+CONST Max = 10
+DIM SHARED C AS Card
+Start:
+Hello 1
+SUB Hello(N%)
+    X$ = "hi"
+END SUB
+"#;
+        let symbols = symbols(source, false);
+        let summary: Vec<(SymbolKind, &str, Position, &str)> = symbols
+            .iter()
+            .map(|s| (s.kind, s.name.as_str(), s.pos, s.detail.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    SymbolKind::Type,
+                    "Card",
+                    Position::new(1, 6),
+                    "TYPE Card\n    Value AS INTEGER\nEND TYPE"
+                ),
+                (
+                    SymbolKind::Constant,
+                    "Max",
+                    Position::new(4, 7),
+                    "CONST Max = 10"
+                ),
+                (
+                    SymbolKind::Variable,
+                    "C",
+                    Position::new(5, 12),
+                    "DIM SHARED C AS Card"
+                ),
+                (SymbolKind::Label, "Start", Position::new(6, 1), "Start:"),
+                (
+                    SymbolKind::Sub,
+                    "Hello",
+                    Position::new(8, 5),
+                    "SUB Hello (N%)"
+                ),
+                (
+                    SymbolKind::Parameter,
+                    "N%",
+                    Position::new(8, 11),
+                    "N% (parameter)"
+                ),
+            ]
+        );
+        assert_eq!(symbols[5].scope, ScopeName::Sub("Hello".into()));
+    }
+
+    #[test]
+    fn test_linted_program_has_implicit_variables() {
+        let source = "DEFINT A-Z\nA = 1\nFUNCTION Twice(X)\n    Twice = X * 2\nEND FUNCTION";
+        let symbols = symbols(source, true);
+        let summary: Vec<(SymbolKind, &str, &str)> = symbols
+            .iter()
+            .map(|s| (s.kind, s.name.as_str(), s.detail.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (SymbolKind::Variable, "A%", "DIM A%"),
+                (SymbolKind::Function, "Twice%", "FUNCTION Twice% (X%)"),
+                (SymbolKind::Parameter, "X%", "X% (parameter)"),
+            ]
+        );
+    }
+}
@@ -9,6 +9,9 @@ pub enum BuiltInFunction SORTED_BUILT_IN_FUNCTIONS SORTED_BUILT_IN_FUNCTION_NAME
     /// `CHR$(ascii-code%)` returns the text representation of the given ascii code
     Chr,
 
+    /// `COMMAND$` returns the command line arguments of the program, in uppercase
+    Command,
+
     /// `CVD(8 byte string-expression)`
     ///
     /// Converts a string previously created by `MKD$` into a double.
@@ -22,6 +25,12 @@ pub enum BuiltInFunction SORTED_BUILT_IN_FUNCTIONS SORTED_BUILT_IN_FUNCTION_NAME
     /// `EOF(file-number%)` -> checks if the end of file has been reached
     Eof,
 
+    /// `ERDEV` -> the error code of the last device error
+    ErDev,
+
+    /// `ERL` -> the line of the statement that caused the most recent error
+    Erl,
+
     /// `ERR`
     Err,
 
@@ -49,6 +58,10 @@ pub enum BuiltInFunction SORTED_BUILT_IN_FUNCTIONS SORTED_BUILT_IN_FUNCTION_NAME
     /// `LEN(variable)` -> number of bytes required to store a variable
     Len,
 
+    /// `LPOS(n%)` returns the current position of the print head
+    /// in the printer buffer (1 based). `n%` is 0 or 1 for LPT1.
+    LPos,
+
     /// `LTRIM$`
     LTrim,
 
@@ -123,9 +136,12 @@ impl From<&BuiltInFunction> for TypeQualifier {
     fn from(x: &BuiltInFunction) -> Self {
         match x {
             BuiltInFunction::Chr => Self::DollarString,
+            BuiltInFunction::Command => Self::DollarString,
             BuiltInFunction::Cvd => Self::HashDouble,
             BuiltInFunction::Environ => Self::DollarString,
             BuiltInFunction::Eof => Self::PercentInteger,
+            BuiltInFunction::ErDev => Self::PercentInteger,
+            BuiltInFunction::Erl => Self::PercentInteger,
             BuiltInFunction::Err => Self::PercentInteger,
             BuiltInFunction::InKey => Self::DollarString,
             BuiltInFunction::InStr => Self::PercentInteger,
@@ -133,6 +149,7 @@ impl From<&BuiltInFunction> for TypeQualifier {
             BuiltInFunction::LCase => Self::DollarString,
             BuiltInFunction::Left => Self::DollarString,
             BuiltInFunction::Len => Self::PercentInteger,
+            BuiltInFunction::LPos => Self::PercentInteger,
             BuiltInFunction::LTrim => Self::DollarString,
             BuiltInFunction::Mid => Self::DollarString,
             BuiltInFunction::Mkd => Self::DollarString,
@@ -1,5 +1,12 @@
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum BuiltInSub {
+    /// `ASSERT condition [, message$]` -> fails the running test
+    /// if the condition is false (zero)
+    Assert,
+
+    /// `ASSERTEQUAL expected, actual` -> fails the running test
+    /// if the two numbers (or the two strings) are not equal
+    AssertEqual,
     Beep,
     CallAbsolute,
     Close,
@@ -8,6 +15,9 @@ pub enum BuiltInSub {
     Data,
     DefSeg,
     Environ,
+
+    /// `ERROR integer-expression%` -> simulates the error with the given code (1-255)
+    Error,
     Field,
     Get,
 
@@ -27,6 +37,12 @@ pub enum BuiltInSub {
     ///
     Input,
 
+    /// `KEY n%, string-expression$` -> assigns a soft key string to a function key
+    /// (n 1-10, 30, 31) or defines a trappable key (n 15-25)
+    ///
+    /// `KEY LIST` -> displays the soft key assignments
+    Key,
+
     /// `KILL file-spec$` -> deletes files from disk
     Kill,
 
@@ -74,6 +90,25 @@ pub enum BuiltInSub {
     Width,
 }
 
+/// The built-in subs which are not implemented with a keyword, by name.
+const NON_KEYWORD_SUBS: &[(&str, BuiltInSub)] = &[
+    ("Beep", BuiltInSub::Beep),
+    ("Call", BuiltInSub::CallAbsolute),
+    ("Cls", BuiltInSub::Cls),
+    ("Color", BuiltInSub::Color),
+    ("Environ", BuiltInSub::Environ),
+    ("Kill", BuiltInSub::Kill),
+    ("Poke", BuiltInSub::Poke),
+    ("Screen", BuiltInSub::Screen),
+];
+
+/// The built-in subs which are only available to unit tests, by name.
+/// Other programs are free to implement SUBs with these names.
+const TEST_SUBS: &[(&str, BuiltInSub)] = &[
+    ("Assert", BuiltInSub::Assert),
+    ("AssertEqual", BuiltInSub::AssertEqual),
+];
+
 impl BuiltInSub {
     /// Parses a built-in sub name which isn't implemented with a keyword.
     /// This sub would appear as a user defined SUB on the parser layer.
@@ -82,24 +117,25 @@ impl BuiltInSub {
     /// they can't hit this function, as they are represented by keywords and are
     /// parsed by custom parsers.
     pub fn parse_non_keyword_sub(s: &str) -> Option<Self> {
-        if s.eq_ignore_ascii_case("Beep") {
-            Some(Self::Beep)
-        } else if s.eq_ignore_ascii_case("Call") {
-            Some(Self::CallAbsolute)
-        } else if s.eq_ignore_ascii_case("Cls") {
-            Some(Self::Cls)
-        } else if s.eq_ignore_ascii_case("Color") {
-            Some(Self::Color)
-        } else if s.eq_ignore_ascii_case("Environ") {
-            Some(Self::Environ)
-        } else if s.eq_ignore_ascii_case("Kill") {
-            Some(Self::Kill)
-        } else if s.eq_ignore_ascii_case("Poke") {
-            Some(Self::Poke)
-        } else if s.eq_ignore_ascii_case("Screen") {
-            Some(Self::Screen)
-        } else {
-            None
-        }
+        find_by_name(NON_KEYWORD_SUBS, s)
     }
+
+    /// Parses a built-in sub name which is only available to unit tests
+    /// (e.g. `ASSERT`). Like [Self::parse_non_keyword_sub], this sub would
+    /// appear as a user defined SUB on the parser layer.
+    pub fn parse_test_sub(s: &str) -> Option<Self> {
+        find_by_name(TEST_SUBS, s)
+    }
+
+    /// Gets the names of the built-in subs which are not implemented with a keyword
+    /// (see [Self::parse_non_keyword_sub]).
+    pub fn non_keyword_sub_names() -> impl Iterator<Item = &'static str> {
+        NON_KEYWORD_SUBS.iter().map(|(name, _)| *name)
+    }
+}
+
+fn find_by_name(subs: &[(&str, BuiltInSub)], s: &str) -> Option<BuiltInSub> {
+    subs.iter()
+        .find(|(name, _)| s.eq_ignore_ascii_case(name))
+        .map(|(_, built_in_sub)| *built_in_sub)
 }
@@ -0,0 +1,34 @@
+use rusty_pc::*;
+
+use crate::expr::ws_expr_pos_p;
+use crate::input::StringView;
+use crate::pc_specific::*;
+use crate::{BuiltInSub, ParserError, *};
+
+// ERROR integer-expression%
+pub fn parse() -> impl Parser<StringView, Output = Statement, Error = ParserError> {
+    keyword(Keyword::Error)
+        .and_keep_right(ws_expr_pos_p().or_expected("expression"))
+        .map(|error_code| Statement::built_in_sub_call(BuiltInSub::Error, vec![error_code]))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{DemandSingleStatement, ExpressionLiteralFactory};
+    use crate::{BuiltInSub, assert_parser_err, parse, *};
+
+    #[test]
+    fn parse_error() {
+        let input = "ERROR 5";
+        let statement = parse(input).demand_single_statement();
+        assert_eq!(
+            statement,
+            Statement::built_in_sub_call(BuiltInSub::Error, vec![5.as_lit_expr(1, 7)])
+        );
+    }
+
+    #[test]
+    fn parse_error_requires_code() {
+        assert_parser_err!("ERROR", expected("expression"));
+    }
+}
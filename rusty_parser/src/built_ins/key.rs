@@ -0,0 +1,62 @@
+use rusty_pc::*;
+
+use crate::expr::csv_expressions_first_guarded;
+use crate::input::StringView;
+use crate::pc_specific::*;
+use crate::{BuiltInSub, ParserError, *};
+
+// KEY n%, string-expression$
+// KEY LIST
+//
+// KEY(n%) ON|OFF|STOP is parsed as an event control statement.
+pub fn parse() -> impl Parser<StringView, Output = Statement, Error = ParserError> {
+    keyword(Keyword::Key)
+        .and_keep_right(
+            lead_ws(unreserved_keyword("LIST"))
+                .map(|_| vec![])
+                .or(csv_expressions_first_guarded())
+                .or_expected("LIST or expression"),
+        )
+        .map(|args| Statement::built_in_sub_call(BuiltInSub::Key, args))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{DemandSingleStatement, ExpressionLiteralFactory};
+    use crate::{BuiltInSub, assert_parser_err, parse, *};
+
+    #[test]
+    fn parse_key_list() {
+        let input = "KEY LIST";
+        let statement = parse(input).demand_single_statement();
+        assert_eq!(
+            statement,
+            Statement::built_in_sub_call(BuiltInSub::Key, vec![])
+        );
+    }
+
+    #[test]
+    fn parse_soft_key_definition() {
+        let input = r#"KEY 1, "LIST""#;
+        let statement = parse(input).demand_single_statement();
+        assert_eq!(
+            statement,
+            Statement::built_in_sub_call(
+                BuiltInSub::Key,
+                vec![1.as_lit_expr(1, 5), "LIST".as_lit_expr(1, 8)]
+            )
+        );
+    }
+
+    #[test]
+    fn parse_event_control_is_not_key_statement() {
+        let input = "KEY(1) ON";
+        let statement = parse(input).demand_single_statement();
+        assert!(matches!(statement, Statement::EventControl(_)));
+    }
+
+    #[test]
+    fn key_without_args() {
+        assert_parser_err!("KEY", expected("LIST or expression"));
+    }
+}
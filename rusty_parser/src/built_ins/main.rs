@@ -10,9 +10,11 @@ pub fn built_in_sub_call_p() -> OrParser<StringView, (), Statement, ParserError>
         Box::new(super::color::parse()),
         Box::new(super::data::parse()),
         Box::new(super::def_seg::parse()),
+        Box::new(super::error::parse()),
         Box::new(super::field::parse()),
         Box::new(super::get::parse()),
         Box::new(super::input::parse()),
+        Box::new(super::key::parse()),
         Box::new(super::line_input::parse()),
         Box::new(super::locate::parse()),
         Box::new(super::lset::parse()),
@@ -6,9 +6,11 @@ mod color;
 mod common;
 mod data;
 mod def_seg;
+mod error;
 mod field;
 mod get;
 mod input;
+mod key;
 mod len;
 mod line_input;
 mod locate;
@@ -22,6 +24,6 @@ mod string_fn;
 mod view_print;
 mod width;
 
-pub use self::built_in_function::BuiltInFunction;
+pub use self::built_in_function::{BuiltInFunction, SORTED_BUILT_IN_FUNCTIONS};
 pub use self::built_in_sub::BuiltInSub;
 pub use self::main::{built_in_function_call_p, built_in_sub_call_p};
@@ -2,13 +2,31 @@ use rusty_common::*;
 use rusty_pc::*;
 
 use crate::built_ins::common::csv_allow_missing;
+use crate::expr::ws_expr_pos_p;
 use crate::input::StringView;
 use crate::pc_specific::*;
 use crate::{BuiltInSub, ParserError, *};
+
+// WIDTH [columns%] [,rows%]
+// WIDTH LPRINT columns%
 pub fn parse() -> impl Parser<StringView, Output = Statement, Error = ParserError> {
     keyword_ws_p(Keyword::Width)
-        .and_keep_right(csv_allow_missing())
-        .map(|opt_args| Statement::built_in_sub_call(BuiltInSub::Width, map_args(opt_args)))
+        .and_keep_right(lprint_width_p().or(csv_allow_missing().map(map_args)))
+        .map(|args| Statement::built_in_sub_call(BuiltInSub::Width, args))
+}
+
+/// Parses the `LPRINT columns%` part.
+/// Encoded with a leading flag of 2, to distinguish it from the screen form,
+/// where the flags are 0 (absent) or 1 (present).
+fn lprint_width_p() -> impl Parser<StringView, Output = Expressions, Error = ParserError> {
+    keyword(Keyword::LPrint)
+        .and_keep_right(ws_expr_pos_p().or_expected("expression"))
+        .map(|columns| {
+            vec![
+                Expression::IntegerLiteral(2).at_pos(Position::start()),
+                columns,
+            ]
+        })
 }
 
 fn map_args(args: Vec<Option<ExpressionPos>>) -> Expressions {
@@ -62,6 +80,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_lprint() {
+        let input = "WIDTH LPRINT 40";
+        let statement = parse(input).demand_single_statement();
+        assert_eq!(
+            statement,
+            Statement::built_in_sub_call(
+                BuiltInSub::Width,
+                vec![
+                    2.as_lit_expr(1, 1),   // LPRINT
+                    40.as_lit_expr(1, 14)  // columns
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn lprint_requires_columns() {
+        assert_parser_err!("WIDTH LPRINT", expected("expression"));
+    }
+
     #[test]
     fn cannot_have_trailing_comma() {
         assert_parser_err!(
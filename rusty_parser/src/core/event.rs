@@ -0,0 +1,207 @@
+use rusty_pc::*;
+
+use crate::core::name::bare_name_p;
+use crate::expr::expression_pos_p;
+use crate::input::StringView;
+use crate::pc_specific::*;
+use crate::*;
+
+// ON KEY(n) GOSUB label
+// ON PLAY(n) GOSUB label
+// ON TIMER(n) GOSUB label
+//
+// KEY(n) ON|OFF|STOP
+// PLAY ON|OFF|STOP
+// TIMER ON|OFF|STOP
+
+/// Parses the part after `ON` of an event trapping statement,
+/// e.g. `TIMER(1) GOSUB Tick`.
+pub fn on_event_go_sub_p() -> impl Parser<StringView, Output = Statement, Error = ParserError> {
+    seq3(
+        event_kind_p(),
+        event_arg_p(),
+        lead_opt_ws(keyword_ws_p(Keyword::GoSub))
+            .and_keep_right(bare_name_p().or_expected("label")),
+        |kind, arg, label| Statement::OnEvent(OnEvent { kind, arg, label }),
+    )
+}
+
+/// Parses `KEY(n) ON|OFF|STOP`, `PLAY ON|OFF|STOP` and `TIMER ON|OFF|STOP`.
+pub fn statement_event_control_p()
+-> impl Parser<StringView, Output = Statement, Error = ParserError> {
+    OrParser::new(vec![
+        Box::new(key_event_control_p()),
+        Box::new(event_control_without_arg_p(Keyword::Play, EventKind::Play)),
+        Box::new(event_control_without_arg_p(
+            Keyword::Timer,
+            EventKind::Timer,
+        )),
+    ])
+}
+
+fn key_event_control_p() -> impl Parser<StringView, Output = Statement, Error = ParserError> {
+    seq2(
+        // without the parenthesis, it is the KEY statement (e.g. `KEY LIST`)
+        keyword(Keyword::Key).and_keep_right(lead_opt_ws(in_parenthesis(
+            expression_pos_p().or_expected("expression inside parenthesis"),
+        ))),
+        lead_opt_ws(event_action_p()),
+        |arg, action| {
+            Statement::EventControl(EventControl {
+                kind: EventKind::Key,
+                arg: Some(arg),
+                action,
+            })
+        },
+    )
+}
+
+fn event_control_without_arg_p(
+    k: Keyword,
+    kind: EventKind,
+) -> impl Parser<StringView, Output = Statement, Error = ParserError> {
+    seq2(keyword_ws_p(k), event_action_p(), move |_, action| {
+        Statement::EventControl(EventControl {
+            kind,
+            arg: None,
+            action,
+        })
+    })
+}
+
+fn event_kind_p() -> impl Parser<StringView, Output = EventKind, Error = ParserError> {
+    keyword_map(&[
+        (Keyword::Key, EventKind::Key),
+        (Keyword::Play, EventKind::Play),
+        (Keyword::Timer, EventKind::Timer),
+    ])
+}
+
+fn event_arg_p() -> impl Parser<StringView, Output = ExpressionPos, Error = ParserError> {
+    lead_opt_ws(in_parenthesis(
+        expression_pos_p().or_expected("expression inside parenthesis"),
+    ))
+    .or_expected("(")
+}
+
+fn event_action_p() -> impl Parser<StringView, Output = EventAction, Error = ParserError> {
+    keyword_map(&[
+        (Keyword::Off, EventAction::Off),
+        (Keyword::On, EventAction::On),
+    ])
+    .or(unreserved_keyword("STOP").map(|_| EventAction::Stop))
+    .or_expected("OFF or ON or STOP")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{DemandSingleStatement, ExpressionLiteralFactory};
+    use crate::{assert_parser_err, parse, *};
+
+    #[test]
+    fn on_timer_go_sub() {
+        let input = "ON TIMER(1) GOSUB Tick";
+        let statement = parse(input).demand_single_statement();
+        assert_eq!(
+            statement,
+            Statement::OnEvent(OnEvent {
+                kind: EventKind::Timer,
+                arg: 1.as_lit_expr(1, 10),
+                label: "Tick".into()
+            })
+        );
+    }
+
+    #[test]
+    fn on_key_go_sub_with_spaces() {
+        let input = "ON KEY ( 11 ) GOSUB UpArrow";
+        let statement = parse(input).demand_single_statement();
+        assert_eq!(
+            statement,
+            Statement::OnEvent(OnEvent {
+                kind: EventKind::Key,
+                arg: 11.as_lit_expr(1, 10),
+                label: "UpArrow".into()
+            })
+        );
+    }
+
+    #[test]
+    fn on_play_go_sub() {
+        let input = "ON PLAY(3) GOSUB MoreMusic";
+        let statement = parse(input).demand_single_statement();
+        assert_eq!(
+            statement,
+            Statement::OnEvent(OnEvent {
+                kind: EventKind::Play,
+                arg: 3.as_lit_expr(1, 9),
+                label: "MoreMusic".into()
+            })
+        );
+    }
+
+    #[test]
+    fn on_timer_without_interval() {
+        assert_parser_err!("ON TIMER GOSUB Tick", expected("("));
+    }
+
+    #[test]
+    fn on_timer_without_go_sub() {
+        assert_parser_err!("ON TIMER(1) GOTO Tick", expected("GOSUB"));
+    }
+
+    #[test]
+    fn on_timer_go_sub_without_label() {
+        assert_parser_err!("ON TIMER(1) GOSUB ", expected("label"));
+    }
+
+    #[test]
+    fn timer_on_off_stop() {
+        for (input, action) in [
+            ("TIMER ON", EventAction::On),
+            ("TIMER OFF", EventAction::Off),
+            ("TIMER STOP", EventAction::Stop),
+        ] {
+            let statement = parse(input).demand_single_statement();
+            assert_eq!(
+                statement,
+                Statement::EventControl(EventControl {
+                    kind: EventKind::Timer,
+                    arg: None,
+                    action
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn play_on() {
+        let statement = parse("PLAY ON").demand_single_statement();
+        assert_eq!(
+            statement,
+            Statement::EventControl(EventControl {
+                kind: EventKind::Play,
+                arg: None,
+                action: EventAction::On
+            })
+        );
+    }
+
+    #[test]
+    fn key_stop() {
+        let statement = parse("KEY(1) STOP").demand_single_statement();
+        assert_eq!(
+            statement,
+            Statement::EventControl(EventControl {
+                kind: EventKind::Key,
+                arg: Some(1.as_lit_expr(1, 5)),
+                action: EventAction::Stop
+            })
+        );
+    }
+
+    #[test]
+    fn timer_without_action() {
+        assert_parser_err!("TIMER GOSUB", expected("OFF or ON or STOP"));
+    }
+}
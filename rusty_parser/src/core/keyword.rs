@@ -23,7 +23,7 @@ macro_rules! keyword_enum {
         }
 
         /// Stores all keywords.
-        const $all_names : &[$name] = &[
+        $vis const $all_names : &[$name] = &[
             $($name::$member),+
         ];
 
@@ -126,6 +126,8 @@ keyword_enum!(pub enum Keyword SORTED_KEYWORDS SORTED_KEYWORDS_STR {
     Integer,
     /// IS
     Is,
+    /// KEY
+    Key,
     /// LEN
     Len,
     /// LINE
@@ -148,6 +150,8 @@ keyword_enum!(pub enum Keyword SORTED_KEYWORDS SORTED_KEYWORDS_STR {
     Next,
     /// NOT
     Not,
+    /// OFF
+    Off,
     /// ON
     On,
     /// OPEN
@@ -156,6 +160,8 @@ keyword_enum!(pub enum Keyword SORTED_KEYWORDS SORTED_KEYWORDS_STR {
     Or,
     /// OUTPUT
     Output,
+    /// PLAY
+    Play,
     /// PRINT
     Print,
     /// PUT
@@ -190,6 +196,8 @@ keyword_enum!(pub enum Keyword SORTED_KEYWORDS SORTED_KEYWORDS_STR {
     System,
     /// THEN
     Then,
+    /// TIMER
+    Timer,
     /// TO
     To,
     /// TYPE
@@ -10,9 +10,9 @@ mod dim;
 mod dim_name;
 mod dim_type;
 mod do_loop;
+mod event;
 mod exit;
 mod expression_type;
-mod file_constants;
 mod for_loop;
 mod global_statement;
 mod go_sub;
@@ -33,7 +33,6 @@ mod statement;
 mod statement_separator;
 mod statements;
 mod sub_call;
-mod type_qualifier;
 mod unary_operator;
 mod user_defined_type;
 mod var_name;
@@ -48,12 +47,11 @@ pub use self::def_type::DefType;
 pub use self::dim_name::{DimNameBuilder, DimVar, DimVarPos, DimVars};
 pub use self::dim_type::*;
 pub use self::expression_type::{ExpressionType, HasExpressionType};
-pub use self::file_constants::*;
 pub use self::global_statement::{
     FunctionDeclaration, FunctionImplementation, GlobalStatement, GlobalStatementPos, Program,
     SubDeclaration, SubImplementation, SubprogramImplementation, program_parser_p,
 };
-pub use self::keyword::Keyword;
+pub use self::keyword::{Keyword, SORTED_KEYWORDS};
 pub use self::letter_range::LetterRange;
 pub use self::name::{
     Name, NameAsTokens, NamePos, name_as_tokens_p, name_p, token_to_type_qualifier,
@@ -63,12 +61,19 @@ pub use self::param_name::{ParamType, Parameter, ParameterPos, Parameters};
 pub use self::print::{Print, PrintArg};
 pub use self::statement::{
     Assignment, BuiltInSubCall, CaseBlock, CaseExpression, ConditionalBlock, Constant, DimList,
-    DoLoop, DoLoopConditionKind, DoLoopConditionPosition, ExitObject, ForLoop, IfBlock,
-    OnErrorOption, ResumeOption, SelectCase, Statement, StatementPos, Statements, SubCall,
+    DoLoop, DoLoopConditionKind, DoLoopConditionPosition, EventAction, EventControl, EventKind,
+    ExitObject, ForLoop, IfBlock, OnErrorOption, OnEvent, ResumeOption, SelectCase, Statement,
+    StatementPos, Statements, SubCall,
 };
-pub use self::type_qualifier::TypeQualifier;
 pub use self::unary_operator::UnaryOperator;
 pub use self::user_defined_type::{
     Element, ElementPos, ElementType, UserDefinedType, UserDefinedTypes,
 };
 pub use self::var_name::*;
+
+// the types that the runtime shares with the parser
+pub use rusty_variant::{
+    FILE_ACCESS_READ, FILE_ACCESS_UNSPECIFIED, FILE_ACCESS_WRITE, FILE_MODE_APPEND,
+    FILE_MODE_INPUT, FILE_MODE_OUTPUT, FILE_MODE_RANDOM, FileAccess, FileHandle, FileMode,
+    TypeQualifier,
+};
@@ -383,7 +383,7 @@ mod parse_tests {
         fn cannot_exceed_max_length() {
             let input = "ABCDEFGHIJKLMNOPQRSTUVWXYZABCDEFGHIJKLMNO".to_owned();
             assert_eq!(input.len(), 41);
-            let mut reader = create_string_tokenizer(String::from(input.clone()));
+            let mut reader = create_string_tokenizer(input.clone());
             let result = bare_name_p().parse(&mut reader);
             assert_err(&input, result, ParserError::IdentifierTooLong);
         }
@@ -410,7 +410,7 @@ mod parse_tests {
         fn cannot_exceed_max_length() {
             let input = "ABCDEFGHIJKLMNOPQRSTUVWXYZABCDEFGHIJKLMNO".to_owned();
             assert_eq!(input.len(), 41);
-            let mut reader = create_string_tokenizer(String::from(input.clone()));
+            let mut reader = create_string_tokenizer(input.clone());
             let result = identifier().parse(&mut reader);
             assert_err(&input, result, ParserError::IdentifierTooLong);
         }
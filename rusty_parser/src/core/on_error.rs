@@ -1,6 +1,7 @@
 use rusty_common::Positioned;
 use rusty_pc::*;
 
+use crate::core::event::on_event_go_sub_p;
 use crate::core::name::bare_name_p;
 use crate::error::ParserError;
 use crate::expr::expression_pos_p;
@@ -8,9 +9,19 @@ use crate::input::StringView;
 use crate::pc_specific::*;
 use crate::{Expression, Keyword, OnErrorOption, Statement};
 
-pub fn statement_on_error_go_to_p()
--> impl Parser<StringView, Output = Statement, Error = ParserError> {
-    keyword_pair(Keyword::On, Keyword::Error)
+/// Parses statements starting with `ON`,
+/// i.e. `ON ERROR` and event trapping (e.g. `ON TIMER(1) GOSUB label`).
+pub fn statement_on_p() -> impl Parser<StringView, Output = Statement, Error = ParserError> {
+    keyword_ws_p(Keyword::On).and_keep_right(
+        statement_on_error_go_to_p()
+            .or(on_event_go_sub_p())
+            .or_expected("ERROR or KEY or PLAY or TIMER"),
+    )
+}
+
+fn statement_on_error_go_to_p() -> impl Parser<StringView, Output = Statement, Error = ParserError>
+{
+    keyword_ignoring(Keyword::Error)
         .and_keep_right(demand_lead_ws(
             next().or(goto()).or_expected("GOTO or RESUME"),
         ))
@@ -37,3 +48,18 @@ fn goto_zero() -> impl Parser<StringView, Output = OnErrorOption, Error = Parser
         _ => Err(ParserError::expected("label or 0").to_fatal()),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::assert_parser_err;
+
+    #[test]
+    fn on_without_error_or_event() {
+        assert_parser_err!("ON X GOTO A", expected("ERROR or KEY or PLAY or TIMER"));
+    }
+
+    #[test]
+    fn on_error_without_goto_or_resume() {
+        assert_parser_err!("ON ERROR GOSUB A", expected("GOTO or RESUME"));
+    }
+}
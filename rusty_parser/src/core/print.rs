@@ -249,7 +249,7 @@ mod tests {
             match statement {
                 Statement::Print(print) => {
                     assert_eq!(print.file_number, None);
-                    assert_eq!(print.lpt1, false);
+                    assert!(!print.lpt1);
                     assert_eq!(print.format_string, None);
                     assert_eq!(print.args[0], PrintArg::Comma);
                     match print.args[1] {
@@ -274,7 +274,7 @@ mod tests {
             match statement {
                 Statement::Print(print) => {
                     assert_eq!(print.file_number, None);
-                    assert_eq!(print.lpt1, false);
+                    assert!(!print.lpt1);
                     assert_eq!(print.format_string, None);
                     assert_eq!(print.args[0], PrintArg::Semicolon);
                     match print.args[1] {
@@ -563,6 +563,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_print_file_using_one_arg() {
         let input = "PRINT #1, USING \"#\"; 3.14";
         let statement = parse(input).demand_single_statement();
@@ -6,13 +6,14 @@ use crate::core::comment::comment_p;
 use crate::core::constant::constant_p;
 use crate::core::dim::{dim_p, redim_p};
 use crate::core::do_loop::do_loop_p;
+use crate::core::event::statement_event_control_p;
 use crate::core::exit::statement_exit_p;
 use crate::core::for_loop::for_loop_p;
 use crate::core::go_sub::{statement_go_sub_p, statement_return_p};
 use crate::core::if_block::if_block_p;
 use crate::core::macros::bi_tuple;
 use crate::core::name::{bare_name_p, identifier};
-use crate::core::on_error::statement_on_error_go_to_p;
+use crate::core::on_error::statement_on_p;
 use crate::core::print::{parse_lprint_p, parse_print_p};
 use crate::core::resume::statement_resume_p;
 use crate::core::select_case::select_case_p;
@@ -130,9 +131,22 @@ pub enum Statement {
 
     Exit(ExitObject),
 
+    /*
+     * Event trapping
+     */
+    /// Sets the handler of an event, e.g. `ON TIMER(1) GOSUB Tick`.
+    OnEvent(OnEvent),
+
+    /// Enables, disables or suspends event trapping, e.g. `TIMER ON`, `KEY(1) STOP`.
+    EventControl(EventControl),
+
     End,
     System,
 
+    /// Pauses the program when running under a debugger,
+    /// otherwise ends it like `END`.
+    Stop,
+
     /*
      * Special statements
      */
@@ -190,6 +204,48 @@ pub enum OnErrorOption {
     Zero,
 }
 
+/// The source of a trappable event.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum EventKind {
+    /// A key press (`ON KEY(n)`).
+    Key,
+    /// The background music queue (`ON PLAY(n)`).
+    Play,
+    /// A recurring timer (`ON TIMER(n)`).
+    Timer,
+}
+
+/// `ON KEY(n) GOSUB label`, `ON PLAY(n) GOSUB label`, `ON TIMER(n) GOSUB label`
+#[derive(Clone, Debug, PartialEq)]
+pub struct OnEvent {
+    pub kind: EventKind,
+    /// The key number for `KEY`, the note threshold for `PLAY`,
+    /// the interval in seconds for `TIMER`.
+    pub arg: ExpressionPos,
+    /// The label of the handler. It is called like a `GOSUB`.
+    pub label: CaseInsensitiveString,
+}
+
+/// `KEY(n) ON|OFF|STOP`, `PLAY ON|OFF|STOP`, `TIMER ON|OFF|STOP`
+#[derive(Clone, Debug, PartialEq)]
+pub struct EventControl {
+    pub kind: EventKind,
+    /// The key number, only present for `KEY`.
+    pub arg: Option<ExpressionPos>,
+    pub action: EventAction,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EventAction {
+    /// Enables trapping of the event.
+    On,
+    /// Disables trapping of the event. Events that occur are lost.
+    Off,
+    /// Suspends trapping of the event. An event that occurs is remembered
+    /// and it is trapped as soon as trapping is enabled again.
+    Stop,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct ForLoop {
     pub variable_name: ExpressionPos,
@@ -312,15 +368,19 @@ pub fn single_line_non_comment_statement_p() -> OrParser<StringView, (), Stateme
         Box::new(dim_p()),
         Box::new(redim_p()),
         Box::new(constant_p()),
+        // before built-in subs, to tell apart `KEY(1) ON` from `KEY 1, "text"`
+        Box::new(statement_event_control_p()),
         Box::new(built_in_sub_call_p()),
         Box::new(parse_print_p()),
         Box::new(parse_lprint_p()),
+        // before sub calls, as `STOP` is not a reserved word
+        Box::new(stop::parse_stop_p()),
         Box::new(sub_call_or_assignment_p()),
         Box::new(statement_go_to_p()),
         Box::new(statement_go_sub_p()),
         Box::new(statement_return_p()),
         Box::new(statement_exit_p()),
-        Box::new(statement_on_error_go_to_p()),
+        Box::new(statement_on_p()),
         Box::new(statement_resume_p()),
         Box::new(end::parse_end_p()),
         Box::new(system::parse_system_p()),
@@ -413,6 +473,42 @@ mod system {
     }
 }
 
+mod stop {
+    use rusty_pc::*;
+
+    use crate::core::statement_separator::peek_eof_or_statement_separator;
+    use crate::input::StringView;
+    use crate::pc_specific::*;
+    use crate::{ParserError, Statement};
+
+    /// `STOP` is not a reserved word, so that existing programs
+    /// can keep using it as a variable name (e.g. `stop%`).
+    pub fn parse_stop_p() -> impl Parser<StringView, Output = Statement, Error = ParserError> {
+        unreserved_keyword("STOP").and(lead_opt_ws(peek_eof_or_statement_separator()), |_, _| {
+            Statement::Stop
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::test_utils::{DemandSingleStatement, ExpressionLiteralFactory};
+        use crate::{Expression, Statement, parse};
+
+        #[test]
+        fn test_stop() {
+            assert_eq!(parse("STOP").demand_single_statement(), Statement::Stop);
+        }
+
+        #[test]
+        fn test_stop_can_be_a_variable() {
+            assert_eq!(
+                parse("STOP = 42").demand_single_statement(),
+                Statement::assignment(Expression::var_unresolved("STOP"), 42.as_lit_expr(1, 8))
+            );
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use rusty_common::*;
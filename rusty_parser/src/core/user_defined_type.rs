@@ -42,6 +42,10 @@ impl UserDefinedType {
         &self.name
     }
 
+    pub fn comments(&self) -> Iter<'_, Positioned<String>> {
+        self.comments.iter()
+    }
+
     pub fn elements(&self) -> Iter<'_, ElementPos> {
         self.elements.iter()
     }
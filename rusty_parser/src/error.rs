@@ -112,3 +112,27 @@ impl From<&str> for ParserError {
         Self::Expected(format!("Expected: {}", e))
     }
 }
+
+impl std::fmt::Display for ParserError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Miss => f.write_str("Syntax error"),
+            Self::Expected(msg) | Self::SyntaxError(msg) => write!(f, "Syntax error. {}", msg),
+            Self::NextWithoutFor => f.write_str("NEXT without FOR"),
+            Self::Overflow => f.write_str("Overflow"),
+            Self::ForWithoutNext => f.write_str("FOR without NEXT"),
+            Self::WhileWithoutWend => f.write_str("WHILE without WEND"),
+            Self::WendWithoutWhile => f.write_str("WEND without WHILE"),
+            Self::ParseNumError(msg) => write!(f, "Invalid number. {}", msg),
+            Self::BadFileNameOrNumber => f.write_str("Bad file name or number"),
+            Self::FileNotFound => f.write_str("File not found"),
+            Self::DeviceIOError(msg) => write!(f, "Device I/O error. {}", msg),
+            Self::InputPastEndOfFile => f.write_str("Input past end of file"),
+            Self::ElseWithoutIf => f.write_str("ELSE without IF"),
+            Self::IdentifierCannotIncludePeriod => f.write_str("Identifier cannot include period"),
+            Self::IdentifierTooLong => f.write_str("Identifier too long"),
+            Self::ElementNotDefined => f.write_str("Element not defined"),
+            Self::LoopWithoutDo => f.write_str("LOOP without DO"),
+        }
+    }
+}
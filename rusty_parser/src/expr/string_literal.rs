@@ -34,6 +34,7 @@ mod tests {
     use crate::{assert_literal_expression, *};
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_parse_literals() {
         assert_literal_expression!(r#""hello, world""#, "hello, world");
         assert_literal_expression!(r#""hello 123 . AS""#, "hello 123 . AS");
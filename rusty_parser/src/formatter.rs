@@ -0,0 +1,1235 @@
+//! Regenerates canonical QBasic source from a parsed [Program].
+//!
+//! Keywords are written in uppercase (as the QBasic editor does), block
+//! statements are indented by four spaces and binary operators are
+//! surrounded by a single space. Comments are kept, either on their own
+//! line or after the statement they followed. Blank lines between
+//! statements are kept, collapsed to a single one.
+//!
+//! The parser does not keep the position of every keyword (e.g. `END IF`),
+//! so the rows of the closing keywords are estimated. The estimation is
+//! exact for formatted code, which makes formatting idempotent.
+
+use rusty_common::Positioned;
+
+use crate::*;
+
+const INDENT: &str = "    ";
+
+/// Formats the given (parsed, not linted) program.
+pub fn format_program(program: &Program) -> String {
+    let mut formatter = Formatter::default();
+    let mut previous_end_row: Option<u32> = None;
+    for Positioned { element, pos } in program {
+        let row = pos.row();
+        if let GlobalStatement::Statement(Statement::Comment(comment)) = element
+            && previous_end_row == Some(row)
+        {
+            formatter.append_comment(comment);
+            continue;
+        }
+        if previous_end_row.is_some_and(|end_row| row > end_row + 1) {
+            formatter.blank_line();
+        }
+        formatter.global_statement(element, row);
+        previous_end_row = Some(global_statement_end_row(element, row));
+    }
+    formatter.output
+}
+
+#[derive(Default)]
+struct Formatter {
+    output: String,
+    indent: usize,
+}
+
+impl Formatter {
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.indent {
+            self.output.push_str(INDENT);
+        }
+        self.output.push_str(text);
+        self.output.push('\n');
+    }
+
+    fn blank_line(&mut self) {
+        if !self.output.is_empty() && !self.output.ends_with("\n\n") {
+            self.output.push('\n');
+        }
+    }
+
+    /// Appends a comment to the last line.
+    fn append_comment(&mut self, comment: &str) {
+        self.output.pop();
+        self.output.push_str(" '");
+        self.output.push_str(comment);
+        self.output.push('\n');
+    }
+
+    fn comment_line(&mut self, comment: &str) {
+        self.line(&format!("'{}", comment));
+    }
+
+    /// Formats the given comments, appending to the last line
+    /// the one that is on the given row.
+    fn comments(&mut self, comments: &[Positioned<String>], row: u32) {
+        for Positioned { element, pos } in comments {
+            if pos.row() == row {
+                self.append_comment(element);
+            } else {
+                self.comment_line(element);
+            }
+        }
+    }
+
+    fn indented<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        self.indent += 1;
+        f(self);
+        self.indent -= 1;
+    }
+
+    fn global_statement(&mut self, global_statement: &GlobalStatement, row: u32) {
+        match global_statement {
+            GlobalStatement::DefType(def_type) => self.line(&def_type_to_string(def_type)),
+            GlobalStatement::FunctionDeclaration(f) => self.line(&format!(
+                "DECLARE FUNCTION {} ({})",
+                f.name.element,
+                parameters_to_string(&f.parameters)
+            )),
+            GlobalStatement::FunctionImplementation(f) => {
+                self.implementation("FUNCTION", &f.name.element.to_string(), f, row)
+            }
+            GlobalStatement::Statement(statement) => self.statement(statement, row),
+            GlobalStatement::SubDeclaration(s) => self.line(&format!(
+                "DECLARE SUB {} ({})",
+                s.name.element,
+                parameters_to_string(&s.parameters)
+            )),
+            GlobalStatement::SubImplementation(s) => {
+                self.implementation("SUB", &s.name.element.to_string(), s, row)
+            }
+            GlobalStatement::UserDefinedType(user_defined_type) => {
+                self.user_defined_type(user_defined_type, row)
+            }
+        }
+    }
+
+    fn implementation<T>(
+        &mut self,
+        keyword: &str,
+        name: &str,
+        implementation: &SubprogramImplementation<T>,
+        row: u32,
+    ) {
+        let mut header = format!("{} {}", keyword, name);
+        if !implementation.params.is_empty() {
+            header.push_str(&format!(
+                " ({})",
+                parameters_to_string(&implementation.params)
+            ));
+        }
+        if implementation.is_static {
+            header.push_str(" STATIC");
+        }
+        self.line(&header);
+        self.indented(|f| f.statements(&implementation.body, row));
+        self.line(&format!("END {}", keyword));
+    }
+
+    fn user_defined_type(&mut self, user_defined_type: &UserDefinedType, row: u32) {
+        self.line(&format!("TYPE {}", user_defined_type.bare_name()));
+        self.indented(|f| {
+            let comments: Vec<Positioned<String>> = user_defined_type.comments().cloned().collect();
+            f.comments(&comments, row);
+            for Positioned { element, pos } in user_defined_type.elements() {
+                f.line(&format!(
+                    "{} AS {}",
+                    element.name,
+                    element_type_to_string(&element.element_type)
+                ));
+                f.comments(&element.comments, pos.row());
+            }
+        });
+        self.line("END TYPE");
+    }
+
+    /// Formats the statements of a block, whose first line
+    /// (e.g. `FOR I = 1 TO 10`) is on the given row.
+    fn statements(&mut self, statements: &Statements, header_row: u32) {
+        let mut previous_end_row = header_row;
+        for Positioned { element, pos } in statements {
+            let row = pos.row();
+            if let Statement::Comment(comment) = element
+                && row == previous_end_row
+            {
+                self.append_comment(comment);
+                continue;
+            }
+            if row > previous_end_row + 1 {
+                self.blank_line();
+            }
+            self.statement(element, row);
+            previous_end_row = statement_end_row(element, row);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement, row: u32) {
+        match statement {
+            Statement::Comment(comment) => self.comment_line(comment),
+            Statement::Label(label) => {
+                // labels are not indented
+                let indent = std::mem::take(&mut self.indent);
+                self.line(&format!("{}:", label));
+                self.indent = indent;
+            }
+            Statement::IfBlock(if_block) if !is_single_line_if(if_block) => self.if_block(if_block),
+            Statement::SelectCase(select_case) => self.select_case(select_case, row),
+            Statement::ForLoop(for_loop) => {
+                let mut header = format!(
+                    "FOR {} = {} TO {}",
+                    expression_to_string(&for_loop.variable_name.element),
+                    expression_to_string(&for_loop.lower_bound.element),
+                    expression_to_string(&for_loop.upper_bound.element)
+                );
+                if let Some(step) = &for_loop.step {
+                    header.push_str(&format!(" STEP {}", expression_to_string(&step.element)));
+                }
+                self.line(&header);
+                self.indented(|f| f.statements(&for_loop.statements, row));
+                match &for_loop.next_counter {
+                    Some(next_counter) => self.line(&format!(
+                        "NEXT {}",
+                        expression_to_string(&next_counter.element)
+                    )),
+                    None => self.line("NEXT"),
+                }
+            }
+            Statement::While(conditional_block) => {
+                self.line(&format!(
+                    "WHILE {}",
+                    expression_to_string(&conditional_block.condition.element)
+                ));
+                self.indented(|f| f.statements(&conditional_block.statements, row));
+                self.line("WEND");
+            }
+            Statement::DoLoop(do_loop) => {
+                let condition = format!(
+                    "{} {}",
+                    match do_loop.kind {
+                        DoLoopConditionKind::Until => "UNTIL",
+                        DoLoopConditionKind::While => "WHILE",
+                    },
+                    expression_to_string(&do_loop.condition.element)
+                );
+                match do_loop.position {
+                    DoLoopConditionPosition::Top => {
+                        self.line(&format!("DO {}", condition));
+                        self.indented(|f| f.statements(&do_loop.statements, row));
+                        self.line("LOOP");
+                    }
+                    DoLoopConditionPosition::Bottom => {
+                        self.line("DO");
+                        self.indented(|f| f.statements(&do_loop.statements, row));
+                        self.line(&format!("LOOP {}", condition));
+                    }
+                }
+            }
+            _ => self.line(&statement_to_string(statement)),
+        }
+    }
+
+    fn if_block(&mut self, if_block: &IfBlock) {
+        let ConditionalBlock {
+            condition,
+            statements,
+        } = &if_block.if_block;
+        self.line(&format!(
+            "IF {} THEN",
+            expression_to_string(&condition.element)
+        ));
+        self.indented(|f| f.statements(statements, condition.pos.row()));
+        let mut end_row = statements_end_row(statements, condition.pos.row());
+        for ConditionalBlock {
+            condition,
+            statements,
+        } in &if_block.else_if_blocks
+        {
+            self.line(&format!(
+                "ELSEIF {} THEN",
+                expression_to_string(&condition.element)
+            ));
+            self.indented(|f| f.statements(statements, condition.pos.row()));
+            end_row = statements_end_row(statements, condition.pos.row());
+        }
+        if let Some(else_block) = &if_block.else_block {
+            self.line("ELSE");
+            self.indented(|f| f.statements(else_block, end_row + 1));
+        }
+        self.line("END IF");
+    }
+
+    fn select_case(&mut self, select_case: &SelectCase, row: u32) {
+        self.line(&format!(
+            "SELECT CASE {}",
+            expression_to_string(&select_case.expr.element)
+        ));
+        let mut end_row = row;
+        self.indented(|f| {
+            f.comments(&select_case.inline_comments, row);
+            if let Some(last) = select_case.inline_comments.last() {
+                end_row = end_row.max(last.pos.row());
+            }
+            for case_block in &select_case.case_blocks {
+                let case_row = case_block
+                    .conditions()
+                    .first()
+                    .map(case_expression_row)
+                    .unwrap_or(end_row + 1);
+                let conditions: Vec<String> = case_block
+                    .conditions()
+                    .iter()
+                    .map(case_expression_to_string)
+                    .collect();
+                f.line(&format!("CASE {}", conditions.join(", ")));
+                f.indented(|f| f.statements(case_block.statements(), case_row));
+                end_row = statements_end_row(case_block.statements(), case_row);
+            }
+            if let Some(else_block) = &select_case.else_block {
+                f.line("CASE ELSE");
+                f.indented(|f| f.statements(else_block, end_row + 1));
+            }
+        });
+        self.line("END SELECT");
+    }
+}
+
+/// Checks if the IF block was written in a single line,
+/// e.g. `IF A THEN PRINT A ELSE PRINT B`.
+fn is_single_line_if(if_block: &IfBlock) -> bool {
+    let ConditionalBlock {
+        condition,
+        statements,
+    } = &if_block.if_block;
+    if_block.else_if_blocks.is_empty()
+        && statements.first().is_some_and(|first| {
+            !matches!(first.element, Statement::Comment(_))
+                && first.pos.row() == condition.pos.row()
+        })
+}
+
+/// Estimates the last row of the given global statement.
+fn global_statement_end_row(global_statement: &GlobalStatement, row: u32) -> u32 {
+    match global_statement {
+        GlobalStatement::FunctionImplementation(f) => statements_end_row(&f.body, row) + 1,
+        GlobalStatement::SubImplementation(s) => statements_end_row(&s.body, row) + 1,
+        GlobalStatement::Statement(statement) => statement_end_row(statement, row),
+        GlobalStatement::UserDefinedType(user_defined_type) => {
+            let last_comment_row = user_defined_type
+                .comments()
+                .chain(
+                    user_defined_type
+                        .elements()
+                        .flat_map(|element| element.element.comments.iter()),
+                )
+                .map(|comment| comment.pos.row());
+            let last_element_row = user_defined_type
+                .elements()
+                .map(|element| element.pos.row());
+            last_comment_row.chain(last_element_row).fold(row, u32::max) + 1
+        }
+        _ => row,
+    }
+}
+
+/// Estimates the last row of the given statements,
+/// which are in a block whose first line is on the given row.
+fn statements_end_row(statements: &Statements, header_row: u32) -> u32 {
+    statements
+        .last()
+        .map(|Positioned { element, pos }| statement_end_row(element, pos.row()))
+        .unwrap_or(header_row)
+}
+
+/// Estimates the last row of the given statement.
+fn statement_end_row(statement: &Statement, row: u32) -> u32 {
+    match statement {
+        Statement::IfBlock(if_block) if !is_single_line_if(if_block) => {
+            let mut end_row = statements_end_row(&if_block.if_block.statements, row);
+            for else_if_block in &if_block.else_if_blocks {
+                end_row = statements_end_row(
+                    &else_if_block.statements,
+                    else_if_block.condition.pos.row(),
+                );
+            }
+            if let Some(else_block) = &if_block.else_block {
+                end_row = statements_end_row(else_block, end_row + 1);
+            }
+            end_row + 1
+        }
+        Statement::SelectCase(select_case) => {
+            let mut end_row = select_case
+                .inline_comments
+                .last()
+                .map(|comment| comment.pos.row())
+                .unwrap_or(row);
+            for case_block in &select_case.case_blocks {
+                let case_row = case_block
+                    .conditions()
+                    .first()
+                    .map(case_expression_row)
+                    .unwrap_or(end_row + 1);
+                end_row = statements_end_row(case_block.statements(), case_row);
+            }
+            if let Some(else_block) = &select_case.else_block {
+                end_row = statements_end_row(else_block, end_row + 1);
+            }
+            end_row + 1
+        }
+        Statement::ForLoop(for_loop) => match &for_loop.next_counter {
+            Some(next_counter) => next_counter.pos.row(),
+            None => statements_end_row(&for_loop.statements, row) + 1,
+        },
+        Statement::While(conditional_block) => {
+            statements_end_row(&conditional_block.statements, row) + 1
+        }
+        Statement::DoLoop(do_loop) => match do_loop.position {
+            DoLoopConditionPosition::Top => statements_end_row(&do_loop.statements, row) + 1,
+            DoLoopConditionPosition::Bottom => do_loop.condition.pos.row(),
+        },
+        _ => row,
+    }
+}
+
+fn case_expression_row(case_expression: &CaseExpression) -> u32 {
+    match case_expression {
+        CaseExpression::Simple(e) | CaseExpression::Is(_, e) | CaseExpression::Range(e, _) => {
+            e.pos.row()
+        }
+    }
+}
+
+fn case_expression_to_string(case_expression: &CaseExpression) -> String {
+    match case_expression {
+        CaseExpression::Simple(e) => expression_to_string(&e.element),
+        CaseExpression::Is(op, e) => {
+            format!(
+                "IS {} {}",
+                operator_to_str(*op),
+                expression_to_string(&e.element)
+            )
+        }
+        CaseExpression::Range(from, to) => format!(
+            "{} TO {}",
+            expression_to_string(&from.element),
+            expression_to_string(&to.element)
+        ),
+    }
+}
+
+/// Formats a statement that fits in a single line.
+fn statement_to_string(statement: &Statement) -> String {
+    match statement {
+        Statement::Comment(comment) => format!("'{}", comment),
+        Statement::Assignment(assignment) => format!(
+            "{} = {}",
+            expression_to_string(assignment.lvalue()),
+            expression_to_string(&assignment.rvalue().element)
+        ),
+        Statement::Const(constant) => format!(
+            "CONST {} = {}",
+            constant.name().element,
+            expression_to_string(&constant.value().element)
+        ),
+        Statement::Dim(dim_list) => dim_list_to_string("DIM", dim_list),
+        Statement::Redim(dim_list) => dim_list_to_string("REDIM", dim_list),
+        Statement::SubCall(sub_call) => {
+            let name = sub_call.sub_name().to_string();
+            // built-in subs like CLS are written in uppercase, like keywords
+            let name = match BuiltInSub::parse_non_keyword_sub(&name) {
+                Some(_) => name.to_uppercase(),
+                None => name,
+            };
+            with_args(&name, &expressions_to_string(sub_call.args()))
+        }
+        Statement::BuiltInSubCall(built_in_sub_call) => {
+            built_in_sub_call_to_string(*built_in_sub_call.built_in_sub(), built_in_sub_call.args())
+        }
+        Statement::IfBlock(if_block) => single_line_if_to_string(if_block),
+        Statement::SelectCase(_)
+        | Statement::ForLoop(_)
+        | Statement::While(_)
+        | Statement::DoLoop(_) => {
+            panic!("Block statement cannot be formatted in a single line")
+        }
+        Statement::Label(label) => format!("{}:", label),
+        Statement::GoTo(label) => format!("GOTO {}", label),
+        Statement::OnError(on_error_option) => match on_error_option {
+            OnErrorOption::Next => "ON ERROR RESUME NEXT".to_owned(),
+            OnErrorOption::Label(label) => format!("ON ERROR GOTO {}", label),
+            OnErrorOption::Zero => "ON ERROR GOTO 0".to_owned(),
+        },
+        Statement::Resume(resume_option) => match resume_option {
+            ResumeOption::Bare => "RESUME".to_owned(),
+            ResumeOption::Next => "RESUME NEXT".to_owned(),
+            ResumeOption::Label(label) => format!("RESUME {}", label),
+        },
+        Statement::GoSub(label) => format!("GOSUB {}", label),
+        Statement::Return(opt_label) => match opt_label {
+            Some(label) => format!("RETURN {}", label),
+            None => "RETURN".to_owned(),
+        },
+        Statement::Exit(ExitObject::Function) => "EXIT FUNCTION".to_owned(),
+        Statement::Exit(ExitObject::Sub) => "EXIT SUB".to_owned(),
+        Statement::OnEvent(on_event) => format!(
+            "ON {}({}) GOSUB {}",
+            event_kind_to_str(on_event.kind),
+            expression_to_string(&on_event.arg.element),
+            on_event.label
+        ),
+        Statement::EventControl(event_control) => {
+            let action = match event_control.action {
+                EventAction::On => "ON",
+                EventAction::Off => "OFF",
+                EventAction::Stop => "STOP",
+            };
+            match &event_control.arg {
+                Some(arg) => format!(
+                    "{}({}) {}",
+                    event_kind_to_str(event_control.kind),
+                    expression_to_string(&arg.element),
+                    action
+                ),
+                None => format!("{} {}", event_kind_to_str(event_control.kind), action),
+            }
+        }
+        Statement::End => "END".to_owned(),
+        Statement::System => "SYSTEM".to_owned(),
+        Statement::Stop => "STOP".to_owned(),
+        Statement::Print(print) => print_to_string(print),
+    }
+}
+
+fn single_line_if_to_string(if_block: &IfBlock) -> String {
+    let mut result = format!(
+        "IF {} THEN {}",
+        expression_to_string(&if_block.if_block.condition.element),
+        single_line_statements_to_string(&if_block.if_block.statements)
+    );
+    if let Some(else_block) = &if_block.else_block {
+        // a trailing comment is parsed as the ELSE block
+        if !else_block
+            .iter()
+            .all(|s| matches!(s.element, Statement::Comment(_)))
+        {
+            result.push_str(" ELSE ");
+        } else {
+            result.push(' ');
+        }
+        result.push_str(&single_line_statements_to_string(else_block));
+    }
+    result
+}
+
+/// Formats statements separated by colons, with a trailing comment.
+fn single_line_statements_to_string(statements: &Statements) -> String {
+    let mut result = String::new();
+    for Positioned { element, .. } in statements {
+        match element {
+            Statement::Comment(comment) => {
+                if !result.is_empty() {
+                    result.push(' ');
+                }
+                result.push_str(&format!("'{}", comment));
+            }
+            _ => {
+                if !result.is_empty() {
+                    result.push_str(": ");
+                }
+                result.push_str(&statement_to_string(element));
+            }
+        }
+    }
+    result
+}
+
+fn event_kind_to_str(event_kind: EventKind) -> &'static str {
+    match event_kind {
+        EventKind::Key => "KEY",
+        EventKind::Play => "PLAY",
+        EventKind::Timer => "TIMER",
+    }
+}
+
+fn print_to_string(print: &Print) -> String {
+    let mut result = String::from(if print.lpt1 { "LPRINT" } else { "PRINT" });
+    if let Some(file_number) = print.file_number {
+        result.push_str(&format!(" #{},", i32::from(file_number)));
+    }
+    if let Some(format_string) = &print.format_string {
+        result.push_str(&format!(
+            " USING {};",
+            expression_to_string(&format_string.element)
+        ));
+    }
+    let mut previous_is_expression = false;
+    for arg in &print.args {
+        // no space between an expression and the separator that follows it
+        if !previous_is_expression || arg.is_expression() {
+            result.push(' ');
+        }
+        match arg {
+            PrintArg::Comma => result.push(','),
+            PrintArg::Semicolon => result.push(';'),
+            PrintArg::Expression(e) => result.push_str(&expression_to_string(&e.element)),
+        }
+        previous_is_expression = arg.is_expression();
+    }
+    result
+}
+
+fn dim_list_to_string(keyword: &str, dim_list: &DimList) -> String {
+    let variables: Vec<String> = dim_list
+        .variables
+        .iter()
+        .map(|dim_var| dim_var_to_string(&dim_var.element))
+        .collect();
+    format!(
+        "{}{} {}",
+        keyword,
+        if dim_list.shared { " SHARED" } else { "" },
+        variables.join(", ")
+    )
+}
+
+fn dim_var_to_string(dim_var: &DimVar) -> String {
+    let mut result = dim_var.as_bare_name().to_string();
+    dim_type_to_string(dim_var.var_type(), &mut result);
+    result
+}
+
+fn dim_type_to_string(dim_type: &DimType, result: &mut String) {
+    match dim_type {
+        DimType::Bare => {}
+        DimType::BuiltIn(q, BuiltInStyle::Compact) => result.push(char::from(*q)),
+        DimType::BuiltIn(q, BuiltInStyle::Extended) => {
+            result.push_str(" AS ");
+            result.push_str(qualifier_to_type_name(*q));
+        }
+        DimType::FixedLengthString(length, _) => {
+            result.push_str(" AS STRING * ");
+            result.push_str(&expression_to_string(&length.element));
+        }
+        DimType::UserDefined(type_name) => {
+            result.push_str(" AS ");
+            result.push_str(&type_name.element.to_string());
+        }
+        DimType::Array(dimensions, element_type) => {
+            // the qualifier goes before the dimensions, e.g. `A$(10)`
+            if let DimType::BuiltIn(q, BuiltInStyle::Compact) = element_type.as_ref() {
+                result.push(char::from(*q));
+            }
+            let dimensions: Vec<String> = dimensions
+                .iter()
+                .map(|dimension| match &dimension.lbound {
+                    Some(lbound) => format!(
+                        "{} TO {}",
+                        expression_to_string(&lbound.element),
+                        expression_to_string(&dimension.ubound.element)
+                    ),
+                    None => expression_to_string(&dimension.ubound.element),
+                })
+                .collect();
+            result.push_str(&format!("({})", dimensions.join(", ")));
+            if !matches!(
+                element_type.as_ref(),
+                DimType::BuiltIn(_, BuiltInStyle::Compact)
+            ) {
+                dim_type_to_string(element_type, result);
+            }
+        }
+    }
+}
+
+fn parameters_to_string(parameters: &Parameters) -> String {
+    let parameters: Vec<String> = parameters
+        .iter()
+        .map(|parameter| {
+            let mut result = parameter.element.as_bare_name().to_string();
+            param_type_to_string(parameter.element.var_type(), &mut result);
+            result
+        })
+        .collect();
+    parameters.join(", ")
+}
+
+fn param_type_to_string(param_type: &ParamType, result: &mut String) {
+    match param_type {
+        ParamType::Bare => {}
+        ParamType::BuiltIn(q, BuiltInStyle::Compact) => result.push(char::from(*q)),
+        ParamType::BuiltIn(q, BuiltInStyle::Extended) => {
+            result.push_str(" AS ");
+            result.push_str(qualifier_to_type_name(*q));
+        }
+        ParamType::UserDefined(type_name) => {
+            result.push_str(" AS ");
+            result.push_str(&type_name.element.to_string());
+        }
+        ParamType::Array(element_type) => {
+            if let ParamType::BuiltIn(q, BuiltInStyle::Compact) = element_type.as_ref() {
+                result.push(char::from(*q));
+                result.push_str("()");
+            } else {
+                result.push_str("()");
+                param_type_to_string(element_type, result);
+            }
+        }
+    }
+}
+
+fn element_type_to_string(element_type: &ElementType) -> String {
+    match element_type {
+        ElementType::Integer => "INTEGER".to_owned(),
+        ElementType::Long => "LONG".to_owned(),
+        ElementType::Single => "SINGLE".to_owned(),
+        ElementType::Double => "DOUBLE".to_owned(),
+        ElementType::FixedLengthString(length, _) => {
+            format!("STRING * {}", expression_to_string(&length.element))
+        }
+        ElementType::UserDefined(type_name) => type_name.element.to_string(),
+    }
+}
+
+fn qualifier_to_type_name(q: TypeQualifier) -> &'static str {
+    match q {
+        TypeQualifier::BangSingle => "SINGLE",
+        TypeQualifier::HashDouble => "DOUBLE",
+        TypeQualifier::DollarString => "STRING",
+        TypeQualifier::PercentInteger => "INTEGER",
+        TypeQualifier::AmpersandLong => "LONG",
+    }
+}
+
+fn def_type_to_string(def_type: &DefType) -> String {
+    let keyword = match def_type.qualifier() {
+        TypeQualifier::BangSingle => "DEFSNG",
+        TypeQualifier::HashDouble => "DEFDBL",
+        TypeQualifier::DollarString => "DEFSTR",
+        TypeQualifier::PercentInteger => "DEFINT",
+        TypeQualifier::AmpersandLong => "DEFLNG",
+    };
+    let ranges: Vec<String> = def_type
+        .ranges()
+        .iter()
+        .map(|range| match range {
+            LetterRange::Range(from, to) => {
+                format!("{}-{}", from.to_ascii_uppercase(), to.to_ascii_uppercase())
+            }
+            LetterRange::Single(letter) => letter.to_ascii_uppercase().to_string(),
+        })
+        .collect();
+    format!("{} {}", keyword, ranges.join(", "))
+}
+
+/// Formats a call of a built-in sub, decoding the arguments
+/// the way the parser encodes them (see the parsers of the built-in subs).
+fn built_in_sub_call_to_string(built_in_sub: BuiltInSub, args: &Expressions) -> String {
+    match built_in_sub {
+        BuiltInSub::Close => {
+            let file_handles: Vec<String> = args.iter().map(file_handle_to_string).collect();
+            with_args("CLOSE", &file_handles.join(", "))
+        }
+        BuiltInSub::Color => with_args("COLOR", &flagged_args_to_string(args)),
+        BuiltInSub::Data => with_args("DATA", &expressions_to_string(args)),
+        BuiltInSub::DefSeg => match args.first() {
+            Some(address) => format!("DEF SEG = {}", expression_to_string(&address.element)),
+            None => "DEF SEG".to_owned(),
+        },
+        BuiltInSub::Error => with_args("ERROR", &expressions_to_string(args)),
+        BuiltInSub::Field => {
+            let mut fields: Vec<String> = vec![];
+            for field in args[1..].chunks(3) {
+                fields.push(format!(
+                    "{} AS {}",
+                    expression_to_string(&field[0].element),
+                    expression_to_string(&field[2].element)
+                ));
+            }
+            format!(
+                "FIELD {}, {}",
+                file_handle_to_string(&args[0]),
+                fields.join(", ")
+            )
+        }
+        BuiltInSub::Get => get_or_put_to_string("GET", args),
+        BuiltInSub::Input => input_to_string("INPUT", args),
+        BuiltInSub::Key => {
+            if args.is_empty() {
+                "KEY LIST".to_owned()
+            } else {
+                with_args("KEY", &expressions_to_string(args))
+            }
+        }
+        BuiltInSub::LineInput => input_to_string("LINE INPUT", args),
+        BuiltInSub::Locate => with_args("LOCATE", &flagged_args_to_string(args)),
+        BuiltInSub::LSet => format!(
+            "LSET {} = {}",
+            expression_to_string(&args[1].element),
+            expression_to_string(&args[2].element)
+        ),
+        BuiltInSub::Name => format!(
+            "NAME {} AS {}",
+            expression_to_string(&args[0].element),
+            expression_to_string(&args[1].element)
+        ),
+        BuiltInSub::Open => open_to_string(args),
+        BuiltInSub::Put => get_or_put_to_string("PUT", args),
+        BuiltInSub::Read => with_args("READ", &expressions_to_string(args)),
+        BuiltInSub::ViewPrint => match args.as_slice() {
+            [top, bottom] => format!(
+                "VIEW PRINT {} TO {}",
+                expression_to_string(&top.element),
+                expression_to_string(&bottom.element)
+            ),
+            _ => "VIEW PRINT".to_owned(),
+        },
+        BuiltInSub::Width => width_to_string(args),
+        // these are parsed as user defined subs (see [BuiltInSub::parse_non_keyword_sub])
+        BuiltInSub::Assert => with_args("ASSERT", &expressions_to_string(args)),
+        BuiltInSub::AssertEqual => with_args("ASSERTEQUAL", &expressions_to_string(args)),
+        BuiltInSub::Beep => with_args("BEEP", &expressions_to_string(args)),
+        BuiltInSub::CallAbsolute => with_args("CALL ABSOLUTE", &expressions_to_string(args)),
+        BuiltInSub::Cls => with_args("CLS", &expressions_to_string(args)),
+        BuiltInSub::Environ => with_args("ENVIRON", &expressions_to_string(args)),
+        BuiltInSub::Kill => with_args("KILL", &expressions_to_string(args)),
+        BuiltInSub::Poke => with_args("POKE", &expressions_to_string(args)),
+        BuiltInSub::Screen => with_args("SCREEN", &expressions_to_string(args)),
+    }
+}
+
+fn with_args(name: &str, args: &str) -> String {
+    if args.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{} {}", name, args)
+    }
+}
+
+/// Formats an argument which is a file handle (e.g. `#1`) or an expression.
+fn file_handle_to_string(arg: &ExpressionPos) -> String {
+    match &arg.element {
+        Expression::IntegerLiteral(i) => format!("#{}", i),
+        e => expression_to_string(e),
+    }
+}
+
+/// Decodes the arguments of `COLOR` and `LOCATE`. The first argument is a bit
+/// mask that specifies which of the optional arguments are present.
+fn flagged_args_to_string(args: &Expressions) -> String {
+    let Some(Expression::IntegerLiteral(flags)) = args.first().map(|arg| &arg.element) else {
+        return String::new();
+    };
+    let mut present_args = args[1..].iter();
+    let mut result: Vec<String> = vec![];
+    let mut mask = 1;
+    while mask <= *flags {
+        if flags & mask != 0 {
+            result.push(
+                present_args
+                    .next()
+                    .map(|arg| expression_to_string(&arg.element))
+                    .unwrap_or_default(),
+            );
+        } else {
+            result.push(String::new());
+        }
+        mask <<= 1;
+    }
+    result.join(", ").trim_end().to_owned()
+}
+
+fn get_or_put_to_string(keyword: &str, args: &Expressions) -> String {
+    format!(
+        "{} {}, {}",
+        keyword,
+        file_handle_to_string(&args[0]),
+        expression_to_string(&args[1].element)
+    )
+}
+
+/// Formats `INPUT` and `LINE INPUT`. The first argument specifies
+/// if a file handle follows.
+fn input_to_string(keyword: &str, args: &Expressions) -> String {
+    let variables = match args.first().map(|arg| &arg.element) {
+        Some(Expression::IntegerLiteral(1)) => {
+            let mut variables = vec![file_handle_to_string(&args[1])];
+            variables.extend(args[2..].iter().map(|a| expression_to_string(&a.element)));
+            variables
+        }
+        _ => args
+            .iter()
+            .skip(1)
+            .map(|a| expression_to_string(&a.element))
+            .collect(),
+    };
+    with_args(keyword, &variables.join(", "))
+}
+
+fn open_to_string(args: &Expressions) -> String {
+    let mut result = format!("OPEN {}", expression_to_string(&args[0].element));
+    // the mode and the access are at the start position when they are omitted
+    let is_present = |arg: &ExpressionPos| arg.pos != rusty_common::Position::start();
+    if is_present(&args[1]) {
+        let mode = match &args[1].element {
+            Expression::IntegerLiteral(mode) => match FileMode::from(*mode as u8) {
+                FileMode::Append => "APPEND",
+                FileMode::Input => "INPUT",
+                FileMode::Output => "OUTPUT",
+                FileMode::Random => "RANDOM",
+            },
+            _ => "RANDOM",
+        };
+        result.push_str(&format!(" FOR {}", mode));
+    }
+    if is_present(&args[2]) {
+        result.push_str(" ACCESS READ");
+    }
+    result.push_str(&format!(" AS {}", file_handle_to_string(&args[3])));
+    if is_present(&args[4]) {
+        result.push_str(&format!(
+            " LEN = {}",
+            expression_to_string(&args[4].element)
+        ));
+    }
+    result
+}
+
+/// Formats `WIDTH`. It is either `WIDTH LPRINT columns` (encoded as `2, columns`)
+/// or a list of optional arguments, each one prefixed by `1` if present or `0` if missing.
+fn width_to_string(args: &Expressions) -> String {
+    if let [first, columns] = args.as_slice()
+        && first.element == Expression::IntegerLiteral(2)
+    {
+        return format!("WIDTH LPRINT {}", expression_to_string(&columns.element));
+    }
+    let mut result: Vec<String> = vec![];
+    let mut iter = args.iter();
+    while let Some(flag) = iter.next() {
+        if flag.element == Expression::IntegerLiteral(1) {
+            result.push(
+                iter.next()
+                    .map(|arg| expression_to_string(&arg.element))
+                    .unwrap_or_default(),
+            );
+        } else {
+            result.push(String::new());
+        }
+    }
+    with_args("WIDTH", result.join(", ").trim_end())
+}
+
+fn expressions_to_string(expressions: &Expressions) -> String {
+    let strings: Vec<String> = expressions
+        .iter()
+        .map(|e| expression_to_string(&e.element))
+        .collect();
+    strings.join(", ")
+}
+
+/// Formats an expression.
+fn expression_to_string(expression: &Expression) -> String {
+    match expression {
+        Expression::SingleLiteral(f) => float_to_string(f.to_string()),
+        Expression::DoubleLiteral(f) => format!("{}#", float_to_string(f.to_string())),
+        Expression::StringLiteral(s) => format!("\"{}\"", s),
+        Expression::IntegerLiteral(i) => i.to_string(),
+        Expression::LongLiteral(l) => l.to_string(),
+        Expression::Variable(name, _) => name.to_string(),
+        Expression::FunctionCall(name, args) | Expression::ArrayElement(name, args, _) => {
+            format!("{}({})", name, expressions_to_string(args))
+        }
+        Expression::BuiltInFunctionCall(built_in_function, args) => {
+            let mut name = built_in_function.as_str().to_uppercase();
+            if TypeQualifier::from(built_in_function) == TypeQualifier::DollarString {
+                name.push('$');
+            }
+            if args.is_empty() {
+                name
+            } else {
+                format!("{}({})", name, expressions_to_string(args))
+            }
+        }
+        Expression::BinaryExpression(op, left, right, _) => format!(
+            "{} {} {}",
+            expression_to_string(&left.element),
+            operator_to_str(*op),
+            expression_to_string(&right.element)
+        ),
+        Expression::UnaryExpression(UnaryOperator::Minus, child) => {
+            let child = expression_to_string(&child.element);
+            if child.starts_with('-') {
+                format!("- {}", child)
+            } else {
+                format!("-{}", child)
+            }
+        }
+        Expression::UnaryExpression(UnaryOperator::Not, child) => {
+            format!("NOT {}", expression_to_string(&child.element))
+        }
+        Expression::Parenthesis(child) => format!("({})", expression_to_string(&child.element)),
+        Expression::Property(left, name, _) => {
+            format!("{}.{}", expression_to_string(left), name)
+        }
+    }
+}
+
+/// Number literals with a decimal point are parsed as floating point numbers,
+/// so the decimal point is kept even for whole numbers (e.g. `1.0`).
+fn float_to_string(s: String) -> String {
+    if s.contains('.') {
+        s
+    } else {
+        format!("{}.0", s)
+    }
+}
+
+fn operator_to_str(op: Operator) -> &'static str {
+    match op {
+        Operator::Less => "<",
+        Operator::LessOrEqual => "<=",
+        Operator::Equal => "=",
+        Operator::GreaterOrEqual => ">=",
+        Operator::Greater => ">",
+        Operator::NotEqual => "<>",
+        Operator::Plus => "+",
+        Operator::Minus => "-",
+        Operator::Multiply => "*",
+        Operator::Divide => "/",
+        Operator::Modulo => "MOD",
+        Operator::And => "AND",
+        Operator::Or => "OR",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::parse_file;
+
+    /// Formats the given source and checks that formatting again
+    /// the formatted source does not change it.
+    fn format(input: &str) -> String {
+        let formatted = format_program(&parse(input));
+        assert_eq!(format_program(&parse(&formatted)), formatted);
+        formatted
+    }
+
+    #[test]
+    fn test_keywords_indentation_and_spacing() {
+        let input = r#"
+declare function Add%(a%,b as integer)
+defint a-c,x
+  dim shared Names$(1 to 10), Total as long
+for i=1 to 10 step 2
+if i mod 2=0 then
+print i;"even",
+elseif not i>5 and i<>3 then
+print
+else
+x=-i*(2+3)
+end if
+next i
+"#;
+        assert_eq!(
+            format(input),
+            r#"DECLARE FUNCTION Add% (a%, b AS INTEGER)
+DEFINT A-C, X
+DIM SHARED Names$(1 TO 10), Total AS LONG
+FOR i = 1 TO 10 STEP 2
+    IF i MOD 2 = 0 THEN
+        PRINT i; "even",
+    ELSEIF NOT i > 5 AND i <> 3 THEN
+        PRINT
+    ELSE
+        x = -i * (2 + 3)
+    END IF
+NEXT i
+"#
+        );
+    }
+
+    #[test]
+    fn test_comments_and_blank_lines_are_kept() {
+        let input = r#"' program
+PRINT "a"   ' greet
+
+
+WHILE X < 1.0 ' loop
+  ' inside
+  X = X + 0.5
+WEND ' done
+Label1:
+  GOTO Label1
+"#;
+        assert_eq!(
+            format(input),
+            r#"' program
+PRINT "a" ' greet
+
+WHILE X < 1.0 ' loop
+    ' inside
+    X = X + 0.5
+WEND ' done
+Label1:
+GOTO Label1
+"#
+        );
+    }
+
+    #[test]
+    fn test_single_line_if() {
+        assert_eq!(
+            format("IF A THEN PRINT 1 : PRINT 2 ELSE PRINT 3 ' c\nIF B THEN GOTO X ' d\n"),
+            "IF A THEN PRINT 1: PRINT 2 ELSE PRINT 3 ' c\nIF B THEN GOTO X ' d\n"
+        );
+    }
+
+    #[test]
+    fn test_select_case_do_loop_and_subprograms() {
+        let input = r#"TYPE Card ' a card
+Value AS INTEGER ' the value
+Suit AS STRING * 5
+END TYPE
+SELECT CASE X ' choose
+' first
+CASE 1, 2
+PRINT "low"
+CASE IS >= 10, 3 TO 5
+CASE ELSE
+PRINT "other"
+END SELECT
+DO
+X = X + 1.5#
+LOOP UNTIL X > 10
+DO WHILE X
+LOOP
+SUB Hello(A() AS Card, B$()) STATIC
+cls
+Hello A, B$
+EXIT SUB
+END SUB
+FUNCTION Twice(N)
+Twice = N * 2
+END FUNCTION
+"#;
+        assert_eq!(
+            format(input),
+            r#"TYPE Card ' a card
+    Value AS INTEGER ' the value
+    Suit AS STRING * 5
+END TYPE
+SELECT CASE X ' choose
+    ' first
+    CASE 1, 2
+        PRINT "low"
+    CASE IS >= 10, 3 TO 5
+    CASE ELSE
+        PRINT "other"
+END SELECT
+DO
+    X = X + 1.5#
+LOOP UNTIL X > 10
+DO WHILE X
+LOOP
+SUB Hello (A() AS Card, B$()) STATIC
+    CLS
+    Hello A, B$
+    EXIT SUB
+END SUB
+FUNCTION Twice (N)
+    Twice = N * 2
+END FUNCTION
+"#
+        );
+    }
+
+    #[test]
+    fn test_built_in_subs() {
+        let input = r##"OPEN "a.txt" FOR INPUT AS #1
+OPEN "b.txt" ACCESS READ AS 2 LEN=64
+FIELD #2, 10 AS A$, 20 AS B$
+GET #2, 1
+LINE INPUT #1, L$
+INPUT A, B
+CLOSE #1, #2
+COLOR , 2
+LOCATE 1
+WIDTH LPRINT 80
+WIDTH , 25
+VIEW PRINT 1 TO 10
+DEF SEG = 0
+KEY LIST
+KEY(1) ON
+ON TIMER(1) GOSUB Tick
+TIMER OFF
+NAME "a" AS "b"
+LSET A$ = "x"
+DATA 1, "two"
+READ A
+PRINT #1, USING "#.#"; A
+LPRINT , , "x"
+ON ERROR RESUME NEXT
+RESUME NEXT
+PRINT INKEY$; LEFT$(A$, 2); LEN(A$)
+"##;
+        assert_eq!(
+            format(input),
+            r##"OPEN "a.txt" FOR INPUT AS #1
+OPEN "b.txt" ACCESS READ AS #2 LEN = 64
+FIELD #2, 10 AS A$, 20 AS B$
+GET #2, 1
+LINE INPUT #1, L$
+INPUT A, B
+CLOSE #1, #2
+COLOR , 2
+LOCATE 1
+WIDTH LPRINT 80
+WIDTH , 25
+VIEW PRINT 1 TO 10
+DEF SEG = 0
+KEY LIST
+KEY(1) ON
+ON TIMER(1) GOSUB Tick
+TIMER OFF
+NAME "a" AS "b"
+LSET A$ = "x"
+DATA 1, "two"
+READ A
+PRINT #1, USING "#.#"; A
+LPRINT , , "x"
+ON ERROR RESUME NEXT
+RESUME NEXT
+PRINT INKEY$; LEFT$(A$, 2); LEN(A$)
+"##
+        );
+    }
+
+    #[test]
+    fn test_fixtures_are_formatted_idempotently() {
+        let mut count = 0;
+        for entry in std::fs::read_dir("../fixtures").unwrap() {
+            let file_name = entry.unwrap().file_name().into_string().unwrap();
+            let formatted = format_program(&parse_file(&file_name));
+            assert_eq!(
+                format_program(&parse(&formatted)),
+                formatted,
+                "{}",
+                file_name
+            );
+            count += 1;
+        }
+        assert!(count > 0);
+    }
+}
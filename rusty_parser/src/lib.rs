@@ -2,6 +2,7 @@ mod built_ins;
 mod core;
 mod error;
 pub mod expr;
+mod formatter;
 mod input;
 mod parser;
 mod pc_specific;
@@ -14,4 +15,5 @@ pub use self::built_ins::*;
 pub use self::core::*;
 pub use self::error::*;
 pub use self::expr::types::*;
+pub use self::formatter::*;
 pub use self::parser::*;
@@ -5,7 +5,7 @@ use rusty_pc::*;
 
 use crate::input::StringView;
 use crate::pc_specific::whitespace_ignoring;
-use crate::tokens::{TokenMatcher, TokenType, any_token};
+use crate::tokens::{TokenMatcher, TokenType, any_token, any_token_of};
 use crate::{Keyword, ParserError};
 
 /// Matches the specific keyword.
@@ -58,6 +58,19 @@ pub fn keyword_pair(
     keyword_ws_p(first).and(keyword_ignoring(second).to_fatal(), IgnoringBothCombiner)
 }
 
+/// Parses a word that acts as a keyword only within specific statements
+/// (e.g. `STOP` in `TIMER STOP`, `LIST` in `KEY LIST`).
+///
+/// The word is not reserved, so it can still be used as a variable name.
+pub fn unreserved_keyword(
+    name: &'static str,
+) -> impl Parser<StringView, Output = (), Error = ParserError> {
+    any_token_of!(TokenType::Identifier)
+        .filter(move |token: &Token| token.as_str().eq_ignore_ascii_case(name))
+        .map_to_unit()
+        .with_expected_message(name.to_uppercase())
+}
+
 /// Parses the specific keyword, ensuring it's not followed by a dollar sign.
 /// See [keyword].
 pub struct KeywordParser<P> {
@@ -150,19 +163,19 @@ mod tests {
 
     #[test]
     fn test_to_syntax_err_one_item() {
-        let keywords = vec![Keyword::If];
+        let keywords = [Keyword::If];
         assert_eq!(to_syntax_err(keywords.iter()), "IF");
     }
 
     #[test]
     fn test_to_syntax_err_two_items() {
-        let keywords = vec![Keyword::If, Keyword::Then];
+        let keywords = [Keyword::If, Keyword::Then];
         assert_eq!(to_syntax_err(keywords.iter()), "IF or THEN");
     }
 
     #[test]
     fn test_to_syntax_err_three_items() {
-        let keywords = vec![Keyword::If, Keyword::Then, Keyword::Else];
+        let keywords = [Keyword::If, Keyword::Then, Keyword::Else];
         assert_eq!(to_syntax_err(keywords.iter()), "IF or THEN or ELSE");
     }
 }
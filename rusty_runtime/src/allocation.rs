@@ -0,0 +1,72 @@
+use rusty_variant::TypeQualifier;
+use rusty_variant::{VArray, Variant};
+
+use crate::RuntimeError;
+
+/// Creates the default variant for the given type.
+pub fn allocate_built_in(type_qualifier: TypeQualifier) -> Variant {
+    match type_qualifier {
+        TypeQualifier::BangSingle => Variant::VSingle(0.0),
+        TypeQualifier::HashDouble => Variant::VDouble(0.0),
+        TypeQualifier::DollarString => Variant::VString(String::new()),
+        TypeQualifier::PercentInteger => Variant::VInteger(0),
+        TypeQualifier::AmpersandLong => Variant::VLong(0),
+    }
+}
+
+/// Allocates a new Variant that holds a string.
+/// The string is padded with whitespace of the given length.
+pub fn allocate_fixed_length_string(len: usize) -> Variant {
+    Variant::VString(" ".repeat(len))
+}
+
+/// Allocates a new array.
+///
+/// The dimension arguments are pairs of lower and upper bounds,
+/// e.g. `DIM A(1 TO 3, 5)` has the arguments `1, 3, 0, 5`.
+/// All elements are initialized to the given element.
+pub fn allocate_array(dimension_args: Vec<i32>, element: Variant) -> Result<Variant, RuntimeError> {
+    let dimensions = to_dimensions(dimension_args)?;
+    Ok(Variant::VArray(Box::new(VArray::new(dimensions, element))))
+}
+
+fn to_dimensions(dimension_args: Vec<i32>) -> Result<Vec<(i32, i32)>, RuntimeError> {
+    let mut dimensions: Vec<(i32, i32)> = vec![];
+    let mut i: usize = 0;
+    while i < dimension_args.len() {
+        let lbound = dimension_args[i];
+        i += 1;
+        let ubound = dimension_args[i];
+        if ubound < lbound {
+            return Err(RuntimeError::SubscriptOutOfRange);
+        }
+        i += 1;
+        dimensions.push((lbound, ubound));
+    }
+    Ok(dimensions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allocate_string() {
+        assert_eq!(
+            allocate_fixed_length_string(1),
+            Variant::VString(" ".to_owned())
+        );
+        assert_eq!(
+            allocate_fixed_length_string(2),
+            Variant::VString("  ".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_allocate_array_upper_bound_less_than_lower_bound() {
+        assert_eq!(
+            allocate_array(vec![2, 1], Variant::VInteger(0)),
+            Err(RuntimeError::SubscriptOutOfRange)
+        );
+    }
+}
@@ -0,0 +1,350 @@
+use rusty_variant::{SubscriptOutOfRangeError, VariantError};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RuntimeError {
+    /// A failed `ASSERT` or `ASSERTEQUAL`, with the message to report.
+    AssertionFailed(String),
+    BadFileMode,
+    BadFileNameOrNumber,
+    BadRecordLength,
+    BadRecordNumber,
+    DivisionByZero,
+    ElementNotDefined,
+    FieldOverflow,
+    FileAlreadyOpen,
+    FileNotFound,
+    ForLoopZeroStep,
+    DeviceIOError(String),
+    IllegalFunctionCall,
+    InputPastEndOfFile,
+    /// The program ran more instructions than allowed by the execution limits.
+    InstructionLimitExceeded,
+    OutOfData,
+    /// The program needs more memory than allowed by the execution limits.
+    OutOfMemory,
+    /// The program called more nested subprograms than allowed by the execution limits.
+    OutOfStackSpace,
+    Overflow,
+    /// The program tried to access a file or an environment variable,
+    /// which is not allowed by the file policy of the execution limits.
+    PermissionDenied,
+    ReturnWithoutGoSub,
+    SubscriptOutOfRange,
+    /// The program ran longer than allowed by the execution limits.
+    TimeLimitExceeded,
+    TypeMismatch,
+    VariableRequired,
+    Other(String),
+    ResumeWithoutError,
+    /// An error that has no dedicated variant,
+    /// typically raised by the `ERROR` statement (e.g. `ERROR 2`, `ERROR 200`).
+    ErrorCode(i32),
+}
+
+/// The QBasic code of the "Internal error",
+/// used for errors that the linter should have caught.
+const INTERNAL_ERROR_CODE: i32 = 51;
+
+impl RuntimeError {
+    pub fn get_code(&self) -> i32 {
+        match self {
+            Self::ReturnWithoutGoSub => 3,
+            Self::OutOfData => 4,
+            Self::IllegalFunctionCall => 5,
+            Self::Overflow => 6,
+            Self::OutOfMemory => 7,
+            Self::SubscriptOutOfRange => 9,
+            Self::DivisionByZero => 11,
+            Self::TypeMismatch => 13,
+            Self::ResumeWithoutError => 20,
+            Self::OutOfStackSpace => 28,
+            Self::VariableRequired => 40,
+            Self::FieldOverflow => 50,
+            Self::BadFileNameOrNumber => 52,
+            Self::FileNotFound => 53,
+            Self::BadFileMode => 54,
+            Self::FileAlreadyOpen => 55,
+            Self::DeviceIOError(_) => 57,
+            Self::BadRecordLength => 59,
+            Self::InputPastEndOfFile => 62,
+            Self::BadRecordNumber => 63,
+            Self::PermissionDenied => 70,
+            // should have been caught by the linter
+            Self::ElementNotDefined => INTERNAL_ERROR_CODE,
+            Self::ErrorCode(code) => *code,
+            // the following are not QBasic codes
+            Self::Other(_) => 257,
+            Self::ForLoopZeroStep => 258,
+            Self::AssertionFailed(_) => 259,
+            Self::InstructionLimitExceeded => 260,
+            Self::TimeLimitExceeded => 261,
+        }
+    }
+
+    /// Creates the error that corresponds to the given code,
+    /// as done by the `ERROR` statement.
+    pub fn from_code(code: i32) -> Self {
+        match code {
+            3 => Self::ReturnWithoutGoSub,
+            4 => Self::OutOfData,
+            5 => Self::IllegalFunctionCall,
+            6 => Self::Overflow,
+            9 => Self::SubscriptOutOfRange,
+            11 => Self::DivisionByZero,
+            13 => Self::TypeMismatch,
+            20 => Self::ResumeWithoutError,
+            28 => Self::OutOfStackSpace,
+            40 => Self::VariableRequired,
+            50 => Self::FieldOverflow,
+            52 => Self::BadFileNameOrNumber,
+            53 => Self::FileNotFound,
+            54 => Self::BadFileMode,
+            55 => Self::FileAlreadyOpen,
+            57 => Self::DeviceIOError("Device I/O error".to_owned()),
+            59 => Self::BadRecordLength,
+            62 => Self::InputPastEndOfFile,
+            63 => Self::BadRecordNumber,
+            70 => Self::PermissionDenied,
+            _ => Self::ErrorCode(code),
+        }
+    }
+
+    /// Checks if this error is reported by a device
+    /// (which sets the value of the `ERDEV` function).
+    pub fn is_device_error(&self) -> bool {
+        // device timeout, device fault, out of paper, device I/O error,
+        // device unavailable, disk not ready
+        matches!(self.get_code(), 24 | 25 | 27 | 57 | 68 | 71)
+    }
+
+    /// Checks if this error is raised by the execution limits,
+    /// in which case it ends the program even if an error handler is set
+    /// (e.g. `ON ERROR RESUME NEXT` should not bypass the time limit).
+    /// The `ERROR` statement cannot raise these errors.
+    ///
+    /// Exceeding the call depth is not fatal, as it is in QBasic
+    /// (`Out of stack space` can be handled with `ON ERROR`).
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            Self::InstructionLimitExceeded | Self::TimeLimitExceeded | Self::OutOfMemory
+        )
+    }
+}
+
+impl std::fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DeviceIOError(msg) => write!(f, "Device I/O error. {}", msg),
+            Self::Other(msg) => f.write_str(msg),
+            Self::ForLoopZeroStep => f.write_str("FOR loop with zero STEP"),
+            Self::AssertionFailed(msg) => write!(f, "Assertion failed. {}", msg),
+            Self::InstructionLimitExceeded => f.write_str("Instruction limit exceeded"),
+            Self::TimeLimitExceeded => f.write_str("Time limit exceeded"),
+            _ => f.write_str(message_of_code(self.get_code())),
+        }
+    }
+}
+
+/// Returns the QBasic message of the given error code.
+fn message_of_code(code: i32) -> &'static str {
+    match code {
+        1 => "NEXT without FOR",
+        2 => "Syntax error",
+        3 => "RETURN without GOSUB",
+        4 => "Out of DATA",
+        5 => "Illegal function call",
+        6 => "Overflow",
+        7 => "Out of memory",
+        8 => "Label not defined",
+        9 => "Subscript out of range",
+        10 => "Duplicate definition",
+        11 => "Division by zero",
+        12 => "Illegal in direct mode",
+        13 => "Type mismatch",
+        14 => "Out of string space",
+        16 => "String formula too complex",
+        17 => "Cannot continue",
+        18 => "Function not defined",
+        19 => "No RESUME",
+        20 => "RESUME without error",
+        24 => "Device timeout",
+        25 => "Device fault",
+        26 => "FOR without NEXT",
+        27 => "Out of paper",
+        28 => "Out of stack space",
+        29 => "WHILE without WEND",
+        30 => "WEND without WHILE",
+        33 => "Duplicate label",
+        35 => "Subprogram not defined",
+        37 => "Argument-count mismatch",
+        38 => "Array not defined",
+        40 => "Variable required",
+        50 => "FIELD overflow",
+        51 => "Internal error",
+        52 => "Bad file name or number",
+        53 => "File not found",
+        54 => "Bad file mode",
+        55 => "File already open",
+        56 => "FIELD statement active",
+        57 => "Device I/O error",
+        58 => "File already exists",
+        59 => "Bad record length",
+        61 => "Disk full",
+        62 => "Input past end of file",
+        63 => "Bad record number",
+        64 => "Bad file name",
+        67 => "Too many files",
+        68 => "Device unavailable",
+        69 => "Communication-buffer overflow",
+        70 => "Permission denied",
+        71 => "Disk not ready",
+        72 => "Disk-media error",
+        73 => "Advanced feature unavailable",
+        74 => "Rename across disks",
+        75 => "Path/File access error",
+        76 => "Path not found",
+        _ => "Unprintable error",
+    }
+}
+
+impl From<std::io::Error> for RuntimeError {
+    fn from(e: std::io::Error) -> Self {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            Self::FileNotFound
+        } else if e.kind() == std::io::ErrorKind::UnexpectedEof {
+            Self::InputPastEndOfFile
+        } else {
+            Self::DeviceIOError(e.to_string())
+        }
+    }
+}
+
+impl From<VariantError> for RuntimeError {
+    fn from(e: VariantError) -> Self {
+        match e {
+            VariantError::DivisionByZero => Self::DivisionByZero,
+            // QBasic reports an overflow when casting an infinite number
+            VariantError::Overflow | VariantError::NotFiniteNumber => Self::Overflow,
+            VariantError::TypeMismatch => Self::TypeMismatch,
+        }
+    }
+}
+
+impl From<SubscriptOutOfRangeError> for RuntimeError {
+    fn from(_: SubscriptOutOfRangeError) -> Self {
+        Self::SubscriptOutOfRange
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_code() {
+        let errors = [
+            RuntimeError::ReturnWithoutGoSub,
+            RuntimeError::IllegalFunctionCall,
+            RuntimeError::Overflow,
+            RuntimeError::SubscriptOutOfRange,
+            RuntimeError::DivisionByZero,
+            RuntimeError::TypeMismatch,
+            RuntimeError::ResumeWithoutError,
+            RuntimeError::BadFileNameOrNumber,
+            RuntimeError::FileNotFound,
+            RuntimeError::FileAlreadyOpen,
+            RuntimeError::InputPastEndOfFile,
+            // the following are not qbasic codes
+            RuntimeError::Other("whatever".to_owned()),
+            RuntimeError::ForLoopZeroStep,
+            RuntimeError::AssertionFailed("whatever".to_owned()),
+        ];
+        let codes = [3, 5, 6, 9, 11, 13, 20, 52, 53, 55, 62, 257, 258, 259];
+
+        assert_eq!(errors.len(), codes.len());
+        for i in 0..errors.len() {
+            let error = &errors[i];
+            let code = codes[i];
+            assert_eq!(error.get_code(), code);
+        }
+    }
+
+    #[test]
+    fn test_to_code_file_errors() {
+        let errors = [
+            RuntimeError::OutOfData,
+            RuntimeError::VariableRequired,
+            RuntimeError::FieldOverflow,
+            RuntimeError::BadFileMode,
+            RuntimeError::DeviceIOError("whatever".to_owned()),
+            RuntimeError::BadRecordLength,
+            RuntimeError::BadRecordNumber,
+        ];
+        let codes = [4, 40, 50, 54, 57, 59, 63];
+
+        assert_eq!(errors.len(), codes.len());
+        for i in 0..errors.len() {
+            assert_eq!(errors[i].get_code(), codes[i]);
+        }
+    }
+
+    #[test]
+    fn test_execution_limit_errors() {
+        let errors = [
+            RuntimeError::OutOfMemory,
+            RuntimeError::OutOfStackSpace,
+            RuntimeError::PermissionDenied,
+            RuntimeError::InstructionLimitExceeded,
+            RuntimeError::TimeLimitExceeded,
+        ];
+        let codes = [7, 28, 70, 260, 261];
+        let messages = [
+            "Out of memory",
+            "Out of stack space",
+            "Permission denied",
+            "Instruction limit exceeded",
+            "Time limit exceeded",
+        ];
+        for i in 0..errors.len() {
+            assert_eq!(errors[i].get_code(), codes[i]);
+            assert_eq!(errors[i].to_string(), messages[i]);
+        }
+        // the file policy and the call depth can be handled with ON ERROR
+        assert!(!RuntimeError::PermissionDenied.is_fatal());
+        assert!(!RuntimeError::OutOfStackSpace.is_fatal());
+        // the ERROR statement cannot end the program unconditionally
+        assert!(!RuntimeError::from_code(7).is_fatal());
+    }
+
+    #[test]
+    fn test_from_code_round_trip() {
+        for code in 1..=255 {
+            assert_eq!(RuntimeError::from_code(code).get_code(), code);
+        }
+    }
+
+    #[test]
+    fn test_from_code_without_variant() {
+        assert_eq!(RuntimeError::from_code(2), RuntimeError::ErrorCode(2));
+        assert_eq!(RuntimeError::from_code(200), RuntimeError::ErrorCode(200));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(RuntimeError::TypeMismatch.to_string(), "Type mismatch");
+        assert_eq!(
+            RuntimeError::SubscriptOutOfRange.to_string(),
+            "Subscript out of range"
+        );
+        assert_eq!(RuntimeError::ErrorCode(2).to_string(), "Syntax error");
+        assert_eq!(
+            RuntimeError::ErrorCode(200).to_string(),
+            "Unprintable error"
+        );
+        assert_eq!(
+            RuntimeError::ErrorCode(35).to_string(),
+            "Subprogram not defined"
+        );
+    }
+}
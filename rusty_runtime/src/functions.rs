@@ -0,0 +1,237 @@
+//! The built-in functions that do not depend on the state of the program.
+//!
+//! The arguments have already been cast by the caller,
+//! e.g. `LEFT$` receives the count as a non-negative integer.
+
+use rusty_bit_vec::{MAX_INTEGER, MAX_LONG};
+use rusty_variant::{QBNumberCast, Variant, VariantError};
+
+use crate::RuntimeError;
+
+/// `CHR$(code)`
+pub fn chr(code: i32) -> String {
+    let mut s: String = String::new();
+    s.push((code as u8) as char);
+    s
+}
+
+/// `INSTR([start,] hay, needle)`, where `start` is 1 based.
+pub fn instr(start: usize, hay: &str, needle: &str) -> i32 {
+    debug_assert!(start >= 1);
+    if hay.is_empty() {
+        0
+    } else if needle.is_empty() {
+        1
+    } else {
+        let mut i: usize = start - 1;
+        while i + needle.len() <= hay.len() {
+            let sub = hay.get(i..(i + needle.len())).unwrap();
+            if sub == needle {
+                return (i as i32) + 1;
+            }
+            i += 1;
+        }
+        0
+    }
+}
+
+/// `LBOUND(array[, dimension])`, where `dimension` is 1 based.
+pub fn lbound(v: &Variant, dimension: usize) -> Result<i32, RuntimeError> {
+    dimension_bounds(v, dimension).map(|(lower, _)| lower)
+}
+
+/// `LCASE$(s)`
+pub fn lcase(s: &str) -> String {
+    s.to_ascii_lowercase()
+}
+
+/// `LEFT$(s, count)`
+pub fn left(s: &str, count: usize) -> String {
+    s.chars().take(count).collect()
+}
+
+/// `LTRIM$(s)`
+pub fn ltrim(s: &str) -> String {
+    s.trim_start().to_owned()
+}
+
+/// `MID$(s, start[, length])`, where `start` is 1 based.
+pub fn mid(s: &str, start: usize, opt_length: Option<usize>) -> String {
+    let start_index: usize = start - 1;
+    match opt_length {
+        Some(length) => {
+            let end: usize = if start_index + length > s.len() {
+                s.len()
+            } else {
+                start_index + length
+            };
+            s.get(start_index..end).unwrap_or_default().to_string()
+        }
+        None => s.get(start_index..).unwrap_or_default().to_string(),
+    }
+}
+
+/// `RIGHT$(s, count)`
+pub fn right(s: &str, count: usize) -> String {
+    if s.len() > count {
+        s.chars().skip(s.len() - count).collect()
+    } else {
+        s.to_owned()
+    }
+}
+
+/// `RTRIM$(s)`
+pub fn rtrim(s: &str) -> String {
+    s.trim_end().to_owned()
+}
+
+/// `SPACE$(count)`
+pub fn space(count: i32) -> String {
+    let mut s: String = String::new();
+    for _ in 0..count {
+        s.push(' ');
+    }
+    s
+}
+
+macro_rules! str_fmt {
+    ($e: expr, $zero: expr) => {
+        if $e >= $zero {
+            format!(" {}", $e)
+        } else {
+            format!("{}", $e)
+        }
+    };
+}
+
+/// `STR$(number)`
+///
+/// Panics if the variant is not a number.
+pub fn str(v: &Variant) -> String {
+    match v {
+        Variant::VSingle(f) => str_fmt!(*f, 0.0),
+        Variant::VDouble(f) => str_fmt!(*f, 0.0),
+        Variant::VInteger(f) => str_fmt!(*f, 0),
+        Variant::VLong(f) => str_fmt!(*f, 0),
+        _ => panic!("unexpected arg to STR$"),
+    }
+}
+
+/// `STRING$(count, ascii-code)` or `STRING$(count, s)`
+pub fn string(count: usize, v: &Variant) -> Result<String, RuntimeError> {
+    if let Variant::VString(s) = v {
+        let first_char = s.chars().next().ok_or(RuntimeError::IllegalFunctionCall)?;
+        Ok(repeat_char(count, first_char))
+    } else {
+        let ascii: i32 = v.try_cast()?;
+        if (0..=255).contains(&ascii) {
+            let u: u8 = ascii as u8;
+            Ok(repeat_char(count, u as char))
+        } else {
+            Err(RuntimeError::IllegalFunctionCall)
+        }
+    }
+}
+
+fn repeat_char(count: usize, ch: char) -> String {
+    std::iter::repeat_n(ch, count).collect()
+}
+
+/// `UBOUND(array[, dimension])`, where `dimension` is 1 based.
+pub fn ubound(v: &Variant, dimension: usize) -> Result<i32, RuntimeError> {
+    dimension_bounds(v, dimension).map(|(_, upper)| upper)
+}
+
+fn dimension_bounds(v: &Variant, dimension: usize) -> Result<(i32, i32), RuntimeError> {
+    match v {
+        Variant::VArray(a) => a
+            .get_dimension_bounds(dimension - 1)
+            .copied()
+            .ok_or(RuntimeError::SubscriptOutOfRange),
+        _ => Err(RuntimeError::TypeMismatch),
+    }
+}
+
+/// `UCASE$(s)`
+pub fn ucase(s: &str) -> String {
+    s.to_ascii_uppercase()
+}
+
+/// `VAL(s)`
+pub fn val(s: &str) -> Result<Variant, VariantError> {
+    let mut is_positive = true;
+    let mut value: f64 = 0.0;
+    let mut fraction_power: i32 = 0;
+
+    const STATE_INITIAL: u8 = 0;
+    const STATE_SIGN: u8 = 1;
+    const STATE_INT: u8 = 2;
+    const STATE_DOT: u8 = 3;
+    const STATE_FRACTION: u8 = 4;
+    let mut state: u8 = STATE_INITIAL;
+
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            if state == STATE_INITIAL || state == STATE_SIGN {
+                state = STATE_INT;
+            } else if state == STATE_DOT {
+                state = STATE_FRACTION;
+            }
+            if state == STATE_INT {
+                value = value * 10.0 + ((c as u8) - b'0') as f64;
+            } else {
+                if fraction_power <= MAX_INTEGER {
+                    fraction_power += 1;
+                } else {
+                    return Err(VariantError::Overflow);
+                }
+                value = (value * 10.0_f64.powi(fraction_power) + ((c as u8) - b'0') as f64)
+                    / 10.0_f64.powi(fraction_power);
+            }
+        } else if c == ' ' {
+            // ignore spaces apparently
+        } else if c == '.' {
+            if state == STATE_DOT || state == STATE_FRACTION {
+                break;
+            } else {
+                state = STATE_DOT;
+            }
+        } else if c == '-' {
+            if state == STATE_INITIAL {
+                state = STATE_SIGN;
+                is_positive = false;
+            } else {
+                break;
+            }
+        } else if c == '+' {
+            if state == STATE_INITIAL {
+                state = STATE_SIGN;
+            } else {
+                break;
+            }
+        } else {
+            // bail out
+            break;
+        }
+    }
+
+    if state == STATE_INITIAL || state == STATE_SIGN {
+        Ok(Variant::VInteger(0))
+    } else if state == STATE_INT || state == STATE_DOT {
+        if is_positive && value <= MAX_INTEGER as f64 {
+            Ok(Variant::VInteger(value as i32))
+        } else if !is_positive && value <= (1 + MAX_INTEGER) as f64 {
+            Ok(Variant::VInteger(-value as i32))
+        } else if is_positive && value <= MAX_LONG as f64 {
+            Ok(Variant::VLong(value as i64))
+        } else if !is_positive && value <= (1 + MAX_LONG) as f64 {
+            Ok(Variant::VLong(-value as i64))
+        } else {
+            let x = Variant::VDouble(value);
+            if is_positive { Ok(x) } else { x.negate() }
+        }
+    } else {
+        let x = Variant::VDouble(value);
+        if is_positive { Ok(x) } else { x.negate() }
+    }
+}
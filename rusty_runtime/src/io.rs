@@ -2,11 +2,11 @@ use std::collections::HashMap;
 use std::fs::{File, OpenOptions};
 use std::io::{BufReader, Read, Seek, SeekFrom, Write};
 
-use rusty_parser::{FileAccess, FileHandle, FileMode};
+use rusty_variant::{FileAccess, FileHandle, FileMode};
 
 use crate::RuntimeError;
-use crate::interpreter::read_input::ReadInputSource;
-use crate::interpreter::write_printer::WritePrinter;
+use crate::read_input::ReadInputSource;
+use crate::write_printer::WritePrinter;
 
 pub trait Input {
     fn eof(&mut self) -> std::io::Result<bool>;
@@ -14,6 +14,13 @@ pub trait Input {
     fn input(&mut self) -> std::io::Result<String>;
 
     fn line_input(&mut self) -> std::io::Result<String>;
+
+    /// Checks if a whole line can be read without waiting for it.
+    /// Sources that block until a line is available (e.g. the terminal)
+    /// are always ready.
+    fn is_line_ready(&mut self) -> bool {
+        true
+    }
 }
 
 pub trait Printer {
@@ -22,12 +29,22 @@ pub trait Printer {
     fn println(&mut self) -> std::io::Result<usize>;
 
     fn move_to_next_print_zone(&mut self) -> std::io::Result<usize>;
+
+    /// Returns the current column (0 based).
+    fn column(&self) -> usize;
+
+    /// Sets the width after which the output wraps to a new line.
+    /// `None` means no wrapping.
+    fn set_width(&mut self, width: Option<usize>);
 }
 
 pub type FileInfoInput = ReadInputSource<BufReader<File>>;
 pub type FileInfoOutput = WritePrinter<File>;
 
 pub struct FileInfo {
+    /// The name of the file, as it was opened.
+    file_name: String,
+    file_mode: FileMode,
     field_lists: Vec<Vec<Field>>,
     input: Option<FileInfoInput>,
     output: Option<FileInfoOutput>,
@@ -37,8 +54,10 @@ pub struct FileInfo {
 }
 
 impl FileInfo {
-    pub fn new_input(file: File) -> Self {
+    pub fn new_input(file_name: &str, file: File) -> Self {
         Self {
+            file_name: file_name.to_owned(),
+            file_mode: FileMode::Input,
             field_lists: vec![],
             input: Some(ReadInputSource::new(BufReader::new(file))),
             output: None,
@@ -48,8 +67,10 @@ impl FileInfo {
         }
     }
 
-    pub fn new_output(file: File) -> Self {
+    pub fn new_output(file_name: &str, file_mode: FileMode, file: File) -> Self {
         Self {
+            file_name: file_name.to_owned(),
+            file_mode,
             field_lists: vec![],
             input: None,
             output: Some(WritePrinter::new(file)),
@@ -59,8 +80,10 @@ impl FileInfo {
         }
     }
 
-    pub fn new_random(file: File, rec_len: usize) -> Self {
+    pub fn new_random(file_name: &str, file: File, rec_len: usize) -> Self {
         Self {
+            file_name: file_name.to_owned(),
+            file_mode: FileMode::Random,
             field_lists: vec![],
             input: None,
             output: None,
@@ -163,27 +186,100 @@ impl FileManager {
                     .truncate(true)
                     .open(file_name)?;
                 self.handle_map
-                    .insert(handle, FileInfo::new_random(file, rec_len));
+                    .insert(handle, FileInfo::new_random(file_name, file, rec_len));
             }
             FileMode::Input => {
                 let file = File::open(file_name)?;
-                self.handle_map.insert(handle, FileInfo::new_input(file));
+                self.handle_map
+                    .insert(handle, FileInfo::new_input(file_name, file));
             }
             FileMode::Output => {
                 let file = File::create(file_name)?;
-                self.handle_map.insert(handle, FileInfo::new_output(file));
+                self.handle_map
+                    .insert(handle, FileInfo::new_output(file_name, file_mode, file));
             }
             FileMode::Append => {
                 let file = OpenOptions::new()
                     .append(true)
                     .create(true)
                     .open(file_name)?;
-                self.handle_map.insert(handle, FileInfo::new_output(file));
+                self.handle_map
+                    .insert(handle, FileInfo::new_output(file_name, file_mode, file));
             }
         }
         Ok(())
     }
 
+    /// Gets the open files, ordered by their handle,
+    /// e.g. in order to open them again later with [Self::reopen].
+    pub fn open_files(&mut self) -> Result<Vec<OpenFile>, RuntimeError> {
+        let mut handles: Vec<FileHandle> = self.handle_map.keys().copied().collect();
+        handles.sort();
+        let mut result: Vec<OpenFile> = vec![];
+        for handle in handles {
+            let file_info = self.handle_map.get_mut(&handle).unwrap();
+            let (position, column) = match (&mut file_info.input, &file_info.output) {
+                (Some(input), _) => (input.position()?, 0),
+                (_, Some(output)) => {
+                    let mut file: &File = output.inner();
+                    (file.stream_position()?, output.column())
+                }
+                _ => (0, 0),
+            };
+            result.push(OpenFile {
+                handle,
+                file_name: file_info.file_name.clone(),
+                file_mode: file_info.file_mode,
+                rec_len: file_info.rec_len,
+                position,
+                column,
+                field_lists: file_info.field_lists.clone(),
+                current_field_list_index: file_info.current_field_list_index,
+            });
+        }
+        Ok(result)
+    }
+
+    /// Opens again a file that was open earlier, continuing from
+    /// the position it had. An output file loses whatever was written
+    /// after that position. Unlike `OPEN`, a random access file keeps its contents.
+    pub fn reopen(&mut self, open_file: OpenFile) -> Result<(), RuntimeError> {
+        if self.handle_map.contains_key(&open_file.handle) {
+            return Err(RuntimeError::FileAlreadyOpen);
+        }
+        let file_name = open_file.file_name.as_str();
+        let mut file_info = match open_file.file_mode {
+            FileMode::Random => {
+                let file = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .create(true)
+                    .truncate(false)
+                    .open(file_name)?;
+                FileInfo::new_random(file_name, file, open_file.rec_len)
+            }
+            FileMode::Input => {
+                let mut file = File::open(file_name)?;
+                file.seek(SeekFrom::Start(open_file.position))?;
+                FileInfo::new_input(file_name, file)
+            }
+            FileMode::Output | FileMode::Append => {
+                let mut file = OpenOptions::new().write(true).open(file_name)?;
+                file.set_len(open_file.position)?;
+                file.seek(SeekFrom::End(0))?;
+                let mut file_info = FileInfo::new_output(file_name, open_file.file_mode, file);
+                if let Some(output) = file_info.output.as_mut() {
+                    output.set_column(open_file.column);
+                }
+                file_info
+            }
+        };
+        file_info.field_lists = open_file.field_lists;
+        file_info.current_field_list_index = open_file.current_field_list_index;
+        self.handle_map.insert(open_file.handle, file_info);
+        Ok(())
+    }
+
     pub fn try_get_file_info(
         &mut self,
         handle: &FileHandle,
@@ -240,7 +336,22 @@ impl FileManager {
     }
 }
 
-#[derive(Clone)]
+/// An open file, as returned by [FileManager::open_files].
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpenFile {
+    pub handle: FileHandle,
+    pub file_name: String,
+    pub file_mode: FileMode,
+    pub rec_len: usize,
+    /// The position of the next byte to read (input) or to write (output).
+    pub position: u64,
+    /// The current column of an output file (0 based).
+    pub column: usize,
+    pub field_lists: Vec<Vec<Field>>,
+    pub current_field_list_index: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Field {
     pub width: usize,
     pub name: String,
@@ -0,0 +1,36 @@
+//! The runtime support shared by the interpreter
+//! and by the programs that are transpiled to Rust.
+
+mod allocation;
+mod byte_size;
+mod error;
+pub mod functions;
+mod io;
+mod print;
+mod program;
+mod read_input;
+mod string_utils;
+mod variant_casts;
+mod write_printer;
+
+pub use self::allocation::*;
+pub use self::byte_size::*;
+pub use self::error::*;
+pub use self::io::*;
+pub use self::print::*;
+pub use self::program::*;
+pub use self::read_input::*;
+pub use self::string_utils::*;
+pub use self::variant_casts::*;
+pub use self::write_printer::*;
+
+/// The items that the programs transpiled to Rust use.
+pub mod prelude {
+    pub use rusty_variant::{TypeQualifier, Variant};
+
+    pub use crate::*;
+}
+
+fn is_cr_lf(ch: char) -> bool {
+    ch == '\r' || ch == '\n'
+}
@@ -0,0 +1,592 @@
+//! Formatting of the values printed by `PRINT` and `PRINT USING`.
+
+use std::fmt::Display;
+
+use rusty_variant::Variant;
+
+use crate::RuntimeError;
+use crate::io::Printer;
+use crate::string_utils::fix_length;
+
+/// The format string of a `PRINT USING` statement.
+///
+/// The format string is applied to the printed values one after the other,
+/// looping over to its start when it runs out of formatting fields.
+#[derive(Debug)]
+pub struct FormatString {
+    chars: Vec<char>,
+    index: usize,
+}
+
+impl Display for FormatString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.chars.iter().collect::<String>())
+    }
+}
+
+impl FormatString {
+    pub fn new(format_string: &str) -> Self {
+        Self {
+            chars: format_string.chars().collect(),
+            index: 0,
+        }
+    }
+
+    /// Formats the given value with the next formatting field,
+    /// including any literal characters that precede the field.
+    /// Creates a format string that continues at the given index,
+    /// as returned by [Self::index].
+    pub fn with_index(format_string: &str, index: usize) -> Self {
+        let mut result = Self::new(format_string);
+        result.index = index.min(result.chars.len());
+        result
+    }
+
+    /// Gets the index of the next character to use.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn format_value(&mut self, v: Variant) -> Result<String, RuntimeError> {
+        if self.chars.is_empty() {
+            return Err(RuntimeError::IllegalFunctionCall);
+        }
+
+        // ensure we are in the range of chars
+        self.index %= self.chars.len();
+
+        // copy from format_string until we hit a formatting character
+        let mut result = print_non_formatting_chars(&self.chars, &mut self.index)?;
+
+        // format the argument using the formatting character
+        let second_part = print_formatting_chars(&self.chars, &mut self.index, v)?;
+        result.push_str(&second_part);
+
+        Ok(result)
+    }
+
+    /// Returns the literal characters that follow the last formatted field,
+    /// to be printed at the end of the statement.
+    pub fn remaining(&mut self) -> Result<String, RuntimeError> {
+        print_remaining_non_formatting_chars(&self.chars, &mut self.index)
+    }
+}
+
+fn print_non_formatting_chars(
+    format_string_chars: &[char],
+    index: &mut usize,
+) -> Result<String, RuntimeError> {
+    // copy from format_string until we hit a formatting character
+    let mut buf: String = String::new();
+    let mut i = *index;
+    // counts the characters visited, to detect looping over to the starting point
+    let mut visited: usize = 0;
+    while !is_field_start(format_string_chars, i) {
+        i = print_literal_char(format_string_chars, i, &mut buf);
+        visited += 1;
+        if i >= format_string_chars.len() {
+            i = 0;
+        }
+        if visited > format_string_chars.len() {
+            // looped over to the starting point without encountering a formatting character
+            return Err(RuntimeError::IllegalFunctionCall);
+        }
+    }
+    *index = i;
+    Ok(buf)
+}
+
+/// Checks if a formatting field starts at the given index.
+fn is_field_start(format_string_chars: &[char], index: usize) -> bool {
+    match format_string_chars[index] {
+        '!' | '\\' | '&' => true,
+        _ => numeric_formatting::is_field_start(&format_string_chars[index..]),
+    }
+}
+
+/// Copies the literal character at the given index into the buffer.
+/// The underscore prints the next character literally (e.g. `_#` prints `#`).
+/// Returns the index of the next character.
+fn print_literal_char(format_string_chars: &[char], index: usize, buf: &mut String) -> usize {
+    if format_string_chars[index] == '_' && index + 1 < format_string_chars.len() {
+        buf.push(format_string_chars[index + 1]);
+        index + 2
+    } else {
+        buf.push(format_string_chars[index]);
+        index + 1
+    }
+}
+
+fn print_remaining_non_formatting_chars(
+    format_string_chars: &[char],
+    index: &mut usize,
+) -> Result<String, RuntimeError> {
+    // copy from format_string until we hit a formatting character
+    let mut buf: String = String::new();
+    let mut i = *index;
+    while i < format_string_chars.len() && !is_field_start(format_string_chars, i) {
+        i = print_literal_char(format_string_chars, i, &mut buf);
+    }
+    *index = i;
+    Ok(buf)
+}
+
+fn print_formatting_chars(
+    format_string_chars: &[char],
+    index: &mut usize,
+    v: Variant,
+) -> Result<String, RuntimeError> {
+    match format_string_chars[*index] {
+        '\\' => print_string_formatting_chars(format_string_chars, index, v),
+        '!' => print_first_char_formatting_chars(format_string_chars, index, v),
+        '&' => print_whole_string_formatting_chars(format_string_chars, index, v),
+        _ => numeric_formatting::print_numeric_formatting_chars(format_string_chars, index, v),
+    }
+}
+
+mod numeric_formatting {
+    //! Handles formatting of numbers.
+    //!
+    //! A numeric field consists of:
+    //!
+    //! - an optional leading `+`, printing the sign of the number
+    //! - an optional `**` (fills leading spaces with asterisks),
+    //!   `$$` (prints a dollar sign before the number) or `**$` (both)
+    //! - digit positions `#`, optionally with commas, which print a comma
+    //!   every three digits
+    //! - an optional decimal point, followed by digit positions
+    //! - an optional `^^^^` (or `^^^^^`), which prints the number in exponential format
+    //! - an optional trailing `+` (sign of the number) or `-` (minus for negative numbers)
+    //!
+    //! A number that does not fit in the field is printed in full,
+    //! prefixed with `%`.
+
+    use rusty_variant::Variant;
+
+    use crate::RuntimeError;
+
+    #[derive(Default)]
+    struct NumericField {
+        leading_plus: bool,
+        asterisk_fill: bool,
+        dollar: bool,
+        /// Digit positions before the decimal point (including commas and `**`)
+        integer_digits: usize,
+        comma: bool,
+        decimal_point: bool,
+        fractional_digits: usize,
+        /// Digits of the exponent (2 for `^^^^`, 3 for `^^^^^`)
+        exponent_digits: Option<usize>,
+        trailing_plus: bool,
+        trailing_minus: bool,
+    }
+
+    /// Checks if a numeric field starts at the beginning of the given chars.
+    pub fn is_field_start(chars: &[char]) -> bool {
+        match chars {
+            ['+', rest @ ..] => is_unsigned_field_start(rest),
+            _ => is_unsigned_field_start(chars),
+        }
+    }
+
+    fn is_unsigned_field_start(chars: &[char]) -> bool {
+        matches!(
+            chars,
+            ['#', ..] | ['.', '#', ..] | ['*', '*', ..] | ['$', '$', ..]
+        )
+    }
+
+    pub fn print_numeric_formatting_chars(
+        format_string_chars: &[char],
+        index: &mut usize,
+        v: Variant,
+    ) -> Result<String, RuntimeError> {
+        debug_assert!(is_field_start(&format_string_chars[*index..]));
+        let (field, len) = parse_field(&format_string_chars[*index..]);
+        *index += len;
+        let number = Number::try_from(v)?;
+        match field.exponent_digits {
+            Some(exponent_digits) => fmt_exponential(&field, exponent_digits, number),
+            None => fmt_fixed(&field, number),
+        }
+    }
+
+    /// Parses the numeric field at the beginning of the given chars.
+    /// Returns the field and the number of chars it occupies.
+    fn parse_field(chars: &[char]) -> (NumericField, usize) {
+        let mut field = NumericField::default();
+        let mut i: usize = 0;
+        let starts_with = |i: usize, prefix: &str| {
+            prefix
+                .chars()
+                .enumerate()
+                .all(|(j, ch)| chars.get(i + j) == Some(&ch))
+        };
+        if starts_with(i, "+") {
+            field.leading_plus = true;
+            i += 1;
+        }
+        if starts_with(i, "**$") {
+            field.asterisk_fill = true;
+            field.dollar = true;
+            field.integer_digits += 2;
+            i += 3;
+        } else if starts_with(i, "**") {
+            field.asterisk_fill = true;
+            field.integer_digits += 2;
+            i += 2;
+        } else if starts_with(i, "$$") {
+            field.dollar = true;
+            field.integer_digits += 1;
+            i += 2;
+        }
+        while i < chars.len() {
+            match chars[i] {
+                '#' => field.integer_digits += 1,
+                // a comma is part of the field only if more digits follow
+                ',' if matches!(chars.get(i + 1), Some('#' | ',' | '.')) => {
+                    field.comma = true;
+                    field.integer_digits += 1;
+                }
+                _ => break,
+            }
+            i += 1;
+        }
+        if starts_with(i, ".") {
+            field.decimal_point = true;
+            i += 1;
+            while starts_with(i, "#") {
+                field.fractional_digits += 1;
+                i += 1;
+            }
+        }
+        if starts_with(i, "^^^^^") {
+            field.exponent_digits = Some(3);
+            i += 5;
+        } else if starts_with(i, "^^^^") {
+            field.exponent_digits = Some(2);
+            i += 4;
+        }
+        if !field.leading_plus {
+            if starts_with(i, "+") {
+                field.trailing_plus = true;
+                i += 1;
+            } else if starts_with(i, "-") {
+                field.trailing_minus = true;
+                i += 1;
+            }
+        }
+        (field, i)
+    }
+
+    /// A number to format, keeping its original type for accurate rounding.
+    enum Number {
+        Single(f32),
+        Double(f64),
+        Integer(i64),
+    }
+
+    impl TryFrom<Variant> for Number {
+        type Error = RuntimeError;
+
+        fn try_from(v: Variant) -> Result<Self, Self::Error> {
+            match v {
+                Variant::VSingle(f) => Ok(Self::Single(f)),
+                Variant::VDouble(d) => Ok(Self::Double(d)),
+                Variant::VInteger(i) => Ok(Self::Integer(i as i64)),
+                Variant::VLong(l) => Ok(Self::Integer(l)),
+                _ => Err(RuntimeError::TypeMismatch),
+            }
+        }
+    }
+
+    impl Number {
+        fn is_negative(&self) -> bool {
+            match self {
+                Self::Single(f) => *f < 0.0,
+                Self::Double(d) => *d < 0.0,
+                Self::Integer(i) => *i < 0,
+            }
+        }
+
+        fn abs_f64(&self) -> f64 {
+            match self {
+                Self::Single(f) => f.abs() as f64,
+                Self::Double(d) => d.abs(),
+                Self::Integer(i) => i.unsigned_abs() as f64,
+            }
+        }
+
+        /// Formats the absolute value with the given fractional digits.
+        fn fmt_abs(&self, fractional_digits: usize) -> String {
+            match self {
+                Self::Single(f) => fmt_float(f.abs() as f64, *f as f64, fractional_digits),
+                Self::Double(d) => fmt_float(d.abs(), *d, fractional_digits),
+                Self::Integer(i) => {
+                    let mut s = i.unsigned_abs().to_string();
+                    if fractional_digits > 0 {
+                        s.push('.');
+                        s.push_str(&"0".repeat(fractional_digits));
+                    }
+                    s
+                }
+            }
+        }
+    }
+
+    fn fmt_float(abs: f64, original: f64, fractional_digits: usize) -> String {
+        if fractional_digits > 0 {
+            // format the original value, to round the same way for both signs
+            let s = format!("{:.1$}", original, fractional_digits);
+            s.trim_start_matches('-').to_owned()
+        } else {
+            // round half away from zero
+            abs.round().to_string()
+        }
+    }
+
+    fn fmt_fixed(field: &NumericField, number: Number) -> Result<String, RuntimeError> {
+        let unformatted = number.fmt_abs(field.fractional_digits);
+        let mut decimal_split = unformatted.split('.');
+        let integer_part = decimal_split.next().unwrap_or_default();
+        let fractional_part = decimal_split.next().unwrap_or_default();
+        let integer_part = if integer_part == "0" && field.integer_digits == 0 {
+            // e.g. `.##` prints `.50`
+            String::new()
+        } else if field.comma {
+            insert_thousands_separators(integer_part)
+        } else {
+            integer_part.to_owned()
+        };
+        Ok(assemble(
+            field,
+            number.is_negative(),
+            &integer_part,
+            fractional_part,
+            "",
+        ))
+    }
+
+    fn fmt_exponential(
+        field: &NumericField,
+        exponent_digits: usize,
+        number: Number,
+    ) -> Result<String, RuntimeError> {
+        // without a sign specifier, one digit position is reserved for the sign
+        let has_sign_specifier = field.leading_plus || field.trailing_plus || field.trailing_minus;
+        let integer_digits = if has_sign_specifier {
+            field.integer_digits
+        } else {
+            field.integer_digits.saturating_sub(1)
+        };
+        let abs = number.abs_f64();
+        let mut exponent: i32 = if abs == 0.0 {
+            0
+        } else {
+            abs.log10().floor() as i32 + 1 - integer_digits as i32
+        };
+        let mut mantissa = format!(
+            "{:.1$}",
+            abs / 10_f64.powi(exponent),
+            field.fractional_digits
+        );
+        if abs != 0.0 && mantissa_overflows(&mantissa, integer_digits) {
+            // rounding produced an extra digit (e.g. 9.99 -> 10.0)
+            exponent += 1;
+            mantissa = format!(
+                "{:.1$}",
+                abs / 10_f64.powi(exponent),
+                field.fractional_digits
+            );
+        }
+        let mut decimal_split = mantissa.split('.');
+        let integer_part = decimal_split.next().unwrap_or_default();
+        let fractional_part = decimal_split.next().unwrap_or_default();
+        let integer_part = if integer_digits == 0 {
+            ""
+        } else {
+            integer_part
+        };
+        let exponent_str = format!(
+            "E{}{:0>2$}",
+            if exponent < 0 { '-' } else { '+' },
+            exponent.unsigned_abs(),
+            exponent_digits
+        );
+        let result = assemble(
+            field,
+            number.is_negative(),
+            integer_part,
+            fractional_part,
+            &exponent_str,
+        );
+        if exponent_str.len() > exponent_digits + 2 && !result.starts_with('%') {
+            // the exponent does not fit
+            Ok(format!("%{}", result))
+        } else {
+            Ok(result)
+        }
+    }
+
+    fn mantissa_overflows(mantissa: &str, integer_digits: usize) -> bool {
+        let integer_part = mantissa.split('.').next().unwrap_or_default();
+        if integer_digits == 0 {
+            integer_part != "0"
+        } else {
+            integer_part.len() > integer_digits
+        }
+    }
+
+    /// Puts together the parts of a formatted number,
+    /// adding the sign, the dollar sign and the padding.
+    fn assemble(
+        field: &NumericField,
+        is_negative: bool,
+        integer_part: &str,
+        fractional_part: &str,
+        exponent: &str,
+    ) -> String {
+        let mut left: String = String::new();
+        if field.leading_plus {
+            left.push(if is_negative { '-' } else { '+' });
+        } else if is_negative && !field.trailing_plus && !field.trailing_minus {
+            left.push('-');
+        }
+        if field.dollar {
+            left.push('$');
+        }
+        left.push_str(integer_part);
+        let width =
+            field.integer_digits + usize::from(field.dollar) + usize::from(field.leading_plus);
+        let left_len = left.chars().count();
+        let mut result: String = String::new();
+        if left_len > width {
+            result.push('%');
+        } else {
+            let fill = if field.asterisk_fill { '*' } else { ' ' };
+            result.extend(std::iter::repeat_n(fill, width - left_len));
+        }
+        result.push_str(&left);
+        if field.decimal_point {
+            result.push('.');
+            result.push_str(fractional_part);
+        }
+        result.push_str(exponent);
+        if field.trailing_plus {
+            result.push(if is_negative { '-' } else { '+' });
+        } else if field.trailing_minus {
+            result.push(if is_negative { '-' } else { ' ' });
+        }
+        result
+    }
+
+    fn insert_thousands_separators(digits: &str) -> String {
+        let mut result: String = String::new();
+        for (i, ch) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                result.push(',');
+            }
+            result.push(ch);
+        }
+        result
+    }
+}
+
+fn print_string_formatting_chars(
+    format_string_chars: &[char],
+    index: &mut usize,
+    v: Variant,
+) -> Result<String, RuntimeError> {
+    debug_assert_eq!(format_string_chars[*index], '\\');
+    *index += 1;
+    let mut counter: usize = 2;
+    while *index < format_string_chars.len() && format_string_chars[*index] != '\\' {
+        if format_string_chars[*index] != ' ' {
+            // only spaces should be allowed within backslashes
+            return Err(RuntimeError::IllegalFunctionCall);
+        }
+        *index += 1;
+        counter += 1;
+    }
+    if *index < format_string_chars.len() {
+        *index += 1;
+        if let Variant::VString(mut s) = v {
+            fix_length(&mut s, counter);
+            Ok(s)
+        } else {
+            Err(RuntimeError::TypeMismatch)
+        }
+    } else {
+        // did not find closing backslash
+        Err(RuntimeError::IllegalFunctionCall)
+    }
+}
+
+fn print_first_char_formatting_chars(
+    format_string_chars: &[char],
+    index: &mut usize,
+    v: Variant,
+) -> Result<String, RuntimeError> {
+    debug_assert_eq!(format_string_chars[*index], '!');
+    if let Variant::VString(s) = v {
+        let ch = s.chars().next().ok_or(RuntimeError::IllegalFunctionCall)?;
+        let result = String::from(ch);
+        *index += 1;
+        Ok(result)
+    } else {
+        Err(RuntimeError::TypeMismatch)
+    }
+}
+
+fn print_whole_string_formatting_chars(
+    format_string_chars: &[char],
+    index: &mut usize,
+    v: Variant,
+) -> Result<String, RuntimeError> {
+    debug_assert_eq!(format_string_chars[*index], '&');
+    if let Variant::VString(s) = v {
+        *index += 1;
+        Ok(s)
+    } else {
+        Err(RuntimeError::TypeMismatch)
+    }
+}
+
+pub trait PrintHelper {
+    fn print_number<V: Display>(
+        &mut self,
+        number: V,
+        leading_space: bool,
+    ) -> std::io::Result<usize>;
+
+    fn print_variant(&mut self, v: &Variant) -> std::io::Result<usize>;
+}
+
+impl<T: Printer + ?Sized> PrintHelper for T {
+    fn print_number<V: Display>(
+        &mut self,
+        number: V,
+        leading_space: bool,
+    ) -> std::io::Result<usize> {
+        let s: String = if leading_space {
+            format!(" {} ", number)
+        } else {
+            format!("{} ", number)
+        };
+        self.print(s.as_str())
+    }
+
+    fn print_variant(&mut self, v: &Variant) -> std::io::Result<usize> {
+        match v {
+            Variant::VSingle(f) => self.print_number(f, *f >= 0.0),
+            Variant::VDouble(d) => self.print_number(d, *d >= 0.0),
+            Variant::VString(s) => self.print(s),
+            Variant::VInteger(i) => self.print_number(i, *i >= 0),
+            Variant::VLong(l) => self.print_number(l, *l >= 0),
+            Variant::VArray(_) | Variant::VUserDefined(_) => panic!(
+                "Cannot print user defined type {:?}, linter should have caught this",
+                v
+            ),
+        }
+    }
+}
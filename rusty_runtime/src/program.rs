@@ -0,0 +1,576 @@
+//! The state of a program that has been transpiled to Rust,
+//! together with the helpers that the generated code calls.
+
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::io::{Stdin, Stdout};
+use std::process::ExitCode;
+
+use rusty_variant::{
+    CastVariant, FileAccess, FileHandle, FileMode, QBNumberCast, SubscriptOutOfRangeError,
+    TypeQualifier, Variant, VariantError,
+};
+
+use crate::{
+    FileManager, FormatString, Input, PrintHelper, Printer, QByteSize, ReadInputSource,
+    RuntimeError, VariantCasts, WritePrinter, fix_length, functions, parse_input,
+};
+
+/// The reason a program stops before reaching its end.
+#[derive(Debug, PartialEq)]
+pub enum Halt {
+    /// `END`, `SYSTEM` or `STOP`.
+    End,
+    /// An unhandled runtime error.
+    Error(RuntimeError),
+}
+
+impl From<RuntimeError> for Halt {
+    fn from(e: RuntimeError) -> Self {
+        Self::Error(e)
+    }
+}
+
+impl From<VariantError> for Halt {
+    fn from(e: VariantError) -> Self {
+        Self::Error(e.into())
+    }
+}
+
+impl From<SubscriptOutOfRangeError> for Halt {
+    fn from(e: SubscriptOutOfRangeError) -> Self {
+        Self::Error(e.into())
+    }
+}
+
+/// The state of a running program, i.e. the standard input and output,
+/// the open files and the `DATA` values.
+pub struct Runtime<I: Input = ReadInputSource<Stdin>, O: Printer = WritePrinter<Stdout>> {
+    stdin: I,
+    stdout: O,
+    file_manager: FileManager,
+    data: VecDeque<Variant>,
+    command_line: String,
+    print_file_handle: FileHandle,
+    print_format_string: Option<FormatString>,
+    print_skip_new_line: bool,
+}
+
+impl Runtime {
+    /// Runs the given program with the standard input and output,
+    /// returning the exit code of the process.
+    ///
+    /// An unhandled runtime error is printed to stderr
+    /// and its QBasic error code becomes the exit code.
+    pub fn run(program: impl FnOnce(&mut Self) -> Result<(), Halt>) -> ExitCode {
+        let mut runtime = Self::new(
+            ReadInputSource::new(std::io::stdin()),
+            WritePrinter::new(std::io::stdout()),
+        );
+        runtime.command_line = std::env::args().skip(1).collect::<Vec<_>>().join(" ");
+        match program(&mut runtime) {
+            Ok(()) | Err(Halt::End) => ExitCode::SUCCESS,
+            Err(Halt::Error(e)) => {
+                eprintln!("error: {}", e);
+                ExitCode::from(u8::try_from(e.get_code()).unwrap_or(u8::MAX))
+            }
+        }
+    }
+}
+
+impl<I: Input, O: Printer> Runtime<I, O> {
+    pub fn new(stdin: I, stdout: O) -> Self {
+        Self {
+            stdin,
+            stdout,
+            file_manager: FileManager::new(),
+            data: VecDeque::new(),
+            command_line: String::new(),
+            print_file_handle: FileHandle::default(),
+            print_format_string: None,
+            print_skip_new_line: false,
+        }
+    }
+
+    pub fn stdout(&self) -> &O {
+        &self.stdout
+    }
+
+    /// `DATA` values are collected before the program starts.
+    pub fn push_data(&mut self, v: Variant) {
+        self.data.push_back(v);
+    }
+
+    /// `READ`, casting the next `DATA` value to the type of the target variable.
+    pub fn read(&mut self, q: TypeQualifier) -> Result<Variant, RuntimeError> {
+        let v = self.data.pop_front().ok_or(RuntimeError::OutOfData)?;
+        v.cast(q).map_err(RuntimeError::from)
+    }
+
+    /// `INPUT` of a single variable, from the standard input
+    /// or from the given file (when the file number is positive).
+    pub fn input(
+        &mut self,
+        file_number: Variant,
+        q: TypeQualifier,
+    ) -> Result<Variant, RuntimeError> {
+        let raw_input = match self.opt_input_file_handle(file_number)? {
+            Some(file_handle) => self
+                .file_manager
+                .try_get_file_info_input(&file_handle)?
+                .input()?,
+            None => self.stdin.input()?,
+        };
+        parse_input(raw_input, q)
+    }
+
+    /// `LINE INPUT` of a single variable, from the standard input
+    /// or from the given file (when the file number is positive).
+    pub fn line_input(&mut self, file_number: Variant) -> Result<Variant, RuntimeError> {
+        let s = match self.opt_input_file_handle(file_number)? {
+            Some(file_handle) => self
+                .file_manager
+                .try_get_file_info_input(&file_handle)?
+                .line_input()?,
+            None => self.stdin.input()?,
+        };
+        Ok(Variant::VString(s))
+    }
+
+    fn opt_input_file_handle(
+        &self,
+        file_number: Variant,
+    ) -> Result<Option<FileHandle>, RuntimeError> {
+        let file_handle = if file_number == Variant::VInteger(0) {
+            FileHandle::default()
+        } else {
+            file_number.to_file_handle()?
+        };
+        Ok(Some(file_handle).filter(FileHandle::is_valid))
+    }
+
+    /// Starts a `PRINT` statement, to the screen (file number 0) or to a file.
+    pub fn print_begin(&mut self, file_number: u8) {
+        self.print_file_handle = file_number.into();
+        self.print_format_string = None;
+    }
+
+    /// Sets the format string of `PRINT USING`.
+    pub fn print_using(&mut self, format_string: Variant) {
+        self.print_format_string = Some(FormatString::new(format_string.to_str_unchecked()));
+    }
+
+    pub fn print_value(&mut self, v: Variant) -> Result<(), RuntimeError> {
+        self.print_skip_new_line = false;
+        match self.print_format_string.as_mut() {
+            Some(format_string) => {
+                let s = format_string.format_value(v)?;
+                self.printer()?.print(&s)?;
+            }
+            None => {
+                self.printer()?.print_variant(&v)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn print_comma(&mut self) -> Result<(), RuntimeError> {
+        self.print_skip_new_line = true;
+        self.printer()?.move_to_next_print_zone()?;
+        Ok(())
+    }
+
+    pub fn print_semicolon(&mut self) {
+        self.print_skip_new_line = true;
+    }
+
+    pub fn print_end(&mut self) -> Result<(), RuntimeError> {
+        let opt_remaining = match self.print_format_string.as_mut() {
+            Some(format_string) => Some(format_string.remaining()?),
+            None => None,
+        };
+        let should_print_new_line = !std::mem::take(&mut self.print_skip_new_line);
+        let printer = self.printer()?;
+        if let Some(remaining) = opt_remaining {
+            printer.print(&remaining)?;
+        }
+        if should_print_new_line {
+            printer.println()?;
+        }
+        Ok(())
+    }
+
+    fn printer(&mut self) -> Result<&mut dyn Printer, RuntimeError> {
+        if self.print_file_handle.is_valid() {
+            let file_output = self
+                .file_manager
+                .try_get_file_info_output(&self.print_file_handle)?;
+            Ok(file_output)
+        } else {
+            Ok(&mut self.stdout)
+        }
+    }
+
+    /// `CHR$(code)`
+    pub fn chr(&mut self, args: Vec<Variant>) -> Result<Variant, RuntimeError> {
+        Ok(functions::chr(args[0].try_cast()?).into())
+    }
+
+    /// `COMMAND$`
+    pub fn command(&mut self, _args: Vec<Variant>) -> Result<Variant, RuntimeError> {
+        // QBasic converts the command line to uppercase
+        Ok(self.command_line.to_uppercase().into())
+    }
+
+    /// `ENVIRON$(name)`
+    pub fn environ(&mut self, args: Vec<Variant>) -> Result<Variant, RuntimeError> {
+        Ok(std::env::var(args[0].to_str_unchecked())
+            .unwrap_or_default()
+            .into())
+    }
+
+    /// `EOF(file_number)`
+    pub fn eof(&mut self, args: Vec<Variant>) -> Result<Variant, RuntimeError> {
+        let file_handle = args[0].to_file_handle()?;
+        Ok(self
+            .file_manager
+            .try_get_file_info_input(&file_handle)?
+            .eof()?
+            .into())
+    }
+
+    /// `INSTR([start,] haystack, needle)`
+    pub fn instr(&mut self, args: Vec<Variant>) -> Result<Variant, RuntimeError> {
+        let result = match args.get(2) {
+            Some(c) => functions::instr(
+                args[0].to_positive_int()?,
+                args[1].to_str_unchecked(),
+                c.to_str_unchecked(),
+            ),
+            None => functions::instr(1, args[0].to_str_unchecked(), args[1].to_str_unchecked()),
+        };
+        Ok(result.into())
+    }
+
+    /// `LCASE$(s)`
+    pub fn lcase(&mut self, args: Vec<Variant>) -> Result<Variant, RuntimeError> {
+        Ok(functions::lcase(args[0].to_str_unchecked()).into())
+    }
+
+    /// `LEFT$(s, count)`
+    pub fn left(&mut self, args: Vec<Variant>) -> Result<Variant, RuntimeError> {
+        Ok(functions::left(args[0].to_str_unchecked(), args[1].to_non_negative_int()?).into())
+    }
+
+    /// `LEN(value)`
+    pub fn len(&mut self, args: Vec<Variant>) -> Result<Variant, RuntimeError> {
+        Ok((args[0].byte_size() as i32).into())
+    }
+
+    /// `LTRIM$(s)`
+    pub fn ltrim(&mut self, args: Vec<Variant>) -> Result<Variant, RuntimeError> {
+        Ok(functions::ltrim(args[0].to_str_unchecked()).into())
+    }
+
+    /// `MID$(s, start[, length])`
+    pub fn mid(&mut self, args: Vec<Variant>) -> Result<Variant, RuntimeError> {
+        let length = match args.get(2) {
+            Some(v) => Some(v.to_non_negative_int()?),
+            None => None,
+        };
+        Ok(functions::mid(
+            args[0].to_str_unchecked(),
+            args[1].to_positive_int()?,
+            length,
+        )
+        .into())
+    }
+
+    /// `RIGHT$(s, count)`
+    pub fn right(&mut self, args: Vec<Variant>) -> Result<Variant, RuntimeError> {
+        Ok(functions::right(args[0].to_str_unchecked(), args[1].to_non_negative_int()?).into())
+    }
+
+    /// `RTRIM$(s)`
+    pub fn rtrim(&mut self, args: Vec<Variant>) -> Result<Variant, RuntimeError> {
+        Ok(functions::rtrim(args[0].to_str_unchecked()).into())
+    }
+
+    /// `SPACE$(count)`
+    pub fn space(&mut self, args: Vec<Variant>) -> Result<Variant, RuntimeError> {
+        Ok(functions::space(args[0].try_cast()?).into())
+    }
+
+    /// `STR$(number)`
+    pub fn str(&mut self, args: Vec<Variant>) -> Result<Variant, RuntimeError> {
+        Ok(functions::str(&args[0]).into())
+    }
+
+    /// `STRING$(count, code_or_string)`
+    pub fn string(&mut self, args: Vec<Variant>) -> Result<Variant, RuntimeError> {
+        Ok(functions::string(args[0].to_non_negative_int()?, &args[1])?.into())
+    }
+
+    /// `UCASE$(s)`
+    pub fn ucase(&mut self, args: Vec<Variant>) -> Result<Variant, RuntimeError> {
+        Ok(functions::ucase(args[0].to_str_unchecked()).into())
+    }
+
+    /// `VAL(s)`
+    pub fn val(&mut self, args: Vec<Variant>) -> Result<Variant, RuntimeError> {
+        Ok(functions::val(args[0].to_str_unchecked())?)
+    }
+
+    /// `CLOSE [file_number, ...]`
+    pub fn close(&mut self, args: Vec<Variant>) -> Result<(), RuntimeError> {
+        if args.is_empty() {
+            self.file_manager.close_all();
+        }
+        for arg in &args {
+            self.file_manager.close(&arg.to_file_handle()?);
+        }
+        Ok(())
+    }
+
+    /// `ENVIRON "name=value"`
+    pub fn set_environ(&mut self, args: Vec<Variant>) -> Result<(), RuntimeError> {
+        let s = args[0].to_str_unchecked();
+        match s.split_once('=') {
+            Some((name, value)) if !value.contains('=') => {
+                // SAFETY: the generated programs are single threaded
+                unsafe {
+                    std::env::set_var(name, value);
+                }
+                Ok(())
+            }
+            _ => Err(RuntimeError::Other(
+                "Invalid expression. Must be name=value.".to_string(),
+            )),
+        }
+    }
+
+    /// `ERROR code`
+    pub fn error(&mut self, args: Vec<Variant>) -> Result<(), RuntimeError> {
+        let error_code: i32 = args[0].try_cast()?;
+        if (1..=255).contains(&error_code) {
+            Err(RuntimeError::from_code(error_code))
+        } else {
+            Err(RuntimeError::IllegalFunctionCall)
+        }
+    }
+
+    /// `KILL file_name`
+    pub fn kill(&mut self, args: Vec<Variant>) -> Result<(), RuntimeError> {
+        std::fs::remove_file(args[0].to_str_unchecked()).map_err(RuntimeError::from)
+    }
+
+    /// `NAME old_name AS new_name`
+    pub fn name(&mut self, args: Vec<Variant>) -> Result<(), RuntimeError> {
+        std::fs::rename(args[0].to_str_unchecked(), args[1].to_str_unchecked())
+            .map_err(RuntimeError::from)
+    }
+
+    /// `OPEN file_name FOR mode ACCESS access AS file_number LEN = record_length`
+    pub fn open(&mut self, args: Vec<Variant>) -> Result<(), RuntimeError> {
+        let file_mode: i32 = args[1].try_cast()?;
+        let file_access: i32 = args[2].try_cast()?;
+        let rec_len: i32 = args[4].try_cast()?;
+        if rec_len < 0 {
+            return Err(RuntimeError::BadRecordLength);
+        }
+        self.file_manager.open(
+            args[3].to_file_handle()?,
+            args[0].to_str_unchecked(),
+            FileMode::from(file_mode as u8),
+            FileAccess::from(file_access as u8),
+            rec_len as usize,
+        )
+    }
+}
+
+/// Casts a value to the given type (e.g. when assigning to a variable).
+pub fn cast(v: Variant, q: TypeQualifier) -> Result<Variant, RuntimeError> {
+    v.cast(q).map_err(RuntimeError::from)
+}
+
+/// Casts a value to a fixed length string of the given length.
+pub fn cast_fixed_length(v: Variant, len: u16) -> Result<Variant, RuntimeError> {
+    let mut casted = cast(v, TypeQualifier::DollarString)?;
+    if let Variant::VString(s) = &mut casted {
+        fix_length(s, len as usize);
+    }
+    Ok(casted)
+}
+
+/// Converts a value to an integer, e.g. a dimension of an array.
+pub fn to_int(v: Variant) -> Result<i32, RuntimeError> {
+    v.try_cast().map_err(RuntimeError::from)
+}
+
+/// Evaluates a condition (e.g. of an `IF` statement). Any non-zero number is true.
+pub fn is_true(v: Variant) -> Result<bool, RuntimeError> {
+    QBNumberCast::<bool>::try_cast(&v).map_err(RuntimeError::from)
+}
+
+/// Gets the qualifier of the given value, e.g. of a `READ` or `INPUT` target.
+pub fn qualifier(v: &Variant) -> Result<TypeQualifier, RuntimeError> {
+    v.qualifier().ok_or(RuntimeError::TypeMismatch)
+}
+
+/// The dimension argument of `LBOUND` and `UBOUND`.
+pub fn bound_dimension(v: Variant) -> Result<usize, RuntimeError> {
+    v.to_positive_int_or(RuntimeError::SubscriptOutOfRange)
+}
+
+macro_rules! comparison {
+    ($name: ident, $predicate: expr) => {
+        pub fn $name(a: Variant, b: Variant) -> Result<Variant, RuntimeError> {
+            let order: Ordering = a.try_cmp(&b)?;
+            let predicate: fn(Ordering) -> bool = $predicate;
+            Ok(predicate(order).into())
+        }
+    };
+}
+
+comparison!(equal, |order| order == Ordering::Equal);
+comparison!(not_equal, |order| order != Ordering::Equal);
+comparison!(less, |order| order == Ordering::Less);
+comparison!(less_or_equal, |order| order != Ordering::Greater);
+comparison!(greater, |order| order == Ordering::Greater);
+comparison!(greater_or_equal, |order| order != Ordering::Less);
+
+pub fn and(a: Variant, b: Variant) -> Result<Variant, RuntimeError> {
+    let a = cast(a, TypeQualifier::PercentInteger)?;
+    let b = cast(b, TypeQualifier::PercentInteger)?;
+    a.and(b).map_err(RuntimeError::from)
+}
+
+pub fn or(a: Variant, b: Variant) -> Result<Variant, RuntimeError> {
+    let a = cast(a, TypeQualifier::PercentInteger)?;
+    let b = cast(b, TypeQualifier::PercentInteger)?;
+    a.or(b).map_err(RuntimeError::from)
+}
+
+/// Checks the `STEP` of a `FOR` loop, returning true if it is positive.
+pub fn for_step_is_positive(step: &Variant) -> Result<bool, RuntimeError> {
+    match step.try_cmp(&Variant::VInteger(0))? {
+        Ordering::Greater => Ok(true),
+        Ordering::Less => Ok(false),
+        Ordering::Equal => Err(RuntimeError::ForLoopZeroStep),
+    }
+}
+
+/// Checks if the counter of a `FOR` loop has not gone past the upper bound.
+pub fn for_continues(
+    counter: &Variant,
+    upper: &Variant,
+    is_positive: bool,
+) -> Result<bool, RuntimeError> {
+    let order = counter.try_cmp(upper)?;
+    Ok(if is_positive {
+        order != Ordering::Greater
+    } else {
+        order != Ordering::Less
+    })
+}
+
+/// Gets an element of an array.
+///
+/// The indices come first, so that they are evaluated
+/// before the array is borrowed.
+pub fn array_get(indices: &[Variant], array: &Variant) -> Result<Variant, RuntimeError> {
+    match array {
+        Variant::VArray(a) => Ok(a.get_element(&to_indices(indices)?)?.clone()),
+        _ => Err(RuntimeError::TypeMismatch),
+    }
+}
+
+/// Sets an element of an array.
+pub fn array_set(indices: &[Variant], array: &mut Variant, v: Variant) -> Result<(), RuntimeError> {
+    match array {
+        Variant::VArray(a) => {
+            *a.get_element_mut(&to_indices(indices)?)? = v;
+            Ok(())
+        }
+        _ => Err(RuntimeError::TypeMismatch),
+    }
+}
+
+fn to_indices(indices: &[Variant]) -> Result<Vec<i32>, RuntimeError> {
+    indices.iter().cloned().map(to_int).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runtime() -> Runtime<ReadInputSource<&'static [u8]>, WritePrinter<Vec<u8>>> {
+        Runtime::new(
+            ReadInputSource::new(b"42\r\nhello\r\n"),
+            WritePrinter::new(vec![]),
+        )
+    }
+
+    #[test]
+    fn test_print() {
+        let mut rt = runtime();
+        rt.print_begin(0);
+        rt.print_value(Variant::VString("A".to_owned())).unwrap();
+        rt.print_comma().unwrap();
+        rt.print_value(Variant::VInteger(1)).unwrap();
+        rt.print_semicolon();
+        rt.print_end().unwrap();
+        rt.print_begin(0);
+        rt.print_using(Variant::VString("##.#".to_owned()));
+        rt.print_value(Variant::VSingle(1.5)).unwrap();
+        rt.print_end().unwrap();
+        assert_eq!(rt.stdout().output_lines(), vec!["A              1  1.5"]);
+    }
+
+    #[test]
+    fn test_input() {
+        let mut rt = runtime();
+        assert_eq!(
+            rt.input(Variant::VInteger(0), TypeQualifier::PercentInteger),
+            Ok(Variant::VInteger(42))
+        );
+        assert_eq!(
+            rt.line_input(Variant::VInteger(0)),
+            Ok(Variant::VString("hello".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_read() {
+        let mut rt = runtime();
+        rt.push_data(Variant::VInteger(1));
+        assert_eq!(
+            rt.read(TypeQualifier::BangSingle),
+            Ok(Variant::VSingle(1.0))
+        );
+        assert_eq!(
+            rt.read(TypeQualifier::BangSingle),
+            Err(RuntimeError::OutOfData)
+        );
+    }
+
+    #[test]
+    fn test_for_step_zero() {
+        assert_eq!(
+            for_step_is_positive(&Variant::VInteger(0)),
+            Err(RuntimeError::ForLoopZeroStep)
+        );
+    }
+
+    #[test]
+    fn test_array_get_and_set() {
+        let mut array = crate::allocate_array(vec![1, 3], Variant::VInteger(0)).unwrap();
+        let indices = [Variant::VInteger(2)];
+        array_set(&indices, &mut array, Variant::VInteger(5)).unwrap();
+        assert_eq!(array_get(&indices, &array), Ok(Variant::VInteger(5)));
+        assert_eq!(
+            array_get(&[Variant::VInteger(4)], &array),
+            Err(RuntimeError::SubscriptOutOfRange)
+        );
+    }
+}
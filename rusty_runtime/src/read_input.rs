@@ -0,0 +1,246 @@
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::io::{ErrorKind, Read, Seek};
+use std::str::FromStr;
+
+use rusty_variant::TypeQualifier;
+use rusty_variant::Variant;
+
+use crate::RuntimeError;
+use crate::io::Input;
+use crate::is_cr_lf;
+
+pub struct ReadInputSource<T: Read> {
+    read: T,
+    buffer: Vec<u8>,
+}
+
+impl<T: Read> ReadInputSource<T> {
+    pub fn new(read: T) -> Self {
+        Self {
+            read,
+            buffer: vec![],
+        }
+    }
+
+    pub fn inner(&mut self) -> &mut T {
+        &mut self.read
+    }
+
+    fn peek(&mut self) -> std::io::Result<Option<u8>> {
+        if self.fill_buffer()? == 0 {
+            Ok(None)
+        } else {
+            Ok(self.buffer.first().copied())
+        }
+    }
+
+    fn read(&mut self) -> std::io::Result<Option<u8>> {
+        if self.fill_buffer()? == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(self.buffer.remove(0)))
+        }
+    }
+
+    fn fill_buffer(&mut self) -> std::io::Result<usize> {
+        if self.buffer.is_empty() {
+            let mut buf = [0; 1]; // 1 bytes buffer inefficient
+            let n = self.read.read(&mut buf[..])?;
+            if n > 0 {
+                self.buffer.push(buf[0]);
+            }
+
+            Ok(n)
+        } else {
+            Ok(self.buffer.len())
+        }
+    }
+
+    fn skip_while<F>(&mut self, predicate: F) -> std::io::Result<String>
+    where
+        F: Fn(char) -> bool,
+    {
+        let mut buf: Vec<u8> = vec![];
+        let mut found = true;
+        while found {
+            found = false;
+            if let Some(ch) = self.peek()?
+                && predicate(ch as char)
+            {
+                buf.push(ch);
+                self.read()?;
+                found = true;
+            }
+        }
+        Ok(String::from_utf8(buf).unwrap())
+    }
+
+    fn read_until<F>(&mut self, predicate: F) -> std::io::Result<String>
+    where
+        F: Fn(char) -> bool,
+    {
+        let mut buf: Vec<u8> = vec![];
+        let mut found = true;
+        while found {
+            found = false;
+            if let Some(ch) = self.read()? {
+                if predicate(ch as char) {
+                    // if it was '\r', try to also get the next '\n', if exists
+                    if ch as char == '\r'
+                        && let Some(next_ch) = self.peek()?
+                        && next_ch as char == '\n'
+                    {
+                        self.read()?;
+                    }
+                } else {
+                    buf.push(ch);
+                    found = true;
+                }
+            }
+        }
+        Ok(String::from_utf8(buf).unwrap())
+    }
+}
+
+impl<T: Read + Seek> ReadInputSource<T> {
+    /// Gets the position of the next byte to read,
+    /// taking into account the bytes that have been read ahead.
+    pub fn position(&mut self) -> std::io::Result<u64> {
+        Ok(self.read.stream_position()? - self.buffer.len() as u64)
+    }
+}
+
+impl<T: Read> Input for ReadInputSource<T> {
+    fn eof(&mut self) -> std::io::Result<bool> {
+        self.peek().map(|ch| ch.is_none())
+    }
+
+    fn input(&mut self) -> std::io::Result<String> {
+        if self.eof()? {
+            return Err(std::io::Error::from(ErrorKind::UnexpectedEof));
+        }
+
+        // skip leading whitespace
+        self.skip_while(|ch| ch == ' ')?;
+        // read until comma or eol
+        self.read_until(|ch| ch == ',' || is_cr_lf(ch))
+            .map(|s| s.trim().to_owned())
+    }
+
+    fn line_input(&mut self) -> std::io::Result<String> {
+        if self.eof()? {
+            return Err(std::io::Error::from(ErrorKind::UnexpectedEof));
+        }
+        self.read_until(is_cr_lf)
+    }
+}
+
+/// An input source that holds the text that the application supplied
+/// and never waits for more, e.g. for an interpreter that runs in steps
+/// and pauses when a statement needs input that is not available yet.
+pub struct InputQueue {
+    source: ReadInputSource<VecDeque<u8>>,
+}
+
+impl InputQueue {
+    pub fn new() -> Self {
+        Self {
+            source: ReadInputSource::new(VecDeque::new()),
+        }
+    }
+
+    /// Adds the given text at the end of the input.
+    pub fn push_str(&mut self, s: &str) {
+        self.source.read.extend(s.bytes());
+    }
+
+    /// Adds the given line at the end of the input.
+    pub fn push_line(&mut self, line: &str) {
+        self.push_str(line);
+        self.push_str("\r\n");
+    }
+}
+
+impl Default for InputQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Input for InputQueue {
+    fn eof(&mut self) -> std::io::Result<bool> {
+        self.source.eof()
+    }
+
+    fn input(&mut self) -> std::io::Result<String> {
+        self.source.input()
+    }
+
+    fn line_input(&mut self) -> std::io::Result<String> {
+        self.source.line_input()
+    }
+
+    fn is_line_ready(&mut self) -> bool {
+        self.source
+            .buffer
+            .iter()
+            .chain(self.source.read.iter())
+            .any(|ch| is_cr_lf(*ch as char))
+    }
+}
+
+/// Parses a value that was read by `INPUT` into the type of the target variable.
+/// An empty value is the default value of the type.
+pub fn parse_input(s: String, q: TypeQualifier) -> Result<Variant, RuntimeError> {
+    match q {
+        TypeQualifier::DollarString => Ok(Variant::VString(s)),
+        TypeQualifier::BangSingle => parse_number(&s, "float").map(Variant::VSingle),
+        TypeQualifier::HashDouble => parse_number(&s, "float").map(Variant::VDouble),
+        TypeQualifier::PercentInteger => parse_number(&s, "int").map(Variant::VInteger),
+        TypeQualifier::AmpersandLong => parse_number(&s, "int").map(Variant::VLong),
+    }
+}
+
+fn parse_number<T>(s: &str, type_name: &str) -> Result<T, RuntimeError>
+where
+    T: FromStr + Default,
+    T::Err: Display,
+{
+    if s.is_empty() {
+        Ok(T::default())
+    } else {
+        s.parse::<T>().map_err(|e| {
+            RuntimeError::Other(format!("Could not parse {} as {}: {}", s, type_name, e))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_queue_is_ready_after_a_whole_line() {
+        let mut queue = InputQueue::new();
+        assert!(!queue.is_line_ready());
+        queue.push_str("4");
+        assert!(!queue.is_line_ready());
+        queue.push_str("2, abc\r\n");
+        assert!(queue.is_line_ready());
+        assert_eq!(queue.input().unwrap(), "42");
+        assert!(queue.is_line_ready());
+        assert_eq!(queue.input().unwrap(), "abc");
+        assert!(!queue.is_line_ready());
+    }
+
+    #[test]
+    fn input_queue_line_input() {
+        let mut queue = InputQueue::new();
+        queue.push_line("hello, world");
+        queue.push_line("bye");
+        assert_eq!(queue.line_input().unwrap(), "hello, world");
+        assert_eq!(queue.line_input().unwrap(), "bye");
+        assert!(queue.eof().unwrap());
+    }
+}
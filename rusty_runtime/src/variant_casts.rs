@@ -1,8 +1,6 @@
 use std::convert::TryFrom;
 
-use rusty_linter::core::QBNumberCast;
-use rusty_parser::FileHandle;
-use rusty_variant::Variant;
+use rusty_variant::{FileHandle, QBNumberCast, Variant};
 
 use crate::RuntimeError;
 
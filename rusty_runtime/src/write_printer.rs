@@ -0,0 +1,163 @@
+use std::io::Write;
+
+use crate::io::Printer;
+use crate::is_cr_lf;
+
+/// The form feed character, which ejects the current page of a printer.
+const FORM_FEED: char = '\x0C';
+
+pub struct WritePrinter<T: Write> {
+    writer: T,
+    last_column: usize,
+    /// Wraps the output after this many columns, if set.
+    width: Option<usize>,
+}
+
+impl<T: Write> WritePrinter<T> {
+    pub fn new(writer: T) -> Self {
+        Self {
+            writer,
+            last_column: 0,
+            width: None,
+        }
+    }
+
+    pub fn with_width(writer: T, width: usize) -> Self {
+        Self {
+            writer,
+            last_column: 0,
+            width: Some(width),
+        }
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.writer
+    }
+
+    /// Sets the current column (0 based), e.g. when the output continues
+    /// a file that was written earlier.
+    pub fn set_column(&mut self, column: usize) {
+        self.last_column = column;
+    }
+
+    fn print_as_is(&mut self, s: &str) -> std::io::Result<usize> {
+        let mut bytes_written: usize = 0;
+        let mut is_first = true;
+        for page in s.split(FORM_FEED) {
+            if is_first {
+                is_first = false;
+            } else {
+                // a form feed starts a new page, at the first column
+                self.writer.write_all(&[FORM_FEED as u8])?;
+                self.last_column = 0;
+                bytes_written += 1;
+            }
+            bytes_written += self.print_wrapped(page)?;
+        }
+        self.writer.flush()?;
+        Ok(bytes_written)
+    }
+
+    /// Prints the given string, starting a new line whenever the width is reached.
+    fn print_wrapped(&mut self, s: &str) -> std::io::Result<usize> {
+        let mut bytes_written: usize = 0;
+        let mut remaining = s;
+        while !remaining.is_empty() {
+            let available = match self.width {
+                Some(width) => {
+                    if self.last_column >= width {
+                        bytes_written += self.println()?;
+                    }
+                    width - self.last_column
+                }
+                None => usize::MAX,
+            };
+            let split_index = remaining
+                .char_indices()
+                .nth(available)
+                .map_or(remaining.len(), |(i, _)| i);
+            let (part, rest) = remaining.split_at(split_index);
+            self.writer.write_all(part.as_bytes())?;
+            bytes_written += part.len();
+            self.last_column += part.chars().count();
+            remaining = rest;
+        }
+        Ok(bytes_written)
+    }
+}
+
+/// Helpers for inspecting output captured in memory.
+impl WritePrinter<Vec<u8>> {
+    /// Gets the captured output of stdout as-is, without trimming or removing CRLF
+    pub fn output_exact(&self) -> String {
+        let bytes: Vec<u8> = self.inner().clone();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    /// Gets the captured output of stdout, trimmed and without CRLF
+    pub fn output(&self) -> String {
+        self.output_exact()
+            .trim()
+            .trim_matches(|ch| ch == '\r' || ch == '\n')
+            .to_string()
+    }
+
+    /// Gets the captured output of stdout as a collection of lines.
+    /// Each line is trimmed of whitespace and CRLF.
+    pub fn output_lines(&self) -> Vec<String> {
+        self.output_exact()
+            .trim_matches(|ch| ch == '\r' || ch == '\n')
+            .split("\r\n")
+            .map(|x| x.trim().to_string())
+            .collect()
+    }
+
+    /// Gets the captures output of stdout as a collection of lines.
+    /// Lines are not trimmed.
+    pub fn output_lines_exact(&self) -> Vec<String> {
+        self.output_exact()
+            .split("\r\n")
+            .map(|x| x.to_string())
+            .collect()
+    }
+}
+
+impl<T: Write> Printer for WritePrinter<T> {
+    fn print(&mut self, s: &str) -> std::io::Result<usize> {
+        // This isn't a bug: it seems QBasic does not split on CRLF,
+        // but separately on CR and LF for this particular case.
+        // Might be a bug on QBasic side arguably.
+        let split = s.split(is_cr_lf);
+        let mut is_first = true;
+        let mut bytes_written: usize = 0;
+        for part in split {
+            if is_first {
+                is_first = false;
+            } else {
+                bytes_written += self.println()?;
+            }
+            bytes_written += self.print_as_is(part)?;
+        }
+        Ok(bytes_written)
+    }
+
+    fn println(&mut self) -> std::io::Result<usize> {
+        self.last_column = 0;
+        self.writer.write("\r\n".as_bytes())
+    }
+
+    fn column(&self) -> usize {
+        self.last_column
+    }
+
+    fn set_width(&mut self, width: Option<usize>) {
+        self.width = width;
+    }
+
+    fn move_to_next_print_zone(&mut self) -> std::io::Result<usize> {
+        let col: usize = self.last_column;
+        let len = 14 - col % 14;
+        let s: String = (0..len).map(|_| ' ').collect();
+        self.print(s.as_str())
+    }
+}
@@ -1,10 +1,16 @@
 mod array_value;
 mod bits;
+mod file_constants;
 mod fit;
+mod qb_casting;
+mod type_qualifier;
 mod user_defined_type_value;
 mod variant;
 
 pub use self::array_value::*;
 pub use self::bits::*;
+pub use self::file_constants::*;
+pub use self::qb_casting::*;
+pub use self::type_qualifier::*;
 pub use self::user_defined_type_value::*;
 pub use self::variant::*;
@@ -1,18 +1,16 @@
 use rusty_bit_vec::{MAX_INTEGER, MAX_LONG, MIN_INTEGER, MIN_LONG};
-use rusty_parser::TypeQualifier;
-use rusty_variant::Variant;
 
-use crate::core::LintError;
+use crate::{TypeQualifier, Variant, VariantError};
 
 pub trait QBNumberCast<T> {
-    fn try_cast(&self) -> Result<T, LintError>;
+    fn try_cast(&self) -> Result<T, VariantError>;
 }
 
 impl<T> QBNumberCast<Vec<T>> for Vec<Variant>
 where
     Variant: QBNumberCast<T>,
 {
-    fn try_cast(&self) -> Result<Vec<T>, LintError> {
+    fn try_cast(&self) -> Result<Vec<T>, VariantError> {
         self.iter().map(QBNumberCast::try_cast).collect()
     }
 }
@@ -29,171 +27,171 @@ where
 // 4. casting from an f64 to an f32 will produce the closest possible value (rounding to nearest, ties to even)
 
 impl QBNumberCast<f64> for f32 {
-    fn try_cast(&self) -> Result<f64, LintError> {
+    fn try_cast(&self) -> Result<f64, VariantError> {
         Ok(*self as f64)
     }
 }
 
 impl QBNumberCast<i32> for f32 {
-    fn try_cast(&self) -> Result<i32, LintError> {
+    fn try_cast(&self) -> Result<i32, VariantError> {
         if self.is_finite() {
             let r = self.round();
             if r >= (MIN_INTEGER as Self) && r <= (MAX_INTEGER as Self) {
                 Ok(r as i32)
             } else {
-                Err(LintError::Overflow)
+                Err(VariantError::Overflow)
             }
         } else {
-            Err(LintError::NotFiniteNumber)
+            Err(VariantError::NotFiniteNumber)
         }
     }
 }
 
 impl QBNumberCast<i64> for f32 {
-    fn try_cast(&self) -> Result<i64, LintError> {
+    fn try_cast(&self) -> Result<i64, VariantError> {
         if self.is_finite() {
             let r = self.round();
             if r >= (MIN_LONG as Self) && r <= (MAX_LONG as Self) {
                 Ok(r as i64)
             } else {
-                Err(LintError::Overflow)
+                Err(VariantError::Overflow)
             }
         } else {
-            Err(LintError::NotFiniteNumber)
+            Err(VariantError::NotFiniteNumber)
         }
     }
 }
 
 impl QBNumberCast<f32> for f64 {
-    fn try_cast(&self) -> Result<f32, LintError> {
+    fn try_cast(&self) -> Result<f32, VariantError> {
         Ok(*self as f32)
     }
 }
 
 impl QBNumberCast<i32> for f64 {
-    fn try_cast(&self) -> Result<i32, LintError> {
+    fn try_cast(&self) -> Result<i32, VariantError> {
         if self.is_finite() {
             let r = self.round();
             if r >= (MIN_INTEGER as Self) && r <= (MAX_INTEGER as Self) {
                 Ok(r as i32)
             } else {
-                Err(LintError::Overflow)
+                Err(VariantError::Overflow)
             }
         } else {
-            Err(LintError::NotFiniteNumber)
+            Err(VariantError::NotFiniteNumber)
         }
     }
 }
 
 impl QBNumberCast<i64> for f64 {
-    fn try_cast(&self) -> Result<i64, LintError> {
+    fn try_cast(&self) -> Result<i64, VariantError> {
         if self.is_finite() {
             let r = self.round();
             if r >= (MIN_LONG as Self) && r <= (MAX_LONG as Self) {
                 Ok(r as i64)
             } else {
-                Err(LintError::Overflow)
+                Err(VariantError::Overflow)
             }
         } else {
-            Err(LintError::NotFiniteNumber)
+            Err(VariantError::NotFiniteNumber)
         }
     }
 }
 
 impl QBNumberCast<f32> for i32 {
-    fn try_cast(&self) -> Result<f32, LintError> {
+    fn try_cast(&self) -> Result<f32, VariantError> {
         Ok(*self as f32)
     }
 }
 
 impl QBNumberCast<f64> for i32 {
-    fn try_cast(&self) -> Result<f64, LintError> {
+    fn try_cast(&self) -> Result<f64, VariantError> {
         Ok(*self as f64)
     }
 }
 
 impl QBNumberCast<i64> for i32 {
-    fn try_cast(&self) -> Result<i64, LintError> {
+    fn try_cast(&self) -> Result<i64, VariantError> {
         Ok(*self as i64)
     }
 }
 
 impl QBNumberCast<f32> for i64 {
-    fn try_cast(&self) -> Result<f32, LintError> {
+    fn try_cast(&self) -> Result<f32, VariantError> {
         Ok(*self as f32)
     }
 }
 
 impl QBNumberCast<f64> for i64 {
-    fn try_cast(&self) -> Result<f64, LintError> {
+    fn try_cast(&self) -> Result<f64, VariantError> {
         Ok(*self as f64)
     }
 }
 
 impl QBNumberCast<i32> for i64 {
-    fn try_cast(&self) -> Result<i32, LintError> {
+    fn try_cast(&self) -> Result<i32, VariantError> {
         if *self >= (MIN_INTEGER as Self) && *self <= (MAX_INTEGER as Self) {
             Ok(*self as i32)
         } else {
-            Err(LintError::Overflow)
+            Err(VariantError::Overflow)
         }
     }
 }
 
 impl QBNumberCast<f32> for Variant {
-    fn try_cast(&self) -> Result<f32, LintError> {
+    fn try_cast(&self) -> Result<f32, VariantError> {
         match self {
             Self::VSingle(f) => Ok(*f),
             Self::VDouble(f) => f.try_cast(),
             Self::VInteger(f) => f.try_cast(),
             Self::VLong(f) => f.try_cast(),
-            _ => Err(LintError::TypeMismatch),
+            _ => Err(VariantError::TypeMismatch),
         }
     }
 }
 
 impl QBNumberCast<f64> for Variant {
-    fn try_cast(&self) -> Result<f64, LintError> {
+    fn try_cast(&self) -> Result<f64, VariantError> {
         match self {
             Self::VSingle(f) => f.try_cast(),
             Self::VDouble(f) => Ok(*f),
             Self::VInteger(f) => f.try_cast(),
             Self::VLong(f) => f.try_cast(),
-            _ => Err(LintError::TypeMismatch),
+            _ => Err(VariantError::TypeMismatch),
         }
     }
 }
 
 impl QBNumberCast<i32> for Variant {
-    fn try_cast(&self) -> Result<i32, LintError> {
+    fn try_cast(&self) -> Result<i32, VariantError> {
         match self {
             Self::VSingle(f) => f.try_cast(),
             Self::VDouble(f) => f.try_cast(),
             Self::VInteger(f) => Ok(*f),
             Self::VLong(f) => f.try_cast(),
-            _ => Err(LintError::TypeMismatch),
+            _ => Err(VariantError::TypeMismatch),
         }
     }
 }
 
 impl QBNumberCast<i64> for Variant {
-    fn try_cast(&self) -> Result<i64, LintError> {
+    fn try_cast(&self) -> Result<i64, VariantError> {
         match self {
             Self::VSingle(f) => f.try_cast(),
             Self::VDouble(f) => f.try_cast(),
             Self::VInteger(f) => f.try_cast(),
             Self::VLong(f) => Ok(*f),
-            _ => Err(LintError::TypeMismatch),
+            _ => Err(VariantError::TypeMismatch),
         }
     }
 }
 
 pub trait CastVariant: Sized {
-    fn cast(self, target_type: TypeQualifier) -> Result<Self, LintError>;
+    fn cast(self, target_type: TypeQualifier) -> Result<Self, VariantError>;
 }
 
 impl CastVariant for Variant {
-    fn cast(self, target_type: TypeQualifier) -> Result<Self, LintError> {
+    fn cast(self, target_type: TypeQualifier) -> Result<Self, VariantError> {
         match target_type {
             TypeQualifier::BangSingle => Ok(Self::VSingle(self.try_cast()?)),
             TypeQualifier::HashDouble => Ok(Self::VDouble(self.try_cast()?)),
@@ -201,20 +199,20 @@ impl CastVariant for Variant {
             TypeQualifier::AmpersandLong => Ok(Self::VLong(self.try_cast()?)),
             TypeQualifier::DollarString => match self {
                 Self::VString(_) => Ok(self),
-                _ => Err(LintError::TypeMismatch),
+                _ => Err(VariantError::TypeMismatch),
             },
         }
     }
 }
 
 impl QBNumberCast<bool> for Variant {
-    fn try_cast(&self) -> Result<bool, LintError> {
+    fn try_cast(&self) -> Result<bool, VariantError> {
         match self {
             Self::VSingle(n) => Ok(*n != 0.0),
             Self::VDouble(n) => Ok(*n != 0.0),
             Self::VInteger(n) => Ok(*n != 0),
             Self::VLong(n) => Ok(*n != 0),
-            _ => Err(LintError::TypeMismatch),
+            _ => Err(VariantError::TypeMismatch),
         }
     }
 }
@@ -460,27 +458,27 @@ mod tests {
     }
 
     mod try_from {
-        use rusty_variant::{V_FALSE, V_TRUE};
+        use crate::{V_FALSE, V_TRUE};
 
         use super::*;
 
         #[test]
         fn test_bool_try_from() {
-            assert_eq!(true, bool_try_from(Variant::from(1.0_f32)).unwrap());
-            assert_eq!(false, bool_try_from(Variant::from(0.0_f32)).unwrap());
-            assert_eq!(true, bool_try_from(Variant::from(1.0)).unwrap());
-            assert_eq!(false, bool_try_from(Variant::from(0.0)).unwrap());
+            assert!(bool_try_from(Variant::from(1.0_f32)).unwrap());
+            assert!(!bool_try_from(Variant::from(0.0_f32)).unwrap());
+            assert!(bool_try_from(Variant::from(1.0)).unwrap());
+            assert!(!bool_try_from(Variant::from(0.0)).unwrap());
             bool_try_from(Variant::from("hi")).expect_err("should not convert from string");
             bool_try_from(Variant::from("")).expect_err("should not convert from string");
-            assert_eq!(true, bool_try_from(Variant::from(42)).unwrap());
-            assert_eq!(false, bool_try_from(Variant::from(0)).unwrap());
-            assert_eq!(true, bool_try_from(Variant::from(42_i64)).unwrap());
-            assert_eq!(false, bool_try_from(Variant::from(0_i64)).unwrap());
-            assert_eq!(true, bool_try_from(V_TRUE).unwrap());
-            assert_eq!(false, bool_try_from(V_FALSE).unwrap());
+            assert!(bool_try_from(Variant::from(42)).unwrap());
+            assert!(!bool_try_from(Variant::from(0)).unwrap());
+            assert!(bool_try_from(Variant::from(42_i64)).unwrap());
+            assert!(!bool_try_from(Variant::from(0_i64)).unwrap());
+            assert!(bool_try_from(V_TRUE).unwrap());
+            assert!(!bool_try_from(V_FALSE).unwrap());
         }
 
-        fn bool_try_from(v: Variant) -> Result<bool, LintError> {
+        fn bool_try_from(v: Variant) -> Result<bool, VariantError> {
             v.try_cast()
         }
     }
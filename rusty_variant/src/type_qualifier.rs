@@ -1,7 +1,7 @@
 use std::convert::TryFrom;
 use std::fmt::Display;
 
-use crate::error::ParserError;
+use crate::Variant;
 
 /// The optional character postfix that specifies the type of a name.
 /// Example: A$ denotes a string variable
@@ -23,9 +23,9 @@ pub enum TypeQualifier {
 
 // TODO #[cfg(test)]
 impl TryFrom<char> for TypeQualifier {
-    type Error = ParserError;
+    type Error = ();
 
-    fn try_from(ch: char) -> Result<Self, ParserError> {
+    fn try_from(ch: char) -> Result<Self, ()> {
         if ch == '!' {
             Ok(Self::BangSingle)
         } else if ch == '#' {
@@ -37,7 +37,7 @@ impl TryFrom<char> for TypeQualifier {
         } else if ch == '&' {
             Ok(Self::AmpersandLong)
         } else {
-            Err(ParserError::expected("%, &, !, # or $"))
+            Err(())
         }
     }
 }
@@ -62,6 +62,21 @@ impl Display for TypeQualifier {
     }
 }
 
+impl Variant {
+    /// Gets the type of this value,
+    /// unless it is a user defined type or an array.
+    pub fn qualifier(&self) -> Option<TypeQualifier> {
+        match self {
+            Self::VSingle(_) => Some(TypeQualifier::BangSingle),
+            Self::VDouble(_) => Some(TypeQualifier::HashDouble),
+            Self::VString(_) => Some(TypeQualifier::DollarString),
+            Self::VInteger(_) => Some(TypeQualifier::PercentInteger),
+            Self::VLong(_) => Some(TypeQualifier::AmpersandLong),
+            Self::VUserDefined(_) | Self::VArray(_) => None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,7 +1,7 @@
 use std::cmp::Ordering;
 use std::fmt::Display;
 
-use rusty_bit_vec::{MIN_INTEGER, MIN_LONG};
+use rusty_bit_vec::{MAX_INTEGER, MAX_LONG, MIN_INTEGER, MIN_LONG};
 
 use crate::fit::FitToType;
 use crate::{UserDefinedTypeValue, VArray, qb_and, qb_or};
@@ -20,6 +20,8 @@ pub enum Variant {
 #[derive(Debug)]
 pub enum VariantError {
     DivisionByZero,
+    /// A float that is infinite or NaN was converted to an integer.
+    NotFiniteNumber,
     Overflow,
     TypeMismatch,
 }
@@ -198,7 +200,7 @@ impl Variant {
     }
 
     pub fn plus(self, other: Self) -> Result<Self, VariantError> {
-        match self {
+        let result = match self {
             Self::VSingle(f_left) => match other {
                 Self::VSingle(f_right) => Ok(Self::VSingle(f_left + f_right)),
                 Self::VDouble(d_right) => Ok(Self::VDouble(f_left as f64 + d_right)),
@@ -226,11 +228,12 @@ impl Variant {
                 _ => other.plus(self),
             },
             _ => Err(VariantError::TypeMismatch),
-        }
+        };
+        result.and_then(Self::check_range)
     }
 
     pub fn minus(self, other: Self) -> Result<Self, VariantError> {
-        match self {
+        let result = match self {
             Self::VSingle(f_left) => match other {
                 Self::VSingle(f_right) => Ok(Self::VSingle(f_left - f_right)),
                 Self::VDouble(d_right) => Ok(Self::VDouble(f_left as f64 - d_right)),
@@ -254,11 +257,12 @@ impl Variant {
                 _ => other.minus(self).and_then(|x| x.negate()),
             },
             _ => Err(VariantError::TypeMismatch),
-        }
+        };
+        result.and_then(Self::check_range)
     }
 
     pub fn multiply(self, other: Self) -> Result<Self, VariantError> {
-        match self {
+        let result = match self {
             Self::VSingle(f_left) => match other {
                 Self::VSingle(f_right) => Ok(Self::VSingle(f_left * f_right)),
                 Self::VDouble(d_right) => Ok(Self::VDouble(f_left as f64 * d_right)),
@@ -282,6 +286,21 @@ impl Variant {
                 _ => other.multiply(self),
             },
             _ => Err(VariantError::TypeMismatch),
+        };
+        result.and_then(Self::check_range)
+    }
+
+    /// Checks that an integer or a long value fits its type.
+    /// The operands of an arithmetic operation are promoted to the type
+    /// of the result, so the result can be out of range
+    /// (e.g. `32767 + 1` is an `INTEGER` that overflows).
+    pub fn check_range(self) -> Result<Self, VariantError> {
+        match self {
+            Self::VInteger(n) if !(MIN_INTEGER..=MAX_INTEGER).contains(&n) => {
+                Err(VariantError::Overflow)
+            }
+            Self::VLong(n) if !(MIN_LONG..=MAX_LONG).contains(&n) => Err(VariantError::Overflow),
+            _ => Ok(self),
         }
     }
 
@@ -469,6 +488,7 @@ mod tests {
         use super::*;
 
         #[test]
+        #[allow(clippy::approx_constant)]
         fn test_from() {
             assert_eq!(Variant::from(3.14_f32), Variant::VSingle(3.14));
             assert_eq!(Variant::from(3.14), Variant::VDouble(3.14));
@@ -1152,6 +1172,58 @@ mod tests {
         }
     }
 
+    mod overflow {
+        use super::*;
+
+        fn is_overflow(result: Result<Variant, VariantError>) -> bool {
+            matches!(result, Err(VariantError::Overflow))
+        }
+
+        #[test]
+        fn test_plus() {
+            assert!(is_overflow(
+                Variant::VInteger(MAX_INTEGER).plus(Variant::VInteger(1))
+            ));
+            assert!(is_overflow(
+                Variant::VInteger(1).plus(Variant::VLong(MAX_LONG))
+            ));
+            assert!(
+                Variant::VInteger(MAX_INTEGER - 1)
+                    .plus(Variant::VInteger(1))
+                    .is_ok()
+            );
+        }
+
+        #[test]
+        fn test_minus() {
+            assert!(is_overflow(
+                Variant::VInteger(MIN_INTEGER).minus(Variant::VInteger(1))
+            ));
+            assert!(is_overflow(
+                Variant::VLong(MIN_LONG).minus(Variant::VInteger(1))
+            ));
+        }
+
+        #[test]
+        fn test_multiply() {
+            assert!(is_overflow(
+                Variant::VInteger(200).multiply(Variant::VInteger(200))
+            ));
+            assert!(is_overflow(
+                Variant::VLong(65536).multiply(Variant::VLong(65536))
+            ));
+        }
+
+        #[test]
+        fn test_float_does_not_overflow() {
+            assert!(
+                Variant::VSingle(MAX_INTEGER as f32)
+                    .plus(Variant::VInteger(1))
+                    .is_ok()
+            );
+        }
+    }
+
     mod divide {
         use super::*;
 